@@ -160,10 +160,20 @@ export interface UserStorageStat {
 	total_bytes: number;
 }
 
+export interface ScopeStorageStat {
+	id: string;
+	name: string;
+	file_count: number;
+	total_bytes: number;
+	quota_mb: number | null;
+}
+
 export interface StorageStats {
 	total_files: number;
 	total_bytes: number;
 	per_user: UserStorageStat[];
+	per_community: ScopeStorageStat[];
+	per_group: ScopeStorageStat[];
 }
 
 export async function getStorageStats(): Promise<StorageStats> {
@@ -207,7 +217,9 @@ export async function unquarantineMessage(messageId: string): Promise<void> {
 
 export interface BlockedHash {
 	id: string;
+	kind: 'sha256' | 'dhash';
 	hash: string;
+	max_distance: number | null;
 	reason: string | null;
 	blocked_by: string;
 	created_at: string;
@@ -232,6 +244,25 @@ export async function removeBlockedHash(id: string): Promise<void> {
 	return api.delete(`/admin/blocked-hashes/${id}`);
 }
 
+export interface BlocklistFile {
+	format: 'chatalot-blocklist';
+	version: number;
+	entries: {
+		kind: 'sha256' | 'dhash';
+		hash: string;
+		max_distance?: number;
+		reason?: string;
+	}[];
+}
+
+export async function exportBlocklist(): Promise<BlocklistFile> {
+	return api.get('/admin/blocked-hashes/export');
+}
+
+export async function importBlocklist(file: BlocklistFile): Promise<{ imported: number }> {
+	return api.post('/admin/blocked-hashes/import', file);
+}
+
 // ── Audit Log ──
 
 export interface AuditLogEntry {
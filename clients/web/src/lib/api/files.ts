@@ -16,6 +16,12 @@ export interface FileMetadata {
 	content_type: string | null;
 	checksum: string;
 	created_at: string;
+	has_thumbnail: boolean;
+	duration_ms?: number;
+	width?: number;
+	height?: number;
+	blurhash?: string;
+	variants?: number[];
 }
 
 export async function uploadFile(
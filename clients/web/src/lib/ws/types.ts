@@ -2,7 +2,7 @@
 
 export type ClientMessage =
 	| { type: 'authenticate'; token: string }
-	| { type: 'send_message'; channel_id: string; ciphertext: number[]; nonce: number[]; message_type: 'text' | 'file' | 'system'; reply_to: string | null; sender_key_id: string | null; thread_id?: string | null }
+	| { type: 'send_message'; channel_id: string; ciphertext: number[]; nonce: number[]; message_type: 'text' | 'file' | 'system'; reply_to: string | null; sender_key_id: string | null; thread_id?: string | null; mentions?: { user_ids?: string[]; roles?: string[]; everyone?: boolean } }
 	| { type: 'edit_message'; message_id: string; ciphertext: number[]; nonce: number[] }
 	| { type: 'delete_message'; message_id: string }
 	| { type: 'update_presence'; status: 'online' | 'idle' | 'dnd' | 'invisible' }
@@ -20,6 +20,7 @@ export type ClientMessage =
 	| { type: 'remove_reaction'; message_id: string; emoji: string }
 	| { type: 'mark_read'; channel_id: string; message_id: string }
 	| { type: 'mark_all_read' }
+	| { type: 'mark_delivered'; channel_id: string; message_ids: string[] }
 	| { type: 'ping'; timestamp: number };
 
 export type ServerMessage =
@@ -63,6 +64,8 @@ export type ServerMessage =
 	| { type: 'group_deleted'; group_id: string }
 	| { type: 'announcement'; id: string; title: string; body: string; created_by: string; created_at: string }
 	| { type: 'read_receipt'; channel_id: string; user_id: string; message_id: string; timestamp: string }
+	| { type: 'delivery_receipt'; channel_id: string; message_id: string; user_id: string; delivered_to: string[]; timestamp: string }
 	| { type: 'error'; code: string; message: string }
 	| { type: 'pong'; timestamp: number }
-	| { type: 'keys_low'; remaining: number };
+	| { type: 'keys_low'; remaining: number }
+	| { type: 'server_shutting_down'; reconnect_after_ms: number };
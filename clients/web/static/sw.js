@@ -116,7 +116,9 @@ self.addEventListener('push', (event) => {
 	const title = data.sender_name || 'Chatalot';
 	const body = data.notification_type === 'dm'
 		? 'Sent you a message'
-		: 'New message';
+		: data.notification_type === 'digest'
+			? data.channel_name
+			: 'New message';
 
 	event.waitUntil(
 		self.registration.showNotification(title, {
@@ -253,6 +253,27 @@ pub struct MessageEditResponse {
     pub edited_at: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MentionsQuery {
+    /// Cursor for pagination (message UUID; fetch mentions before this)
+    pub before: Option<Uuid>,
+    /// Number of mentions to return (max 100)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MentionResponse {
+    /// How the user was mentioned: "user", "role" or "everyone"
+    pub mention_kind: String,
+    pub message: MessageResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessageDeliveryResponse {
+    pub user_id: Uuid,
+    pub delivered_at: String,
+}
+
 // ── DMs ──
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -284,6 +305,40 @@ pub struct FileMetadataResponse {
     pub content_type: Option<String>,
     pub checksum: String,
     pub created_at: String,
+    /// Whether `/files/{id}/thumb` has a thumbnail, poster frame, waveform
+    /// or first page to serve.
+    pub has_thumbnail: bool,
+    /// Playing time of video and audio files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+    /// Pixel size of images.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<i32>,
+    /// BlurHash placeholder to show while an image loads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blurhash: Option<String>,
+    /// Longer side of each WebP variant served by
+    /// `/files/{id}/thumb?size=`, smallest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ThumbnailQuery {
+    /// Ask for the WebP variant closest to this size (the smallest at least
+    /// this large, else the largest). Without it, the JPEG thumbnail.
+    pub size: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FileDownloadQuery {
+    /// Ask for `Content-Disposition: inline`. Only honored for image, audio
+    /// and video types that are safe to render; everything else is still
+    /// served as an attachment.
+    #[serde(default)]
+    pub inline: bool,
 }
 
 // ── TOTP 2FA ──
@@ -404,6 +459,15 @@ pub struct UpdateChannelRequest {
     pub voice_background: Option<String>,
 }
 
+/// File retention of a channel or community (`GET`/`PUT
+/// /channels/{id}/file-retention`, `/communities/{cid}/file-retention`).
+/// `null` means no limit; with both `null` the policy is removed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FileRetentionPolicy {
+    pub max_age_days: Option<i32>,
+    pub max_total_mb: Option<i64>,
+}
+
 // ── Invites ──
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -612,6 +676,8 @@ pub struct StorageStatsResponse {
     pub total_files: i64,
     pub total_bytes: i64,
     pub per_user: Vec<UserStorageStatResponse>,
+    pub per_community: Vec<ScopeStorageStatResponse>,
+    pub per_group: Vec<ScopeStorageStatResponse>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -621,21 +687,101 @@ pub struct UserStorageStatResponse {
     pub total_bytes: i64,
 }
 
+/// Storage used by the files posted in a community or group.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScopeStorageStatResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub file_count: i64,
+    pub total_bytes: i64,
+    pub quota_mb: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageQuotaResponse {
+    /// `community`, `group` or `role`.
+    pub scope_type: String,
+    /// Community ID (`community` and `role`) or group ID (`group`).
+    pub scope_id: Uuid,
+    /// Community role of a `role` quota.
+    pub role: Option<String>,
+    /// 0 = unlimited (`role` only).
+    pub max_mb: i64,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetStorageQuotaRequest {
+    pub max_mb: i64,
+}
+
+/// Status of a background job. Cluster jobs report the shared state from the
+/// database; node jobs report the state of the node that served the request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminJobResponse {
+    pub name: String,
+    pub description: String,
+    /// `cluster` (runs on one node at a time) or `node` (runs on every node).
+    pub scope: String,
+    pub interval_secs: u64,
+    pub running: bool,
+    pub lease_owner: Option<Uuid>,
+    pub next_run_at: Option<String>,
+    pub last_started_at: Option<String>,
+    pub last_finished_at: Option<String>,
+    pub last_success_at: Option<String>,
+    pub last_duration_ms: Option<i64>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: i32,
+    pub run_count: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AddBlockedHashRequest {
     pub hash: String,
+    /// `sha256` (the default) or `dhash`.
+    pub kind: Option<String>,
+    /// For `dhash` entries: how many bits an image's hash may differ by.
+    pub max_distance: Option<i32>,
     pub reason: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BlockedHashResponse {
     pub id: Uuid,
+    pub kind: String,
     pub hash: String,
+    pub max_distance: Option<i32>,
     pub reason: Option<String>,
     pub blocked_by: Option<Uuid>,
     pub created_at: String,
 }
 
+/// A hash blocklist in the interchange format shared between instances.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlocklistFile {
+    /// Always `chatalot-blocklist`.
+    pub format: String,
+    pub version: u32,
+    pub entries: Vec<BlocklistEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlocklistEntry {
+    pub kind: String,
+    pub hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_distance: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlocklistImportResponse {
+    pub imported: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuditLogQuery {
     pub page: Option<i64>,
@@ -728,6 +874,26 @@ pub struct HealthResponse {
     pub db_healthy: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    /// `ok`, `degraded` (a component warns) or `unavailable` (a component failed).
+    pub status: String,
+    pub version: String,
+    pub uptime_secs: u64,
+    pub checks: Vec<ComponentCheck>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ComponentCheck {
+    pub component: String,
+    /// `ok`, `warn`, `fail` or `disabled`.
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
 // ── Communities ──
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1131,9 +1297,16 @@ pub struct CustomEmojiResponse {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PushSubscribeRequest {
+    /// "webpush" (default) or "unifiedpush"
+    #[serde(default)]
+    pub kind: Option<String>,
     pub endpoint: String,
-    pub p256dh_key: String,
-    pub auth_key: String,
+    /// Required for Web Push
+    #[serde(default)]
+    pub p256dh_key: Option<String>,
+    /// Required for Web Push
+    #[serde(default)]
+    pub auth_key: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1146,6 +1319,12 @@ pub struct VapidKeyResponse {
     pub public_key: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushTransportsResponse {
+    /// Enabled push transports, e.g. ["webpush", "unifiedpush"]
+    pub transports: Vec<String>,
+}
+
 // ── Announcements ──
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1162,3 +1341,64 @@ pub struct AnnouncementResponse {
     pub created_by: Uuid,
     pub created_at: String,
 }
+
+// ── Notification Settings ──
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateNotificationSettingRequest {
+    /// "channel", "dm", "group" or "community"
+    pub scope_type: String,
+    pub scope_id: Uuid,
+    /// "all", "mentions" or "nothing"
+    pub level: String,
+    /// RFC 3339 timestamp; the scope is silenced until then
+    pub muted_until: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificationSettingResponse {
+    pub scope_type: String,
+    pub scope_id: Uuid,
+    pub level: String,
+    pub muted_until: Option<String>,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietHoursScheduleDto {
+    /// Local weekdays the window starts on, 0 = Monday .. 6 = Sunday
+    pub days: Vec<u8>,
+    /// Local start time, "HH:MM"
+    pub start: String,
+    /// Local end time, "HH:MM"; at or before `start` wraps past midnight
+    pub end: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateQuietHoursRequest {
+    pub enabled: bool,
+    /// IANA time zone name, e.g. "Europe/Berlin"
+    pub timezone: String,
+    /// "suppress" (default) or "batch"
+    #[serde(default)]
+    pub delivery: Option<String>,
+    /// Switch presence to DND during quiet hours (default true)
+    #[serde(default)]
+    pub auto_dnd: Option<bool>,
+    pub schedules: Vec<QuietHoursScheduleDto>,
+    /// DMs from these users are delivered even during quiet hours or DND
+    #[serde(default)]
+    pub breakthrough_user_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuietHoursResponse {
+    pub enabled: bool,
+    pub timezone: String,
+    pub delivery: String,
+    pub auto_dnd: bool,
+    /// Whether quiet hours are in effect right now
+    pub active: bool,
+    pub schedules: Vec<QuietHoursScheduleDto>,
+    pub breakthrough_user_ids: Vec<Uuid>,
+}
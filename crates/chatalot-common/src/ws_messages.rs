@@ -20,6 +20,9 @@ pub enum ClientMessage {
         sender_key_id: Option<Uuid>,
         #[serde(default)]
         thread_id: Option<Uuid>,
+        /// Mentions declared by the sender's client (the server can't read content).
+        #[serde(default)]
+        mentions: Option<Mentions>,
     },
     EditMessage {
         message_id: Uuid,
@@ -95,6 +98,12 @@ pub enum ClientMessage {
     },
     MarkAllRead,
 
+    // Delivery tracking (message reached this device, not necessarily read)
+    MarkDelivered {
+        channel_id: Uuid,
+        message_ids: Vec<Uuid>,
+    },
+
     // Keepalive
     Ping {
         timestamp: i64,
@@ -220,6 +229,17 @@ pub enum ServerMessage {
         timestamp: String,
     },
 
+    // Delivery receipts (sent only to the message's sender)
+    DeliveryReceipt {
+        channel_id: Uuid,
+        message_id: Uuid,
+        /// The recipient whose device just received the message.
+        user_id: Uuid,
+        /// Every recipient the message has been delivered to so far.
+        delivered_to: Vec<Uuid>,
+        timestamp: String,
+    },
+
     // Channel moderation
     MemberKicked {
         channel_id: Uuid,
@@ -377,6 +397,23 @@ pub enum ServerMessage {
     KeysLow {
         remaining: u32,
     },
+    /// The server is restarting; reconnect after the given (jittered) delay.
+    ServerShuttingDown {
+        reconnect_after_ms: u64,
+    },
+}
+
+/// Mentions attached to an outgoing message.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Mentions {
+    #[serde(default)]
+    pub user_ids: Vec<Uuid>,
+    /// Channel roles to mention (e.g. "moderator"). Requires mass-mention permission.
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Mention every channel member. Requires mass-mention permission.
+    #[serde(default)]
+    pub everyone: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
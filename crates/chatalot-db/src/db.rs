@@ -0,0 +1,847 @@
+//! Backend-neutral database handle and query builders.
+//!
+//! Repos are written once against PostgreSQL-flavoured SQL with `$N`
+//! placeholders. On SQLite the statement is rewritten just before execution:
+//!
+//! - `$N` becomes `?K`, and a list parameter expands to one placeholder per
+//!   element, so `= ANY($1)` / `<> ALL($1)` become `IN (...)` / `NOT IN (...)`
+//! - `::type` casts are dropped
+//! - `NOW()` becomes the same millisecond UTC text that timestamps are stored as
+//! - `ILIKE` becomes `LIKE` (case-insensitive for ASCII only)
+//! - `FOR UPDATE [SKIP LOCKED]` is dropped; SQLite transactions start with
+//!   `BEGIN IMMEDIATE` and so already hold the write lock
+//!
+//! Anything else that differs between the two (intervals, arrays, JSON
+//! operators) is spelled out per backend in the repo via [`Db::backend`].
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgArguments, PgConnection, PgPool, PgRow, PgTypeInfo, Postgres};
+use sqlx::sqlite::{SqliteArguments, SqliteConnection, SqlitePool, SqliteRow};
+use sqlx::{Arguments, Decode, Encode, FromRow, Sqlite, Transaction, Type};
+use uuid::Uuid;
+
+/// Which database engine a [`Db`] talks to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Postgres,
+    Sqlite,
+}
+
+impl Backend {
+    /// Pick the backend from a `DATABASE_URL` scheme.
+    pub fn from_url(url: &str) -> Option<Self> {
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            Some(Self::Postgres)
+        } else if url.starts_with("sqlite:") {
+            Some(Self::Sqlite)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Postgres => "postgres",
+            Self::Sqlite => "sqlite",
+        }
+    }
+
+    /// SQL decoding the `BYTEA`/`BLOB` expression `expr` as UTF-8 text.
+    pub fn bytes_as_text(self, expr: &str) -> String {
+        match self {
+            Self::Postgres => format!("convert_from({expr}, 'UTF8')"),
+            Self::Sqlite => format!("CAST({expr} AS TEXT)"),
+        }
+    }
+
+    /// SQL for the current time shifted by `secs`, an SQL expression giving a
+    /// number of seconds (negative for the past), e.g. `"$3"` or `"-86400"`.
+    pub fn now_plus_secs(self, secs: &str) -> String {
+        match self {
+            Self::Postgres => format!("(NOW() + make_interval(secs => {secs}))"),
+            Self::Sqlite => {
+                format!("strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ({secs}) || ' seconds')")
+            }
+        }
+    }
+}
+
+/// A connection pool for either backend. Cheap to clone.
+#[derive(Debug, Clone)]
+pub enum Db {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
+
+impl Db {
+    pub fn backend(&self) -> Backend {
+        match self {
+            Self::Postgres(_) => Backend::Postgres,
+            Self::Sqlite(_) => Backend::Sqlite,
+        }
+    }
+
+    /// Start a transaction. SQLite transactions take the write lock up front
+    /// so two writers never deadlock upgrading from a read.
+    pub async fn begin(&self) -> Result<Tx, sqlx::Error> {
+        match self {
+            Self::Postgres(pool) => Ok(Tx::Postgres(pool.begin().await?)),
+            Self::Sqlite(pool) => Ok(Tx::Sqlite(pool.begin_with("BEGIN IMMEDIATE").await?)),
+        }
+    }
+
+    /// Number of open connections.
+    pub fn size(&self) -> u32 {
+        match self {
+            Self::Postgres(pool) => pool.size(),
+            Self::Sqlite(pool) => pool.size(),
+        }
+    }
+
+    /// Configured upper bound on open connections.
+    pub fn max_connections(&self) -> u32 {
+        match self {
+            Self::Postgres(pool) => pool.options().get_max_connections(),
+            Self::Sqlite(pool) => pool.options().get_max_connections(),
+        }
+    }
+
+    /// Number of idle connections.
+    pub fn num_idle(&self) -> usize {
+        match self {
+            Self::Postgres(pool) => pool.num_idle(),
+            Self::Sqlite(pool) => pool.num_idle(),
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            Self::Postgres(pool) => pool.close().await,
+            Self::Sqlite(pool) => pool.close().await,
+        }
+    }
+
+    /// The underlying pool, for Postgres-only code such as `COPY`.
+    pub fn as_postgres(&self) -> Option<&PgPool> {
+        match self {
+            Self::Postgres(pool) => Some(pool),
+            Self::Sqlite(_) => None,
+        }
+    }
+
+    /// The underlying pool, for SQLite-only code such as `VACUUM INTO`.
+    pub fn as_sqlite(&self) -> Option<&SqlitePool> {
+        match self {
+            Self::Postgres(_) => None,
+            Self::Sqlite(pool) => Some(pool),
+        }
+    }
+}
+
+/// An open transaction on either backend. Rolled back on drop unless committed.
+#[derive(Debug)]
+pub enum Tx {
+    Postgres(Transaction<'static, Postgres>),
+    Sqlite(Transaction<'static, Sqlite>),
+}
+
+impl Tx {
+    pub async fn commit(self) -> Result<(), sqlx::Error> {
+        match self {
+            Self::Postgres(tx) => tx.commit().await,
+            Self::Sqlite(tx) => tx.commit().await,
+        }
+    }
+
+    pub async fn rollback(self) -> Result<(), sqlx::Error> {
+        match self {
+            Self::Postgres(tx) => tx.rollback().await,
+            Self::Sqlite(tx) => tx.rollback().await,
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self {
+            Self::Postgres(_) => Backend::Postgres,
+            Self::Sqlite(_) => Backend::Sqlite,
+        }
+    }
+}
+
+/// Something a query can run against: `&Db` or `&mut Tx`.
+pub trait Executor<'c>: Send {
+    fn target(self) -> Target<'c>;
+}
+
+#[doc(hidden)]
+pub enum Target<'c> {
+    PgPool(&'c PgPool),
+    PgConn(&'c mut PgConnection),
+    SqlitePool(&'c SqlitePool),
+    SqliteConn(&'c mut SqliteConnection),
+}
+
+impl<'c> Executor<'c> for &'c Db {
+    fn target(self) -> Target<'c> {
+        match self {
+            Db::Postgres(pool) => Target::PgPool(pool),
+            Db::Sqlite(pool) => Target::SqlitePool(pool),
+        }
+    }
+}
+
+impl<'c> Executor<'c> for &'c mut Tx {
+    fn target(self) -> Target<'c> {
+        match self {
+            Tx::Postgres(tx) => Target::PgConn(tx),
+            Tx::Sqlite(tx) => Target::SqliteConn(tx),
+        }
+    }
+}
+
+/// An owned bind parameter. Scalars carry their own NULL so Postgres still
+/// sees the right parameter type.
+#[derive(Debug, Clone)]
+pub enum Arg {
+    Bool(Option<bool>),
+    I16(Option<i16>),
+    I32(Option<i32>),
+    I64(Option<i64>),
+    F64(Option<f64>),
+    Text(Option<String>),
+    Bytes(Option<Vec<u8>>),
+    Uuid(Option<Uuid>),
+    Time(Option<DateTime<Utc>>),
+    Json(Option<serde_json::Value>),
+    /// A column value of Postgres type `TEXT[]`, stored as a JSON array on SQLite.
+    TextArray(Option<Vec<String>>),
+    /// A list for `= ANY($N)`; expanded to one placeholder per element on SQLite.
+    UuidList(Vec<Uuid>),
+    TextList(Vec<String>),
+    /// A value of a Postgres enum type, stored as text on SQLite.
+    Enum(&'static str, Option<&'static str>),
+}
+
+/// Types that can be bound as a query parameter.
+pub trait Bind {
+    fn into_arg(self) -> Arg;
+}
+
+macro_rules! scalar {
+    ($($ty:ty => $variant:ident),* $(,)?) => {$(
+        impl Bind for $ty {
+            fn into_arg(self) -> Arg {
+                Arg::$variant(Some(self))
+            }
+        }
+
+        impl Bind for &$ty {
+            fn into_arg(self) -> Arg {
+                Arg::$variant(Some(self.clone()))
+            }
+        }
+
+        impl Bind for Option<$ty> {
+            fn into_arg(self) -> Arg {
+                Arg::$variant(self)
+            }
+        }
+
+        impl Bind for Option<&$ty> {
+            fn into_arg(self) -> Arg {
+                Arg::$variant(self.cloned())
+            }
+        }
+
+        impl Bind for &Option<$ty> {
+            fn into_arg(self) -> Arg {
+                Arg::$variant(self.clone())
+            }
+        }
+
+        /// `Some(None)` and `None` both bind NULL.
+        impl Bind for Option<Option<$ty>> {
+            fn into_arg(self) -> Arg {
+                Arg::$variant(self.flatten())
+            }
+        }
+
+        impl Bind for Option<Option<&$ty>> {
+            fn into_arg(self) -> Arg {
+                Arg::$variant(self.flatten().cloned())
+            }
+        }
+    )*};
+}
+
+scalar! {
+    bool => Bool,
+    i16 => I16,
+    i32 => I32,
+    i64 => I64,
+    f64 => F64,
+    String => Text,
+    Vec<u8> => Bytes,
+    Uuid => Uuid,
+    DateTime<Utc> => Time,
+    serde_json::Value => Json,
+}
+
+impl Bind for &str {
+    fn into_arg(self) -> Arg {
+        Arg::Text(Some(self.to_owned()))
+    }
+}
+
+impl Bind for Option<&str> {
+    fn into_arg(self) -> Arg {
+        Arg::Text(self.map(str::to_owned))
+    }
+}
+
+impl Bind for Option<Option<&str>> {
+    fn into_arg(self) -> Arg {
+        Arg::Text(self.flatten().map(str::to_owned))
+    }
+}
+
+impl Bind for &[u8] {
+    fn into_arg(self) -> Arg {
+        Arg::Bytes(Some(self.to_vec()))
+    }
+}
+
+impl Bind for Option<&[u8]> {
+    fn into_arg(self) -> Arg {
+        Arg::Bytes(self.map(<[u8]>::to_vec))
+    }
+}
+
+impl Bind for &[Uuid] {
+    fn into_arg(self) -> Arg {
+        Arg::UuidList(self.to_vec())
+    }
+}
+
+impl Bind for Vec<Uuid> {
+    fn into_arg(self) -> Arg {
+        Arg::UuidList(self)
+    }
+}
+
+impl Bind for &Vec<Uuid> {
+    fn into_arg(self) -> Arg {
+        Arg::UuidList(self.clone())
+    }
+}
+
+impl Bind for &[String] {
+    fn into_arg(self) -> Arg {
+        Arg::TextList(self.to_vec())
+    }
+}
+
+impl Bind for Vec<String> {
+    fn into_arg(self) -> Arg {
+        Arg::TextList(self)
+    }
+}
+
+impl Bind for &Vec<String> {
+    fn into_arg(self) -> Arg {
+        Arg::TextList(self.clone())
+    }
+}
+
+impl Bind for Arg {
+    fn into_arg(self) -> Arg {
+        self
+    }
+}
+
+/// A `TEXT[]` column value. Postgres stores a native array; SQLite stores the
+/// same list as a JSON array in a `TEXT` column.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct TextArray(pub Vec<String>);
+
+impl Bind for TextArray {
+    fn into_arg(self) -> Arg {
+        Arg::TextArray(Some(self.0))
+    }
+}
+
+impl Type<Postgres> for TextArray {
+    fn type_info() -> PgTypeInfo {
+        <Vec<String> as Type<Postgres>>::type_info()
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for TextArray {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        Ok(Self(<Vec<String> as Decode<Postgres>>::decode(value)?))
+    }
+}
+
+impl Type<Sqlite> for TextArray {
+    fn type_info() -> sqlx::sqlite::SqliteTypeInfo {
+        <String as Type<Sqlite>>::type_info()
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for TextArray {
+    fn decode(value: sqlx::sqlite::SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let text = <&str as Decode<Sqlite>>::decode(value)?;
+        Ok(Self(serde_json::from_str(text)?))
+    }
+}
+
+/// Text bound in place of a Postgres enum, tagged with the enum's type name.
+struct PgEnum {
+    type_name: &'static str,
+    value: Option<&'static str>,
+}
+
+impl Type<Postgres> for PgEnum {
+    fn type_info() -> PgTypeInfo {
+        <str as Type<Postgres>>::type_info()
+    }
+}
+
+impl Encode<'_, Postgres> for PgEnum {
+    fn encode_by_ref(
+        &self,
+        buf: &mut sqlx::postgres::PgArgumentBuffer,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        match self.value {
+            Some(value) => <&str as Encode<Postgres>>::encode(value, buf),
+            None => Ok(sqlx::encode::IsNull::Yes),
+        }
+    }
+
+    fn produces(&self) -> Option<PgTypeInfo> {
+        Some(PgTypeInfo::with_name(self.type_name))
+    }
+}
+
+/// Timestamps are stored on SQLite as fixed-width UTC text so that they sort
+/// and compare correctly as strings.
+pub const SQLITE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
+
+/// SQL for the current time in [`SQLITE_TIME_FORMAT`].
+pub const SQLITE_NOW: &str = "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+
+fn encode_err(e: sqlx::error::BoxDynError) -> sqlx::Error {
+    sqlx::Error::Encode(e)
+}
+
+fn pg_arguments(args: Vec<Arg>) -> Result<PgArguments, sqlx::Error> {
+    let mut out = PgArguments::default();
+    for arg in args {
+        match arg {
+            Arg::Bool(v) => out.add(v),
+            Arg::I16(v) => out.add(v),
+            Arg::I32(v) => out.add(v),
+            Arg::I64(v) => out.add(v),
+            Arg::F64(v) => out.add(v),
+            Arg::Text(v) => out.add(v),
+            Arg::Bytes(v) => out.add(v),
+            Arg::Uuid(v) => out.add(v),
+            Arg::Time(v) => out.add(v),
+            Arg::Json(v) => out.add(v),
+            Arg::TextArray(v) => out.add(v),
+            Arg::UuidList(v) => out.add(v),
+            Arg::TextList(v) => out.add(v),
+            Arg::Enum(type_name, value) => out.add(PgEnum { type_name, value }),
+        }
+        .map_err(encode_err)?;
+    }
+    Ok(out)
+}
+
+fn add_sqlite(out: &mut SqliteArguments<'static>, arg: Arg) -> Result<(), sqlx::Error> {
+    match arg {
+        Arg::Bool(v) => out.add(v),
+        Arg::I16(v) => out.add(v),
+        Arg::I32(v) => out.add(v),
+        Arg::I64(v) => out.add(v),
+        Arg::F64(v) => out.add(v),
+        Arg::Text(v) => out.add(v),
+        Arg::Bytes(v) => out.add(v),
+        Arg::Uuid(v) => out.add(v),
+        Arg::Time(v) => out.add(v.map(|t| t.format(SQLITE_TIME_FORMAT).to_string())),
+        Arg::Json(v) => out.add(v.map(|j| j.to_string())),
+        Arg::TextArray(v) => out.add(v.map(|a| serde_json::Value::from(a).to_string())),
+        Arg::Enum(_, v) => out.add(v),
+        Arg::UuidList(_) | Arg::TextList(_) => unreachable!("lists are expanded by the caller"),
+    }
+    .map_err(encode_err)
+}
+
+/// Rewrite a Postgres-flavoured statement for SQLite and flatten its arguments
+/// in placeholder order. See the module docs for what is rewritten.
+fn sqlite_statement(
+    sql: &str,
+    args: Vec<Arg>,
+) -> Result<(String, SqliteArguments<'static>), sqlx::Error> {
+    let sql = sql
+        .replace("= ANY(", "IN (")
+        .replace("= ANY (", "IN (")
+        .replace("<> ALL(", "NOT IN (")
+        .replace("<> ALL (", "NOT IN (")
+        .replace("ILIKE", "LIKE")
+        .replace("NOW()", SQLITE_NOW)
+        .replace("FOR UPDATE SKIP LOCKED", "")
+        .replace("FOR UPDATE", "");
+
+    let mut args: Vec<Option<Arg>> = args.into_iter().map(Some).collect();
+    // Placeholder number(s) assigned to each `$N`, in order of first use.
+    let mut assigned: Vec<Option<Vec<usize>>> = vec![None; args.len()];
+    let mut flat = SqliteArguments::default();
+    let mut next = 1usize;
+
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.char_indices().peekable();
+    let mut in_literal = false;
+    while let Some((i, c)) = chars.next() {
+        if in_literal {
+            out.push(c);
+            if c == '\'' {
+                in_literal = false;
+            }
+            continue;
+        }
+        match c {
+            '\'' => {
+                in_literal = true;
+                out.push(c);
+            }
+            '-' if chars.peek().map(|&(_, c)| c) == Some('-') => {
+                // A `--` comment runs to the end of the line and may contain
+                // apostrophes, so copy it through untouched.
+                out.push(c);
+                while let Some(&(_, c)) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    out.push(c);
+                    chars.next();
+                }
+            }
+            '$' => {
+                let digits: String = sql[i + 1..]
+                    .chars()
+                    .take_while(char::is_ascii_digit)
+                    .collect();
+                if digits.is_empty() {
+                    out.push(c);
+                    continue;
+                }
+                for _ in 0..digits.len() {
+                    chars.next();
+                }
+                let n: usize = digits
+                    .parse()
+                    .map_err(|_| sqlx::Error::Protocol(format!("bad placeholder ${digits}")))?;
+                let slot = n
+                    .checked_sub(1)
+                    .filter(|&s| s < args.len())
+                    .ok_or_else(|| sqlx::Error::Protocol(format!("no argument bound for ${n}")))?;
+                if assigned[slot].is_none() {
+                    let values = match args[slot].take().expect("argument consumed once") {
+                        Arg::UuidList(list) => {
+                            list.into_iter().map(|u| Arg::Uuid(Some(u))).collect()
+                        }
+                        Arg::TextList(list) => {
+                            list.into_iter().map(|s| Arg::Text(Some(s))).collect()
+                        }
+                        arg => vec![arg],
+                    };
+                    let mut numbers = Vec::with_capacity(values.len());
+                    for value in values {
+                        add_sqlite(&mut flat, value)?;
+                        numbers.push(next);
+                        next += 1;
+                    }
+                    assigned[slot] = Some(numbers);
+                }
+                let numbers = assigned[slot].as_ref().expect("assigned above");
+                let placeholders: Vec<String> = numbers.iter().map(|k| format!("?{k}")).collect();
+                out.push_str(&placeholders.join(", "));
+            }
+            ':' if chars.peek().map(|&(_, c)| c) == Some(':') => {
+                // Drop a `::type` or `::type[]` cast.
+                chars.next();
+                while let Some(&(_, c)) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' || c == '[' || c == ']' {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    Ok((out, flat))
+}
+
+/// Result of a statement run with [`Query::execute`].
+#[derive(Debug, Clone, Copy)]
+pub struct QueryResult {
+    rows_affected: u64,
+}
+
+impl QueryResult {
+    pub fn rows_affected(&self) -> u64 {
+        self.rows_affected
+    }
+}
+
+/// A statement that returns no rows; see [`query`].
+#[must_use = "query must be executed to affect the database"]
+pub struct Query<'q> {
+    sql: Cow<'q, str>,
+    args: Vec<Arg>,
+}
+
+/// Build a statement with `$N` placeholders.
+pub fn query<'q>(sql: impl Into<Cow<'q, str>>) -> Query<'q> {
+    Query {
+        sql: sql.into(),
+        args: Vec::new(),
+    }
+}
+
+impl<'q> Query<'q> {
+    pub fn bind(mut self, value: impl Bind) -> Self {
+        self.args.push(value.into_arg());
+        self
+    }
+
+    pub async fn execute<'c>(
+        self,
+        executor: impl Executor<'c>,
+    ) -> Result<QueryResult, sqlx::Error> {
+        let rows_affected = match executor.target() {
+            Target::PgPool(pool) => sqlx::query_with(&self.sql, pg_arguments(self.args)?)
+                .execute(pool)
+                .await?
+                .rows_affected(),
+            Target::PgConn(conn) => sqlx::query_with(&self.sql, pg_arguments(self.args)?)
+                .execute(conn)
+                .await?
+                .rows_affected(),
+            Target::SqlitePool(pool) => {
+                let (sql, args) = sqlite_statement(&self.sql, self.args)?;
+                sqlx::query_with(&sql, args)
+                    .execute(pool)
+                    .await?
+                    .rows_affected()
+            }
+            Target::SqliteConn(conn) => {
+                let (sql, args) = sqlite_statement(&self.sql, self.args)?;
+                sqlx::query_with(&sql, args)
+                    .execute(conn)
+                    .await?
+                    .rows_affected()
+            }
+        };
+        Ok(QueryResult { rows_affected })
+    }
+}
+
+/// Row types that decode on both backends. Implemented by `#[derive(sqlx::FromRow)]`
+/// and by tuples.
+pub trait Row: for<'r> FromRow<'r, PgRow> + for<'r> FromRow<'r, SqliteRow> + Send + Unpin {}
+
+impl<T> Row for T where T: for<'r> FromRow<'r, PgRow> + for<'r> FromRow<'r, SqliteRow> + Send + Unpin
+{}
+
+/// A statement returning rows mapped to `O`; see [`query_as`].
+#[must_use = "query must be executed to affect the database"]
+pub struct QueryAs<'q, O> {
+    inner: Query<'q>,
+    _row: PhantomData<fn() -> O>,
+}
+
+/// Build a query whose rows decode into `O`.
+pub fn query_as<'q, O: Row>(sql: impl Into<Cow<'q, str>>) -> QueryAs<'q, O> {
+    QueryAs {
+        inner: query(sql),
+        _row: PhantomData,
+    }
+}
+
+macro_rules! fetch {
+    ($self:ident, $executor:ident, $method:ident) => {{
+        let Query { sql, args } = $self.inner;
+        match $executor.target() {
+            Target::PgPool(pool) => {
+                sqlx::query_as_with::<Postgres, O, _>(&sql, pg_arguments(args)?)
+                    .$method(pool)
+                    .await
+            }
+            Target::PgConn(conn) => {
+                sqlx::query_as_with::<Postgres, O, _>(&sql, pg_arguments(args)?)
+                    .$method(conn)
+                    .await
+            }
+            Target::SqlitePool(pool) => {
+                let (sql, args) = sqlite_statement(&sql, args)?;
+                sqlx::query_as_with::<Sqlite, O, _>(&sql, args)
+                    .$method(pool)
+                    .await
+            }
+            Target::SqliteConn(conn) => {
+                let (sql, args) = sqlite_statement(&sql, args)?;
+                sqlx::query_as_with::<Sqlite, O, _>(&sql, args)
+                    .$method(conn)
+                    .await
+            }
+        }
+    }};
+}
+
+impl<'q, O: Row> QueryAs<'q, O> {
+    pub fn bind(mut self, value: impl Bind) -> Self {
+        self.inner = self.inner.bind(value);
+        self
+    }
+
+    pub async fn fetch_one<'c>(self, executor: impl Executor<'c>) -> Result<O, sqlx::Error> {
+        fetch!(self, executor, fetch_one)
+    }
+
+    pub async fn fetch_optional<'c>(
+        self,
+        executor: impl Executor<'c>,
+    ) -> Result<Option<O>, sqlx::Error> {
+        fetch!(self, executor, fetch_optional)
+    }
+
+    pub async fn fetch_all<'c>(self, executor: impl Executor<'c>) -> Result<Vec<O>, sqlx::Error> {
+        fetch!(self, executor, fetch_all)
+    }
+
+    pub async fn execute<'c>(
+        self,
+        executor: impl Executor<'c>,
+    ) -> Result<QueryResult, sqlx::Error> {
+        self.inner.execute(executor).await
+    }
+}
+
+/// A query returning a single column; see [`query_scalar`].
+#[must_use = "query must be executed to affect the database"]
+pub struct QueryScalar<'q, O> {
+    inner: QueryAs<'q, (O,)>,
+}
+
+/// Build a query whose first column decodes into `O`.
+pub fn query_scalar<'q, O>(sql: impl Into<Cow<'q, str>>) -> QueryScalar<'q, O>
+where
+    (O,): Row,
+{
+    QueryScalar {
+        inner: query_as(sql),
+    }
+}
+
+impl<'q, O> QueryScalar<'q, O>
+where
+    (O,): Row,
+{
+    pub fn bind(mut self, value: impl Bind) -> Self {
+        self.inner = self.inner.bind(value);
+        self
+    }
+
+    pub async fn fetch_one<'c>(self, executor: impl Executor<'c>) -> Result<O, sqlx::Error> {
+        Ok(self.inner.fetch_one(executor).await?.0)
+    }
+
+    pub async fn fetch_optional<'c>(
+        self,
+        executor: impl Executor<'c>,
+    ) -> Result<Option<O>, sqlx::Error> {
+        Ok(self.inner.fetch_optional(executor).await?.map(|r| r.0))
+    }
+
+    pub async fn fetch_all<'c>(self, executor: impl Executor<'c>) -> Result<Vec<O>, sqlx::Error> {
+        Ok(self
+            .inner
+            .fetch_all(executor)
+            .await?
+            .into_iter()
+            .map(|r| r.0)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(sql: &str, args: Vec<Arg>) -> String {
+        sqlite_statement(sql, args).unwrap().0
+    }
+
+    #[test]
+    fn placeholders_follow_first_use() {
+        assert_eq!(
+            rewrite(
+                "SELECT * FROM t WHERE a = $2 AND b = $1 AND c = $2",
+                vec![Arg::I32(Some(1)), Arg::I32(Some(2))]
+            ),
+            "SELECT * FROM t WHERE a = ?1 AND b = ?2 AND c = ?1"
+        );
+    }
+
+    #[test]
+    fn lists_expand() {
+        let ids = vec![Uuid::nil(), Uuid::max()];
+        assert_eq!(
+            rewrite(
+                "DELETE FROM t WHERE id = ANY($1) AND u = $2",
+                vec![Arg::UuidList(ids), Arg::Uuid(Some(Uuid::nil()))]
+            ),
+            "DELETE FROM t WHERE id IN (?1, ?2) AND u = ?3"
+        );
+        assert_eq!(
+            rewrite("SELECT 1 WHERE x <> ALL($1)", vec![Arg::TextList(vec![])]),
+            "SELECT 1 WHERE x NOT IN ()"
+        );
+    }
+
+    #[test]
+    fn casts_and_functions() {
+        assert_eq!(
+            rewrite(
+                "SELECT COUNT(*)::BIGINT FROM t WHERE x ILIKE $1 AND at > '1970-01-01T00:00:00Z'::timestamptz AND at < NOW() FOR UPDATE",
+                vec![Arg::Text(Some("%a%".into()))]
+            ),
+            format!(
+                "SELECT COUNT(*) FROM t WHERE x LIKE ?1 AND at > '1970-01-01T00:00:00Z' AND at < {SQLITE_NOW} "
+            )
+        );
+    }
+
+    #[test]
+    fn literals_and_comments_are_untouched() {
+        assert_eq!(
+            rewrite("SELECT '$1 a::b' WHERE y = $1", vec![Arg::Bool(Some(true))]),
+            "SELECT '$1 a::b' WHERE y = ?1"
+        );
+        assert_eq!(
+            rewrite(
+                "SELECT 1 -- don't rewrite $1\nWHERE y = $1",
+                vec![Arg::Bool(Some(true))]
+            ),
+            "SELECT 1 -- don't rewrite $1\nWHERE y = ?1"
+        );
+    }
+}
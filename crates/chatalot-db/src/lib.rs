@@ -1,3 +1,4 @@
+pub mod db;
 pub mod models;
 pub mod pool;
 pub mod repos;
@@ -9,4 +9,8 @@ pub struct BlockedHash {
     pub reason: Option<String>,
     pub blocked_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
+    /// `sha256` (exact checksum) or `dhash` (perceptual hash of an image).
+    pub kind: String,
+    /// For `dhash` entries: how many bits an image's hash may differ by.
+    pub max_distance: Option<i32>,
 }
@@ -29,6 +29,23 @@ pub enum ChannelType {
     Gallery,
 }
 
+impl ChannelType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Voice => "voice",
+            Self::Dm => "dm",
+            Self::Gallery => "gallery",
+        }
+    }
+}
+
+impl crate::db::Bind for ChannelType {
+    fn into_arg(self) -> crate::db::Arg {
+        crate::db::Arg::Enum("channel_type", Some(self.as_str()))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct ChannelMember {
     pub channel_id: Uuid,
@@ -0,0 +1,10 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MessageDelivery {
+    pub message_id: Uuid,
+    pub user_id: Uuid,
+    pub delivered_at: DateTime<Utc>,
+}
@@ -17,6 +17,14 @@ pub struct FileRecord {
     pub created_at: DateTime<Utc>,
     pub thumbnail_path: Option<String>,
     pub exif_stripped: bool,
+    pub duration_ms: Option<i64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub blurhash: Option<String>,
+    /// Comma-separated widths of the stored WebP variants.
+    pub variants: Option<String>,
+    /// Perceptual hash (dHash, hex) of images.
+    pub phash: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -26,3 +34,22 @@ pub struct DmPair {
     pub channel_id: Uuid,
     pub created_at: DateTime<Utc>,
 }
+
+/// Stored bytes shared by every `files` row with the same content.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FileBlob {
+    pub storage_path: String,
+    pub checksum: String,
+    pub size_bytes: i64,
+    pub thumbnail_path: Option<String>,
+    pub ref_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub duration_ms: Option<i64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub blurhash: Option<String>,
+    pub variants: Option<String>,
+    pub phash: Option<String>,
+    /// False while the upload that claimed this key is still storing it.
+    pub stored: bool,
+}
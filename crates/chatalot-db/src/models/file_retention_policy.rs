@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FileRetentionPolicy {
+    /// `channel` or `community`.
+    pub scope_type: String,
+    pub scope_id: Uuid,
+    pub max_age_days: Option<i32>,
+    pub max_total_bytes: Option<i64>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
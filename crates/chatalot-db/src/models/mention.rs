@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::message::Message;
+
+/// A message that mentions a user, with how they were mentioned.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MentionedMessage {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub message: Message,
+    /// "user", "role" or "everyone"
+    pub mention_kind: String,
+}
@@ -5,22 +5,31 @@ pub mod bookmark;
 pub mod channel;
 pub mod community;
 pub mod custom_emoji;
+pub mod delivery;
 pub mod file;
+pub mod file_retention_policy;
 pub mod group;
+pub mod job;
 pub mod key_bundle;
+pub mod mention;
 pub mod message;
+pub mod notification_setting;
 pub mod pin;
 pub mod poll;
 pub mod push_subscription;
+pub mod quiet_hours;
 pub mod reaction;
 pub mod registration_invite;
 pub mod report;
 pub mod scheduled_message;
 pub mod sender_key;
+pub mod storage_quota;
 pub mod timeout;
+pub mod upload;
 pub mod user;
 pub mod user_block;
 pub mod user_preferences;
 pub mod voice;
 pub mod warning;
 pub mod webhook;
+pub mod ws_event;
@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NotificationSetting {
+    pub user_id: Uuid,
+    pub scope_type: String,
+    pub scope_id: Uuid,
+    pub level: String,
+    pub muted_until: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The setting that applies to a user in a specific channel, after walking
+/// channel -> group -> community.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct EffectiveNotificationSetting {
+    pub user_id: Uuid,
+    pub level: String,
+    pub muted_until: Option<DateTime<Utc>>,
+}
+
+impl EffectiveNotificationSetting {
+    /// Whether the scope is temporarily muted at `now`.
+    pub fn is_muted(&self, now: DateTime<Utc>) -> bool {
+        self.muted_until.is_some_and(|t| t > now)
+    }
+}
@@ -7,10 +7,13 @@ pub struct PushSubscription {
     pub id: Uuid,
     pub user_id: Uuid,
     pub endpoint: String,
-    pub p256dh_key: String,
-    pub auth_key: String,
+    /// Encryption keys; only present for Web Push subscriptions.
+    pub p256dh_key: Option<String>,
+    pub auth_key: Option<String>,
     pub user_agent: Option<String>,
     pub created_at: DateTime<Utc>,
     pub last_used: Option<DateTime<Utc>>,
     pub failure_count: i32,
+    /// Transport: 'webpush' or 'unifiedpush'.
+    pub kind: String,
 }
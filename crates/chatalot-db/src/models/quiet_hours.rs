@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct QuietHoursSettings {
+    pub user_id: Uuid,
+    pub enabled: bool,
+    pub timezone: String,
+    pub delivery: String,
+    pub auto_dnd: bool,
+    pub quiet_active: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct QuietHoursSchedule {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// Bitmask of local weekdays the window starts on (bit 0 = Monday).
+    pub days: i16,
+    pub start_minute: i16,
+    pub end_minute: i16,
+}
+
+/// A push recipient who is currently in quiet hours or manual DND.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct QuietRecipient {
+    pub user_id: Uuid,
+    /// 'suppress' or 'batch'
+    pub delivery: String,
+    /// Whether the sender is on the recipient's breakthrough list.
+    pub breakthrough: bool,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DeferredPush {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub notification_type: String,
+    pub sender_name: String,
+    pub channel_id: Uuid,
+    pub channel_name: String,
+    /// Notifications for this channel coalesced into the row.
+    pub count: i32,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StorageQuota {
+    /// `community`, `group` or `role`.
+    pub scope_type: String,
+    /// Community ID (`community` and `role`) or group ID (`group`).
+    pub scope_id: Uuid,
+    /// Community role of a `role` quota, empty otherwise.
+    pub role: String,
+    /// 0 = unlimited (`role` only).
+    pub max_bytes: i64,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A resumable upload that has not received all of its bytes yet.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Upload {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub upload_length: i64,
+    pub upload_offset: i64,
+    pub encrypted_name: String,
+    pub content_type: Option<String>,
+    pub channel_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
@@ -2,6 +2,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::db::TextArray;
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
     pub id: Uuid,
@@ -23,7 +25,7 @@ pub struct User {
     pub suspended_at: Option<DateTime<Utc>>,
     pub suspended_reason: Option<String>,
     pub recovery_code_hash: Option<String>,
-    pub totp_backup_codes: Option<Vec<String>>,
+    pub totp_backup_codes: Option<TextArray>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
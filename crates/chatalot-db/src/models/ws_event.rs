@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct WsEvent {
+    pub id: Uuid,
+    /// JSON-encoded event; the server owns its shape.
+    pub event: String,
+    pub created_at: DateTime<Utc>,
+}
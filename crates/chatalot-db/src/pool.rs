@@ -1,16 +1,138 @@
-use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::str::FromStr;
+use std::time::Duration;
 
-/// Create a PostgreSQL connection pool.
-pub async fn create_pool(database_url: &str) -> Result<PgPool, sqlx::Error> {
-    PgPoolOptions::new()
-        .max_connections(50)
-        .min_connections(2)
-        .acquire_timeout(std::time::Duration::from_secs(5))
-        .connect(database_url)
-        .await
+use chrono::{DateTime, Utc};
+use sqlx::migrate::Migrator;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
+
+use crate::db::{Backend, Db};
+
+/// Create a connection pool for the backend named by the URL scheme:
+/// `postgres://` / `postgresql://` or `sqlite:`.
+pub async fn create_pool(database_url: &str) -> Result<Db, sqlx::Error> {
+    match Backend::from_url(database_url) {
+        Some(Backend::Postgres) => Ok(Db::Postgres(
+            PgPoolOptions::new()
+                .max_connections(50)
+                .min_connections(2)
+                .acquire_timeout(Duration::from_secs(5))
+                .connect(database_url)
+                .await?,
+        )),
+        Some(Backend::Sqlite) => {
+            let options = SqliteConnectOptions::from_str(database_url)?
+                .create_if_missing(true)
+                .foreign_keys(true)
+                .journal_mode(SqliteJournalMode::Wal)
+                .synchronous(SqliteSynchronous::Normal)
+                .busy_timeout(Duration::from_secs(10));
+            // Every connection to an in-memory database gets its own empty
+            // database, so keep exactly one and never recycle it.
+            let in_memory =
+                database_url.contains(":memory:") || database_url.contains("mode=memory");
+            let pool = if in_memory {
+                SqlitePoolOptions::new()
+                    .max_connections(1)
+                    .min_connections(1)
+                    .idle_timeout(None)
+                    .max_lifetime(None)
+            } else {
+                SqlitePoolOptions::new().max_connections(8)
+            };
+            Ok(Db::Sqlite(
+                pool.acquire_timeout(Duration::from_secs(5))
+                    .connect_with(options)
+                    .await?,
+            ))
+        }
+        None => Err(sqlx::Error::Configuration(
+            "DATABASE_URL must start with postgres://, postgresql:// or sqlite:".into(),
+        )),
+    }
+}
+
+/// Migrations embedded in this build for the given backend. SQLite has its
+/// own set under `migrations/sqlite`, numbered to match the Postgres ones.
+fn migrator(backend: Backend) -> Migrator {
+    match backend {
+        Backend::Postgres => sqlx::migrate!("../../migrations"),
+        Backend::Sqlite => sqlx::migrate!("../../migrations/sqlite"),
+    }
+}
+
+async fn run_migrator(db: &Db, migrator: Migrator) -> Result<(), sqlx::migrate::MigrateError> {
+    match db {
+        Db::Postgres(pool) => migrator.run(pool).await,
+        Db::Sqlite(pool) => migrator.run(pool).await,
+    }
+}
+
+/// Versions of migrations embedded in this build that have not been applied
+/// successfully to the database.
+pub async fn pending_migrations(db: &Db) -> Result<Vec<i64>, sqlx::Error> {
+    let applied: Vec<i64> =
+        crate::db::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = true")
+            .fetch_all(db)
+            .await?;
+    Ok(migrator(db.backend())
+        .iter()
+        .map(|m| m.version)
+        .filter(|v| !applied.contains(v))
+        .collect())
+}
+
+/// A migration embedded in this build and whether it has been applied.
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    /// When it was applied successfully, or `None` if pending.
+    pub installed_on: Option<DateTime<Utc>>,
+}
+
+/// Status of every migration embedded in this build, oldest first.
+pub async fn migration_status(db: &Db) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+    let applied: Vec<(i64, DateTime<Utc>)> = crate::db::query_as(
+        "SELECT version, installed_on FROM _sqlx_migrations WHERE success = true",
+    )
+    .fetch_all(db)
+    .await?;
+    Ok(migrator(db.backend())
+        .iter()
+        .map(|m| MigrationStatus {
+            version: m.version,
+            description: m.description.to_string(),
+            installed_on: applied
+                .iter()
+                .find(|(v, _)| *v == m.version)
+                .map(|(_, at)| *at),
+        })
+        .collect())
+}
+
+/// Version of the newest migration embedded in this build for `backend`.
+pub fn latest_migration(backend: Backend) -> i64 {
+    migrator(backend)
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Run pending migrations up to and including `version`.
+pub async fn run_migrations_to(db: &Db, version: i64) -> Result<(), sqlx::migrate::MigrateError> {
+    let mut migrator = migrator(db.backend());
+    migrator.migrations = migrator
+        .iter()
+        .filter(|m| m.version <= version)
+        .cloned()
+        .collect::<Vec<_>>()
+        .into();
+    run_migrator(db, migrator).await
 }
 
 /// Run all pending migrations.
-pub async fn run_migrations(pool: &PgPool) -> Result<(), sqlx::migrate::MigrateError> {
-    sqlx::migrate!("../../migrations").run(pool).await
+pub async fn run_migrations(db: &Db) -> Result<(), sqlx::migrate::MigrateError> {
+    run_migrator(db, migrator(db.backend())).await
 }
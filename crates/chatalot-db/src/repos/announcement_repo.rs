@@ -1,16 +1,16 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::announcement::Announcement;
 
 pub async fn create(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     title: &str,
     body: &str,
     created_by: Uuid,
 ) -> Result<Announcement, sqlx::Error> {
-    sqlx::query_as::<_, Announcement>(
+    db::query_as::<Announcement>(
         r#"
         INSERT INTO announcements (id, title, body, created_by)
         VALUES ($1, $2, $3, $4)
@@ -25,11 +25,8 @@ pub async fn create(
     .await
 }
 
-pub async fn list_undismissed(
-    pool: &PgPool,
-    user_id: Uuid,
-) -> Result<Vec<Announcement>, sqlx::Error> {
-    sqlx::query_as::<_, Announcement>(
+pub async fn list_undismissed(pool: &Db, user_id: Uuid) -> Result<Vec<Announcement>, sqlx::Error> {
+    db::query_as::<Announcement>(
         r#"
         SELECT a.* FROM announcements a
         WHERE a.id NOT IN (
@@ -44,12 +41,8 @@ pub async fn list_undismissed(
     .await
 }
 
-pub async fn dismiss(
-    pool: &PgPool,
-    user_id: Uuid,
-    announcement_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query(
+pub async fn dismiss(pool: &Db, user_id: Uuid, announcement_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query(
         r#"
         INSERT INTO announcement_dismissals (user_id, announcement_id)
         VALUES ($1, $2)
@@ -63,10 +56,8 @@ pub async fn dismiss(
     Ok(())
 }
 
-pub async fn list_all(pool: &PgPool) -> Result<Vec<Announcement>, sqlx::Error> {
-    sqlx::query_as::<_, Announcement>(
-        "SELECT * FROM announcements ORDER BY created_at DESC LIMIT 100",
-    )
-    .fetch_all(pool)
-    .await
+pub async fn list_all(pool: &Db) -> Result<Vec<Announcement>, sqlx::Error> {
+    db::query_as::<Announcement>("SELECT * FROM announcements ORDER BY created_at DESC LIMIT 100")
+        .fetch_all(pool)
+        .await
 }
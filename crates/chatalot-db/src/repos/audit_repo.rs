@@ -1,11 +1,11 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::audit_log::AuditLogEntry;
 
 /// Query audit log with optional filters and pagination.
 pub async fn query_audit_log(
-    pool: &PgPool,
+    pool: &Db,
     action: Option<&str>,
     user_id: Option<Uuid>,
     limit: i64,
@@ -15,7 +15,7 @@ pub async fn query_audit_log(
 
     match (action, user_id) {
         (Some(a), Some(u)) => {
-            sqlx::query_as::<_, AuditLogEntry>(
+            db::query_as::<AuditLogEntry>(
                 r#"
                 SELECT * FROM audit_log
                 WHERE action = $1 AND user_id = $2
@@ -31,7 +31,7 @@ pub async fn query_audit_log(
             .await
         }
         (Some(a), None) => {
-            sqlx::query_as::<_, AuditLogEntry>(
+            db::query_as::<AuditLogEntry>(
                 r#"
                 SELECT * FROM audit_log
                 WHERE action = $1
@@ -46,7 +46,7 @@ pub async fn query_audit_log(
             .await
         }
         (None, Some(u)) => {
-            sqlx::query_as::<_, AuditLogEntry>(
+            db::query_as::<AuditLogEntry>(
                 r#"
                 SELECT * FROM audit_log
                 WHERE user_id = $1
@@ -61,7 +61,7 @@ pub async fn query_audit_log(
             .await
         }
         (None, None) => {
-            sqlx::query_as::<_, AuditLogEntry>(
+            db::query_as::<AuditLogEntry>(
                 "SELECT * FROM audit_log ORDER BY created_at DESC LIMIT $1 OFFSET $2",
             )
             .bind(limit)
@@ -74,32 +74,32 @@ pub async fn query_audit_log(
 
 /// Count audit log entries with optional filters.
 pub async fn count_audit_log(
-    pool: &PgPool,
+    pool: &Db,
     action: Option<&str>,
     user_id: Option<Uuid>,
 ) -> Result<i64, sqlx::Error> {
     let row: (i64,) = match (action, user_id) {
         (Some(a), Some(u)) => {
-            sqlx::query_as("SELECT COUNT(*) FROM audit_log WHERE action = $1 AND user_id = $2")
+            db::query_as("SELECT COUNT(*) FROM audit_log WHERE action = $1 AND user_id = $2")
                 .bind(a)
                 .bind(u)
                 .fetch_one(pool)
                 .await?
         }
         (Some(a), None) => {
-            sqlx::query_as("SELECT COUNT(*) FROM audit_log WHERE action = $1")
+            db::query_as("SELECT COUNT(*) FROM audit_log WHERE action = $1")
                 .bind(a)
                 .fetch_one(pool)
                 .await?
         }
         (None, Some(u)) => {
-            sqlx::query_as("SELECT COUNT(*) FROM audit_log WHERE user_id = $1")
+            db::query_as("SELECT COUNT(*) FROM audit_log WHERE user_id = $1")
                 .bind(u)
                 .fetch_one(pool)
                 .await?
         }
         (None, None) => {
-            sqlx::query_as("SELECT COUNT(*) FROM audit_log")
+            db::query_as("SELECT COUNT(*) FROM audit_log")
                 .fetch_one(pool)
                 .await?
         }
@@ -1,15 +1,11 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::user_block::UserBlock;
 
 /// Block a user.
-pub async fn block_user(
-    pool: &PgPool,
-    blocker_id: Uuid,
-    blocked_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query(
+pub async fn block_user(pool: &Db, blocker_id: Uuid, blocked_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query(
         "INSERT INTO user_blocks (blocker_id, blocked_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
     )
     .bind(blocker_id)
@@ -21,11 +17,11 @@ pub async fn block_user(
 
 /// Unblock a user.
 pub async fn unblock_user(
-    pool: &PgPool,
+    pool: &Db,
     blocker_id: Uuid,
     blocked_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2")
+    let result = db::query("DELETE FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2")
         .bind(blocker_id)
         .bind(blocked_id)
         .execute(pool)
@@ -35,11 +31,11 @@ pub async fn unblock_user(
 
 /// Check if blocker has blocked blocked_id.
 pub async fn is_blocked(
-    pool: &PgPool,
+    pool: &Db,
     blocker_id: Uuid,
     blocked_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as(
+    let row: (bool,) = db::query_as(
         "SELECT EXISTS(SELECT 1 FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2)",
     )
     .bind(blocker_id)
@@ -51,11 +47,11 @@ pub async fn is_blocked(
 
 /// Check if either user has blocked the other (bidirectional check for DMs).
 pub async fn is_blocked_either_way(
-    pool: &PgPool,
+    pool: &Db,
     user_a: Uuid,
     user_b: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as(
+    let row: (bool,) = db::query_as(
         "SELECT EXISTS(SELECT 1 FROM user_blocks WHERE (blocker_id = $1 AND blocked_id = $2) OR (blocker_id = $2 AND blocked_id = $1))",
     )
     .bind(user_a)
@@ -67,10 +63,10 @@ pub async fn is_blocked_either_way(
 
 /// List all users blocked by a given user.
 pub async fn list_blocked_users(
-    pool: &PgPool,
+    pool: &Db,
     blocker_id: Uuid,
 ) -> Result<Vec<UserBlock>, sqlx::Error> {
-    sqlx::query_as::<_, UserBlock>(
+    db::query_as::<UserBlock>(
         "SELECT * FROM user_blocks WHERE blocker_id = $1 ORDER BY created_at DESC",
     )
     .bind(blocker_id)
@@ -79,9 +75,9 @@ pub async fn list_blocked_users(
 }
 
 /// Get all user IDs that a user has blocked (for message filtering).
-pub async fn get_blocked_ids(pool: &PgPool, blocker_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+pub async fn get_blocked_ids(pool: &Db, blocker_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
     let rows: Vec<(Uuid,)> =
-        sqlx::query_as("SELECT blocked_id FROM user_blocks WHERE blocker_id = $1")
+        db::query_as("SELECT blocked_id FROM user_blocks WHERE blocker_id = $1")
             .bind(blocker_id)
             .fetch_all(pool)
             .await?;
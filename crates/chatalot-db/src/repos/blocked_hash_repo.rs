@@ -1,70 +1,137 @@
-use sqlx::PgPool;
+use crate::db::{self, Backend, Db, TextArray};
 use uuid::Uuid;
 
 use crate::models::blocked_hash::BlockedHash;
 
-/// Check if a file hash is in the blocklist.
-pub async fn is_hash_blocked(pool: &PgPool, hash: &str) -> Result<bool, sqlx::Error> {
-    let row: (bool,) =
-        sqlx::query_as("SELECT EXISTS(SELECT 1 FROM blocked_hashes WHERE hash = $1)")
-            .bind(hash)
-            .fetch_one(pool)
-            .await?;
+/// Entry kind for an exact SHA-256 checksum.
+pub const SHA256: &str = "sha256";
+/// Entry kind for a perceptual hash (dHash) of an image, matched within
+/// `max_distance` bits.
+pub const DHASH: &str = "dhash";
+
+/// Check if a file checksum is in the blocklist.
+pub async fn is_hash_blocked(pool: &Db, hash: &str) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as(
+        "SELECT EXISTS(SELECT 1 FROM blocked_hashes WHERE hash = $1 AND kind = 'sha256')",
+    )
+    .bind(hash)
+    .fetch_one(pool)
+    .await?;
     Ok(row.0)
 }
 
-/// Add a hash to the blocklist.
+/// Every perceptual hash in the blocklist. Matching is by Hamming distance,
+/// which the database cannot index, so callers compare them all.
+pub async fn list_perceptual_hashes(pool: &Db) -> Result<Vec<BlockedHash>, sqlx::Error> {
+    db::query_as::<BlockedHash>("SELECT * FROM blocked_hashes WHERE kind = 'dhash'")
+        .fetch_all(pool)
+        .await
+}
+
+const ADD_BLOCKED_HASH: &str = r#"
+    INSERT INTO blocked_hashes (id, kind, hash, max_distance, reason, blocked_by)
+    VALUES ($1, $2, $3, $4, $5, $6)
+    ON CONFLICT (hash) DO UPDATE SET reason = EXCLUDED.reason, max_distance = EXCLUDED.max_distance
+    RETURNING *
+"#;
+
+/// Add a hash to the blocklist. Re-adding a hash updates its reason and
+/// distance.
 pub async fn add_blocked_hash(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
+    kind: &str,
     hash: &str,
+    max_distance: Option<i32>,
     reason: Option<&str>,
-    blocked_by: Uuid,
+    blocked_by: Option<Uuid>,
 ) -> Result<BlockedHash, sqlx::Error> {
-    sqlx::query_as::<_, BlockedHash>(
-        r#"
-        INSERT INTO blocked_hashes (id, hash, reason, blocked_by)
-        VALUES ($1, $2, $3, $4)
-        ON CONFLICT (hash) DO UPDATE SET reason = EXCLUDED.reason
-        RETURNING *
-        "#,
-    )
-    .bind(id)
-    .bind(hash)
-    .bind(reason)
-    .bind(blocked_by)
-    .fetch_one(pool)
-    .await
+    db::query_as::<BlockedHash>(ADD_BLOCKED_HASH)
+        .bind(id)
+        .bind(kind)
+        .bind(hash)
+        .bind(max_distance)
+        .bind(reason)
+        .bind(blocked_by)
+        .fetch_one(pool)
+        .await
+}
+
+/// A hash to add with [`import_blocked_hashes`].
+#[derive(Debug, Clone, Copy)]
+pub struct NewBlockedHash<'a> {
+    pub kind: &'a str,
+    pub hash: &'a str,
+    pub max_distance: Option<i32>,
+    pub reason: Option<&'a str>,
+}
+
+/// Add hashes of any kind in one transaction, so either all of them are
+/// stored or none. Like [`add_blocked_hash`], hashes already blocked take
+/// the new reason and distance. Returns how many were stored.
+pub async fn import_blocked_hashes(
+    pool: &Db,
+    entries: &[NewBlockedHash<'_>],
+    blocked_by: Option<Uuid>,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for entry in entries {
+        db::query(ADD_BLOCKED_HASH)
+            .bind(Uuid::now_v7())
+            .bind(entry.kind)
+            .bind(entry.hash)
+            .bind(entry.max_distance)
+            .bind(entry.reason)
+            .bind(blocked_by)
+            .execute(&mut tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(entries.len() as u64)
 }
 
-/// Add multiple hashes to the blocklist at once.
+/// Add multiple hashes of one kind to the blocklist at once.
 pub async fn add_blocked_hashes(
-    pool: &PgPool,
+    pool: &Db,
+    kind: &str,
     hashes: &[String],
+    max_distance: Option<i32>,
     reason: Option<&str>,
     blocked_by: Uuid,
 ) -> Result<u64, sqlx::Error> {
     if hashes.is_empty() {
         return Ok(0);
     }
-    let result = sqlx::query(
-        r#"
-        INSERT INTO blocked_hashes (id, hash, reason, blocked_by)
-        SELECT gen_random_uuid(), unnest($1::text[]), $2, $3
-        ON CONFLICT (hash) DO UPDATE SET reason = EXCLUDED.reason
-        "#,
-    )
-    .bind(hashes)
-    .bind(reason)
-    .bind(blocked_by)
-    .execute(pool)
-    .await?;
+    let sql = match pool.backend() {
+        Backend::Postgres => {
+            r#"
+            INSERT INTO blocked_hashes (id, hash, kind, max_distance, reason, blocked_by)
+            SELECT gen_random_uuid(), unnest($1::text[]), $2, $3, $4, $5
+            ON CONFLICT (hash) DO UPDATE SET reason = EXCLUDED.reason
+            "#
+        }
+        Backend::Sqlite => {
+            r#"
+            INSERT INTO blocked_hashes (id, hash, kind, max_distance, reason, blocked_by)
+            SELECT randomblob(16), value, $2, $3, $4, $5 FROM json_each($1) WHERE true
+            ON CONFLICT (hash) DO UPDATE SET reason = excluded.reason
+            "#
+        }
+    };
+    let result = db::query(sql)
+        .bind(TextArray(hashes.to_vec()))
+        .bind(kind)
+        .bind(max_distance)
+        .bind(reason)
+        .bind(blocked_by)
+        .execute(pool)
+        .await?;
     Ok(result.rows_affected())
 }
 
 /// Remove a hash from the blocklist.
-pub async fn remove_blocked_hash(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM blocked_hashes WHERE id = $1")
+pub async fn remove_blocked_hash(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM blocked_hashes WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
@@ -73,11 +140,11 @@ pub async fn remove_blocked_hash(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::
 
 /// List blocked hashes with pagination.
 pub async fn list_blocked_hashes(
-    pool: &PgPool,
+    pool: &Db,
     limit: i64,
     offset: i64,
 ) -> Result<Vec<BlockedHash>, sqlx::Error> {
-    sqlx::query_as::<_, BlockedHash>(
+    db::query_as::<BlockedHash>(
         "SELECT * FROM blocked_hashes ORDER BY created_at DESC LIMIT $1 OFFSET $2",
     )
     .bind(limit)
@@ -86,9 +153,16 @@ pub async fn list_blocked_hashes(
     .await
 }
 
+/// The whole blocklist, oldest first, for export.
+pub async fn list_all_blocked_hashes(pool: &Db) -> Result<Vec<BlockedHash>, sqlx::Error> {
+    db::query_as::<BlockedHash>("SELECT * FROM blocked_hashes ORDER BY created_at, hash")
+        .fetch_all(pool)
+        .await
+}
+
 /// Count total blocked hashes.
-pub async fn count_blocked_hashes(pool: &PgPool) -> Result<i64, sqlx::Error> {
-    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM blocked_hashes")
+pub async fn count_blocked_hashes(pool: &Db) -> Result<i64, sqlx::Error> {
+    let row: (i64,) = db::query_as("SELECT COUNT(*) FROM blocked_hashes")
         .fetch_one(pool)
         .await?;
     Ok(row.0)
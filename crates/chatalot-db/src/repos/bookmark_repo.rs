@@ -1,16 +1,16 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::bookmark::Bookmark;
 
 pub async fn create(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     user_id: Uuid,
     message_id: Uuid,
     note: Option<&str>,
 ) -> Result<Bookmark, sqlx::Error> {
-    sqlx::query_as::<_, Bookmark>(
+    db::query_as::<Bookmark>(
         r#"
         INSERT INTO bookmarks (id, user_id, message_id, note)
         VALUES ($1, $2, $3, $4)
@@ -27,12 +27,12 @@ pub async fn create(
 }
 
 pub async fn list_for_user(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     limit: i64,
     offset: i64,
 ) -> Result<Vec<Bookmark>, sqlx::Error> {
-    sqlx::query_as::<_, Bookmark>(
+    db::query_as::<Bookmark>(
         "SELECT * FROM bookmarks WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
     )
     .bind(user_id)
@@ -42,8 +42,8 @@ pub async fn list_for_user(
     .await
 }
 
-pub async fn delete(pool: &PgPool, id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM bookmarks WHERE id = $1 AND user_id = $2")
+pub async fn delete(pool: &Db, id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM bookmarks WHERE id = $1 AND user_id = $2")
         .bind(id)
         .bind(user_id)
         .execute(pool)
@@ -51,8 +51,8 @@ pub async fn delete(pool: &PgPool, id: Uuid, user_id: Uuid) -> Result<bool, sqlx
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn exists(pool: &PgPool, user_id: Uuid, message_id: Uuid) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as(
+pub async fn exists(pool: &Db, user_id: Uuid, message_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as(
         "SELECT EXISTS(SELECT 1 FROM bookmarks WHERE user_id = $1 AND message_id = $2)",
     )
     .bind(user_id)
@@ -1,4 +1,4 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use chrono::{DateTime, Utc};
@@ -6,8 +6,9 @@ use chrono::{DateTime, Utc};
 use crate::models::channel::{Channel, ChannelMember, ChannelMemberInfo, ChannelType};
 
 /// Create a new channel and add the creator as owner.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_channel(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     name: &str,
     channel_type: ChannelType,
@@ -18,7 +19,7 @@ pub async fn create_channel(
 ) -> Result<Channel, sqlx::Error> {
     let mut tx = pool.begin().await?;
 
-    let channel = sqlx::query_as::<_, Channel>(
+    let channel = db::query_as::<Channel>(
         r#"
         INSERT INTO channels (id, name, channel_type, topic, created_by, group_id, discoverable)
         VALUES ($1, $2, $3, $4, $5, $6, $7)
@@ -32,10 +33,10 @@ pub async fn create_channel(
     .bind(created_by)
     .bind(group_id)
     .bind(discoverable)
-    .fetch_one(&mut *tx)
+    .fetch_one(&mut tx)
     .await?;
 
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO channel_members (channel_id, user_id, role)
         VALUES ($1, $2, 'owner')
@@ -43,7 +44,7 @@ pub async fn create_channel(
     )
     .bind(id)
     .bind(created_by)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     tx.commit().await?;
@@ -51,8 +52,8 @@ pub async fn create_channel(
 }
 
 /// List all channels a user is a member of.
-pub async fn list_user_channels(pool: &PgPool, user_id: Uuid) -> Result<Vec<Channel>, sqlx::Error> {
-    sqlx::query_as::<_, Channel>(
+pub async fn list_user_channels(pool: &Db, user_id: Uuid) -> Result<Vec<Channel>, sqlx::Error> {
+    db::query_as::<Channel>(
         r#"
         SELECT c.* FROM channels c
         INNER JOIN channel_members cm ON c.id = cm.channel_id
@@ -66,8 +67,8 @@ pub async fn list_user_channels(pool: &PgPool, user_id: Uuid) -> Result<Vec<Chan
 }
 
 /// Get a channel by ID.
-pub async fn get_channel(pool: &PgPool, id: Uuid) -> Result<Option<Channel>, sqlx::Error> {
-    sqlx::query_as::<_, Channel>("SELECT * FROM channels WHERE id = $1")
+pub async fn get_channel(pool: &Db, id: Uuid) -> Result<Option<Channel>, sqlx::Error> {
+    db::query_as::<Channel>("SELECT * FROM channels WHERE id = $1")
         .bind(id)
         .fetch_optional(pool)
         .await
@@ -75,11 +76,11 @@ pub async fn get_channel(pool: &PgPool, id: Uuid) -> Result<Option<Channel>, sql
 
 /// Check if a channel belongs to a community (via its group).
 pub async fn channel_belongs_to_community(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     community_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as(
+    let row: (bool,) = db::query_as(
         r#"SELECT EXISTS(
             SELECT 1 FROM channels c
             JOIN groups g ON c.group_id = g.id
@@ -94,12 +95,8 @@ pub async fn channel_belongs_to_community(
 }
 
 /// Check if a user is a member of a channel.
-pub async fn is_member(
-    pool: &PgPool,
-    channel_id: Uuid,
-    user_id: Uuid,
-) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as(
+pub async fn is_member(pool: &Db, channel_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as(
         "SELECT EXISTS(SELECT 1 FROM channel_members WHERE channel_id = $1 AND user_id = $2)",
     )
     .bind(channel_id)
@@ -110,12 +107,8 @@ pub async fn is_member(
 }
 
 /// Add a user to a channel.
-pub async fn join_channel(
-    pool: &PgPool,
-    channel_id: Uuid,
-    user_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query(
+pub async fn join_channel(pool: &Db, channel_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query(
         r#"
         INSERT INTO channel_members (channel_id, user_id, role)
         VALUES ($1, $2, 'member')
@@ -131,17 +124,17 @@ pub async fn join_channel(
 
 /// Add multiple users to a channel at once.
 pub async fn join_channel_batch(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     user_ids: &[Uuid],
 ) -> Result<(), sqlx::Error> {
     if user_ids.is_empty() {
         return Ok(());
     }
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO channel_members (channel_id, user_id, role)
-        SELECT $1, unnest($2::uuid[]), 'member'
+        SELECT $1, u.id, 'member' FROM users u WHERE u.id = ANY($2)
         ON CONFLICT (channel_id, user_id) DO NOTHING
         "#,
     )
@@ -153,12 +146,8 @@ pub async fn join_channel_batch(
 }
 
 /// Remove a user from a channel.
-pub async fn leave_channel(
-    pool: &PgPool,
-    channel_id: Uuid,
-    user_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query("DELETE FROM channel_members WHERE channel_id = $1 AND user_id = $2")
+pub async fn leave_channel(pool: &Db, channel_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query("DELETE FROM channel_members WHERE channel_id = $1 AND user_id = $2")
         .bind(channel_id)
         .bind(user_id)
         .execute(pool)
@@ -167,11 +156,8 @@ pub async fn leave_channel(
 }
 
 /// List members of a channel.
-pub async fn list_members(
-    pool: &PgPool,
-    channel_id: Uuid,
-) -> Result<Vec<ChannelMember>, sqlx::Error> {
-    sqlx::query_as::<_, ChannelMember>(
+pub async fn list_members(pool: &Db, channel_id: Uuid) -> Result<Vec<ChannelMember>, sqlx::Error> {
+    db::query_as::<ChannelMember>(
         "SELECT * FROM channel_members WHERE channel_id = $1 ORDER BY joined_at ASC",
     )
     .bind(channel_id)
@@ -179,14 +165,23 @@ pub async fn list_members(
     .await
 }
 
+/// Count members of a channel.
+pub async fn count_members(pool: &Db, channel_id: Uuid) -> Result<i64, sqlx::Error> {
+    let row: (i64,) = db::query_as("SELECT COUNT(*) FROM channel_members WHERE channel_id = $1")
+        .bind(channel_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
 /// List members of a channel with user profile info, ordered by role rank.
 pub async fn list_members_with_users(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     limit: i64,
     offset: i64,
 ) -> Result<Vec<ChannelMemberInfo>, sqlx::Error> {
-    sqlx::query_as::<_, ChannelMemberInfo>(
+    db::query_as::<ChannelMemberInfo>(
         r#"
         SELECT cm.channel_id, cm.user_id, cm.role, cm.joined_at,
                u.username, u.display_name, u.avatar_url
@@ -215,13 +210,13 @@ pub async fn list_members_with_users(
 /// who haven't explicitly joined. Channel members keep their stored role; group-only
 /// members appear as "member".
 pub async fn list_discoverable_channel_members(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     group_id: Uuid,
     limit: i64,
     offset: i64,
 ) -> Result<Vec<ChannelMemberInfo>, sqlx::Error> {
-    sqlx::query_as::<_, ChannelMemberInfo>(
+    db::query_as::<ChannelMemberInfo>(
         r#"
         SELECT * FROM (
             -- Explicit channel members (keep their stored role)
@@ -265,12 +260,12 @@ pub async fn list_discoverable_channel_members(
 
 /// Get a member's role in a channel.
 pub async fn get_member_role(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     user_id: Uuid,
 ) -> Result<Option<String>, sqlx::Error> {
     let row: Option<(String,)> =
-        sqlx::query_as("SELECT role FROM channel_members WHERE channel_id = $1 AND user_id = $2")
+        db::query_as("SELECT role FROM channel_members WHERE channel_id = $1 AND user_id = $2")
             .bind(channel_id)
             .bind(user_id)
             .fetch_optional(pool)
@@ -280,13 +275,13 @@ pub async fn get_member_role(
 
 /// Update a member's role. Returns true if the row was updated.
 pub async fn update_member_role(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     user_id: Uuid,
     new_role: &str,
 ) -> Result<bool, sqlx::Error> {
     let result =
-        sqlx::query("UPDATE channel_members SET role = $1 WHERE channel_id = $2 AND user_id = $3")
+        db::query("UPDATE channel_members SET role = $1 WHERE channel_id = $2 AND user_id = $3")
             .bind(new_role)
             .bind(channel_id)
             .bind(user_id)
@@ -297,7 +292,7 @@ pub async fn update_member_role(
 
 /// Ban a user from a channel (removes membership and records the ban).
 pub async fn ban_user(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     user_id: Uuid,
     banned_by: Uuid,
@@ -305,7 +300,7 @@ pub async fn ban_user(
 ) -> Result<(), sqlx::Error> {
     let mut tx = pool.begin().await?;
 
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO channel_bans (channel_id, user_id, banned_by, reason)
         VALUES ($1, $2, $3, $4)
@@ -316,13 +311,13 @@ pub async fn ban_user(
     .bind(user_id)
     .bind(banned_by)
     .bind(reason)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
-    sqlx::query("DELETE FROM channel_members WHERE channel_id = $1 AND user_id = $2")
+    db::query("DELETE FROM channel_members WHERE channel_id = $1 AND user_id = $2")
         .bind(channel_id)
         .bind(user_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     tx.commit().await?;
@@ -330,12 +325,8 @@ pub async fn ban_user(
 }
 
 /// Remove a ban from a user.
-pub async fn unban_user(
-    pool: &PgPool,
-    channel_id: Uuid,
-    user_id: Uuid,
-) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM channel_bans WHERE channel_id = $1 AND user_id = $2")
+pub async fn unban_user(pool: &Db, channel_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM channel_bans WHERE channel_id = $1 AND user_id = $2")
         .bind(channel_id)
         .bind(user_id)
         .execute(pool)
@@ -344,8 +335,9 @@ pub async fn unban_user(
 }
 
 /// Update a channel's name, topic, read_only, slow_mode_seconds, message_ttl_seconds, discoverable, archived, and/or voice_background.
+#[allow(clippy::too_many_arguments)]
 pub async fn update_channel(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     name: Option<&str>,
     topic: Option<&str>,
@@ -356,7 +348,7 @@ pub async fn update_channel(
     archived: Option<bool>,
     voice_background: Option<&str>,
 ) -> Result<Option<Channel>, sqlx::Error> {
-    sqlx::query_as::<_, Channel>(
+    db::query_as::<Channel>(
         r#"
         UPDATE channels
         SET name = COALESCE($2, name),
@@ -386,8 +378,8 @@ pub async fn update_channel(
 }
 
 /// Delete a channel.
-pub async fn delete_channel(pool: &PgPool, channel_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM channels WHERE id = $1")
+pub async fn delete_channel(pool: &Db, channel_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM channels WHERE id = $1")
         .bind(channel_id)
         .execute(pool)
         .await?;
@@ -396,7 +388,7 @@ pub async fn delete_channel(pool: &PgPool, channel_id: Uuid) -> Result<bool, sql
 
 /// Transfer channel ownership: new owner becomes "owner", old owner becomes "admin".
 pub async fn transfer_ownership(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     old_owner_id: Uuid,
     new_owner_id: Uuid,
@@ -404,17 +396,17 @@ pub async fn transfer_ownership(
     let mut tx = pool.begin().await?;
 
     // Demote old owner to admin
-    sqlx::query("UPDATE channel_members SET role = 'admin' WHERE channel_id = $1 AND user_id = $2")
+    db::query("UPDATE channel_members SET role = 'admin' WHERE channel_id = $1 AND user_id = $2")
         .bind(channel_id)
         .bind(old_owner_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     // Promote new owner
-    sqlx::query("UPDATE channel_members SET role = 'owner' WHERE channel_id = $1 AND user_id = $2")
+    db::query("UPDATE channel_members SET role = 'owner' WHERE channel_id = $1 AND user_id = $2")
         .bind(channel_id)
         .bind(new_owner_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     tx.commit().await?;
@@ -422,12 +414,8 @@ pub async fn transfer_ownership(
 }
 
 /// Check if a user is banned from a channel.
-pub async fn is_banned(
-    pool: &PgPool,
-    channel_id: Uuid,
-    user_id: Uuid,
-) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as(
+pub async fn is_banned(pool: &Db, channel_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as(
         "SELECT EXISTS(SELECT 1 FROM channel_bans WHERE channel_id = $1 AND user_id = $2)",
     )
     .bind(channel_id)
@@ -439,11 +427,11 @@ pub async fn is_banned(
 
 /// Get the last time a user sent a message in a channel (for slow mode).
 pub async fn get_slowmode_last_sent(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     user_id: Uuid,
 ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
-    let row: Option<(DateTime<Utc>,)> = sqlx::query_as(
+    let row: Option<(DateTime<Utc>,)> = db::query_as(
         "SELECT last_sent FROM channel_slowmode_tracker WHERE channel_id = $1 AND user_id = $2",
     )
     .bind(channel_id)
@@ -455,11 +443,11 @@ pub async fn get_slowmode_last_sent(
 
 /// Update (upsert) the slow mode tracker for a user in a channel.
 pub async fn update_slowmode_last_sent(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     user_id: Uuid,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO channel_slowmode_tracker (channel_id, user_id, last_sent)
         VALUES ($1, $2, NOW())
@@ -1,5 +1,5 @@
+use crate::db::{self, Db};
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::models::community::{Community, CommunityBanInfo, CommunityInvite, CommunityMemberInfo};
@@ -8,7 +8,7 @@ use crate::models::user::User;
 // ── CRUD ──
 
 pub async fn create_community(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     name: &str,
     description: Option<&str>,
@@ -17,7 +17,7 @@ pub async fn create_community(
 ) -> Result<Community, sqlx::Error> {
     let mut tx = pool.begin().await?;
 
-    let community = sqlx::query_as::<_, Community>(
+    let community = db::query_as::<Community>(
         r#"
         INSERT INTO communities (id, name, description, icon_url, owner_id)
         VALUES ($1, $2, $3, $4, $5)
@@ -29,11 +29,11 @@ pub async fn create_community(
     .bind(description)
     .bind(icon_url)
     .bind(owner_id)
-    .fetch_one(&mut *tx)
+    .fetch_one(&mut tx)
     .await?;
 
     // Add creator as owner member
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO community_members (community_id, user_id, role)
         VALUES ($1, $2, 'owner')
@@ -41,22 +41,23 @@ pub async fn create_community(
     )
     .bind(id)
     .bind(owner_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     tx.commit().await?;
     Ok(community)
 }
 
-pub async fn get_community(pool: &PgPool, id: Uuid) -> Result<Option<Community>, sqlx::Error> {
-    sqlx::query_as::<_, Community>("SELECT * FROM communities WHERE id = $1")
+pub async fn get_community(pool: &Db, id: Uuid) -> Result<Option<Community>, sqlx::Error> {
+    db::query_as::<Community>("SELECT * FROM communities WHERE id = $1")
         .bind(id)
         .fetch_optional(pool)
         .await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn update_community(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     name: Option<&str>,
     description: Option<&str>,
@@ -68,7 +69,7 @@ pub async fn update_community(
     community_theme: Option<&serde_json::Value>,
     welcome_message: Option<&str>,
 ) -> Result<Option<Community>, sqlx::Error> {
-    sqlx::query_as::<_, Community>(
+    db::query_as::<Community>(
         r#"
         UPDATE communities
         SET name = COALESCE($2, name),
@@ -99,8 +100,8 @@ pub async fn update_community(
     .await
 }
 
-pub async fn delete_community(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM communities WHERE id = $1")
+pub async fn delete_community(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM communities WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
@@ -108,12 +109,12 @@ pub async fn delete_community(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Err
 }
 
 pub async fn list_user_communities(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     limit: i64,
     offset: i64,
 ) -> Result<Vec<Community>, sqlx::Error> {
-    sqlx::query_as::<_, Community>(
+    db::query_as::<Community>(
         r#"
         SELECT c.* FROM communities c
         INNER JOIN community_members cm ON c.id = cm.community_id
@@ -132,11 +133,11 @@ pub async fn list_user_communities(
 // ── Membership ──
 
 pub async fn is_community_member(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     user_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as(
+    let row: (bool,) = db::query_as(
         "SELECT EXISTS(SELECT 1 FROM community_members WHERE community_id = $1 AND user_id = $2)",
     )
     .bind(community_id)
@@ -147,26 +148,25 @@ pub async fn is_community_member(
 }
 
 pub async fn get_community_member_role(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     user_id: Uuid,
 ) -> Result<Option<String>, sqlx::Error> {
-    let row: Option<(String,)> = sqlx::query_as(
-        "SELECT role FROM community_members WHERE community_id = $1 AND user_id = $2",
-    )
-    .bind(community_id)
-    .bind(user_id)
-    .fetch_optional(pool)
-    .await?;
+    let row: Option<(String,)> =
+        db::query_as("SELECT role FROM community_members WHERE community_id = $1 AND user_id = $2")
+            .bind(community_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
     Ok(row.map(|r| r.0))
 }
 
 pub async fn join_community(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     user_id: Uuid,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO community_members (community_id, user_id, role)
         VALUES ($1, $2, 'member')
@@ -182,14 +182,14 @@ pub async fn join_community(
 
 /// Leave a community — removes from community and all its groups/channels.
 pub async fn leave_community(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     user_id: Uuid,
 ) -> Result<(), sqlx::Error> {
     let mut tx = pool.begin().await?;
 
     // Remove from all channels in groups belonging to this community
-    sqlx::query(
+    db::query(
         r#"
         DELETE FROM channel_members
         WHERE user_id = $2
@@ -202,11 +202,11 @@ pub async fn leave_community(
     )
     .bind(community_id)
     .bind(user_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     // Remove from all groups in this community
-    sqlx::query(
+    db::query(
         r#"
         DELETE FROM group_members
         WHERE user_id = $2
@@ -215,14 +215,14 @@ pub async fn leave_community(
     )
     .bind(community_id)
     .bind(user_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     // Remove from community
-    sqlx::query("DELETE FROM community_members WHERE community_id = $1 AND user_id = $2")
+    db::query("DELETE FROM community_members WHERE community_id = $1 AND user_id = $2")
         .bind(community_id)
         .bind(user_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     tx.commit().await?;
@@ -230,12 +230,12 @@ pub async fn leave_community(
 }
 
 pub async fn list_community_members(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     limit: i64,
     offset: i64,
 ) -> Result<Vec<CommunityMemberInfo>, sqlx::Error> {
-    sqlx::query_as::<_, CommunityMemberInfo>(
+    db::query_as::<CommunityMemberInfo>(
         r#"
         SELECT cm.community_id, cm.user_id, cm.role, cm.nickname, cm.joined_at,
                u.username, u.display_name, u.avatar_url
@@ -262,23 +262,20 @@ pub async fn list_community_members(
 
 /// List just the user IDs of all members in a community.
 pub async fn list_community_member_user_ids(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
 ) -> Result<Vec<Uuid>, sqlx::Error> {
     let rows: Vec<(Uuid,)> =
-        sqlx::query_as("SELECT user_id FROM community_members WHERE community_id = $1 LIMIT 5000")
+        db::query_as("SELECT user_id FROM community_members WHERE community_id = $1 LIMIT 5000")
             .bind(community_id)
             .fetch_all(pool)
             .await?;
     Ok(rows.into_iter().map(|(id,)| id).collect())
 }
 
-pub async fn get_community_member_count(
-    pool: &PgPool,
-    community_id: Uuid,
-) -> Result<i64, sqlx::Error> {
+pub async fn get_community_member_count(pool: &Db, community_id: Uuid) -> Result<i64, sqlx::Error> {
     let row: (i64,) =
-        sqlx::query_as("SELECT COUNT(*) FROM community_members WHERE community_id = $1")
+        db::query_as("SELECT COUNT(*) FROM community_members WHERE community_id = $1")
             .bind(community_id)
             .fetch_one(pool)
             .await?;
@@ -287,10 +284,10 @@ pub async fn get_community_member_count(
 
 /// Get member counts for multiple communities in a single query.
 pub async fn get_community_member_counts(
-    pool: &PgPool,
+    pool: &Db,
     community_ids: &[Uuid],
 ) -> Result<std::collections::HashMap<Uuid, i64>, sqlx::Error> {
-    let rows: Vec<(Uuid, i64)> = sqlx::query_as(
+    let rows: Vec<(Uuid, i64)> = db::query_as(
         "SELECT community_id, COUNT(*) FROM community_members WHERE community_id = ANY($1) GROUP BY community_id",
     )
     .bind(community_ids)
@@ -300,12 +297,12 @@ pub async fn get_community_member_counts(
 }
 
 pub async fn set_community_member_role(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     user_id: Uuid,
     role: &str,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
+    let result = db::query(
         "UPDATE community_members SET role = $3 WHERE community_id = $1 AND user_id = $2",
     )
     .bind(community_id)
@@ -317,12 +314,12 @@ pub async fn set_community_member_role(
 }
 
 pub async fn set_community_nickname(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     user_id: Uuid,
     nickname: Option<&str>,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
+    let result = db::query(
         "UPDATE community_members SET nickname = $3 WHERE community_id = $1 AND user_id = $2",
     )
     .bind(community_id)
@@ -334,35 +331,35 @@ pub async fn set_community_nickname(
 }
 
 pub async fn transfer_community_ownership(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     old_owner_id: Uuid,
     new_owner_id: Uuid,
 ) -> Result<(), sqlx::Error> {
     let mut tx = pool.begin().await?;
 
-    sqlx::query("UPDATE communities SET owner_id = $1, updated_at = NOW() WHERE id = $2")
+    db::query("UPDATE communities SET owner_id = $1, updated_at = NOW() WHERE id = $2")
         .bind(new_owner_id)
         .bind(community_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     // Demote old owner to admin
-    sqlx::query(
+    db::query(
         "UPDATE community_members SET role = 'admin' WHERE community_id = $1 AND user_id = $2",
     )
     .bind(community_id)
     .bind(old_owner_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     // Promote new owner
-    sqlx::query(
+    db::query(
         "UPDATE community_members SET role = 'owner' WHERE community_id = $1 AND user_id = $2",
     )
     .bind(community_id)
     .bind(new_owner_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     tx.commit().await?;
@@ -372,7 +369,7 @@ pub async fn transfer_community_ownership(
 // ── Bans ──
 
 pub async fn ban_from_community(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     user_id: Uuid,
     banned_by: Uuid,
@@ -381,7 +378,7 @@ pub async fn ban_from_community(
     let mut tx = pool.begin().await?;
 
     // Insert ban record
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO community_bans (community_id, user_id, banned_by, reason)
         VALUES ($1, $2, $3, $4)
@@ -392,11 +389,11 @@ pub async fn ban_from_community(
     .bind(user_id)
     .bind(banned_by)
     .bind(reason)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     // Remove from all channels in groups belonging to this community
-    sqlx::query(
+    db::query(
         r#"
         DELETE FROM channel_members
         WHERE user_id = $2
@@ -409,11 +406,11 @@ pub async fn ban_from_community(
     )
     .bind(community_id)
     .bind(user_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     // Remove from all groups in this community
-    sqlx::query(
+    db::query(
         r#"
         DELETE FROM group_members
         WHERE user_id = $2
@@ -422,14 +419,14 @@ pub async fn ban_from_community(
     )
     .bind(community_id)
     .bind(user_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     // Remove from community
-    sqlx::query("DELETE FROM community_members WHERE community_id = $1 AND user_id = $2")
+    db::query("DELETE FROM community_members WHERE community_id = $1 AND user_id = $2")
         .bind(community_id)
         .bind(user_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     tx.commit().await?;
@@ -437,11 +434,11 @@ pub async fn ban_from_community(
 }
 
 pub async fn unban_from_community(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     user_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM community_bans WHERE community_id = $1 AND user_id = $2")
+    let result = db::query("DELETE FROM community_bans WHERE community_id = $1 AND user_id = $2")
         .bind(community_id)
         .bind(user_id)
         .execute(pool)
@@ -450,11 +447,11 @@ pub async fn unban_from_community(
 }
 
 pub async fn is_banned_from_community(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     user_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as(
+    let row: (bool,) = db::query_as(
         "SELECT EXISTS(SELECT 1 FROM community_bans WHERE community_id = $1 AND user_id = $2)",
     )
     .bind(community_id)
@@ -465,10 +462,10 @@ pub async fn is_banned_from_community(
 }
 
 pub async fn list_community_bans(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
 ) -> Result<Vec<CommunityBanInfo>, sqlx::Error> {
-    sqlx::query_as::<_, CommunityBanInfo>(
+    db::query_as::<CommunityBanInfo>(
         r#"SELECT cb.user_id, u.username, u.display_name, cb.reason, cb.created_at
         FROM community_bans cb
         INNER JOIN users u ON u.id = cb.user_id
@@ -484,7 +481,7 @@ pub async fn list_community_bans(
 // ── Invites ──
 
 pub async fn create_community_invite(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     community_id: Uuid,
     code: &str,
@@ -492,7 +489,7 @@ pub async fn create_community_invite(
     max_uses: Option<i32>,
     expires_at: Option<DateTime<Utc>>,
 ) -> Result<CommunityInvite, sqlx::Error> {
-    sqlx::query_as::<_, CommunityInvite>(
+    db::query_as::<CommunityInvite>(
         r#"
         INSERT INTO community_invites (id, community_id, code, created_by, max_uses, expires_at)
         VALUES ($1, $2, $3, $4, $5, $6)
@@ -510,10 +507,10 @@ pub async fn create_community_invite(
 }
 
 pub async fn list_community_invites(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
 ) -> Result<Vec<CommunityInvite>, sqlx::Error> {
-    sqlx::query_as::<_, CommunityInvite>(
+    db::query_as::<CommunityInvite>(
         "SELECT * FROM community_invites WHERE community_id = $1 ORDER BY created_at DESC LIMIT 200",
     )
     .bind(community_id)
@@ -522,34 +519,33 @@ pub async fn list_community_invites(
 }
 
 pub async fn get_community_invite_by_code(
-    pool: &PgPool,
+    pool: &Db,
     code: &str,
 ) -> Result<Option<CommunityInvite>, sqlx::Error> {
-    sqlx::query_as::<_, CommunityInvite>("SELECT * FROM community_invites WHERE code = $1")
+    db::query_as::<CommunityInvite>("SELECT * FROM community_invites WHERE code = $1")
         .bind(code)
         .fetch_optional(pool)
         .await
 }
 
 pub async fn delete_community_invite(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     community_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let result =
-        sqlx::query("DELETE FROM community_invites WHERE id = $1 AND community_id = $2")
-            .bind(id)
-            .bind(community_id)
-            .execute(pool)
-            .await?;
+    let result = db::query("DELETE FROM community_invites WHERE id = $1 AND community_id = $2")
+        .bind(id)
+        .bind(community_id)
+        .execute(pool)
+        .await?;
     Ok(result.rows_affected() > 0)
 }
 
 pub async fn increment_community_invite_usage(
-    pool: &PgPool,
+    pool: &Db,
     invite_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
+    let result = db::query(
         r#"
         UPDATE community_invites
         SET used_count = used_count + 1
@@ -567,12 +563,8 @@ pub async fn increment_community_invite_usage(
 // ── Shared Community Checks (for DM/user visibility scoping) ──
 
 /// Check if two users share at least one community.
-pub async fn shares_community(
-    pool: &PgPool,
-    user_a: Uuid,
-    user_b: Uuid,
-) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as(
+pub async fn shares_community(pool: &Db, user_a: Uuid, user_b: Uuid) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as(
         r#"SELECT EXISTS(
             SELECT 1 FROM community_members cm1
             INNER JOIN community_members cm2 ON cm1.community_id = cm2.community_id
@@ -587,19 +579,18 @@ pub async fn shares_community(
 }
 
 /// Get all member user IDs for a specific community (lightweight, no JOINs).
-pub async fn get_member_ids(pool: &PgPool, community_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
-    let rows: Vec<(Uuid,)> = sqlx::query_as(
-        "SELECT user_id FROM community_members WHERE community_id = $1",
-    )
-    .bind(community_id)
-    .fetch_all(pool)
-    .await?;
+pub async fn get_member_ids(pool: &Db, community_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows: Vec<(Uuid,)> =
+        db::query_as("SELECT user_id FROM community_members WHERE community_id = $1")
+            .bind(community_id)
+            .fetch_all(pool)
+            .await?;
     Ok(rows.into_iter().map(|(id,)| id).collect())
 }
 
 /// Get all distinct user IDs that share at least one community with the given user.
-pub async fn get_community_mates(pool: &PgPool, user_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
-    let rows: Vec<(Uuid,)> = sqlx::query_as(
+pub async fn get_community_mates(pool: &Db, user_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows: Vec<(Uuid,)> = db::query_as(
         r#"SELECT DISTINCT cm2.user_id
            FROM community_members cm1
            INNER JOIN community_members cm2 ON cm1.community_id = cm2.community_id
@@ -614,12 +605,12 @@ pub async fn get_community_mates(pool: &PgPool, user_id: Uuid) -> Result<Vec<Uui
 
 /// Search users who share at least one community with the given user.
 pub async fn search_visible_users(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     query: &str,
     limit: i64,
 ) -> Result<Vec<User>, sqlx::Error> {
-    sqlx::query_as::<_, User>(
+    db::query_as::<User>(
         r#"SELECT DISTINCT u.* FROM users u
         INNER JOIN community_members cm1 ON cm1.user_id = u.id
         INNER JOIN community_members cm2 ON cm2.community_id = cm1.community_id
@@ -636,11 +627,10 @@ pub async fn search_visible_users(
 }
 
 /// Check if a user owns any communities (blocks account deletion).
-pub async fn user_owns_communities(pool: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
-    let row: (bool,) =
-        sqlx::query_as("SELECT EXISTS(SELECT 1 FROM communities WHERE owner_id = $1)")
-            .bind(user_id)
-            .fetch_one(pool)
-            .await?;
+pub async fn user_owns_communities(pool: &Db, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as("SELECT EXISTS(SELECT 1 FROM communities WHERE owner_id = $1)")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
     Ok(row.0)
 }
@@ -1,10 +1,10 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::custom_emoji::CustomEmoji;
 
 pub async fn create(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     community_id: Uuid,
     shortcode: &str,
@@ -12,7 +12,7 @@ pub async fn create(
     content_type: &str,
     uploaded_by: Uuid,
 ) -> Result<CustomEmoji, sqlx::Error> {
-    sqlx::query_as::<_, CustomEmoji>(
+    db::query_as::<CustomEmoji>(
         r#"
         INSERT INTO custom_emojis (id, community_id, shortcode, file_path, content_type, uploaded_by)
         VALUES ($1, $2, $3, $4, $5, $6)
@@ -30,10 +30,10 @@ pub async fn create(
 }
 
 pub async fn list_for_community(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
 ) -> Result<Vec<CustomEmoji>, sqlx::Error> {
-    sqlx::query_as::<_, CustomEmoji>(
+    db::query_as::<CustomEmoji>(
         "SELECT * FROM custom_emojis WHERE community_id = $1 ORDER BY shortcode ASC LIMIT 500",
     )
     .bind(community_id)
@@ -42,11 +42,11 @@ pub async fn list_for_community(
 }
 
 pub async fn get_by_shortcode(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     shortcode: &str,
 ) -> Result<Option<CustomEmoji>, sqlx::Error> {
-    sqlx::query_as::<_, CustomEmoji>(
+    db::query_as::<CustomEmoji>(
         "SELECT * FROM custom_emojis WHERE community_id = $1 AND shortcode = $2",
     )
     .bind(community_id)
@@ -55,23 +55,35 @@ pub async fn get_by_shortcode(
     .await
 }
 
-pub async fn get_by_id(pool: &PgPool, id: Uuid) -> Result<Option<CustomEmoji>, sqlx::Error> {
-    sqlx::query_as::<_, CustomEmoji>("SELECT * FROM custom_emojis WHERE id = $1")
+pub async fn get_by_id(pool: &Db, id: Uuid) -> Result<Option<CustomEmoji>, sqlx::Error> {
+    db::query_as::<CustomEmoji>("SELECT * FROM custom_emojis WHERE id = $1")
         .bind(id)
         .fetch_optional(pool)
         .await
 }
 
-pub async fn delete(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM custom_emojis WHERE id = $1")
+pub async fn delete(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM custom_emojis WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn count_for_community(pool: &PgPool, community_id: Uuid) -> Result<i64, sqlx::Error> {
-    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM custom_emojis WHERE community_id = $1")
+/// Make stored file paths that begin with `prefix` relative to it.
+pub async fn strip_path_prefix(pool: &Db, prefix: &str) -> Result<u64, sqlx::Error> {
+    let result = db::query(
+        "UPDATE custom_emojis SET file_path = substr(file_path, length($1) + 1) \
+         WHERE substr(file_path, 1, length($1)) = $1",
+    )
+    .bind(prefix)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn count_for_community(pool: &Db, community_id: Uuid) -> Result<i64, sqlx::Error> {
+    let row: (i64,) = db::query_as("SELECT COUNT(*) FROM custom_emojis WHERE community_id = $1")
         .bind(community_id)
         .fetch_one(pool)
         .await?;
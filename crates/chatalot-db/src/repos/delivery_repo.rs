@@ -0,0 +1,68 @@
+use crate::db::{self, Db};
+use uuid::Uuid;
+
+use crate::models::delivery::MessageDelivery;
+
+/// A message that was newly marked as delivered, with its original sender.
+#[derive(Debug, sqlx::FromRow)]
+pub struct NewDelivery {
+    pub message_id: Uuid,
+    pub sender_id: Option<Uuid>,
+}
+
+/// Record that messages in a channel reached a recipient's device.
+/// Ignores the recipient's own messages, deleted messages and messages from
+/// other channels. Returns only the messages that were not already marked.
+pub async fn mark_delivered(
+    pool: &Db,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_ids: &[Uuid],
+) -> Result<Vec<NewDelivery>, sqlx::Error> {
+    if message_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    db::query_as::<NewDelivery>(
+        r#"
+        INSERT INTO message_deliveries (message_id, user_id)
+        SELECT m.id, $1
+        FROM messages m
+        WHERE m.id = ANY($3)
+          AND m.channel_id = $2
+          AND (m.sender_id IS NULL OR m.sender_id <> $1)
+          AND m.deleted_at IS NULL
+        ON CONFLICT (message_id, user_id) DO NOTHING
+        RETURNING message_id,
+            (SELECT m.sender_id FROM messages m WHERE m.id = message_deliveries.message_id) AS sender_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(channel_id)
+    .bind(message_ids)
+    .fetch_all(pool)
+    .await
+}
+
+/// Get the IDs of all users a message has been delivered to.
+pub async fn get_delivered_user_ids(pool: &Db, message_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows: Vec<(Uuid,)> = db::query_as(
+        "SELECT user_id FROM message_deliveries WHERE message_id = $1 ORDER BY delivered_at ASC",
+    )
+    .bind(message_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
+/// Get all delivery receipts for a message.
+pub async fn list_deliveries(
+    pool: &Db,
+    message_id: Uuid,
+) -> Result<Vec<MessageDelivery>, sqlx::Error> {
+    db::query_as::<MessageDelivery>(
+        "SELECT * FROM message_deliveries WHERE message_id = $1 ORDER BY delivered_at ASC",
+    )
+    .bind(message_id)
+    .fetch_all(pool)
+    .await
+}
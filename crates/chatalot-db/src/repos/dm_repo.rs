@@ -1,4 +1,4 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::channel::{Channel, ChannelType};
@@ -7,7 +7,7 @@ use crate::models::user::User;
 
 /// Find an existing DM channel between two users.
 pub async fn find_dm_channel(
-    pool: &PgPool,
+    pool: &Db,
     user_a: Uuid,
     user_b: Uuid,
 ) -> Result<Option<DmPair>, sqlx::Error> {
@@ -17,7 +17,7 @@ pub async fn find_dm_channel(
         (user_b, user_a)
     };
 
-    sqlx::query_as::<_, DmPair>("SELECT * FROM dm_pairs WHERE user_a = $1 AND user_b = $2")
+    db::query_as::<DmPair>("SELECT * FROM dm_pairs WHERE user_a = $1 AND user_b = $2")
         .bind(a)
         .bind(b)
         .fetch_optional(pool)
@@ -26,7 +26,7 @@ pub async fn find_dm_channel(
 
 /// Create a DM channel between two users, or return the existing one.
 pub async fn get_or_create_dm(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     user_a: Uuid,
     user_b: Uuid,
@@ -39,7 +39,7 @@ pub async fn get_or_create_dm(
 
     // Check if DM already exists
     if let Some(existing) = find_dm_channel(pool, a, b).await? {
-        let channel = sqlx::query_as::<_, Channel>("SELECT * FROM channels WHERE id = $1")
+        let channel = db::query_as::<Channel>("SELECT * FROM channels WHERE id = $1")
             .bind(existing.channel_id)
             .fetch_one(pool)
             .await?;
@@ -49,7 +49,7 @@ pub async fn get_or_create_dm(
     // Create new DM channel + memberships + dm_pair in a transaction
     let mut tx = pool.begin().await?;
 
-    let channel = sqlx::query_as::<_, Channel>(
+    let channel = db::query_as::<Channel>(
         r#"
         INSERT INTO channels (id, name, channel_type, created_by)
         VALUES ($1, NULL, $2, $3)
@@ -59,25 +59,25 @@ pub async fn get_or_create_dm(
     .bind(channel_id)
     .bind(ChannelType::Dm)
     .bind(a)
-    .fetch_one(&mut *tx)
+    .fetch_one(&mut tx)
     .await?;
 
     // Add both users as members
-    sqlx::query(
+    db::query(
         "INSERT INTO channel_members (channel_id, user_id, role) VALUES ($1, $2, 'member'), ($1, $3, 'member')",
     )
     .bind(channel_id)
     .bind(a)
     .bind(b)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     // Record the DM pair
-    sqlx::query("INSERT INTO dm_pairs (user_a, user_b, channel_id) VALUES ($1, $2, $3)")
+    db::query("INSERT INTO dm_pairs (user_a, user_b, channel_id) VALUES ($1, $2, $3)")
         .bind(a)
         .bind(b)
         .bind(channel_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     tx.commit().await?;
@@ -86,11 +86,8 @@ pub async fn get_or_create_dm(
 
 /// List all DM channels for a user, with the other user's info.
 /// Uses batch queries instead of per-pair lookups to avoid N+1.
-pub async fn list_user_dms(
-    pool: &PgPool,
-    user_id: Uuid,
-) -> Result<Vec<(Channel, User)>, sqlx::Error> {
-    let pairs = sqlx::query_as::<_, DmPair>(
+pub async fn list_user_dms(pool: &Db, user_id: Uuid) -> Result<Vec<(Channel, User)>, sqlx::Error> {
+    let pairs = db::query_as::<DmPair>(
         "SELECT * FROM dm_pairs WHERE user_a = $1 OR user_b = $1 ORDER BY created_at DESC",
     )
     .bind(user_id)
@@ -109,19 +106,15 @@ pub async fn list_user_dms(
         .collect();
 
     // Batch-fetch channels and users
-    let channels = sqlx::query_as::<_, Channel>(
-        "SELECT * FROM channels WHERE id = ANY($1)",
-    )
-    .bind(&channel_ids)
-    .fetch_all(pool)
-    .await?;
+    let channels = db::query_as::<Channel>("SELECT * FROM channels WHERE id = ANY($1)")
+        .bind(&channel_ids)
+        .fetch_all(pool)
+        .await?;
 
-    let users = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE id = ANY($1)",
-    )
-    .bind(&other_user_ids)
-    .fetch_all(pool)
-    .await?;
+    let users = db::query_as::<User>("SELECT * FROM users WHERE id = ANY($1)")
+        .bind(&other_user_ids)
+        .fetch_all(pool)
+        .await?;
 
     // Index by ID for fast lookup
     let channel_map: std::collections::HashMap<Uuid, Channel> =
@@ -1,11 +1,39 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
-use crate::models::file::FileRecord;
+use crate::models::file::{FileBlob, FileRecord};
+
+/// Previews of a blob, copied to every file that uses it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BlobPreview<'a> {
+    pub thumbnail_path: Option<&'a str>,
+    pub duration_ms: Option<i64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub blurhash: Option<&'a str>,
+    /// Comma-separated widths of the stored WebP variants.
+    pub variants: Option<&'a str>,
+    pub phash: Option<&'a str>,
+}
+
+impl<'a> BlobPreview<'a> {
+    pub fn of(blob: &'a FileBlob) -> Self {
+        Self {
+            thumbnail_path: blob.thumbnail_path.as_deref(),
+            duration_ms: blob.duration_ms,
+            width: blob.width,
+            height: blob.height,
+            blurhash: blob.blurhash.as_deref(),
+            variants: blob.variants.as_deref(),
+            phash: blob.phash.as_deref(),
+        }
+    }
+}
 
 /// Store file metadata after upload.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_file(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     uploader_id: Uuid,
     encrypted_name: &str,
@@ -14,13 +42,14 @@ pub async fn create_file(
     storage_path: &str,
     checksum: &str,
     channel_id: Option<Uuid>,
-    thumbnail_path: Option<&str>,
+    preview: &BlobPreview<'_>,
     exif_stripped: bool,
 ) -> Result<FileRecord, sqlx::Error> {
-    sqlx::query_as::<_, FileRecord>(
+    db::query_as::<FileRecord>(
         r#"
-        INSERT INTO files (id, uploader_id, encrypted_name, size_bytes, content_type, storage_path, checksum, channel_id, thumbnail_path, exif_stripped)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        INSERT INTO files (id, uploader_id, encrypted_name, size_bytes, content_type, storage_path, checksum, channel_id,
+                           thumbnail_path, duration_ms, width, height, blurhash, variants, phash, exif_stripped)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
         RETURNING *
         "#,
     )
@@ -32,15 +61,21 @@ pub async fn create_file(
     .bind(storage_path)
     .bind(checksum)
     .bind(channel_id)
-    .bind(thumbnail_path)
+    .bind(preview.thumbnail_path)
+    .bind(preview.duration_ms)
+    .bind(preview.width)
+    .bind(preview.height)
+    .bind(preview.blurhash)
+    .bind(preview.variants)
+    .bind(preview.phash)
     .bind(exif_stripped)
     .fetch_one(pool)
     .await
 }
 
 /// Fetch file metadata by ID.
-pub async fn get_file(pool: &PgPool, id: Uuid) -> Result<Option<FileRecord>, sqlx::Error> {
-    sqlx::query_as::<_, FileRecord>("SELECT * FROM files WHERE id = $1")
+pub async fn get_file(pool: &Db, id: Uuid) -> Result<Option<FileRecord>, sqlx::Error> {
+    db::query_as::<FileRecord>("SELECT * FROM files WHERE id = $1")
         .bind(id)
         .fetch_optional(pool)
         .await
@@ -48,11 +83,11 @@ pub async fn get_file(pool: &PgPool, id: Uuid) -> Result<Option<FileRecord>, sql
 
 /// List files uploaded by a user.
 pub async fn list_user_files(
-    pool: &PgPool,
+    pool: &Db,
     uploader_id: Uuid,
     limit: i64,
 ) -> Result<Vec<FileRecord>, sqlx::Error> {
-    sqlx::query_as::<_, FileRecord>(
+    db::query_as::<FileRecord>(
         "SELECT * FROM files WHERE uploader_id = $1 ORDER BY created_at DESC LIMIT $2",
     )
     .bind(uploader_id)
@@ -61,19 +96,10 @@ pub async fn list_user_files(
     .await
 }
 
-/// Delete file metadata (the actual file must be removed separately).
-pub async fn delete_file(pool: &PgPool, id: Uuid, uploader_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM files WHERE id = $1 AND uploader_id = $2")
-        .bind(id)
-        .bind(uploader_id)
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected() > 0)
-}
-
-/// Delete file metadata by ID without ownership check (admin).
-pub async fn delete_file_admin(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM files WHERE id = $1")
+/// Delete file metadata by ID. The blob's reference must be released
+/// separately (see `release_blob`).
+pub async fn delete_file(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM files WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
@@ -91,7 +117,7 @@ pub struct FileFilter<'a> {
 
 /// List all files with pagination, sorting, and filters (admin browser).
 pub async fn list_all_files(
-    pool: &PgPool,
+    pool: &Db,
     filter: &FileFilter<'_>,
     sort_by: &str,
     limit: i64,
@@ -133,7 +159,7 @@ pub async fn list_all_files(
     param_idx += 1;
     sql.push_str(&format!(" OFFSET ${param_idx}"));
 
-    let mut q = sqlx::query_as::<_, FileRecord>(&sql);
+    let mut q = db::query_as::<FileRecord>(&sql);
 
     // Bind params in the same order as above
     if let Some(uid) = filter.user_id {
@@ -158,7 +184,7 @@ pub async fn list_all_files(
 }
 
 /// Count files with optional filters.
-pub async fn count_files(pool: &PgPool, filter: &FileFilter<'_>) -> Result<i64, sqlx::Error> {
+pub async fn count_files(pool: &Db, filter: &FileFilter<'_>) -> Result<i64, sqlx::Error> {
     let mut sql = String::from("SELECT COUNT(*) FROM files WHERE TRUE");
     let mut param_idx = 0u32;
 
@@ -183,7 +209,7 @@ pub async fn count_files(pool: &PgPool, filter: &FileFilter<'_>) -> Result<i64,
         sql.push_str(&format!(" AND created_at <= ${param_idx}"));
     }
 
-    let mut q = sqlx::query_as::<_, (i64,)>(&sql);
+    let mut q = db::query_as::<(i64,)>(&sql);
 
     if let Some(uid) = filter.user_id {
         q = q.bind(uid);
@@ -213,26 +239,98 @@ pub struct UserStorageStat {
     pub total_bytes: i64,
 }
 
-/// Get per-user storage breakdown.
-pub async fn storage_stats(pool: &PgPool) -> Result<Vec<UserStorageStat>, sqlx::Error> {
-    sqlx::query_as::<_, UserStorageStat>(
+/// Storage used by the files posted in a community or group, with its quota.
+#[derive(Debug, sqlx::FromRow, serde::Serialize)]
+pub struct ScopeStorageStat {
+    pub scope_id: Uuid,
+    pub name: String,
+    pub file_count: i64,
+    pub total_bytes: i64,
+    pub quota_bytes: Option<i64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct StorageStats {
+    pub per_user: Vec<UserStorageStat>,
+    pub per_community: Vec<ScopeStorageStat>,
+    pub per_group: Vec<ScopeStorageStat>,
+}
+
+/// Get the storage breakdown per user, community and group. Communities and
+/// groups appear if they have files or a quota.
+pub async fn storage_stats(pool: &Db) -> Result<StorageStats, sqlx::Error> {
+    let per_user = db::query_as::<UserStorageStat>(
         r#"
-        SELECT uploader_id, COUNT(*) as file_count, COALESCE(SUM(size_bytes), 0) as total_bytes
+        SELECT uploader_id, COUNT(*) as file_count, COALESCE(SUM(size_bytes), 0)::BIGINT as total_bytes
         FROM files
         GROUP BY uploader_id
         ORDER BY total_bytes DESC
         "#,
     )
     .fetch_all(pool)
-    .await
+    .await?;
+    let per_community = db::query_as::<ScopeStorageStat>(
+        r#"
+        SELECT cm.id AS scope_id, cm.name, COUNT(f.id) AS file_count,
+               COALESCE(SUM(f.size_bytes), 0)::BIGINT AS total_bytes, q.max_bytes AS quota_bytes
+        FROM communities cm
+        LEFT JOIN groups g ON g.community_id = cm.id
+        LEFT JOIN channels c ON c.group_id = g.id
+        LEFT JOIN files f ON f.channel_id = c.id
+        LEFT JOIN storage_quotas q ON q.scope_type = 'community' AND q.scope_id = cm.id AND q.role = ''
+        GROUP BY cm.id, cm.name, q.max_bytes
+        HAVING COUNT(f.id) > 0 OR q.max_bytes IS NOT NULL
+        ORDER BY total_bytes DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    let per_group = db::query_as::<ScopeStorageStat>(
+        r#"
+        SELECT g.id AS scope_id, g.name, COUNT(f.id) AS file_count,
+               COALESCE(SUM(f.size_bytes), 0)::BIGINT AS total_bytes, q.max_bytes AS quota_bytes
+        FROM groups g
+        LEFT JOIN channels c ON c.group_id = g.id
+        LEFT JOIN files f ON f.channel_id = c.id
+        LEFT JOIN storage_quotas q ON q.scope_type = 'group' AND q.scope_id = g.id AND q.role = ''
+        GROUP BY g.id, g.name, q.max_bytes
+        HAVING COUNT(f.id) > 0 OR q.max_bytes IS NOT NULL
+        ORDER BY total_bytes DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(StorageStats {
+        per_user,
+        per_community,
+        per_group,
+    })
+}
+
+/// Bytes taken in a community or group by its files and by unfinished
+/// resumable uploads into its channels (`scope_type` is `community` or `group`).
+pub async fn scope_usage(pool: &Db, scope_type: &str, scope_id: Uuid) -> Result<i64, sqlx::Error> {
+    let channels = match scope_type {
+        "community" => {
+            "SELECT c.id FROM channels c JOIN groups g ON g.id = c.group_id WHERE g.community_id = $1"
+        }
+        _ => "SELECT c.id FROM channels c WHERE c.group_id = $1",
+    };
+    let sql = format!(
+        "SELECT (SELECT COALESCE(SUM(size_bytes), 0) FROM files WHERE channel_id IN ({channels}))::BIGINT \
+         + (SELECT COALESCE(SUM(upload_length), 0) FROM uploads \
+            WHERE channel_id IN ({channels}) AND expires_at > NOW())::BIGINT"
+    );
+    let row: (i64,) = db::query_as(sql).bind(scope_id).fetch_one(pool).await?;
+    Ok(row.0)
 }
 
 /// List all files uploaded by a user (no limit, for purge operations).
 pub async fn list_all_user_files(
-    pool: &PgPool,
+    pool: &Db,
     uploader_id: Uuid,
 ) -> Result<Vec<FileRecord>, sqlx::Error> {
-    sqlx::query_as::<_, FileRecord>("SELECT * FROM files WHERE uploader_id = $1")
+    db::query_as::<FileRecord>("SELECT * FROM files WHERE uploader_id = $1")
         .bind(uploader_id)
         .fetch_all(pool)
         .await
@@ -240,40 +338,23 @@ pub async fn list_all_user_files(
 
 /// List all files in a channel (for purge operations).
 pub async fn list_channel_files(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
 ) -> Result<Vec<FileRecord>, sqlx::Error> {
-    sqlx::query_as::<_, FileRecord>("SELECT * FROM files WHERE channel_id = $1")
+    db::query_as::<FileRecord>("SELECT * FROM files WHERE channel_id = $1")
         .bind(channel_id)
         .fetch_all(pool)
         .await
 }
 
-/// Hard-delete all file records for a user.
-pub async fn delete_all_user_files(pool: &PgPool, uploader_id: Uuid) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM files WHERE uploader_id = $1")
-        .bind(uploader_id)
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected())
-}
-
-/// Hard-delete all file records for a channel.
-pub async fn delete_channel_files(pool: &PgPool, channel_id: Uuid) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM files WHERE channel_id = $1")
-        .bind(channel_id)
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected())
-}
-
-/// Quarantine a file (admin).
+/// Quarantine a file. `quarantined_by` is `None` when the malware scanner
+/// quarantined it.
 pub async fn quarantine_file(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
-    quarantined_by: Uuid,
+    quarantined_by: Option<Uuid>,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
+    let result = db::query(
         "UPDATE files SET quarantined_at = NOW(), quarantined_by = $2 WHERE id = $1 AND quarantined_at IS NULL",
     )
     .bind(id)
@@ -283,9 +364,43 @@ pub async fn quarantine_file(
     Ok(result.rows_affected() > 0)
 }
 
+/// Quarantine every file that shares a blob (malware found on a rescan).
+/// Returns the files that were not quarantined yet.
+pub async fn quarantine_blob_files(
+    pool: &Db,
+    storage_path: &str,
+) -> Result<Vec<FileRecord>, sqlx::Error> {
+    db::query_as::<FileRecord>(
+        "UPDATE files SET quarantined_at = NOW(), quarantined_by = NULL \
+         WHERE storage_path = $1 AND quarantined_at IS NULL \
+         RETURNING *",
+    )
+    .bind(storage_path)
+    .fetch_all(pool)
+    .await
+}
+
+/// Blobs of files uploaded since `since` that are not quarantined, oldest
+/// first (for malware rescans).
+pub async fn list_recent_blob_paths(
+    pool: &Db,
+    since: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<String>, sqlx::Error> {
+    db::query_as::<(String,)>(
+        "SELECT storage_path FROM files \
+         WHERE created_at >= $1 AND quarantined_at IS NULL \
+         GROUP BY storage_path \
+         ORDER BY MIN(created_at)",
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await
+    .map(|rows| rows.into_iter().map(|(path,)| path).collect())
+}
+
 /// Unquarantine a file (admin).
-pub async fn unquarantine_file(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
+pub async fn unquarantine_file(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query(
         "UPDATE files SET quarantined_at = NULL, quarantined_by = NULL WHERE id = $1 AND quarantined_at IS NOT NULL",
     )
     .bind(id)
@@ -294,9 +409,265 @@ pub async fn unquarantine_file(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Er
     Ok(result.rows_affected() > 0)
 }
 
-/// List all file IDs and storage paths (for orphan cleanup background task).
-pub async fn list_all_file_paths(pool: &PgPool) -> Result<Vec<(Uuid, String)>, sqlx::Error> {
-    sqlx::query_as::<_, (Uuid, String)>("SELECT id, storage_path FROM files")
+/// List all file IDs, storage paths and thumbnail paths (for orphan checks).
+pub async fn list_all_file_paths(
+    pool: &Db,
+) -> Result<Vec<(Uuid, String, Option<String>)>, sqlx::Error> {
+    db::query_as::<(Uuid, String, Option<String>)>(
+        "SELECT id, storage_path, thumbnail_path FROM files",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// List the stored paths and thumbnails of all blobs (for orphan checks).
+pub async fn list_blobs(pool: &Db) -> Result<Vec<FileBlob>, sqlx::Error> {
+    db::query_as::<FileBlob>("SELECT * FROM file_blobs")
         .fetch_all(pool)
         .await
 }
+
+/// Make stored blob and thumbnail paths that begin with `prefix` relative
+/// to it. Returns the number of files changed.
+pub async fn strip_path_prefix(pool: &Db, prefix: &str) -> Result<u64, sqlx::Error> {
+    let mut changed = 0;
+    for table in ["files", "file_blobs"] {
+        let result = db::query(format!(
+            "UPDATE {table} SET \
+               storage_path = CASE WHEN substr(storage_path, 1, length($1)) = $1 \
+                 THEN substr(storage_path, length($1) + 1) ELSE storage_path END, \
+               thumbnail_path = CASE WHEN substr(thumbnail_path, 1, length($1)) = $1 \
+                 THEN substr(thumbnail_path, length($1) + 1) ELSE thumbnail_path END \
+             WHERE substr(storage_path, 1, length($1)) = $1 \
+                OR substr(thumbnail_path, 1, length($1)) = $1"
+        ))
+        .bind(prefix)
+        .execute(pool)
+        .await?;
+        if table == "files" {
+            changed = result.rows_affected();
+        }
+    }
+    Ok(changed)
+}
+
+/// Replace the previews of a blob and every file that shares it.
+pub async fn set_preview(
+    pool: &Db,
+    storage_path: &str,
+    preview: &BlobPreview<'_>,
+) -> Result<(), sqlx::Error> {
+    for table in ["files", "file_blobs"] {
+        db::query(format!(
+            "UPDATE {table} SET thumbnail_path = $2, duration_ms = $3, width = $4, height = $5, \
+               blurhash = $6, variants = $7, phash = $8 \
+             WHERE storage_path = $1"
+        ))
+        .bind(storage_path)
+        .bind(preview.thumbnail_path)
+        .bind(preview.duration_ms)
+        .bind(preview.width)
+        .bind(preview.height)
+        .bind(preview.blurhash)
+        .bind(preview.variants)
+        .bind(preview.phash)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+/// List files that can have a preview (images, video, audio and PDFs),
+/// oldest first (for thumbnail regeneration).
+pub async fn list_previewable_files(pool: &Db) -> Result<Vec<FileRecord>, sqlx::Error> {
+    db::query_as::<FileRecord>(
+        "SELECT * FROM files \
+         WHERE content_type LIKE 'image/%' OR content_type LIKE 'video/%' \
+            OR content_type LIKE 'audio/%' OR content_type = 'application/pdf' \
+         ORDER BY created_at",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// ── Blobs ──
+
+/// Take a reference to an existing blob with this checksum, if there is one.
+pub async fn acquire_blob(pool: &Db, checksum: &str) -> Result<Option<FileBlob>, sqlx::Error> {
+    // A blob whose count has dropped to zero is being deleted; never revive
+    // it. One that is not stored yet may still fail.
+    db::query_as::<FileBlob>(
+        r#"
+        UPDATE file_blobs SET ref_count = ref_count + 1
+        WHERE storage_path = (
+            SELECT storage_path FROM file_blobs
+            WHERE checksum = $1 AND ref_count > 0 AND stored
+            ORDER BY created_at
+            LIMIT 1
+        ) AND ref_count > 0 AND stored
+        RETURNING *
+        "#,
+    )
+    .bind(checksum)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Claim `storage_path` for new content before storing it: inserts a record
+/// with one reference that is not yet `stored`. If a stored blob with this
+/// path exists already (a concurrent upload of the same content finished
+/// first), takes a reference to it instead. Returns `None` while the path
+/// is taken by an unfinished upload or a blob whose bytes are being deleted.
+pub async fn claim_blob(
+    pool: &Db,
+    storage_path: &str,
+    checksum: &str,
+    size_bytes: i64,
+) -> Result<Option<FileBlob>, sqlx::Error> {
+    db::query_as::<FileBlob>(
+        r#"
+        INSERT INTO file_blobs (storage_path, checksum, size_bytes, stored)
+        VALUES ($1, $2, $3, FALSE)
+        ON CONFLICT (storage_path) DO UPDATE SET ref_count = file_blobs.ref_count + 1
+        WHERE file_blobs.ref_count > 0 AND file_blobs.stored
+        RETURNING *
+        "#,
+    )
+    .bind(storage_path)
+    .bind(checksum)
+    .bind(size_bytes)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Mark a claimed blob as stored, with its final size and previews.
+pub async fn finish_blob(
+    pool: &Db,
+    storage_path: &str,
+    size_bytes: i64,
+    preview: &BlobPreview<'_>,
+) -> Result<FileBlob, sqlx::Error> {
+    db::query_as::<FileBlob>(
+        r#"
+        UPDATE file_blobs SET stored = TRUE, size_bytes = $2, thumbnail_path = $3, duration_ms = $4,
+                              width = $5, height = $6, blurhash = $7, variants = $8, phash = $9
+        WHERE storage_path = $1
+        RETURNING *
+        "#,
+    )
+    .bind(storage_path)
+    .bind(size_bytes)
+    .bind(preview.thumbnail_path)
+    .bind(preview.duration_ms)
+    .bind(preview.width)
+    .bind(preview.height)
+    .bind(preview.blurhash)
+    .bind(preview.variants)
+    .bind(preview.phash)
+    .fetch_one(pool)
+    .await
+}
+
+/// Drop a reference to a blob. Returns the blob if that was the last one:
+/// its record stays behind as a tombstone, so nothing can store the same key
+/// until the caller has deleted the stored bytes and then the record (see
+/// [`delete_blob_record`]).
+pub async fn release_blob(pool: &Db, storage_path: &str) -> Result<Option<FileBlob>, sqlx::Error> {
+    let blob = db::query_as::<FileBlob>(
+        "UPDATE file_blobs SET ref_count = ref_count - 1 \
+         WHERE storage_path = $1 AND ref_count > 0 RETURNING *",
+    )
+    .bind(storage_path)
+    .fetch_optional(pool)
+    .await?;
+    Ok(blob.filter(|blob| blob.ref_count == 0))
+}
+
+/// Delete a tombstoned blob record once its stored bytes are gone.
+pub async fn delete_blob_record(pool: &Db, storage_path: &str) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM file_blobs WHERE storage_path = $1 AND ref_count = 0")
+        .bind(storage_path)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Whether the user has a file other than `except` stored in this blob.
+/// Quota is charged once per user and blob.
+pub async fn user_has_blob(
+    pool: &Db,
+    uploader_id: Uuid,
+    storage_path: &str,
+    except: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as(
+        "SELECT EXISTS(SELECT 1 FROM files WHERE uploader_id = $1 AND storage_path = $2 AND id <> $3)",
+    )
+    .bind(uploader_id)
+    .bind(storage_path)
+    .bind(except)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+/// Blobs created before `before` that no file refers to, whatever their
+/// count says (for orphan cleanup).
+pub async fn list_unreferenced_blobs(
+    pool: &Db,
+    before: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<FileBlob>, sqlx::Error> {
+    db::query_as::<FileBlob>(
+        "SELECT * FROM file_blobs b WHERE created_at < $1 \
+         AND NOT EXISTS (SELECT 1 FROM files f WHERE f.storage_path = b.storage_path)",
+    )
+    .bind(before)
+    .fetch_all(pool)
+    .await
+}
+
+/// Tombstone a blob record if still no file refers to it and its count is
+/// still the `ref_count` seen when it was found unreferenced, as
+/// [`release_blob`] does for the last reference. A changed count means an
+/// upload took a reference and has not recorded its file yet.
+pub async fn tombstone_unreferenced_blob(
+    pool: &Db,
+    storage_path: &str,
+    ref_count: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = db::query(
+        "UPDATE file_blobs SET ref_count = 0 WHERE storage_path = $1 AND ref_count = $2 \
+         AND NOT EXISTS (SELECT 1 FROM files WHERE storage_path = $1)",
+    )
+    .bind(storage_path)
+    .bind(ref_count)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Raise counts that are lower than the number of files referring to the
+/// blob, and add records for files whose blob has none, so no blob is
+/// deleted while a file still uses it. Returns the number of blobs fixed.
+pub async fn repair_blob_refs(pool: &Db) -> Result<u64, sqlx::Error> {
+    let raised = db::query(
+        "UPDATE file_blobs SET ref_count = \
+           (SELECT COUNT(*) FROM files f WHERE f.storage_path = file_blobs.storage_path) \
+         WHERE ref_count < \
+           (SELECT COUNT(*) FROM files f WHERE f.storage_path = file_blobs.storage_path)",
+    )
+    .execute(pool)
+    .await?;
+    let added = db::query(
+        "INSERT INTO file_blobs (storage_path, checksum, size_bytes, thumbnail_path, duration_ms, \
+                                 width, height, blurhash, variants, phash, ref_count, created_at) \
+         SELECT storage_path, MIN(checksum), MAX(size_bytes), MAX(thumbnail_path), MAX(duration_ms), \
+                MAX(width), MAX(height), MAX(blurhash), MAX(variants), MAX(phash), COUNT(*), MIN(created_at) \
+         FROM files f \
+         WHERE NOT EXISTS (SELECT 1 FROM file_blobs b WHERE b.storage_path = f.storage_path) \
+         GROUP BY storage_path",
+    )
+    .execute(pool)
+    .await?;
+    Ok(raised.rows_affected() + added.rows_affected())
+}
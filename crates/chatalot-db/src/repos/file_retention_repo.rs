@@ -0,0 +1,152 @@
+use crate::db::{self, Db};
+use uuid::Uuid;
+
+use crate::models::file::FileRecord;
+use crate::models::file_retention_policy::FileRetentionPolicy;
+
+/// Condition selecting the files `f` of a scope, with its ID as `$1`.
+/// Community files are those posted in channels of its groups.
+fn scope_filter(scope_type: &str) -> &'static str {
+    match scope_type {
+        "community" => {
+            "f.channel_id IN (SELECT c.id FROM channels c JOIN groups g ON g.id = c.group_id \
+             WHERE g.community_id = $1)"
+        }
+        _ => "f.channel_id = $1",
+    }
+}
+
+/// The retention policy of a channel or community, if it has one.
+pub async fn get_policy(
+    pool: &Db,
+    scope_type: &str,
+    scope_id: Uuid,
+) -> Result<Option<FileRetentionPolicy>, sqlx::Error> {
+    db::query_as::<FileRetentionPolicy>(
+        "SELECT * FROM file_retention_policies WHERE scope_type = $1 AND scope_id = $2",
+    )
+    .bind(scope_type)
+    .bind(scope_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Create or replace the retention policy of a channel or community.
+pub async fn upsert_policy(
+    pool: &Db,
+    scope_type: &str,
+    scope_id: Uuid,
+    max_age_days: Option<i32>,
+    max_total_bytes: Option<i64>,
+    updated_by: Uuid,
+) -> Result<FileRetentionPolicy, sqlx::Error> {
+    db::query_as::<FileRetentionPolicy>(
+        r#"
+        INSERT INTO file_retention_policies (scope_type, scope_id, max_age_days, max_total_bytes, updated_by, updated_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        ON CONFLICT (scope_type, scope_id) DO UPDATE
+        SET max_age_days = $3, max_total_bytes = $4, updated_by = $5, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(scope_type)
+    .bind(scope_id)
+    .bind(max_age_days)
+    .bind(max_total_bytes)
+    .bind(updated_by)
+    .fetch_one(pool)
+    .await
+}
+
+/// Remove the retention policy of a channel or community.
+pub async fn delete_policy(
+    pool: &Db,
+    scope_type: &str,
+    scope_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result =
+        db::query("DELETE FROM file_retention_policies WHERE scope_type = $1 AND scope_id = $2")
+            .bind(scope_type)
+            .bind(scope_id)
+            .execute(pool)
+            .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Every retention policy.
+pub async fn list_policies(pool: &Db) -> Result<Vec<FileRetentionPolicy>, sqlx::Error> {
+    db::query_as::<FileRetentionPolicy>(
+        "SELECT * FROM file_retention_policies ORDER BY scope_type, scope_id",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Up to `limit` files of a scope older than `max_age_days`, oldest first.
+pub async fn list_files_older_than(
+    pool: &Db,
+    scope_type: &str,
+    scope_id: Uuid,
+    max_age_days: i32,
+    limit: i64,
+) -> Result<Vec<FileRecord>, sqlx::Error> {
+    let sql = format!(
+        "SELECT f.* FROM files f WHERE {} AND f.created_at < {} \
+         ORDER BY f.created_at LIMIT $3",
+        scope_filter(scope_type),
+        pool.backend().now_plus_secs("-$2 * 86400"),
+    );
+    db::query_as::<FileRecord>(sql)
+        .bind(scope_id)
+        .bind(max_age_days)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+/// Up to `limit` of the oldest files of a scope that keep its total size
+/// over `max_total_bytes`: the newest files that fit are kept.
+pub async fn list_files_over_size(
+    pool: &Db,
+    scope_type: &str,
+    scope_id: Uuid,
+    max_total_bytes: i64,
+    limit: i64,
+) -> Result<Vec<FileRecord>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        SELECT * FROM (
+            SELECT f.*, SUM(f.size_bytes) OVER (ORDER BY f.created_at DESC, f.id DESC) AS newer_bytes
+            FROM files f WHERE {}
+        ) ranked
+        WHERE newer_bytes > $2
+        ORDER BY created_at
+        LIMIT $3
+        "#,
+        scope_filter(scope_type),
+    );
+    db::query_as::<FileRecord>(sql)
+        .bind(scope_id)
+        .bind(max_total_bytes)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+/// Up to `limit` files posted in disappearing channels that are older than
+/// the channel's message TTL, so the messages that shared them are gone.
+pub async fn list_files_past_message_ttl(
+    pool: &Db,
+    limit: i64,
+) -> Result<Vec<FileRecord>, sqlx::Error> {
+    let sql = format!(
+        "SELECT f.* FROM files f JOIN channels c ON c.id = f.channel_id \
+         WHERE c.message_ttl_seconds IS NOT NULL AND f.created_at < {} \
+         ORDER BY f.created_at LIMIT $1",
+        pool.backend().now_plus_secs("-c.message_ttl_seconds"),
+    );
+    db::query_as::<FileRecord>(sql)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
@@ -1,4 +1,4 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::channel::Channel;
@@ -7,8 +7,9 @@ use crate::models::group::{Group, GroupMemberInfo};
 /// Create a new group and add the creator as owner.
 /// If `assigned_member_id` is provided, the assigned member becomes the group owner
 /// in group_members instead of the creator (personal group).
+#[allow(clippy::too_many_arguments)]
 pub async fn create_group(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     name: &str,
     description: Option<&str>,
@@ -19,7 +20,7 @@ pub async fn create_group(
 ) -> Result<Group, sqlx::Error> {
     let mut tx = pool.begin().await?;
 
-    let group = sqlx::query_as::<_, Group>(
+    let group = db::query_as::<Group>(
         r#"
         INSERT INTO groups (id, name, description, owner_id, community_id, visibility, assigned_member_id)
         VALUES ($1, $2, $3, $4, $5, $6, $7)
@@ -33,13 +34,13 @@ pub async fn create_group(
     .bind(community_id)
     .bind(visibility)
     .bind(assigned_member_id)
-    .fetch_one(&mut *tx)
+    .fetch_one(&mut tx)
     .await?;
 
     // For personal groups, the assigned member is the group owner;
     // for regular groups, the creator is the owner.
     let member_owner_id = assigned_member_id.unwrap_or(owner_id);
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO group_members (group_id, user_id, role)
         VALUES ($1, $2, 'owner')
@@ -47,7 +48,7 @@ pub async fn create_group(
     )
     .bind(id)
     .bind(member_owner_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     tx.commit().await?;
@@ -55,8 +56,8 @@ pub async fn create_group(
 }
 
 /// List all groups a user is a member of.
-pub async fn list_user_groups(pool: &PgPool, user_id: Uuid) -> Result<Vec<Group>, sqlx::Error> {
-    sqlx::query_as::<_, Group>(
+pub async fn list_user_groups(pool: &Db, user_id: Uuid) -> Result<Vec<Group>, sqlx::Error> {
+    db::query_as::<Group>(
         r#"
         SELECT g.* FROM groups g
         INNER JOIN group_members gm ON g.id = gm.group_id
@@ -70,16 +71,17 @@ pub async fn list_user_groups(pool: &PgPool, user_id: Uuid) -> Result<Vec<Group>
 }
 
 /// Get a group by ID.
-pub async fn get_group(pool: &PgPool, id: Uuid) -> Result<Option<Group>, sqlx::Error> {
-    sqlx::query_as::<_, Group>("SELECT * FROM groups WHERE id = $1")
+pub async fn get_group(pool: &Db, id: Uuid) -> Result<Option<Group>, sqlx::Error> {
+    db::query_as::<Group>("SELECT * FROM groups WHERE id = $1")
         .bind(id)
         .fetch_optional(pool)
         .await
 }
 
 /// Update a group's settings.
+#[allow(clippy::too_many_arguments)]
 pub async fn update_group(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     name: Option<&str>,
     description: Option<&str>,
@@ -90,7 +92,7 @@ pub async fn update_group(
     banner_url: Option<&str>,
     accent_color: Option<&str>,
 ) -> Result<Option<Group>, sqlx::Error> {
-    sqlx::query_as::<_, Group>(
+    db::query_as::<Group>(
         r#"
         UPDATE groups
         SET name = COALESCE($2, name),
@@ -120,8 +122,8 @@ pub async fn update_group(
 }
 
 /// Delete a group (cascades to channels and members).
-pub async fn delete_group(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM groups WHERE id = $1")
+pub async fn delete_group(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM groups WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
@@ -129,10 +131,10 @@ pub async fn delete_group(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error>
 }
 
 /// Join a group — adds user as member and to all group channels.
-pub async fn join_group(pool: &PgPool, group_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
+pub async fn join_group(pool: &Db, group_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
     let mut tx = pool.begin().await?;
 
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO group_members (group_id, user_id, role)
         VALUES ($1, $2, 'member')
@@ -141,11 +143,11 @@ pub async fn join_group(pool: &PgPool, group_id: Uuid, user_id: Uuid) -> Result<
     )
     .bind(group_id)
     .bind(user_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     // Add user to all discoverable channels in this group
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO channel_members (channel_id, user_id, role)
         SELECT c.id, $2, 'member'
@@ -156,7 +158,7 @@ pub async fn join_group(pool: &PgPool, group_id: Uuid, user_id: Uuid) -> Result<
     )
     .bind(group_id)
     .bind(user_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     tx.commit().await?;
@@ -165,7 +167,7 @@ pub async fn join_group(pool: &PgPool, group_id: Uuid, user_id: Uuid) -> Result<
 
 /// Add multiple users to a group and all its discoverable channels at once.
 pub async fn join_group_batch(
-    pool: &PgPool,
+    pool: &Db,
     group_id: Uuid,
     user_ids: &[Uuid],
 ) -> Result<u64, sqlx::Error> {
@@ -175,33 +177,33 @@ pub async fn join_group_batch(
     let mut tx = pool.begin().await?;
 
     // Batch insert into group_members
-    let result = sqlx::query(
+    let result = db::query(
         r#"
         INSERT INTO group_members (group_id, user_id, role)
-        SELECT $1, unnest($2::uuid[]), 'member'
+        SELECT $1, u.id, 'member' FROM users u WHERE u.id = ANY($2)
         ON CONFLICT (group_id, user_id) DO NOTHING
         "#,
     )
     .bind(group_id)
     .bind(user_ids)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
     let added = result.rows_affected();
 
     // Batch insert into all discoverable channels for these users
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO channel_members (channel_id, user_id, role)
-        SELECT c.id, u.uid, 'member'
+        SELECT c.id, u.id, 'member'
         FROM channels c
-        CROSS JOIN unnest($2::uuid[]) AS u(uid)
-        WHERE c.group_id = $1 AND c.discoverable = TRUE
+        CROSS JOIN users u
+        WHERE c.group_id = $1 AND c.discoverable = TRUE AND u.id = ANY($2)
         ON CONFLICT (channel_id, user_id) DO NOTHING
         "#,
     )
     .bind(group_id)
     .bind(user_ids)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     tx.commit().await?;
@@ -209,17 +211,17 @@ pub async fn join_group_batch(
 }
 
 /// Leave a group — removes from group and all its channels.
-pub async fn leave_group(pool: &PgPool, group_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
+pub async fn leave_group(pool: &Db, group_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
     let mut tx = pool.begin().await?;
 
-    sqlx::query("DELETE FROM group_members WHERE group_id = $1 AND user_id = $2")
+    db::query("DELETE FROM group_members WHERE group_id = $1 AND user_id = $2")
         .bind(group_id)
         .bind(user_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     // Remove from all channels in this group
-    sqlx::query(
+    db::query(
         r#"
         DELETE FROM channel_members
         WHERE user_id = $2
@@ -228,7 +230,7 @@ pub async fn leave_group(pool: &PgPool, group_id: Uuid, user_id: Uuid) -> Result
     )
     .bind(group_id)
     .bind(user_id)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     tx.commit().await?;
@@ -237,12 +239,12 @@ pub async fn leave_group(pool: &PgPool, group_id: Uuid, user_id: Uuid) -> Result
 
 /// List members of a group with user profile info.
 pub async fn list_group_members(
-    pool: &PgPool,
+    pool: &Db,
     group_id: Uuid,
     limit: i64,
     offset: i64,
 ) -> Result<Vec<GroupMemberInfo>, sqlx::Error> {
-    sqlx::query_as::<_, GroupMemberInfo>(
+    db::query_as::<GroupMemberInfo>(
         r#"
         SELECT gm.group_id, gm.user_id, gm.role, gm.joined_at,
                u.username, u.display_name, u.avatar_url
@@ -267,8 +269,8 @@ pub async fn list_group_members(
 }
 
 /// Check if a user owns any groups (blocks account deletion).
-pub async fn user_owns_groups(pool: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM groups WHERE owner_id = $1)")
+pub async fn user_owns_groups(pool: &Db, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as("SELECT EXISTS(SELECT 1 FROM groups WHERE owner_id = $1)")
         .bind(user_id)
         .fetch_one(pool)
         .await?;
@@ -277,7 +279,7 @@ pub async fn user_owns_groups(pool: &PgPool, user_id: Uuid) -> Result<bool, sqlx
 
 /// Transfer group ownership: update owner_id, new owner gets "owner" role, old owner becomes "admin".
 pub async fn transfer_ownership(
-    pool: &PgPool,
+    pool: &Db,
     group_id: Uuid,
     old_owner_id: Uuid,
     new_owner_id: Uuid,
@@ -285,24 +287,24 @@ pub async fn transfer_ownership(
     let mut tx = pool.begin().await?;
 
     // Update group owner_id
-    sqlx::query("UPDATE groups SET owner_id = $1, updated_at = NOW() WHERE id = $2")
+    db::query("UPDATE groups SET owner_id = $1, updated_at = NOW() WHERE id = $2")
         .bind(new_owner_id)
         .bind(group_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     // Demote old owner to admin
-    sqlx::query("UPDATE group_members SET role = 'admin' WHERE group_id = $1 AND user_id = $2")
+    db::query("UPDATE group_members SET role = 'admin' WHERE group_id = $1 AND user_id = $2")
         .bind(group_id)
         .bind(old_owner_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     // Promote new owner
-    sqlx::query("UPDATE group_members SET role = 'owner' WHERE group_id = $1 AND user_id = $2")
+    db::query("UPDATE group_members SET role = 'owner' WHERE group_id = $1 AND user_id = $2")
         .bind(group_id)
         .bind(new_owner_id)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
 
     tx.commit().await?;
@@ -311,12 +313,12 @@ pub async fn transfer_ownership(
 
 /// Get a member's role in a group.
 pub async fn get_member_role(
-    pool: &PgPool,
+    pool: &Db,
     group_id: Uuid,
     user_id: Uuid,
 ) -> Result<Option<String>, sqlx::Error> {
     let row: Option<(String,)> =
-        sqlx::query_as("SELECT role FROM group_members WHERE group_id = $1 AND user_id = $2")
+        db::query_as("SELECT role FROM group_members WHERE group_id = $1 AND user_id = $2")
             .bind(group_id)
             .bind(user_id)
             .fetch_optional(pool)
@@ -325,8 +327,8 @@ pub async fn get_member_role(
 }
 
 /// Check if a user is a member of a group.
-pub async fn is_member(pool: &PgPool, group_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as(
+pub async fn is_member(pool: &Db, group_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as(
         "SELECT EXISTS(SELECT 1 FROM group_members WHERE group_id = $1 AND user_id = $2)",
     )
     .bind(group_id)
@@ -337,25 +339,20 @@ pub async fn is_member(pool: &PgPool, group_id: Uuid, user_id: Uuid) -> Result<b
 }
 
 /// List all channels in a group.
-pub async fn list_group_channels(
-    pool: &PgPool,
-    group_id: Uuid,
-) -> Result<Vec<Channel>, sqlx::Error> {
-    sqlx::query_as::<_, Channel>(
-        "SELECT * FROM channels WHERE group_id = $1 ORDER BY created_at ASC",
-    )
-    .bind(group_id)
-    .fetch_all(pool)
-    .await
+pub async fn list_group_channels(pool: &Db, group_id: Uuid) -> Result<Vec<Channel>, sqlx::Error> {
+    db::query_as::<Channel>("SELECT * FROM channels WHERE group_id = $1 ORDER BY created_at ASC")
+        .bind(group_id)
+        .fetch_all(pool)
+        .await
 }
 
 /// List channels visible to a user: discoverable channels + channels they're a member of.
 pub async fn list_visible_group_channels(
-    pool: &PgPool,
+    pool: &Db,
     group_id: Uuid,
     user_id: Uuid,
 ) -> Result<Vec<Channel>, sqlx::Error> {
-    sqlx::query_as::<_, Channel>(
+    db::query_as::<Channel>(
         r#"
         SELECT c.* FROM channels c
         WHERE c.group_id = $1
@@ -372,8 +369,8 @@ pub async fn list_visible_group_channels(
 }
 
 /// Get the member count for a group.
-pub async fn get_member_count(pool: &PgPool, group_id: Uuid) -> Result<i64, sqlx::Error> {
-    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM group_members WHERE group_id = $1")
+pub async fn get_member_count(pool: &Db, group_id: Uuid) -> Result<i64, sqlx::Error> {
+    let row: (i64,) = db::query_as("SELECT COUNT(*) FROM group_members WHERE group_id = $1")
         .bind(group_id)
         .fetch_one(pool)
         .await?;
@@ -382,10 +379,10 @@ pub async fn get_member_count(pool: &PgPool, group_id: Uuid) -> Result<i64, sqlx
 
 /// Get member counts for multiple groups in a single query.
 pub async fn get_member_counts(
-    pool: &PgPool,
+    pool: &Db,
     group_ids: &[Uuid],
 ) -> Result<std::collections::HashMap<Uuid, i64>, sqlx::Error> {
-    let rows: Vec<(Uuid, i64)> = sqlx::query_as(
+    let rows: Vec<(Uuid, i64)> = db::query_as(
         "SELECT group_id, COUNT(*) FROM group_members WHERE group_id = ANY($1) GROUP BY group_id",
     )
     .bind(group_ids)
@@ -395,19 +392,16 @@ pub async fn get_member_counts(
 }
 
 /// List all groups (for discovery/browsing).
-pub async fn list_all_groups(pool: &PgPool) -> Result<Vec<Group>, sqlx::Error> {
-    sqlx::query_as::<_, Group>("SELECT * FROM groups ORDER BY name ASC LIMIT 200")
+pub async fn list_all_groups(pool: &Db) -> Result<Vec<Group>, sqlx::Error> {
+    db::query_as::<Group>("SELECT * FROM groups ORDER BY name ASC LIMIT 200")
         .fetch_all(pool)
         .await
 }
 
 /// List groups in communities the user belongs to (for scoped discovery).
 /// Only shows discoverable public groups + groups the user is already a member of.
-pub async fn list_discoverable_groups(
-    pool: &PgPool,
-    user_id: Uuid,
-) -> Result<Vec<Group>, sqlx::Error> {
-    sqlx::query_as::<_, Group>(
+pub async fn list_discoverable_groups(pool: &Db, user_id: Uuid) -> Result<Vec<Group>, sqlx::Error> {
+    db::query_as::<Group>(
         r#"SELECT g.* FROM groups g
          JOIN community_members cm ON cm.community_id = g.community_id
          WHERE cm.user_id = $1
@@ -423,10 +417,10 @@ pub async fn list_discoverable_groups(
 
 /// List IDs of public groups in a community (for auto-joining new members).
 pub async fn list_public_group_ids(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
 ) -> Result<Vec<Uuid>, sqlx::Error> {
-    sqlx::query_scalar::<_, Uuid>(
+    db::query_scalar::<Uuid>(
         "SELECT id FROM groups WHERE community_id = $1 AND visibility = 'public'",
     )
     .bind(community_id)
@@ -436,11 +430,11 @@ pub async fn list_public_group_ids(
 
 /// List groups in a community that the user is a member of.
 pub async fn list_community_groups(
-    pool: &PgPool,
+    pool: &Db,
     community_id: Uuid,
     user_id: Uuid,
 ) -> Result<Vec<Group>, sqlx::Error> {
-    sqlx::query_as::<_, Group>(
+    db::query_as::<Group>(
         "SELECT g.* FROM groups g \
          INNER JOIN group_members gm ON g.id = gm.group_id \
          WHERE g.community_id = $1 AND gm.user_id = $2 \
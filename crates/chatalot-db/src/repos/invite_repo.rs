@@ -1,11 +1,11 @@
+use crate::db::{self, Db};
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::models::group::GroupInvite;
 
 pub async fn create_invite(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     group_id: Uuid,
     created_by: Uuid,
@@ -13,7 +13,7 @@ pub async fn create_invite(
     max_uses: Option<i32>,
     expires_at: Option<DateTime<Utc>>,
 ) -> Result<GroupInvite, sqlx::Error> {
-    sqlx::query_as::<_, GroupInvite>(
+    db::query_as::<GroupInvite>(
         r#"
         INSERT INTO group_invites (id, group_id, created_by, code, max_uses, expires_at)
         VALUES ($1, $2, $3, $4, $5, $6)
@@ -30,19 +30,16 @@ pub async fn create_invite(
     .await
 }
 
-pub async fn get_invite_by_code(
-    pool: &PgPool,
-    code: &str,
-) -> Result<Option<GroupInvite>, sqlx::Error> {
-    sqlx::query_as::<_, GroupInvite>("SELECT * FROM group_invites WHERE code = $1")
+pub async fn get_invite_by_code(pool: &Db, code: &str) -> Result<Option<GroupInvite>, sqlx::Error> {
+    db::query_as::<GroupInvite>("SELECT * FROM group_invites WHERE code = $1")
         .bind(code)
         .fetch_optional(pool)
         .await
 }
 
 /// Atomically increment usage count, returning true if the invite was valid and used.
-pub async fn increment_usage(pool: &PgPool, invite_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
+pub async fn increment_usage(pool: &Db, invite_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query(
         r#"
         UPDATE group_invites
         SET used_count = used_count + 1
@@ -59,10 +56,10 @@ pub async fn increment_usage(pool: &PgPool, invite_id: Uuid) -> Result<bool, sql
 }
 
 pub async fn list_group_invites(
-    pool: &PgPool,
+    pool: &Db,
     group_id: Uuid,
 ) -> Result<Vec<GroupInvite>, sqlx::Error> {
-    sqlx::query_as::<_, GroupInvite>(
+    db::query_as::<GroupInvite>(
         "SELECT * FROM group_invites WHERE group_id = $1 ORDER BY created_at DESC LIMIT 100",
     )
     .bind(group_id)
@@ -71,11 +68,11 @@ pub async fn list_group_invites(
 }
 
 pub async fn delete_invite(
-    pool: &PgPool,
+    pool: &Db,
     invite_id: Uuid,
     group_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM group_invites WHERE id = $1 AND group_id = $2")
+    let result = db::query("DELETE FROM group_invites WHERE id = $1 AND group_id = $2")
         .bind(invite_id)
         .bind(group_id)
         .execute(pool)
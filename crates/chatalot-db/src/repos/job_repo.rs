@@ -0,0 +1,120 @@
+use crate::db::{self, Db};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::job::Job;
+
+/// Make sure a job has a row; an existing schedule is left untouched.
+pub async fn register(
+    pool: &Db,
+    name: &str,
+    first_run_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    db::query("INSERT INTO jobs (name, next_run_at) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING")
+        .bind(name)
+        .bind(first_run_at)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Take the lease on a job if it is due and nobody else holds a live lease.
+/// Returns the job row (as it was before this run) on success.
+pub async fn try_acquire(
+    pool: &Db,
+    name: &str,
+    owner: Uuid,
+    lease_secs: i64,
+) -> Result<Option<Job>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        UPDATE jobs
+        SET lease_owner = $2,
+            lease_expires_at = {},
+            last_started_at = NOW()
+        WHERE name = $1
+          AND next_run_at <= NOW()
+          AND (lease_expires_at IS NULL OR lease_expires_at < NOW() OR lease_owner = $2)
+        RETURNING *
+        "#,
+        pool.backend().now_plus_secs("$3"),
+    );
+    db::query_as::<Job>(sql)
+        .bind(name)
+        .bind(owner)
+        .bind(lease_secs as f64)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Extend a lease held by `owner` while its job is still running.
+pub async fn renew_lease(
+    pool: &Db,
+    name: &str,
+    owner: Uuid,
+    lease_secs: i64,
+) -> Result<bool, sqlx::Error> {
+    let sql = format!(
+        "UPDATE jobs SET lease_expires_at = {} WHERE name = $1 AND lease_owner = $2",
+        pool.backend().now_plus_secs("$3"),
+    );
+    let result = db::query(sql)
+        .bind(name)
+        .bind(owner)
+        .bind(lease_secs as f64)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Record the outcome of a run, schedule the next one and release the lease.
+pub async fn finish(
+    pool: &Db,
+    name: &str,
+    owner: Uuid,
+    duration_ms: i64,
+    error: Option<&str>,
+    consecutive_failures: i32,
+    next_run_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    db::query(
+        r#"
+        UPDATE jobs
+        SET lease_owner = NULL,
+            lease_expires_at = NULL,
+            last_finished_at = NOW(),
+            last_success_at = CASE WHEN $4::TEXT IS NULL THEN NOW() ELSE last_success_at END,
+            last_duration_ms = $3,
+            last_error = $4,
+            consecutive_failures = $5,
+            run_count = run_count + 1,
+            next_run_at = $6
+        WHERE name = $1 AND lease_owner = $2
+        "#,
+    )
+    .bind(name)
+    .bind(owner)
+    .bind(duration_ms)
+    .bind(error)
+    .bind(consecutive_failures)
+    .bind(next_run_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Make a job due immediately.
+pub async fn trigger_now(pool: &Db, name: &str) -> Result<bool, sqlx::Error> {
+    let result = db::query("UPDATE jobs SET next_run_at = NOW() WHERE name = $1")
+        .bind(name)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// List all cluster jobs.
+pub async fn list_jobs(pool: &Db) -> Result<Vec<Job>, sqlx::Error> {
+    db::query_as::<Job>("SELECT * FROM jobs ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
@@ -1,4 +1,4 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::key_bundle::{OneTimePrekey, SignedPrekey};
@@ -6,12 +6,12 @@ use crate::models::user::IdentityKey;
 
 /// Upsert an identity key (for users who registered before E2E was active).
 pub async fn upsert_identity_key(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     identity_key: &[u8],
     fingerprint: &str,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO identity_keys (user_id, identity_key, fingerprint)
         VALUES ($1, $2, $3)
@@ -31,14 +31,14 @@ pub async fn upsert_identity_key(
 
 /// Upload a signed prekey.
 pub async fn upsert_signed_prekey(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     user_id: Uuid,
     key_id: i32,
     public_key: &[u8],
     signature: &[u8],
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO signed_prekeys (id, user_id, key_id, public_key, signature)
         VALUES ($1, $2, $3, $4, $5)
@@ -60,13 +60,13 @@ pub async fn upsert_signed_prekey(
 
 /// Upload a batch of one-time prekeys.
 pub async fn upload_one_time_prekeys(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     prekeys: &[(i32, Vec<u8>)], // (key_id, public_key)
 ) -> Result<(), sqlx::Error> {
     let mut tx = pool.begin().await?;
     for (key_id, public_key) in prekeys {
-        sqlx::query(
+        db::query(
             r#"
             INSERT INTO one_time_prekeys (id, user_id, key_id, public_key)
             VALUES ($1, $2, $3, $4)
@@ -77,29 +77,25 @@ pub async fn upload_one_time_prekeys(
         .bind(user_id)
         .bind(key_id)
         .bind(public_key)
-        .execute(&mut *tx)
+        .execute(&mut tx)
         .await?;
     }
     tx.commit().await
 }
 
 /// Fetch a user's key bundle for X3DH (consumes one one-time prekey).
-pub async fn fetch_key_bundle(
-    pool: &PgPool,
-    user_id: Uuid,
-) -> Result<Option<KeyBundle>, sqlx::Error> {
-    let identity =
-        sqlx::query_as::<_, IdentityKey>("SELECT * FROM identity_keys WHERE user_id = $1")
-            .bind(user_id)
-            .fetch_optional(pool)
-            .await?;
+pub async fn fetch_key_bundle(pool: &Db, user_id: Uuid) -> Result<Option<KeyBundle>, sqlx::Error> {
+    let identity = db::query_as::<IdentityKey>("SELECT * FROM identity_keys WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
 
     let identity = match identity {
         Some(ik) => ik,
         None => return Ok(None),
     };
 
-    let signed_prekey = sqlx::query_as::<_, SignedPrekey>(
+    let signed_prekey = db::query_as::<SignedPrekey>(
         "SELECT * FROM signed_prekeys WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1",
     )
     .bind(user_id)
@@ -112,7 +108,7 @@ pub async fn fetch_key_bundle(
     };
 
     // Atomically claim one unused one-time prekey
-    let one_time_prekey = sqlx::query_as::<_, OneTimePrekey>(
+    let one_time_prekey = db::query_as::<OneTimePrekey>(
         r#"
         UPDATE one_time_prekeys SET used = TRUE
         WHERE id = (
@@ -137,9 +133,9 @@ pub async fn fetch_key_bundle(
 }
 
 /// Fetch just the identity key bytes for a user.
-pub async fn fetch_identity_key(pool: &PgPool, user_id: Uuid) -> Result<Option<Vec<u8>>, sqlx::Error> {
+pub async fn fetch_identity_key(pool: &Db, user_id: Uuid) -> Result<Option<Vec<u8>>, sqlx::Error> {
     let row: Option<(Vec<u8>,)> =
-        sqlx::query_as("SELECT identity_key FROM identity_keys WHERE user_id = $1")
+        db::query_as("SELECT identity_key FROM identity_keys WHERE user_id = $1")
             .bind(user_id)
             .fetch_optional(pool)
             .await?;
@@ -147,8 +143,8 @@ pub async fn fetch_identity_key(pool: &PgPool, user_id: Uuid) -> Result<Option<V
 }
 
 /// Delete ALL one-time prekeys for a user (used during full key re-registration).
-pub async fn delete_all_prekeys(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query("DELETE FROM one_time_prekeys WHERE user_id = $1")
+pub async fn delete_all_prekeys(pool: &Db, user_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query("DELETE FROM one_time_prekeys WHERE user_id = $1")
         .bind(user_id)
         .execute(pool)
         .await?;
@@ -156,9 +152,9 @@ pub async fn delete_all_prekeys(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx
 }
 
 /// Count remaining unused one-time prekeys for a user.
-pub async fn count_unused_prekeys(pool: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+pub async fn count_unused_prekeys(pool: &Db, user_id: Uuid) -> Result<i64, sqlx::Error> {
     let row: (i64,) =
-        sqlx::query_as("SELECT COUNT(*) FROM one_time_prekeys WHERE user_id = $1 AND NOT used")
+        db::query_as("SELECT COUNT(*) FROM one_time_prekeys WHERE user_id = $1 AND NOT used")
             .bind(user_id)
             .fetch_one(pool)
             .await?;
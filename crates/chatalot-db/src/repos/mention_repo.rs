@@ -0,0 +1,89 @@
+use crate::db::{self, Db};
+use uuid::Uuid;
+
+use crate::models::mention::MentionedMessage;
+use crate::repos::notification_repo::effective_setting_join;
+
+/// Record the mentions declared for a message.
+///
+/// Explicit user mentions, role mentions and `@everyone` are all resolved against
+/// current channel membership, so non-members are silently dropped and the sender
+/// never mentions themselves. Returns the IDs of the users actually mentioned.
+pub async fn record_mentions(
+    pool: &Db,
+    message_id: Uuid,
+    channel_id: Uuid,
+    sender_id: Uuid,
+    user_ids: &[Uuid],
+    roles: &[String],
+    everyone: bool,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    if user_ids.is_empty() && roles.is_empty() && !everyone {
+        return Ok(Vec::new());
+    }
+    let rows: Vec<(Uuid,)> = db::query_as(
+        r#"
+        INSERT INTO message_mentions (message_id, channel_id, user_id, kind)
+        SELECT $1, $2, cm.user_id,
+            CASE
+                WHEN cm.user_id = ANY($4) THEN 'user'
+                WHEN cm.role = ANY($5) THEN 'role'
+                ELSE 'everyone'
+            END
+        FROM channel_members cm
+        WHERE cm.channel_id = $2
+          AND cm.user_id != $3
+          AND (cm.user_id = ANY($4) OR cm.role = ANY($5) OR $6)
+        ON CONFLICT (message_id, user_id) DO NOTHING
+        RETURNING user_id
+        "#,
+    )
+    .bind(message_id)
+    .bind(channel_id)
+    .bind(sender_id)
+    .bind(user_ids)
+    .bind(roles)
+    .bind(everyone)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
+/// List messages that mention a user across all channels they are still a member of,
+/// skipping channels the user has set to `nothing` or currently muted.
+/// Returns messages in reverse chronological order, paginated by cursor.
+pub async fn list_mentions(
+    pool: &Db,
+    user_id: Uuid,
+    before: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<MentionedMessage>, sqlx::Error> {
+    let limit = limit.min(100);
+    let sql = format!(
+        r#"
+        SELECT m.*, mm.kind AS mention_kind
+        FROM message_mentions mm
+        JOIN messages m ON m.id = mm.message_id
+        JOIN channel_members cm ON cm.channel_id = mm.channel_id AND cm.user_id = mm.user_id
+        {join}
+        WHERE mm.user_id = $1
+          AND (eff.level IS NULL OR eff.level != 'nothing')
+          AND (eff.muted_until IS NULL OR eff.muted_until <= NOW())
+          AND m.deleted_at IS NULL
+          AND m.quarantined_at IS NULL
+          AND (m.expires_at IS NULL OR m.expires_at > NOW())
+          AND ($2::uuid IS NULL
+               OR mm.created_at < (SELECT created_at FROM message_mentions
+                                   WHERE message_id = $2 AND user_id = $1))
+        ORDER BY mm.created_at DESC
+        LIMIT $3
+        "#,
+        join = effective_setting_join("mm.user_id", "mm.channel_id"),
+    );
+    db::query_as::<MentionedMessage>(&sql)
+        .bind(user_id)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
@@ -0,0 +1,150 @@
+//! Monthly partitions of `messages`, `reactions` and `message_edits`, and
+//! moving cold months into the `archived_*` tables.
+//!
+//! Partitioning is Postgres-only; on SQLite these functions do nothing.
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::db::{self, Backend, Db};
+
+/// Row counts moved by [`archive_month`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArchivedMonth {
+    pub messages: u64,
+    pub reactions: u64,
+    pub edits: u64,
+    /// Soft-deleted or expired messages dropped instead of archived.
+    pub discarded: u64,
+}
+
+/// Name of a month's partition of `parent`, as created by the
+/// `create_message_partitions` SQL function.
+fn partition_name(parent: &str, month: NaiveDate) -> String {
+    format!("{parent}_y{:04}m{:02}", month.year(), month.month())
+}
+
+/// Parse the month out of a partition name; `None` for the default partition.
+fn partition_month(parent: &str, name: &str) -> Option<NaiveDate> {
+    let rest = name.strip_prefix(parent)?.strip_prefix("_y")?;
+    let (year, month) = rest.split_once('m')?;
+    NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)
+}
+
+/// Make sure partitions exist from the current month through `months_ahead`
+/// months from now. Returns the number of tables created.
+pub async fn ensure_partitions(pool: &Db, months_ahead: i32) -> Result<i32, sqlx::Error> {
+    if pool.backend() == Backend::Sqlite {
+        return Ok(0);
+    }
+    db::query_scalar(
+        r#"
+        SELECT create_message_partitions(
+            date_trunc('month', NOW() AT TIME ZONE 'UTC')::date,
+            (date_trunc('month', NOW() AT TIME ZONE 'UTC') + make_interval(months => $1))::date
+        )
+        "#,
+    )
+    .bind(months_ahead)
+    .fetch_one(pool)
+    .await
+}
+
+/// Months that currently have a `messages` partition, oldest first.
+pub async fn list_partition_months(pool: &Db) -> Result<Vec<NaiveDate>, sqlx::Error> {
+    if pool.backend() == Backend::Sqlite {
+        return Ok(Vec::new());
+    }
+    let names: Vec<String> = db::query_scalar(
+        r#"
+        SELECT c.relname::text FROM pg_inherits i
+        JOIN pg_class c ON c.oid = i.inhrelid
+        WHERE i.inhparent = 'messages'::regclass
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut months: Vec<NaiveDate> = names
+        .iter()
+        .filter_map(|name| partition_month("messages", name))
+        .collect();
+    months.sort();
+    Ok(months)
+}
+
+/// Move one month out of the live tables: soft-deleted and expired messages
+/// are deleted, everything else is copied to the `archived_*` tables, and the
+/// month's partitions are dropped. Runs in one transaction, so readers see
+/// the month either live or archived, never both.
+pub async fn archive_month(pool: &Db, month: NaiveDate) -> Result<ArchivedMonth, sqlx::Error> {
+    if pool.backend() == Backend::Sqlite {
+        return Ok(ArchivedMonth::default());
+    }
+    let messages = partition_name("messages", month);
+    let reactions = partition_name("reactions", month);
+    let edits = partition_name("message_edits", month);
+
+    let mut tx = pool.begin().await?;
+    // Dropping a partition briefly locks the whole table; give up rather
+    // than queue behind a long query and stall every reader behind us.
+    db::query("SET LOCAL lock_timeout = '10s'")
+        .execute(&mut tx)
+        .await?;
+
+    let mut moved = ArchivedMonth {
+        // Deleted through the parent so the usual cascades run.
+        discarded: db::query(format!(
+            "DELETE FROM messages WHERE id IN (SELECT id FROM {messages} \
+             WHERE deleted_at IS NOT NULL OR (expires_at IS NOT NULL AND expires_at < NOW()))"
+        ))
+        .execute(&mut tx)
+        .await?
+        .rows_affected(),
+        ..Default::default()
+    };
+    moved.messages = db::query(format!(
+        r#"
+        INSERT INTO archived_messages (id, channel_id, sender_id, ciphertext, nonce, message_type,
+            sender_key_id, reply_to_id, edited_at, deleted_at, created_at, quarantined_at,
+            quarantined_by, plaintext, expires_at, thread_id)
+        SELECT id, channel_id, sender_id, ciphertext, nonce, message_type,
+            sender_key_id, reply_to_id, edited_at, deleted_at, created_at, quarantined_at,
+            quarantined_by, plaintext, expires_at, thread_id
+        FROM {messages}
+        "#
+    ))
+    .execute(&mut tx)
+    .await?
+    .rows_affected();
+    moved.reactions = db::query(format!(
+        r#"
+        INSERT INTO archived_reactions (id, message_id, user_id, emoji, created_at, message_created_at)
+        SELECT id, message_id, user_id, emoji, created_at, message_created_at FROM {reactions}
+        "#
+    ))
+    .execute(&mut tx)
+    .await?
+    .rows_affected();
+    moved.edits = db::query(format!(
+        r#"
+        INSERT INTO archived_message_edits (id, message_id, old_ciphertext, old_nonce, edited_at, message_created_at)
+        SELECT id, message_id, old_ciphertext, old_nonce, edited_at, message_created_at FROM {edits}
+        "#
+    ))
+    .execute(&mut tx)
+    .await?
+    .rows_affected();
+
+    // The messages partition is referenced by the other two, so it goes last
+    // and has to be detached before it can be dropped.
+    db::query(format!("DROP TABLE {reactions}, {edits}"))
+        .execute(&mut tx)
+        .await?;
+    db::query(format!("ALTER TABLE messages DETACH PARTITION {messages}"))
+        .execute(&mut tx)
+        .await?;
+    db::query(format!("DROP TABLE {messages}"))
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(moved)
+}
@@ -1,5 +1,5 @@
+use crate::db::{self, Db};
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::models::message::{Message, MessageEdit};
@@ -12,6 +12,12 @@ pub struct SearchFilters {
     pub has_file: Option<bool>,
 }
 
+/// Columns shared by `messages` and `archived_messages`, for queries that
+/// read both.
+const MESSAGE_COLUMNS: &str = "id, channel_id, sender_id, ciphertext, nonce, message_type, \
+    sender_key_id, reply_to_id, edited_at, deleted_at, created_at, quarantined_at, quarantined_by, \
+    plaintext, expires_at, thread_id";
+
 /// Escape ILIKE special characters to prevent wildcard injection.
 fn escape_ilike(s: &str) -> String {
     s.replace('\\', "\\\\")
@@ -20,8 +26,9 @@ fn escape_ilike(s: &str) -> String {
 }
 
 /// Insert a new message (ciphertext — server cannot read it).
+#[allow(clippy::too_many_arguments)]
 pub async fn create_message(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     channel_id: Uuid,
     sender_id: Uuid,
@@ -34,7 +41,7 @@ pub async fn create_message(
     expires_at: Option<chrono::DateTime<chrono::Utc>>,
     thread_id: Option<Uuid>,
 ) -> Result<Message, sqlx::Error> {
-    sqlx::query_as::<_, Message>(
+    db::query_as::<Message>(
         r#"
         INSERT INTO messages (id, channel_id, sender_id, ciphertext, nonce, message_type, sender_key_id, reply_to_id, plaintext, expires_at, thread_id)
         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
@@ -57,8 +64,8 @@ pub async fn create_message(
 }
 
 /// Count messages in a channel (excluding deleted and quarantined).
-pub async fn count_messages(pool: &PgPool, channel_id: Uuid) -> Result<i64, sqlx::Error> {
-    let row: (i64,) = sqlx::query_as(
+pub async fn count_messages(pool: &Db, channel_id: Uuid) -> Result<i64, sqlx::Error> {
+    let row: (i64,) = db::query_as(
         "SELECT COUNT(*) FROM messages WHERE channel_id = $1 AND deleted_at IS NULL AND quarantined_at IS NULL",
     )
     .bind(channel_id)
@@ -68,107 +75,107 @@ pub async fn count_messages(pool: &PgPool, channel_id: Uuid) -> Result<i64, sqlx
 }
 
 /// Get messages in a channel, paginated by cursor (before a given message ID).
-/// Returns messages in reverse chronological order.
+/// Returns messages in reverse chronological order. Pages continue into
+/// `archived_messages` once the live table runs out, and the cursor may be
+/// an archived message.
 pub async fn get_messages(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     before: Option<Uuid>,
     limit: i64,
     root_only: bool,
 ) -> Result<Vec<Message>, sqlx::Error> {
     let limit = limit.min(100);
-    let thread_filter = if root_only {
-        " AND thread_id IS NULL"
-    } else {
-        ""
-    };
+    let mut filter = String::from(
+        "channel_id = $1 AND deleted_at IS NULL AND quarantined_at IS NULL \
+         AND (expires_at IS NULL OR expires_at > NOW())",
+    );
+    if root_only {
+        filter.push_str(" AND thread_id IS NULL");
+    }
+    if before.is_some() {
+        filter.push_str(
+            " AND created_at < COALESCE((SELECT created_at FROM messages WHERE id = $3), \
+             (SELECT created_at FROM archived_messages WHERE id = $3))",
+        );
+    }
 
+    let sql = format!(
+        r#"
+        SELECT * FROM (
+            SELECT {MESSAGE_COLUMNS} FROM messages WHERE {filter}
+            UNION ALL
+            SELECT {MESSAGE_COLUMNS} FROM archived_messages WHERE {filter}
+        ) m
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+    );
+    let mut query = db::query_as::<Message>(sql).bind(channel_id).bind(limit);
     if let Some(before_id) = before {
-        let sql = format!(
-            r#"
-            SELECT * FROM messages
-            WHERE channel_id = $1
-              AND created_at < (SELECT created_at FROM messages WHERE id = $2)
-              AND deleted_at IS NULL
-              AND quarantined_at IS NULL
-              AND (expires_at IS NULL OR expires_at > NOW()){thread_filter}
-            ORDER BY created_at DESC
-            LIMIT $3
-            "#,
-        );
-        sqlx::query_as::<_, Message>(&sql)
-            .bind(channel_id)
-            .bind(before_id)
-            .bind(limit)
-            .fetch_all(pool)
-            .await
-    } else {
-        let sql = format!(
-            r#"
-            SELECT * FROM messages
-            WHERE channel_id = $1
-              AND deleted_at IS NULL
-              AND quarantined_at IS NULL
-              AND (expires_at IS NULL OR expires_at > NOW()){thread_filter}
-            ORDER BY created_at DESC
-            LIMIT $2
-            "#,
-        );
-        sqlx::query_as::<_, Message>(&sql)
-            .bind(channel_id)
-            .bind(limit)
-            .fetch_all(pool)
-            .await
+        query = query.bind(before_id);
     }
+    query.fetch_all(pool).await
 }
 
-/// Search messages across all channels a user is a member of.
-/// Note: This works because messages are currently stored as UTF-8 bytes (Phase 1).
-pub async fn search_messages_global(
-    pool: &PgPool,
-    user_id: Uuid,
-    query: &str,
-    limit: i64,
-    filters: &SearchFilters,
-) -> Result<Vec<Message>, sqlx::Error> {
-    let limit = limit.min(50);
-    let pattern = format!("%{}%", escape_ilike(query));
-
-    let mut sql = String::from(
-        r#"SELECT m.* FROM messages m
-        INNER JOIN channel_members cm ON cm.channel_id = m.channel_id AND cm.user_id = $1
-        WHERE m.deleted_at IS NULL
+/// Build a search over the live and archive tables. `scope` restricts the
+/// rows (using `$1`); `$2` is the pattern and `$3` the limit, and the
+/// optional filters follow from `$4` in the order they are bound.
+fn search_sql(pool: &Db, scope: &str, filters: &SearchFilters) -> String {
+    let mut filter = format!(
+        r#"{scope}
+          AND m.deleted_at IS NULL
           AND m.quarantined_at IS NULL
           AND (m.expires_at IS NULL OR m.expires_at > NOW())
-          AND convert_from(m.ciphertext, 'UTF8') ILIKE $2"#,
+          AND {} ILIKE $2 ESCAPE '\'"#,
+        pool.backend().bytes_as_text("m.ciphertext"),
     );
     let mut param_idx = 4u32; // $3 is limit
 
     if filters.sender.is_some() {
-        sql.push_str(&format!(
-            " AND m.sender_id IN (SELECT id FROM users WHERE username ILIKE ${param_idx})"
+        filter.push_str(&format!(
+            " AND m.sender_id IN (SELECT id FROM users WHERE username ILIKE ${param_idx} ESCAPE '\\')"
         ));
         param_idx += 1;
     }
     if filters.after.is_some() {
-        sql.push_str(&format!(" AND m.created_at >= ${param_idx}"));
+        filter.push_str(&format!(" AND m.created_at >= ${param_idx}"));
         param_idx += 1;
     }
     if filters.before.is_some() {
-        sql.push_str(&format!(" AND m.created_at <= ${param_idx}"));
+        filter.push_str(&format!(" AND m.created_at <= ${param_idx}"));
         let _ = param_idx;
     }
     if filters.has_file == Some(true) {
-        sql.push_str(" AND m.message_type = 'file'");
+        filter.push_str(" AND m.message_type = 'file'");
     }
 
-    sql.push_str(" ORDER BY m.created_at DESC LIMIT $3");
-
-    let mut q = sqlx::query_as::<_, Message>(&sql)
-        .bind(user_id)
-        .bind(pattern)
-        .bind(limit);
+    let columns: String = MESSAGE_COLUMNS
+        .split(", ")
+        .map(|c| format!("m.{c}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"
+        SELECT * FROM (
+            SELECT {columns} FROM messages m WHERE {filter}
+            UNION ALL
+            SELECT {columns} FROM archived_messages m WHERE {filter}
+        ) found
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#
+    )
+}
 
+/// Bind the pattern, limit and optional filters of a [`search_sql`] query.
+fn bind_search<'q>(
+    q: db::QueryAs<'q, Message>,
+    query: &str,
+    limit: i64,
+    filters: &SearchFilters,
+) -> db::QueryAs<'q, Message> {
+    let mut q = q.bind(format!("%{}%", escape_ilike(query))).bind(limit);
     if let Some(ref sender) = filters.sender {
         q = q.bind(format!("%{}%", escape_ilike(sender)));
     }
@@ -178,69 +185,53 @@ pub async fn search_messages_global(
     if let Some(before) = filters.before {
         q = q.bind(before);
     }
+    q
+}
 
-    q.fetch_all(pool).await
+/// Search messages across all channels a user is a member of, archived or not.
+/// Note: This works because messages are currently stored as UTF-8 bytes (Phase 1).
+pub async fn search_messages_global(
+    pool: &Db,
+    user_id: Uuid,
+    query: &str,
+    limit: i64,
+    filters: &SearchFilters,
+) -> Result<Vec<Message>, sqlx::Error> {
+    let sql = search_sql(
+        pool,
+        "m.channel_id IN (SELECT channel_id FROM channel_members WHERE user_id = $1)",
+        filters,
+    );
+    bind_search(
+        db::query_as::<Message>(sql).bind(user_id),
+        query,
+        limit.min(50),
+        filters,
+    )
+    .fetch_all(pool)
+    .await
 }
 
-/// Search messages in a channel by content (plaintext search on ciphertext bytes).
+/// Search messages in a channel by content (plaintext search on ciphertext
+/// bytes), archived or not.
 /// Note: This works because messages are currently stored as UTF-8 bytes (Phase 1).
 /// Will need to change when E2E encryption is enabled in Phase 2.
 pub async fn search_messages(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     query: &str,
     limit: i64,
     filters: &SearchFilters,
 ) -> Result<Vec<Message>, sqlx::Error> {
-    let limit = limit.min(50);
-    let pattern = format!("%{}%", escape_ilike(query));
-
-    let mut sql = String::from(
-        r#"SELECT * FROM messages
-        WHERE channel_id = $1
-          AND deleted_at IS NULL
-          AND quarantined_at IS NULL
-          AND (expires_at IS NULL OR expires_at > NOW())
-          AND convert_from(ciphertext, 'UTF8') ILIKE $2"#,
-    );
-    let mut param_idx = 4u32; // $3 is limit
-
-    if filters.sender.is_some() {
-        sql.push_str(&format!(
-            " AND sender_id IN (SELECT id FROM users WHERE username ILIKE ${param_idx})"
-        ));
-        param_idx += 1;
-    }
-    if filters.after.is_some() {
-        sql.push_str(&format!(" AND created_at >= ${param_idx}"));
-        param_idx += 1;
-    }
-    if filters.before.is_some() {
-        sql.push_str(&format!(" AND created_at <= ${param_idx}"));
-        let _ = param_idx;
-    }
-    if filters.has_file == Some(true) {
-        sql.push_str(" AND message_type = 'file'");
-    }
-
-    sql.push_str(" ORDER BY created_at DESC LIMIT $3");
-
-    let mut q = sqlx::query_as::<_, Message>(&sql)
-        .bind(channel_id)
-        .bind(pattern)
-        .bind(limit);
-
-    if let Some(ref sender) = filters.sender {
-        q = q.bind(format!("%{}%", escape_ilike(sender)));
-    }
-    if let Some(after) = filters.after {
-        q = q.bind(after);
-    }
-    if let Some(before) = filters.before {
-        q = q.bind(before);
-    }
-
-    q.fetch_all(pool).await
+    let sql = search_sql(pool, "m.channel_id = $1", filters);
+    bind_search(
+        db::query_as::<Message>(sql).bind(channel_id),
+        query,
+        limit.min(50),
+        filters,
+    )
+    .fetch_all(pool)
+    .await
 }
 
 /// Thread info for batch-fetching reply counts.
@@ -251,67 +242,63 @@ pub struct ThreadInfo {
     pub last_reply_at: DateTime<Utc>,
 }
 
-/// Get messages in a thread (replies to a root message), ordered chronologically.
+/// Get messages in a thread (replies to a root message), ordered
+/// chronologically. Replies may be live or archived.
 pub async fn get_thread_messages(
-    pool: &PgPool,
+    pool: &Db,
     thread_id: Uuid,
     before: Option<Uuid>,
     limit: i64,
 ) -> Result<Vec<Message>, sqlx::Error> {
     let limit = limit.min(100);
+    let mut filter =
+        String::from("thread_id = $1 AND deleted_at IS NULL AND quarantined_at IS NULL");
+    if before.is_some() {
+        filter.push_str(
+            " AND created_at < COALESCE((SELECT created_at FROM messages WHERE id = $3), \
+             (SELECT created_at FROM archived_messages WHERE id = $3))",
+        );
+    }
 
+    let sql = format!(
+        r#"
+        SELECT * FROM (
+            SELECT {MESSAGE_COLUMNS} FROM messages WHERE {filter}
+            UNION ALL
+            SELECT {MESSAGE_COLUMNS} FROM archived_messages WHERE {filter}
+        ) m
+        ORDER BY created_at ASC
+        LIMIT $2
+        "#,
+    );
+    let mut query = db::query_as::<Message>(sql).bind(thread_id).bind(limit);
     if let Some(before_id) = before {
-        sqlx::query_as::<_, Message>(
-            r#"
-            SELECT * FROM messages
-            WHERE thread_id = $1
-              AND created_at < (SELECT created_at FROM messages WHERE id = $2)
-              AND deleted_at IS NULL
-              AND quarantined_at IS NULL
-            ORDER BY created_at ASC
-            LIMIT $3
-            "#,
-        )
-        .bind(thread_id)
-        .bind(before_id)
-        .bind(limit)
-        .fetch_all(pool)
-        .await
-    } else {
-        sqlx::query_as::<_, Message>(
-            r#"
-            SELECT * FROM messages
-            WHERE thread_id = $1
-              AND deleted_at IS NULL
-              AND quarantined_at IS NULL
-            ORDER BY created_at ASC
-            LIMIT $2
-            "#,
-        )
-        .bind(thread_id)
-        .bind(limit)
-        .fetch_all(pool)
-        .await
+        query = query.bind(before_id);
     }
+    query.fetch_all(pool).await
 }
 
-/// Batch-fetch thread reply counts for a set of message IDs (root messages).
+/// Batch-fetch thread reply counts for a set of message IDs (root messages),
+/// counting live and archived replies.
 pub async fn get_thread_reply_counts(
-    pool: &PgPool,
+    pool: &Db,
     message_ids: &[Uuid],
 ) -> Result<Vec<ThreadInfo>, sqlx::Error> {
     if message_ids.is_empty() {
         return Ok(Vec::new());
     }
-    sqlx::query_as::<_, ThreadInfo>(
+    db::query_as::<ThreadInfo>(
         r#"
         SELECT thread_id AS root_message_id,
                COUNT(*) AS reply_count,
                MAX(created_at) AS last_reply_at
-        FROM messages
-        WHERE thread_id = ANY($1)
-          AND deleted_at IS NULL
-          AND quarantined_at IS NULL
+        FROM (
+            SELECT thread_id, created_at FROM messages
+            WHERE thread_id = ANY($1) AND deleted_at IS NULL AND quarantined_at IS NULL
+            UNION ALL
+            SELECT thread_id, created_at FROM archived_messages
+            WHERE thread_id = ANY($1) AND deleted_at IS NULL AND quarantined_at IS NULL
+        ) replies
         GROUP BY thread_id
         "#,
     )
@@ -320,128 +307,180 @@ pub async fn get_thread_reply_counts(
     .await
 }
 
-/// Get a single message by ID (needed for broadcast after edit/delete).
+/// Get a single message by ID from the live or archive table.
 pub async fn get_message_by_id(
-    pool: &PgPool,
+    pool: &Db,
     message_id: Uuid,
 ) -> Result<Option<Message>, sqlx::Error> {
-    sqlx::query_as::<_, Message>("SELECT * FROM messages WHERE id = $1")
+    db::query_as::<Message>(format!(
+        "SELECT {MESSAGE_COLUMNS} FROM messages WHERE id = $1 \
+         UNION ALL SELECT {MESSAGE_COLUMNS} FROM archived_messages WHERE id = $1 LIMIT 1"
+    ))
+    .bind(message_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Whether a message has been moved to the archive tables. Archived messages
+/// are read-only apart from deletion and moderation.
+pub async fn is_archived(pool: &Db, message_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row: Option<(Uuid,)> = db::query_as("SELECT id FROM archived_messages WHERE id = $1")
         .bind(message_id)
         .fetch_optional(pool)
-        .await
+        .await?;
+    Ok(row.is_some())
+}
+
+/// Run an UPDATE against the live and archive tables; returns the rows changed.
+/// `set_where` is everything after the table name and may use `$1` and `$2`.
+async fn update_live_and_archived(
+    pool: &Db,
+    set_where: &str,
+    id: Uuid,
+    other: Option<Uuid>,
+) -> Result<u64, sqlx::Error> {
+    let mut updated = 0;
+    for table in ["messages", "archived_messages"] {
+        let mut query = db::query(format!("UPDATE {table} {set_where}")).bind(id);
+        if let Some(other) = other {
+            query = query.bind(other);
+        }
+        updated += query.execute(pool).await?.rows_affected();
+    }
+    Ok(updated)
 }
 
-/// Soft-delete a message.
+/// Soft-delete a message, archived or not.
 pub async fn delete_message(
-    pool: &PgPool,
+    pool: &Db,
     message_id: Uuid,
     sender_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
-        "UPDATE messages SET deleted_at = NOW() WHERE id = $1 AND sender_id = $2 AND deleted_at IS NULL",
+    let updated = update_live_and_archived(
+        pool,
+        "SET deleted_at = NOW() WHERE id = $1 AND sender_id = $2 AND deleted_at IS NULL",
+        message_id,
+        Some(sender_id),
     )
-    .bind(message_id)
-    .bind(sender_id)
-    .execute(pool)
     .await?;
-    Ok(result.rows_affected() > 0)
+    Ok(updated > 0)
 }
 
-/// Soft-delete a message as a moderator (no sender ownership check).
-pub async fn delete_message_as_mod(pool: &PgPool, message_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result =
-        sqlx::query("UPDATE messages SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL")
-            .bind(message_id)
-            .execute(pool)
-            .await?;
-    Ok(result.rows_affected() > 0)
+/// Soft-delete a message as a moderator (no sender ownership check), archived or not.
+pub async fn delete_message_as_mod(pool: &Db, message_id: Uuid) -> Result<bool, sqlx::Error> {
+    let updated = update_live_and_archived(
+        pool,
+        "SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL",
+        message_id,
+        None,
+    )
+    .await?;
+    Ok(updated > 0)
 }
 
-/// Hard-delete a single message (complete removal from DB).
-pub async fn hard_delete_message(pool: &PgPool, message_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM messages WHERE id = $1")
-        .bind(message_id)
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected() > 0)
+/// Delete from the live and archive tables in one transaction.
+async fn delete_live_and_archived(
+    pool: &Db,
+    condition: &str,
+    id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut deleted = 0;
+    for table in ["messages", "archived_messages"] {
+        deleted += db::query(format!("DELETE FROM {table} WHERE {condition}"))
+            .bind(id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+    }
+    tx.commit().await?;
+    Ok(deleted)
 }
 
-/// Hard-delete ALL messages from a user across all channels.
-pub async fn hard_delete_user_messages(pool: &PgPool, sender_id: Uuid) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM messages WHERE sender_id = $1")
-        .bind(sender_id)
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected())
+/// Hard-delete a single message (complete removal from DB), archived or not.
+pub async fn hard_delete_message(pool: &Db, message_id: Uuid) -> Result<bool, sqlx::Error> {
+    Ok(delete_live_and_archived(pool, "id = $1", message_id).await? > 0)
 }
 
-/// Hard-delete ALL messages in a channel.
-pub async fn hard_delete_channel_messages(
-    pool: &PgPool,
-    channel_id: Uuid,
-) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM messages WHERE channel_id = $1")
-        .bind(channel_id)
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected())
+/// Hard-delete ALL messages from a user across all channels, including archived ones.
+pub async fn hard_delete_user_messages(pool: &Db, sender_id: Uuid) -> Result<u64, sqlx::Error> {
+    delete_live_and_archived(pool, "sender_id = $1", sender_id).await
 }
 
-/// Garbage collect: hard-delete messages soft-deleted more than N days ago.
-pub async fn gc_soft_deleted(pool: &PgPool, days: i64) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query(
-        "DELETE FROM messages WHERE deleted_at IS NOT NULL AND deleted_at < NOW() - make_interval(days => $1)",
-    )
-    .bind(days as i32)
-    .execute(pool)
-    .await?;
-    Ok(result.rows_affected())
+/// Hard-delete ALL messages in a channel, including archived ones.
+pub async fn hard_delete_channel_messages(pool: &Db, channel_id: Uuid) -> Result<u64, sqlx::Error> {
+    delete_live_and_archived(pool, "channel_id = $1", channel_id).await
 }
 
-/// Quarantine a message (hide without deleting, preserve for evidence).
+/// Garbage collect: hard-delete messages soft-deleted more than N days ago,
+/// archived or not.
+pub async fn gc_soft_deleted(pool: &Db, days: i64) -> Result<u64, sqlx::Error> {
+    let mut deleted = 0;
+    for table in ["messages", "archived_messages"] {
+        let sql = format!(
+            "DELETE FROM {table} WHERE deleted_at IS NOT NULL AND deleted_at < {}",
+            pool.backend().now_plus_secs("-$1"),
+        );
+        deleted += db::query(sql)
+            .bind((days * 86_400) as f64)
+            .execute(pool)
+            .await?
+            .rows_affected();
+    }
+    Ok(deleted)
+}
+
+/// Quarantine a message (hide without deleting, preserve for evidence),
+/// archived or not.
 pub async fn quarantine_message(
-    pool: &PgPool,
+    pool: &Db,
     message_id: Uuid,
     quarantined_by: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
-        "UPDATE messages SET quarantined_at = NOW(), quarantined_by = $2 WHERE id = $1 AND quarantined_at IS NULL",
+    let updated = update_live_and_archived(
+        pool,
+        "SET quarantined_at = NOW(), quarantined_by = $2 WHERE id = $1 AND quarantined_at IS NULL",
+        message_id,
+        Some(quarantined_by),
     )
-    .bind(message_id)
-    .bind(quarantined_by)
-    .execute(pool)
     .await?;
-    Ok(result.rows_affected() > 0)
+    Ok(updated > 0)
 }
 
-/// Unquarantine a message.
-pub async fn unquarantine_message(pool: &PgPool, message_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
-        "UPDATE messages SET quarantined_at = NULL, quarantined_by = NULL WHERE id = $1 AND quarantined_at IS NOT NULL",
+/// Unquarantine a message, archived or not.
+pub async fn unquarantine_message(pool: &Db, message_id: Uuid) -> Result<bool, sqlx::Error> {
+    let updated = update_live_and_archived(
+        pool,
+        "SET quarantined_at = NULL, quarantined_by = NULL WHERE id = $1 AND quarantined_at IS NOT NULL",
+        message_id,
+        None,
     )
-    .bind(message_id)
-    .execute(pool)
     .await?;
-    Ok(result.rows_affected() > 0)
+    Ok(updated > 0)
 }
 
-/// Delete messages that have expired (TTL).
-pub async fn delete_expired_messages(pool: &PgPool) -> Result<u64, sqlx::Error> {
-    let result =
-        sqlx::query("DELETE FROM messages WHERE expires_at IS NOT NULL AND expires_at < NOW()")
-            .execute(pool)
-            .await?;
-    Ok(result.rows_affected())
+/// Delete messages that have expired (TTL), archived or not.
+pub async fn delete_expired_messages(pool: &Db) -> Result<u64, sqlx::Error> {
+    let mut deleted = 0;
+    for table in ["messages", "archived_messages"] {
+        deleted += db::query(format!(
+            "DELETE FROM {table} WHERE expires_at IS NOT NULL AND expires_at < NOW()"
+        ))
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+    Ok(deleted)
 }
 
 /// Insert a webhook message (plaintext, no sender_id encryption).
 pub async fn create_webhook_message(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     channel_id: Uuid,
     plaintext: &str,
 ) -> Result<Message, sqlx::Error> {
-    sqlx::query_as::<_, Message>(
+    db::query_as::<Message>(
         r#"
         INSERT INTO messages (id, channel_id, ciphertext, nonce, message_type, plaintext)
         VALUES ($1, $2, '\x00', '\x00', 'webhook', $3)
@@ -455,15 +494,16 @@ pub async fn create_webhook_message(
     .await
 }
 
-/// Update message ciphertext (edit).
+/// Update message ciphertext (edit). Live table only: archived messages are
+/// long past the edit window, which callers check first.
 pub async fn edit_message(
-    pool: &PgPool,
+    pool: &Db,
     message_id: Uuid,
     sender_id: Uuid,
     ciphertext: &[u8],
     nonce: &[u8],
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
+    let result = db::query(
         r#"
         UPDATE messages SET ciphertext = $1, nonce = $2, edited_at = NOW()
         WHERE id = $3 AND sender_id = $4 AND deleted_at IS NULL
@@ -478,17 +518,18 @@ pub async fn edit_message(
     Ok(result.rows_affected() > 0)
 }
 
-/// Save old content before an edit (for edit history).
+/// Save old content before an edit (for edit history). Live table only, like
+/// [`edit_message`].
 pub async fn save_edit_history(
-    pool: &PgPool,
+    pool: &Db,
     message_id: Uuid,
     old_ciphertext: &[u8],
     old_nonce: &[u8],
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
+    db::query(
         r#"
-        INSERT INTO message_edits (message_id, old_ciphertext, old_nonce)
-        VALUES ($1, $2, $3)
+        INSERT INTO message_edits (message_id, old_ciphertext, old_nonce, message_created_at)
+        SELECT id, $2, $3, created_at FROM messages WHERE id = $1
         "#,
     )
     .bind(message_id)
@@ -499,14 +540,17 @@ pub async fn save_edit_history(
     Ok(())
 }
 
-/// Get edit history for a message (oldest first).
+/// Get edit history for a message (oldest first), archived or not.
 pub async fn get_edit_history(
-    pool: &PgPool,
+    pool: &Db,
     message_id: Uuid,
 ) -> Result<Vec<MessageEdit>, sqlx::Error> {
-    sqlx::query_as::<_, MessageEdit>(
+    db::query_as::<MessageEdit>(
         r#"
-        SELECT * FROM message_edits
+        SELECT id, message_id, old_ciphertext, old_nonce, edited_at FROM message_edits
+        WHERE message_id = $1
+        UNION ALL
+        SELECT id, message_id, old_ciphertext, old_nonce, edited_at FROM archived_message_edits
         WHERE message_id = $1
         ORDER BY edited_at ASC
         "#,
@@ -6,25 +6,35 @@ pub mod bookmark_repo;
 pub mod channel_repo;
 pub mod community_repo;
 pub mod custom_emoji_repo;
+pub mod delivery_repo;
 pub mod dm_repo;
 pub mod file_repo;
+pub mod file_retention_repo;
 pub mod group_repo;
 pub mod invite_repo;
+pub mod job_repo;
 pub mod key_repo;
+pub mod mention_repo;
+pub mod message_archive_repo;
 pub mod message_repo;
+pub mod notification_repo;
 pub mod pin_repo;
 pub mod poll_repo;
 pub mod preferences_repo;
 pub mod push_subscription_repo;
+pub mod quiet_hours_repo;
 pub mod reaction_repo;
 pub mod registration_invite_repo;
 pub mod report_repo;
 pub mod scheduled_message_repo;
 pub mod sender_key_repo;
 pub mod settings_repo;
+pub mod storage_quota_repo;
 pub mod timeout_repo;
 pub mod unread_repo;
+pub mod upload_repo;
 pub mod user_repo;
 pub mod voice_repo;
 pub mod warning_repo;
 pub mod webhook_repo;
+pub mod ws_event_repo;
@@ -0,0 +1,140 @@
+use crate::db::{self, Db};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::notification_setting::{EffectiveNotificationSetting, NotificationSetting};
+
+/// Join selecting the most specific notification setting of user `{user}`
+/// for the channel `{channel}` (channel/dm > group > community).
+/// Exposes `eff.level` and `eff.muted_until`; both are NULL when nothing is set.
+pub(crate) fn effective_setting_join(user: &str, channel: &str) -> String {
+    format!(
+        r#"
+        LEFT JOIN notification_settings eff ON eff.user_id = {user} AND (eff.scope_type, eff.scope_id) = (
+            SELECT ns.scope_type, ns.scope_id
+            FROM channels ec
+            LEFT JOIN groups eg ON eg.id = ec.group_id
+            JOIN notification_settings ns ON ns.user_id = {user} AND (
+                (ns.scope_type IN ('channel', 'dm') AND ns.scope_id = ec.id)
+                OR (ns.scope_type = 'group' AND ns.scope_id = ec.group_id)
+                OR (ns.scope_type = 'community' AND ns.scope_id = eg.community_id)
+            )
+            WHERE ec.id = {channel}
+            ORDER BY CASE ns.scope_type WHEN 'group' THEN 1 WHEN 'community' THEN 2 ELSE 0 END
+            LIMIT 1
+        )
+        "#
+    )
+}
+
+/// Create or replace a user's notification setting for a scope.
+pub async fn upsert_setting(
+    pool: &Db,
+    user_id: Uuid,
+    scope_type: &str,
+    scope_id: Uuid,
+    level: &str,
+    muted_until: Option<DateTime<Utc>>,
+) -> Result<NotificationSetting, sqlx::Error> {
+    db::query_as::<NotificationSetting>(
+        r#"
+        INSERT INTO notification_settings (user_id, scope_type, scope_id, level, muted_until, updated_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        ON CONFLICT (user_id, scope_type, scope_id) DO UPDATE
+        SET level = $4, muted_until = $5, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(scope_type)
+    .bind(scope_id)
+    .bind(level)
+    .bind(muted_until)
+    .fetch_one(pool)
+    .await
+}
+
+/// Remove a user's setting for a scope (it falls back to the enclosing scope).
+pub async fn delete_setting(
+    pool: &Db,
+    user_id: Uuid,
+    scope_type: &str,
+    scope_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = db::query(
+        "DELETE FROM notification_settings WHERE user_id = $1 AND scope_type = $2 AND scope_id = $3",
+    )
+    .bind(user_id)
+    .bind(scope_type)
+    .bind(scope_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// List all notification settings for a user.
+pub async fn list_settings(
+    pool: &Db,
+    user_id: Uuid,
+) -> Result<Vec<NotificationSetting>, sqlx::Error> {
+    db::query_as::<NotificationSetting>(
+        "SELECT * FROM notification_settings WHERE user_id = $1 ORDER BY scope_type, scope_id",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Resolve the effective setting in a channel for each of the given users.
+/// Users with no applicable setting are omitted from the result.
+pub async fn get_effective_settings(
+    pool: &Db,
+    channel_id: Uuid,
+    user_ids: &[Uuid],
+) -> Result<Vec<EffectiveNotificationSetting>, sqlx::Error> {
+    if user_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let sql = format!(
+        r#"
+        SELECT eff.user_id, eff.level, eff.muted_until
+        FROM users u
+        {join}
+        WHERE u.id = ANY($2) AND eff.user_id IS NOT NULL
+        ORDER BY eff.user_id
+        "#,
+        join = effective_setting_join("u.id", "$1"),
+    );
+    db::query_as::<EffectiveNotificationSetting>(sql)
+        .bind(channel_id)
+        .bind(user_ids)
+        .fetch_all(pool)
+        .await
+}
+
+/// Members of a channel whose effective setting is explicitly `all` and not muted
+/// (they want a push for every message, not just mentions).
+pub async fn list_notify_all_members(
+    pool: &Db,
+    channel_id: Uuid,
+    exclude_user_id: Uuid,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        SELECT cm.user_id
+        FROM channel_members cm
+        {join}
+        WHERE cm.channel_id = $1
+          AND cm.user_id != $2
+          AND eff.level = 'all'
+          AND (eff.muted_until IS NULL OR eff.muted_until <= NOW())
+        "#,
+        join = effective_setting_join("cm.user_id", "cm.channel_id"),
+    );
+    let rows: Vec<(Uuid,)> = db::query_as(&sql)
+        .bind(channel_id)
+        .bind(exclude_user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
@@ -1,16 +1,16 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::pin::{PinnedMessage, PinnedMessageWithContent};
 
 /// Pin a message. Returns the pin record.
 pub async fn pin_message(
-    pool: &PgPool,
+    pool: &Db,
     message_id: Uuid,
     channel_id: Uuid,
     pinned_by: Uuid,
 ) -> Result<PinnedMessage, sqlx::Error> {
-    sqlx::query_as::<_, PinnedMessage>(
+    db::query_as::<PinnedMessage>(
         r#"
         INSERT INTO pinned_messages (message_id, channel_id, pinned_by)
         VALUES ($1, $2, $3)
@@ -25,8 +25,8 @@ pub async fn pin_message(
 }
 
 /// Unpin a message. Returns true if a row was deleted.
-pub async fn unpin_message(pool: &PgPool, message_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM pinned_messages WHERE message_id = $1")
+pub async fn unpin_message(pool: &Db, message_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM pinned_messages WHERE message_id = $1")
         .bind(message_id)
         .execute(pool)
         .await?;
@@ -36,17 +36,25 @@ pub async fn unpin_message(pool: &PgPool, message_id: Uuid) -> Result<bool, sqlx
 /// List all pinned messages in a channel with full message content.
 /// Ordered by pin time, most recent first. Excludes deleted messages.
 pub async fn list_pinned_messages(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
 ) -> Result<Vec<PinnedMessageWithContent>, sqlx::Error> {
-    sqlx::query_as::<_, PinnedMessageWithContent>(
+    db::query_as::<PinnedMessageWithContent>(
         r#"
         SELECT m.id, m.channel_id, m.sender_id, m.ciphertext, m.nonce,
                m.message_type, m.sender_key_id, m.reply_to_id, m.edited_at,
                m.deleted_at, m.created_at,
                pm.pinned_by, pm.pinned_at
         FROM pinned_messages pm
-        INNER JOIN messages m ON m.id = pm.message_id
+        INNER JOIN (
+            SELECT id, channel_id, sender_id, ciphertext, nonce, message_type, sender_key_id,
+                   reply_to_id, edited_at, deleted_at, created_at
+            FROM messages
+            UNION ALL
+            SELECT id, channel_id, sender_id, ciphertext, nonce, message_type, sender_key_id,
+                   reply_to_id, edited_at, deleted_at, created_at
+            FROM archived_messages
+        ) m ON m.id = pm.message_id
         WHERE pm.channel_id = $1 AND m.deleted_at IS NULL
         ORDER BY pm.pinned_at DESC
         "#,
@@ -57,8 +65,8 @@ pub async fn list_pinned_messages(
 }
 
 /// Count pins in a channel.
-pub async fn count_pins(pool: &PgPool, channel_id: Uuid) -> Result<i64, sqlx::Error> {
-    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM pinned_messages WHERE channel_id = $1")
+pub async fn count_pins(pool: &Db, channel_id: Uuid) -> Result<i64, sqlx::Error> {
+    let row: (i64,) = db::query_as("SELECT COUNT(*) FROM pinned_messages WHERE channel_id = $1")
         .bind(channel_id)
         .fetch_one(pool)
         .await?;
@@ -66,9 +74,9 @@ pub async fn count_pins(pool: &PgPool, channel_id: Uuid) -> Result<i64, sqlx::Er
 }
 
 /// Check if a message is pinned.
-pub async fn is_pinned(pool: &PgPool, message_id: Uuid) -> Result<bool, sqlx::Error> {
+pub async fn is_pinned(pool: &Db, message_id: Uuid) -> Result<bool, sqlx::Error> {
     let row: (bool,) =
-        sqlx::query_as("SELECT EXISTS(SELECT 1 FROM pinned_messages WHERE message_id = $1)")
+        db::query_as("SELECT EXISTS(SELECT 1 FROM pinned_messages WHERE message_id = $1)")
             .bind(message_id)
             .fetch_one(pool)
             .await?;
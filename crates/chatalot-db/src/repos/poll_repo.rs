@@ -1,10 +1,11 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::poll::{Poll, PollVote};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     channel_id: Uuid,
     created_by: Uuid,
@@ -14,7 +15,7 @@ pub async fn create(
     anonymous: bool,
     expires_at: Option<chrono::DateTime<chrono::Utc>>,
 ) -> Result<Poll, sqlx::Error> {
-    sqlx::query_as::<_, Poll>(
+    db::query_as::<Poll>(
         r#"
         INSERT INTO polls (id, channel_id, created_by, question, options, multi_select, anonymous, expires_at)
         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
@@ -33,21 +34,21 @@ pub async fn create(
     .await
 }
 
-pub async fn get(pool: &PgPool, id: Uuid) -> Result<Option<Poll>, sqlx::Error> {
-    sqlx::query_as::<_, Poll>("SELECT * FROM polls WHERE id = $1")
+pub async fn get(pool: &Db, id: Uuid) -> Result<Option<Poll>, sqlx::Error> {
+    db::query_as::<Poll>("SELECT * FROM polls WHERE id = $1")
         .bind(id)
         .fetch_optional(pool)
         .await
 }
 
 pub async fn vote(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     poll_id: Uuid,
     user_id: Uuid,
     option_index: i32,
 ) -> Result<Option<PollVote>, sqlx::Error> {
-    sqlx::query_as::<_, PollVote>(
+    db::query_as::<PollVote>(
         r#"
         INSERT INTO poll_votes (id, poll_id, user_id, option_index)
         VALUES ($1, $2, $3, $4)
@@ -64,12 +65,12 @@ pub async fn vote(
 }
 
 pub async fn remove_vote(
-    pool: &PgPool,
+    pool: &Db,
     poll_id: Uuid,
     user_id: Uuid,
     option_index: i32,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
+    let result = db::query(
         "DELETE FROM poll_votes WHERE poll_id = $1 AND user_id = $2 AND option_index = $3",
     )
     .bind(poll_id)
@@ -80,8 +81,8 @@ pub async fn remove_vote(
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn get_votes(pool: &PgPool, poll_id: Uuid) -> Result<Vec<PollVote>, sqlx::Error> {
-    sqlx::query_as::<_, PollVote>(
+pub async fn get_votes(pool: &Db, poll_id: Uuid) -> Result<Vec<PollVote>, sqlx::Error> {
+    db::query_as::<PollVote>(
         "SELECT * FROM poll_votes WHERE poll_id = $1 ORDER BY option_index ASC",
     )
     .bind(poll_id)
@@ -91,10 +92,10 @@ pub async fn get_votes(pool: &PgPool, poll_id: Uuid) -> Result<Vec<PollVote>, sq
 
 /// Get all votes for a batch of polls in a single query.
 pub async fn get_votes_for_polls(
-    pool: &PgPool,
+    pool: &Db,
     poll_ids: &[Uuid],
 ) -> Result<Vec<PollVote>, sqlx::Error> {
-    sqlx::query_as::<_, PollVote>(
+    db::query_as::<PollVote>(
         "SELECT * FROM poll_votes WHERE poll_id = ANY($1) ORDER BY poll_id, option_index ASC",
     )
     .bind(poll_ids)
@@ -102,16 +103,16 @@ pub async fn get_votes_for_polls(
     .await
 }
 
-pub async fn close(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("UPDATE polls SET closed = true WHERE id = $1 AND closed = false")
+pub async fn close(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("UPDATE polls SET closed = true WHERE id = $1 AND closed = false")
         .bind(id)
         .execute(pool)
         .await?;
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn list_for_channel(pool: &PgPool, channel_id: Uuid) -> Result<Vec<Poll>, sqlx::Error> {
-    sqlx::query_as::<_, Poll>("SELECT * FROM polls WHERE channel_id = $1 ORDER BY created_at DESC")
+pub async fn list_for_channel(pool: &Db, channel_id: Uuid) -> Result<Vec<Poll>, sqlx::Error> {
+    db::query_as::<Poll>("SELECT * FROM polls WHERE channel_id = $1 ORDER BY created_at DESC")
         .bind(channel_id)
         .fetch_all(pool)
         .await
@@ -120,11 +121,11 @@ pub async fn list_for_channel(pool: &PgPool, channel_id: Uuid) -> Result<Vec<Pol
 /// Remove all votes for a user in a poll (for switching single-select votes).
 /// Returns the option indices that were removed.
 pub async fn remove_all_votes_for_user(
-    pool: &PgPool,
+    pool: &Db,
     poll_id: Uuid,
     user_id: Uuid,
 ) -> Result<Vec<i32>, sqlx::Error> {
-    let rows: Vec<(i32,)> = sqlx::query_as(
+    let rows: Vec<(i32,)> = db::query_as(
         "DELETE FROM poll_votes WHERE poll_id = $1 AND user_id = $2 RETURNING option_index",
     )
     .bind(poll_id)
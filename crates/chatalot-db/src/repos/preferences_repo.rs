@@ -1,13 +1,10 @@
-use sqlx::PgPool;
+use crate::db::{self, Backend, Db};
 use uuid::Uuid;
 
 /// Get preferences for a user. Returns empty JSON object if no row exists.
-pub async fn get_preferences(
-    pool: &PgPool,
-    user_id: Uuid,
-) -> Result<serde_json::Value, sqlx::Error> {
+pub async fn get_preferences(pool: &Db, user_id: Uuid) -> Result<serde_json::Value, sqlx::Error> {
     let row: Option<(serde_json::Value,)> =
-        sqlx::query_as("SELECT preferences FROM user_preferences WHERE user_id = $1")
+        db::query_as("SELECT preferences FROM user_preferences WHERE user_id = $1")
             .bind(user_id)
             .fetch_optional(pool)
             .await?;
@@ -17,11 +14,14 @@ pub async fn get_preferences(
 /// Merge partial preferences into existing ones using PostgreSQL's `||` operator.
 /// Uses UPSERT so the first call creates the row.
 pub async fn merge_preferences(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     partial: &serde_json::Value,
 ) -> Result<serde_json::Value, sqlx::Error> {
-    let row: (serde_json::Value,) = sqlx::query_as(
+    if pool.backend() == Backend::Sqlite {
+        return merge_preferences_sqlite(pool, user_id, partial).await;
+    }
+    let row: (serde_json::Value,) = db::query_as(
         r#"
         INSERT INTO user_preferences (user_id, preferences, updated_at)
         VALUES ($1, $2, NOW())
@@ -38,3 +38,39 @@ pub async fn merge_preferences(
     .await?;
     Ok(row.0)
 }
+
+/// SQLite has no shallow JSON merge (`json_patch` recurses and drops nulls),
+/// so merge top-level keys the way `||` does inside a write transaction.
+async fn merge_preferences_sqlite(
+    pool: &Db,
+    user_id: Uuid,
+    partial: &serde_json::Value,
+) -> Result<serde_json::Value, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let existing: Option<serde_json::Value> =
+        db::query_scalar("SELECT preferences FROM user_preferences WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(&mut tx)
+            .await?;
+    let merged = match (existing, partial) {
+        (Some(serde_json::Value::Object(mut current)), serde_json::Value::Object(update)) => {
+            current.extend(update.clone());
+            serde_json::Value::Object(current)
+        }
+        _ => partial.clone(),
+    };
+    db::query(
+        r#"
+        INSERT INTO user_preferences (user_id, preferences, updated_at)
+        VALUES ($1, $2, NOW())
+        ON CONFLICT (user_id)
+        DO UPDATE SET preferences = $2, updated_at = NOW()
+        "#,
+    )
+    .bind(user_id)
+    .bind(&merged)
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+    Ok(merged)
+}
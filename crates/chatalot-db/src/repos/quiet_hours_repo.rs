@@ -0,0 +1,256 @@
+use crate::db::{self, Db};
+use uuid::Uuid;
+
+use crate::models::quiet_hours::{
+    DeferredPush, QuietHoursSchedule, QuietHoursSettings, QuietRecipient,
+};
+
+/// Get a user's quiet hours settings, if they have configured any.
+pub async fn get_settings(
+    pool: &Db,
+    user_id: Uuid,
+) -> Result<Option<QuietHoursSettings>, sqlx::Error> {
+    db::query_as::<QuietHoursSettings>("SELECT * FROM quiet_hours_settings WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// List a user's quiet hours schedules.
+pub async fn list_schedules(
+    pool: &Db,
+    user_id: Uuid,
+) -> Result<Vec<QuietHoursSchedule>, sqlx::Error> {
+    db::query_as::<QuietHoursSchedule>(
+        "SELECT * FROM quiet_hours_schedules WHERE user_id = $1 ORDER BY start_minute",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// List the users whose DMs break through a user's quiet hours.
+pub async fn list_breakthrough(pool: &Db, user_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows: Vec<(Uuid,)> =
+        db::query_as("SELECT allowed_user_id FROM quiet_hours_breakthrough WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
+/// Replace a user's quiet hours configuration: settings, schedules
+/// (`(days, start_minute, end_minute)`) and breakthrough list.
+#[allow(clippy::too_many_arguments)]
+pub async fn replace_config(
+    pool: &Db,
+    user_id: Uuid,
+    enabled: bool,
+    timezone: &str,
+    delivery: &str,
+    auto_dnd: bool,
+    schedules: &[(i16, i16, i16)],
+    breakthrough: &[Uuid],
+) -> Result<QuietHoursSettings, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let settings = db::query_as::<QuietHoursSettings>(
+        r#"
+        INSERT INTO quiet_hours_settings (user_id, enabled, timezone, delivery, auto_dnd, updated_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        ON CONFLICT (user_id) DO UPDATE
+        SET enabled = $2, timezone = $3, delivery = $4, auto_dnd = $5, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(enabled)
+    .bind(timezone)
+    .bind(delivery)
+    .bind(auto_dnd)
+    .fetch_one(&mut tx)
+    .await?;
+
+    db::query("DELETE FROM quiet_hours_schedules WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut tx)
+        .await?;
+    for &(days, start_minute, end_minute) in schedules {
+        db::query(
+            r#"
+            INSERT INTO quiet_hours_schedules (id, user_id, days, start_minute, end_minute)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(user_id)
+        .bind(days)
+        .bind(start_minute)
+        .bind(end_minute)
+        .execute(&mut tx)
+        .await?;
+    }
+
+    db::query("DELETE FROM quiet_hours_breakthrough WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut tx)
+        .await?;
+    db::query(
+        r#"
+        INSERT INTO quiet_hours_breakthrough (user_id, allowed_user_id)
+        SELECT $1, u.id FROM users u WHERE u.id = ANY($2)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(breakthrough)
+    .execute(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(settings)
+}
+
+/// Delete a user's quiet hours configuration entirely.
+pub async fn delete_config(pool: &Db, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for table in ["quiet_hours_schedules", "quiet_hours_breakthrough"] {
+        db::query(format!("DELETE FROM {table} WHERE user_id = $1"))
+            .bind(user_id)
+            .execute(&mut tx)
+            .await?;
+    }
+    let result = db::query("DELETE FROM quiet_hours_settings WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// List enabled quiet hours settings with all of their schedules (for the scheduler).
+pub async fn list_enabled(
+    pool: &Db,
+) -> Result<(Vec<QuietHoursSettings>, Vec<QuietHoursSchedule>), sqlx::Error> {
+    let settings = db::query_as::<QuietHoursSettings>(
+        "SELECT * FROM quiet_hours_settings WHERE enabled OR quiet_active",
+    )
+    .fetch_all(pool)
+    .await?;
+    let schedules = db::query_as::<QuietHoursSchedule>(
+        r#"
+        SELECT s.* FROM quiet_hours_schedules s
+        JOIN quiet_hours_settings qs ON qs.user_id = s.user_id
+        WHERE qs.enabled
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok((settings, schedules))
+}
+
+/// Record whether a user's quiet hours are currently in effect.
+pub async fn set_quiet_active(pool: &Db, user_id: Uuid, active: bool) -> Result<(), sqlx::Error> {
+    db::query("UPDATE quiet_hours_settings SET quiet_active = $2 WHERE user_id = $1")
+        .bind(user_id)
+        .bind(active)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Whether a user should currently appear as Do Not Disturb, either because
+/// they chose it or because scheduled quiet hours switched them automatically.
+pub async fn is_dnd(pool: &Db, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as(
+        r#"
+        SELECT u.status = 'dnd' OR COALESCE(qs.enabled AND qs.quiet_active AND qs.auto_dnd, false)
+        FROM users u
+        LEFT JOIN quiet_hours_settings qs ON qs.user_id = u.id
+        WHERE u.id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+/// Of the given users, those who are in quiet hours or manual DND right now,
+/// with how to handle their notifications and whether `sender_id` breaks through.
+pub async fn get_quiet_recipients(
+    pool: &Db,
+    user_ids: &[Uuid],
+    sender_id: Uuid,
+) -> Result<Vec<QuietRecipient>, sqlx::Error> {
+    db::query_as::<QuietRecipient>(
+        r#"
+        SELECT u.id AS user_id,
+               COALESCE(qs.delivery, 'suppress') AS delivery,
+               EXISTS (
+                   SELECT 1 FROM quiet_hours_breakthrough b
+                   WHERE b.user_id = u.id AND b.allowed_user_id = $2
+               ) AS breakthrough
+        FROM users u
+        LEFT JOIN quiet_hours_settings qs ON qs.user_id = u.id
+        WHERE u.id = ANY($1)
+          AND (u.status = 'dnd' OR COALESCE(qs.enabled AND qs.quiet_active, false))
+        "#,
+    )
+    .bind(user_ids)
+    .bind(sender_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Hold back a notification until the user's quiet hours end. Notifications
+/// are kept per user and channel: another one for the same channel raises
+/// its count, takes the latest sender, and turns a plain message into a
+/// mention.
+pub async fn defer_push(
+    pool: &Db,
+    user_id: Uuid,
+    notification_type: &str,
+    sender_name: &str,
+    channel_id: Uuid,
+    channel_name: &str,
+) -> Result<(), sqlx::Error> {
+    db::query(
+        r#"
+        INSERT INTO deferred_push_notifications
+            (id, user_id, notification_type, sender_name, channel_id, channel_name)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id, channel_id) DO UPDATE
+        SET count = deferred_push_notifications.count + 1,
+            notification_type = CASE
+                WHEN deferred_push_notifications.notification_type = 'message'
+                THEN EXCLUDED.notification_type
+                ELSE deferred_push_notifications.notification_type
+            END,
+            sender_name = EXCLUDED.sender_name,
+            channel_name = EXCLUDED.channel_name,
+            created_at = EXCLUDED.created_at
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(user_id)
+    .bind(notification_type)
+    .bind(sender_name)
+    .bind(channel_id)
+    .bind(channel_name)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Remove and return all notifications deferred for a user, oldest first.
+pub async fn take_deferred(pool: &Db, user_id: Uuid) -> Result<Vec<DeferredPush>, sqlx::Error> {
+    let mut rows = db::query_as::<DeferredPush>(
+        "DELETE FROM deferred_push_notifications WHERE user_id = $1 RETURNING *",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    rows.sort_by_key(|r| r.created_at);
+    Ok(rows)
+}
@@ -1,20 +1,21 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::reaction::Reaction;
 
-/// Add a reaction to a message. Returns the reaction or None if it already exists.
+/// Add a reaction to a message. Fails with `RowNotFound` if the message is
+/// not in the live table (archived messages are read-only).
 pub async fn add_reaction(
-    pool: &PgPool,
+    pool: &Db,
     message_id: Uuid,
     user_id: Uuid,
     emoji: &str,
 ) -> Result<Reaction, sqlx::Error> {
-    sqlx::query_as::<_, Reaction>(
+    db::query_as::<Reaction>(
         r#"
-        INSERT INTO reactions (message_id, user_id, emoji)
-        VALUES ($1, $2, $3)
-        ON CONFLICT (message_id, user_id, emoji) DO UPDATE SET emoji = EXCLUDED.emoji
+        INSERT INTO reactions (message_id, user_id, emoji, message_created_at)
+        SELECT id, $2, $3, created_at FROM messages WHERE id = $1
+        ON CONFLICT (message_id, user_id, emoji, message_created_at) DO UPDATE SET emoji = EXCLUDED.emoji
         RETURNING *
         "#,
     )
@@ -27,13 +28,13 @@ pub async fn add_reaction(
 
 /// Remove a reaction from a message.
 pub async fn remove_reaction(
-    pool: &PgPool,
+    pool: &Db,
     message_id: Uuid,
     user_id: Uuid,
     emoji: &str,
 ) -> Result<bool, sqlx::Error> {
     let result =
-        sqlx::query("DELETE FROM reactions WHERE message_id = $1 AND user_id = $2 AND emoji = $3")
+        db::query("DELETE FROM reactions WHERE message_id = $1 AND user_id = $2 AND emoji = $3")
             .bind(message_id)
             .bind(user_id)
             .bind(emoji)
@@ -43,17 +44,15 @@ pub async fn remove_reaction(
 }
 
 /// Get all reactions for a message.
-pub async fn get_reactions(pool: &PgPool, message_id: Uuid) -> Result<Vec<Reaction>, sqlx::Error> {
-    sqlx::query_as::<_, Reaction>(
-        "SELECT * FROM reactions WHERE message_id = $1 ORDER BY created_at",
-    )
-    .bind(message_id)
-    .fetch_all(pool)
-    .await
+pub async fn get_reactions(pool: &Db, message_id: Uuid) -> Result<Vec<Reaction>, sqlx::Error> {
+    db::query_as::<Reaction>("SELECT * FROM reactions WHERE message_id = $1 ORDER BY created_at")
+        .bind(message_id)
+        .fetch_all(pool)
+        .await
 }
 
 /// Get reaction counts grouped by emoji for a message.
-#[derive(Debug, sqlx::FromRow, serde::Serialize)]
+#[derive(Debug, serde::Serialize)]
 pub struct ReactionCount {
     pub emoji: String,
     pub count: i64,
@@ -61,27 +60,39 @@ pub struct ReactionCount {
 }
 
 pub async fn get_reaction_counts(
-    pool: &PgPool,
+    pool: &Db,
     message_id: Uuid,
 ) -> Result<Vec<ReactionCount>, sqlx::Error> {
-    sqlx::query_as::<_, ReactionCount>(
-        r#"
-        SELECT emoji, COUNT(*) as count, ARRAY_AGG(user_id) as user_ids
-        FROM reactions
-        WHERE message_id = $1
-        GROUP BY emoji
-        ORDER BY MIN(created_at)
-        "#,
+    // Grouped here rather than with ARRAY_AGG so it works on both backends;
+    // rows arrive oldest first, so groups keep the order of their first reaction.
+    let rows: Vec<(String, Uuid)> = db::query_as(
+        "SELECT emoji, user_id FROM reactions WHERE message_id = $1 ORDER BY created_at",
     )
     .bind(message_id)
     .fetch_all(pool)
-    .await
+    .await?;
+
+    let mut counts: Vec<ReactionCount> = Vec::new();
+    for (emoji, user_id) in rows {
+        match counts.iter_mut().find(|c| c.emoji == emoji) {
+            Some(count) => {
+                count.count += 1;
+                count.user_ids.push(user_id);
+            }
+            None => counts.push(ReactionCount {
+                emoji,
+                count: 1,
+                user_ids: vec![user_id],
+            }),
+        }
+    }
+    Ok(counts)
 }
 
 /// Count distinct emoji types on a message.
-pub async fn count_unique_reactions(pool: &PgPool, message_id: Uuid) -> Result<i64, sqlx::Error> {
+pub async fn count_unique_reactions(pool: &Db, message_id: Uuid) -> Result<i64, sqlx::Error> {
     let row: (i64,) =
-        sqlx::query_as("SELECT COUNT(DISTINCT emoji) FROM reactions WHERE message_id = $1")
+        db::query_as("SELECT COUNT(DISTINCT emoji) FROM reactions WHERE message_id = $1")
             .bind(message_id)
             .fetch_one(pool)
             .await?;
@@ -89,7 +100,7 @@ pub async fn count_unique_reactions(pool: &PgPool, message_id: Uuid) -> Result<i
 }
 
 /// Get reactions for multiple messages at once, grouped by message_id and emoji.
-#[derive(Debug, sqlx::FromRow, serde::Serialize)]
+#[derive(Debug, serde::Serialize)]
 pub struct MessageReactionCount {
     pub message_id: Uuid,
     pub emoji: String,
@@ -98,19 +109,49 @@ pub struct MessageReactionCount {
 }
 
 pub async fn get_reactions_for_messages(
-    pool: &PgPool,
+    pool: &Db,
     message_ids: &[Uuid],
 ) -> Result<Vec<MessageReactionCount>, sqlx::Error> {
-    sqlx::query_as::<_, MessageReactionCount>(
+    // Includes archived reactions so history pages keep theirs.
+    let rows: Vec<(Uuid, String, Uuid)> = db::query_as(
         r#"
-        SELECT message_id, emoji, COUNT(*) as count, ARRAY_AGG(user_id) as user_ids
-        FROM reactions
-        WHERE message_id = ANY($1)
-        GROUP BY message_id, emoji
-        ORDER BY message_id, MIN(created_at)
+        SELECT message_id, emoji, user_id FROM (
+            SELECT message_id, emoji, user_id, created_at FROM reactions WHERE message_id = ANY($1)
+            UNION ALL
+            SELECT message_id, emoji, user_id, created_at FROM archived_reactions WHERE message_id = ANY($1)
+        ) r
+        ORDER BY message_id, created_at
         "#,
     )
     .bind(message_ids)
     .fetch_all(pool)
-    .await
+    .await?;
+
+    let mut counts: Vec<MessageReactionCount> = Vec::new();
+    // Index in `counts` where the current message's groups start.
+    let mut message_start = 0;
+    for (message_id, emoji, user_id) in rows {
+        if counts
+            .get(message_start)
+            .is_some_and(|c| c.message_id != message_id)
+        {
+            message_start = counts.len();
+        }
+        match counts[message_start..]
+            .iter_mut()
+            .find(|c| c.emoji == emoji)
+        {
+            Some(count) => {
+                count.count += 1;
+                count.user_ids.push(user_id);
+            }
+            None => counts.push(MessageReactionCount {
+                message_id,
+                emoji,
+                count: 1,
+                user_ids: vec![user_id],
+            }),
+        }
+    }
+    Ok(counts)
 }
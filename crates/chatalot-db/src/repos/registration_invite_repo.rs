@@ -1,18 +1,18 @@
+use crate::db::{self, Db};
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::models::registration_invite::RegistrationInvite;
 
 pub async fn create_invite(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     code: &str,
-    created_by: Uuid,
+    created_by: Option<Uuid>,
     max_uses: Option<i32>,
     expires_at: Option<DateTime<Utc>>,
 ) -> Result<RegistrationInvite, sqlx::Error> {
-    sqlx::query_as::<_, RegistrationInvite>(
+    db::query_as::<RegistrationInvite>(
         r#"
         INSERT INTO registration_invites (id, code, created_by, max_uses, expires_at)
         VALUES ($1, $2, $3, $4, $5)
@@ -31,10 +31,10 @@ pub async fn create_invite(
 /// Atomically validate and consume an invite code.
 /// Returns the invite if valid, None if invalid/expired/exhausted.
 pub async fn validate_and_consume(
-    pool: &PgPool,
+    pool: &Db,
     code: &str,
 ) -> Result<Option<RegistrationInvite>, sqlx::Error> {
-    sqlx::query_as::<_, RegistrationInvite>(
+    db::query_as::<RegistrationInvite>(
         r#"
         UPDATE registration_invites
         SET used_count = used_count + 1
@@ -49,16 +49,16 @@ pub async fn validate_and_consume(
     .await
 }
 
-pub async fn list_invites(pool: &PgPool) -> Result<Vec<RegistrationInvite>, sqlx::Error> {
-    sqlx::query_as::<_, RegistrationInvite>(
+pub async fn list_invites(pool: &Db) -> Result<Vec<RegistrationInvite>, sqlx::Error> {
+    db::query_as::<RegistrationInvite>(
         "SELECT * FROM registration_invites ORDER BY created_at DESC LIMIT 500",
     )
     .fetch_all(pool)
     .await
 }
 
-pub async fn delete_invite(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM registration_invites WHERE id = $1")
+pub async fn delete_invite(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM registration_invites WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
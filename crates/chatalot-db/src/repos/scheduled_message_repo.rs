@@ -1,10 +1,11 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::scheduled_message::ScheduledMessage;
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     channel_id: Uuid,
     user_id: Uuid,
@@ -13,7 +14,7 @@ pub async fn create(
     content_preview: Option<&str>,
     scheduled_for: chrono::DateTime<chrono::Utc>,
 ) -> Result<ScheduledMessage, sqlx::Error> {
-    sqlx::query_as::<_, ScheduledMessage>(
+    db::query_as::<ScheduledMessage>(
         r#"
         INSERT INTO scheduled_messages (id, channel_id, user_id, ciphertext, nonce, content_preview, scheduled_for)
         VALUES ($1, $2, $3, $4, $5, $6, $7)
@@ -31,11 +32,8 @@ pub async fn create(
     .await
 }
 
-pub async fn list_for_user(
-    pool: &PgPool,
-    user_id: Uuid,
-) -> Result<Vec<ScheduledMessage>, sqlx::Error> {
-    sqlx::query_as::<_, ScheduledMessage>(
+pub async fn list_for_user(pool: &Db, user_id: Uuid) -> Result<Vec<ScheduledMessage>, sqlx::Error> {
+    db::query_as::<ScheduledMessage>(
         "SELECT * FROM scheduled_messages WHERE user_id = $1 ORDER BY scheduled_for ASC LIMIT 200",
     )
     .bind(user_id)
@@ -43,16 +41,16 @@ pub async fn list_for_user(
     .await
 }
 
-pub async fn get_due_messages(pool: &PgPool) -> Result<Vec<ScheduledMessage>, sqlx::Error> {
-    sqlx::query_as::<_, ScheduledMessage>(
+pub async fn get_due_messages(pool: &Db) -> Result<Vec<ScheduledMessage>, sqlx::Error> {
+    db::query_as::<ScheduledMessage>(
         "SELECT * FROM scheduled_messages WHERE scheduled_for <= NOW() ORDER BY scheduled_for ASC LIMIT 500",
     )
     .fetch_all(pool)
     .await
 }
 
-pub async fn delete(pool: &PgPool, id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM scheduled_messages WHERE id = $1 AND user_id = $2")
+pub async fn delete(pool: &Db, id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM scheduled_messages WHERE id = $1 AND user_id = $2")
         .bind(id)
         .bind(user_id)
         .execute(pool)
@@ -60,8 +58,8 @@ pub async fn delete(pool: &PgPool, id: Uuid, user_id: Uuid) -> Result<bool, sqlx
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn delete_by_id(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM scheduled_messages WHERE id = $1")
+pub async fn delete_by_id(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM scheduled_messages WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
@@ -1,18 +1,18 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::sender_key::SenderKeyDistributionRow;
 
 /// Upsert a sender key distribution for a user in a channel.
 pub async fn upsert_distribution(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     channel_id: Uuid,
     user_id: Uuid,
     chain_id: i64,
     distribution: &serde_json::Value,
 ) -> Result<SenderKeyDistributionRow, sqlx::Error> {
-    sqlx::query_as::<_, SenderKeyDistributionRow>(
+    db::query_as::<SenderKeyDistributionRow>(
         r#"
         INSERT INTO sender_key_distributions (id, channel_id, user_id, chain_id, distribution)
         VALUES ($1, $2, $3, $4, $5)
@@ -35,10 +35,10 @@ pub async fn upsert_distribution(
 
 /// Fetch all sender key distributions for a channel.
 pub async fn get_channel_distributions(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
 ) -> Result<Vec<SenderKeyDistributionRow>, sqlx::Error> {
-    sqlx::query_as::<_, SenderKeyDistributionRow>(
+    db::query_as::<SenderKeyDistributionRow>(
         "SELECT * FROM sender_key_distributions WHERE channel_id = $1 ORDER BY created_at ASC",
     )
     .bind(channel_id)
@@ -48,12 +48,12 @@ pub async fn get_channel_distributions(
 
 /// Delete a user's sender key distribution in a channel (on member removal).
 pub async fn delete_distribution(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     user_id: Uuid,
 ) -> Result<bool, sqlx::Error> {
     let result =
-        sqlx::query("DELETE FROM sender_key_distributions WHERE channel_id = $1 AND user_id = $2")
+        db::query("DELETE FROM sender_key_distributions WHERE channel_id = $1 AND user_id = $2")
             .bind(channel_id)
             .bind(user_id)
             .execute(pool)
@@ -63,14 +63,14 @@ pub async fn delete_distribution(
 
 /// Delete a user's sender key distributions across multiple channels at once.
 pub async fn delete_distributions_for_channels(
-    pool: &PgPool,
+    pool: &Db,
     channel_ids: &[Uuid],
     user_id: Uuid,
 ) -> Result<u64, sqlx::Error> {
     if channel_ids.is_empty() {
         return Ok(0);
     }
-    let result = sqlx::query(
+    let result = db::query(
         "DELETE FROM sender_key_distributions WHERE channel_id = ANY($1) AND user_id = $2",
     )
     .bind(channel_ids)
@@ -81,8 +81,8 @@ pub async fn delete_distributions_for_channels(
 }
 
 /// Delete ALL sender key distributions for a channel (full rotation).
-pub async fn delete_all_distributions(pool: &PgPool, channel_id: Uuid) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM sender_key_distributions WHERE channel_id = $1")
+pub async fn delete_all_distributions(pool: &Db, channel_id: Uuid) -> Result<u64, sqlx::Error> {
+    let result = db::query("DELETE FROM sender_key_distributions WHERE channel_id = $1")
         .bind(channel_id)
         .execute(pool)
         .await?;
@@ -1,4 +1,4 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 
 #[derive(sqlx::FromRow)]
 pub struct SettingRow {
@@ -6,17 +6,16 @@ pub struct SettingRow {
     pub value: String,
 }
 
-pub async fn get(pool: &PgPool, key: &str) -> Result<Option<String>, sqlx::Error> {
-    let row: Option<(String,)> =
-        sqlx::query_as("SELECT value FROM instance_settings WHERE key = $1")
-            .bind(key)
-            .fetch_optional(pool)
-            .await?;
+pub async fn get(pool: &Db, key: &str) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = db::query_as("SELECT value FROM instance_settings WHERE key = $1")
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
     Ok(row.map(|r| r.0))
 }
 
-pub async fn set(pool: &PgPool, key: &str, value: &str) -> Result<(), sqlx::Error> {
-    sqlx::query(
+pub async fn set(pool: &Db, key: &str, value: &str) -> Result<(), sqlx::Error> {
+    db::query(
         r#"
         INSERT INTO instance_settings (key, value, updated_at)
         VALUES ($1, $2, NOW())
@@ -30,10 +29,8 @@ pub async fn set(pool: &PgPool, key: &str, value: &str) -> Result<(), sqlx::Erro
     Ok(())
 }
 
-pub async fn list_all(pool: &PgPool) -> Result<Vec<SettingRow>, sqlx::Error> {
-    sqlx::query_as::<_, SettingRow>(
-        "SELECT key, value FROM instance_settings ORDER BY key",
-    )
-    .fetch_all(pool)
-    .await
+pub async fn list_all(pool: &Db) -> Result<Vec<SettingRow>, sqlx::Error> {
+    db::query_as::<SettingRow>("SELECT key, value FROM instance_settings ORDER BY key")
+        .fetch_all(pool)
+        .await
 }
@@ -0,0 +1,108 @@
+use crate::db::{self, Db};
+use uuid::Uuid;
+
+use crate::models::storage_quota::StorageQuota;
+
+pub const COMMUNITY: &str = "community";
+pub const GROUP: &str = "group";
+pub const ROLE: &str = "role";
+
+/// Every storage quota.
+pub async fn list_quotas(pool: &Db) -> Result<Vec<StorageQuota>, sqlx::Error> {
+    db::query_as::<StorageQuota>("SELECT * FROM storage_quotas ORDER BY scope_type, scope_id, role")
+        .fetch_all(pool)
+        .await
+}
+
+/// Create or replace a quota. `role` is empty except for `role` quotas.
+pub async fn upsert_quota(
+    pool: &Db,
+    scope_type: &str,
+    scope_id: Uuid,
+    role: &str,
+    max_bytes: i64,
+    updated_by: Uuid,
+) -> Result<StorageQuota, sqlx::Error> {
+    db::query_as::<StorageQuota>(
+        r#"
+        INSERT INTO storage_quotas (scope_type, scope_id, role, max_bytes, updated_by, updated_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        ON CONFLICT (scope_type, scope_id, role) DO UPDATE
+        SET max_bytes = $4, updated_by = $5, updated_at = NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(scope_type)
+    .bind(scope_id)
+    .bind(role)
+    .bind(max_bytes)
+    .bind(updated_by)
+    .fetch_one(pool)
+    .await
+}
+
+/// Remove a quota. Returns whether it existed.
+pub async fn delete_quota(
+    pool: &Db,
+    scope_type: &str,
+    scope_id: Uuid,
+    role: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = db::query(
+        "DELETE FROM storage_quotas WHERE scope_type = $1 AND scope_id = $2 AND role = $3",
+    )
+    .bind(scope_type)
+    .bind(scope_id)
+    .bind(role)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// The quotas an upload by `user_id` into `channel_id` falls under: those of
+/// the channel's group and community, and the override for the user's role
+/// in that community. Channels outside communities have none.
+pub async fn quotas_for_upload(
+    pool: &Db,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<Vec<StorageQuota>, sqlx::Error> {
+    db::query_as::<StorageQuota>(
+        r#"
+        SELECT q.* FROM storage_quotas q
+        JOIN channels c ON c.id = $2
+        JOIN groups g ON g.id = c.group_id
+        WHERE (q.scope_type = 'group' AND q.scope_id = g.id)
+           OR (q.scope_type = 'community' AND q.scope_id = g.community_id)
+           OR (q.scope_type = 'role' AND q.scope_id = g.community_id
+               AND q.role = (SELECT m.role FROM community_members m
+                             WHERE m.community_id = g.community_id AND m.user_id = $1))
+        "#,
+    )
+    .bind(user_id)
+    .bind(channel_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Whether any quota applies to uploads by `user_id` into the channels of a
+/// community they belong to: a community quota, a quota on one of its
+/// groups, or an override for their role in it.
+pub async fn has_quotas_for_member(pool: &Db, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM storage_quotas q
+            JOIN community_members m ON m.user_id = $1
+            WHERE (q.scope_type = 'community' AND q.scope_id = m.community_id)
+               OR (q.scope_type = 'group'
+                   AND q.scope_id IN (SELECT id FROM groups WHERE community_id = m.community_id))
+               OR (q.scope_type = 'role' AND q.scope_id = m.community_id AND q.role = m.role)
+        )
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
@@ -1,11 +1,11 @@
+use crate::db::{self, Db};
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::models::timeout::UserTimeout;
 
 pub async fn create(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     user_id: Uuid,
     channel_id: Uuid,
@@ -14,13 +14,13 @@ pub async fn create(
     expires_at: DateTime<Utc>,
 ) -> Result<UserTimeout, sqlx::Error> {
     // Remove any existing timeout for this user+channel first
-    sqlx::query("DELETE FROM user_timeouts WHERE user_id = $1 AND channel_id = $2")
+    db::query("DELETE FROM user_timeouts WHERE user_id = $1 AND channel_id = $2")
         .bind(user_id)
         .bind(channel_id)
         .execute(pool)
         .await?;
 
-    sqlx::query_as::<_, UserTimeout>(
+    db::query_as::<UserTimeout>(
         r#"
         INSERT INTO user_timeouts (id, user_id, channel_id, issued_by, reason, expires_at)
         VALUES ($1, $2, $3, $4, $5, $6)
@@ -38,11 +38,11 @@ pub async fn create(
 }
 
 pub async fn get_active_timeout(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     channel_id: Uuid,
 ) -> Result<Option<UserTimeout>, sqlx::Error> {
-    sqlx::query_as::<_, UserTimeout>(
+    db::query_as::<UserTimeout>(
         "SELECT * FROM user_timeouts WHERE user_id = $1 AND channel_id = $2 AND expires_at > NOW()",
     )
     .bind(user_id)
@@ -52,11 +52,11 @@ pub async fn get_active_timeout(
 }
 
 pub async fn list_for_user(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     channel_id: Uuid,
 ) -> Result<Vec<UserTimeout>, sqlx::Error> {
-    sqlx::query_as::<_, UserTimeout>(
+    db::query_as::<UserTimeout>(
         "SELECT * FROM user_timeouts WHERE user_id = $1 AND channel_id = $2 ORDER BY created_at DESC",
     )
     .bind(user_id)
@@ -65,8 +65,8 @@ pub async fn list_for_user(
     .await
 }
 
-pub async fn remove(pool: &PgPool, user_id: Uuid, channel_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM user_timeouts WHERE user_id = $1 AND channel_id = $2")
+pub async fn remove(pool: &Db, user_id: Uuid, channel_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM user_timeouts WHERE user_id = $1 AND channel_id = $2")
         .bind(user_id)
         .bind(channel_id)
         .execute(pool)
@@ -74,8 +74,8 @@ pub async fn remove(pool: &PgPool, user_id: Uuid, channel_id: Uuid) -> Result<bo
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn cleanup_expired(pool: &PgPool) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM user_timeouts WHERE expires_at < NOW()")
+pub async fn cleanup_expired(pool: &Db) -> Result<u64, sqlx::Error> {
+    let result = db::query("DELETE FROM user_timeouts WHERE expires_at < NOW()")
         .execute(pool)
         .await?;
     Ok(result.rows_affected())
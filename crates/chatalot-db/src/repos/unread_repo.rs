@@ -1,14 +1,14 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::reaction::ReadCursor;
 
 /// Get all read cursors for members of a channel (for read receipt display).
 pub async fn get_channel_read_cursors(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
 ) -> Result<Vec<ReadCursor>, sqlx::Error> {
-    sqlx::query_as::<_, ReadCursor>(
+    db::query_as::<ReadCursor>(
         r#"
         SELECT rc.*
         FROM read_cursors rc
@@ -22,12 +22,9 @@ pub async fn get_channel_read_cursors(
 }
 
 /// Check if a user has opted out of broadcasting read receipts.
-pub async fn is_read_receipts_disabled(
-    pool: &PgPool,
-    user_id: Uuid,
-) -> Result<bool, sqlx::Error> {
+pub async fn is_read_receipts_disabled(pool: &Db, user_id: Uuid) -> Result<bool, sqlx::Error> {
     let row: Option<(serde_json::Value,)> =
-        sqlx::query_as("SELECT preferences FROM user_preferences WHERE user_id = $1")
+        db::query_as("SELECT preferences FROM user_preferences WHERE user_id = $1")
             .bind(user_id)
             .fetch_optional(pool)
             .await?;
@@ -39,12 +36,12 @@ pub async fn is_read_receipts_disabled(
 
 /// Update the read cursor for a user in a channel.
 pub async fn mark_read(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     channel_id: Uuid,
     message_id: Uuid,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO read_cursors (user_id, channel_id, last_read_message_id, last_read_at)
         VALUES ($1, $2, $3, NOW())
@@ -62,35 +59,32 @@ pub async fn mark_read(
 
 /// Get the read cursor for a user in a channel.
 pub async fn get_read_cursor(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     channel_id: Uuid,
 ) -> Result<Option<ReadCursor>, sqlx::Error> {
-    sqlx::query_as::<_, ReadCursor>(
-        "SELECT * FROM read_cursors WHERE user_id = $1 AND channel_id = $2",
-    )
-    .bind(user_id)
-    .bind(channel_id)
-    .fetch_optional(pool)
-    .await
+    db::query_as::<ReadCursor>("SELECT * FROM read_cursors WHERE user_id = $1 AND channel_id = $2")
+        .bind(user_id)
+        .bind(channel_id)
+        .fetch_optional(pool)
+        .await
 }
 
 /// Count of unread messages in a channel for a user.
-pub async fn count_unread(
-    pool: &PgPool,
-    user_id: Uuid,
-    channel_id: Uuid,
-) -> Result<i64, sqlx::Error> {
+pub async fn count_unread(pool: &Db, user_id: Uuid, channel_id: Uuid) -> Result<i64, sqlx::Error> {
     let cursor = get_read_cursor(pool, user_id, channel_id).await?;
 
     let count: (i64,) = match cursor.and_then(|c| c.last_read_message_id) {
         Some(last_read_id) => {
-            sqlx::query_as(
+            db::query_as(
                 r#"
                 SELECT COUNT(*) FROM messages
                 WHERE channel_id = $1
                   AND deleted_at IS NULL
-                  AND created_at > (SELECT created_at FROM messages WHERE id = $2)
+                  AND created_at > COALESCE(
+                      (SELECT created_at FROM messages WHERE id = $2),
+                      (SELECT created_at FROM archived_messages WHERE id = $2)
+                  )
                 "#,
             )
             .bind(channel_id)
@@ -100,7 +94,7 @@ pub async fn count_unread(
         }
         None => {
             // No cursor = all messages are unread
-            sqlx::query_as(
+            db::query_as(
                 "SELECT COUNT(*) FROM messages WHERE channel_id = $1 AND deleted_at IS NULL",
             )
             .bind(channel_id)
@@ -113,18 +107,21 @@ pub async fn count_unread(
 }
 
 /// Mark all channels as read for a user (set cursor to latest message).
-pub async fn mark_all_read(pool: &PgPool, user_id: Uuid) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query(
+pub async fn mark_all_read(pool: &Db, user_id: Uuid) -> Result<u64, sqlx::Error> {
+    let result = db::query(
         r#"
         INSERT INTO read_cursors (user_id, channel_id, last_read_message_id, last_read_at)
-        SELECT $1, cm.channel_id, latest.id, NOW()
-        FROM channel_members cm
-        JOIN LATERAL (
-            SELECT id FROM messages
-            WHERE channel_id = cm.channel_id AND deleted_at IS NULL
-            ORDER BY created_at DESC LIMIT 1
-        ) latest ON true
-        WHERE cm.user_id = $1
+        SELECT $1, latest.channel_id, latest.id, NOW()
+        FROM (
+            SELECT cm.channel_id, (
+                SELECT id FROM messages
+                WHERE channel_id = cm.channel_id AND deleted_at IS NULL
+                ORDER BY created_at DESC, id DESC LIMIT 1
+            ) AS id
+            FROM channel_members cm
+            WHERE cm.user_id = $1
+        ) latest
+        WHERE latest.id IS NOT NULL
         ON CONFLICT (user_id, channel_id) DO UPDATE
         SET last_read_message_id = EXCLUDED.last_read_message_id, last_read_at = NOW()
         "#,
@@ -140,27 +137,36 @@ pub async fn mark_all_read(pool: &PgPool, user_id: Uuid) -> Result<u64, sqlx::Er
 pub struct ChannelUnreadCount {
     pub channel_id: Uuid,
     pub unread_count: i64,
+    /// Unread messages that mention the user (directly, by role or @everyone).
+    pub mention_count: i64,
 }
 
 pub async fn get_all_unread_counts(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
 ) -> Result<Vec<ChannelUnreadCount>, sqlx::Error> {
-    sqlx::query_as::<_, ChannelUnreadCount>(
+    db::query_as::<ChannelUnreadCount>(
         r#"
         SELECT
             cm.channel_id,
-            COUNT(m.id) AS unread_count
+            COUNT(m.id) AS unread_count,
+            COUNT(mm.message_id) AS mention_count
         FROM channel_members cm
         LEFT JOIN read_cursors rc
             ON rc.user_id = cm.user_id AND rc.channel_id = cm.channel_id
-        LEFT JOIN messages cursor_msg
+        LEFT JOIN (
+            SELECT id, created_at FROM messages
+            UNION ALL
+            SELECT id, created_at FROM archived_messages
+        ) cursor_msg
             ON cursor_msg.id = rc.last_read_message_id
         JOIN messages m
             ON m.channel_id = cm.channel_id
             AND m.deleted_at IS NULL
             AND m.sender_id != $1
             AND m.created_at > COALESCE(cursor_msg.created_at, '1970-01-01T00:00:00Z'::timestamptz)
+        LEFT JOIN message_mentions mm
+            ON mm.message_id = m.id AND mm.user_id = $1
         WHERE cm.user_id = $1
         GROUP BY cm.channel_id
         "#,
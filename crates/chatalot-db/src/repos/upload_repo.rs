@@ -0,0 +1,96 @@
+use crate::db::{self, Db};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::upload::Upload;
+
+#[allow(clippy::too_many_arguments)]
+pub async fn create_upload(
+    pool: &Db,
+    id: Uuid,
+    user_id: Uuid,
+    upload_length: i64,
+    encrypted_name: &str,
+    content_type: Option<&str>,
+    channel_id: Option<Uuid>,
+    expires_at: DateTime<Utc>,
+) -> Result<Upload, sqlx::Error> {
+    db::query_as::<Upload>(
+        r#"
+        INSERT INTO uploads (id, user_id, upload_length, encrypted_name, content_type, channel_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(upload_length)
+    .bind(encrypted_name)
+    .bind(content_type)
+    .bind(channel_id)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await
+}
+
+/// Fetch an upload that has not expired yet.
+pub async fn get_upload(pool: &Db, id: Uuid) -> Result<Option<Upload>, sqlx::Error> {
+    db::query_as::<Upload>("SELECT * FROM uploads WHERE id = $1 AND expires_at > NOW()")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Number of unfinished uploads and the total bytes they will add once complete.
+pub async fn get_pending_for_user(pool: &Db, user_id: Uuid) -> Result<(i64, i64), sqlx::Error> {
+    db::query_as(
+        "SELECT COUNT(*), COALESCE(SUM(upload_length), 0)::BIGINT FROM uploads \
+         WHERE user_id = $1 AND expires_at > NOW()",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Record that the bytes up to `new_offset` are on disk. Only applies if the
+/// stored offset is still `old_offset`, so a concurrent PATCH cannot rewind it.
+pub async fn advance_offset(
+    pool: &Db,
+    id: Uuid,
+    old_offset: i64,
+    new_offset: i64,
+    expires_at: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let result = db::query(
+        "UPDATE uploads SET upload_offset = $3, expires_at = $4 WHERE id = $1 AND upload_offset = $2",
+    )
+    .bind(id)
+    .bind(old_offset)
+    .bind(new_offset)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn delete_upload(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM uploads WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete expired uploads, returning their IDs so the partial files can be removed.
+pub async fn delete_expired(pool: &Db) -> Result<Vec<Uuid>, sqlx::Error> {
+    db::query_scalar("DELETE FROM uploads WHERE expires_at <= NOW() RETURNING id")
+        .fetch_all(pool)
+        .await
+}
+
+/// IDs of every upload row, expired or not.
+pub async fn list_upload_ids(pool: &Db) -> Result<Vec<Uuid>, sqlx::Error> {
+    db::query_scalar("SELECT id FROM uploads")
+        .fetch_all(pool)
+        .await
+}
@@ -1,11 +1,12 @@
-use sqlx::PgPool;
+use crate::db::{self, Backend, Db, TextArray, Tx};
 use uuid::Uuid;
 
 use crate::models::user::{IdentityKey, RefreshToken, User};
 
 /// Create a new user with their identity key in a single transaction.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_user(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     username: &str,
     display_name: &str,
@@ -16,7 +17,7 @@ pub async fn create_user(
 ) -> Result<User, sqlx::Error> {
     let mut tx = pool.begin().await?;
 
-    let user = sqlx::query_as::<_, User>(
+    let user = db::query_as::<User>(
         r#"
         INSERT INTO users (id, username, display_name, email, password_hash)
         VALUES ($1, $2, $3, $4, $5)
@@ -28,10 +29,10 @@ pub async fn create_user(
     .bind(display_name)
     .bind(email)
     .bind(password_hash)
-    .fetch_one(&mut *tx)
+    .fetch_one(&mut tx)
     .await?;
 
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO identity_keys (user_id, identity_key, fingerprint)
         VALUES ($1, $2, $3)
@@ -40,7 +41,7 @@ pub async fn create_user(
     .bind(id)
     .bind(identity_key)
     .bind(fingerprint)
-    .execute(&mut *tx)
+    .execute(&mut tx)
     .await?;
 
     tx.commit().await?;
@@ -48,24 +49,24 @@ pub async fn create_user(
 }
 
 /// Find a user by username.
-pub async fn find_by_username(pool: &PgPool, username: &str) -> Result<Option<User>, sqlx::Error> {
-    sqlx::query_as::<_, User>("SELECT * FROM users WHERE username = $1")
+pub async fn find_by_username(pool: &Db, username: &str) -> Result<Option<User>, sqlx::Error> {
+    db::query_as::<User>("SELECT * FROM users WHERE username = $1")
         .bind(username)
         .fetch_optional(pool)
         .await
 }
 
 /// Find a user by ID.
-pub async fn find_by_id(pool: &PgPool, id: Uuid) -> Result<Option<User>, sqlx::Error> {
-    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+pub async fn find_by_id(pool: &Db, id: Uuid) -> Result<Option<User>, sqlx::Error> {
+    db::query_as::<User>("SELECT * FROM users WHERE id = $1")
         .bind(id)
         .fetch_optional(pool)
         .await
 }
 
 /// Check if a username is already taken.
-pub async fn username_exists(pool: &PgPool, username: &str) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)")
+pub async fn username_exists(pool: &Db, username: &str) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as("SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)")
         .bind(username)
         .fetch_one(pool)
         .await?;
@@ -73,8 +74,8 @@ pub async fn username_exists(pool: &PgPool, username: &str) -> Result<bool, sqlx
 }
 
 /// Check if an email is already taken.
-pub async fn email_exists(pool: &PgPool, email: &str) -> Result<bool, sqlx::Error> {
-    let row: (bool,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)")
+pub async fn email_exists(pool: &Db, email: &str) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)")
         .bind(email)
         .fetch_one(pool)
         .await?;
@@ -83,10 +84,10 @@ pub async fn email_exists(pool: &PgPool, email: &str) -> Result<bool, sqlx::Erro
 
 /// Get a user's identity key.
 pub async fn get_identity_key(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
 ) -> Result<Option<IdentityKey>, sqlx::Error> {
-    sqlx::query_as::<_, IdentityKey>("SELECT * FROM identity_keys WHERE user_id = $1")
+    db::query_as::<IdentityKey>("SELECT * FROM identity_keys WHERE user_id = $1")
         .bind(user_id)
         .fetch_optional(pool)
         .await
@@ -94,7 +95,7 @@ pub async fn get_identity_key(
 
 /// Store a refresh token hash.
 pub async fn create_refresh_token(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     user_id: Uuid,
     token_hash: &[u8],
@@ -102,7 +103,7 @@ pub async fn create_refresh_token(
     ip_address: Option<&str>,
     expires_at: chrono::DateTime<chrono::Utc>,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO refresh_tokens (id, user_id, token_hash, device_name, ip_address, expires_at)
         VALUES ($1, $2, $3, $4, $5, $6)
@@ -121,10 +122,10 @@ pub async fn create_refresh_token(
 
 /// Find a valid (non-revoked, non-expired) refresh token by its hash.
 pub async fn find_refresh_token_by_hash(
-    pool: &PgPool,
+    pool: &Db,
     token_hash: &[u8],
 ) -> Result<Option<RefreshToken>, sqlx::Error> {
-    sqlx::query_as::<_, RefreshToken>(
+    db::query_as::<RefreshToken>(
         r#"
         SELECT * FROM refresh_tokens
         WHERE token_hash = $1
@@ -138,8 +139,8 @@ pub async fn find_refresh_token_by_hash(
 }
 
 /// Revoke a refresh token.
-pub async fn revoke_refresh_token(pool: &PgPool, token_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE refresh_tokens SET revoked_at = NOW() WHERE id = $1")
+pub async fn revoke_refresh_token(pool: &Db, token_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query("UPDATE refresh_tokens SET revoked_at = NOW() WHERE id = $1")
         .bind(token_id)
         .execute(pool)
         .await?;
@@ -148,10 +149,10 @@ pub async fn revoke_refresh_token(pool: &PgPool, token_id: Uuid) -> Result<(), s
 
 /// Find a refresh token by hash within a transaction (for atomic rotation).
 pub async fn find_refresh_token_by_hash_tx(
-    tx: &mut sqlx::PgConnection,
+    tx: &mut Tx,
     token_hash: &[u8],
 ) -> Result<Option<RefreshToken>, sqlx::Error> {
-    sqlx::query_as::<_, RefreshToken>(
+    db::query_as::<RefreshToken>(
         r#"
         SELECT * FROM refresh_tokens
         WHERE token_hash = $1
@@ -166,11 +167,8 @@ pub async fn find_refresh_token_by_hash_tx(
 }
 
 /// Revoke a refresh token within a transaction.
-pub async fn revoke_refresh_token_tx(
-    tx: &mut sqlx::PgConnection,
-    token_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE refresh_tokens SET revoked_at = NOW() WHERE id = $1")
+pub async fn revoke_refresh_token_tx(tx: &mut Tx, token_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query("UPDATE refresh_tokens SET revoked_at = NOW() WHERE id = $1")
         .bind(token_id)
         .execute(&mut *tx)
         .await?;
@@ -179,7 +177,7 @@ pub async fn revoke_refresh_token_tx(
 
 /// Create a refresh token within a transaction.
 pub async fn create_refresh_token_tx(
-    tx: &mut sqlx::PgConnection,
+    tx: &mut Tx,
     id: Uuid,
     user_id: Uuid,
     token_hash: &[u8],
@@ -187,7 +185,7 @@ pub async fn create_refresh_token_tx(
     ip_address: Option<&str>,
     expires_at: chrono::DateTime<chrono::Utc>,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO refresh_tokens (id, user_id, token_hash, device_name, ip_address, expires_at)
         VALUES ($1, $2, $3, $4, $5, $6)
@@ -212,13 +210,9 @@ fn escape_ilike(s: &str) -> String {
 }
 
 /// Search users by username prefix.
-pub async fn search_users(
-    pool: &PgPool,
-    query: &str,
-    limit: i64,
-) -> Result<Vec<User>, sqlx::Error> {
-    sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE (username ILIKE $1 OR display_name ILIKE $1) ORDER BY username ASC LIMIT $2",
+pub async fn search_users(pool: &Db, query: &str, limit: i64) -> Result<Vec<User>, sqlx::Error> {
+    db::query_as::<User>(
+        "SELECT * FROM users WHERE (username ILIKE $1 ESCAPE '\\' OR display_name ILIKE $1 ESCAPE '\\') ORDER BY username ASC LIMIT $2",
     )
     .bind(format!("%{}%", escape_ilike(query)))
     .bind(limit)
@@ -227,12 +221,8 @@ pub async fn search_users(
 }
 
 /// Store a TOTP secret (setup phase, not yet enabled).
-pub async fn set_totp_secret(
-    pool: &PgPool,
-    user_id: Uuid,
-    secret: &[u8],
-) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET totp_secret = $1, updated_at = NOW() WHERE id = $2")
+pub async fn set_totp_secret(pool: &Db, user_id: Uuid, secret: &[u8]) -> Result<(), sqlx::Error> {
+    db::query("UPDATE users SET totp_secret = $1, updated_at = NOW() WHERE id = $2")
         .bind(secret)
         .bind(user_id)
         .execute(pool)
@@ -241,8 +231,8 @@ pub async fn set_totp_secret(
 }
 
 /// Enable TOTP for a user (after successful verification).
-pub async fn enable_totp(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET totp_enabled = true, updated_at = NOW() WHERE id = $1")
+pub async fn enable_totp(pool: &Db, user_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query("UPDATE users SET totp_enabled = true, updated_at = NOW() WHERE id = $1")
         .bind(user_id)
         .execute(pool)
         .await?;
@@ -250,8 +240,8 @@ pub async fn enable_totp(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error
 }
 
 /// Disable TOTP and clear the secret.
-pub async fn disable_totp(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query(
+pub async fn disable_totp(pool: &Db, user_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query(
         "UPDATE users SET totp_enabled = false, totp_secret = NULL, updated_at = NOW() WHERE id = $1",
     )
     .bind(user_id)
@@ -263,12 +253,8 @@ pub async fn disable_totp(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Erro
 // ── Account Management ──
 
 /// Update a user's password hash.
-pub async fn update_password(
-    pool: &PgPool,
-    user_id: Uuid,
-    new_hash: &str,
-) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+pub async fn update_password(pool: &Db, user_id: Uuid, new_hash: &str) -> Result<(), sqlx::Error> {
+    db::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
         .bind(new_hash)
         .bind(user_id)
         .execute(pool)
@@ -277,8 +263,9 @@ pub async fn update_password(
 }
 
 /// Update profile fields (only non-None values are applied).
+#[allow(clippy::too_many_arguments)]
 pub async fn update_profile(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     display_name: Option<&str>,
     avatar_url: Option<Option<&str>>,
@@ -326,7 +313,7 @@ pub async fn update_profile(
         set_clauses.join(", ")
     );
 
-    let mut q = sqlx::query_as::<_, User>(&query).bind(user_id);
+    let mut q = db::query_as::<User>(&query).bind(user_id);
     if let Some(dn) = display_name {
         q = q.bind(dn);
     }
@@ -353,8 +340,8 @@ pub async fn update_profile(
 }
 
 /// Revoke ALL active refresh tokens for a user (server-side logout).
-pub async fn revoke_all_refresh_tokens(pool: &PgPool, user_id: Uuid) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query(
+pub async fn revoke_all_refresh_tokens(pool: &Db, user_id: Uuid) -> Result<u64, sqlx::Error> {
+    let result = db::query(
         "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
     )
     .bind(user_id)
@@ -365,10 +352,10 @@ pub async fn revoke_all_refresh_tokens(pool: &PgPool, user_id: Uuid) -> Result<u
 
 /// List all active (non-revoked, non-expired) sessions for a user.
 pub async fn list_active_sessions(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
 ) -> Result<Vec<RefreshToken>, sqlx::Error> {
-    sqlx::query_as::<_, RefreshToken>(
+    db::query_as::<RefreshToken>(
         r#"
         SELECT * FROM refresh_tokens
         WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
@@ -381,8 +368,8 @@ pub async fn list_active_sessions(
 }
 
 /// Delete a user (FK cascades handle tokens, memberships, keys).
-pub async fn delete_user(pool: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM users WHERE id = $1")
+pub async fn delete_user(pool: &Db, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM users WHERE id = $1")
         .bind(user_id)
         .execute(pool)
         .await?;
@@ -392,16 +379,16 @@ pub async fn delete_user(pool: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Err
 // ── Admin ──
 
 /// Count total users.
-pub async fn count_users(pool: &PgPool) -> Result<i64, sqlx::Error> {
-    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
+pub async fn count_users(pool: &Db) -> Result<i64, sqlx::Error> {
+    let row: (i64,) = db::query_as("SELECT COUNT(*) FROM users")
         .fetch_one(pool)
         .await?;
     Ok(row.0)
 }
 
 /// Set a user's admin flag.
-pub async fn set_admin(pool: &PgPool, user_id: Uuid, is_admin: bool) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET is_admin = $1, updated_at = NOW() WHERE id = $2")
+pub async fn set_admin(pool: &Db, user_id: Uuid, is_admin: bool) -> Result<(), sqlx::Error> {
+    db::query("UPDATE users SET is_admin = $1, updated_at = NOW() WHERE id = $2")
         .bind(is_admin)
         .bind(user_id)
         .execute(pool)
@@ -410,8 +397,8 @@ pub async fn set_admin(pool: &PgPool, user_id: Uuid, is_admin: bool) -> Result<(
 }
 
 /// Set a user's instance owner flag.
-pub async fn set_owner(pool: &PgPool, user_id: Uuid, is_owner: bool) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET is_owner = $1, updated_at = NOW() WHERE id = $2")
+pub async fn set_owner(pool: &Db, user_id: Uuid, is_owner: bool) -> Result<(), sqlx::Error> {
+    db::query("UPDATE users SET is_owner = $1, updated_at = NOW() WHERE id = $2")
         .bind(is_owner)
         .bind(user_id)
         .execute(pool)
@@ -421,17 +408,16 @@ pub async fn set_owner(pool: &PgPool, user_id: Uuid, is_owner: bool) -> Result<(
 
 /// Ensure a user is admin by username (for env-var seeding).
 /// Check if any admin user exists.
-pub async fn has_any_admin(pool: &PgPool) -> Result<bool, sqlx::Error> {
-    let row: (bool,) =
-        sqlx::query_as("SELECT EXISTS(SELECT 1 FROM users WHERE is_admin = true)")
-            .fetch_one(pool)
-            .await?;
+pub async fn has_any_admin(pool: &Db) -> Result<bool, sqlx::Error> {
+    let row: (bool,) = db::query_as("SELECT EXISTS(SELECT 1 FROM users WHERE is_admin = true)")
+        .fetch_one(pool)
+        .await?;
     Ok(row.0)
 }
 
-pub async fn ensure_admin(pool: &PgPool, username: &str) -> Result<bool, sqlx::Error> {
+pub async fn ensure_admin(pool: &Db, username: &str) -> Result<bool, sqlx::Error> {
     let result =
-        sqlx::query("UPDATE users SET is_admin = true WHERE username = $1 AND is_admin = false")
+        db::query("UPDATE users SET is_admin = true WHERE username = $1 AND is_admin = false")
             .bind(username)
             .execute(pool)
             .await?;
@@ -440,11 +426,11 @@ pub async fn ensure_admin(pool: &PgPool, username: &str) -> Result<bool, sqlx::E
 
 /// Suspend a user.
 pub async fn suspend_user(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     reason: Option<&str>,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
+    db::query(
         "UPDATE users SET suspended_at = NOW(), suspended_reason = $1, updated_at = NOW() WHERE id = $2",
     )
     .bind(reason)
@@ -455,8 +441,8 @@ pub async fn suspend_user(
 }
 
 /// Unsuspend a user.
-pub async fn unsuspend_user(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query(
+pub async fn unsuspend_user(pool: &Db, user_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query(
         "UPDATE users SET suspended_at = NULL, suspended_reason = NULL, updated_at = NOW() WHERE id = $1",
     )
     .bind(user_id)
@@ -466,26 +452,25 @@ pub async fn unsuspend_user(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Er
 }
 
 /// Get all currently suspended user IDs (for populating in-memory set on startup).
-pub async fn get_suspended_user_ids(pool: &PgPool) -> Result<Vec<Uuid>, sqlx::Error> {
-    let rows: Vec<(Uuid,)> =
-        sqlx::query_as("SELECT id FROM users WHERE suspended_at IS NOT NULL")
-            .fetch_all(pool)
-            .await?;
+pub async fn get_suspended_user_ids(pool: &Db) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows: Vec<(Uuid,)> = db::query_as("SELECT id FROM users WHERE suspended_at IS NOT NULL")
+        .fetch_all(pool)
+        .await?;
     Ok(rows.into_iter().map(|r| r.0).collect())
 }
 
 /// List all users (paginated, with optional search).
 pub async fn list_all_users(
-    pool: &PgPool,
+    pool: &Db,
     search: Option<&str>,
     limit: i64,
     offset: i64,
 ) -> Result<Vec<User>, sqlx::Error> {
     if let Some(q) = search {
-        sqlx::query_as::<_, User>(
+        db::query_as::<User>(
             r#"
             SELECT * FROM users
-            WHERE username ILIKE $1 OR display_name ILIKE $1 OR email ILIKE $1
+            WHERE username ILIKE $1 ESCAPE '\' OR display_name ILIKE $1 ESCAPE '\' OR email ILIKE $1 ESCAPE '\'
             ORDER BY created_at DESC
             LIMIT $2 OFFSET $3
             "#,
@@ -496,7 +481,7 @@ pub async fn list_all_users(
         .fetch_all(pool)
         .await
     } else {
-        sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY created_at DESC LIMIT $1 OFFSET $2")
+        db::query_as::<User>("SELECT * FROM users ORDER BY created_at DESC LIMIT $1 OFFSET $2")
             .bind(limit)
             .bind(offset)
             .fetch_all(pool)
@@ -505,9 +490,9 @@ pub async fn list_all_users(
 }
 
 /// Get a user's current upload bytes used.
-pub async fn get_upload_bytes_used(pool: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+pub async fn get_upload_bytes_used(pool: &Db, user_id: Uuid) -> Result<i64, sqlx::Error> {
     let row: (i64,) =
-        sqlx::query_as("SELECT COALESCE(upload_bytes_used, 0) FROM users WHERE id = $1")
+        db::query_as("SELECT COALESCE(upload_bytes_used, 0) FROM users WHERE id = $1")
             .bind(user_id)
             .fetch_one(pool)
             .await?;
@@ -516,11 +501,11 @@ pub async fn get_upload_bytes_used(pool: &PgPool, user_id: Uuid) -> Result<i64,
 
 /// Increment a user's upload bytes used.
 pub async fn increment_upload_bytes(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     bytes: i64,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET upload_bytes_used = upload_bytes_used + $2 WHERE id = $1")
+    db::query("UPDATE users SET upload_bytes_used = upload_bytes_used + $2 WHERE id = $1")
         .bind(user_id)
         .bind(bytes)
         .execute(pool)
@@ -530,12 +515,12 @@ pub async fn increment_upload_bytes(
 
 /// Decrement a user's upload bytes used (on file deletion).
 pub async fn decrement_upload_bytes(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     bytes: i64,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
-        "UPDATE users SET upload_bytes_used = GREATEST(upload_bytes_used - $2, 0) WHERE id = $1",
+    db::query(
+        "UPDATE users SET upload_bytes_used = CASE WHEN upload_bytes_used > $2 THEN upload_bytes_used - $2 ELSE 0 END WHERE id = $1",
     )
     .bind(user_id)
     .bind(bytes)
@@ -546,7 +531,7 @@ pub async fn decrement_upload_bytes(
 
 /// Insert an audit log entry.
 pub async fn insert_audit_log(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     user_id: Option<Uuid>,
     action: &str,
@@ -554,7 +539,7 @@ pub async fn insert_audit_log(
     user_agent: Option<&str>,
     metadata: Option<serde_json::Value>,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query(
+    db::query(
         r#"
         INSERT INTO audit_log (id, user_id, action, ip_address, user_agent, metadata)
         VALUES ($1, $2, $3, $4, $5, $6)
@@ -575,11 +560,11 @@ pub async fn insert_audit_log(
 
 /// Store a hashed recovery code for a user.
 pub async fn set_recovery_code_hash(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     hash: &str,
 ) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET recovery_code_hash = $1 WHERE id = $2")
+    db::query("UPDATE users SET recovery_code_hash = $1 WHERE id = $2")
         .bind(hash)
         .bind(user_id)
         .execute(pool)
@@ -589,14 +574,15 @@ pub async fn set_recovery_code_hash(
 
 /// Get a user's ID and recovery code hash by username (for account recovery).
 pub async fn get_user_for_recovery(
-    pool: &PgPool,
+    pool: &Db,
     username: &str,
 ) -> Result<Option<(Uuid, Option<String>)>, sqlx::Error> {
-    let row: Option<(Uuid, Option<String>)> =
-        sqlx::query_as("SELECT id, recovery_code_hash FROM users WHERE username = $1 AND suspended_at IS NULL")
-            .bind(username)
-            .fetch_optional(pool)
-            .await?;
+    let row: Option<(Uuid, Option<String>)> = db::query_as(
+        "SELECT id, recovery_code_hash FROM users WHERE username = $1 AND suspended_at IS NULL",
+    )
+    .bind(username)
+    .fetch_optional(pool)
+    .await?;
     Ok(row)
 }
 
@@ -604,12 +590,12 @@ pub async fn get_user_for_recovery(
 
 /// Store hashed TOTP backup codes for a user.
 pub async fn set_totp_backup_codes(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     hashed_codes: &[String],
 ) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE users SET totp_backup_codes = $1 WHERE id = $2")
-        .bind(hashed_codes)
+    db::query("UPDATE users SET totp_backup_codes = $1 WHERE id = $2")
+        .bind(TextArray(hashed_codes.to_vec()))
         .bind(user_id)
         .execute(pool)
         .await?;
@@ -618,29 +604,59 @@ pub async fn set_totp_backup_codes(
 
 /// Get TOTP backup codes for a user.
 pub async fn get_totp_backup_codes(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
 ) -> Result<Option<Vec<String>>, sqlx::Error> {
-    let row: Option<(Option<Vec<String>>,)> =
-        sqlx::query_as("SELECT totp_backup_codes FROM users WHERE id = $1")
+    let row: Option<(Option<TextArray>,)> =
+        db::query_as("SELECT totp_backup_codes FROM users WHERE id = $1")
             .bind(user_id)
             .fetch_optional(pool)
             .await?;
-    Ok(row.and_then(|r| r.0))
+    Ok(row.and_then(|r| r.0).map(|codes| codes.0))
 }
 
 /// Remove a used backup code from the array.
 pub async fn consume_totp_backup_code(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     code_hash: &str,
 ) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
-        "UPDATE users SET totp_backup_codes = array_remove(totp_backup_codes, $1) WHERE id = $2 AND $1 = ANY(totp_backup_codes)",
-    )
-    .bind(code_hash)
-    .bind(user_id)
-    .execute(pool)
-    .await?;
+    let sql = match pool.backend() {
+        Backend::Postgres => {
+            "UPDATE users SET totp_backup_codes = array_remove(totp_backup_codes, $1) WHERE id = $2 AND $1 = ANY(totp_backup_codes)"
+        }
+        Backend::Sqlite => {
+            r#"
+            UPDATE users
+            SET totp_backup_codes = (SELECT json_group_array(value) FROM json_each(totp_backup_codes) WHERE value <> $1)
+            WHERE id = $2 AND EXISTS (SELECT 1 FROM json_each(totp_backup_codes) WHERE value = $1)
+            "#
+        }
+    };
+    let result = db::query(sql)
+        .bind(code_hash)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
     Ok(result.rows_affected() > 0)
 }
+
+/// Record a self-chosen DND or invisible presence, which push delivery and
+/// scheduled quiet hours must respect. Any other status clears it.
+pub async fn set_presence_override(
+    pool: &Db,
+    user_id: Uuid,
+    status: &str,
+) -> Result<(), sqlx::Error> {
+    let status = if matches!(status, "dnd" | "invisible") {
+        status
+    } else {
+        "online"
+    };
+    db::query("UPDATE users SET status = $1 WHERE id = $2 AND status <> $1")
+        .bind(status)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use sqlx::PgPool;
+use crate::db::{self, Backend, Db};
 use uuid::Uuid;
 
 use crate::models::voice::VoiceSession;
@@ -7,12 +7,38 @@ use crate::models::voice::VoiceSession;
 /// Uses a CTE to atomically check-then-insert, avoiding TOCTOU races
 /// where two concurrent callers could create duplicate sessions.
 pub async fn get_or_create_session(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
     started_by: Uuid,
 ) -> Result<VoiceSession, sqlx::Error> {
     let id = Uuid::now_v7();
-    sqlx::query_as::<_, VoiceSession>(
+    if pool.backend() == Backend::Sqlite {
+        // No data-modifying CTEs on SQLite, but its transactions hold the
+        // write lock from the start, so check-then-insert is already atomic.
+        let mut tx = pool.begin().await?;
+        let existing = db::query_as::<VoiceSession>(
+            "SELECT * FROM voice_sessions WHERE channel_id = $1 AND ended_at IS NULL ORDER BY started_at DESC LIMIT 1",
+        )
+        .bind(channel_id)
+        .fetch_optional(&mut tx)
+        .await?;
+        let session = match existing {
+            Some(session) => session,
+            None => {
+                db::query_as::<VoiceSession>(
+                    "INSERT INTO voice_sessions (id, channel_id, started_by) VALUES ($1, $2, $3) RETURNING *",
+                )
+                .bind(id)
+                .bind(channel_id)
+                .bind(started_by)
+                .fetch_one(&mut tx)
+                .await?
+            }
+        };
+        tx.commit().await?;
+        return Ok(session);
+    }
+    db::query_as::<VoiceSession>(
         r#"
         WITH existing AS (
             SELECT * FROM voice_sessions
@@ -40,10 +66,10 @@ pub async fn get_or_create_session(
 
 /// Get the active voice session for a channel (if any).
 pub async fn get_active_session(
-    pool: &PgPool,
+    pool: &Db,
     channel_id: Uuid,
 ) -> Result<Option<VoiceSession>, sqlx::Error> {
-    sqlx::query_as::<_, VoiceSession>(
+    db::query_as::<VoiceSession>(
         "SELECT * FROM voice_sessions WHERE channel_id = $1 AND ended_at IS NULL ORDER BY started_at DESC LIMIT 1",
     )
     .bind(channel_id)
@@ -53,12 +79,8 @@ pub async fn get_active_session(
 
 /// Add a participant to a voice session.
 /// Returns `false` if the session already has 25 active participants (cap enforced atomically).
-pub async fn join_session(
-    pool: &PgPool,
-    session_id: Uuid,
-    user_id: Uuid,
-) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query(
+pub async fn join_session(pool: &Db, session_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query(
         r#"
         INSERT INTO voice_session_participants (session_id, user_id)
         VALUES ($1, $2)
@@ -84,12 +106,8 @@ pub async fn join_session(
 }
 
 /// Remove a participant from a voice session.
-pub async fn leave_session(
-    pool: &PgPool,
-    session_id: Uuid,
-    user_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query(
+pub async fn leave_session(pool: &Db, session_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query(
         "UPDATE voice_session_participants SET left_at = NOW() WHERE session_id = $1 AND user_id = $2 AND left_at IS NULL",
     )
     .bind(session_id)
@@ -100,8 +118,8 @@ pub async fn leave_session(
 }
 
 /// Get current participants in a voice session.
-pub async fn get_participants(pool: &PgPool, session_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
-    let rows: Vec<(Uuid,)> = sqlx::query_as(
+pub async fn get_participants(pool: &Db, session_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows: Vec<(Uuid,)> = db::query_as(
         "SELECT user_id FROM voice_session_participants WHERE session_id = $1 AND left_at IS NULL",
     )
     .bind(session_id)
@@ -113,19 +131,18 @@ pub async fn get_participants(pool: &PgPool, session_id: Uuid) -> Result<Vec<Uui
 /// Remove a user from all active voice sessions they're in.
 /// Returns (session_id, channel_id) pairs for each session they were removed from.
 pub async fn leave_all_sessions(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
 ) -> Result<Vec<(Uuid, Uuid)>, sqlx::Error> {
-    let rows: Vec<(Uuid, Uuid)> = sqlx::query_as(
+    let rows: Vec<(Uuid, Uuid)> = db::query_as(
         r#"
-        UPDATE voice_session_participants vsp
+        UPDATE voice_session_participants
         SET left_at = NOW()
-        FROM voice_sessions vs
-        WHERE vsp.session_id = vs.id
-          AND vsp.user_id = $1
-          AND vsp.left_at IS NULL
-          AND vs.ended_at IS NULL
-        RETURNING vs.id, vs.channel_id
+        WHERE user_id = $1
+          AND left_at IS NULL
+          AND session_id IN (SELECT id FROM voice_sessions WHERE ended_at IS NULL)
+        RETURNING session_id,
+            (SELECT vs.channel_id FROM voice_sessions vs WHERE vs.id = voice_session_participants.session_id)
         "#,
     )
     .bind(user_id)
@@ -138,21 +155,20 @@ pub async fn leave_all_sessions(
 /// Used by the grace period cleanup: if the user rejoined after the cutoff,
 /// their new session record is preserved.
 pub async fn leave_sessions_joined_before(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     cutoff: chrono::DateTime<chrono::Utc>,
 ) -> Result<Vec<(Uuid, Uuid)>, sqlx::Error> {
-    let rows: Vec<(Uuid, Uuid)> = sqlx::query_as(
+    let rows: Vec<(Uuid, Uuid)> = db::query_as(
         r#"
-        UPDATE voice_session_participants vsp
+        UPDATE voice_session_participants
         SET left_at = NOW()
-        FROM voice_sessions vs
-        WHERE vsp.session_id = vs.id
-          AND vsp.user_id = $1
-          AND vsp.left_at IS NULL
-          AND vsp.joined_at <= $2
-          AND vs.ended_at IS NULL
-        RETURNING vs.id, vs.channel_id
+        WHERE user_id = $1
+          AND left_at IS NULL
+          AND joined_at <= $2
+          AND session_id IN (SELECT id FROM voice_sessions WHERE ended_at IS NULL)
+        RETURNING session_id,
+            (SELECT vs.channel_id FROM voice_sessions vs WHERE vs.id = voice_session_participants.session_id)
         "#,
     )
     .bind(user_id)
@@ -164,11 +180,11 @@ pub async fn leave_sessions_joined_before(
 
 /// Check if two users are in the same active voice session.
 pub async fn are_in_same_session(
-    pool: &PgPool,
+    pool: &Db,
     user_a: Uuid,
     user_b: Uuid,
 ) -> Result<bool, sqlx::Error> {
-    let row: Option<(i32,)> = sqlx::query_as(
+    let row: Option<(i32,)> = db::query_as(
         r#"
         SELECT 1 FROM voice_session_participants a
         JOIN voice_session_participants b ON a.session_id = b.session_id
@@ -187,8 +203,8 @@ pub async fn are_in_same_session(
 }
 
 /// End a voice session (when last participant leaves).
-pub async fn end_session(pool: &PgPool, session_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query("UPDATE voice_sessions SET ended_at = NOW() WHERE id = $1")
+pub async fn end_session(pool: &Db, session_id: Uuid) -> Result<(), sqlx::Error> {
+    db::query("UPDATE voice_sessions SET ended_at = NOW() WHERE id = $1")
         .bind(session_id)
         .execute(pool)
         .await?;
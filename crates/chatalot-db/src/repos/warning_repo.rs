@@ -1,17 +1,17 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::warning::Warning;
 
 pub async fn create(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     user_id: Uuid,
     channel_id: Uuid,
     issued_by: Uuid,
     reason: &str,
 ) -> Result<Warning, sqlx::Error> {
-    sqlx::query_as::<_, Warning>(
+    db::query_as::<Warning>(
         r#"
         INSERT INTO warnings (id, user_id, channel_id, issued_by, reason)
         VALUES ($1, $2, $3, $4, $5)
@@ -28,11 +28,11 @@ pub async fn create(
 }
 
 pub async fn list_for_user(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     channel_id: Uuid,
 ) -> Result<Vec<Warning>, sqlx::Error> {
-    sqlx::query_as::<_, Warning>(
+    db::query_as::<Warning>(
         "SELECT * FROM warnings WHERE user_id = $1 AND channel_id = $2 ORDER BY created_at DESC",
     )
     .bind(user_id)
@@ -42,12 +42,12 @@ pub async fn list_for_user(
 }
 
 pub async fn count_for_user_in_channel(
-    pool: &PgPool,
+    pool: &Db,
     user_id: Uuid,
     channel_id: Uuid,
 ) -> Result<i64, sqlx::Error> {
     let row: (i64,) =
-        sqlx::query_as("SELECT COUNT(*) FROM warnings WHERE user_id = $1 AND channel_id = $2")
+        db::query_as("SELECT COUNT(*) FROM warnings WHERE user_id = $1 AND channel_id = $2")
             .bind(user_id)
             .bind(channel_id)
             .fetch_one(pool)
@@ -55,8 +55,8 @@ pub async fn count_for_user_in_channel(
     Ok(row.0)
 }
 
-pub async fn delete(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM warnings WHERE id = $1")
+pub async fn delete(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM warnings WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
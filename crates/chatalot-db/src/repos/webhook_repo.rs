@@ -1,10 +1,10 @@
-use sqlx::PgPool;
+use crate::db::{self, Db};
 use uuid::Uuid;
 
 use crate::models::webhook::Webhook;
 
 pub async fn create(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     channel_id: Uuid,
     name: &str,
@@ -12,7 +12,7 @@ pub async fn create(
     created_by: Uuid,
     avatar_url: Option<&str>,
 ) -> Result<Webhook, sqlx::Error> {
-    sqlx::query_as::<_, Webhook>(
+    db::query_as::<Webhook>(
         r#"
         INSERT INTO webhooks (id, channel_id, name, token, created_by, avatar_url)
         VALUES ($1, $2, $3, $4, $5, $6)
@@ -29,33 +29,28 @@ pub async fn create(
     .await
 }
 
-pub async fn get_by_token(pool: &PgPool, token: &str) -> Result<Option<Webhook>, sqlx::Error> {
-    sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE token = $1 AND active = true")
+pub async fn get_by_token(pool: &Db, token: &str) -> Result<Option<Webhook>, sqlx::Error> {
+    db::query_as::<Webhook>("SELECT * FROM webhooks WHERE token = $1 AND active = true")
         .bind(token)
         .fetch_optional(pool)
         .await
 }
 
-pub async fn list_for_channel(
-    pool: &PgPool,
-    channel_id: Uuid,
-) -> Result<Vec<Webhook>, sqlx::Error> {
-    sqlx::query_as::<_, Webhook>(
-        "SELECT * FROM webhooks WHERE channel_id = $1 ORDER BY created_at ASC",
-    )
-    .bind(channel_id)
-    .fetch_all(pool)
-    .await
+pub async fn list_for_channel(pool: &Db, channel_id: Uuid) -> Result<Vec<Webhook>, sqlx::Error> {
+    db::query_as::<Webhook>("SELECT * FROM webhooks WHERE channel_id = $1 ORDER BY created_at ASC")
+        .bind(channel_id)
+        .fetch_all(pool)
+        .await
 }
 
 pub async fn update(
-    pool: &PgPool,
+    pool: &Db,
     id: Uuid,
     name: Option<&str>,
     avatar_url: Option<Option<&str>>,
     active: Option<bool>,
 ) -> Result<Option<Webhook>, sqlx::Error> {
-    sqlx::query_as::<_, Webhook>(
+    db::query_as::<Webhook>(
         r#"
         UPDATE webhooks
         SET name = COALESCE($2, name),
@@ -73,39 +68,33 @@ pub async fn update(
     .await
 }
 
-pub async fn delete(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM webhooks WHERE id = $1")
+pub async fn delete(pool: &Db, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = db::query("DELETE FROM webhooks WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
     Ok(result.rows_affected() > 0)
 }
 
-pub async fn get_by_id(pool: &PgPool, id: Uuid) -> Result<Option<Webhook>, sqlx::Error> {
-    sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = $1")
+pub async fn get_by_id(pool: &Db, id: Uuid) -> Result<Option<Webhook>, sqlx::Error> {
+    db::query_as::<Webhook>("SELECT * FROM webhooks WHERE id = $1")
         .bind(id)
         .fetch_optional(pool)
         .await
 }
 
 /// List all webhooks across all channels (for admin overview).
-pub async fn list_all(
-    pool: &PgPool,
-    limit: i64,
-    offset: i64,
-) -> Result<Vec<Webhook>, sqlx::Error> {
-    sqlx::query_as::<_, Webhook>(
-        "SELECT * FROM webhooks ORDER BY created_at DESC LIMIT $1 OFFSET $2",
-    )
-    .bind(limit)
-    .bind(offset)
-    .fetch_all(pool)
-    .await
+pub async fn list_all(pool: &Db, limit: i64, offset: i64) -> Result<Vec<Webhook>, sqlx::Error> {
+    db::query_as::<Webhook>("SELECT * FROM webhooks ORDER BY created_at DESC LIMIT $1 OFFSET $2")
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
 }
 
 /// Count all webhooks (for admin pagination).
-pub async fn count_all(pool: &PgPool) -> Result<i64, sqlx::Error> {
-    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM webhooks")
+pub async fn count_all(pool: &Db) -> Result<i64, sqlx::Error> {
+    let row: (i64,) = db::query_as("SELECT COUNT(*) FROM webhooks")
         .fetch_one(pool)
         .await?;
     Ok(row.0)
@@ -0,0 +1,37 @@
+use crate::db::{self, Db};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::ws_event::WsEvent;
+
+pub async fn insert(pool: &Db, id: Uuid, event: &str) -> Result<(), sqlx::Error> {
+    db::query("INSERT INTO ws_events (id, event) VALUES ($1, $2)")
+        .bind(id)
+        .bind(event)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Events recorded after `since`, oldest first.
+pub async fn list_since(pool: &Db, since: DateTime<Utc>) -> Result<Vec<WsEvent>, sqlx::Error> {
+    db::query_as::<WsEvent>(
+        "SELECT * FROM ws_events WHERE created_at > $1 ORDER BY created_at ASC LIMIT 1000",
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await
+}
+
+/// Drop events older than `max_age_secs`.
+pub async fn delete_older_than(pool: &Db, max_age_secs: i64) -> Result<u64, sqlx::Error> {
+    let sql = format!(
+        "DELETE FROM ws_events WHERE created_at < {}",
+        pool.backend().now_plus_secs("$1"),
+    );
+    let result = db::query(sql)
+        .bind(-max_age_secs as f64)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,1190 @@
+//! Repository tests run against every configured backend: always an
+//! in-memory SQLite database, plus Postgres when `TEST_DATABASE_URL` is set.
+//! Rows are keyed by fresh UUIDs so a shared Postgres database can be reused.
+
+use chatalot_db::db::{self, Backend, Db};
+use chatalot_db::models::channel::ChannelType;
+use chatalot_db::repos::{
+    blocked_hash_repo, bookmark_repo, channel_repo, community_repo, delivery_repo, file_repo,
+    file_retention_repo, group_repo, job_repo, message_archive_repo, message_repo,
+    notification_repo, preferences_repo, quiet_hours_repo, reaction_repo, storage_quota_repo,
+    unread_repo, upload_repo, user_repo, voice_repo, ws_event_repo,
+};
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use uuid::Uuid;
+
+async fn databases() -> Vec<Db> {
+    let mut urls = vec!["sqlite::memory:".to_string()];
+    if let Ok(url) = std::env::var("TEST_DATABASE_URL") {
+        urls.push(url);
+    }
+    let mut dbs = Vec::new();
+    for url in urls {
+        let db = chatalot_db::pool::create_pool(&url).await.unwrap();
+        chatalot_db::pool::run_migrations(&db).await.unwrap();
+        dbs.push(db);
+    }
+    dbs
+}
+
+fn short(id: Uuid) -> String {
+    id.simple().to_string()[..12].to_string()
+}
+
+async fn user(db: &Db, name: &str) -> Uuid {
+    let id = Uuid::now_v7();
+    let username = format!("{name}_{}", short(id));
+    user_repo::create_user(
+        db,
+        id,
+        &username,
+        name,
+        &format!("{username}@example.com"),
+        "hash",
+        &[7u8; 32],
+        &short(id),
+    )
+    .await
+    .unwrap();
+    id
+}
+
+async fn channel(db: &Db, owner: Uuid) -> Uuid {
+    let id = Uuid::now_v7();
+    channel_repo::create_channel(
+        db,
+        id,
+        "general",
+        ChannelType::Text,
+        None,
+        owner,
+        None,
+        false,
+    )
+    .await
+    .unwrap();
+    id
+}
+
+async fn message(db: &Db, channel_id: Uuid, sender: Uuid, text: &str) -> Uuid {
+    let id = Uuid::now_v7();
+    message_repo::create_message(
+        db,
+        id,
+        channel_id,
+        sender,
+        text.as_bytes(),
+        &[0u8; 12],
+        "text",
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    id
+}
+
+fn no_filters() -> message_repo::SearchFilters {
+    message_repo::SearchFilters {
+        sender: None,
+        before: None,
+        after: None,
+        has_file: None,
+    }
+}
+
+#[tokio::test]
+async fn users_round_trip_and_search_escapes_wildcards() {
+    for db in databases().await {
+        let id = user(&db, "alice").await;
+        let found = user_repo::find_by_id(&db, id).await.unwrap().unwrap();
+        assert_eq!(found.display_name, "alice");
+        assert!(
+            user_repo::username_exists(&db, &found.username)
+                .await
+                .unwrap()
+        );
+
+        let suffix = short(id);
+        let hits = user_repo::search_users(&db, &suffix.to_uppercase(), 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            hits.len(),
+            1,
+            "{}: search is case-insensitive",
+            db.backend().name()
+        );
+        let hits = user_repo::search_users(&db, "%", 10).await.unwrap();
+        assert!(hits.iter().all(|u| u.username.contains('%')));
+
+        user_repo::increment_upload_bytes(&db, id, 100)
+            .await
+            .unwrap();
+        user_repo::decrement_upload_bytes(&db, id, 250)
+            .await
+            .unwrap();
+        assert_eq!(user_repo::get_upload_bytes_used(&db, id).await.unwrap(), 0);
+    }
+}
+
+#[tokio::test]
+async fn totp_backup_codes_are_consumed_once() {
+    for db in databases().await {
+        let id = user(&db, "totp").await;
+        let codes = vec!["a".to_string(), "b".to_string()];
+        user_repo::set_totp_backup_codes(&db, id, &codes)
+            .await
+            .unwrap();
+        assert!(
+            user_repo::consume_totp_backup_code(&db, id, "a")
+                .await
+                .unwrap()
+        );
+        assert!(
+            !user_repo::consume_totp_backup_code(&db, id, "a")
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            user_repo::get_totp_backup_codes(&db, id).await.unwrap(),
+            Some(vec!["b".to_string()])
+        );
+    }
+}
+
+#[tokio::test]
+async fn channel_batch_join_skips_existing_and_unknown_users() {
+    for db in databases().await {
+        let owner = user(&db, "owner").await;
+        let member = user(&db, "member").await;
+        let channel_id = channel(&db, owner).await;
+        channel_repo::join_channel_batch(&db, channel_id, &[owner, member, Uuid::now_v7()])
+            .await
+            .unwrap();
+        assert_eq!(
+            channel_repo::count_members(&db, channel_id).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            channel_repo::get_member_role(&db, channel_id, owner)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("owner")
+        );
+    }
+}
+
+#[tokio::test]
+async fn message_search_matches_stored_text() {
+    for db in databases().await {
+        let owner = user(&db, "writer").await;
+        let channel_id = channel(&db, owner).await;
+        let hit = message(&db, channel_id, owner, "Hello 100% World").await;
+        message(&db, channel_id, owner, "Hello 1000 World").await;
+
+        let found = message_repo::search_messages(&db, channel_id, "100%", 10, &no_filters())
+            .await
+            .unwrap();
+        assert_eq!(found.iter().map(|m| m.id).collect::<Vec<_>>(), vec![hit]);
+
+        let found = message_repo::search_messages_global(&db, owner, "hello", 10, &no_filters())
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 2);
+
+        let filters = message_repo::SearchFilters {
+            after: Some(Utc::now() + Duration::hours(1)),
+            ..no_filters()
+        };
+        let found = message_repo::search_messages(&db, channel_id, "hello", 10, &filters)
+            .await
+            .unwrap();
+        assert!(found.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn reactions_are_grouped_by_emoji() {
+    for db in databases().await {
+        let a = user(&db, "ra").await;
+        let b = user(&db, "rb").await;
+        let channel_id = channel(&db, a).await;
+        let msg = message(&db, channel_id, a, "react to me").await;
+        reaction_repo::add_reaction(&db, msg, a, "👍")
+            .await
+            .unwrap();
+        reaction_repo::add_reaction(&db, msg, b, "🎉")
+            .await
+            .unwrap();
+        reaction_repo::add_reaction(&db, msg, b, "👍")
+            .await
+            .unwrap();
+
+        let counts = reaction_repo::get_reaction_counts(&db, msg).await.unwrap();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[0].emoji, "👍");
+        assert_eq!(counts[0].user_ids, vec![a, b]);
+        assert_eq!(
+            reaction_repo::count_unique_reactions(&db, msg)
+                .await
+                .unwrap(),
+            2
+        );
+    }
+}
+
+#[tokio::test]
+async fn job_leases_expire_and_exclude_other_owners() {
+    for db in databases().await {
+        let name = format!("test_job_{}", short(Uuid::now_v7()));
+        let (first, second) = (Uuid::now_v7(), Uuid::now_v7());
+        job_repo::register(&db, &name, Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+
+        assert!(
+            job_repo::try_acquire(&db, &name, first, 60)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            job_repo::try_acquire(&db, &name, second, 60)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(job_repo::renew_lease(&db, &name, first, -1).await.unwrap());
+        // The lease is now in the past, so another owner may take it.
+        assert!(
+            job_repo::try_acquire(&db, &name, second, 60)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        let next = Utc::now() + Duration::minutes(5);
+        job_repo::finish(&db, &name, second, 12, None, 0, next)
+            .await
+            .unwrap();
+        let job = job_repo::list_jobs(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|j| j.name == name)
+            .unwrap();
+        assert_eq!(job.run_count, 1);
+        assert!(job.last_success_at.is_some());
+        assert!(
+            job_repo::try_acquire(&db, &name, first, 60)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
+
+#[tokio::test]
+async fn preferences_merge_top_level_keys() {
+    for db in databases().await {
+        let id = user(&db, "prefs").await;
+        let partial = serde_json::json!({"theme": "dark", "nested": {"a": 1}});
+        preferences_repo::merge_preferences(&db, id, &partial)
+            .await
+            .unwrap();
+        let partial = serde_json::json!({"nested": {"b": 2}, "sound": false});
+        let merged = preferences_repo::merge_preferences(&db, id, &partial)
+            .await
+            .unwrap();
+        assert_eq!(
+            merged,
+            serde_json::json!({"theme": "dark", "nested": {"b": 2}, "sound": false})
+        );
+        assert_eq!(
+            preferences_repo::get_preferences(&db, id).await.unwrap(),
+            merged
+        );
+    }
+}
+
+#[tokio::test]
+async fn effective_notification_settings_prefer_the_channel_scope() {
+    for db in databases().await {
+        let a = user(&db, "na").await;
+        let b = user(&db, "nb").await;
+        let channel_id = channel(&db, a).await;
+        let until = Utc::now() + Duration::hours(1);
+        notification_repo::upsert_setting(&db, a, "channel", channel_id, "nothing", Some(until))
+            .await
+            .unwrap();
+
+        let settings = notification_repo::get_effective_settings(&db, channel_id, &[a, b])
+            .await
+            .unwrap();
+        assert_eq!(settings.len(), 1);
+        assert_eq!(settings[0].user_id, a);
+        assert_eq!(settings[0].level, "nothing");
+        assert!(settings[0].is_muted(Utc::now()));
+    }
+}
+
+#[tokio::test]
+async fn deferred_pushes_coalesce_per_channel() {
+    for db in databases().await {
+        let u = user(&db, "quiet").await;
+        let (c1, c2) = (channel(&db, u).await, channel(&db, u).await);
+        for (kind, sender, channel_id) in [
+            ("message", "a", c1),
+            ("mention", "b", c1),
+            ("message", "c", c1),
+            ("message", "d", c2),
+        ] {
+            quiet_hours_repo::defer_push(&db, u, kind, sender, channel_id, "general")
+                .await
+                .unwrap();
+        }
+
+        let deferred = quiet_hours_repo::take_deferred(&db, u).await.unwrap();
+        assert_eq!(deferred.len(), 2);
+        let first = deferred.iter().find(|d| d.channel_id == c1).unwrap();
+        assert_eq!(first.count, 3);
+        assert_eq!(first.notification_type, "mention");
+        assert_eq!(first.sender_name, "c");
+        assert!(
+            quiet_hours_repo::take_deferred(&db, u)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // A window that starts where it ends is rejected
+        let empty = quiet_hours_repo::replace_config(
+            &db,
+            u,
+            true,
+            "UTC",
+            "batch",
+            true,
+            &[(1, 60, 60)],
+            &[],
+        )
+        .await;
+        assert!(empty.is_err());
+    }
+}
+
+#[tokio::test]
+async fn voice_sessions_are_shared_and_left() {
+    for db in databases().await {
+        let a = user(&db, "va").await;
+        let b = user(&db, "vb").await;
+        let channel_id = channel(&db, a).await;
+        let session = voice_repo::get_or_create_session(&db, channel_id, a)
+            .await
+            .unwrap();
+        let again = voice_repo::get_or_create_session(&db, channel_id, b)
+            .await
+            .unwrap();
+        assert_eq!(session.id, again.id);
+
+        assert!(voice_repo::join_session(&db, session.id, a).await.unwrap());
+        assert!(voice_repo::join_session(&db, session.id, b).await.unwrap());
+        assert!(voice_repo::are_in_same_session(&db, a, b).await.unwrap());
+
+        let left = voice_repo::leave_all_sessions(&db, a).await.unwrap();
+        assert_eq!(left, vec![(session.id, channel_id)]);
+        assert_eq!(
+            voice_repo::get_participants(&db, session.id).await.unwrap(),
+            vec![b]
+        );
+    }
+}
+
+#[tokio::test]
+async fn deliveries_skip_own_and_repeated_messages() {
+    for db in databases().await {
+        let a = user(&db, "da").await;
+        let b = user(&db, "db").await;
+        let channel_id = channel(&db, a).await;
+        let from_a = message(&db, channel_id, a, "from a").await;
+        let from_b = message(&db, channel_id, b, "from b").await;
+
+        let new = delivery_repo::mark_delivered(&db, b, channel_id, &[from_a, from_b])
+            .await
+            .unwrap();
+        assert_eq!(new.len(), 1);
+        assert_eq!((new[0].message_id, new[0].sender_id), (from_a, Some(a)));
+        let new = delivery_repo::mark_delivered(&db, b, channel_id, &[from_a])
+            .await
+            .unwrap();
+        assert!(new.is_empty());
+        assert_eq!(
+            delivery_repo::get_delivered_user_ids(&db, from_a)
+                .await
+                .unwrap(),
+            vec![b]
+        );
+    }
+}
+
+#[tokio::test]
+async fn mark_all_read_moves_cursors_to_the_latest_message() {
+    for db in databases().await {
+        let a = user(&db, "ua").await;
+        let b = user(&db, "ub").await;
+        let channel_id = channel(&db, a).await;
+        let empty = channel(&db, b).await;
+        channel_repo::join_channel(&db, channel_id, b)
+            .await
+            .unwrap();
+        channel_repo::join_channel(&db, empty, a).await.unwrap();
+        message(&db, channel_id, b, "one").await;
+        let last = message(&db, channel_id, b, "two").await;
+        assert_eq!(
+            unread_repo::count_unread(&db, a, channel_id).await.unwrap(),
+            2
+        );
+
+        assert_eq!(unread_repo::mark_all_read(&db, a).await.unwrap(), 1);
+        assert_eq!(
+            unread_repo::count_unread(&db, a, channel_id).await.unwrap(),
+            0
+        );
+        let cursor = unread_repo::get_read_cursor(&db, a, channel_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cursor.last_read_message_id, Some(last));
+    }
+}
+
+#[tokio::test]
+async fn history_pages_across_the_archive_boundary() {
+    for db in databases().await {
+        let a = user(&db, "arch").await;
+        let channel_id = channel(&db, a).await;
+        let month = NaiveDate::from_ymd_opt(2001, 3, 1).unwrap();
+        if db.backend() == Backend::Postgres {
+            db::query("SELECT create_message_partitions('2001-03-01', '2001-03-01')")
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        let first = message(&db, channel_id, a, "first").await;
+        let second = message(&db, channel_id, a, "second").await;
+        for (id, day) in [(first, 10), (second, 11)] {
+            db::query("UPDATE messages SET created_at = $1 WHERE id = $2")
+                .bind(Utc.with_ymd_and_hms(2001, 3, day, 12, 0, 0).unwrap())
+                .bind(id)
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+        reaction_repo::add_reaction(&db, second, a, "👍")
+            .await
+            .unwrap();
+        message_repo::save_edit_history(&db, second, b"old", &[0u8; 12])
+            .await
+            .unwrap();
+        let live = message(&db, channel_id, a, "live").await;
+
+        let moved = message_archive_repo::archive_month(&db, month)
+            .await
+            .unwrap();
+        if db.backend() == Backend::Postgres {
+            assert_eq!(moved.messages, 2);
+            assert_eq!(moved.reactions, 1);
+            assert_eq!(moved.edits, 1);
+            let months = message_archive_repo::list_partition_months(&db)
+                .await
+                .unwrap();
+            assert!(!months.contains(&month));
+        }
+
+        let page = message_repo::get_messages(&db, channel_id, None, 2, false)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = page.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![live, second]);
+        let page = message_repo::get_messages(&db, channel_id, Some(second), 10, false)
+            .await
+            .unwrap();
+        assert_eq!(page.iter().map(|m| m.id).collect::<Vec<_>>(), vec![first]);
+
+        let reactions = reaction_repo::get_reactions_for_messages(&db, &[second])
+            .await
+            .unwrap();
+        assert_eq!(reactions.len(), 1);
+        assert_eq!(reactions[0].user_ids, vec![a]);
+        let edits = message_repo::get_edit_history(&db, second).await.unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].old_ciphertext, b"old");
+
+        // Lookups, search, moderation and references reach archived messages
+        assert_eq!(
+            message_repo::is_archived(&db, second).await.unwrap(),
+            db.backend() == Backend::Postgres
+        );
+        let found = message_repo::search_messages(&db, channel_id, "second", 10, &no_filters())
+            .await
+            .unwrap();
+        assert_eq!(found.iter().map(|m| m.id).collect::<Vec<_>>(), vec![second]);
+        bookmark_repo::create(&db, Uuid::now_v7(), a, second, None)
+            .await
+            .unwrap();
+        assert!(
+            bookmark_repo::create(&db, Uuid::now_v7(), a, Uuid::now_v7(), None)
+                .await
+                .is_err()
+        );
+        assert!(
+            message_repo::quarantine_message(&db, first, a)
+                .await
+                .unwrap()
+        );
+        assert!(
+            message_repo::unquarantine_message(&db, first)
+                .await
+                .unwrap()
+        );
+        assert!(message_repo::delete_message(&db, first, a).await.unwrap());
+        assert!(!message_repo::delete_message(&db, first, a).await.unwrap());
+
+        assert!(
+            message_repo::hard_delete_message(&db, second)
+                .await
+                .unwrap()
+        );
+        assert!(
+            message_repo::get_message_by_id(&db, second)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
+
+#[tokio::test]
+async fn upload_offsets_only_advance_from_the_stored_offset() {
+    for db in databases().await {
+        let a = user(&db, "up").await;
+        let id = Uuid::now_v7();
+        let expires = Utc::now() + Duration::hours(1);
+        upload_repo::create_upload(&db, id, a, 100, "name", None, None, expires)
+            .await
+            .unwrap();
+        assert_eq!(
+            upload_repo::get_pending_for_user(&db, a).await.unwrap(),
+            (1, 100)
+        );
+
+        assert!(
+            upload_repo::advance_offset(&db, id, 0, 40, expires)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !upload_repo::advance_offset(&db, id, 0, 60, expires)
+                .await
+                .unwrap()
+        );
+        let upload = upload_repo::get_upload(&db, id).await.unwrap().unwrap();
+        assert_eq!(upload.upload_offset, 40);
+
+        let past = Utc::now() - Duration::seconds(1);
+        assert!(
+            upload_repo::advance_offset(&db, id, 40, 50, past)
+                .await
+                .unwrap()
+        );
+        assert!(upload_repo::get_upload(&db, id).await.unwrap().is_none());
+        assert!(
+            upload_repo::delete_expired(&db)
+                .await
+                .unwrap()
+                .contains(&id)
+        );
+        assert!(
+            !upload_repo::list_upload_ids(&db)
+                .await
+                .unwrap()
+                .contains(&id)
+        );
+    }
+}
+
+#[tokio::test]
+async fn blobs_are_shared_and_released_with_their_last_file() {
+    for db in databases().await {
+        let a = user(&db, "blob").await;
+        let b = user(&db, "blob").await;
+        let checksum = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let key = format!("{}/{checksum}", &checksum[..2]);
+
+        assert!(
+            file_repo::acquire_blob(&db, &checksum)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let claim = file_repo::claim_blob(&db, &key, &checksum, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!claim.stored);
+        // Neither shared nor claimed again until it is stored
+        assert!(
+            file_repo::acquire_blob(&db, &checksum)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            file_repo::claim_blob(&db, &key, &checksum, 10)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let blob = file_repo::finish_blob(&db, &key, 10, &Default::default())
+            .await
+            .unwrap();
+        assert!(blob.stored);
+        assert_eq!(blob.ref_count, 1);
+        let file = |id: Uuid, owner: Uuid| {
+            let (db, key, checksum) = (&db, &key, &checksum);
+            async move {
+                file_repo::create_file(
+                    db,
+                    id,
+                    owner,
+                    "f",
+                    10,
+                    None,
+                    key,
+                    checksum,
+                    None,
+                    &Default::default(),
+                    false,
+                )
+                .await
+                .unwrap();
+            }
+        };
+        let (f1, f2, f3) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+        file(f1, a).await;
+
+        // Same content again, from the same user and from another one
+        for (id, owner) in [(f2, a), (f3, b)] {
+            let shared = file_repo::acquire_blob(&db, &checksum)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(shared.storage_path, key);
+            file(id, owner).await;
+        }
+        assert!(file_repo::user_has_blob(&db, a, &key, f2).await.unwrap());
+        assert!(!file_repo::user_has_blob(&db, b, &key, f3).await.unwrap());
+
+        for id in [f1, f2] {
+            assert!(file_repo::delete_file(&db, id).await.unwrap());
+            assert!(file_repo::release_blob(&db, &key).await.unwrap().is_none());
+        }
+        assert!(file_repo::delete_file(&db, f3).await.unwrap());
+        let last = file_repo::release_blob(&db, &key).await.unwrap().unwrap();
+        assert_eq!(last.ref_count, 0);
+        assert!(
+            file_repo::acquire_blob(&db, &checksum)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        // The tombstone holds the key until the bytes are deleted
+        assert!(
+            file_repo::claim_blob(&db, &key, &checksum, 10)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(file_repo::delete_blob_record(&db, &key).await.unwrap());
+
+        // Two uploads of the same content: the second joins the first once
+        // it is stored
+        file_repo::claim_blob(&db, &key, &checksum, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        file_repo::finish_blob(&db, &key, 10, &Default::default())
+            .await
+            .unwrap();
+        let joined = file_repo::claim_blob(&db, &key, &checksum, 10)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(joined.stored);
+        assert_eq!(joined.ref_count, 2);
+        assert!(file_repo::release_blob(&db, &key).await.unwrap().is_none());
+
+        // An upload that took a reference after the orphan scan, and has not
+        // recorded its file yet, keeps the blob from being tombstoned
+        file_repo::acquire_blob(&db, &checksum)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            !file_repo::tombstone_unreferenced_blob(&db, &key, 1)
+                .await
+                .unwrap()
+        );
+        assert!(file_repo::release_blob(&db, &key).await.unwrap().is_none());
+
+        // A file left behind by a failed delete gets its reference back
+        file(Uuid::now_v7(), a).await;
+        file(Uuid::now_v7(), b).await;
+        assert!(file_repo::repair_blob_refs(&db).await.unwrap() >= 1);
+        let repaired = file_repo::acquire_blob(&db, &checksum)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(repaired.ref_count, 3);
+    }
+}
+
+#[tokio::test]
+async fn blocked_hashes_match_by_kind() {
+    for db in databases().await {
+        let admin = user(&db, "blocker").await;
+        let checksum = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let dhash = Uuid::new_v4().simple().to_string()[..16].to_string();
+
+        blocked_hash_repo::add_blocked_hashes(
+            &db,
+            blocked_hash_repo::SHA256,
+            std::slice::from_ref(&checksum),
+            None,
+            Some("purge"),
+            admin,
+        )
+        .await
+        .unwrap();
+        blocked_hash_repo::add_blocked_hashes(
+            &db,
+            blocked_hash_repo::DHASH,
+            std::slice::from_ref(&dhash),
+            Some(8),
+            Some("purge"),
+            admin,
+        )
+        .await
+        .unwrap();
+        assert!(
+            blocked_hash_repo::is_hash_blocked(&db, &checksum)
+                .await
+                .unwrap()
+        );
+        // Perceptual hashes never match a checksum exactly
+        assert!(
+            !blocked_hash_repo::is_hash_blocked(&db, &dhash)
+                .await
+                .unwrap()
+        );
+
+        // Adding it again updates the distance
+        let updated = blocked_hash_repo::add_blocked_hash(
+            &db,
+            Uuid::now_v7(),
+            blocked_hash_repo::DHASH,
+            &dhash,
+            Some(3),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(updated.max_distance, Some(3));
+        let perceptual = blocked_hash_repo::list_perceptual_hashes(&db)
+            .await
+            .unwrap();
+        let entry = perceptual.iter().find(|h| h.hash == dhash).unwrap();
+        assert_eq!(
+            (entry.kind.as_str(), entry.max_distance),
+            ("dhash", Some(3))
+        );
+        assert!(perceptual.iter().all(|h| h.hash != checksum));
+
+        let all = blocked_hash_repo::list_all_blocked_hashes(&db)
+            .await
+            .unwrap();
+        assert!(all.iter().any(|h| h.hash == checksum && h.kind == "sha256"));
+
+        // Imports are all or nothing
+        let imported = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        fn new_hash(hash: &str) -> blocked_hash_repo::NewBlockedHash<'_> {
+            blocked_hash_repo::NewBlockedHash {
+                kind: blocked_hash_repo::SHA256,
+                hash,
+                max_distance: None,
+                reason: Some("import"),
+            }
+        }
+        if db.backend() == Backend::Postgres {
+            let too_long = "f".repeat(200);
+            assert!(
+                blocked_hash_repo::import_blocked_hashes(
+                    &db,
+                    &[new_hash(&imported), new_hash(&too_long)],
+                    None
+                )
+                .await
+                .is_err()
+            );
+            assert!(
+                !blocked_hash_repo::is_hash_blocked(&db, &imported)
+                    .await
+                    .unwrap()
+            );
+        }
+        let stored = blocked_hash_repo::import_blocked_hashes(
+            &db,
+            &[new_hash(&imported), new_hash(&checksum)],
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stored, 2);
+        assert!(
+            blocked_hash_repo::is_hash_blocked(&db, &imported)
+                .await
+                .unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn retention_selects_old_files_and_the_oldest_over_size() {
+    for db in databases().await {
+        let owner = user(&db, "keeper").await;
+        let community = Uuid::now_v7();
+        community_repo::create_community(&db, community, "c", None, None, owner)
+            .await
+            .unwrap();
+        let group = Uuid::now_v7();
+        group_repo::create_group(&db, group, "g", None, owner, community, "public", None)
+            .await
+            .unwrap();
+        let in_group = Uuid::now_v7();
+        channel_repo::create_channel(
+            &db,
+            in_group,
+            "files",
+            ChannelType::Text,
+            None,
+            owner,
+            Some(group),
+            false,
+        )
+        .await
+        .unwrap();
+        let loose = channel(&db, owner).await;
+
+        // Three 10-byte files per channel, 5, 3 and 1 days old
+        let mut files = Vec::new();
+        for channel_id in [in_group, loose] {
+            for days in [5, 3, 1] {
+                let id = Uuid::now_v7();
+                file_repo::create_file(
+                    &db,
+                    id,
+                    owner,
+                    "f",
+                    10,
+                    None,
+                    &format!("ff/{id}"),
+                    &id.to_string(),
+                    Some(channel_id),
+                    &Default::default(),
+                    false,
+                )
+                .await
+                .unwrap();
+                db::query("UPDATE files SET created_at = $1 WHERE id = $2")
+                    .bind(Utc::now() - Duration::days(days))
+                    .bind(id)
+                    .execute(&db)
+                    .await
+                    .unwrap();
+                files.push(id);
+            }
+        }
+        let ids = |found: Vec<chatalot_db::models::file::FileRecord>| {
+            found.into_iter().map(|f| f.id).collect::<Vec<_>>()
+        };
+
+        let old = file_retention_repo::list_files_older_than(&db, "channel", loose, 2, 100)
+            .await
+            .unwrap();
+        assert_eq!(ids(old), files[3..5]);
+        let old = file_retention_repo::list_files_older_than(&db, "community", community, 4, 100)
+            .await
+            .unwrap();
+        assert_eq!(ids(old), files[..1]);
+
+        // 15 bytes keeps only the newest file
+        let over = file_retention_repo::list_files_over_size(&db, "community", community, 15, 100)
+            .await
+            .unwrap();
+        assert_eq!(ids(over), files[..2]);
+        let over = file_retention_repo::list_files_over_size(&db, "channel", loose, 30, 100)
+            .await
+            .unwrap();
+        assert!(over.is_empty());
+
+        db::query("UPDATE channels SET message_ttl_seconds = 2 * 86400 WHERE id = $1")
+            .bind(loose)
+            .execute(&db)
+            .await
+            .unwrap();
+        let expired = file_retention_repo::list_files_past_message_ttl(&db, 10_000)
+            .await
+            .unwrap();
+        let expired: Vec<Uuid> = ids(expired)
+            .into_iter()
+            .filter(|id| files.contains(id))
+            .collect();
+        assert_eq!(expired, files[3..5]);
+
+        file_retention_repo::upsert_policy(&db, "community", community, Some(30), None, owner)
+            .await
+            .unwrap();
+        let policy = file_retention_repo::upsert_policy(
+            &db,
+            "community",
+            community,
+            None,
+            Some(1 << 20),
+            owner,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            (policy.max_age_days, policy.max_total_bytes),
+            (None, Some(1 << 20))
+        );
+        assert!(
+            file_retention_repo::delete_policy(&db, "community", community)
+                .await
+                .unwrap()
+        );
+        assert!(
+            file_retention_repo::get_policy(&db, "community", community)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
+
+#[tokio::test]
+async fn quotas_apply_by_channel_scope_and_role() {
+    for db in databases().await {
+        let owner = user(&db, "quotaowner").await;
+        let member = user(&db, "quotamember").await;
+        let community = Uuid::now_v7();
+        community_repo::create_community(&db, community, "c", None, None, owner)
+            .await
+            .unwrap();
+        community_repo::join_community(&db, community, member)
+            .await
+            .unwrap();
+        let group = Uuid::now_v7();
+        group_repo::create_group(&db, group, "g", None, owner, community, "public", None)
+            .await
+            .unwrap();
+        let in_group = Uuid::now_v7();
+        channel_repo::create_channel(
+            &db,
+            in_group,
+            "files",
+            ChannelType::Text,
+            None,
+            owner,
+            Some(group),
+            false,
+        )
+        .await
+        .unwrap();
+        let loose = channel(&db, owner).await;
+
+        storage_quota_repo::upsert_quota(&db, "community", community, "", 1000, owner)
+            .await
+            .unwrap();
+        storage_quota_repo::upsert_quota(&db, "group", group, "", 500, owner)
+            .await
+            .unwrap();
+        storage_quota_repo::upsert_quota(&db, "role", community, "moderator", 0, owner)
+            .await
+            .unwrap();
+        storage_quota_repo::upsert_quota(&db, "role", community, "member", 50, owner)
+            .await
+            .unwrap();
+
+        let scopes = |quotas: Vec<chatalot_db::models::storage_quota::StorageQuota>| {
+            let mut scopes: Vec<(String, String)> =
+                quotas.into_iter().map(|q| (q.scope_type, q.role)).collect();
+            scopes.sort();
+            scopes
+        };
+        let quotas = storage_quota_repo::quotas_for_upload(&db, member, in_group)
+            .await
+            .unwrap();
+        assert_eq!(
+            scopes(quotas),
+            [
+                ("community".to_string(), String::new()),
+                ("group".to_string(), String::new()),
+                ("role".to_string(), "member".to_string()),
+            ]
+        );
+        // The owner has no role override; channels outside communities have no quotas
+        let quotas = storage_quota_repo::quotas_for_upload(&db, owner, in_group)
+            .await
+            .unwrap();
+        assert_eq!(quotas.len(), 2);
+        assert!(
+            storage_quota_repo::quotas_for_upload(&db, member, loose)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            storage_quota_repo::has_quotas_for_member(&db, member)
+                .await
+                .unwrap()
+        );
+        let outsider = user(&db, "outsider").await;
+        assert!(
+            !storage_quota_repo::has_quotas_for_member(&db, outsider)
+                .await
+                .unwrap()
+        );
+
+        // Files and unfinished uploads in the group's channels count
+        let id = Uuid::now_v7();
+        file_repo::create_file(
+            &db,
+            id,
+            member,
+            "f",
+            100,
+            None,
+            &format!("ff/{id}"),
+            &id.to_string(),
+            Some(in_group),
+            &Default::default(),
+            false,
+        )
+        .await
+        .unwrap();
+        upload_repo::create_upload(
+            &db,
+            Uuid::now_v7(),
+            member,
+            40,
+            "u",
+            None,
+            Some(in_group),
+            Utc::now() + Duration::hours(1),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            file_repo::scope_usage(&db, "group", group).await.unwrap(),
+            140
+        );
+        assert_eq!(
+            file_repo::scope_usage(&db, "community", community)
+                .await
+                .unwrap(),
+            140
+        );
+
+        let stats = file_repo::storage_stats(&db).await.unwrap();
+        let stat = stats
+            .per_community
+            .iter()
+            .find(|s| s.scope_id == community)
+            .unwrap();
+        assert_eq!(
+            (stat.file_count, stat.total_bytes, stat.quota_bytes),
+            (1, 100, Some(1000))
+        );
+        let stat = stats
+            .per_group
+            .iter()
+            .find(|s| s.scope_id == group)
+            .unwrap();
+        assert_eq!((stat.total_bytes, stat.quota_bytes), (100, Some(500)));
+        assert!(
+            stats
+                .per_user
+                .iter()
+                .any(|s| s.uploader_id == member && s.total_bytes == 100)
+        );
+
+        assert!(
+            storage_quota_repo::delete_quota(&db, "role", community, "member")
+                .await
+                .unwrap()
+        );
+        assert!(
+            !storage_quota_repo::delete_quota(&db, "role", community, "member")
+                .await
+                .unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn ws_events_are_listed_in_window_and_pruned() {
+    for db in databases().await {
+        let id = Uuid::now_v7();
+        ws_event_repo::insert(&db, id, r#"{"kind":"test"}"#)
+            .await
+            .unwrap();
+
+        let recent = ws_event_repo::list_since(&db, Utc::now() - Duration::minutes(1))
+            .await
+            .unwrap();
+        assert!(
+            recent
+                .iter()
+                .any(|e| e.id == id && e.event == r#"{"kind":"test"}"#)
+        );
+        let later = ws_event_repo::list_since(&db, Utc::now() + Duration::minutes(1))
+            .await
+            .unwrap();
+        assert!(!later.iter().any(|e| e.id == id));
+
+        ws_event_repo::delete_older_than(&db, 600).await.unwrap();
+        let kept = ws_event_repo::list_since(&db, Utc::now() - Duration::hours(2))
+            .await
+            .unwrap();
+        assert!(kept.iter().any(|e| e.id == id));
+
+        db::query("UPDATE ws_events SET created_at = $1 WHERE id = $2")
+            .bind(Utc::now() - Duration::hours(1))
+            .bind(id)
+            .execute(&db)
+            .await
+            .unwrap();
+        assert!(ws_event_repo::delete_older_than(&db, 600).await.unwrap() >= 1);
+        let gone = ws_event_repo::list_since(&db, Utc::now() - Duration::hours(2))
+            .await
+            .unwrap();
+        assert!(!gone.iter().any(|e| e.id == id));
+    }
+}
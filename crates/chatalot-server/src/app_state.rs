@@ -1,13 +1,21 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Instant;
 
 use anyhow::Result;
+use chatalot_db::db::Db;
 use jsonwebtoken::{DecodingKey, EncodingKey};
-use sqlx::PgPool;
 
-use crate::config::Config;
-use crate::services::push_service::PushService;
+use crate::config::{Config, LiveConfig};
+use crate::jobs::JobRegistry;
+use crate::scanner::{self, Scanner};
+use crate::services::event_relay::EventRelay;
+use crate::services::push_service::{
+    PushService, PushTransport, UnifiedPushTransport, WebPushTransport,
+};
+use crate::storage::{self, Storage};
 use crate::ws::connection_manager::ConnectionManager;
+use crate::ws::sse::SseSessions;
 
 /// Admin-configurable instance settings (loaded from DB, cached in memory).
 #[derive(Debug, Clone)]
@@ -15,6 +23,8 @@ pub struct InstanceSettings {
     pub max_messages_cache: u32,
     pub max_pins_per_channel: i64,
     pub e2e_enabled: bool,
+    /// Per-job interval overrides in seconds (`job_interval.<name>` settings).
+    pub job_intervals: HashMap<&'static str, u64>,
 }
 
 impl Default for InstanceSettings {
@@ -23,29 +33,46 @@ impl Default for InstanceSettings {
             max_messages_cache: 500,
             max_pins_per_channel: 50,
             e2e_enabled: true,
+            job_intervals: HashMap::new(),
         }
     }
 }
 
 pub struct AppState {
     pub config: Config,
-    pub db: PgPool,
+    pub db: Db,
     pub jwt_encoding_key: EncodingKey,
     pub jwt_decoding_key: DecodingKey,
     pub start_time: Instant,
     pub connections: ConnectionManager,
     pub client_version: String,
     pub http_client: reqwest::Client,
+    /// Blob storage for uploads, thumbnails, avatars, assets and emojis.
+    pub storage: Arc<dyn Storage>,
+    /// Malware scanner for uploads (None if `CLAMD_ADDRESS` is unset).
+    pub scanner: Option<Arc<dyn Scanner>>,
     /// In-memory set of suspended user IDs for instant JWT rejection.
     pub suspended_users: dashmap::DashSet<uuid::Uuid>,
-    /// Web Push notification service (None if VAPID keys not configured).
+    /// Push notification service (None if no push transport is configured).
     pub push_service: Option<Arc<PushService>>,
+    /// Open SSE fallback sessions.
+    pub sse_sessions: SseSessions,
+    /// Flips to true once graceful shutdown begins.
+    pub shutdown: tokio::sync::watch::Sender<bool>,
     /// Admin-configurable instance settings (cached in memory).
     pub instance_settings: tokio::sync::RwLock<InstanceSettings>,
+    pub jobs: JobRegistry,
+    /// Cross-node delivery of events raised by cluster jobs.
+    pub event_relay: EventRelay,
+    /// Settings reloaded on SIGHUP; read through `live()`.
+    live: std::sync::RwLock<Arc<LiveConfig>>,
+    /// The configuration as last read from disk, to report restart-only
+    /// changes once rather than on every reload.
+    last_loaded: std::sync::Mutex<Config>,
 }
 
 impl AppState {
-    pub fn new(config: Config, db: PgPool, start_time: Instant) -> Result<Self> {
+    pub fn new(config: Config, db: Db, start_time: Instant) -> Result<Self> {
         let private_pem = std::fs::read_to_string(&config.jwt_private_key_path)?;
         let public_pem = std::fs::read_to_string(&config.jwt_public_key_path)?;
 
@@ -66,28 +93,32 @@ impl AppState {
             .redirect(reqwest::redirect::Policy::limited(3))
             .build()?;
 
-        // Initialize push service if VAPID keys are configured
-        let push_service = config
-            .vapid_private_key
-            .as_ref()
-            .and_then(|key| {
-                if key.is_empty() {
-                    return None;
+        let storage = storage::from_config(&config)?;
+        let scanner = scanner::from_config(&config)?;
+
+        // Initialize push service with every configured transport
+        let mut transports: Vec<Box<dyn PushTransport>> = Vec::new();
+        if let Some(key) = config.vapid_private_key.as_ref().filter(|k| !k.is_empty()) {
+            match WebPushTransport::new(key, config.public_url.as_deref()) {
+                Ok(t) => {
+                    tracing::info!("Web Push notifications enabled");
+                    transports.push(Box::new(t));
                 }
-                match PushService::new(key, config.public_url.as_deref()) {
-                    Ok(svc) => {
-                        tracing::info!("Web Push notifications enabled");
-                        Some(Arc::new(svc))
-                    }
-                    Err(e) => {
-                        tracing::warn!("Web Push disabled: {e}");
-                        None
-                    }
+                Err(e) => tracing::warn!("Web Push disabled: {e}"),
+            }
+        }
+        if config.unifiedpush_enabled {
+            match UnifiedPushTransport::new(&config.unifiedpush_allowed_hosts) {
+                Ok(t) => {
+                    tracing::info!("UnifiedPush notifications enabled");
+                    transports.push(Box::new(t));
                 }
-            });
+                Err(e) => tracing::warn!("UnifiedPush disabled: {e}"),
+            }
+        }
+        let push_service = (!transports.is_empty()).then(|| Arc::new(PushService::new(transports)));
 
         Ok(Self {
-            config,
             db,
             jwt_encoding_key,
             jwt_decoding_key,
@@ -95,9 +126,91 @@ impl AppState {
             connections: ConnectionManager::new(),
             client_version,
             http_client,
+            storage,
+            scanner,
             suspended_users: dashmap::DashSet::new(),
             push_service,
+            sse_sessions: SseSessions::default(),
+            shutdown: tokio::sync::watch::Sender::new(false),
             instance_settings: tokio::sync::RwLock::new(InstanceSettings::default()),
+            jobs: JobRegistry::default(),
+            event_relay: EventRelay::default(),
+            live: std::sync::RwLock::new(Arc::new(config.live.clone())),
+            last_loaded: std::sync::Mutex::new(config.clone()),
+            config,
         })
     }
+
+    /// Current values of the settings that can change without a restart.
+    pub fn live(&self) -> Arc<LiveConfig> {
+        self.live.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Re-read the configuration and apply the reloadable settings. An invalid
+    /// configuration is rejected as a whole and the current settings are kept.
+    pub fn reload_config(&self) {
+        let (config, problems) = match Config::load(self.config.config_file.as_deref()) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                tracing::error!("Config reload failed, keeping current settings: {e:#}");
+                return;
+            }
+        };
+        if !problems.is_empty() {
+            for problem in &problems {
+                tracing::error!("Config reload: {problem}");
+            }
+            tracing::error!("Config reload rejected, keeping current settings");
+            return;
+        }
+
+        let current = self.live();
+        let new = &config.live;
+        let changed: Vec<&str> = [
+            (
+                "registration_mode",
+                current.registration_mode != new.registration_mode,
+            ),
+            (
+                "community_creation_mode",
+                current.community_creation_mode != new.community_creation_mode,
+            ),
+            (
+                "upload_quota_mb",
+                current.upload_quota_mb != new.upload_quota_mb,
+            ),
+            (
+                "ice_servers",
+                current.ice_servers_json != new.ice_servers_json,
+            ),
+            ("rate_limit", current.rate_limit != new.rate_limit),
+            (
+                "auth_rate_limit",
+                current.auth_rate_limit != new.auth_rate_limit,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, differs)| differs.then_some(name))
+        .collect();
+
+        if changed.is_empty() {
+            tracing::info!("Config reloaded, no changes");
+        } else {
+            *self.live.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(new.clone());
+            tracing::info!("Config reloaded, changed: {}", changed.join(", "));
+        }
+
+        let mut last_loaded = self.last_loaded.lock().unwrap_or_else(|e| e.into_inner());
+        let mut restart_only = config.clone();
+        restart_only.live = last_loaded.live.clone();
+        if restart_only != *last_loaded {
+            tracing::warn!("Some changed settings only take effect after a restart");
+        }
+        *last_loaded = config;
+    }
+
+    /// Whether graceful shutdown has begun.
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
 }
@@ -0,0 +1,880 @@
+//! Offline administration for a Chatalot instance.
+//!
+//! Talks to the database and file storage directly, using the same
+//! configuration as the server, so it works whether or not the server is
+//! running. Every change is written to the audit log with `"via": "cli"`.
+
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use chatalot_db::db::Db;
+use clap::{Parser, Subcommand};
+use uuid::Uuid;
+
+use chatalot_db::models::user::User;
+use chatalot_db::repos::{
+    audit_repo, custom_emoji_repo, file_repo, message_repo, registration_invite_repo, user_repo,
+};
+use chatalot_server::config::Config;
+use chatalot_server::services::event_relay::{self, RelayedEvent};
+use chatalot_server::services::{
+    auth_service, backup, blocklist, orphan_files, thumbnail_service, upload_service,
+};
+use chatalot_server::storage::{self, Storage};
+
+#[derive(Parser)]
+#[command(
+    name = "chatalot-admin",
+    version,
+    about = "Chatalot instance administration"
+)]
+struct Cli {
+    /// TOML config file (defaults to $CHATALOT_CONFIG, then environment only).
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage user accounts.
+    #[command(subcommand)]
+    Users(UsersCommand),
+    /// Manage registration invites.
+    #[command(subcommand)]
+    Invites(InvitesCommand),
+    /// Show recent audit log entries, newest first.
+    Audit {
+        #[arg(long)]
+        action: Option<String>,
+        /// Only entries recorded for this username.
+        #[arg(long)]
+        user: Option<String>,
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+    },
+    /// Check and repair uploaded files.
+    #[command(subcommand)]
+    Files(FilesCommand),
+    /// Share the hash blocklist with other instances.
+    #[command(subcommand)]
+    Blocklist(BlocklistCommand),
+    /// Show storage usage per user, community and group, or move blobs
+    /// between backends.
+    Storage {
+        #[command(subcommand)]
+        command: Option<StorageCommand>,
+    },
+    /// Inspect or apply database migrations.
+    #[command(subcommand)]
+    Migrations(MigrationsCommand),
+    /// Create, verify and restore instance backups.
+    #[command(subcommand)]
+    Backup(BackupCommand),
+}
+
+#[derive(Subcommand)]
+enum UsersCommand {
+    /// Grant (or with --revoke, remove) instance admin.
+    SetAdmin {
+        username: String,
+        #[arg(long)]
+        revoke: bool,
+    },
+    /// Suspend an account and revoke its sessions.
+    Suspend {
+        username: String,
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Lift a suspension.
+    Unsuspend { username: String },
+    /// Set a new password, read from the first line of stdin, and revoke
+    /// all sessions.
+    SetPassword { username: String },
+    /// Hard-delete all of a user's messages and uploaded files.
+    Purge {
+        username: String,
+        /// Required; this cannot be undone.
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum InvitesCommand {
+    /// Create a registration invite and print its code.
+    Create {
+        #[arg(long)]
+        max_uses: Option<i32>,
+        #[arg(long, value_parser = clap::value_parser!(i64).range(1..=8760))]
+        expires_hours: Option<i64>,
+    },
+    /// List registration invites.
+    List,
+}
+
+#[derive(Subcommand)]
+enum FilesCommand {
+    /// Report blobs no file uses and files with no blob.
+    Orphans {
+        /// Delete the blobs no file uses and repair reference counts.
+        #[arg(long)]
+        delete: bool,
+    },
+    /// Generate thumbnails (and perceptual hashes) for images, and previews
+    /// for video, audio and PDFs, that lack one.
+    RegenThumbnails {
+        /// Regenerate every thumbnail, not only missing ones.
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BlocklistCommand {
+    /// Write the blocklist as JSON, to stdout unless a file is given.
+    Export { output: Option<PathBuf> },
+    /// Add the entries of a blocklist file. Nothing is added if any entry
+    /// is invalid.
+    Import { input: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum StorageCommand {
+    /// Show storage usage per user, community and group (the default).
+    Usage,
+    /// Copy every blob from one storage backend to another and store paths
+    /// as backend-independent keys. Safe to rerun; blobs already copied are skipped.
+    Migrate {
+        #[arg(long, value_parser = ["local", "s3"])]
+        from: String,
+        #[arg(long, value_parser = ["local", "s3"])]
+        to: String,
+        /// Delete each blob from the source once it is in the destination.
+        #[arg(long)]
+        delete_source: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MigrationsCommand {
+    /// List migrations and whether each has been applied.
+    Status,
+    /// Apply pending migrations.
+    Run,
+}
+
+#[derive(Subcommand)]
+enum BackupCommand {
+    /// Write the database and file storage to a single archive.
+    Create {
+        output: PathBuf,
+        /// Encrypt with the passphrase on the first line of this file
+        /// (or set CHATALOT_BACKUP_PASSPHRASE).
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+    },
+    /// Check an archive against its manifest without restoring it.
+    Verify {
+        archive: PathBuf,
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+    },
+    /// Restore an archive into an empty database and storage directory.
+    Restore {
+        archive: PathBuf,
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+        /// Replace an existing database and write over existing files.
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run(Cli::parse()).await {
+        eprintln!("error: {e:#}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    let config_file = cli
+        .config
+        .or_else(|| std::env::var_os("CHATALOT_CONFIG").map(PathBuf::from));
+    let (config, problems) = Config::load(config_file.as_deref())?;
+    for problem in &problems {
+        eprintln!("warning: config: {problem}");
+    }
+
+    let db = chatalot_db::pool::create_pool(&config.database_url)
+        .await
+        .context("failed to connect to the database")?;
+    let storage = storage::from_config(&config)?;
+
+    match cli.command {
+        Command::Users(cmd) => users(&db, storage.as_ref(), cmd).await,
+        Command::Invites(cmd) => invites(&db, cmd).await,
+        Command::Audit {
+            action,
+            user,
+            limit,
+        } => audit(&db, action.as_deref(), user.as_deref(), limit).await,
+        Command::Files(cmd) => files(&db, &config, storage.as_ref(), cmd).await,
+        Command::Blocklist(cmd) => blocklist_cmd(&db, cmd).await,
+        Command::Storage { command } => match command.unwrap_or(StorageCommand::Usage) {
+            StorageCommand::Usage => storage_usage(&db).await,
+            StorageCommand::Migrate {
+                from,
+                to,
+                delete_source,
+            } => migrate_storage(&db, &config, &from, &to, delete_source).await,
+        },
+        Command::Migrations(cmd) => migrations(&db, cmd).await,
+        Command::Backup(cmd) => backup_cmd(&db, &config, cmd).await,
+    }
+}
+
+async fn find_user(db: &Db, username: &str) -> anyhow::Result<User> {
+    user_repo::find_by_username(db, username)
+        .await?
+        .with_context(|| format!("no user named '{username}'"))
+}
+
+async fn audit_log(db: &Db, action: &str, target: Uuid) -> anyhow::Result<()> {
+    user_repo::insert_audit_log(
+        db,
+        Uuid::now_v7(),
+        None,
+        action,
+        None,
+        None,
+        Some(serde_json::json!({ "target_user_id": target, "via": "cli" })),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn users(db: &Db, storage: &dyn Storage, cmd: UsersCommand) -> anyhow::Result<()> {
+    match cmd {
+        UsersCommand::SetAdmin { username, revoke } => {
+            let user = find_user(db, &username).await?;
+            if revoke && user.is_owner {
+                bail!("'{username}' is the instance owner and cannot lose admin");
+            }
+            user_repo::set_admin(db, user.id, !revoke).await?;
+            let action = if revoke {
+                "admin_revoke_admin"
+            } else {
+                "admin_grant_admin"
+            };
+            audit_log(db, action, user.id).await?;
+            println!(
+                "{} admin for '{username}'",
+                if revoke { "Revoked" } else { "Granted" }
+            );
+        }
+        UsersCommand::Suspend { username, reason } => {
+            if reason.as_ref().is_some_and(|r| r.chars().count() > 500) {
+                bail!("reason must be at most 500 characters");
+            }
+            let user = find_user(db, &username).await?;
+            if user.is_owner {
+                bail!("cannot suspend the instance owner");
+            }
+            user_repo::suspend_user(db, user.id, reason.as_deref()).await?;
+            let revoked = user_repo::revoke_all_refresh_tokens(db, user.id).await?;
+            // Running servers pick this up and drop the user's live sessions
+            event_relay::publish(
+                db,
+                &RelayedEvent::Suspension {
+                    user_id: user.id,
+                    suspended: true,
+                },
+            )
+            .await?;
+            audit_log(db, "admin_suspend_user", user.id).await?;
+            println!("Suspended '{username}' and revoked {revoked} session(s)");
+        }
+        UsersCommand::Unsuspend { username } => {
+            let user = find_user(db, &username).await?;
+            user_repo::unsuspend_user(db, user.id).await?;
+            event_relay::publish(
+                db,
+                &RelayedEvent::Suspension {
+                    user_id: user.id,
+                    suspended: false,
+                },
+            )
+            .await?;
+            audit_log(db, "admin_unsuspend_user", user.id).await?;
+            println!("Unsuspended '{username}'");
+        }
+        UsersCommand::SetPassword { username } => {
+            let user = find_user(db, &username).await?;
+            let mut password = String::new();
+            std::io::stdin()
+                .lock()
+                .read_line(&mut password)
+                .context("failed to read password from stdin")?;
+            let password = password.trim_end_matches(['\r', '\n']);
+            auth_service::validate_password_public(password)?;
+            let hash = auth_service::hash_password_public(password)?;
+            user_repo::update_password(db, user.id, &hash).await?;
+            let revoked = user_repo::revoke_all_refresh_tokens(db, user.id).await?;
+            audit_log(db, "admin_reset_password", user.id).await?;
+            println!("Password updated for '{username}'; revoked {revoked} session(s)");
+        }
+        UsersCommand::Purge { username, yes } => {
+            if !yes {
+                bail!("purging deletes all messages and files permanently; pass --yes");
+            }
+            let user = find_user(db, &username).await?;
+            let files = file_repo::list_all_user_files(db, user.id).await?;
+            let mut files_deleted = 0u64;
+            for file in &files {
+                if upload_service::delete_file(db, storage, file).await? {
+                    files_deleted += 1;
+                }
+            }
+            let messages_deleted = message_repo::hard_delete_user_messages(db, user.id).await?;
+            user_repo::insert_audit_log(
+                db,
+                Uuid::now_v7(),
+                None,
+                "admin_purge_user_messages",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "target_user_id": user.id,
+                    "messages_deleted": messages_deleted,
+                    "files_deleted": files_deleted,
+                    "via": "cli",
+                })),
+            )
+            .await?;
+            println!("Purged '{username}': {messages_deleted} message(s), {files_deleted} file(s)");
+        }
+    }
+    Ok(())
+}
+
+async fn invites(db: &Db, cmd: InvitesCommand) -> anyhow::Result<()> {
+    match cmd {
+        InvitesCommand::Create {
+            max_uses,
+            expires_hours,
+        } => {
+            use rand::Rng;
+            use rand::distributions::Alphanumeric;
+            let code: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(12)
+                .map(char::from)
+                .collect();
+            let expires_at =
+                expires_hours.map(|h| chrono::Utc::now() + chrono::TimeDelta::hours(h));
+            let invite = registration_invite_repo::create_invite(
+                db,
+                Uuid::now_v7(),
+                &code,
+                None,
+                max_uses,
+                expires_at,
+            )
+            .await?;
+            user_repo::insert_audit_log(
+                db,
+                Uuid::now_v7(),
+                None,
+                "admin_create_invite",
+                None,
+                None,
+                Some(serde_json::json!({ "invite_id": invite.id, "via": "cli" })),
+            )
+            .await?;
+            println!("{}", invite.code);
+        }
+        InvitesCommand::List => {
+            let invites = registration_invite_repo::list_invites(db).await?;
+            println!("{:<14} {:>9} {:<25} CREATED", "CODE", "USES", "EXPIRES");
+            for invite in invites {
+                let uses = match invite.max_uses {
+                    Some(max) => format!("{}/{max}", invite.used_count),
+                    None => invite.used_count.to_string(),
+                };
+                let expires = invite
+                    .expires_at
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_else(|| "never".to_string());
+                println!(
+                    "{:<14} {uses:>9} {expires:<25} {}",
+                    invite.code,
+                    invite.created_at.to_rfc3339()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn audit(
+    db: &Db,
+    action: Option<&str>,
+    username: Option<&str>,
+    limit: i64,
+) -> anyhow::Result<()> {
+    let user_id = match username {
+        Some(name) => Some(find_user(db, name).await?.id),
+        None => None,
+    };
+    // The repo caps each page at 100 rows.
+    let mut offset = 0;
+    while offset < limit {
+        let page = (limit - offset).min(100);
+        let entries = audit_repo::query_audit_log(db, action, user_id, page, offset).await?;
+        for entry in &entries {
+            let actor = entry
+                .user_id
+                .map(|u| u.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            let metadata = entry
+                .metadata
+                .as_ref()
+                .map(|m| m.to_string())
+                .unwrap_or_default();
+            println!(
+                "{} {:<28} {actor:<36} {metadata}",
+                entry.created_at.to_rfc3339(),
+                entry.action
+            );
+        }
+        if (entries.len() as i64) < page {
+            break;
+        }
+        offset += page;
+    }
+    Ok(())
+}
+
+async fn files(
+    db: &Db,
+    config: &Config,
+    storage: &dyn Storage,
+    cmd: FilesCommand,
+) -> anyhow::Result<()> {
+    match cmd {
+        FilesCommand::Orphans { delete } => {
+            if delete {
+                let repaired = file_repo::repair_blob_refs(db).await?;
+                if repaired > 0 {
+                    println!("Repaired reference counts of {repaired} blob(s)");
+                }
+            }
+            let report = orphan_files::scan(db, storage, &config.file_storage_path).await?;
+            for key in &report.storage_only {
+                println!("unreferenced {key}");
+            }
+            for blob in &report.unreferenced {
+                println!("unused       {}", blob.storage_path);
+            }
+            for (id, path) in &report.missing_blobs {
+                println!("missing      {id} {path}");
+            }
+            println!(
+                "{} stored blob(s) without a record, {} blob record(s) no file uses, \
+                 {} file record(s) without a blob",
+                report.storage_only.len(),
+                report.unreferenced.len(),
+                report.missing_blobs.len()
+            );
+            if delete {
+                let removed = orphan_files::remove(db, storage, &report).await;
+                println!("Removed {removed} blob(s)");
+            }
+        }
+        FilesCommand::RegenThumbnails { all } => {
+            let (mut generated, mut failed) = (0u64, 0u64);
+            for file in file_repo::list_previewable_files(db).await? {
+                let has_thumb = match &file.thumbnail_path {
+                    Some(p) => storage.size(p).await?.is_some(),
+                    None => false,
+                };
+                // Images stored before variants and perceptual hashes existed
+                // only have a thumbnail
+                let is_image = file
+                    .content_type
+                    .as_deref()
+                    .is_some_and(|ct| ct.starts_with("image/"));
+                if has_thumb && (file.phash.is_some() || !is_image) && !all {
+                    continue;
+                }
+                match regen_thumbnail(db, storage, config, &file).await {
+                    Ok(true) => generated += 1,
+                    Ok(false) => {}
+                    Err(e) => {
+                        failed += 1;
+                        eprintln!("warning: {}: {e:#}", file.id);
+                    }
+                }
+            }
+            println!("Generated {generated} thumbnail(s), {failed} failure(s)");
+        }
+    }
+    Ok(())
+}
+
+async fn regen_thumbnail(
+    db: &Db,
+    storage: &dyn Storage,
+    config: &Config,
+    file: &chatalot_db::models::file::FileRecord,
+) -> anyhow::Result<bool> {
+    let content_type = file.content_type.as_deref().unwrap_or_default();
+    let data = storage
+        .read(&file.storage_path)
+        .await
+        .with_context(|| format!("read {}", file.storage_path))?;
+    let preview = if thumbnail_service::is_media(content_type) {
+        // The preview tools read from disk
+        let tmp = upload_service::partial_path(&config.file_storage_path, Uuid::new_v4());
+        if let Some(dir) = tmp.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&tmp, &data)
+            .await
+            .with_context(|| format!("write {}", tmp.display()))?;
+        let media =
+            thumbnail_service::generate_media_preview(&config.previews, &tmp, content_type).await;
+        let _ = tokio::fs::remove_file(&tmp).await;
+        let media = media.map_err(anyhow::Error::msg)?.unwrap_or_default();
+        upload_service::store_media_preview(storage, &file.storage_path, media).await
+    } else {
+        let Some(image) = thumbnail_service::generate_image_preview(&data, content_type)
+            .await
+            .map_err(anyhow::Error::msg)?
+        else {
+            return Ok(false);
+        };
+        upload_service::store_image_preview(storage, &file.storage_path, image).await
+    };
+    let mut preview = preview.as_blob_preview();
+    let generated = preview.thumbnail_path.is_some();
+    if !generated {
+        // Keep the old thumbnail rather than lose it to a failed run
+        preview.thumbnail_path = file.thumbnail_path.as_deref();
+    }
+    file_repo::set_preview(db, &file.storage_path, &preview).await?;
+    Ok(generated)
+}
+
+async fn blocklist_cmd(db: &Db, cmd: BlocklistCommand) -> anyhow::Result<()> {
+    match cmd {
+        BlocklistCommand::Export { output } => {
+            let file = blocklist::export(db).await?;
+            let json = serde_json::to_string_pretty(&file)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, json + "\n")
+                        .with_context(|| format!("write {}", path.display()))?;
+                    eprintln!(
+                        "Exported {} entries to {}",
+                        file.entries.len(),
+                        path.display()
+                    );
+                }
+                None => println!("{json}"),
+            }
+        }
+        BlocklistCommand::Import { input } => {
+            let json = std::fs::read_to_string(&input)
+                .with_context(|| format!("read {}", input.display()))?;
+            let file = serde_json::from_str(&json)
+                .with_context(|| format!("parse {}", input.display()))?;
+            let entries = blocklist::parse(&file).map_err(anyhow::Error::msg)?;
+            let imported = blocklist::import(db, &entries, None).await?;
+            user_repo::insert_audit_log(
+                db,
+                Uuid::now_v7(),
+                None,
+                "admin_import_blocklist",
+                None,
+                None,
+                Some(serde_json::json!({ "imported": imported, "via": "cli" })),
+            )
+            .await?;
+            println!("Imported {imported} entries");
+        }
+    }
+    Ok(())
+}
+
+async fn storage_usage(db: &Db) -> anyhow::Result<()> {
+    let stats = file_repo::storage_stats(db).await?;
+    let (mut files, mut bytes) = (0i64, 0i64);
+    println!("{:<32} {:>8} {:>14}", "USER", "FILES", "BYTES");
+    for stat in &stats.per_user {
+        let name = user_repo::find_by_id(db, stat.uploader_id)
+            .await?
+            .map(|u| u.username)
+            .unwrap_or_else(|| stat.uploader_id.to_string());
+        println!("{name:<32} {:>8} {:>14}", stat.file_count, stat.total_bytes);
+        files += stat.file_count;
+        bytes += stat.total_bytes;
+    }
+    println!("{:<32} {files:>8} {bytes:>14}", "TOTAL");
+
+    for (heading, scopes) in [
+        ("COMMUNITY", &stats.per_community),
+        ("GROUP", &stats.per_group),
+    ] {
+        if scopes.is_empty() {
+            continue;
+        }
+        println!();
+        println!(
+            "{heading:<32} {:>8} {:>14} {:>14}",
+            "FILES", "BYTES", "QUOTA"
+        );
+        for stat in scopes {
+            let quota = stat
+                .quota_bytes
+                .map_or_else(|| "-".to_string(), |q| q.to_string());
+            println!(
+                "{:<32} {:>8} {:>14} {quota:>14}",
+                stat.name, stat.file_count, stat.total_bytes
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn migrate_storage(
+    db: &Db,
+    config: &Config,
+    from: &str,
+    to: &str,
+    delete_source: bool,
+) -> anyhow::Result<()> {
+    if from == to {
+        bail!("--from and --to name the same backend");
+    }
+    let source = storage::open(from, config)?;
+    let dest = storage::open(to, config)?;
+
+    // Rows written before storage keys hold absolute local paths; keys work
+    // with every backend, so rewrite them first.
+    let prefix = format!("{}/", config.file_storage_path.trim_end_matches('/'));
+    let rewritten = file_repo::strip_path_prefix(db, &prefix).await?
+        + custom_emoji_repo::strip_path_prefix(db, &prefix).await?;
+
+    let objects = source
+        .list("")
+        .await
+        .with_context(|| format!("failed to list {from} storage"))?;
+    let (mut copied, mut present, mut failed, mut bytes) = (0u64, 0u64, 0u64, 0u64);
+    for object in &objects {
+        // Partial uploads stay on local disk, and top-level keys are
+        // readiness probes.
+        if object.key.starts_with("uploads/") || !object.key.contains('/') {
+            continue;
+        }
+        if dest.size(&object.key).await? == Some(object.size) {
+            present += 1;
+        } else {
+            let result = match source.get(&object.key).await {
+                Ok(body) => dest.put_stream(&object.key, body, object.size).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                failed += 1;
+                eprintln!("warning: {}: {e}", object.key);
+                continue;
+            }
+            copied += 1;
+            bytes += object.size;
+        }
+        if delete_source && let Err(e) = source.delete(&object.key).await {
+            eprintln!("warning: failed to delete {} from {from}: {e}", object.key);
+        }
+    }
+
+    user_repo::insert_audit_log(
+        db,
+        Uuid::now_v7(),
+        None,
+        "admin_migrate_storage",
+        None,
+        None,
+        Some(serde_json::json!({
+            "from": from,
+            "to": to,
+            "copied": copied,
+            "failed": failed,
+            "delete_source": delete_source,
+            "via": "cli",
+        })),
+    )
+    .await?;
+    println!(
+        "Copied {copied} blob(s) ({bytes} bytes) from {from} to {to}; {present} already present"
+    );
+    if rewritten > 0 {
+        println!("Rewrote {rewritten} stored path(s) as storage keys");
+    }
+    if failed > 0 {
+        bail!("{failed} blob(s) could not be copied; rerun to retry");
+    }
+    if config.storage_backend != to {
+        println!("Set STORAGE_BACKEND={to} and restart the server to use the new backend");
+    }
+    Ok(())
+}
+
+async fn migrations(db: &Db, cmd: MigrationsCommand) -> anyhow::Result<()> {
+    match cmd {
+        MigrationsCommand::Status => {
+            let status = chatalot_db::pool::migration_status(db).await?;
+            for m in &status {
+                let state = m
+                    .installed_on
+                    .map(|t| format!("applied {}", t.to_rfc3339()))
+                    .unwrap_or_else(|| "pending".to_string());
+                println!("{:>4} {:<40} {state}", m.version, m.description);
+            }
+            let pending = status.iter().filter(|m| m.installed_on.is_none()).count();
+            println!("{pending} pending migration(s)");
+        }
+        MigrationsCommand::Run => {
+            chatalot_db::pool::run_migrations(db).await?;
+            println!("Migrations applied");
+        }
+    }
+    Ok(())
+}
+
+fn read_passphrase(file: Option<&Path>) -> anyhow::Result<Option<String>> {
+    let passphrase = match file {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            text.lines().next().unwrap_or_default().to_string()
+        }
+        None => match std::env::var("CHATALOT_BACKUP_PASSPHRASE") {
+            Ok(p) => p,
+            Err(_) => return Ok(None),
+        },
+    };
+    if passphrase.is_empty() {
+        bail!("the backup passphrase is empty");
+    }
+    Ok(Some(passphrase))
+}
+
+async fn backup_cmd(db: &Db, config: &Config, cmd: BackupCommand) -> anyhow::Result<()> {
+    match cmd {
+        BackupCommand::Create {
+            output,
+            passphrase_file,
+        } => {
+            let passphrase = read_passphrase(passphrase_file.as_deref())?;
+            if config.storage_backend != "local" {
+                eprintln!(
+                    "warning: blobs in {} storage are not included; back up the bucket separately",
+                    config.storage_backend
+                );
+            }
+            let manifest = backup::create(
+                db,
+                &config.file_storage_path,
+                &output,
+                passphrase.as_deref(),
+            )
+            .await?;
+            user_repo::insert_audit_log(
+                db,
+                Uuid::now_v7(),
+                None,
+                "admin_create_backup",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "encrypted": passphrase.is_some(),
+                    "schema_version": manifest.schema_version,
+                    "via": "cli",
+                })),
+            )
+            .await?;
+            println!(
+                "Wrote {}: {} table(s), {} file(s), {} bytes before compression{}",
+                output.display(),
+                manifest.tables.len(),
+                manifest.file_count(),
+                manifest.total_bytes(),
+                if passphrase.is_some() {
+                    ", encrypted"
+                } else {
+                    ""
+                }
+            );
+        }
+        BackupCommand::Verify {
+            archive,
+            passphrase_file,
+        } => {
+            let passphrase = read_passphrase(passphrase_file.as_deref())?;
+            let manifest = backup::verify(&archive, passphrase.as_deref()).await?;
+            println!(
+                "OK: created {} by server {}, schema version {}, {} table(s), {} file(s)",
+                manifest.created_at.to_rfc3339(),
+                manifest.server_version,
+                manifest.schema_version,
+                manifest.tables.len(),
+                manifest.file_count()
+            );
+        }
+        BackupCommand::Restore {
+            archive,
+            passphrase_file,
+            force,
+        } => {
+            let passphrase = read_passphrase(passphrase_file.as_deref())?;
+            let summary = backup::restore(
+                db,
+                &config.file_storage_path,
+                &archive,
+                passphrase.as_deref(),
+                force,
+            )
+            .await?;
+            user_repo::insert_audit_log(
+                db,
+                Uuid::now_v7(),
+                None,
+                "admin_restore_backup",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "created_at": summary.manifest.created_at,
+                    "schema_version": summary.manifest.schema_version,
+                    "via": "cli",
+                })),
+            )
+            .await?;
+            println!(
+                "Restored {} row(s) and {} file(s) from a backup taken {}",
+                summary.rows,
+                summary.files,
+                summary.manifest.created_at.to_rfc3339()
+            );
+            if let Some((old, new)) = summary.rewritten_root {
+                println!("Rewrote stored file paths from {old} to {new}");
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,100 +1,579 @@
-use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+use anyhow::{Context, Result, bail};
+
+use crate::middleware::admin_network::IpNetwork;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Config {
+    /// TOML file the settings were read from, if any (re-read on SIGHUP).
+    pub config_file: Option<PathBuf>,
     pub database_url: String,
     pub listen_addr: String,
     pub jwt_private_key_path: String,
     pub jwt_public_key_path: String,
     pub totp_encryption_key: String,
     pub file_storage_path: String,
+    /// Where blobs are kept: `local` (under `file_storage_path`) or `s3`.
+    pub storage_backend: String,
+    /// S3-compatible object storage, configured when `S3_BUCKET` is set.
+    pub s3: Option<S3Config>,
     pub max_file_size_mb: u64,
+    /// External tools that render video, audio and PDF previews.
+    pub previews: PreviewConfig,
+    /// Malware scanning of uploads (None unless `CLAMD_ADDRESS` is set).
+    pub malware_scan: Option<ScanConfig>,
     pub github_api_token: Option<String>,
     pub github_repo_owner: Option<String>,
     pub github_repo_name: Option<String>,
     pub admin_username: Option<String>,
-    pub registration_mode: String,
-    pub community_creation_mode: String,
     pub public_url: Option<String>,
-    /// ICE servers for WebRTC (JSON array of {urls, username?, credential?}).
-    pub ice_servers_json: Option<String>,
     /// VAPID private key for Web Push (base64 URL-safe encoded).
     pub vapid_private_key: Option<String>,
     /// VAPID public key for Web Push (base64 URL-safe encoded).
     pub vapid_public_key: Option<String>,
+    /// Accept UnifiedPush/ntfy subscriptions.
+    pub unifiedpush_enabled: bool,
+    /// If non-empty, UnifiedPush endpoints must be on one of these hosts
+    /// (which may then also use plain HTTP or private addresses, e.g. a local ntfy).
+    pub unifiedpush_allowed_hosts: Vec<String>,
+    /// Seconds to wait on shutdown for connections and background jobs to finish.
+    pub shutdown_deadline_secs: u64,
+    /// Expose Prometheus metrics at `/metrics`.
+    pub metrics_enabled: bool,
+    /// Optional second listener for operator endpoints (e.g. `127.0.0.1:9090`).
+    /// When set, `/metrics` is served only there, never on `listen_addr`.
+    pub admin_listen_addr: Option<String>,
+    /// Networks allowed to reach operator endpoints such as `/api/health/ready`.
+    pub admin_networks: Vec<IpNetwork>,
+    /// Readiness fails when `file_storage_path` has less free space than this.
+    pub min_free_storage_mb: u64,
+    /// Settings that are reloaded on SIGHUP. This copy holds the startup
+    /// values; request handling reads the current ones via `AppState::live()`.
+    pub live: LiveConfig,
+}
+
+/// Settings that can change without a restart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveConfig {
+    pub registration_mode: String,
+    pub community_creation_mode: String,
     /// Per-user upload quota in MB (0 = unlimited). Default 500 MB.
     pub upload_quota_mb: u64,
+    /// ICE servers for WebRTC (JSON array of {urls, username?, credential?}).
+    pub ice_servers_json: Option<String>,
+    /// Per-IP limit for all requests.
+    pub rate_limit: RateLimit,
+    /// Stricter per-IP limit for login/registration.
+    pub auth_rate_limit: RateLimit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Address the bucket as `endpoint/bucket` rather than `bucket.endpoint`.
+    pub path_style: bool,
+    /// Redirect downloads to presigned URLs instead of proxying the bytes.
+    pub presigned_downloads: bool,
+    pub presign_expiry_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewConfig {
+    /// Run the tools at all; without them only images get thumbnails.
+    pub enabled: bool,
+    pub ffmpeg_path: String,
+    pub ffprobe_path: String,
+    pub pdftoppm_path: String,
+    /// Each tool run is killed after this long.
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanConfig {
+    pub clamd_address: ClamdAddress,
+    /// A scan that takes longer than this fails.
+    pub timeout_secs: u64,
+    /// Reject uploads that cannot be scanned instead of storing them unscanned.
+    pub fail_closed: bool,
+    /// Files uploaded this many days back are rescanned when the signatures change.
+    pub rescan_days: u32,
+}
+
+/// Where clamd listens: `unix:/path` (or just `/path`) or `host:port`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClamdAddress {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl FromStr for ClamdAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(path) = s.strip_prefix("unix:").or(s.starts_with('/').then_some(s)) {
+            return Ok(Self::Unix(path.into()));
+        }
+        match s.strip_prefix("tcp://").unwrap_or(s).rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                Ok(Self::Tcp(format!("{host}:{port}")))
+            }
+            _ => Err(format!(
+                "CLAMD_ADDRESS must be unix:/path/to/clamd.sock or host:port (got '{s}')"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_second: u32,
+    pub burst: u32,
 }
 
 impl Config {
-    pub fn from_env() -> Result<Self> {
-        let max_file_size_mb = std::env::var("MAX_FILE_SIZE_MB")
-            .unwrap_or_else(|_| "100".to_string())
-            .parse()
-            .unwrap_or(100u64)
-            .clamp(1, 10_000);
-
-        let registration_mode = std::env::var("REGISTRATION_MODE")
-            .unwrap_or_else(|_| "invite_only".to_string());
-        let registration_mode = match registration_mode.as_str() {
-            "open" | "invite_only" | "closed" => registration_mode,
-            other => {
-                tracing::warn!(
-                    "Invalid REGISTRATION_MODE '{other}', falling back to 'invite_only'"
-                );
-                "invite_only".to_string()
+    /// Load settings from the environment, layered over an optional TOML file
+    /// whose keys are the lowercase names of the environment variables.
+    ///
+    /// Missing required settings and unreadable files are errors. Invalid
+    /// values fall back to their defaults and are returned as problems, all
+    /// of which the server refuses; `chatalot-admin` only warns about them.
+    pub fn load(config_file: Option<&Path>) -> Result<(Self, Vec<String>)> {
+        let file = match config_file {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                toml::from_str::<toml::Table>(&text)
+                    .with_context(|| format!("failed to parse {}", path.display()))?
             }
+            None => toml::Table::new(),
+        };
+        let mut src = Source {
+            file,
+            used: HashSet::new(),
+            problems: Vec::new(),
         };
 
-        let community_creation_mode = std::env::var("COMMUNITY_CREATION_MODE")
-            .unwrap_or_else(|_| "admin_only".to_string());
-        let community_creation_mode = match community_creation_mode.as_str() {
-            "open" | "admin_only" => community_creation_mode,
-            other => {
-                tracing::warn!(
-                    "Invalid COMMUNITY_CREATION_MODE '{other}', falling back to 'admin_only'"
-                );
-                "admin_only".to_string()
-            }
+        let Some(database_url) = src.opt("database_url") else {
+            bail!("DATABASE_URL must be set");
+        };
+        let Some(totp_encryption_key) = src.opt("totp_encryption_key") else {
+            bail!("TOTP_ENCRYPTION_KEY must be set (hex-encoded 256-bit key)");
+        };
+
+        let listen_addr = src.string("listen_addr", "0.0.0.0:8080");
+        src.check_addr("listen_addr", &listen_addr);
+        let admin_listen_addr = src.opt("admin_listen_addr");
+        if let Some(ref addr) = admin_listen_addr {
+            src.check_addr("admin_listen_addr", addr);
+        }
+
+        let public_url = src.opt("public_url");
+        if let Some(ref u) = public_url
+            && !matches!(url::Url::parse(u), Ok(p) if matches!(p.scheme(), "http" | "https"))
+        {
+            src.problem("public_url", format!("must be an http(s) URL (got '{u}')"));
+        }
+
+        let storage_backend = src.one_of("storage_backend", "local", &["local", "s3"]);
+        let s3 = S3Config::from_source(&mut src)?;
+        if storage_backend == "s3" && s3.is_none() {
+            bail!("S3_BUCKET must be set when STORAGE_BACKEND is s3");
+        }
+
+        let admin_networks = src
+            .list("admin_networks", "127.0.0.0/8,::1")
+            .into_iter()
+            .filter_map(|s| {
+                let net = IpNetwork::parse(&s);
+                if net.is_none() {
+                    src.problem("admin_networks", format!("invalid network '{s}', ignored"));
+                }
+                net
+            })
+            .collect();
+
+        let config = Self {
+            config_file: config_file.map(Path::to_path_buf),
+            database_url,
+            listen_addr,
+            jwt_private_key_path: src.string("jwt_private_key_path", "./secrets/jwt_private.pem"),
+            jwt_public_key_path: src.string("jwt_public_key_path", "./secrets/jwt_public.pem"),
+            totp_encryption_key,
+            file_storage_path: src.string("file_storage_path", "./data/files"),
+            storage_backend,
+            s3,
+            max_file_size_mb: src.number("max_file_size_mb", 100, 1..=10_000),
+            previews: PreviewConfig {
+                enabled: src.bool("media_previews", true),
+                ffmpeg_path: src.string("ffmpeg_path", "ffmpeg"),
+                ffprobe_path: src.string("ffprobe_path", "ffprobe"),
+                pdftoppm_path: src.string("pdftoppm_path", "pdftoppm"),
+                timeout_secs: src.number("preview_timeout_secs", 15, 1..=300),
+            },
+            malware_scan: ScanConfig::from_source(&mut src)?,
+            github_api_token: src.opt("github_api_token"),
+            github_repo_owner: src.opt("github_repo_owner"),
+            github_repo_name: src.opt("github_repo_name"),
+            admin_username: src.opt("admin_username"),
+            public_url,
+            vapid_private_key: src.opt("vapid_private_key"),
+            vapid_public_key: src.opt("vapid_public_key"),
+            unifiedpush_enabled: src.bool("unifiedpush_enabled", false),
+            unifiedpush_allowed_hosts: src
+                .list("unifiedpush_allowed_hosts", "")
+                .into_iter()
+                .map(|h| h.to_ascii_lowercase())
+                .collect(),
+            shutdown_deadline_secs: src.number("shutdown_deadline_secs", 30, 1..=600),
+            metrics_enabled: src.bool("metrics_enabled", false),
+            admin_listen_addr,
+            admin_networks,
+            min_free_storage_mb: src.number("min_free_storage_mb", 1024, 0..=u64::MAX),
+            live: LiveConfig::from_source(&mut src),
         };
 
-        let ice_servers_json = std::env::var("ICE_SERVERS").ok();
-        if let Some(ref json) = ice_servers_json
-            && serde_json::from_str::<serde_json::Value>(json).is_err()
+        for key in src.file.keys() {
+            if !src.used.contains(key.as_str()) {
+                src.problems.push(format!("{key}: unknown setting"));
+            }
+        }
+        Ok((config, src.problems))
+    }
+}
+
+impl S3Config {
+    /// `None` unless a bucket is configured; a bucket without credentials is an error.
+    fn from_source(src: &mut Source) -> Result<Option<Self>> {
+        let region = src.string("s3_region", "us-east-1");
+        let endpoint = src.opt("s3_endpoint");
+        let path_style = src.bool("s3_path_style", endpoint.is_some());
+        let presigned_downloads = src.bool("s3_presigned_downloads", false);
+        let presign_expiry_secs = src.number("s3_presign_expiry_secs", 300, 1..=604_800);
+        let access_key_id = src.opt("s3_access_key_id");
+        let secret_access_key = src.opt("s3_secret_access_key");
+        let Some(bucket) = src.opt("s3_bucket") else {
+            return Ok(None);
+        };
+        let (Some(access_key_id), Some(secret_access_key)) = (access_key_id, secret_access_key)
+        else {
+            bail!("S3_ACCESS_KEY_ID and S3_SECRET_ACCESS_KEY must be set with S3_BUCKET");
+        };
+        let endpoint = endpoint.unwrap_or_else(|| format!("https://s3.{region}.amazonaws.com"));
+        if !matches!(url::Url::parse(&endpoint), Ok(u) if matches!(u.scheme(), "http" | "https") && u.has_host())
         {
-            tracing::warn!("ICE_SERVERS is not valid JSON, will be ignored");
+            bail!("S3_ENDPOINT must be an http(s) URL (got '{endpoint}')");
         }
+        Ok(Some(Self {
+            endpoint,
+            region,
+            bucket,
+            access_key_id,
+            secret_access_key,
+            path_style,
+            presigned_downloads,
+            presign_expiry_secs,
+        }))
+    }
+}
+
+impl ScanConfig {
+    /// `None` unless a clamd address is configured.
+    fn from_source(src: &mut Source) -> Result<Option<Self>> {
+        let timeout_secs = src.number("clamd_timeout_secs", 60, 1..=3600);
+        let fail_closed = src.bool("malware_scan_fail_closed", false);
+        let rescan_days = src.number("malware_rescan_days", 7, 0..=365);
+        let Some(address) = src.opt("clamd_address") else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            clamd_address: address.parse().map_err(anyhow::Error::msg)?,
+            timeout_secs,
+            fail_closed,
+            rescan_days,
+        }))
+    }
+}
 
-        let upload_quota_mb = std::env::var("UPLOAD_QUOTA_MB")
-            .unwrap_or_else(|_| "500".to_string())
-            .parse()
-            .unwrap_or(500u64)
-            .clamp(0, 100_000);
-
-        Ok(Self {
-            database_url: std::env::var("DATABASE_URL").context("DATABASE_URL must be set")?,
-            listen_addr: std::env::var("LISTEN_ADDR")
-                .unwrap_or_else(|_| "0.0.0.0:8080".to_string()),
-            jwt_private_key_path: std::env::var("JWT_PRIVATE_KEY_PATH")
-                .unwrap_or_else(|_| "./secrets/jwt_private.pem".to_string()),
-            jwt_public_key_path: std::env::var("JWT_PUBLIC_KEY_PATH")
-                .unwrap_or_else(|_| "./secrets/jwt_public.pem".to_string()),
-            totp_encryption_key: std::env::var("TOTP_ENCRYPTION_KEY")
-                .context("TOTP_ENCRYPTION_KEY must be set (hex-encoded 256-bit key)")?,
-            file_storage_path: std::env::var("FILE_STORAGE_PATH")
-                .unwrap_or_else(|_| "./data/files".to_string()),
-            max_file_size_mb,
-            github_api_token: std::env::var("GITHUB_API_TOKEN").ok(),
-            github_repo_owner: std::env::var("GITHUB_REPO_OWNER").ok(),
-            github_repo_name: std::env::var("GITHUB_REPO_NAME").ok(),
-            admin_username: std::env::var("ADMIN_USERNAME").ok(),
-            registration_mode,
-            community_creation_mode,
-            public_url: std::env::var("PUBLIC_URL").ok(),
+impl LiveConfig {
+    fn from_source(src: &mut Source) -> Self {
+        let ice_servers_json = src.opt("ice_servers");
+        let ice_servers_json = match ice_servers_json {
+            Some(json) if !is_json_array(&json) => {
+                src.problem(
+                    "ice_servers",
+                    "must be a JSON array of ICE servers, ignored",
+                );
+                None
+            }
+            other => other,
+        };
+
+        Self {
+            registration_mode: src.one_of(
+                "registration_mode",
+                "invite_only",
+                &["open", "invite_only", "closed"],
+            ),
+            community_creation_mode: src.one_of(
+                "community_creation_mode",
+                "admin_only",
+                &["open", "admin_only"],
+            ),
+            upload_quota_mb: src.number("upload_quota_mb", 500, 0..=100_000),
             ice_servers_json,
-            vapid_private_key: std::env::var("VAPID_PRIVATE_KEY").ok(),
-            vapid_public_key: std::env::var("VAPID_PUBLIC_KEY").ok(),
-            upload_quota_mb,
+            rate_limit: RateLimit {
+                per_second: src.number("rate_limit_per_second", 20, 1..=10_000),
+                burst: src.number("rate_limit_burst", 50, 1..=100_000),
+            },
+            auth_rate_limit: RateLimit {
+                per_second: src.number("auth_rate_limit_per_second", 5, 1..=1_000),
+                burst: src.number("auth_rate_limit_burst", 10, 1..=10_000),
+            },
+        }
+    }
+}
+
+fn is_json_array(json: &str) -> bool {
+    matches!(
+        serde_json::from_str::<serde_json::Value>(json),
+        Ok(serde_json::Value::Array(_))
+    )
+}
+
+/// Environment variables (`UPPER_CASE`) take precedence over file keys (`lower_case`).
+struct Source {
+    file: toml::Table,
+    used: HashSet<&'static str>,
+    problems: Vec<String>,
+}
+
+impl Source {
+    fn raw(&mut self, key: &'static str) -> Option<String> {
+        self.used.insert(key);
+        if let Ok(v) = std::env::var(key.to_ascii_uppercase()) {
+            return Some(v);
+        }
+        Some(match self.file.get(key)? {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Array(items) if items.iter().all(toml::Value::is_str) => items
+                .iter()
+                .filter_map(toml::Value::as_str)
+                .collect::<Vec<_>>()
+                .join(","),
+            // Structured values (e.g. ice_servers as an array of tables) become JSON
+            v @ (toml::Value::Array(_) | toml::Value::Table(_)) => {
+                serde_json::to_string(v).unwrap_or_default()
+            }
+            v => v.to_string(),
         })
     }
+
+    fn problem(&mut self, key: &str, message: impl std::fmt::Display) {
+        self.problems.push(format!("{key}: {message}"));
+    }
+
+    /// Empty values count as unset.
+    fn opt(&mut self, key: &'static str) -> Option<String> {
+        self.raw(key).filter(|v| !v.trim().is_empty())
+    }
+
+    fn string(&mut self, key: &'static str, default: &str) -> String {
+        self.opt(key).unwrap_or_else(|| default.to_string())
+    }
+
+    fn list(&mut self, key: &'static str, default: &str) -> Vec<String> {
+        self.string(key, default)
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    fn number<T>(&mut self, key: &'static str, default: T, range: std::ops::RangeInclusive<T>) -> T
+    where
+        T: FromStr + PartialOrd + Copy + std::fmt::Display,
+    {
+        let Some(v) = self.opt(key) else {
+            return default;
+        };
+        match v.trim().parse::<T>() {
+            Ok(n) if range.contains(&n) => n,
+            Ok(n) => {
+                let clamped = if n < *range.start() {
+                    *range.start()
+                } else {
+                    *range.end()
+                };
+                self.problem(
+                    key,
+                    format!(
+                        "{n} is outside {}..={}, using {clamped}",
+                        range.start(),
+                        range.end()
+                    ),
+                );
+                clamped
+            }
+            Err(_) => {
+                self.problem(key, format!("'{v}' is not a number, using {default}"));
+                default
+            }
+        }
+    }
+
+    fn bool(&mut self, key: &'static str, default: bool) -> bool {
+        match self.opt(key).as_deref() {
+            None => default,
+            Some("true" | "1") => true,
+            Some("false" | "0") => false,
+            Some(v) => {
+                self.problem(key, format!("'{v}' is not true/false, using {default}"));
+                default
+            }
+        }
+    }
+
+    fn one_of(&mut self, key: &'static str, default: &str, allowed: &[&str]) -> String {
+        match self.opt(key) {
+            None => default.to_string(),
+            Some(v) if allowed.contains(&v.as_str()) => v,
+            Some(v) => {
+                self.problem(
+                    key,
+                    format!(
+                        "'{v}' must be one of {}, using '{default}'",
+                        allowed.join(", ")
+                    ),
+                );
+                default.to_string()
+            }
+        }
+    }
+
+    /// `host:port` with a valid port (the host may be a name).
+    fn check_addr(&mut self, key: &str, addr: &str) {
+        let valid = addr
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+        if !valid {
+            self.problem(key, format!("'{addr}' is not a host:port address"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(toml: &str) -> Source {
+        Source {
+            file: toml::from_str(toml).unwrap(),
+            used: HashSet::new(),
+            problems: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn invalid_values_fall_back_and_are_reported() {
+        let mut src = source(
+            r#"
+            registration_mode = "sometimes"
+            upload_quota_mb = 200000
+            rate_limit_burst = "lots"
+            "#,
+        );
+        let live = LiveConfig::from_source(&mut src);
+        assert_eq!(live.registration_mode, "invite_only");
+        assert_eq!(live.upload_quota_mb, 100_000);
+        assert_eq!(live.rate_limit.burst, 50);
+        assert_eq!(src.problems.len(), 3);
+    }
+
+    #[test]
+    fn file_values_are_typed_and_structured() {
+        let mut src = source(
+            r#"
+            community_creation_mode = "open"
+            auth_rate_limit_per_second = 2
+            [[ice_servers]]
+            urls = "stun:stun.example.com:3478"
+            "#,
+        );
+        let live = LiveConfig::from_source(&mut src);
+        assert_eq!(live.community_creation_mode, "open");
+        assert_eq!(live.auth_rate_limit.per_second, 2);
+        assert_eq!(
+            live.ice_servers_json.as_deref(),
+            Some(r#"[{"urls":"stun:stun.example.com:3478"}]"#)
+        );
+        assert!(src.problems.is_empty());
+    }
+
+    #[test]
+    fn clamd_addresses_are_unix_sockets_or_host_port() {
+        assert!(ScanConfig::from_source(&mut source("")).unwrap().is_none());
+        let scan =
+            ScanConfig::from_source(&mut source(r#"clamd_address = "/run/clamav/clamd.ctl""#))
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            scan.clamd_address,
+            ClamdAddress::Unix("/run/clamav/clamd.ctl".into())
+        );
+        assert_eq!(scan.rescan_days, 7);
+        assert!(!scan.fail_closed);
+
+        assert_eq!(
+            "unix:/tmp/c.sock".parse(),
+            Ok(ClamdAddress::Unix("/tmp/c.sock".into()))
+        );
+        assert_eq!(
+            "clamav:3310".parse(),
+            Ok(ClamdAddress::Tcp("clamav:3310".into()))
+        );
+        assert_eq!(
+            "tcp://[::1]:3310".parse(),
+            Ok(ClamdAddress::Tcp("[::1]:3310".into()))
+        );
+        assert!("clamav".parse::<ClamdAddress>().is_err());
+        assert!(ScanConfig::from_source(&mut source(r#"clamd_address = "clamav:x""#)).is_err());
+    }
+
+    #[test]
+    fn s3_needs_credentials_and_defaults_to_aws() {
+        assert!(S3Config::from_source(&mut source("")).unwrap().is_none());
+        assert!(S3Config::from_source(&mut source(r#"s3_bucket = "chatalot""#)).is_err());
+
+        let mut src = source(
+            r#"
+            s3_bucket = "chatalot"
+            s3_region = "eu-west-1"
+            s3_access_key_id = "AKIA"
+            s3_secret_access_key = "secret"
+            "#,
+        );
+        let s3 = S3Config::from_source(&mut src).unwrap().unwrap();
+        assert_eq!(s3.endpoint, "https://s3.eu-west-1.amazonaws.com");
+        assert!(!s3.path_style);
+        assert!(!s3.presigned_downloads);
+
+        let mut src = source(
+            r#"
+            s3_bucket = "chatalot"
+            s3_endpoint = "http://localhost:9000"
+            s3_access_key_id = "minio"
+            s3_secret_access_key = "minio123"
+            "#,
+        );
+        let s3 = S3Config::from_source(&mut src).unwrap().unwrap();
+        assert!(s3.path_style, "custom endpoints default to path-style");
+    }
 }
@@ -19,6 +19,14 @@ pub enum AppError {
     #[error("validation error: {0}")]
     Validation(String),
 
+    /// An upload would take a storage quota over its limit. `scope` names
+    /// the quota: `user`, `role`, `group` or `community`.
+    #[error("quota exceeded: {message}")]
+    QuotaExceeded {
+        scope: &'static str,
+        message: String,
+    },
+
     #[error("internal error: {0}")]
     Internal(String),
 
@@ -37,6 +45,11 @@ impl IntoResponse for AppError {
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg.clone()),
             AppError::Conflict(msg) => (StatusCode::CONFLICT, "conflict", msg.clone()),
             AppError::Validation(msg) => (StatusCode::BAD_REQUEST, "validation_error", msg.clone()),
+            AppError::QuotaExceeded { message, .. } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "quota_exceeded",
+                message.clone(),
+            ),
             AppError::Internal(msg) => {
                 tracing::error!("Internal error: {msg}");
                 (
@@ -63,12 +76,15 @@ impl IntoResponse for AppError {
             }
         };
 
-        let body = json!({
+        let mut body = json!({
             "error": {
                 "code": code,
                 "message": message,
             }
         });
+        if let AppError::QuotaExceeded { scope, .. } = &self {
+            body["error"]["scope"] = json!(scope);
+        }
 
         (status, axum::Json(body)).into_response()
     }
@@ -0,0 +1,646 @@
+//! The built-in background jobs.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use chatalot_db::repos::{file_repo, message_archive_repo, settings_repo};
+use chrono::Datelike;
+use futures_util::FutureExt;
+
+use crate::app_state::AppState;
+use crate::jobs::{JobScope, JobSpec};
+use crate::metrics::METRICS;
+use crate::services::event_relay::{self, RelayedEvent};
+use crate::services::{file_retention, malware_scan, orphan_files, upload_service};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub static JOBS: &[JobSpec] = &[
+    JobSpec {
+        name: "typing_timeout",
+        description: "Expire typing indicators older than 10s",
+        scope: JobScope::Node,
+        default_interval: Duration::from_secs(5),
+        initial_delay: Duration::ZERO,
+        run: |state| typing_timeout(state).boxed(),
+    },
+    JobSpec {
+        name: "broadcast_channel_cleanup",
+        description: "Drop idle in-memory channel broadcast senders",
+        scope: JobScope::Node,
+        default_interval: Duration::from_secs(5 * 60),
+        initial_delay: Duration::ZERO,
+        run: |state| broadcast_channel_cleanup(state).boxed(),
+    },
+    JobSpec {
+        name: "cache_cleanup",
+        description: "Evict expired GIF search and link preview cache entries",
+        scope: JobScope::Node,
+        default_interval: Duration::from_secs(10 * 60),
+        initial_delay: Duration::ZERO,
+        run: |state| cache_cleanup(state).boxed(),
+    },
+    JobSpec {
+        name: "event_relay",
+        description: "Deliver events raised by cluster jobs to this node's connections",
+        scope: JobScope::Node,
+        default_interval: Duration::from_secs(2),
+        initial_delay: Duration::ZERO,
+        run: |state| event_relay(state).boxed(),
+    },
+    JobSpec {
+        name: "partial_upload_sweep",
+        description: "Delete this node's partial upload files that no upload tracks",
+        scope: JobScope::Node,
+        default_interval: HOUR,
+        initial_delay: Duration::from_secs(10 * 60),
+        run: |state| partial_upload_sweep(state).boxed(),
+    },
+    JobSpec {
+        name: "data_cleanup",
+        description: "Prune expired tokens, used prekeys, old audit logs, orphaned voice sessions and dead push subscriptions",
+        scope: JobScope::Cluster,
+        default_interval: HOUR,
+        initial_delay: MINUTE,
+        run: |state| data_cleanup(state).boxed(),
+    },
+    JobSpec {
+        name: "message_gc",
+        description: "Hard-delete messages soft-deleted more than 30 days ago",
+        scope: JobScope::Cluster,
+        default_interval: DAY,
+        initial_delay: Duration::from_secs(5 * 60),
+        run: |state| message_gc(state).boxed(),
+    },
+    JobSpec {
+        name: "orphan_file_cleanup",
+        description: "Remove stored files that have no database record",
+        scope: JobScope::Cluster,
+        default_interval: DAY,
+        initial_delay: Duration::from_secs(2 * 60 * 60),
+        run: |state| orphan_file_cleanup(state).boxed(),
+    },
+    JobSpec {
+        name: "file_retention",
+        description: "Delete files past their channel or community retention policy or message TTL",
+        scope: JobScope::Cluster,
+        default_interval: HOUR,
+        initial_delay: Duration::from_secs(20 * 60),
+        run: |state| file_retention(state).boxed(),
+    },
+    JobSpec {
+        name: "malware_rescan",
+        description: "Rescan recent uploads after a malware signature update",
+        scope: JobScope::Cluster,
+        default_interval: HOUR,
+        initial_delay: Duration::from_secs(15 * 60),
+        run: |state| malware_rescan(state).boxed(),
+    },
+    JobSpec {
+        name: "upload_expiry",
+        description: "Delete resumable uploads that were never finished",
+        scope: JobScope::Cluster,
+        default_interval: HOUR,
+        initial_delay: Duration::from_secs(10 * 60),
+        run: |state| upload_expiry(state).boxed(),
+    },
+    JobSpec {
+        name: "scheduled_messages",
+        description: "Deliver scheduled messages that are due",
+        scope: JobScope::Cluster,
+        default_interval: Duration::from_secs(30),
+        initial_delay: Duration::ZERO,
+        run: |state| scheduled_messages(state).boxed(),
+    },
+    JobSpec {
+        name: "message_expiry",
+        description: "Delete messages past their channel TTL",
+        scope: JobScope::Cluster,
+        default_interval: Duration::from_secs(5 * 60),
+        initial_delay: Duration::ZERO,
+        run: |state| message_expiry(state).boxed(),
+    },
+    JobSpec {
+        name: "message_partitions",
+        description: "Create monthly message partitions ahead of time",
+        scope: JobScope::Cluster,
+        default_interval: DAY,
+        initial_delay: Duration::ZERO,
+        run: |state| message_partitions(state).boxed(),
+    },
+    JobSpec {
+        name: "message_archival",
+        description: "Move cold message partitions to the archive tables",
+        scope: JobScope::Cluster,
+        default_interval: DAY,
+        initial_delay: Duration::from_secs(3 * 60 * 60),
+        run: |state| message_archival(state).boxed(),
+    },
+    JobSpec {
+        name: "timeout_cleanup",
+        description: "Remove expired user timeouts",
+        scope: JobScope::Cluster,
+        default_interval: Duration::from_secs(5 * 60),
+        initial_delay: Duration::ZERO,
+        run: |state| timeout_cleanup(state).boxed(),
+    },
+    JobSpec {
+        name: "quiet_hours",
+        description: "Apply quiet hours schedules to presence and deferred notifications",
+        scope: JobScope::Cluster,
+        default_interval: MINUTE,
+        initial_delay: Duration::ZERO,
+        run: |state| quiet_hours(state).boxed(),
+    },
+];
+
+async fn typing_timeout(state: Arc<AppState>) -> anyhow::Result<()> {
+    let expired = state
+        .connections
+        .expire_typing(std::time::Duration::from_secs(10));
+    for (channel_id, user_id) in expired {
+        state.connections.broadcast_to_channel(
+            channel_id,
+            chatalot_common::ws_messages::ServerMessage::UserStoppedTyping {
+                channel_id,
+                user_id,
+            },
+        );
+    }
+    Ok(())
+}
+
+async fn broadcast_channel_cleanup(state: Arc<AppState>) -> anyhow::Result<()> {
+    let removed = state.connections.cleanup_idle_channels();
+    if removed > 0 {
+        tracing::debug!("Cleaned up {removed} idle broadcast channels");
+    }
+    Ok(())
+}
+
+async fn cache_cleanup(_state: Arc<AppState>) -> anyhow::Result<()> {
+    let gifs = crate::routes::gifs::cleanup_gif_cache();
+    let previews = crate::routes::link_preview::cleanup_preview_cache();
+    if gifs > 0 || previews > 0 {
+        tracing::debug!(
+            "Cache cleanup: evicted {gifs} GIF entries, {previews} link preview entries"
+        );
+    }
+    Ok(())
+}
+
+/// Each step runs even if an earlier one failed; failures are reported together.
+async fn data_cleanup(state: Arc<AppState>) -> anyhow::Result<()> {
+    let db = state.db.clone();
+    let mut errors: Vec<String> = Vec::new();
+    // Delete refresh tokens expired more than 7 days ago
+    match chatalot_db::db::query(format!(
+        "DELETE FROM refresh_tokens WHERE expires_at < {}",
+        db.backend().now_plus_secs("-7 * 86400")
+    ))
+    .execute(&db)
+    .await
+    {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                tracing::info!("Cleaned up {} expired refresh tokens", r.rows_affected());
+            }
+        }
+        Err(e) => errors.push(format!("Failed to clean expired tokens: {e}")),
+    }
+    // Delete used one-time prekeys older than 30 days
+    match chatalot_db::db::query(format!(
+        "DELETE FROM one_time_prekeys WHERE used = true AND created_at < {}",
+        db.backend().now_plus_secs("-30 * 86400")
+    ))
+    .execute(&db)
+    .await
+    {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                tracing::info!("Cleaned up {} used prekeys", r.rows_affected());
+            }
+        }
+        Err(e) => errors.push(format!("Failed to clean used prekeys: {e}")),
+    }
+    // Prune audit logs older than 90 days
+    match chatalot_db::db::query(format!(
+        "DELETE FROM audit_log WHERE created_at < {}",
+        db.backend().now_plus_secs("-90 * 86400")
+    ))
+    .execute(&db)
+    .await
+    {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                tracing::info!(
+                    "Cleaned up {} audit log entries older than 90 days",
+                    r.rows_affected()
+                );
+            }
+        }
+        Err(e) => errors.push(format!("Failed to clean old audit logs: {e}")),
+    }
+    // End orphaned voice sessions (no participants, still active)
+    match chatalot_db::db::query(
+        "UPDATE voice_sessions SET ended_at = NOW() WHERE ended_at IS NULL AND NOT EXISTS (SELECT 1 FROM voice_session_participants WHERE session_id = voice_sessions.id AND left_at IS NULL)"
+    )
+        .execute(&db)
+        .await
+    {
+        Ok(r) => {
+            if r.rows_affected() > 0 {
+                tracing::info!("Cleaned up {} orphaned voice sessions", r.rows_affected());
+            }
+        }
+        Err(e) => errors.push(format!("Failed to clean orphaned voice sessions: {e}")),
+    }
+    // Clean up failed and stale push subscriptions
+    match chatalot_db::repos::push_subscription_repo::cleanup_failed(&db).await {
+        Ok(n) if n > 0 => tracing::info!("Cleaned up {n} failed push subscriptions"),
+        Err(e) => errors.push(format!("Failed to clean push subscriptions: {e}")),
+        _ => {}
+    }
+    match chatalot_db::repos::push_subscription_repo::cleanup_stale(&db).await {
+        Ok(n) if n > 0 => tracing::info!("Cleaned up {n} stale push subscriptions"),
+        Err(e) => errors.push(format!("Failed to clean stale push subscriptions: {e}")),
+        _ => {}
+    }
+    if !errors.is_empty() {
+        anyhow::bail!(errors.join("; "));
+    }
+    Ok(())
+}
+
+async fn message_gc(state: Arc<AppState>) -> anyhow::Result<()> {
+    let n = chatalot_db::repos::message_repo::gc_soft_deleted(&state.db, 30)
+        .await
+        .context("GC soft-delete cleanup failed")?;
+    if n > 0 {
+        tracing::info!("GC: hard-deleted {n} messages soft-deleted >30 days ago");
+    }
+    Ok(())
+}
+
+/// Removes stored blobs with no DB record.
+async fn orphan_file_cleanup(state: Arc<AppState>) -> anyhow::Result<()> {
+    let repaired = file_repo::repair_blob_refs(&state.db)
+        .await
+        .context("failed to repair blob reference counts")?;
+    if repaired > 0 {
+        tracing::warn!("Orphan cleanup: repaired reference counts of {repaired} blobs");
+    }
+    let report = orphan_files::scan(
+        &state.db,
+        state.storage.as_ref(),
+        &state.config.file_storage_path,
+    )
+    .await?;
+    let removed = orphan_files::remove(&state.db, state.storage.as_ref(), &report).await;
+    if removed > 0 {
+        tracing::info!("Orphan cleanup: removed {removed} blobs no file refers to");
+    }
+    if !report.missing_blobs.is_empty() {
+        tracing::warn!(
+            "Orphan cleanup: {} file records have no blob in storage",
+            report.missing_blobs.len()
+        );
+    }
+    Ok(())
+}
+
+async fn file_retention(state: Arc<AppState>) -> anyhow::Result<()> {
+    let report = file_retention::enforce(&state.db, state.storage.as_ref())
+        .await
+        .context("failed to enforce file retention")?;
+    if report.deleted > 0 {
+        tracing::info!(
+            "File retention: deleted {} files ({} bytes)",
+            report.deleted,
+            report.bytes
+        );
+    }
+    if report.failed > 0 {
+        anyhow::bail!("{} files could not be deleted", report.failed);
+    }
+    Ok(())
+}
+
+async fn malware_rescan(state: Arc<AppState>) -> anyhow::Result<()> {
+    let Some(report) = malware_scan::rescan(&state).await? else {
+        return Ok(());
+    };
+    tracing::info!(
+        "Malware rescan with {}: {} blobs scanned, {} files quarantined, {} blobs too large to scan",
+        report.signatures,
+        report.scanned,
+        report.quarantined,
+        report.too_large
+    );
+    if report.failed > 0 {
+        anyhow::bail!("{} blobs could not be scanned", report.failed);
+    }
+    Ok(())
+}
+
+async fn upload_expiry(state: Arc<AppState>) -> anyhow::Result<()> {
+    let expired = upload_service::expire_uploads(&state)
+        .await
+        .context("failed to expire uploads")?;
+    if expired > 0 {
+        tracing::info!("Expired {expired} abandoned resumable uploads");
+    }
+    Ok(())
+}
+
+async fn partial_upload_sweep(state: Arc<AppState>) -> anyhow::Result<()> {
+    let removed = upload_service::sweep_partials(&state)
+        .await
+        .context("failed to sweep partial uploads")?;
+    if removed > 0 {
+        tracing::info!("Removed {removed} abandoned partial uploads");
+    }
+    Ok(())
+}
+
+async fn scheduled_messages(state: Arc<AppState>) -> anyhow::Result<()> {
+    let messages = chatalot_db::repos::scheduled_message_repo::get_due_messages(&state.db)
+        .await
+        .context("failed to query due scheduled messages")?;
+
+    for msg in messages {
+        // Check if user is suspended
+        if state.suspended_users.contains(&msg.user_id) {
+            tracing::info!("Dropping scheduled message {}: user is suspended", msg.id);
+            let _ =
+                chatalot_db::repos::scheduled_message_repo::delete_by_id(&state.db, msg.id).await;
+            continue;
+        }
+
+        // Verify user is still a member before delivering
+        match chatalot_db::repos::channel_repo::is_member(&state.db, msg.channel_id, msg.user_id)
+            .await
+        {
+            Ok(false) | Err(_) => {
+                tracing::info!(
+                    "Dropping scheduled message {}: user no longer a member",
+                    msg.id
+                );
+                let _ = chatalot_db::repos::scheduled_message_repo::delete_by_id(&state.db, msg.id)
+                    .await;
+                continue;
+            }
+            Ok(true) => {}
+        }
+
+        // Fetch channel for restriction checks and TTL
+        let channel = match chatalot_db::repos::channel_repo::get_channel(&state.db, msg.channel_id)
+            .await
+        {
+            Ok(Some(ch)) => ch,
+            _ => {
+                tracing::info!("Dropping scheduled message {}: channel not found", msg.id);
+                let _ = chatalot_db::repos::scheduled_message_repo::delete_by_id(&state.db, msg.id)
+                    .await;
+                continue;
+            }
+        };
+
+        // Check channel restrictions
+        let skip = 'check: {
+            if channel.archived || channel.read_only {
+                break 'check true;
+            }
+            // For DMs, check blocks and shared community
+            if channel.channel_type == chatalot_db::models::channel::ChannelType::Dm {
+                if let Ok(members) =
+                    chatalot_db::repos::channel_repo::list_members(&state.db, msg.channel_id).await
+                {
+                    for member in &members {
+                        if member.user_id != msg.user_id {
+                            // Block check (fail closed)
+                            match chatalot_db::repos::block_repo::is_blocked_either_way(
+                                &state.db,
+                                msg.user_id,
+                                member.user_id,
+                            )
+                            .await
+                            {
+                                Ok(true) => break 'check true,
+                                Err(_) => break 'check true,
+                                Ok(false) => {}
+                            }
+                            // Shared community check (fail closed)
+                            match chatalot_db::repos::community_repo::shares_community(
+                                &state.db,
+                                msg.user_id,
+                                member.user_id,
+                            )
+                            .await
+                            {
+                                Ok(false) => break 'check true,
+                                Err(_) => break 'check true,
+                                Ok(true) => {}
+                            }
+                        }
+                    }
+                } else {
+                    break 'check true; // fail closed
+                }
+            }
+            if let Ok(Some(_)) = chatalot_db::repos::timeout_repo::get_active_timeout(
+                &state.db,
+                msg.user_id,
+                msg.channel_id,
+            )
+            .await
+            {
+                break 'check true;
+            }
+            false
+        };
+        if skip {
+            tracing::info!(
+                "Dropping scheduled message {}: channel restricted or user blocked/timed out",
+                msg.id
+            );
+            let _ =
+                chatalot_db::repos::scheduled_message_repo::delete_by_id(&state.db, msg.id).await;
+            continue;
+        }
+
+        // Deliver the message as if the user sent it now
+        let message_id = uuid::Uuid::now_v7();
+
+        // Compute expires_at if channel has a TTL configured
+        let expires_at = channel
+            .message_ttl_seconds
+            .map(|ttl| chrono::Utc::now() + chrono::Duration::seconds(ttl as i64));
+
+        // Decode ciphertext/nonce: new format is JSON byte arrays,
+        // fallback to raw UTF-8 bytes for legacy plaintext messages
+        let ciphertext_bytes: Vec<u8> = serde_json::from_str(&msg.ciphertext)
+            .unwrap_or_else(|_| msg.ciphertext.as_bytes().to_vec());
+        let nonce_bytes: Vec<u8> =
+            serde_json::from_str(&msg.nonce).unwrap_or_else(|_| msg.nonce.as_bytes().to_vec());
+
+        match chatalot_db::repos::message_repo::create_message(
+            &state.db,
+            message_id,
+            msg.channel_id,
+            msg.user_id,
+            &ciphertext_bytes,
+            &nonce_bytes,
+            "text",
+            None,
+            None,
+            None,
+            expires_at,
+            None,
+        )
+        .await
+        {
+            Ok(stored) => {
+                METRICS.message_sent();
+
+                // Delete the scheduled message first to minimize duplicate risk on crash
+                let _ = chatalot_db::repos::scheduled_message_repo::delete_by_id(&state.db, msg.id)
+                    .await;
+
+                let new_msg = chatalot_common::ws_messages::ServerMessage::NewMessage {
+                    id: message_id,
+                    channel_id: msg.channel_id,
+                    sender_id: msg.user_id,
+                    ciphertext: ciphertext_bytes,
+                    nonce: nonce_bytes,
+                    message_type: chatalot_common::ws_messages::MessageType::Text,
+                    reply_to: None,
+                    sender_key_id: None,
+                    created_at: stored.created_at.to_rfc3339(),
+                    thread_id: None,
+                };
+
+                // For DM channels, deliver directly to users (not via channel subscription).
+                // Recipients may be connected to any node, so delivery goes through the relay.
+                let is_dm = channel.channel_type == chatalot_db::models::channel::ChannelType::Dm;
+                let event = if is_dm {
+                    match chatalot_db::repos::channel_repo::list_members(&state.db, msg.channel_id)
+                        .await
+                    {
+                        Ok(members) => Some(RelayedEvent::Users {
+                            user_ids: members
+                                .iter()
+                                .map(|m| m.user_id)
+                                .filter(|uid| *uid != msg.user_id)
+                                .collect(),
+                            message: new_msg,
+                        }),
+                        Err(_) => None,
+                    }
+                } else {
+                    Some(RelayedEvent::Channel {
+                        channel_id: msg.channel_id,
+                        message: new_msg,
+                    })
+                };
+                if let Some(event) = event
+                    && let Err(e) = event_relay::publish(&state.db, &event).await
+                {
+                    tracing::warn!("Failed to publish scheduled message {}: {e}", msg.id);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed to deliver scheduled message {}: {e}", msg.id);
+                // Delete the failed message to prevent infinite retry loops.
+                // If the scheduled_for is more than 5 minutes in the past,
+                // the message is likely permanently undeliverable.
+                if msg.scheduled_for < chrono::Utc::now() - chrono::Duration::minutes(5) {
+                    tracing::warn!(
+                        "Dropping undeliverable scheduled message {} (overdue by >5min)",
+                        msg.id
+                    );
+                    let _ =
+                        chatalot_db::repos::scheduled_message_repo::delete_by_id(&state.db, msg.id)
+                            .await;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn message_expiry(state: Arc<AppState>) -> anyhow::Result<()> {
+    let n = chatalot_db::repos::message_repo::delete_expired_messages(&state.db)
+        .await
+        .context("failed to delete expired messages")?;
+    if n > 0 {
+        tracing::info!("Expired {n} messages past their TTL");
+    }
+    Ok(())
+}
+
+async fn message_partitions(state: Arc<AppState>) -> anyhow::Result<()> {
+    let created = message_archive_repo::ensure_partitions(&state.db, 2)
+        .await
+        .context("failed to create message partitions")?;
+    if created > 0 {
+        tracing::info!("Created {created} message partitions");
+    }
+    Ok(())
+}
+
+/// Archive every month older than the `message_archive_after_months`
+/// setting. Unset or 0 leaves all months live.
+async fn message_archival(state: Arc<AppState>) -> anyhow::Result<()> {
+    let months: u32 = settings_repo::get(&state.db, "message_archive_after_months")
+        .await?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    if months == 0 {
+        return Ok(());
+    }
+    let this_month = chrono::Utc::now()
+        .date_naive()
+        .with_day(1)
+        .expect("day 1 exists");
+    let Some(cutoff) = this_month.checked_sub_months(chrono::Months::new(months)) else {
+        return Ok(());
+    };
+    for month in message_archive_repo::list_partition_months(&state.db).await? {
+        if month >= cutoff {
+            break;
+        }
+        let moved = message_archive_repo::archive_month(&state.db, month)
+            .await
+            .with_context(|| format!("failed to archive messages for {}", month.format("%Y-%m")))?;
+        tracing::info!(
+            "Archived {}: {} messages, {} reactions, {} edits ({} deleted or expired dropped)",
+            month.format("%Y-%m"),
+            moved.messages,
+            moved.reactions,
+            moved.edits,
+            moved.discarded
+        );
+    }
+    Ok(())
+}
+
+async fn timeout_cleanup(state: Arc<AppState>) -> anyhow::Result<()> {
+    chatalot_db::repos::timeout_repo::cleanup_expired(&state.db).await?;
+    Ok(())
+}
+
+async fn event_relay(state: Arc<AppState>) -> anyhow::Result<()> {
+    event_relay::relay(&state)
+        .await
+        .context("failed to relay events")?;
+    Ok(())
+}
+
+async fn quiet_hours(state: Arc<AppState>) -> anyhow::Result<()> {
+    crate::services::quiet_hours::run_scheduler(&state).await?;
+    Ok(())
+}
@@ -0,0 +1,337 @@
+//! Background job runner.
+//!
+//! Cluster jobs are scheduled through the `jobs` table: a node takes a lease on
+//! a due job, runs it, records the outcome and the next run time, and releases
+//! the lease, so each job runs on one replica at a time. Node jobs maintain
+//! per-process in-memory state and run on every node without leasing.
+//!
+//! Intervals default per job and can be overridden with the instance setting
+//! `job_interval.<name>` (seconds). Failed runs are retried with exponential
+//! backoff, never later than the normal interval.
+
+pub mod builtin;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use futures_util::future::BoxFuture;
+use uuid::Uuid;
+
+use chatalot_db::repos::job_repo;
+
+use crate::app_state::AppState;
+use crate::metrics::METRICS;
+use crate::shutdown;
+
+/// Instance setting key prefix for per-job interval overrides.
+pub const INTERVAL_SETTING_PREFIX: &str = "job_interval.";
+/// Bounds for interval overrides, in seconds.
+pub const MIN_INTERVAL_SECS: u64 = 5;
+pub const MAX_INTERVAL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// How long a lease lasts without renewal, and how often a running job renews it.
+const LEASE_SECS: i64 = 120;
+const LEASE_RENEW_EVERY: Duration = Duration::from_secs(30);
+/// How often a node checks whether a cluster job is due.
+const MAX_POLL: Duration = Duration::from_secs(10);
+/// First retry delay after a failure; doubles with each consecutive failure.
+const RETRY_BASE: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobScope {
+    /// Runs on one node at a time, coordinated through the `jobs` table.
+    Cluster,
+    /// Runs on every node (maintains in-memory state).
+    Node,
+}
+
+impl JobScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobScope::Cluster => "cluster",
+            JobScope::Node => "node",
+        }
+    }
+}
+
+pub struct JobSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub scope: JobScope,
+    pub default_interval: Duration,
+    /// Delay before the first run (for cluster jobs: the first run ever).
+    pub initial_delay: Duration,
+    pub run: fn(Arc<AppState>) -> BoxFuture<'static, anyhow::Result<()>>,
+}
+
+/// Outcome of the last run of a node job (cluster jobs keep this in the DB).
+#[derive(Debug, Clone, Default)]
+pub struct NodeJobRun {
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<i64>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: i32,
+    pub run_count: i64,
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+/// Per-process job bookkeeping.
+pub struct JobRegistry {
+    /// Identifies this process as a lease owner.
+    pub node_id: Uuid,
+    pub node_runs: DashMap<&'static str, NodeJobRun>,
+}
+
+impl Default for JobRegistry {
+    fn default() -> Self {
+        Self {
+            node_id: Uuid::new_v4(),
+            node_runs: DashMap::new(),
+        }
+    }
+}
+
+/// Look up a job by name.
+pub fn find(name: &str) -> Option<&'static JobSpec> {
+    builtin::JOBS.iter().find(|j| j.name == name)
+}
+
+/// The job named by an instance setting key like `job_interval.message_gc`.
+pub fn job_for_setting(key: &str) -> Option<&'static JobSpec> {
+    key.strip_prefix(INTERVAL_SETTING_PREFIX).and_then(find)
+}
+
+/// Current interval for a job, honoring any instance setting override.
+pub async fn interval_for(state: &AppState, spec: &JobSpec) -> Duration {
+    state
+        .instance_settings
+        .read()
+        .await
+        .job_intervals
+        .get(spec.name)
+        .map(|secs| Duration::from_secs(*secs))
+        .unwrap_or(spec.default_interval)
+}
+
+/// Jobs that look unhealthy, for readiness checks.
+#[derive(Debug, Default)]
+pub struct JobHealth {
+    /// Overdue and not running: nothing is picking the job up, or a run hung.
+    pub stale: Vec<&'static str>,
+    /// Failed at least `FAILING_AFTER` times in a row.
+    pub failing: Vec<&'static str>,
+}
+
+const FAILING_AFTER: i32 = 3;
+
+pub async fn health(state: &AppState) -> Result<JobHealth, sqlx::Error> {
+    let rows = job_repo::list_jobs(&state.db).await?;
+    let now = Utc::now();
+    let mut health = JobHealth::default();
+
+    for spec in builtin::JOBS {
+        let interval = interval_for(state, spec).await;
+        let grace =
+            chrono::Duration::from_std(interval.max(Duration::from_secs(120))).unwrap_or_default();
+        let (next_run_at, leased, failures) = match spec.scope {
+            JobScope::Cluster => match rows.iter().find(|r| r.name == spec.name) {
+                Some(r) => (
+                    Some(r.next_run_at),
+                    r.lease_expires_at.is_some_and(|t| t > now),
+                    r.consecutive_failures,
+                ),
+                None => (None, false, 0),
+            },
+            JobScope::Node => match state.jobs.node_runs.get(spec.name) {
+                Some(r) => (r.next_run_at, false, r.consecutive_failures),
+                None => (None, false, 0),
+            },
+        };
+        if !leased && next_run_at.is_some_and(|t| now - t > grace) {
+            health.stale.push(spec.name);
+        }
+        if failures >= FAILING_AFTER {
+            health.failing.push(spec.name);
+        }
+    }
+    Ok(health)
+}
+
+/// Delay before retrying after `failures` consecutive failures.
+fn retry_delay(failures: i32, interval: Duration) -> Duration {
+    let exp = failures.saturating_sub(1).clamp(0, 16) as u32;
+    RETRY_BASE.saturating_mul(2u32.pow(exp)).min(interval)
+}
+
+/// Register cluster jobs and spawn one runner per job. The returned handles
+/// finish once shutdown begins and any in-progress run has completed.
+pub async fn spawn_all(state: &Arc<AppState>) -> Vec<tokio::task::JoinHandle<()>> {
+    let mut handles = Vec::new();
+    for spec in builtin::JOBS {
+        let state = state.clone();
+        match spec.scope {
+            JobScope::Cluster => {
+                let first_run =
+                    Utc::now() + chrono::Duration::from_std(spec.initial_delay).unwrap_or_default();
+                if let Err(e) = job_repo::register(&state.db, spec.name, first_run).await {
+                    tracing::error!(job = spec.name, "Failed to register job: {e}");
+                    continue;
+                }
+                handles.push(tokio::spawn(run_cluster_job(state, spec)));
+            }
+            JobScope::Node => handles.push(tokio::spawn(run_node_job(state, spec))),
+        }
+    }
+    handles
+}
+
+async fn run_cluster_job(state: Arc<AppState>, spec: &'static JobSpec) {
+    let mut shutdown_rx = state.shutdown.subscribe();
+    let node_id = state.jobs.node_id;
+    let poll = spec.default_interval.min(MAX_POLL);
+
+    while shutdown::sleep(poll, &mut shutdown_rx).await {
+        let job = match job_repo::try_acquire(&state.db, spec.name, node_id, LEASE_SECS).await {
+            Ok(Some(job)) => job,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!(job = spec.name, "Failed to acquire job lease: {e}");
+                continue;
+            }
+        };
+
+        let (error, duration) = execute(&state, spec).await;
+        let interval = interval_for(&state, spec).await;
+        let failures = if error.is_some() {
+            job.consecutive_failures + 1
+        } else {
+            0
+        };
+        let delay = if error.is_some() {
+            retry_delay(failures, interval)
+        } else {
+            interval
+        };
+        let next_run_at = Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default();
+
+        if let Err(e) = job_repo::finish(
+            &state.db,
+            spec.name,
+            node_id,
+            duration.as_millis() as i64,
+            error.as_deref(),
+            failures,
+            next_run_at,
+        )
+        .await
+        {
+            tracing::warn!(job = spec.name, "Failed to record job run: {e}");
+        }
+    }
+}
+
+async fn run_node_job(state: Arc<AppState>, spec: &'static JobSpec) {
+    let mut shutdown_rx = state.shutdown.subscribe();
+    let mut delay = spec.initial_delay;
+
+    loop {
+        state
+            .jobs
+            .node_runs
+            .entry(spec.name)
+            .or_default()
+            .next_run_at = Some(Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default());
+        if !shutdown::sleep(delay, &mut shutdown_rx).await {
+            break;
+        }
+
+        let started_at = Utc::now();
+        let (error, duration) = execute(&state, spec).await;
+        let interval = interval_for(&state, spec).await;
+
+        let mut run = state.jobs.node_runs.entry(spec.name).or_default();
+        run.last_started_at = Some(started_at);
+        run.last_finished_at = Some(Utc::now());
+        run.last_duration_ms = Some(duration.as_millis() as i64);
+        run.run_count += 1;
+        if error.is_some() {
+            run.consecutive_failures += 1;
+            delay = retry_delay(run.consecutive_failures, interval);
+        } else {
+            run.consecutive_failures = 0;
+            run.last_success_at = run.last_finished_at;
+            delay = interval;
+        }
+        run.last_error = error;
+    }
+}
+
+/// Run a job to completion (shutdown never interrupts a run), renewing the
+/// lease of cluster jobs while it is in progress. Returns the error, if any,
+/// and how long it took.
+async fn execute(state: &Arc<AppState>, spec: &'static JobSpec) -> (Option<String>, Duration) {
+    let started = std::time::Instant::now();
+    let mut task = tokio::spawn((spec.run)(state.clone()));
+    let mut renew = tokio::time::interval_at(
+        tokio::time::Instant::now() + LEASE_RENEW_EVERY,
+        LEASE_RENEW_EVERY,
+    );
+
+    let result = loop {
+        tokio::select! {
+            result = &mut task => break result,
+            _ = renew.tick(), if spec.scope == JobScope::Cluster => {
+                if let Err(e) =
+                    job_repo::renew_lease(&state.db, spec.name, state.jobs.node_id, LEASE_SECS).await
+                {
+                    tracing::warn!(job = spec.name, "Failed to renew job lease: {e}");
+                }
+            }
+        }
+    };
+
+    let error = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(format!("{e:#}")),
+        Err(e) => Some(format!("job panicked: {e}")),
+    };
+    if let Some(ref e) = error {
+        tracing::warn!(job = spec.name, "Job failed: {e}");
+    }
+    let elapsed = started.elapsed();
+    METRICS.job_finished(spec.name, elapsed, error.is_some());
+    (error, elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_backoff_doubles_up_to_interval() {
+        let hour = Duration::from_secs(3600);
+        assert_eq!(retry_delay(1, hour), Duration::from_secs(10));
+        assert_eq!(retry_delay(2, hour), Duration::from_secs(20));
+        assert_eq!(retry_delay(4, hour), Duration::from_secs(80));
+        assert_eq!(retry_delay(20, hour), hour);
+        assert_eq!(
+            retry_delay(3, Duration::from_secs(30)),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn job_names_are_unique_and_settable() {
+        let mut names: Vec<_> = builtin::JOBS.iter().map(|j| j.name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), builtin::JOBS.len());
+        assert!(job_for_setting("job_interval.message_gc").is_some());
+        assert!(job_for_setting("job_interval.nope").is_none());
+        assert!(job_for_setting("message_gc").is_none());
+    }
+}
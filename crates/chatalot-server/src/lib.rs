@@ -0,0 +1,16 @@
+//! Chatalot server. The `chatalot-server` binary runs it; `chatalot-admin`
+//! reuses its configuration and services for offline maintenance.
+
+pub mod app_state;
+pub mod config;
+pub mod error;
+pub mod jobs;
+pub mod metrics;
+pub mod middleware;
+pub mod permissions;
+pub mod routes;
+pub mod scanner;
+pub mod services;
+pub mod shutdown;
+pub mod storage;
+pub mod ws;
@@ -1,19 +1,14 @@
-mod app_state;
-mod config;
-mod error;
-mod middleware;
-pub mod permissions;
-mod routes;
-mod services;
-mod ws;
-
+use std::future::IntoFuture;
 use std::sync::Arc;
 use std::time::Instant;
 
+use anyhow::Context;
+
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::app_state::AppState;
-use crate::config::Config;
+use chatalot_server::app_state::AppState;
+use chatalot_server::config::Config;
+use chatalot_server::{jobs, routes, shutdown};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -26,8 +21,34 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    // Parse command line: [--config <file>] [--check-config]
+    let mut config_file = std::env::var_os("CHATALOT_CONFIG").map(std::path::PathBuf::from);
+    let mut check_only = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                config_file = Some(args.next().context("--config requires a file path")?.into())
+            }
+            "--check-config" => check_only = true,
+            other => anyhow::bail!("unknown argument: {other}"),
+        }
+    }
+
     // Load configuration
-    let config = Config::from_env()?;
+    // Invalid settings are refused at startup, as by --check-config and reload
+    let (config, problems) = Config::load(config_file.as_deref())?;
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("error: {problem}");
+        }
+        eprintln!("{} invalid setting(s)", problems.len());
+        std::process::exit(1);
+    }
+    if check_only {
+        println!("Configuration OK");
+        return Ok(());
+    }
     tracing::info!("Starting Chatalot server on {}", config.listen_addr);
 
     // Create database pool and run migrations
@@ -37,9 +58,14 @@ async fn main() -> anyhow::Result<()> {
     chatalot_db::pool::run_migrations(&db_pool).await?;
     tracing::info!("Migrations applied");
 
+    // Uploads in progress are written under the storage root whatever the backend
+    std::fs::create_dir_all(&config.file_storage_path)
+        .with_context(|| format!("failed to create {}", config.file_storage_path))?;
+
     // Build application state
     let start_time = Instant::now();
     let state = Arc::new(AppState::new(config.clone(), db_pool, start_time)?);
+    tracing::info!("File storage: {}", state.storage.kind());
 
     // Seed admin user from env var — only if there are zero admins (first-run bootstrap).
     // This prevents ADMIN_USERNAME from silently re-granting admin on every restart.
@@ -60,486 +86,6 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    // Spawn background task: typing indicator timeout (10s)
-    {
-        let state = state.clone();
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
-            loop {
-                interval.tick().await;
-                let expired = state
-                    .connections
-                    .expire_typing(std::time::Duration::from_secs(10));
-                for (channel_id, user_id) in expired {
-                    state.connections.broadcast_to_channel(
-                        channel_id,
-                        chatalot_common::ws_messages::ServerMessage::UserStoppedTyping {
-                            channel_id,
-                            user_id,
-                        },
-                    );
-                }
-            }
-        });
-    }
-
-    // Spawn background task: broadcast channel cleanup (every 5 minutes)
-    {
-        let state = state.clone();
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300));
-            loop {
-                interval.tick().await;
-                let removed = state.connections.cleanup_idle_channels();
-                if removed > 0 {
-                    tracing::debug!("Cleaned up {removed} idle broadcast channels");
-                }
-            }
-        });
-    }
-
-    // Spawn background task: periodic data cleanup (every hour)
-    {
-        let db = state.db.clone();
-        tokio::spawn(async move {
-            // Wait 1 minute after startup before first cleanup
-            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
-            loop {
-                interval.tick().await;
-                // Delete refresh tokens expired more than 7 days ago
-                match sqlx::query(
-                    "DELETE FROM refresh_tokens WHERE expires_at < NOW() - INTERVAL '7 days'",
-                )
-                .execute(&db)
-                .await
-                {
-                    Ok(r) => {
-                        if r.rows_affected() > 0 {
-                            tracing::info!(
-                                "Cleaned up {} expired refresh tokens",
-                                r.rows_affected()
-                            );
-                        }
-                    }
-                    Err(e) => tracing::warn!("Failed to clean expired tokens: {e}"),
-                }
-                // Delete used one-time prekeys older than 30 days
-                match sqlx::query("DELETE FROM one_time_prekeys WHERE used = true AND created_at < NOW() - INTERVAL '30 days'")
-                    .execute(&db)
-                    .await
-                {
-                    Ok(r) => {
-                        if r.rows_affected() > 0 {
-                            tracing::info!("Cleaned up {} used prekeys", r.rows_affected());
-                        }
-                    }
-                    Err(e) => tracing::warn!("Failed to clean used prekeys: {e}"),
-                }
-                // Prune audit logs older than 90 days
-                match sqlx::query(
-                    "DELETE FROM audit_log WHERE created_at < NOW() - INTERVAL '90 days'",
-                )
-                .execute(&db)
-                .await
-                {
-                    Ok(r) => {
-                        if r.rows_affected() > 0 {
-                            tracing::info!(
-                                "Cleaned up {} audit log entries older than 90 days",
-                                r.rows_affected()
-                            );
-                        }
-                    }
-                    Err(e) => tracing::warn!("Failed to clean old audit logs: {e}"),
-                }
-                // End orphaned voice sessions (no participants, still active)
-                match sqlx::query(
-                    "UPDATE voice_sessions SET ended_at = NOW() WHERE ended_at IS NULL AND NOT EXISTS (SELECT 1 FROM voice_session_participants WHERE session_id = voice_sessions.id AND left_at IS NULL)"
-                )
-                    .execute(&db)
-                    .await
-                {
-                    Ok(r) => {
-                        if r.rows_affected() > 0 {
-                            tracing::info!("Cleaned up {} orphaned voice sessions", r.rows_affected());
-                        }
-                    }
-                    Err(e) => tracing::warn!("Failed to clean orphaned voice sessions: {e}"),
-                }
-                // Clean up failed and stale push subscriptions
-                match chatalot_db::repos::push_subscription_repo::cleanup_failed(&db).await {
-                    Ok(n) if n > 0 => tracing::info!("Cleaned up {n} failed push subscriptions"),
-                    Err(e) => tracing::warn!("Failed to clean push subscriptions: {e}"),
-                    _ => {}
-                }
-                match chatalot_db::repos::push_subscription_repo::cleanup_stale(&db).await {
-                    Ok(n) if n > 0 => tracing::info!("Cleaned up {n} stale push subscriptions"),
-                    Err(e) => tracing::warn!("Failed to clean stale push subscriptions: {e}"),
-                    _ => {}
-                }
-            }
-        });
-    }
-
-    // Spawn background task: soft-delete message GC (daily, 5min startup delay)
-    // Hard-deletes messages that were soft-deleted more than 30 days ago
-    {
-        let db = state.db.clone();
-        tokio::spawn(async move {
-            tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
-            let mut interval =
-                tokio::time::interval(tokio::time::Duration::from_secs(24 * 60 * 60));
-            loop {
-                interval.tick().await;
-                match chatalot_db::repos::message_repo::gc_soft_deleted(&db, 30).await {
-                    Ok(0) => {}
-                    Ok(n) => {
-                        tracing::info!("GC: hard-deleted {n} messages soft-deleted >30 days ago")
-                    }
-                    Err(e) => tracing::warn!("GC soft-delete cleanup failed: {e}"),
-                }
-            }
-        });
-    }
-
-    // Spawn background task: orphan file cleanup (daily, 2h startup delay)
-    // Removes disk files with no DB record and DB records with missing disk files
-    {
-        let db = state.db.clone();
-        let storage_path = state.config.file_storage_path.clone();
-        tokio::spawn(async move {
-            tokio::time::sleep(tokio::time::Duration::from_secs(7200)).await;
-            let mut interval =
-                tokio::time::interval(tokio::time::Duration::from_secs(24 * 60 * 60));
-            loop {
-                interval.tick().await;
-
-                // Get all known file paths from DB
-                let db_files = match chatalot_db::repos::file_repo::list_all_file_paths(&db).await {
-                    Ok(f) => f,
-                    Err(e) => {
-                        tracing::warn!("Orphan cleanup: failed to list DB files: {e}");
-                        continue;
-                    }
-                };
-
-                let db_paths: std::collections::HashSet<String> =
-                    db_files.iter().map(|(_, p)| p.clone()).collect();
-
-                // Walk storage directory and find disk files with no DB record
-                let mut orphan_disk_files = 0u64;
-                let storage = std::path::Path::new(&storage_path);
-                // Directories that store non-file assets (avatars, community/group
-                // images).  These are NOT tracked in the `files` table, so they must
-                // be skipped or the cleanup will delete them.
-                const SKIP_DIRS: &[&str] = &["avatars", "community_assets", "group_assets"];
-
-                if let Ok(mut shard_dirs) = tokio::fs::read_dir(storage).await {
-                    while let Ok(Some(shard_entry)) = shard_dirs.next_entry().await {
-                        let shard_path = shard_entry.path();
-                        if !shard_path.is_dir() {
-                            continue;
-                        }
-                        // Skip asset directories that aren't managed by the files table
-                        if let Some(name) = shard_path.file_name().and_then(|n| n.to_str())
-                            && SKIP_DIRS.contains(&name)
-                        {
-                            continue;
-                        }
-                        if let Ok(mut files) = tokio::fs::read_dir(&shard_path).await {
-                            while let Ok(Some(file_entry)) = files.next_entry().await {
-                                let file_path = file_entry.path();
-                                let path_str = file_path.to_string_lossy().to_string();
-                                if !db_paths.contains(&path_str) {
-                                    if let Err(e) = tokio::fs::remove_file(&file_path).await {
-                                        tracing::warn!(
-                                            "Orphan cleanup: failed to remove {path_str}: {e}"
-                                        );
-                                    } else {
-                                        orphan_disk_files += 1;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                if orphan_disk_files > 0 {
-                    tracing::info!(
-                        "Orphan cleanup: removed {orphan_disk_files} disk files with no DB record"
-                    );
-                }
-            }
-        });
-    }
-
-    // Spawn background task: scheduled message delivery (every 30s)
-    {
-        let state = state.clone();
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
-            loop {
-                interval.tick().await;
-                match chatalot_db::repos::scheduled_message_repo::get_due_messages(&state.db).await
-                {
-                    Ok(messages) => {
-                        for msg in messages {
-                            // Check if user is suspended
-                            if state.suspended_users.contains(&msg.user_id) {
-                                tracing::info!(
-                                    "Dropping scheduled message {}: user is suspended",
-                                    msg.id
-                                );
-                                let _ =
-                                    chatalot_db::repos::scheduled_message_repo::delete_by_id(
-                                        &state.db, msg.id,
-                                    )
-                                    .await;
-                                continue;
-                            }
-
-                            // Verify user is still a member before delivering
-                            match chatalot_db::repos::channel_repo::is_member(
-                                &state.db,
-                                msg.channel_id,
-                                msg.user_id,
-                            )
-                            .await
-                            {
-                                Ok(false) | Err(_) => {
-                                    tracing::info!(
-                                        "Dropping scheduled message {}: user no longer a member",
-                                        msg.id
-                                    );
-                                    let _ =
-                                        chatalot_db::repos::scheduled_message_repo::delete_by_id(
-                                            &state.db, msg.id,
-                                        )
-                                        .await;
-                                    continue;
-                                }
-                                Ok(true) => {}
-                            }
-
-                            // Fetch channel for restriction checks and TTL
-                            let channel = match chatalot_db::repos::channel_repo::get_channel(
-                                &state.db,
-                                msg.channel_id,
-                            )
-                            .await
-                            {
-                                Ok(Some(ch)) => ch,
-                                _ => {
-                                    tracing::info!(
-                                        "Dropping scheduled message {}: channel not found",
-                                        msg.id
-                                    );
-                                    let _ =
-                                        chatalot_db::repos::scheduled_message_repo::delete_by_id(
-                                            &state.db, msg.id,
-                                        )
-                                        .await;
-                                    continue;
-                                }
-                            };
-
-                            // Check channel restrictions
-                            let skip = 'check: {
-                                if channel.archived || channel.read_only {
-                                    break 'check true;
-                                }
-                                // For DMs, check blocks and shared community
-                                if channel.channel_type == chatalot_db::models::channel::ChannelType::Dm {
-                                    if let Ok(members) = chatalot_db::repos::channel_repo::list_members(&state.db, msg.channel_id).await {
-                                        for member in &members {
-                                            if member.user_id != msg.user_id {
-                                                // Block check (fail closed)
-                                                match chatalot_db::repos::block_repo::is_blocked_either_way(&state.db, msg.user_id, member.user_id).await {
-                                                    Ok(true) => break 'check true,
-                                                    Err(_) => break 'check true,
-                                                    Ok(false) => {}
-                                                }
-                                                // Shared community check (fail closed)
-                                                match chatalot_db::repos::community_repo::shares_community(&state.db, msg.user_id, member.user_id).await {
-                                                    Ok(false) => break 'check true,
-                                                    Err(_) => break 'check true,
-                                                    Ok(true) => {}
-                                                }
-                                            }
-                                        }
-                                    } else {
-                                        break 'check true; // fail closed
-                                    }
-                                }
-                                if let Ok(Some(_)) =
-                                    chatalot_db::repos::timeout_repo::get_active_timeout(
-                                        &state.db,
-                                        msg.user_id,
-                                        msg.channel_id,
-                                    )
-                                    .await
-                                {
-                                    break 'check true;
-                                }
-                                false
-                            };
-                            if skip {
-                                tracing::info!(
-                                    "Dropping scheduled message {}: channel restricted or user blocked/timed out",
-                                    msg.id
-                                );
-                                let _ =
-                                    chatalot_db::repos::scheduled_message_repo::delete_by_id(
-                                        &state.db, msg.id,
-                                    )
-                                    .await;
-                                continue;
-                            }
-
-                            // Deliver the message as if the user sent it now
-                            let message_id = uuid::Uuid::now_v7();
-
-                            // Compute expires_at if channel has a TTL configured
-                            let expires_at = channel
-                                .message_ttl_seconds
-                                .map(|ttl| chrono::Utc::now() + chrono::Duration::seconds(ttl as i64));
-
-                            // Decode ciphertext/nonce: new format is JSON byte arrays,
-                            // fallback to raw UTF-8 bytes for legacy plaintext messages
-                            let ciphertext_bytes: Vec<u8> =
-                                serde_json::from_str(&msg.ciphertext)
-                                    .unwrap_or_else(|_| msg.ciphertext.as_bytes().to_vec());
-                            let nonce_bytes: Vec<u8> =
-                                serde_json::from_str(&msg.nonce)
-                                    .unwrap_or_else(|_| msg.nonce.as_bytes().to_vec());
-
-                            match chatalot_db::repos::message_repo::create_message(
-                                &state.db,
-                                message_id,
-                                msg.channel_id,
-                                msg.user_id,
-                                &ciphertext_bytes,
-                                &nonce_bytes,
-                                "text",
-                                None,
-                                None,
-                                None,
-                                expires_at,
-                                None,
-                            )
-                            .await
-                            {
-                                Ok(stored) => {
-                                    // Delete the scheduled message first to minimize duplicate risk on crash
-                                    let _ =
-                                        chatalot_db::repos::scheduled_message_repo::delete_by_id(
-                                            &state.db, msg.id,
-                                        )
-                                        .await;
-
-                                    let new_msg = chatalot_common::ws_messages::ServerMessage::NewMessage {
-                                        id: message_id,
-                                        channel_id: msg.channel_id,
-                                        sender_id: msg.user_id,
-                                        ciphertext: ciphertext_bytes,
-                                        nonce: nonce_bytes,
-                                        message_type:
-                                            chatalot_common::ws_messages::MessageType::Text,
-                                        reply_to: None,
-                                        sender_key_id: None,
-                                        created_at: stored.created_at.to_rfc3339(),
-                                        thread_id: None,
-                                    };
-
-                                    // For DM channels, deliver directly to users (not via channel subscription)
-                                    let is_dm = channel.channel_type == chatalot_db::models::channel::ChannelType::Dm;
-                                    if is_dm {
-                                        if let Ok(members) = chatalot_db::repos::channel_repo::list_members(&state.db, msg.channel_id).await {
-                                            for member in &members {
-                                                if member.user_id != msg.user_id {
-                                                    state.connections.send_to_user(&member.user_id, &new_msg);
-                                                }
-                                            }
-                                        }
-                                    } else {
-                                        state.connections.broadcast_to_channel(msg.channel_id, new_msg);
-                                    }
-                                }
-                                Err(e) => {
-                                    tracing::warn!(
-                                        "Failed to deliver scheduled message {}: {e}",
-                                        msg.id
-                                    );
-                                    // Delete the failed message to prevent infinite retry loops.
-                                    // If the scheduled_for is more than 5 minutes in the past,
-                                    // the message is likely permanently undeliverable.
-                                    if msg.scheduled_for < chrono::Utc::now() - chrono::Duration::minutes(5) {
-                                        tracing::warn!(
-                                            "Dropping undeliverable scheduled message {} (overdue by >5min)",
-                                            msg.id
-                                        );
-                                        let _ = chatalot_db::repos::scheduled_message_repo::delete_by_id(
-                                            &state.db, msg.id,
-                                        ).await;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to query due scheduled messages: {e}");
-                    }
-                }
-            }
-        });
-    }
-
-    // Spawn background task: message expiry cleanup (every 5 min)
-    {
-        let db = state.db.clone();
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300));
-            loop {
-                interval.tick().await;
-                match chatalot_db::repos::message_repo::delete_expired_messages(&db).await {
-                    Ok(0) => {}
-                    Ok(n) => tracing::info!("Expired {n} messages past their TTL"),
-                    Err(e) => tracing::warn!("Failed to delete expired messages: {e}"),
-                }
-            }
-        });
-    }
-
-    // Spawn background task: timeout cleanup (every 5 min)
-    {
-        let db = state.db.clone();
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300));
-            loop {
-                interval.tick().await;
-                let _ = chatalot_db::repos::timeout_repo::cleanup_expired(&db).await;
-            }
-        });
-    }
-
-    // Spawn background task: in-memory cache cleanup (every 10 min)
-    {
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(600));
-            loop {
-                interval.tick().await;
-                let gifs = routes::gifs::cleanup_gif_cache();
-                let previews = routes::link_preview::cleanup_preview_cache();
-                if gifs > 0 || previews > 0 {
-                    tracing::debug!(
-                        "Cache cleanup: evicted {gifs} GIF entries, {previews} link preview entries"
-                    );
-                }
-            }
-        });
-    }
-
     // Load instance settings from DB into memory
     match chatalot_db::repos::settings_repo::list_all(&state.db).await {
         Ok(rows) => {
@@ -559,7 +105,16 @@ async fn main() -> anyhow::Result<()> {
                     "e2e_enabled" => {
                         settings.e2e_enabled = row.value == "true";
                     }
-                    _ => {}
+                    key => {
+                        if let Some(job) = jobs::job_for_setting(key)
+                            && let Ok(v) = row.value.parse::<u64>()
+                        {
+                            settings.job_intervals.insert(
+                                job.name,
+                                v.clamp(jobs::MIN_INTERVAL_SECS, jobs::MAX_INTERVAL_SECS),
+                            );
+                        }
+                    }
                 }
             }
             tracing::info!(
@@ -585,6 +140,22 @@ async fn main() -> anyhow::Result<()> {
         Err(e) => tracing::warn!("Failed to load suspended users: {e}"),
     }
 
+    // Reload safe settings on SIGHUP
+    #[cfg(unix)]
+    {
+        let state = state.clone();
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                state.reload_config();
+            }
+        });
+    }
+
+    // Start background jobs. Once shutdown begins they stop scheduling new
+    // runs; a run that is already in progress always completes.
+    let jobs = jobs::spawn_all(&state).await;
+
     // Build the router
     let app = routes::build_router(state.clone());
 
@@ -592,39 +163,69 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind(&config.listen_addr).await?;
     tracing::info!("Listening on {}", config.listen_addr);
 
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
-    )
-    .with_graceful_shutdown(shutdown_signal())
-    .await?;
-
-    tracing::info!("Server shut down gracefully");
-    Ok(())
-}
-
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        tokio::signal::ctrl_c()
+    let mut shutdown_rx = state.shutdown.subscribe();
+    let mut server = tokio::spawn(
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move { shutdown::wait(&mut shutdown_rx).await })
+        .into_future(),
+    );
+
+    // Operator listener (metrics), never exposed on the main listener
+    if let Some(ref addr) = config.admin_listen_addr {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        tracing::info!("Admin listener on {addr}");
+        let app = routes::build_admin_router(state.clone());
+        let mut shutdown_rx = state.shutdown.subscribe();
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            )
+            .with_graceful_shutdown(async move { shutdown::wait(&mut shutdown_rx).await })
             .await
-            .expect("failed to install Ctrl+C handler");
-    };
-
-    #[cfg(unix)]
-    let terminate = async {
-        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
-            .expect("failed to install SIGTERM handler")
-            .recv()
-            .await;
-    };
-
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
+            {
+                tracing::error!("Admin listener failed: {e}");
+            }
+        });
+    }
 
     tokio::select! {
-        _ = ctrl_c => {},
-        _ = terminate => {},
+        result = &mut server => {
+            result??;
+            return Ok(());
+        }
+        _ = shutdown::signal() => {}
     }
 
-    tracing::info!("Shutdown signal received");
+    // Stop accepting connections and upgrades, tell realtime clients to
+    // reconnect later, let background jobs finish their current iteration,
+    // and give everything until the deadline to wind down.
+    let deadline = std::time::Duration::from_secs(config.shutdown_deadline_secs);
+    tracing::info!("Shutting down (deadline {}s)", deadline.as_secs());
+    state.shutdown.send_replace(true);
+
+    let drained = tokio::time::timeout(deadline, async {
+        if let Ok(Err(e)) = server.await {
+            tracing::warn!("Server error during shutdown: {e}");
+        }
+        for job in jobs {
+            let _ = job.await;
+        }
+        while state.connections.session_count() > 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+    })
+    .await;
+
+    match drained {
+        Ok(()) => tracing::info!("Server shut down gracefully"),
+        Err(_) => tracing::warn!(
+            "Shutdown deadline reached with {} realtime session(s) still open",
+            state.connections.session_count()
+        ),
+    }
+    Ok(())
 }
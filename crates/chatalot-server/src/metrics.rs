@@ -0,0 +1,319 @@
+//! Process-wide operational metrics in the Prometheus text exposition format.
+//!
+//! Counters and histograms are recorded unconditionally (they are plain
+//! atomics); they are only exposed when `METRICS_ENABLED` is set. Gauges that
+//! can be read from existing state (sessions, DB pool) are sampled at scrape
+//! time by `routes::metrics`.
+
+use std::fmt::Write;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use dashmap::DashMap;
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// Request latency buckets, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+/// Background job duration buckets, in seconds.
+const JOB_BUCKETS: &[f64] = &[0.01, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0];
+
+#[derive(Default)]
+pub struct Metrics {
+    pub messages_sent: AtomicU64,
+    pub broadcast_lag_events: AtomicU64,
+    pub broadcast_lag_skipped: AtomicU64,
+    pub upload_bytes: AtomicU64,
+    pub uploads: AtomicU64,
+    /// Keyed by limiter: `http`, `auth`, `ws_message`, `voice`, `reaction`.
+    rate_limited: DashMap<&'static str, u64>,
+    /// Keyed by (transport, reason).
+    push_failures: DashMap<(String, &'static str), u64>,
+    /// Keyed by (method, route, status class).
+    requests: DashMap<(String, String, &'static str), Histogram>,
+    /// Keyed by job name.
+    jobs: DashMap<&'static str, Histogram>,
+    job_failures: DashMap<&'static str, u64>,
+}
+
+struct Histogram {
+    buckets: &'static [f64],
+    /// Non-cumulative count per bucket, plus one for +Inf.
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            counts: vec![0; buckets.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        let idx = self
+            .buckets
+            .iter()
+            .position(|b| value <= *b)
+            .unwrap_or(self.buckets.len());
+        self.counts[idx] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let sep = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (bound, count) in self.buckets.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {}",
+            self.count
+        );
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+    }
+}
+
+impl Metrics {
+    pub fn message_sent(&self) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn broadcast_lagged(&self, skipped: u64) {
+        self.broadcast_lag_events.fetch_add(1, Ordering::Relaxed);
+        self.broadcast_lag_skipped
+            .fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    pub fn rate_limited(&self, limiter: &'static str) {
+        *self.rate_limited.entry(limiter).or_default() += 1;
+    }
+
+    pub fn push_failed(&self, transport: &str, reason: &'static str) {
+        *self
+            .push_failures
+            .entry((transport.to_string(), reason))
+            .or_default() += 1;
+    }
+
+    pub fn uploaded(&self, bytes: u64) {
+        self.uploads.fetch_add(1, Ordering::Relaxed);
+        self.upload_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let class = match status {
+            100..=199 => "1xx",
+            200..=299 => "2xx",
+            300..=399 => "3xx",
+            400..=499 => "4xx",
+            _ => "5xx",
+        };
+        self.requests
+            .entry((method.to_string(), route.to_string(), class))
+            .or_insert_with(|| Histogram::new(LATENCY_BUCKETS))
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn job_finished(&self, job: &'static str, elapsed: Duration, failed: bool) {
+        self.jobs
+            .entry(job)
+            .or_insert_with(|| Histogram::new(JOB_BUCKETS))
+            .observe(elapsed.as_secs_f64());
+        if failed {
+            *self.job_failures.entry(job).or_default() += 1;
+        }
+    }
+
+    /// Append all recorded counters and histograms to `out`.
+    pub fn render(&self, out: &mut String) {
+        counter(
+            out,
+            "chatalot_messages_sent_total",
+            "Messages persisted (user, scheduled and webhook).",
+            self.messages_sent.load(Ordering::Relaxed),
+        );
+        counter(
+            out,
+            "chatalot_broadcast_lag_events_total",
+            "Times a session fell behind a channel broadcast and was resynced.",
+            self.broadcast_lag_events.load(Ordering::Relaxed),
+        );
+        counter(
+            out,
+            "chatalot_broadcast_lag_skipped_total",
+            "Broadcast events dropped for lagging sessions.",
+            self.broadcast_lag_skipped.load(Ordering::Relaxed),
+        );
+        counter(
+            out,
+            "chatalot_uploads_total",
+            "Files uploaded.",
+            self.uploads.load(Ordering::Relaxed),
+        );
+        counter(
+            out,
+            "chatalot_upload_bytes_total",
+            "Bytes of file uploads stored.",
+            self.upload_bytes.load(Ordering::Relaxed),
+        );
+
+        header(
+            out,
+            "chatalot_rate_limited_total",
+            "Requests or realtime messages rejected by a rate limiter.",
+            "counter",
+        );
+        for (limiter, value) in sorted(&self.rate_limited, |k| k.to_string()) {
+            let _ = writeln!(
+                out,
+                "chatalot_rate_limited_total{{limiter=\"{limiter}\"}} {value}"
+            );
+        }
+
+        header(
+            out,
+            "chatalot_push_failures_total",
+            "Push deliveries that failed (`gone` subscriptions are removed).",
+            "counter",
+        );
+        for ((transport, reason), value) in sorted(&self.push_failures, |(t, r)| format!("{t}{r}"))
+        {
+            let _ = writeln!(
+                out,
+                "chatalot_push_failures_total{{transport=\"{}\",reason=\"{reason}\"}} {value}",
+                escape(&transport)
+            );
+        }
+
+        header(
+            out,
+            "chatalot_http_request_duration_seconds",
+            "HTTP request latency by matched route.",
+            "histogram",
+        );
+        let mut requests: Vec<_> = self.requests.iter().collect();
+        requests.sort_by(|a, b| a.key().cmp(b.key()));
+        for entry in requests {
+            let (method, route, status) = entry.key();
+            let labels = format!(
+                "method=\"{method}\",route=\"{}\",status=\"{status}\"",
+                escape(route)
+            );
+            entry
+                .value()
+                .render(out, "chatalot_http_request_duration_seconds", &labels);
+        }
+
+        header(
+            out,
+            "chatalot_job_duration_seconds",
+            "Background job run duration.",
+            "histogram",
+        );
+        let mut jobs: Vec<_> = self.jobs.iter().collect();
+        jobs.sort_by_key(|e| *e.key());
+        for entry in jobs {
+            let labels = format!("job=\"{}\"", entry.key());
+            entry
+                .value()
+                .render(out, "chatalot_job_duration_seconds", &labels);
+        }
+
+        header(
+            out,
+            "chatalot_job_failures_total",
+            "Background job runs that returned an error or panicked.",
+            "counter",
+        );
+        for (job, value) in sorted(&self.job_failures, |k| k.to_string()) {
+            let _ = writeln!(out, "chatalot_job_failures_total{{job=\"{job}\"}} {value}");
+        }
+    }
+}
+
+fn sorted<K, V>(map: &DashMap<K, V>, key: impl Fn(&K) -> String) -> Vec<(K, V)>
+where
+    K: Clone + Eq + std::hash::Hash,
+    V: Copy,
+{
+    let mut entries: Vec<_> = map.iter().map(|e| (e.key().clone(), *e.value())).collect();
+    entries.sort_by_key(|(k, _)| key(k));
+    entries
+}
+
+pub fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+pub fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, help, "counter");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+pub fn gauge(out: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    header(out, name, help, "gauge");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let mut h = Histogram::new(&[0.1, 1.0]);
+        h.observe(0.05);
+        h.observe(0.5);
+        h.observe(3.0);
+        let mut out = String::new();
+        h.render(&mut out, "x", "job=\"a\"");
+        assert!(out.contains("x_bucket{job=\"a\",le=\"0.1\"} 1\n"));
+        assert!(out.contains("x_bucket{job=\"a\",le=\"1\"} 2\n"));
+        assert!(out.contains("x_bucket{job=\"a\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("x_count{job=\"a\"} 3\n"));
+    }
+
+    #[test]
+    fn renders_labeled_counters() {
+        let m = Metrics::default();
+        m.rate_limited("http");
+        m.rate_limited("http");
+        m.push_failed("webpush", "failed");
+        m.request("GET", "/api/channels/{id}", 200, Duration::from_millis(3));
+        let mut out = String::new();
+        m.render(&mut out);
+        assert!(out.contains("chatalot_rate_limited_total{limiter=\"http\"} 2\n"));
+        assert!(
+            out.contains(
+                "chatalot_push_failures_total{transport=\"webpush\",reason=\"failed\"} 1\n"
+            )
+        );
+        assert!(out.contains(
+            "chatalot_http_request_duration_seconds_count{method=\"GET\",route=\"/api/channels/{id}\",status=\"2xx\"} 1\n"
+        ));
+    }
+}
@@ -0,0 +1,114 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::app_state::AppState;
+use crate::error::AppError;
+
+/// An address range in CIDR notation (a bare address is a /32 or /128).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn parse(s: &str) -> Option<Self> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((a, p)) => (a.parse::<IpAddr>().ok()?, Some(p.parse::<u8>().ok()?)),
+            None => (s.trim().parse::<IpAddr>().ok()?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { addr, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // Match IPv4 clients on dual-stack sockets against IPv4 ranges
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Only allow requests whose direct peer address is in `ADMIN_NETWORKS`.
+/// Proxy headers are deliberately ignored: a proxy in front of the server
+/// must not make operator endpoints reachable from the internet.
+pub async fn require_admin_network(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ci| ci.0.ip());
+
+    match peer {
+        Some(ip) if state.config.admin_networks.iter().any(|n| n.contains(ip)) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err(AppError::NotFound("not found".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_cidrs_and_bare_addresses() {
+        assert!(IpNetwork::parse("10.0.0.0/8").is_some());
+        assert!(IpNetwork::parse("::1").is_some());
+        assert!(IpNetwork::parse("10.0.0.0/33").is_none());
+        assert!(IpNetwork::parse("fd00::/129").is_none());
+        assert!(IpNetwork::parse("example.com").is_none());
+    }
+
+    #[test]
+    fn matches_addresses_in_range() {
+        let net = IpNetwork::parse("172.16.0.0/12").unwrap();
+        assert!(net.contains(ip("172.31.255.1")));
+        assert!(!net.contains(ip("172.32.0.1")));
+        assert!(net.contains(ip("::ffff:172.16.0.5")));
+
+        let host = IpNetwork::parse("127.0.0.1").unwrap();
+        assert!(host.contains(ip("127.0.0.1")));
+        assert!(!host.contains(ip("127.0.0.2")));
+
+        assert!(
+            IpNetwork::parse("0.0.0.0/0")
+                .unwrap()
+                .contains(ip("8.8.8.8"))
+        );
+        assert!(
+            IpNetwork::parse("fd00::/8")
+                .unwrap()
+                .contains(ip("fd12::1"))
+        );
+        assert!(
+            !IpNetwork::parse("fd00::/8")
+                .unwrap()
+                .contains(ip("127.0.0.1"))
+        );
+    }
+}
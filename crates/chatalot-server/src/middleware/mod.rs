@@ -1,3 +1,4 @@
+pub mod admin_network;
 pub mod auth;
 pub mod community_gate;
 pub mod rate_limit;
@@ -1,19 +1,23 @@
 use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
 use std::time::Instant;
 
-use axum::extract::{ConnectInfo, Request};
+use axum::extract::{ConnectInfo, Request, State};
 use axum::http::{header, StatusCode};
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
 use serde_json::json;
 use tokio::sync::Mutex;
 
-/// Simple token-bucket rate limiter per IP address.
+use crate::app_state::AppState;
+use crate::config::RateLimit;
+use crate::metrics::METRICS;
+
+/// Simple token-bucket rate limiter per IP address. Limits are passed on
+/// each check so they can be changed at runtime.
 pub struct RateLimiter {
     buckets: Mutex<HashMap<IpAddr, Bucket>>,
-    max_tokens: u32,
-    refill_rate: f64, // tokens per second
     last_eviction: Mutex<Instant>,
 }
 
@@ -28,16 +32,16 @@ const EVICTION_INTERVAL_SECS: u64 = 300;
 const BUCKET_TTL_SECS: u64 = 600;
 
 impl RateLimiter {
-    pub fn new(max_requests_per_second: u32, burst: u32) -> Self {
+    pub fn new() -> Self {
         Self {
             buckets: Mutex::new(HashMap::new()),
-            max_tokens: burst,
-            refill_rate: max_requests_per_second as f64,
             last_eviction: Mutex::new(Instant::now()),
         }
     }
 
-    async fn check(&self, ip: IpAddr) -> bool {
+    async fn check(&self, ip: IpAddr, limit: RateLimit) -> bool {
+        let max_tokens = limit.burst as f64;
+        let refill_rate = limit.per_second as f64; // tokens per second
         let mut buckets = self.buckets.lock().await;
         let now = Instant::now();
 
@@ -50,13 +54,13 @@ impl RateLimiter {
         drop(last_eviction);
 
         let bucket = buckets.entry(ip).or_insert(Bucket {
-            tokens: self.max_tokens as f64,
+            tokens: max_tokens,
             last_refill: now,
         });
 
         // Refill tokens based on elapsed time
         let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
-        bucket.tokens = (bucket.tokens + elapsed * self.refill_rate).min(self.max_tokens as f64);
+        bucket.tokens = (bucket.tokens + elapsed * refill_rate).min(max_tokens);
         bucket.last_refill = now;
 
         if bucket.tokens >= 1.0 {
@@ -68,6 +72,12 @@ impl RateLimiter {
     }
 }
 
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Check if an IP is a trusted reverse proxy (loopback or Docker bridge).
 fn is_trusted_proxy(ip: IpAddr) -> bool {
     match ip {
@@ -108,16 +118,20 @@ fn extract_client_ip(request: &Request) -> IpAddr {
 }
 
 /// Rate limiting middleware.
-pub async fn rate_limit_middleware(request: Request, next: Next) -> Response {
+pub async fn rate_limit_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
     let ip = extract_client_ip(&request);
 
     // Use a lazily initialized static rate limiter
-    static LIMITER: std::sync::LazyLock<RateLimiter> =
-        std::sync::LazyLock::new(|| RateLimiter::new(20, 50));
+    static LIMITER: std::sync::LazyLock<RateLimiter> = std::sync::LazyLock::new(RateLimiter::new);
 
-    if LIMITER.check(ip).await {
+    if LIMITER.check(ip, state.live().rate_limit).await {
         next.run(request).await
     } else {
+        METRICS.rate_limited("http");
         let body = json!({
             "error": {
                 "code": "rate_limited",
@@ -130,16 +144,21 @@ pub async fn rate_limit_middleware(request: Request, next: Next) -> Response {
 }
 
 /// Stricter rate limiter for auth endpoints (login/register).
-pub async fn auth_rate_limit_middleware(request: Request, next: Next) -> Response {
+pub async fn auth_rate_limit_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
     let ip = extract_client_ip(&request);
 
-    // 5 auth attempts per second, burst of 10
+    // Separate buckets from the general limiter (default 5/s, burst of 10)
     static AUTH_LIMITER: std::sync::LazyLock<RateLimiter> =
-        std::sync::LazyLock::new(|| RateLimiter::new(5, 10));
+        std::sync::LazyLock::new(RateLimiter::new);
 
-    if AUTH_LIMITER.check(ip).await {
+    if AUTH_LIMITER.check(ip, state.live().auth_rate_limit).await {
         next.run(request).await
     } else {
+        METRICS.rate_limited("auth");
         let body = json!({
             "error": {
                 "code": "rate_limited",
@@ -34,6 +34,11 @@ pub fn can_delete_others_messages(role: &str) -> bool {
     role_level(role) >= 1 // moderator and above
 }
 
+/// Check if a role can mention @everyone or a whole role in a channel.
+pub fn can_mass_mention(role: &str) -> bool {
+    role_level(role) >= 1 // moderator and above
+}
+
 /// Check if a role can change other users' roles.
 pub fn can_manage_roles(role: &str) -> bool {
     role_level(role) >= 3 // owner and above
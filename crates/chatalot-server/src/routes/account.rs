@@ -5,7 +5,6 @@ use axum::extract::{Path, State};
 use axum::http::header;
 use axum::routing::{delete, get, post, put};
 use axum::{Extension, Json, Router};
-use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
 use chatalot_common::api_types::{
@@ -19,7 +18,9 @@ use chatalot_db::repos::{announcement_repo, group_repo, preferences_repo, user_r
 use crate::app_state::AppState;
 use crate::error::AppError;
 use crate::middleware::auth::AccessClaims;
+use crate::routes::files::blob_body;
 use crate::services::auth_service;
+use crate::storage;
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
@@ -305,24 +306,8 @@ async fn upload_avatar(
         _ => "bin",
     };
 
-    // Store in avatars/ subdirectory
-    let avatar_dir = std::path::Path::new(&state.config.file_storage_path).join("avatars");
-    tokio::fs::create_dir_all(&avatar_dir)
-        .await
-        .map_err(|e| AppError::Internal(format!("create avatar dir: {e}")))?;
-
     let filename = format!("{}.{ext}", claims.sub);
-    let file_path = avatar_dir.join(&filename);
-
-    let mut f = tokio::fs::File::create(&file_path)
-        .await
-        .map_err(|e| AppError::Internal(format!("create avatar file: {e}")))?;
-    f.write_all(&data)
-        .await
-        .map_err(|e| AppError::Internal(format!("write avatar: {e}")))?;
-    f.flush()
-        .await
-        .map_err(|e| AppError::Internal(format!("flush avatar: {e}")))?;
+    store_avatar(&state, &filename, data).await?;
 
     // Update user's avatar_url to the public serving path (with cache-bust param)
     let ts = chrono::Utc::now().timestamp();
@@ -425,23 +410,8 @@ async fn upload_banner(
         _ => "bin",
     };
 
-    let avatar_dir = std::path::Path::new(&state.config.file_storage_path).join("avatars");
-    tokio::fs::create_dir_all(&avatar_dir)
-        .await
-        .map_err(|e| AppError::Internal(format!("create avatar dir: {e}")))?;
-
     let filename = format!("{}_banner.{ext}", claims.sub);
-    let file_path = avatar_dir.join(&filename);
-
-    let mut f = tokio::fs::File::create(&file_path)
-        .await
-        .map_err(|e| AppError::Internal(format!("create banner file: {e}")))?;
-    f.write_all(&data)
-        .await
-        .map_err(|e| AppError::Internal(format!("write banner: {e}")))?;
-    f.flush()
-        .await
-        .map_err(|e| AppError::Internal(format!("flush banner: {e}")))?;
+    store_avatar(&state, &filename, data).await?;
 
     let ts = chrono::Utc::now().timestamp();
     let banner_url = format!("/api/avatars/{filename}?v={ts}");
@@ -543,23 +513,8 @@ async fn upload_voice_background(
         _ => "bin",
     };
 
-    let avatar_dir = std::path::Path::new(&state.config.file_storage_path).join("avatars");
-    tokio::fs::create_dir_all(&avatar_dir)
-        .await
-        .map_err(|e| AppError::Internal(format!("create dir: {e}")))?;
-
     let filename = format!("{}_voicebg.{ext}", claims.sub);
-    let file_path = avatar_dir.join(&filename);
-
-    let mut f = tokio::fs::File::create(&file_path)
-        .await
-        .map_err(|e| AppError::Internal(format!("create file: {e}")))?;
-    f.write_all(&data)
-        .await
-        .map_err(|e| AppError::Internal(format!("write file: {e}")))?;
-    f.flush()
-        .await
-        .map_err(|e| AppError::Internal(format!("flush file: {e}")))?;
+    store_avatar(&state, &filename, data).await?;
 
     let ts = chrono::Utc::now().timestamp();
     let url = format!("/api/avatars/{filename}?v={ts}");
@@ -626,6 +581,15 @@ async fn clear_voice_background(
     Ok(())
 }
 
+/// Store an avatar, banner or voice background under `avatars/`.
+async fn store_avatar(state: &AppState, filename: &str, data: Vec<u8>) -> Result<(), AppError> {
+    state
+        .storage
+        .put(&format!("{}/{filename}", storage::AVATARS), data.into())
+        .await
+        .map_err(|e| AppError::Internal(format!("store avatar: {e}")))
+}
+
 async fn serve_avatar(
     State(state): State<Arc<AppState>>,
     Path(filename): Path<String>,
@@ -635,13 +599,8 @@ async fn serve_avatar(
         return Err(AppError::Validation("invalid filename".into()));
     }
 
-    let avatar_path = std::path::Path::new(&state.config.file_storage_path)
-        .join("avatars")
-        .join(&filename);
-
-    let file = tokio::fs::File::open(&avatar_path)
-        .await
-        .map_err(|_| AppError::NotFound("avatar not found".into()))?;
+    let key = format!("{}/{filename}", storage::AVATARS);
+    let body = blob_body(&state, &key, "avatar not found").await?;
 
     let content_type = if filename.ends_with(".png") {
         "image/png"
@@ -655,9 +614,6 @@ async fn serve_avatar(
         "application/octet-stream"
     };
 
-    let stream = tokio_util::io::ReaderStream::new(file);
-    let body = Body::from_stream(stream);
-
     Ok((
         [
             (header::CONTENT_TYPE, content_type.to_string()),
@@ -8,26 +8,32 @@ use axum::{Extension, Json, Router};
 use uuid::Uuid;
 
 use chatalot_common::api_types::{
-    AddBlockedHashRequest, AdminFileEntry, AdminFilesQuery, AdminFilesResponse,
+    AddBlockedHashRequest, AdminFileEntry, AdminFilesQuery, AdminFilesResponse, AdminJobResponse,
     AdminUserMembership, AdminUserResponse, AdminUsersQuery, AnnouncementResponse,
-    AuditLogEntryResponse, AuditLogQuery, AuditLogResponse, BlockedHashResponse,
-    CreateAnnouncementRequest, CreateRegistrationInviteRequest, PurgeParams, PurgeResult,
-    RegistrationInviteResponse, ReportResponse, ReportsQuery, ReportsResponse,
-    ResetPasswordRequest, ReviewReportRequest, SetAdminRequest, StorageStatsResponse,
+    AuditLogEntryResponse, AuditLogQuery, AuditLogResponse, BlockedHashResponse, BlocklistFile,
+    BlocklistImportResponse, CreateAnnouncementRequest, CreateRegistrationInviteRequest,
+    PurgeParams, PurgeResult, RegistrationInviteResponse, ReportResponse, ReportsQuery,
+    ReportsResponse, ResetPasswordRequest, ReviewReportRequest, ScopeStorageStatResponse,
+    SetAdminRequest, SetStorageQuotaRequest, StorageQuotaResponse, StorageStatsResponse,
     SuspendUserRequest, UserStorageStatResponse,
 };
 use std::collections::HashMap;
 use chatalot_common::ws_messages::ServerMessage;
+use chatalot_db::models::blocked_hash::BlockedHash;
 use chatalot_db::models::file::FileRecord;
+use chatalot_db::repos::file_repo::ScopeStorageStat;
+use chatalot_db::repos::storage_quota_repo::{self, COMMUNITY, GROUP, ROLE};
 use chatalot_db::repos::{
-    announcement_repo, audit_repo, blocked_hash_repo, file_repo, message_repo,
+    announcement_repo, audit_repo, blocked_hash_repo, file_repo, job_repo, message_repo,
     registration_invite_repo, report_repo, settings_repo, user_repo, webhook_repo,
 };
 
 use crate::app_state::AppState;
 use crate::error::AppError;
+use crate::jobs::{self, JobScope};
 use crate::middleware::auth::AccessClaims;
-use crate::services::auth_service;
+use crate::services::event_relay::{self, RelayedEvent};
+use crate::services::{auth_service, blocklist, storage_quota, upload_service};
 
 /// Guard: returns Forbidden if the caller is not an admin or instance owner.
 fn require_admin(claims: &AccessClaims) -> Result<(), AppError> {
@@ -86,6 +92,20 @@ pub fn routes() -> Router<Arc<AppState>> {
         .route("/admin/files/{id}/quarantine", post(quarantine_file))
         .route("/admin/files/{id}/unquarantine", post(unquarantine_file))
         .route("/admin/storage-stats", get(storage_stats))
+        // Storage quotas
+        .route("/admin/storage-quotas", get(list_storage_quotas))
+        .route(
+            "/admin/communities/{id}/storage-quota",
+            put(set_community_quota).delete(remove_community_quota),
+        )
+        .route(
+            "/admin/groups/{id}/storage-quota",
+            put(set_group_quota).delete(remove_group_quota),
+        )
+        .route(
+            "/admin/communities/{id}/role-quotas/{role}",
+            put(set_role_quota).delete(remove_role_quota),
+        )
         // Message quarantine
         .route("/admin/messages/{id}/quarantine", post(quarantine_message))
         .route(
@@ -97,6 +117,8 @@ pub fn routes() -> Router<Arc<AppState>> {
             "/admin/blocked-hashes",
             get(list_blocked_hashes).post(add_blocked_hash),
         )
+        .route("/admin/blocked-hashes/export", get(export_blocked_hashes))
+        .route("/admin/blocked-hashes/import", post(import_blocked_hashes))
         .route("/admin/blocked-hashes/{id}", delete(remove_blocked_hash))
         // Audit log
         .route("/admin/audit-log", get(query_audit_log))
@@ -115,6 +137,9 @@ pub fn routes() -> Router<Arc<AppState>> {
         )
         // Webhooks overview
         .route("/admin/webhooks", get(list_all_webhooks))
+        // Background jobs
+        .route("/admin/jobs", get(list_jobs))
+        .route("/admin/jobs/{name}/run", post(run_job))
 }
 
 // ── User Management (existing) ──
@@ -135,7 +160,7 @@ async fn list_users(
     let user_ids: Vec<Uuid> = users.iter().map(|u| u.id).collect();
 
     // Fetch group memberships for all listed users
-    let group_rows: Vec<UserMembershipRow> = sqlx::query_as(
+    let group_rows: Vec<UserMembershipRow> = chatalot_db::db::query_as(
         r#"
         SELECT gm.user_id, g.id, g.name, gm.role
         FROM group_members gm
@@ -149,7 +174,7 @@ async fn list_users(
     .await?;
 
     // Fetch community memberships for all listed users
-    let community_rows: Vec<UserMembershipRow> = sqlx::query_as(
+    let community_rows: Vec<UserMembershipRow> = chatalot_db::db::query_as(
         r#"
         SELECT cm.user_id, c.id, c.name, cm.role
         FROM community_members cm
@@ -238,8 +263,16 @@ async fn suspend_user(
     user_repo::suspend_user(&state.db, user_id, req.reason.as_deref()).await?;
     state.suspended_users.insert(user_id);
 
-    // Revoke all their sessions
+    // Revoke all their sessions, and drop live ones on every node
     user_repo::revoke_all_refresh_tokens(&state.db, user_id).await?;
+    event_relay::publish(
+        &state.db,
+        &RelayedEvent::Suspension {
+            user_id,
+            suspended: true,
+        },
+    )
+    .await?;
 
     // Audit log
     user_repo::insert_audit_log(
@@ -270,6 +303,14 @@ async fn unsuspend_user(
 
     user_repo::unsuspend_user(&state.db, user_id).await?;
     state.suspended_users.remove(&user_id);
+    event_relay::publish(
+        &state.db,
+        &RelayedEvent::Suspension {
+            user_id,
+            suspended: false,
+        },
+    )
+    .await?;
 
     user_repo::insert_audit_log(
         &state.db,
@@ -335,19 +376,18 @@ async fn delete_user(
 
     // Transfer community/group ownership to the instance owner before deletion.
     // This prevents orphaned communities/groups with NULL owner_id.
-    let instance_owner_id = sqlx::query_scalar::<_, Uuid>(
-        "SELECT id FROM users WHERE is_owner = TRUE LIMIT 1",
-    )
-    .fetch_optional(&state.db)
-    .await?;
+    let instance_owner_id =
+        chatalot_db::db::query_scalar::<Uuid>("SELECT id FROM users WHERE is_owner = TRUE LIMIT 1")
+            .fetch_optional(&state.db)
+            .await?;
 
     if let Some(owner_id) = instance_owner_id {
-        sqlx::query("UPDATE communities SET owner_id = $1 WHERE owner_id = $2")
+        chatalot_db::db::query("UPDATE communities SET owner_id = $1 WHERE owner_id = $2")
             .bind(owner_id)
             .bind(user_id)
             .execute(&state.db)
             .await?;
-        sqlx::query("UPDATE groups SET owner_id = $1 WHERE owner_id = $2")
+        chatalot_db::db::query("UPDATE groups SET owner_id = $1 WHERE owner_id = $2")
             .bind(owner_id)
             .bind(user_id)
             .execute(&state.db)
@@ -355,14 +395,14 @@ async fn delete_user(
     }
 
     // Clean up orphaned DM channels (ones where the deleted user is the only remaining member)
-    sqlx::query(
+    chatalot_db::db::query(
         "DELETE FROM channels WHERE channel_type = 'dm' AND id IN (
             SELECT c.id FROM channels c
             JOIN channel_members cm ON cm.channel_id = c.id
             WHERE c.channel_type = 'dm'
             GROUP BY c.id
             HAVING COUNT(cm.user_id) = 1
-            AND BOOL_OR(cm.user_id = $1)
+            AND SUM(CASE WHEN cm.user_id = $1 THEN 1 ELSE 0 END) = 1
         )",
     )
     .bind(user_id)
@@ -501,7 +541,7 @@ async fn create_registration_invite(
         &state.db,
         id,
         &code,
-        claims.sub,
+        Some(claims.sub),
         req.max_uses,
         expires_at,
     )
@@ -560,17 +600,19 @@ async fn delete_registration_invite(
 
 // ── Purge Endpoints ──
 
-/// Helper: delete file blobs from disk, return count of successful deletions.
-async fn delete_files_from_disk(files: &[FileRecord]) -> u64 {
+/// Helper: delete file records and release their blobs (a blob shared with
+/// files outside the purge is kept). Returns the number of files deleted.
+async fn delete_files(state: &AppState, files: &[FileRecord]) -> u64 {
     let mut count = 0u64;
     for file in files {
-        match tokio::fs::remove_file(&file.storage_path).await {
-            Ok(()) => count += 1,
+        match upload_service::delete_file(&state.db, state.storage.as_ref(), file).await {
+            Ok(true) => count += 1,
+            Ok(false) => {}
             Err(e) => {
                 tracing::warn!(
                     file_id = %file.id,
                     path = %file.storage_path,
-                    "Failed to delete file from disk: {e}"
+                    "Failed to delete file: {e}"
                 );
             }
         }
@@ -578,9 +620,10 @@ async fn delete_files_from_disk(files: &[FileRecord]) -> u64 {
     count
 }
 
-/// Helper: optionally block file hashes and return count of hashes blocked.
+/// Helper: optionally block file hashes, and the perceptual hashes of
+/// images, and return count of hashes blocked.
 async fn maybe_block_hashes(
-    db: &sqlx::PgPool,
+    db: &chatalot_db::db::Db,
     files: &[FileRecord],
     block: bool,
     blocked_by: Uuid,
@@ -588,15 +631,9 @@ async fn maybe_block_hashes(
     if !block || files.is_empty() {
         return 0;
     }
-    let hashes: Vec<String> = files.iter().map(|f| f.checksum.clone()).collect();
-    blocked_hash_repo::add_blocked_hashes(
-        db,
-        &hashes,
-        Some("auto-blocked via admin purge"),
-        blocked_by,
-    )
-    .await
-    .unwrap_or(0)
+    blocklist::block_files(db, files, "auto-blocked via admin purge", blocked_by)
+        .await
+        .unwrap_or(0)
 }
 
 /// Hard-delete a single message and its sender's files from disk.
@@ -662,11 +699,8 @@ async fn purge_user_messages(
     // Optionally block hashes
     let hashes_blocked = maybe_block_hashes(&state.db, &files, block_hashes, claims.sub).await;
 
-    // Delete files from disk
-    let files_deleted = delete_files_from_disk(&files).await;
-
-    // Delete file DB records
-    file_repo::delete_all_user_files(&state.db, user_id).await?;
+    // Delete file records and their blobs
+    let files_deleted = delete_files(&state, &files).await;
 
     // Hard-delete all messages
     let messages_deleted = message_repo::hard_delete_user_messages(&state.db, user_id).await?;
@@ -712,11 +746,8 @@ async fn purge_channel(
     // Optionally block hashes
     let hashes_blocked = maybe_block_hashes(&state.db, &files, block_hashes, claims.sub).await;
 
-    // Delete files from disk
-    let files_deleted = delete_files_from_disk(&files).await;
-
-    // Delete file DB records
-    file_repo::delete_channel_files(&state.db, channel_id).await?;
+    // Delete file records and their blobs
+    let files_deleted = delete_files(&state, &files).await;
 
     // Hard-delete all messages
     let messages_deleted =
@@ -821,28 +852,19 @@ async fn admin_delete_file(
         .await?
         .ok_or_else(|| AppError::NotFound("file not found".to_string()))?;
 
-    // Optionally block the hash
+    // Optionally block the hash, and the image's perceptual hash
     if params.block_hashes.unwrap_or(false) {
-        let _ = blocked_hash_repo::add_blocked_hash(
+        let _ = blocklist::block_files(
             &state.db,
-            Uuid::now_v7(),
-            &record.checksum,
-            Some("blocked via admin file delete"),
+            std::slice::from_ref(&record),
+            "blocked via admin file delete",
             claims.sub,
         )
         .await;
     }
 
-    // Delete from disk
-    if let Err(e) = tokio::fs::remove_file(&record.storage_path).await {
-        tracing::warn!(
-            "Failed to delete file from disk {}: {e}",
-            record.storage_path
-        );
-    }
-
-    // Delete from DB
-    file_repo::delete_file_admin(&state.db, file_id).await?;
+    // Delete the record; the blob goes with its last file
+    upload_service::delete_file(&state.db, state.storage.as_ref(), &record).await?;
 
     // Audit log
     user_repo::insert_audit_log(
@@ -871,10 +893,11 @@ async fn storage_stats(
     require_admin(&claims)?;
 
     let stats = file_repo::storage_stats(&state.db).await?;
-    let total_files: i64 = stats.iter().map(|s| s.file_count).sum();
-    let total_bytes: i64 = stats.iter().map(|s| s.total_bytes).sum();
+    let total_files: i64 = stats.per_user.iter().map(|s| s.file_count).sum();
+    let total_bytes: i64 = stats.per_user.iter().map(|s| s.total_bytes).sum();
 
     let per_user = stats
+        .per_user
         .into_iter()
         .map(|s| UserStorageStatResponse {
             user_id: s.uploader_id,
@@ -887,9 +910,116 @@ async fn storage_stats(
         total_files,
         total_bytes,
         per_user,
+        per_community: stats
+            .per_community
+            .into_iter()
+            .map(scope_stat_response)
+            .collect(),
+        per_group: stats
+            .per_group
+            .into_iter()
+            .map(scope_stat_response)
+            .collect(),
     }))
 }
 
+fn scope_stat_response(s: ScopeStorageStat) -> ScopeStorageStatResponse {
+    ScopeStorageStatResponse {
+        id: s.scope_id,
+        name: s.name,
+        file_count: s.file_count,
+        total_bytes: s.total_bytes,
+        quota_mb: s.quota_bytes.map(|b| b / (1024 * 1024)),
+    }
+}
+
+// ── Storage Quotas ──
+
+/// List community, group and role quotas.
+async fn list_storage_quotas(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+) -> Result<Json<Vec<StorageQuotaResponse>>, AppError> {
+    require_admin(&claims)?;
+
+    let quotas = storage_quota_repo::list_quotas(&state.db).await?;
+    Ok(Json(
+        quotas.into_iter().map(storage_quota::to_response).collect(),
+    ))
+}
+
+/// Limit the total size of the files posted in a community.
+async fn set_community_quota(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<SetStorageQuotaRequest>,
+) -> Result<Json<StorageQuotaResponse>, AppError> {
+    require_admin(&claims)?;
+
+    let quota =
+        storage_quota::set_quota(&state.db, COMMUNITY, id, "", req.max_mb, claims.sub).await?;
+    Ok(Json(quota))
+}
+
+async fn remove_community_quota(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(id): Path<Uuid>,
+) -> Result<(), AppError> {
+    require_admin(&claims)?;
+
+    storage_quota::remove_quota(&state.db, COMMUNITY, id, "", claims.sub).await
+}
+
+/// Limit the total size of the files posted in a group.
+async fn set_group_quota(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<SetStorageQuotaRequest>,
+) -> Result<Json<StorageQuotaResponse>, AppError> {
+    require_admin(&claims)?;
+
+    let quota = storage_quota::set_quota(&state.db, GROUP, id, "", req.max_mb, claims.sub).await?;
+    Ok(Json(quota))
+}
+
+async fn remove_group_quota(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(id): Path<Uuid>,
+) -> Result<(), AppError> {
+    require_admin(&claims)?;
+
+    storage_quota::remove_quota(&state.db, GROUP, id, "", claims.sub).await
+}
+
+/// Replace the per-user upload quota for members of a community with the
+/// given role, when they upload into its channels.
+async fn set_role_quota(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path((id, role)): Path<(Uuid, String)>,
+    Json(req): Json<SetStorageQuotaRequest>,
+) -> Result<Json<StorageQuotaResponse>, AppError> {
+    require_admin(&claims)?;
+
+    let quota =
+        storage_quota::set_quota(&state.db, ROLE, id, &role, req.max_mb, claims.sub).await?;
+    Ok(Json(quota))
+}
+
+async fn remove_role_quota(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path((id, role)): Path<(Uuid, String)>,
+) -> Result<(), AppError> {
+    require_admin(&claims)?;
+
+    storage_quota::remove_quota(&state.db, ROLE, id, &role, claims.sub).await
+}
+
 // ── Quarantine ──
 
 /// Quarantine a file (hide from downloads, preserve for evidence).
@@ -900,7 +1030,7 @@ async fn quarantine_file(
 ) -> Result<(), AppError> {
     require_admin(&claims)?;
 
-    let updated = file_repo::quarantine_file(&state.db, file_id, claims.sub).await?;
+    let updated = file_repo::quarantine_file(&state.db, file_id, Some(claims.sub)).await?;
     if !updated {
         return Err(AppError::NotFound(
             "file not found or already quarantined".to_string(),
@@ -1024,16 +1154,7 @@ async fn list_blocked_hashes(
 
     let hashes = blocked_hash_repo::list_blocked_hashes(&state.db, limit, offset).await?;
 
-    let responses = hashes
-        .into_iter()
-        .map(|h| BlockedHashResponse {
-            id: h.id,
-            hash: h.hash,
-            reason: h.reason,
-            blocked_by: h.blocked_by,
-            created_at: h.created_at.to_rfc3339(),
-        })
-        .collect();
+    let responses = hashes.into_iter().map(blocked_hash_response).collect();
 
     Ok(Json(responses))
 }
@@ -1046,28 +1167,14 @@ async fn add_blocked_hash(
 ) -> Result<Json<BlockedHashResponse>, AppError> {
     require_admin(&claims)?;
 
-    if req.hash.len() != 64 || !req.hash.chars().all(|c| c.is_ascii_hexdigit()) {
-        return Err(AppError::Validation(
-            "hash must be a 64-character hex SHA256".to_string(),
-        ));
-    }
-
-    if let Some(ref r) = req.reason
-        && r.len() > 500
-    {
-        return Err(AppError::Validation(
-            "reason must be at most 500 characters".to_string(),
-        ));
-    }
-
-    let record = blocked_hash_repo::add_blocked_hash(
-        &state.db,
-        Uuid::now_v7(),
+    let entry = blocklist::validate(
+        req.kind.as_deref(),
         &req.hash,
+        req.max_distance,
         req.reason.as_deref(),
-        claims.sub,
     )
-    .await?;
+    .map_err(AppError::Validation)?;
+    let record = blocklist::add(&state.db, &entry, Some(claims.sub)).await?;
 
     user_repo::insert_audit_log(
         &state.db,
@@ -1077,19 +1184,63 @@ async fn add_blocked_hash(
         None,
         None,
         Some(serde_json::json!({
-            "hash": req.hash,
-            "reason": req.reason,
+            "kind": entry.kind,
+            "hash": entry.hash,
+            "max_distance": entry.max_distance,
+            "reason": entry.reason,
         })),
     )
     .await?;
 
-    Ok(Json(BlockedHashResponse {
-        id: record.id,
-        hash: record.hash,
-        reason: record.reason,
-        blocked_by: record.blocked_by,
-        created_at: record.created_at.to_rfc3339(),
-    }))
+    Ok(Json(blocked_hash_response(record)))
+}
+
+fn blocked_hash_response(h: BlockedHash) -> BlockedHashResponse {
+    BlockedHashResponse {
+        id: h.id,
+        kind: h.kind,
+        hash: h.hash,
+        max_distance: h.max_distance,
+        reason: h.reason,
+        blocked_by: h.blocked_by,
+        created_at: h.created_at.to_rfc3339(),
+    }
+}
+
+/// Export the blocklist in the interchange format.
+async fn export_blocked_hashes(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+) -> Result<Json<BlocklistFile>, AppError> {
+    require_admin(&claims)?;
+
+    Ok(Json(blocklist::export(&state.db).await?))
+}
+
+/// Import a blocklist exported by this or another instance. Nothing is
+/// imported unless every entry is valid.
+async fn import_blocked_hashes(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Json(file): Json<BlocklistFile>,
+) -> Result<Json<BlocklistImportResponse>, AppError> {
+    require_admin(&claims)?;
+
+    let entries = blocklist::parse(&file).map_err(AppError::Validation)?;
+    let imported = blocklist::import(&state.db, &entries, Some(claims.sub)).await?;
+
+    user_repo::insert_audit_log(
+        &state.db,
+        Uuid::now_v7(),
+        Some(claims.sub),
+        "admin_import_blocklist",
+        None,
+        None,
+        Some(serde_json::json!({ "imported": imported })),
+    )
+    .await?;
+
+    Ok(Json(BlocklistImportResponse { imported }))
 }
 
 /// Remove a hash from the blocklist.
@@ -1353,10 +1504,15 @@ async fn update_instance_settings(
 ) -> Result<Json<HashMap<String, String>>, AppError> {
     require_admin(&claims)?;
 
-    const ALLOWED_KEYS: &[&str] = &["max_messages_cache", "max_pins_per_channel", "e2e_enabled"];
+    const ALLOWED_KEYS: &[&str] = &[
+        "max_messages_cache",
+        "max_pins_per_channel",
+        "e2e_enabled",
+        "message_archive_after_months",
+    ];
 
     for (key, value) in &updates {
-        if !ALLOWED_KEYS.contains(&key.as_str()) {
+        if !ALLOWED_KEYS.contains(&key.as_str()) && jobs::job_for_setting(key).is_none() {
             return Err(AppError::Validation(format!("unknown setting: {key}")));
         }
         // Validate numeric values
@@ -1388,6 +1544,28 @@ async fn update_instance_settings(
                     ));
                 }
             }
+            "message_archive_after_months" => {
+                let v: u32 = value.parse().map_err(|_| {
+                    AppError::Validation("message_archive_after_months must be a number".into())
+                })?;
+                if v > 120 {
+                    return Err(AppError::Validation(
+                        "message_archive_after_months must be between 0 (off) and 120".into(),
+                    ));
+                }
+            }
+            k if k.starts_with(jobs::INTERVAL_SETTING_PREFIX) => {
+                let v: u64 = value.parse().map_err(|_| {
+                    AppError::Validation(format!("{k} must be a number of seconds"))
+                })?;
+                if !(jobs::MIN_INTERVAL_SECS..=jobs::MAX_INTERVAL_SECS).contains(&v) {
+                    return Err(AppError::Validation(format!(
+                        "{k} must be between {} and {} seconds",
+                        jobs::MIN_INTERVAL_SECS,
+                        jobs::MAX_INTERVAL_SECS
+                    )));
+                }
+            }
             _ => {}
         }
     }
@@ -1415,7 +1593,13 @@ async fn update_instance_settings(
                 "e2e_enabled" => {
                     settings.e2e_enabled = value == "true";
                 }
-                _ => {}
+                k => {
+                    if let Some(job) = jobs::job_for_setting(k)
+                        && let Ok(v) = value.parse::<u64>()
+                    {
+                        settings.job_intervals.insert(job.name, v);
+                    }
+                }
             }
         }
     }
@@ -1426,6 +1610,110 @@ async fn update_instance_settings(
     Ok(Json(map))
 }
 
+// ── Background Jobs ──
+
+async fn list_jobs(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+) -> Result<Json<Vec<AdminJobResponse>>, AppError> {
+    require_admin(&claims)?;
+
+    let rows: HashMap<String, _> = job_repo::list_jobs(&state.db)
+        .await?
+        .into_iter()
+        .map(|j| (j.name.clone(), j))
+        .collect();
+    let now = chrono::Utc::now();
+
+    let mut jobs = Vec::with_capacity(jobs::builtin::JOBS.len());
+    for spec in jobs::builtin::JOBS {
+        let interval_secs = jobs::interval_for(&state, spec).await.as_secs();
+        let entry = match spec.scope {
+            JobScope::Cluster => {
+                let row = rows.get(spec.name);
+                let running = row.is_some_and(|r| r.lease_expires_at.is_some_and(|t| t > now));
+                AdminJobResponse {
+                    name: spec.name.to_string(),
+                    description: spec.description.to_string(),
+                    scope: spec.scope.as_str().to_string(),
+                    interval_secs,
+                    running,
+                    lease_owner: row.and_then(|r| r.lease_owner).filter(|_| running),
+                    next_run_at: row.map(|r| r.next_run_at.to_rfc3339()),
+                    last_started_at: row.and_then(|r| r.last_started_at).map(|t| t.to_rfc3339()),
+                    last_finished_at: row.and_then(|r| r.last_finished_at).map(|t| t.to_rfc3339()),
+                    last_success_at: row.and_then(|r| r.last_success_at).map(|t| t.to_rfc3339()),
+                    last_duration_ms: row.and_then(|r| r.last_duration_ms),
+                    last_error: row.and_then(|r| r.last_error.clone()),
+                    consecutive_failures: row.map(|r| r.consecutive_failures).unwrap_or(0),
+                    run_count: row.map(|r| r.run_count).unwrap_or(0),
+                }
+            }
+            JobScope::Node => {
+                let run = state
+                    .jobs
+                    .node_runs
+                    .get(spec.name)
+                    .map(|r| r.clone())
+                    .unwrap_or_default();
+                let running =
+                    run.last_started_at.is_some() && run.last_finished_at < run.last_started_at;
+                AdminJobResponse {
+                    name: spec.name.to_string(),
+                    description: spec.description.to_string(),
+                    scope: spec.scope.as_str().to_string(),
+                    interval_secs,
+                    running,
+                    lease_owner: None,
+                    next_run_at: run.next_run_at.map(|t| t.to_rfc3339()),
+                    last_started_at: run.last_started_at.map(|t| t.to_rfc3339()),
+                    last_finished_at: run.last_finished_at.map(|t| t.to_rfc3339()),
+                    last_success_at: run.last_success_at.map(|t| t.to_rfc3339()),
+                    last_duration_ms: run.last_duration_ms,
+                    last_error: run.last_error,
+                    consecutive_failures: run.consecutive_failures,
+                    run_count: run.run_count,
+                }
+            }
+        };
+        jobs.push(entry);
+    }
+
+    Ok(Json(jobs))
+}
+
+/// Schedule a cluster job to run at the next poll (within ~10 seconds).
+async fn run_job(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(name): Path<String>,
+) -> Result<(), AppError> {
+    require_admin(&claims)?;
+
+    let spec = jobs::find(&name).ok_or_else(|| AppError::NotFound("job not found".into()))?;
+    if spec.scope != JobScope::Cluster {
+        return Err(AppError::Validation(format!(
+            "{name} runs on every node and cannot be triggered manually"
+        )));
+    }
+    if !job_repo::trigger_now(&state.db, spec.name).await? {
+        return Err(AppError::NotFound("job not found".into()));
+    }
+
+    user_repo::insert_audit_log(
+        &state.db,
+        Uuid::now_v7(),
+        Some(claims.sub),
+        "admin_run_job",
+        None,
+        None,
+        Some(serde_json::json!({ "job": spec.name })),
+    )
+    .await?;
+
+    Ok(())
+}
+
 // ── Webhooks Overview ──
 
 #[derive(Debug, serde::Serialize)]
@@ -29,12 +29,15 @@ pub fn routes() -> Router<Arc<AppState>> {
 }
 
 async fn server_config(State(state): State<Arc<AppState>>) -> Json<ServerConfigResponse> {
-    let ice_servers = state.config.ice_servers_json.as_ref()
+    let live = state.live();
+    let ice_servers = live
+        .ice_servers_json
+        .as_ref()
         .and_then(|json| serde_json::from_str(json).ok())
         .unwrap_or_default();
     let settings = state.instance_settings.read().await;
     Json(ServerConfigResponse {
-        registration_mode: state.config.registration_mode.clone(),
+        registration_mode: live.registration_mode.clone(),
         public_url: state.config.public_url.clone(),
         ice_servers,
         max_messages_cache: settings.max_messages_cache,
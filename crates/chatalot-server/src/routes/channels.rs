@@ -7,21 +7,29 @@ use chatalot_common::ws_messages::ServerMessage;
 use uuid::Uuid;
 
 use chatalot_common::api_types::{
-    BanRequest, ChannelMemberResponse, ChannelResponse, CreateChannelRequest, PaginationQuery,
-    TransferOwnershipRequest, UpdateChannelRequest, UpdateRoleRequest,
+    BanRequest, ChannelMemberResponse, ChannelResponse, CreateChannelRequest, FileRetentionPolicy,
+    PaginationQuery, TransferOwnershipRequest, UpdateChannelRequest, UpdateRoleRequest,
 };
 use chatalot_db::models::channel::ChannelType;
-use chatalot_db::repos::{channel_repo, community_repo, group_repo, sender_key_repo, unread_repo, user_repo, voice_repo};
+use chatalot_db::repos::{
+    channel_repo, community_repo, file_retention_repo, group_repo, sender_key_repo, unread_repo,
+    user_repo, voice_repo,
+};
 
 use crate::app_state::AppState;
 use crate::error::AppError;
 use crate::middleware::auth::AccessClaims;
 use crate::permissions;
+use crate::services::file_retention;
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/channels", get(list_channels).post(create_channel))
         .route("/channels/{id}", get(get_channel).patch(update_channel))
+        .route(
+            "/channels/{id}/file-retention",
+            get(get_file_retention).put(set_file_retention),
+        )
         .route("/channels/{id}/join", post(join_channel))
         .route("/channels/{id}/leave", post(leave_channel))
         .route("/channels/{id}/members", get(list_channel_members))
@@ -204,6 +212,42 @@ async fn update_channel(
     Ok(Json(channel_to_response(&channel)))
 }
 
+async fn get_file_retention(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<FileRetentionPolicy>, AppError> {
+    if !channel_repo::is_member(&state.db, id, claims.sub).await? {
+        return Err(AppError::Forbidden);
+    }
+
+    let policy = file_retention_repo::get_policy(&state.db, "channel", id).await?;
+    Ok(Json(file_retention::to_response(policy.as_ref())))
+}
+
+/// Set how long the channel's files are kept and how much space they may
+/// take; the oldest files beyond either limit are deleted.
+async fn set_file_retention(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<FileRetentionPolicy>,
+) -> Result<Json<FileRetentionPolicy>, AppError> {
+    // Same permission as the other channel settings
+    let channel_role = channel_repo::get_member_role(&state.db, id, claims.sub).await?;
+    let role =
+        permissions::effective_role(channel_role.as_deref(), claims.is_owner, claims.is_admin);
+    if !permissions::can_manage_roles(&role) {
+        return Err(AppError::Forbidden);
+    }
+    channel_repo::get_channel(&state.db, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("channel not found".to_string()))?;
+
+    let policy = file_retention::set_policy(&state.db, "channel", id, &req, claims.sub).await?;
+    Ok(Json(policy))
+}
+
 async fn join_channel(
     State(state): State<Arc<AppState>>,
     Extension(claims): Extension<AccessClaims>,
@@ -602,7 +646,8 @@ async fn get_unread_counts(
         .map(|c| {
             serde_json::json!({
                 "channel_id": c.channel_id,
-                "unread_count": c.unread_count
+                "unread_count": c.unread_count,
+                "mention_count": c.mention_count
             })
         })
         .collect();
@@ -10,20 +10,19 @@ use uuid::Uuid;
 
 use axum::body::Body;
 use axum::http::header;
-use tokio::io::AsyncWriteExt;
 
 use chatalot_common::api_types::{
     AcceptCommunityInviteResponse, CommunityBanRequest, CommunityInviteInfoResponse,
     CommunityInviteResponse, CommunityMemberResponse, CommunityResponse,
     CreateCommunityInviteRequest, CreateCommunityRequest, CreateTimeoutRequest,
-    CreateWarningRequest, CustomEmojiResponse, PaginationQuery, SetCommunityRoleRequest,
-    SetNicknameRequest, TimeoutResponse, TransferCommunityOwnershipRequest, UpdateCommunityRequest,
-    WarningResponse,
+    CreateWarningRequest, CustomEmojiResponse, FileRetentionPolicy, PaginationQuery,
+    SetCommunityRoleRequest, SetNicknameRequest, TimeoutResponse,
+    TransferCommunityOwnershipRequest, UpdateCommunityRequest, WarningResponse,
 };
 use chatalot_common::ws_messages::ServerMessage;
 use chatalot_db::repos::{
-    channel_repo, community_repo, custom_emoji_repo, group_repo, timeout_repo, user_repo,
-    warning_repo,
+    channel_repo, community_repo, custom_emoji_repo, file_retention_repo, group_repo, timeout_repo,
+    user_repo, warning_repo,
 };
 use rand::Rng as _;
 
@@ -32,7 +31,9 @@ use crate::error::AppError;
 use crate::middleware::auth::AccessClaims;
 use crate::middleware::community_gate::CommunityContext;
 use crate::permissions;
-use crate::services::css_sanitizer;
+use crate::routes::files::blob_body;
+use crate::services::{css_sanitizer, file_retention};
+use crate::storage;
 
 /// Public routes (no community gate — user may not be member, but auth required).
 pub fn public_routes() -> Router<Arc<AppState>> {
@@ -68,6 +69,10 @@ pub fn gated_routes() -> Router<Arc<AppState>> {
             "/communities/{cid}/transfer-ownership",
             post(transfer_ownership),
         )
+        .route(
+            "/communities/{cid}/file-retention",
+            get(get_file_retention).put(set_file_retention),
+        )
         .route("/communities/{cid}/leave", post(leave_community))
         .route("/communities/{cid}/members", get(list_members))
         .route(
@@ -126,7 +131,7 @@ async fn create_community(
     Json(req): Json<CreateCommunityRequest>,
 ) -> Result<Json<CommunityResponse>, AppError> {
     // Check creation mode
-    if state.config.community_creation_mode == "admin_only" && !claims.is_admin && !claims.is_owner
+    if state.live().community_creation_mode == "admin_only" && !claims.is_admin && !claims.is_owner
     {
         return Err(AppError::Forbidden);
     }
@@ -539,6 +544,32 @@ async fn transfer_ownership(
     Ok(())
 }
 
+async fn get_file_retention(
+    State(state): State<Arc<AppState>>,
+    Extension(ctx): Extension<CommunityContext>,
+) -> Result<Json<FileRetentionPolicy>, AppError> {
+    let policy = file_retention_repo::get_policy(&state.db, "community", ctx.community_id).await?;
+    Ok(Json(file_retention::to_response(policy.as_ref())))
+}
+
+/// Set how long files shared in the community's channels are kept and how
+/// much space they may take together.
+async fn set_file_retention(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Extension(ctx): Extension<CommunityContext>,
+    Json(req): Json<FileRetentionPolicy>,
+) -> Result<Json<FileRetentionPolicy>, AppError> {
+    if !ctx.can_manage() {
+        return Err(AppError::Forbidden);
+    }
+
+    let policy =
+        file_retention::set_policy(&state.db, "community", ctx.community_id, &req, claims.sub)
+            .await?;
+    Ok(Json(policy))
+}
+
 async fn leave_community(
     State(state): State<Arc<AppState>>,
     Extension(claims): Extension<AccessClaims>,
@@ -987,7 +1018,7 @@ struct ChannelUserPath {
 
 /// Verify a channel belongs to the expected community (via its group).
 async fn verify_channel_community(
-    db: &sqlx::PgPool,
+    db: &chatalot_db::db::Db,
     channel_id: Uuid,
     community_id: Uuid,
 ) -> Result<(), AppError> {
@@ -1229,14 +1260,8 @@ async fn upload_community_icon(
     let (data, ct) = read_image_field(&mut multipart, "icon", MAX_COMMUNITY_ICON_SIZE).await?;
     let ext = image_ext(&ct);
 
-    let asset_dir = std::path::Path::new(&state.config.file_storage_path).join("community_assets");
-    tokio::fs::create_dir_all(&asset_dir)
-        .await
-        .map_err(|e| AppError::Internal(format!("create dir: {e}")))?;
-
     let filename = format!("{}_icon.{ext}", ctx.community_id);
-    let file_path = asset_dir.join(&filename);
-    write_file(&file_path, &data).await?;
+    write_file(&state, &filename, data).await?;
 
     let ts = chrono::Utc::now().timestamp();
     let icon_url = format!("/api/community-assets/{filename}?v={ts}");
@@ -1272,14 +1297,8 @@ async fn upload_community_banner(
     let (data, ct) = read_image_field(&mut multipart, "banner", MAX_COMMUNITY_BANNER_SIZE).await?;
     let ext = image_ext(&ct);
 
-    let asset_dir = std::path::Path::new(&state.config.file_storage_path).join("community_assets");
-    tokio::fs::create_dir_all(&asset_dir)
-        .await
-        .map_err(|e| AppError::Internal(format!("create dir: {e}")))?;
-
     let filename = format!("{}_banner.{ext}", ctx.community_id);
-    let file_path = asset_dir.join(&filename);
-    write_file(&file_path, &data).await?;
+    write_file(&state, &filename, data).await?;
 
     let ts = chrono::Utc::now().timestamp();
     let banner_url = format!("/api/community-assets/{filename}?v={ts}");
@@ -1311,17 +1330,9 @@ async fn serve_community_asset(
         return Err(AppError::Validation("invalid filename".into()));
     }
 
-    let path = std::path::Path::new(&state.config.file_storage_path)
-        .join("community_assets")
-        .join(&filename);
-
-    let file = tokio::fs::File::open(&path)
-        .await
-        .map_err(|_| AppError::NotFound("asset not found".into()))?;
-
+    let key = format!("{}/{filename}", storage::COMMUNITY_ASSETS);
+    let body = blob_body(&state, &key, "asset not found").await?;
     let content_type = guess_content_type(&filename);
-    let stream = tokio_util::io::ReaderStream::new(file);
-    let body = Body::from_stream(stream);
 
     Ok((
         [
@@ -1413,17 +1424,15 @@ fn image_ext(content_type: &str) -> &'static str {
     }
 }
 
-async fn write_file(path: &std::path::Path, data: &[u8]) -> Result<(), AppError> {
-    let mut f = tokio::fs::File::create(path)
-        .await
-        .map_err(|e| AppError::Internal(format!("create file: {e}")))?;
-    f.write_all(data)
-        .await
-        .map_err(|e| AppError::Internal(format!("write file: {e}")))?;
-    f.flush()
+async fn write_file(state: &AppState, filename: &str, data: Vec<u8>) -> Result<(), AppError> {
+    state
+        .storage
+        .put(
+            &format!("{}/{filename}", storage::COMMUNITY_ASSETS),
+            data.into(),
+        )
         .await
-        .map_err(|e| AppError::Internal(format!("flush file: {e}")))?;
-    Ok(())
+        .map_err(|e| AppError::Internal(format!("store asset: {e}")))
 }
 
 fn guess_content_type(filename: &str) -> &'static str {
@@ -1553,34 +1562,16 @@ async fn upload_emoji(
     };
 
     let id = Uuid::now_v7();
-    let emoji_dir = std::path::Path::new(&state.config.file_storage_path).join("emojis");
-    tokio::fs::create_dir_all(&emoji_dir)
-        .await
-        .map_err(|e| AppError::Internal(format!("create emoji dir: {e}")))?;
-
-    let filename = format!("{id}.{ext}");
-    let file_path = emoji_dir.join(&filename);
-
-    let mut f = tokio::fs::File::create(&file_path)
-        .await
-        .map_err(|e| AppError::Internal(format!("create emoji file: {e}")))?;
-    f.write_all(&data)
+    let key = format!("{}/{id}.{ext}", storage::EMOJIS);
+    state
+        .storage
+        .put(&key, data.into())
         .await
-        .map_err(|e| AppError::Internal(format!("write emoji: {e}")))?;
-    f.flush()
-        .await
-        .map_err(|e| AppError::Internal(format!("flush emoji: {e}")))?;
+        .map_err(|e| AppError::Internal(format!("store emoji: {e}")))?;
 
-    let emoji = custom_emoji_repo::create(
-        &state.db,
-        id,
-        ctx.community_id,
-        &sc,
-        &file_path.to_string_lossy(),
-        ct,
-        claims.sub,
-    )
-    .await?;
+    let emoji =
+        custom_emoji_repo::create(&state.db, id, ctx.community_id, &sc, &key, ct, claims.sub)
+            .await?;
 
     Ok(Json(CustomEmojiResponse {
         id: emoji.id,
@@ -1611,8 +1602,8 @@ async fn delete_emoji(
         return Err(AppError::NotFound("emoji not found".into()));
     }
 
-    // Delete file from disk
-    let _ = tokio::fs::remove_file(&emoji.file_path).await;
+    // Delete file from storage
+    let _ = state.storage.delete(&emoji.file_path).await;
 
     custom_emoji_repo::delete(&state.db, path.eid).await?;
     Ok(())
@@ -1626,12 +1617,7 @@ async fn serve_emoji(
         .await?
         .ok_or_else(|| AppError::NotFound("emoji not found".into()))?;
 
-    let file = tokio::fs::File::open(&emoji.file_path)
-        .await
-        .map_err(|_| AppError::NotFound("emoji file not found".into()))?;
-
-    let stream = tokio_util::io::ReaderStream::new(file);
-    let body = Body::from_stream(stream);
+    let body = blob_body(&state, &emoji.file_path, "emoji file not found").await?;
 
     Ok((
         [
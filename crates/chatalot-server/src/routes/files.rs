@@ -1,29 +1,45 @@
+use std::ops::Range;
 use std::sync::Arc;
-use std::time::Instant;
 
 use axum::body::Body;
-use axum::extract::{Path, State};
-use axum::http::header;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Redirect, Response};
 use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
-use dashmap::DashMap;
-use sha2::{Digest, Sha256};
 use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
-use chatalot_common::api_types::{FileMetadataResponse, FileUploadResponse};
-use chatalot_db::repos::{blocked_hash_repo, channel_repo, file_repo, user_repo};
+use chatalot_common::api_types::{
+    FileDownloadQuery, FileMetadataResponse, FileUploadResponse, ThumbnailQuery,
+};
+use chatalot_db::repos::{channel_repo, file_repo};
 
 use crate::app_state::AppState;
 use crate::error::AppError;
 use crate::middleware::auth::AccessClaims;
-use crate::services::{file_security, thumbnail_service};
-
-/// Per-user upload rate limiter: max 10 uploads per 60 seconds.
-static UPLOAD_RATE: std::sync::LazyLock<DashMap<Uuid, (Instant, u32)>> =
-    std::sync::LazyLock::new(DashMap::new);
-const UPLOAD_RATE_WINDOW: u64 = 60;
-const UPLOAD_RATE_MAX: u32 = 10;
+use crate::services::thumbnail_service;
+use crate::services::upload_service::{self, UploadMeta};
+use crate::storage::{self, Presign};
+
+use super::uploads::http_date;
+
+/// Types that may be served with `Content-Disposition: inline`: media the
+/// browser decodes without running scripts. SVG, text and PDFs are always
+/// downloaded as attachments.
+const INLINE_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/bmp",
+    "audio/mpeg",
+    "audio/ogg",
+    "audio/flac",
+    "audio/wav",
+    "video/mp4",
+    "video/webm",
+];
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
@@ -38,19 +54,60 @@ pub fn routes() -> Router<Arc<AppState>> {
 
 /// Upload an encrypted file.
 /// The client encrypts the file before upload — the server only stores the ciphertext blob.
+/// The file field is streamed to disk; use the resumable upload endpoints for
+/// large files on unreliable connections.
 async fn upload_file(
     State(state): State<Arc<AppState>>,
     Extension(claims): Extension<AccessClaims>,
     mut multipart: axum::extract::Multipart,
 ) -> Result<Json<FileUploadResponse>, AppError> {
+    // Rate limiting: max 10 uploads per minute per user
+    upload_service::check_rate_limit(claims.sub)?;
+
+    let file_id = Uuid::now_v7();
+    let partial = upload_service::partial_path(&state.config.file_storage_path, file_id);
+    let result = receive_multipart(&state, &mut multipart, &partial).await;
+    let meta = match result {
+        Ok(meta) => meta,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+    };
+
+    // Verify channel membership if a channel_id was provided
+    if let Some(cid) = meta.channel_id
+        && !channel_repo::is_member(&state.db, cid, claims.sub).await?
+    {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(AppError::Forbidden);
+    }
+
+    let record = upload_service::finish_upload(&state, claims.sub, file_id, &partial, meta).await?;
+
+    Ok(Json(FileUploadResponse {
+        id: record.id,
+        size_bytes: record.size_bytes,
+        created_at: record.created_at.to_rfc3339(),
+    }))
+}
+
+/// Read the multipart fields, writing the file field to `partial`.
+async fn receive_multipart(
+    state: &AppState,
+    multipart: &mut axum::extract::Multipart,
+    partial: &std::path::Path,
+) -> Result<UploadMeta, AppError> {
     let max_size = state.config.max_file_size_mb * 1024 * 1024;
 
-    let mut file_data: Option<Vec<u8>> = None;
-    let mut encrypted_name = String::from("unnamed");
-    let mut content_type: Option<String> = None;
-    let mut channel_id: Option<Uuid> = None;
+    let mut received_file = false;
+    let mut meta = UploadMeta {
+        encrypted_name: String::from("unnamed"),
+        content_type: None,
+        channel_id: None,
+    };
 
-    while let Some(field) = multipart
+    while let Some(mut field) = multipart
         .next_field()
         .await
         .map_err(|e| AppError::Validation(format!("multipart error: {e}")))?
@@ -58,25 +115,43 @@ async fn upload_file(
         let name = field.name().unwrap_or("").to_string();
         match name.as_str() {
             "file" => {
-                content_type = field.content_type().map(|s| s.to_string());
-                let bytes = field
-                    .bytes()
+                meta.content_type = field.content_type().map(|s| s.to_string());
+                if let Some(dir) = partial.parent() {
+                    tokio::fs::create_dir_all(dir)
+                        .await
+                        .map_err(|e| AppError::Internal(format!("create dir: {e}")))?;
+                }
+                let mut f = tokio::fs::File::create(partial)
                     .await
-                    .map_err(|e| AppError::Validation(format!("read error: {e}")))?;
-                if bytes.len() as u64 > max_size {
-                    return Err(AppError::Validation(format!(
-                        "file too large (max {} MB)",
-                        state.config.max_file_size_mb
-                    )));
+                    .map_err(|e| AppError::Internal(format!("create file: {e}")))?;
+                let mut size = 0u64;
+                while let Some(chunk) = field
+                    .chunk()
+                    .await
+                    .map_err(|e| AppError::Validation(format!("read error: {e}")))?
+                {
+                    size += chunk.len() as u64;
+                    if size > max_size {
+                        return Err(AppError::Validation(format!(
+                            "file too large (max {} MB)",
+                            state.config.max_file_size_mb
+                        )));
+                    }
+                    f.write_all(&chunk)
+                        .await
+                        .map_err(|e| AppError::Internal(format!("write file: {e}")))?;
                 }
-                file_data = Some(bytes.to_vec());
+                f.flush()
+                    .await
+                    .map_err(|e| AppError::Internal(format!("flush file: {e}")))?;
+                received_file = true;
             }
             "name" => {
-                encrypted_name = field
+                meta.encrypted_name = field
                     .text()
                     .await
                     .map_err(|e| AppError::Validation(format!("read name: {e}")))?;
-                if encrypted_name.len() > 512 {
+                if meta.encrypted_name.len() > 512 {
                     return Err(AppError::Validation("file name too long (max 512 bytes)".to_string()));
                 }
             }
@@ -85,188 +160,30 @@ async fn upload_file(
                     .text()
                     .await
                     .map_err(|e| AppError::Validation(format!("read channel_id: {e}")))?;
-                channel_id = Uuid::parse_str(&text).ok();
+                meta.channel_id = Some(
+                    Uuid::parse_str(&text)
+                        .map_err(|_| AppError::Validation("invalid channel_id".to_string()))?,
+                );
             }
             _ => {}
         }
     }
 
-    let data = file_data.ok_or_else(|| AppError::Validation("no file field".to_string()))?;
-
-    // Rate limiting: max 10 uploads per minute per user
-    {
-        let mut entry = UPLOAD_RATE.entry(claims.sub).or_insert((Instant::now(), 0));
-        let (window_start, count) = entry.value_mut();
-        if window_start.elapsed().as_secs() >= UPLOAD_RATE_WINDOW {
-            *window_start = Instant::now();
-            *count = 1;
-        } else if *count >= UPLOAD_RATE_MAX {
-            return Err(AppError::Validation(
-                "upload rate limit exceeded (max 10 per minute)".to_string(),
-            ));
-        } else {
-            *count += 1;
-        }
+    if !received_file {
+        return Err(AppError::Validation("no file field".to_string()));
     }
-
-    // Verify channel membership if a channel_id was provided
-    if let Some(cid) = channel_id
-        && !channel_repo::is_member(&state.db, cid, claims.sub).await?
-    {
-        return Err(AppError::Forbidden);
-    }
-
-    let size_bytes = data.len() as i64;
-
-    // Check per-user upload quota
-    let quota_bytes = state.config.upload_quota_mb as i64 * 1024 * 1024;
-    if quota_bytes > 0 {
-        let used = user_repo::get_upload_bytes_used(&state.db, claims.sub).await?;
-        if used + size_bytes > quota_bytes {
-            let remaining_mb = ((quota_bytes - used) as f64 / 1024.0 / 1024.0).max(0.0);
-            return Err(AppError::Validation(format!(
-                "upload quota exceeded ({} MB limit, {:.1} MB remaining)",
-                state.config.upload_quota_mb, remaining_mb
-            )));
-        }
-    }
-
-    let checksum = hex::encode(Sha256::digest(&data));
-
-    // Security: check if this file hash is blocked
-    if blocked_hash_repo::is_hash_blocked(&state.db, &checksum).await? {
-        return Err(AppError::Validation(
-            "this file has been blocked by an administrator".to_string(),
-        ));
-    }
-
-    // Security: validate file type via magic bytes and use detected type
-    let detected_type = match file_security::validate_file_type(&data) {
-        Ok(detected) => Some(detected.to_string()),
-        Err(reason) => {
-            return Err(AppError::Validation(format!(
-                "file type not allowed: {reason}"
-            )));
-        }
-    };
-
-    // Prefer server-detected content type over client-claimed MIME
-    let content_type = detected_type.or(content_type);
-
-    // Store the file on disk
-    let file_id = Uuid::now_v7();
-    let storage_dir = std::path::Path::new(&state.config.file_storage_path);
-    // Use first 2 chars of ID as subdirectory for sharding
-    let id_str = file_id.to_string();
-    let shard_dir = storage_dir.join(&id_str[..2]);
-    tokio::fs::create_dir_all(&shard_dir)
-        .await
-        .map_err(|e| AppError::Internal(format!("create dir: {e}")))?;
-
-    // Security: strip EXIF metadata and sanitize SVGs before storing
-    let mut data = data;
-    let mut exif_stripped = false;
-
-    if let Some(ref ct) = content_type {
-        // Strip EXIF from images (removes GPS coordinates, camera info, etc.)
-        if matches!(ct.as_str(), "image/jpeg" | "image/png" | "image/webp") {
-            match thumbnail_service::strip_exif(&data, ct).await {
-                Some(clean) => {
-                    data = clean;
-                    exif_stripped = true;
-                }
-                None => {
-                    tracing::warn!("EXIF stripping failed for upload, rejecting");
-                    return Err(AppError::Validation(
-                        "failed to process image metadata — please try a different file".to_string(),
-                    ));
-                }
-            }
-        }
-
-        // Sanitize SVG files (strip scripts, event handlers, etc.)
-        if ct == "image/svg+xml" {
-            match file_security::sanitize_svg(&data) {
-                Ok(clean) => data = clean,
-                Err(reason) => {
-                    return Err(AppError::Validation(format!("invalid SVG: {reason}")));
-                }
-            }
-        }
-    }
-
-    // Update size after potential re-encoding
-    let size_bytes = data.len() as i64;
-
-    let file_path = shard_dir.join(&id_str);
-    let mut f = tokio::fs::File::create(&file_path)
-        .await
-        .map_err(|e| AppError::Internal(format!("create file: {e}")))?;
-    f.write_all(&data)
-        .await
-        .map_err(|e| AppError::Internal(format!("write file: {e}")))?;
-    f.flush()
-        .await
-        .map_err(|e| AppError::Internal(format!("flush file: {e}")))?;
-
-    // Generate thumbnail for image files
-    let mut thumbnail_path: Option<String> = None;
-    if let Some(ref ct) = content_type {
-        let thumb_file = shard_dir.join(format!("{id_str}_thumb"));
-        match thumbnail_service::generate_thumbnail(&data, ct, &thumb_file).await {
-            Ok(true) => {
-                thumbnail_path = Some(thumb_file.to_string_lossy().to_string());
-            }
-            Ok(false) => {} // not an image type
-            Err(e) => {
-                tracing::warn!("thumbnail generation failed for {id_str}: {e}");
-                // Non-fatal — upload still succeeds without thumbnail
-            }
-        }
-    }
-
-    // Record metadata in DB (clean up orphaned file on failure)
-    let record = match file_repo::create_file(
-        &state.db,
-        file_id,
-        claims.sub,
-        &encrypted_name,
-        size_bytes,
-        content_type.as_deref(),
-        &file_path.to_string_lossy(),
-        &checksum,
-        channel_id,
-        thumbnail_path.as_deref(),
-        exif_stripped,
-    )
-    .await
-    {
-        Ok(r) => r,
-        Err(e) => {
-            let _ = tokio::fs::remove_file(&file_path).await;
-            if let Some(ref tp) = thumbnail_path {
-                let _ = tokio::fs::remove_file(tp).await;
-            }
-            return Err(e.into());
-        }
-    };
-
-    // Track quota usage
-    user_repo::increment_upload_bytes(&state.db, claims.sub, size_bytes).await?;
-
-    Ok(Json(FileUploadResponse {
-        id: record.id,
-        size_bytes: record.size_bytes,
-        created_at: record.created_at.to_rfc3339(),
-    }))
+    Ok(meta)
 }
 
-/// Download an encrypted file blob.
+/// Download an encrypted file blob. Supports `Range`, `If-Range` and
+/// `If-None-Match`; `?inline=true` renders safe media types in the browser.
 async fn download_file(
     State(state): State<Arc<AppState>>,
     Extension(claims): Extension<AccessClaims>,
     Path(file_id): Path<Uuid>,
-) -> Result<([(header::HeaderName, String); 3], Body), AppError> {
+    Query(query): Query<FileDownloadQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
     let record = file_repo::get_file(&state.db, file_id)
         .await?
         .ok_or_else(|| AppError::NotFound("file not found".to_string()))?;
@@ -286,31 +203,178 @@ async fn download_file(
         return Err(AppError::Forbidden);
     }
 
-    let file = tokio::fs::File::open(&record.storage_path)
-        .await
-        .map_err(|e| AppError::Internal(format!("open file: {e}")))?;
-
-    let stream = tokio_util::io::ReaderStream::new(file);
-    let body = Body::from_stream(stream);
-
     let content_type = record
         .content_type
         .unwrap_or_else(|| "application/octet-stream".to_string());
+    let inline = query.inline && INLINE_TYPES.contains(&content_type.as_str());
+    let disposition = format!(
+        "{}; filename=\"{}\"",
+        if inline { "inline" } else { "attachment" },
+        record
+            .encrypted_name
+            .replace('"', "'")
+            .replace(['\n', '\r'], "_")
+    );
+
+    // The storage service handles Range requests for presigned URLs itself
+    if let Some(url) = state.storage.presigned_url(
+        &record.storage_path,
+        &Presign {
+            content_type: &content_type,
+            content_disposition: &disposition,
+        },
+    ) {
+        return Ok(presigned_redirect(&url));
+    }
 
-    Ok((
-        [
-            (header::CONTENT_TYPE, content_type),
-            (header::CACHE_CONTROL, "private, no-store".to_string()),
-            (
-                header::CONTENT_DISPOSITION,
-                format!(
-                    "attachment; filename=\"{}\"",
-                    record.encrypted_name.replace('"', "'").replace(['\n', '\r'], "_")
-                ),
-            ),
-        ],
-        body,
-    ))
+    // Blobs never change once stored, so the upload checksum is a strong
+    // validator. `no-cache` lets clients keep a copy but revalidate, which
+    // repeats the access check above.
+    let etag = format!("\"{}\"", record.checksum);
+    let last_modified = http_date(record.created_at);
+    let validators = [
+        (header::ETAG, etag.clone()),
+        (header::LAST_MODIFIED, last_modified.clone()),
+        (header::CACHE_CONTROL, "private, no-cache".to_string()),
+    ];
+    if if_none_match(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, validators).into_response());
+    }
+
+    let size = record.size_bytes.max(0) as u64;
+    let range = if if_range_holds(&headers, &etag, &last_modified) {
+        byte_range(&headers, size)
+    } else {
+        ByteRange::Full
+    };
+    let entity = [
+        (header::CONTENT_TYPE, content_type),
+        (header::CONTENT_DISPOSITION, disposition),
+        (header::ACCEPT_RANGES, "bytes".to_string()),
+    ];
+
+    match range {
+        ByteRange::Full => {
+            let body = blob_body(&state, &record.storage_path, "file not found").await?;
+            Ok((
+                validators,
+                entity,
+                [(header::CONTENT_LENGTH, size.to_string())],
+                body,
+            )
+                .into_response())
+        }
+        ByteRange::Partial(range) => {
+            let content_range = format!("bytes {}-{}/{size}", range.start, range.end - 1);
+            let length = range.end - range.start;
+            let body = match state.storage.get_range(&record.storage_path, range).await {
+                Ok(stream) => Body::from_stream(stream),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(AppError::NotFound("file not found".to_string()));
+                }
+                Err(e) => {
+                    return Err(AppError::Internal(format!(
+                        "read {}: {e}",
+                        record.storage_path
+                    )));
+                }
+            };
+            Ok((
+                StatusCode::PARTIAL_CONTENT,
+                validators,
+                entity,
+                [
+                    (header::CONTENT_RANGE, content_range),
+                    (header::CONTENT_LENGTH, length.to_string()),
+                ],
+                body,
+            )
+                .into_response())
+        }
+        ByteRange::Unsatisfiable => Ok((
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            validators,
+            [(header::CONTENT_RANGE, format!("bytes */{size}"))],
+        )
+            .into_response()),
+    }
+}
+
+/// What part of a blob a `Range` header asks for.
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Full,
+    Partial(Range<u64>),
+    Unsatisfiable,
+}
+
+/// Parse a single `bytes=` range against a blob of `size` bytes. Headers
+/// this does not understand, including multiple ranges, are ignored and the
+/// whole blob is sent, as RFC 9110 allows.
+fn byte_range(headers: &HeaderMap, size: u64) -> ByteRange {
+    let Some(spec) = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().strip_prefix("bytes="))
+    else {
+        return ByteRange::Full;
+    };
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let (first, last) = (first.trim(), last.trim());
+
+    if first.is_empty() {
+        // Suffix range: the last N bytes
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(n) => ByteRange::Partial(size.saturating_sub(n)..size),
+            Err(_) => ByteRange::Full,
+        };
+    }
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = if last.is_empty() {
+        size
+    } else {
+        match last.parse::<u64>() {
+            Ok(last) if last >= start => last.saturating_add(1).min(size),
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start..end)
+}
+
+/// Whether `If-None-Match` lists `etag` (or `*`), compared weakly as
+/// RFC 9110 prescribes.
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// `If-Range`: honor the range only if the client's copy is still current.
+/// Absent the header, ranges are always honored.
+fn if_range_holds(headers: &HeaderMap, etag: &str, last_modified: &str) -> bool {
+    match headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok()) {
+        None => true,
+        Some(v) => {
+            let v = v.trim();
+            v == etag || v == last_modified
+        }
+    }
 }
 
 /// Get file metadata without downloading the blob.
@@ -340,22 +404,35 @@ async fn get_file_meta(
         content_type: record.content_type,
         checksum: record.checksum,
         created_at: record.created_at.to_rfc3339(),
+        has_thumbnail: record.thumbnail_path.is_some(),
+        duration_ms: record.duration_ms,
+        width: record.width,
+        height: record.height,
+        blurhash: record.blurhash,
+        variants: thumbnail_service::parse_variants(record.variants.as_deref()),
     }))
 }
 
-/// Download the thumbnail for a file.
+/// Download the thumbnail for a file, or with `?size=` one of its WebP
+/// variants. Files without variants fall back to the thumbnail.
 async fn download_thumbnail(
     State(state): State<Arc<AppState>>,
     Extension(claims): Extension<AccessClaims>,
     Path(file_id): Path<Uuid>,
-) -> Result<([(header::HeaderName, String); 3], Body), AppError> {
+    Query(query): Query<ThumbnailQuery>,
+) -> Result<Response, AppError> {
     let record = file_repo::get_file(&state.db, file_id)
         .await?
         .ok_or_else(|| AppError::NotFound("file not found".to_string()))?;
 
-    let thumb_path = record
-        .thumbnail_path
+    let variant = query.size.and_then(|size| {
+        let sizes = thumbnail_service::parse_variants(record.variants.as_deref());
+        thumbnail_service::pick_variant(&sizes, size)
+    });
+    let variant_path = variant.map(|size| storage::variant_key(&record.storage_path, size));
+    let thumb_path = variant_path
         .as_ref()
+        .or(record.thumbnail_path.as_ref())
         .ok_or_else(|| AppError::NotFound("no thumbnail available".to_string()))?;
 
     // Same access control as file download
@@ -370,28 +447,62 @@ async fn download_thumbnail(
         return Err(AppError::Forbidden);
     }
 
-    let file = tokio::fs::File::open(thumb_path)
-        .await
-        .map_err(|e| AppError::Internal(format!("open thumbnail: {e}")))?;
-
-    let stream = tokio_util::io::ReaderStream::new(file);
-    let body = Body::from_stream(stream);
-
-    // Thumbnails are always JPEG (except sanitized SVGs)
-    let ct = if record.content_type.as_deref() == Some("image/svg+xml") {
-        "image/svg+xml".to_string()
+    // Thumbnails, poster frames, waveforms and PDF pages are always JPEG
+    // (except sanitized SVGs)
+    let ct = if variant.is_some() {
+        "image/webp"
+    } else if record.content_type.as_deref() == Some("image/svg+xml") {
+        "image/svg+xml"
     } else {
-        "image/jpeg".to_string()
+        "image/jpeg"
     };
 
+    if let Some(url) = state.storage.presigned_url(
+        thumb_path,
+        &Presign {
+            content_type: ct,
+            content_disposition: "inline",
+        },
+    ) {
+        return Ok(presigned_redirect(&url));
+    }
+
+    let body = blob_body(&state, thumb_path, "no thumbnail available").await?;
     Ok((
         [
-            (header::CONTENT_TYPE, ct),
+            (header::CONTENT_TYPE, ct.to_string()),
             (header::CACHE_CONTROL, "private, max-age=3600".to_string()),
             (header::CONTENT_DISPOSITION, "inline".to_string()),
         ],
         body,
-    ))
+    )
+        .into_response())
+}
+
+/// Response body streaming the blob at `key` from storage.
+/// A missing blob is a 404 with the given message.
+pub(crate) async fn blob_body(
+    state: &AppState,
+    key: &str,
+    missing: &str,
+) -> Result<Body, AppError> {
+    match state.storage.get(key).await {
+        Ok(stream) => Ok(Body::from_stream(stream)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(AppError::NotFound(missing.to_string()))
+        }
+        Err(e) => Err(AppError::Internal(format!("read {key}: {e}"))),
+    }
+}
+
+/// Send the client to a presigned storage URL. The URL expires, so neither
+/// the redirect nor the URL may be cached.
+fn presigned_redirect(url: &str) -> Response {
+    (
+        [(header::CACHE_CONTROL, "private, no-store")],
+        Redirect::temporary(url),
+    )
+        .into_response()
 }
 
 /// Delete a file. Owner can delete own files; admins can delete any file.
@@ -411,28 +522,11 @@ async fn delete_own_file(
         return Err(AppError::Forbidden);
     }
 
-    // Delete from disk
-    if let Err(e) = tokio::fs::remove_file(&record.storage_path).await {
-        tracing::warn!(
-            "Failed to delete file from disk {}: {e}",
-            record.storage_path
-        );
-    }
-    // Delete thumbnail if present
-    if let Some(ref tp) = record.thumbnail_path {
-        let _ = tokio::fs::remove_file(tp).await;
-    }
-
-    // Delete from DB
-    if is_admin {
-        file_repo::delete_file_admin(&state.db, file_id).await?;
-    } else {
-        file_repo::delete_file(&state.db, file_id, claims.sub).await?;
+    // Delete the record, returning quota; the blob goes with its last file
+    if !upload_service::delete_file(&state.db, state.storage.as_ref(), &record).await? {
+        return Err(AppError::NotFound("file not found".to_string()));
     }
 
-    // Return quota to uploader
-    user_repo::decrement_upload_bytes(&state.db, record.uploader_id, record.size_bytes).await?;
-
     // Audit log
     chatalot_db::repos::user_repo::insert_audit_log(
         &state.db,
@@ -450,3 +544,62 @@ async fn delete_own_file(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, value.parse().unwrap());
+        headers
+    }
+
+    fn range(value: &str, size: u64) -> ByteRange {
+        byte_range(&with(header::RANGE, value), size)
+    }
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        assert_eq!(byte_range(&HeaderMap::new(), 100), ByteRange::Full);
+        assert_eq!(range("bytes=0-9", 100), ByteRange::Partial(0..10));
+        assert_eq!(range("bytes=90-", 100), ByteRange::Partial(90..100));
+        assert_eq!(range("bytes=90-500", 100), ByteRange::Partial(90..100));
+        assert_eq!(range("bytes=-10", 100), ByteRange::Partial(90..100));
+        assert_eq!(range("bytes=-500", 100), ByteRange::Partial(0..100));
+
+        assert_eq!(range("bytes=100-", 100), ByteRange::Unsatisfiable);
+        assert_eq!(range("bytes=-0", 100), ByteRange::Unsatisfiable);
+        assert_eq!(range("bytes=0-", 0), ByteRange::Unsatisfiable);
+
+        // Malformed, reversed, multiple or non-byte ranges get the whole blob
+        assert_eq!(range("bytes=9-0", 100), ByteRange::Full);
+        assert_eq!(range("bytes=a-b", 100), ByteRange::Full);
+        assert_eq!(range("bytes=0-1,5-6", 100), ByteRange::Full);
+        assert_eq!(range("items=0-1", 100), ByteRange::Full);
+    }
+
+    #[test]
+    fn checks_validators() {
+        let etag = "\"abc\"";
+        let date = "Tue, 01 Sep 2026 10:00:00 GMT";
+
+        assert!(if_none_match(&with(header::IF_NONE_MATCH, "\"abc\""), etag));
+        assert!(if_none_match(
+            &with(header::IF_NONE_MATCH, "\"x\", W/\"abc\""),
+            etag
+        ));
+        assert!(if_none_match(&with(header::IF_NONE_MATCH, "*"), etag));
+        assert!(!if_none_match(&with(header::IF_NONE_MATCH, "\"x\""), etag));
+        assert!(!if_none_match(&HeaderMap::new(), etag));
+
+        assert!(if_range_holds(&HeaderMap::new(), etag, date));
+        assert!(if_range_holds(&with(header::IF_RANGE, etag), etag, date));
+        assert!(if_range_holds(&with(header::IF_RANGE, date), etag, date));
+        assert!(!if_range_holds(
+            &with(header::IF_RANGE, "\"old\""),
+            etag,
+            date
+        ));
+    }
+}
@@ -5,8 +5,7 @@ use axum::extract::{Path, Query, State};
 use axum::http::header;
 use axum::routing::{get, patch, post};
 use axum::{Extension, Json, Router};
-use sqlx::query_scalar;
-use tokio::io::AsyncWriteExt;
+use chatalot_db::db::query_scalar;
 use uuid::Uuid;
 
 use chatalot_common::api_types::{
@@ -15,19 +14,25 @@ use chatalot_common::api_types::{
     PaginationQuery, TransferOwnershipRequest, UpdateChannelRequest, UpdateGroupRequest,
 };
 use chatalot_common::ws_messages::ServerMessage;
+use chatalot_db::db::Db;
 use chatalot_db::models::channel::ChannelType;
 use chatalot_db::models::group::Group;
 use chatalot_db::repos::{channel_repo, community_repo, group_repo, invite_repo, sender_key_repo, user_repo};
 use rand::Rng as _;
-use sqlx::PgPool;
 
 use crate::app_state::AppState;
 use crate::error::AppError;
 use crate::middleware::auth::AccessClaims;
 use crate::permissions;
+use crate::routes::files::blob_body;
+use crate::storage;
 
 /// Add a user to all existing channels in a group.
-async fn add_user_to_group_channels(db: &PgPool, group_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+async fn add_user_to_group_channels(
+    db: &Db,
+    group_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), AppError> {
     let channels = group_repo::list_group_channels(db, group_id).await?;
     for ch in &channels {
         // Ignore duplicate key errors (user already in channel)
@@ -41,7 +46,7 @@ async fn add_user_to_group_channels(db: &PgPool, group_id: Uuid, user_id: Uuid)
 /// Instance owner gets implicit 'admin' access to all groups.
 /// For personal groups, community moderators+ get implicit 'admin' access.
 async fn get_effective_group_role(
-    db: &PgPool,
+    db: &Db,
     group: &Group,
     user_id: Uuid,
     is_instance_owner: bool,
@@ -77,7 +82,7 @@ async fn get_effective_group_role(
 
 /// Check if a user is a community moderator+ for the group's community.
 async fn is_community_moderator(
-    db: &PgPool,
+    db: &Db,
     community_id: Uuid,
     user_id: Uuid,
     is_instance_owner: bool,
@@ -1259,14 +1264,8 @@ async fn upload_group_icon(
     let (data, ct) = read_image_field(&mut multipart, "icon", MAX_GROUP_ICON_SIZE).await?;
     let ext = image_ext(&ct);
 
-    let asset_dir = std::path::Path::new(&state.config.file_storage_path).join("group_assets");
-    tokio::fs::create_dir_all(&asset_dir)
-        .await
-        .map_err(|e| AppError::Internal(format!("create dir: {e}")))?;
-
     let filename = format!("{id}_icon.{ext}");
-    let file_path = asset_dir.join(&filename);
-    write_asset_file(&file_path, &data).await?;
+    write_asset_file(&state, &filename, data).await?;
 
     let ts = chrono::Utc::now().timestamp();
     let icon_url = format!("/api/group-assets/{filename}?v={ts}");
@@ -1310,14 +1309,8 @@ async fn upload_group_banner(
     let (data, ct) = read_image_field(&mut multipart, "banner", MAX_GROUP_BANNER_SIZE).await?;
     let ext = image_ext(&ct);
 
-    let asset_dir = std::path::Path::new(&state.config.file_storage_path).join("group_assets");
-    tokio::fs::create_dir_all(&asset_dir)
-        .await
-        .map_err(|e| AppError::Internal(format!("create dir: {e}")))?;
-
     let filename = format!("{id}_banner.{ext}");
-    let file_path = asset_dir.join(&filename);
-    write_asset_file(&file_path, &data).await?;
+    write_asset_file(&state, &filename, data).await?;
 
     let ts = chrono::Utc::now().timestamp();
     let banner_url = format!("/api/group-assets/{filename}?v={ts}");
@@ -1371,14 +1364,8 @@ async fn upload_channel_voice_background(
     let (data, ct) = read_image_field(&mut multipart, "background", MAX_VOICE_BG_SIZE).await?;
     let ext = image_ext(&ct);
 
-    let asset_dir = std::path::Path::new(&state.config.file_storage_path).join("group_assets");
-    tokio::fs::create_dir_all(&asset_dir)
-        .await
-        .map_err(|e| AppError::Internal(format!("create dir: {e}")))?;
-
     let filename = format!("{}_voicebg.{ext}", path.channel_id);
-    let file_path = asset_dir.join(&filename);
-    write_asset_file(&file_path, &data).await?;
+    write_asset_file(&state, &filename, data).await?;
 
     let ts = chrono::Utc::now().timestamp();
     let bg_url = format!("/api/group-assets/{filename}?v={ts}");
@@ -1421,17 +1408,9 @@ async fn serve_group_asset(
         return Err(AppError::Validation("invalid filename".into()));
     }
 
-    let path = std::path::Path::new(&state.config.file_storage_path)
-        .join("group_assets")
-        .join(&filename);
-
-    let file = tokio::fs::File::open(&path)
-        .await
-        .map_err(|_| AppError::NotFound("asset not found".into()))?;
-
+    let key = format!("{}/{filename}", storage::GROUP_ASSETS);
+    let body = blob_body(&state, &key, "asset not found").await?;
     let content_type = guess_content_type(&filename);
-    let stream = tokio_util::io::ReaderStream::new(file);
-    let body = Body::from_stream(stream);
 
     Ok((
         [
@@ -1521,17 +1500,15 @@ fn image_ext(content_type: &str) -> &'static str {
     }
 }
 
-async fn write_asset_file(path: &std::path::Path, data: &[u8]) -> Result<(), AppError> {
-    let mut f = tokio::fs::File::create(path)
-        .await
-        .map_err(|e| AppError::Internal(format!("create file: {e}")))?;
-    f.write_all(data)
-        .await
-        .map_err(|e| AppError::Internal(format!("write file: {e}")))?;
-    f.flush()
+async fn write_asset_file(state: &AppState, filename: &str, data: Vec<u8>) -> Result<(), AppError> {
+    state
+        .storage
+        .put(
+            &format!("{}/{filename}", storage::GROUP_ASSETS),
+            data.into(),
+        )
         .await
-        .map_err(|e| AppError::Internal(format!("flush file: {e}")))?;
-    Ok(())
+        .map_err(|e| AppError::Internal(format!("store asset: {e}")))
 }
 
 fn guess_content_type(filename: &str) -> &'static str {
@@ -1,20 +1,32 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use axum::extract::State;
+use axum::http::StatusCode;
 use axum::routing::get;
 use axum::{Json, Router};
 
-use chatalot_common::api_types::HealthResponse;
+use chatalot_common::api_types::{ComponentCheck, HealthResponse, ReadinessResponse};
 
 use crate::app_state::AppState;
+use crate::jobs;
+use crate::middleware::admin_network::require_admin_network;
 
-pub fn routes() -> Router<Arc<AppState>> {
-    Router::new().route("/health", get(health_check))
+/// DB round trips slower than this mark the database as degraded.
+const DB_SLOW: Duration = Duration::from_millis(250);
+
+pub fn routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/health", get(health_check))
+        .route("/health/live", get(liveness))
+        .merge(Router::new().route("/health/ready", get(readiness)).layer(
+            axum::middleware::from_fn_with_state(state, require_admin_network),
+        ))
 }
 
 async fn health_check(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
     let uptime = state.start_time.elapsed().as_secs();
-    let db_healthy = sqlx::query_scalar::<_, i32>("SELECT 1")
+    let db_healthy = chatalot_db::db::query_scalar::<i32>("SELECT 1")
         .fetch_one(&state.db)
         .await
         .is_ok();
@@ -28,3 +40,199 @@ async fn health_check(State(state): State<Arc<AppState>>) -> Json<HealthResponse
         db_healthy,
     })
 }
+
+/// Liveness: the process is up and serving requests. Does not touch
+/// dependencies, so a database outage never gets the server restarted, and
+/// stays up while draining on shutdown; readiness takes the node out of
+/// rotation instead.
+async fn liveness() -> (StatusCode, &'static str) {
+    (StatusCode::OK, "ok")
+}
+
+/// Readiness: every dependency needed to serve traffic is usable. Responds
+/// 503 if any component fails.
+async fn readiness(State(state): State<Arc<AppState>>) -> (StatusCode, Json<ReadinessResponse>) {
+    let (database, migrations, storage, jobs) = tokio::join!(
+        check_database(&state),
+        check_migrations(&state),
+        check_storage(&state),
+        check_jobs(&state),
+    );
+    let checks = vec![
+        database,
+        migrations,
+        storage,
+        check_jwt_keys(&state),
+        check_push(&state),
+        jobs,
+    ];
+
+    let status = if state.is_shutting_down() || checks.iter().any(|c| c.status == "fail") {
+        "unavailable"
+    } else if checks.iter().any(|c| c.status == "warn") {
+        "degraded"
+    } else {
+        "ok"
+    };
+    let code = if status == "unavailable" {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+
+    (
+        code,
+        Json(ReadinessResponse {
+            status: status.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_secs: state.start_time.elapsed().as_secs(),
+            checks,
+        }),
+    )
+}
+
+fn check(component: &str, status: &str, detail: Option<String>) -> ComponentCheck {
+    ComponentCheck {
+        component: component.to_string(),
+        status: status.to_string(),
+        latency_ms: None,
+        detail,
+    }
+}
+
+async fn check_database(state: &AppState) -> ComponentCheck {
+    let start = Instant::now();
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        chatalot_db::db::query_scalar::<i32>("SELECT 1").fetch_one(&state.db),
+    )
+    .await;
+    let elapsed = start.elapsed();
+
+    let mut c = match result {
+        Ok(Ok(_)) if elapsed > DB_SLOW => check("database", "warn", Some("slow round trip".into())),
+        Ok(Ok(_)) => check("database", "ok", None),
+        Ok(Err(e)) => check("database", "fail", Some(e.to_string())),
+        Err(_) => check("database", "fail", Some("timed out after 5s".into())),
+    };
+    c.latency_ms = Some(elapsed.as_millis() as u64);
+    c
+}
+
+async fn check_migrations(state: &AppState) -> ComponentCheck {
+    match chatalot_db::pool::pending_migrations(&state.db).await {
+        Ok(pending) if pending.is_empty() => check("migrations", "ok", None),
+        Ok(pending) => check("migrations", "fail", Some(format!("pending: {pending:?}"))),
+        Err(e) => check("migrations", "fail", Some(e.to_string())),
+    }
+}
+
+async fn check_storage(state: &AppState) -> ComponentCheck {
+    let dir = std::path::PathBuf::from(&state.config.file_storage_path);
+
+    // Writability: create and remove a probe blob
+    let probe = format!(".readiness-{}", uuid::Uuid::new_v4());
+    let put = tokio::time::timeout(
+        Duration::from_secs(5),
+        state.storage.put(&probe, bytes::Bytes::from_static(b"ok")),
+    )
+    .await;
+    match put {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => return check("storage", "fail", Some(format!("not writable: {e}"))),
+        Err(_) => return check("storage", "fail", Some("write timed out after 5s".into())),
+    }
+    let _ = state.storage.delete(&probe).await;
+
+    // Uploads in progress are always written to local disk, whatever the backend
+
+    let free = match tokio::task::spawn_blocking(move || free_bytes(&dir)).await {
+        Ok(Ok(free)) => free,
+        Ok(Err(e)) => return check("storage", "warn", Some(format!("free space unknown: {e}"))),
+        Err(e) => return check("storage", "warn", Some(format!("free space unknown: {e}"))),
+    };
+    let free_mb = free / (1024 * 1024);
+    let min_mb = state.config.min_free_storage_mb;
+    let detail = Some(format!("{free_mb} MB free"));
+    if free_mb < min_mb {
+        check("storage", "fail", detail)
+    } else if free_mb < min_mb.saturating_mul(2) {
+        check("storage", "warn", detail)
+    } else {
+        check("storage", "ok", detail)
+    }
+}
+
+/// Free space available to unprivileged users on the filesystem holding `path`.
+#[cfg(unix)]
+fn free_bytes(path: &std::path::Path) -> std::io::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is a valid NUL-terminated string and stat is a valid out pointer.
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_bytes(_path: &std::path::Path) -> std::io::Result<u64> {
+    Err(std::io::Error::other("unsupported platform"))
+}
+
+fn check_jwt_keys(state: &AppState) -> ComponentCheck {
+    // The keys are loaded at startup; make sure the files are still there for
+    // the next restart and that the loaded pair signs and verifies.
+    for path in [
+        &state.config.jwt_private_key_path,
+        &state.config.jwt_public_key_path,
+    ] {
+        if let Err(e) = std::fs::metadata(path) {
+            return check("jwt_keys", "fail", Some(format!("{path}: {e}")));
+        }
+    }
+
+    let claims = serde_json::json!({
+        "sub": "readiness",
+        "exp": chrono::Utc::now().timestamp() + 60,
+    });
+    let header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::EdDSA);
+    let validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::EdDSA);
+    match jsonwebtoken::encode(&header, &claims, &state.jwt_encoding_key).and_then(|token| {
+        jsonwebtoken::decode::<serde_json::Value>(&token, &state.jwt_decoding_key, &validation)
+    }) {
+        Ok(_) => check("jwt_keys", "ok", None),
+        Err(e) => check("jwt_keys", "fail", Some(format!("sign/verify failed: {e}"))),
+    }
+}
+
+fn check_push(state: &AppState) -> ComponentCheck {
+    match state.push_service {
+        Some(ref push) => check(
+            "push",
+            "ok",
+            Some(format!("transports: {}", push.kinds().join(", "))),
+        ),
+        None => check("push", "disabled", None),
+    }
+}
+
+async fn check_jobs(state: &AppState) -> ComponentCheck {
+    match jobs::health(state).await {
+        Ok(h) if !h.stale.is_empty() => check(
+            "jobs",
+            "fail",
+            Some(format!("overdue: {}", h.stale.join(", "))),
+        ),
+        Ok(h) if !h.failing.is_empty() => check(
+            "jobs",
+            "warn",
+            Some(format!("failing: {}", h.failing.join(", "))),
+        ),
+        Ok(_) => check("jobs", "ok", None),
+        Err(e) => check("jobs", "fail", Some(e.to_string())),
+    }
+}
@@ -257,7 +257,7 @@ pub fn cleanup_preview_cache() -> usize {
     count
 }
 
-fn is_private_ip(ip: std::net::IpAddr) -> bool {
+pub(crate) fn is_private_ip(ip: std::net::IpAddr) -> bool {
     match ip {
         std::net::IpAddr::V4(v4) => {
             v4.is_private()
@@ -290,7 +290,7 @@ fn is_private_ip(ip: std::net::IpAddr) -> bool {
     }
 }
 
-fn is_private_host(host: &str) -> bool {
+pub(crate) fn is_private_host(host: &str) -> bool {
     if host == "localhost" || host == "0.0.0.0" {
         return true;
     }
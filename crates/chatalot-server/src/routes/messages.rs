@@ -6,11 +6,13 @@ use axum::{Extension, Json, Router};
 use uuid::Uuid;
 
 use chatalot_common::api_types::{
-    MessageEditResponse, MessageResponse, MessagesQuery, PinnedMessageResponse, ReactionInfo,
-    SearchQuery,
+    MentionResponse, MentionsQuery, MessageDeliveryResponse, MessageEditResponse, MessageResponse,
+    MessagesQuery, PinnedMessageResponse, ReactionInfo, SearchQuery,
 };
 use chatalot_common::ws_messages::ServerMessage;
-use chatalot_db::repos::{channel_repo, message_repo, pin_repo, reaction_repo};
+use chatalot_db::repos::{
+    channel_repo, delivery_repo, mention_repo, message_repo, pin_repo, reaction_repo,
+};
 
 use crate::app_state::AppState;
 use crate::error::AppError;
@@ -19,6 +21,7 @@ use crate::permissions;
 
 use message_repo::SearchFilters;
 
+#[allow(dead_code)]
 const DEFAULT_MAX_PINS: i64 = 50;
 
 fn build_search_filters(query: &SearchQuery) -> SearchFilters {
@@ -46,11 +49,16 @@ pub fn routes() -> Router<Arc<AppState>> {
             "/channels/{id}/messages/{msg_id}/history",
             get(get_edit_history),
         )
+        .route(
+            "/channels/{id}/messages/{msg_id}/deliveries",
+            get(get_deliveries),
+        )
         .route(
             "/channels/{id}/threads/{msg_id}",
             get(get_thread_messages),
         )
         .route("/messages/search", get(global_search_messages))
+        .route("/mentions", get(list_mentions))
         .route("/channels/{id}/pins", get(list_pins))
         .route(
             "/channels/{id}/pins/{msg_id}",
@@ -128,6 +136,34 @@ async fn global_search_messages(
     Ok(Json(messages_to_responses(messages, reactions_map, thread_map)))
 }
 
+// ── Mentions ──
+
+/// Inbox of messages that mention the current user, newest first.
+async fn list_mentions(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Query(query): Query<MentionsQuery>,
+) -> Result<Json<Vec<MentionResponse>>, AppError> {
+    let limit = query.limit.unwrap_or(50).min(100);
+    let mentioned = mention_repo::list_mentions(&state.db, claims.sub, query.before, limit).await?;
+
+    let kinds: Vec<String> = mentioned.iter().map(|m| m.mention_kind.clone()).collect();
+    let messages: Vec<_> = mentioned.into_iter().map(|m| m.message).collect();
+
+    let reactions_map = fetch_reactions_map(&state.db, &messages).await?;
+    let thread_map = fetch_thread_map(&state.db, &messages).await?;
+    Ok(Json(
+        messages_to_responses(messages, reactions_map, thread_map)
+            .into_iter()
+            .zip(kinds)
+            .map(|(message, mention_kind)| MentionResponse {
+                mention_kind,
+                message,
+            })
+            .collect(),
+    ))
+}
+
 // ── Threads ──
 
 async fn get_thread_messages(
@@ -279,7 +315,7 @@ async fn unpin_message(
 // ── Helpers ──
 
 async fn fetch_reactions_map(
-    db: &sqlx::PgPool,
+    db: &chatalot_db::db::Db,
     messages: &[chatalot_db::models::message::Message],
 ) -> Result<std::collections::HashMap<Uuid, Vec<ReactionInfo>>, AppError> {
     let message_ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
@@ -298,7 +334,7 @@ async fn fetch_reactions_map(
 }
 
 async fn fetch_thread_map(
-    db: &sqlx::PgPool,
+    db: &chatalot_db::db::Db,
     messages: &[chatalot_db::models::message::Message],
 ) -> Result<std::collections::HashMap<Uuid, message_repo::ThreadInfo>, AppError> {
     let message_ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
@@ -372,3 +408,35 @@ async fn get_edit_history(
             .collect(),
     ))
 }
+
+/// Delivery receipts for a message. Only the sender may see who received it.
+async fn get_deliveries(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path((channel_id, msg_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<MessageDeliveryResponse>>, AppError> {
+    if !channel_repo::is_member(&state.db, channel_id, claims.sub).await? {
+        return Err(AppError::Forbidden);
+    }
+
+    let msg = message_repo::get_message_by_id(&state.db, msg_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("message not found".into()))?;
+    if msg.channel_id != channel_id {
+        return Err(AppError::NotFound("message not found".into()));
+    }
+    if msg.sender_id != Some(claims.sub) {
+        return Err(AppError::Forbidden);
+    }
+
+    let deliveries = delivery_repo::list_deliveries(&state.db, msg_id).await?;
+    Ok(Json(
+        deliveries
+            .into_iter()
+            .map(|d| MessageDeliveryResponse {
+                user_id: d.user_id,
+                delivered_at: d.delivered_at.to_rfc3339(),
+            })
+            .collect(),
+    ))
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::Router;
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::{Method, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+
+use crate::app_state::AppState;
+use crate::metrics::{self, METRICS};
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/metrics", get(render_metrics))
+}
+
+/// Prometheus scrape endpoint. Only routed when `METRICS_ENABLED` is set.
+async fn render_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let mut out = String::with_capacity(16 * 1024);
+
+    let sessions = state.connections.session_count();
+    let sse_sessions = state.sse_sessions.len();
+    metrics::gauge(
+        &mut out,
+        "chatalot_ws_sessions",
+        "Open WebSocket sessions.",
+        sessions.saturating_sub(sse_sessions),
+    );
+    metrics::gauge(
+        &mut out,
+        "chatalot_sse_sessions",
+        "Open SSE fallback sessions.",
+        sse_sessions,
+    );
+    metrics::gauge(
+        &mut out,
+        "chatalot_online_users",
+        "Users with at least one realtime session.",
+        state.connections.user_count(),
+    );
+
+    let pool_size = state.db.size();
+    let pool_idle = state.db.num_idle() as u32;
+    metrics::gauge(
+        &mut out,
+        "chatalot_db_pool_connections",
+        "Open database connections.",
+        pool_size,
+    );
+    metrics::gauge(
+        &mut out,
+        "chatalot_db_pool_in_use",
+        "Database connections checked out of the pool.",
+        pool_size.saturating_sub(pool_idle),
+    );
+    metrics::gauge(
+        &mut out,
+        "chatalot_db_pool_max_connections",
+        "Configured maximum pool size.",
+        state.db.max_connections(),
+    );
+
+    metrics::gauge(
+        &mut out,
+        "chatalot_uptime_seconds",
+        "Seconds since the server started.",
+        state.start_time.elapsed().as_secs(),
+    );
+
+    METRICS.render(&mut out);
+
+    (
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        out,
+    )
+}
+
+/// Record request latency per matched route. Installed with `route_layer` so
+/// the matched path is known; unmatched requests (SPA fallback) are not recorded.
+pub async fn track_latency(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string());
+    let method = request.method().clone();
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    if let Some(route) = route {
+        METRICS.request(
+            method_label(&method),
+            &route,
+            response.status().as_u16(),
+            start.elapsed(),
+        );
+    }
+    response
+}
+
+/// Label for a request method. Extension methods are collapsed so clients
+/// can't create unbounded label values.
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::OPTIONS => "OPTIONS",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_methods_are_labelled_other() {
+        assert_eq!(method_label(&Method::PATCH), "PATCH");
+        assert_eq!(method_label(&Method::CONNECT), "other");
+        assert_eq!(
+            method_label(&Method::from_bytes(b"PURGE").unwrap()),
+            "other"
+        );
+    }
+}
@@ -15,11 +15,14 @@ pub mod keys;
 pub mod legal;
 pub mod link_preview;
 pub mod messages;
+pub mod metrics;
+pub mod notifications;
 pub mod polls;
 pub mod push;
 pub mod scheduled;
 pub mod sender_keys;
 pub mod totp;
+pub mod uploads;
 pub mod users;
 pub mod webhooks;
 
@@ -45,13 +48,17 @@ use crate::ws::session::ws_upgrade;
 
 pub fn build_router(state: Arc<AppState>) -> Router {
     // Auth routes with stricter rate limiting
-    let auth_routes = Router::new()
-        .merge(auth::routes())
-        .layer(axum::middleware::from_fn(auth_rate_limit_middleware));
+    let auth_routes =
+        Router::new()
+            .merge(auth::routes())
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                auth_rate_limit_middleware,
+            ));
 
     // Public routes (no auth required)
     let public_routes = auth_routes
-        .merge(health::routes())
+        .merge(health::routes(state.clone()))
         .merge(legal::routes())
         .merge(account::public_routes())
         .merge(webhooks::public_routes())
@@ -73,6 +80,7 @@ pub fn build_router(state: Arc<AppState>) -> Router {
         .merge(sender_keys::routes())
         .merge(dms::routes())
         .merge(files::routes())
+        .merge(uploads::routes())
         .merge(totp::routes())
         .merge(users::routes())
         .merge(feedback::routes())
@@ -86,6 +94,8 @@ pub fn build_router(state: Arc<AppState>) -> Router {
         .merge(bookmarks::routes())
         .merge(announcements::routes())
         .merge(push::routes())
+        .merge(notifications::routes())
+        .merge(crate::ws::sse::routes())
         .merge(communities::public_routes())
         .merge(community_gated_routes)
         .layer(axum::middleware::from_fn_with_state(
@@ -158,6 +168,7 @@ pub fn build_router(state: Arc<AppState>) -> Router {
             .allow_origin(origins)
             .allow_methods([
                 Method::GET,
+                Method::HEAD,
                 Method::POST,
                 Method::PUT,
                 Method::PATCH,
@@ -167,16 +178,49 @@ pub fn build_router(state: Arc<AppState>) -> Router {
             .allow_headers([
                 header::CONTENT_TYPE,
                 header::AUTHORIZATION,
+                header::HeaderName::from_static("last-event-id"),
+                header::HeaderName::from_static("tus-resumable"),
+                header::HeaderName::from_static("upload-length"),
+                header::HeaderName::from_static("upload-metadata"),
+                header::HeaderName::from_static("upload-offset"),
+            ])
+            // Resumable upload clients read these from cross-origin responses
+            .expose_headers([
+                header::LOCATION,
+                header::HeaderName::from_static("tus-resumable"),
+                header::HeaderName::from_static("tus-version"),
+                header::HeaderName::from_static("tus-extension"),
+                header::HeaderName::from_static("tus-max-size"),
+                header::HeaderName::from_static("upload-expires"),
+                header::HeaderName::from_static("upload-length"),
+                header::HeaderName::from_static("upload-offset"),
             ])
     };
 
-    Router::new()
+    let mut app = Router::new()
         .nest("/api", public_routes.merge(protected_routes))
-        .route("/ws", get(ws_upgrade))
-        .merge(sw_route)
+        .route("/ws", get(ws_upgrade));
+    if state.config.metrics_enabled {
+        app = app.route_layer(axum::middleware::from_fn(metrics::track_latency));
+        // Without a dedicated admin listener, scrape from the main one, but
+        // only from ADMIN_NETWORKS like the readiness check
+        if state.config.admin_listen_addr.is_none() {
+            app = app.merge(
+                metrics::routes().layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    crate::middleware::admin_network::require_admin_network,
+                )),
+            );
+        }
+    }
+
+    app.merge(sw_route)
         .merge(favicon_route)
         .fallback_service(spa_fallback)
-        .layer(axum::middleware::from_fn(rate_limit_middleware))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit_middleware,
+        ))
         .layer(axum::middleware::from_fn(security_headers))
         .layer(cors)
         .layer(DefaultBodyLimit::max(110 * 1024 * 1024)) // 110MB (slightly above MAX_FILE_SIZE_MB default)
@@ -184,3 +228,13 @@ pub fn build_router(state: Arc<AppState>) -> Router {
         .layer(TraceLayer::new_for_http())
         .with_state(state)
 }
+
+/// Router for the optional operator listener (`ADMIN_LISTEN_ADDR`): health
+/// checks, plus metrics, which are then not served on the main listener.
+pub fn build_admin_router(state: Arc<AppState>) -> Router {
+    let mut app = Router::new().nest("/api", health::routes(state.clone()));
+    if state.config.metrics_enabled {
+        app = app.merge(metrics::routes());
+    }
+    app.layer(TraceLayer::new_for_http()).with_state(state)
+}
@@ -0,0 +1,273 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::routing::{delete, get};
+use axum::{Extension, Json, Router};
+use uuid::Uuid;
+
+use chatalot_common::api_types::{
+    NotificationSettingResponse, QuietHoursResponse, QuietHoursScheduleDto,
+    UpdateNotificationSettingRequest, UpdateQuietHoursRequest,
+};
+use chatalot_db::models::channel::ChannelType;
+use chatalot_db::models::notification_setting::NotificationSetting;
+use chatalot_db::repos::{
+    channel_repo, community_repo, group_repo, notification_repo, quiet_hours_repo,
+};
+
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::middleware::auth::AccessClaims;
+use crate::services::quiet_hours;
+
+const MAX_QUIET_HOURS_SCHEDULES: usize = 20;
+const MAX_BREAKTHROUGH_USERS: usize = 50;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/notifications/settings",
+            get(list_settings).put(update_setting),
+        )
+        .route(
+            "/notifications/settings/{scope_type}/{scope_id}",
+            delete(delete_setting),
+        )
+        .route(
+            "/notifications/quiet-hours",
+            get(get_quiet_hours)
+                .put(update_quiet_hours)
+                .delete(delete_quiet_hours),
+        )
+}
+
+async fn list_settings(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+) -> Result<Json<Vec<NotificationSettingResponse>>, AppError> {
+    let settings = notification_repo::list_settings(&state.db, claims.sub).await?;
+    Ok(Json(
+        settings.into_iter().map(setting_to_response).collect(),
+    ))
+}
+
+async fn update_setting(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Json(req): Json<UpdateNotificationSettingRequest>,
+) -> Result<Json<NotificationSettingResponse>, AppError> {
+    if !matches!(req.level.as_str(), "all" | "mentions" | "nothing") {
+        return Err(AppError::Validation(
+            "level must be 'all', 'mentions' or 'nothing'".into(),
+        ));
+    }
+
+    let muted_until = match req.muted_until.as_deref() {
+        Some(s) => Some(
+            chrono::DateTime::parse_from_rfc3339(s)
+                .map_err(|_| {
+                    AppError::Validation("muted_until must be an RFC 3339 timestamp".into())
+                })?
+                .with_timezone(&chrono::Utc),
+        ),
+        None => None,
+    };
+
+    check_scope_access(&state, claims.sub, &req.scope_type, req.scope_id).await?;
+
+    let setting = notification_repo::upsert_setting(
+        &state.db,
+        claims.sub,
+        &req.scope_type,
+        req.scope_id,
+        &req.level,
+        muted_until,
+    )
+    .await?;
+
+    Ok(Json(setting_to_response(setting)))
+}
+
+async fn delete_setting(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path((scope_type, scope_id)): Path<(String, Uuid)>,
+) -> Result<(), AppError> {
+    if !notification_repo::delete_setting(&state.db, claims.sub, &scope_type, scope_id).await? {
+        return Err(AppError::NotFound("notification setting not found".into()));
+    }
+    Ok(())
+}
+
+async fn get_quiet_hours(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+) -> Result<Json<QuietHoursResponse>, AppError> {
+    quiet_hours_response(&state, claims.sub).await.map(Json)
+}
+
+async fn update_quiet_hours(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Json(req): Json<UpdateQuietHoursRequest>,
+) -> Result<Json<QuietHoursResponse>, AppError> {
+    if req.timezone.parse::<chrono_tz::Tz>().is_err() {
+        return Err(AppError::Validation(
+            "timezone must be an IANA time zone name".into(),
+        ));
+    }
+    let delivery = req.delivery.as_deref().unwrap_or("suppress");
+    if !matches!(delivery, "suppress" | "batch") {
+        return Err(AppError::Validation(
+            "delivery must be 'suppress' or 'batch'".into(),
+        ));
+    }
+    if req.schedules.len() > MAX_QUIET_HOURS_SCHEDULES {
+        return Err(AppError::Validation(format!(
+            "at most {MAX_QUIET_HOURS_SCHEDULES} quiet hours schedules allowed"
+        )));
+    }
+    if req.breakthrough_user_ids.len() > MAX_BREAKTHROUGH_USERS {
+        return Err(AppError::Validation(format!(
+            "at most {MAX_BREAKTHROUGH_USERS} breakthrough users allowed"
+        )));
+    }
+
+    let mut schedules = Vec::with_capacity(req.schedules.len());
+    for s in &req.schedules {
+        if s.days.is_empty() || s.days.iter().any(|d| *d > 6) {
+            return Err(AppError::Validation(
+                "schedule days must be weekdays 0 (Monday) to 6 (Sunday)".into(),
+            ));
+        }
+        let days = s.days.iter().fold(0i16, |acc, d| acc | (1 << d));
+        let (start, end) = (parse_time(&s.start)?, parse_time(&s.end)?);
+        if start == end {
+            return Err(AppError::Validation(
+                "schedule start and end must differ".into(),
+            ));
+        }
+        schedules.push((days, start, end));
+    }
+
+    quiet_hours_repo::replace_config(
+        &state.db,
+        claims.sub,
+        req.enabled,
+        &req.timezone,
+        delivery,
+        req.auto_dnd.unwrap_or(true),
+        &schedules,
+        &req.breakthrough_user_ids,
+    )
+    .await?;
+    quiet_hours::refresh_user(&state, claims.sub).await;
+
+    quiet_hours_response(&state, claims.sub).await.map(Json)
+}
+
+async fn delete_quiet_hours(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+) -> Result<(), AppError> {
+    let was_active = quiet_hours_repo::get_settings(&state.db, claims.sub)
+        .await?
+        .is_some_and(|s| s.quiet_active);
+    if !quiet_hours_repo::delete_config(&state.db, claims.sub).await? {
+        return Err(AppError::NotFound("quiet hours not configured".into()));
+    }
+    if was_active && let Some(ref push) = state.push_service {
+        quiet_hours::flush_deferred(&state.db, push, claims.sub).await;
+    }
+    Ok(())
+}
+
+async fn quiet_hours_response(
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<QuietHoursResponse, AppError> {
+    let settings = quiet_hours_repo::get_settings(&state.db, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("quiet hours not configured".into()))?;
+    let schedules = quiet_hours_repo::list_schedules(&state.db, user_id).await?;
+    let breakthrough_user_ids = quiet_hours_repo::list_breakthrough(&state.db, user_id).await?;
+
+    Ok(QuietHoursResponse {
+        enabled: settings.enabled,
+        timezone: settings.timezone,
+        delivery: settings.delivery,
+        auto_dnd: settings.auto_dnd,
+        active: settings.enabled && settings.quiet_active,
+        schedules: schedules
+            .into_iter()
+            .map(|s| QuietHoursScheduleDto {
+                days: (0..7u8).filter(|d| s.days & (1 << d) != 0).collect(),
+                start: format_time(s.start_minute),
+                end: format_time(s.end_minute),
+            })
+            .collect(),
+        breakthrough_user_ids,
+    })
+}
+
+/// Parse "HH:MM" into minutes after midnight.
+fn parse_time(s: &str) -> Result<i16, AppError> {
+    let invalid = || AppError::Validation(format!("invalid time '{s}', expected HH:MM"));
+    let (h, m) = s.split_once(':').ok_or_else(invalid)?;
+    let (h, m): (i16, i16) = (
+        h.parse().map_err(|_| invalid())?,
+        m.parse().map_err(|_| invalid())?,
+    );
+    if !(0..24).contains(&h) || !(0..60).contains(&m) {
+        return Err(invalid());
+    }
+    Ok(h * 60 + m)
+}
+
+fn format_time(minutes: i16) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Only allow settings on scopes the user belongs to.
+async fn check_scope_access(
+    state: &AppState,
+    user_id: Uuid,
+    scope_type: &str,
+    scope_id: Uuid,
+) -> Result<(), AppError> {
+    let allowed = match scope_type {
+        "channel" | "dm" => {
+            let channel = channel_repo::get_channel(&state.db, scope_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("channel not found".into()))?;
+            let is_dm = channel.channel_type == ChannelType::Dm;
+            if is_dm != (scope_type == "dm") {
+                return Err(AppError::Validation(
+                    "use scope_type 'dm' for direct messages and 'channel' otherwise".into(),
+                ));
+            }
+            channel_repo::is_member(&state.db, scope_id, user_id).await?
+        }
+        "group" => group_repo::is_member(&state.db, scope_id, user_id).await?,
+        "community" => community_repo::is_community_member(&state.db, scope_id, user_id).await?,
+        _ => {
+            return Err(AppError::Validation(
+                "scope_type must be 'channel', 'dm', 'group' or 'community'".into(),
+            ));
+        }
+    };
+    if !allowed {
+        return Err(AppError::Forbidden);
+    }
+    Ok(())
+}
+
+fn setting_to_response(s: NotificationSetting) -> NotificationSettingResponse {
+    NotificationSettingResponse {
+        scope_type: s.scope_type,
+        scope_id: s.scope_id,
+        level: s.level,
+        muted_until: s.muted_until.map(|t| t.to_rfc3339()),
+        updated_at: s.updated_at.to_rfc3339(),
+    }
+}
@@ -5,11 +5,14 @@ use axum::http::StatusCode;
 use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
 
-use chatalot_common::api_types::{PushSubscribeRequest, PushUnsubscribeRequest, VapidKeyResponse};
+use chatalot_common::api_types::{
+    PushSubscribeRequest, PushTransportsResponse, PushUnsubscribeRequest, VapidKeyResponse,
+};
 use chatalot_db::repos::push_subscription_repo;
 
 use crate::app_state::AppState;
 use crate::middleware::auth::AccessClaims;
+use crate::services::push_service;
 
 /// Public route: returns the VAPID public key so clients can subscribe.
 async fn get_vapid_key(
@@ -27,6 +30,16 @@ async fn get_vapid_key(
     }))
 }
 
+/// Public route: which push transports this server delivers to.
+async fn get_transports(State(state): State<Arc<AppState>>) -> Json<PushTransportsResponse> {
+    let transports = state
+        .push_service
+        .as_ref()
+        .map(|p| p.kinds().into_iter().map(String::from).collect())
+        .unwrap_or_default();
+    Json(PushTransportsResponse { transports })
+}
+
 /// Subscribe the client's push endpoint.
 async fn subscribe(
     State(state): State<Arc<AppState>>,
@@ -37,20 +50,58 @@ async fn subscribe(
     if req.endpoint.len() > 2048 {
         return Err((StatusCode::BAD_REQUEST, "Endpoint URL too long".into()));
     }
-    if req.p256dh_key.is_empty() || req.auth_key.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "Missing encryption keys".into()));
-    }
-    if !req.endpoint.starts_with("https://") {
-        return Err((StatusCode::BAD_REQUEST, "Endpoint must use HTTPS".into()));
+    let kind = req.kind.as_deref().unwrap_or("webpush");
+    if !state
+        .push_service
+        .as_ref()
+        .is_some_and(|p| p.supports(kind))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Push transport '{kind}' is not enabled on this server"),
+        ));
     }
 
+    let (p256dh_key, auth_key) = match kind {
+        "webpush" => {
+            let p256dh = req.p256dh_key.as_deref().filter(|k| !k.is_empty());
+            let auth = req.auth_key.as_deref().filter(|k| !k.is_empty());
+            if p256dh.is_none() || auth.is_none() {
+                return Err((StatusCode::BAD_REQUEST, "Missing encryption keys".into()));
+            }
+            if !req.endpoint.starts_with("https://") {
+                return Err((StatusCode::BAD_REQUEST, "Endpoint must use HTTPS".into()));
+            }
+            (p256dh, auth)
+        }
+        _ => {
+            push_service::validate_unifiedpush_endpoint(
+                &req.endpoint,
+                &state.config.unifiedpush_allowed_hosts,
+            )
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+            // Optional: with keys the payload is encrypted to the app, without
+            // them the distributor only gets an empty wake-up ping
+            let p256dh = req.p256dh_key.as_deref().filter(|k| !k.is_empty());
+            let auth = req.auth_key.as_deref().filter(|k| !k.is_empty());
+            if p256dh.is_some() != auth.is_some() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "p256dh_key and auth_key must be given together".into(),
+                ));
+            }
+            (p256dh, auth)
+        }
+    };
+
     push_subscription_repo::upsert_subscription(
         &state.db,
         uuid::Uuid::new_v4(),
         claims.sub,
+        kind,
         &req.endpoint,
-        &req.p256dh_key,
-        &req.auth_key,
+        p256dh_key,
+        auth_key,
         None,
     )
     .await
@@ -84,9 +135,11 @@ async fn unsubscribe(
     Ok(StatusCode::NO_CONTENT)
 }
 
-/// Public routes (no auth required) — VAPID key endpoint.
+/// Public routes (no auth required) — VAPID key and transport discovery.
 pub fn public_routes() -> Router<Arc<AppState>> {
-    Router::new().route("/push/vapid-key", get(get_vapid_key))
+    Router::new()
+        .route("/push/vapid-key", get(get_vapid_key))
+        .route("/push/transports", get(get_transports))
 }
 
 /// Protected routes (auth required) — subscribe/unsubscribe.
@@ -0,0 +1,458 @@
+//! Resumable uploads using the tus 1.0.0 protocol (core plus the creation,
+//! expiration and termination extensions).
+//!
+//! `POST /uploads` reserves an upload of a known length, `PATCH` appends
+//! chunks at the current offset, `HEAD` reports the offset after a dropped
+//! connection and `DELETE` abandons the upload. Chunks are streamed to disk;
+//! when the last byte arrives the file goes through the same checks as a
+//! multipart upload and becomes a file whose ID is the upload ID.
+//!
+//! There is no `OPTIONS` discovery: the CORS layer answers every `OPTIONS`
+//! request as a preflight.
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Extension, Router};
+use chrono::{DateTime, Utc};
+use dashmap::DashSet;
+use futures_util::StreamExt;
+use serde_json::json;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use uuid::Uuid;
+
+use chatalot_db::models::upload::Upload;
+use chatalot_db::repos::{channel_repo, upload_repo};
+
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::middleware::auth::AccessClaims;
+use crate::services::upload_service::{self, UPLOAD_EXPIRY, UploadMeta};
+
+const TUS_VERSION: &str = "1.0.0";
+const OFFSET_CONTENT_TYPE: &str = "application/offset+octet-stream";
+
+/// Maximum unfinished uploads per user.
+const MAX_PENDING_UPLOADS: i64 = 10;
+
+/// Uploads currently receiving a chunk on this node.
+static ACTIVE: std::sync::LazyLock<DashSet<Uuid>> = std::sync::LazyLock::new(DashSet::new);
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/uploads", post(create_upload))
+        .route(
+            "/uploads/{upload_id}",
+            axum::routing::head(get_offset)
+                .patch(append_chunk)
+                .delete(cancel_upload),
+        )
+        .layer(axum::middleware::map_response(
+            |mut response: Response| async move {
+                response
+                    .headers_mut()
+                    .insert("tus-resumable", HeaderValue::from_static(TUS_VERSION));
+                response
+            },
+        ))
+}
+
+/// Error response with the usual JSON body but a tus-specific status code.
+fn tus_error(status: StatusCode, code: &str, message: &str) -> Response {
+    let body = json!({
+        "error": {
+            "code": code,
+            "message": message,
+        }
+    });
+    (status, axum::Json(body)).into_response()
+}
+
+/// Reject requests from clients speaking another protocol version.
+fn check_version(headers: &HeaderMap) -> Option<Response> {
+    if headers.get("tus-resumable").and_then(|v| v.to_str().ok()) == Some(TUS_VERSION) {
+        return None;
+    }
+    let mut response = tus_error(
+        StatusCode::PRECONDITION_FAILED,
+        "unsupported_version",
+        "Tus-Resumable must be 1.0.0",
+    );
+    response
+        .headers_mut()
+        .insert("tus-version", HeaderValue::from_static(TUS_VERSION));
+    Some(response)
+}
+
+fn header_i64(headers: &HeaderMap, name: &str) -> Option<i64> {
+    headers
+        .get(name)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+        .filter(|v: &i64| *v >= 0)
+}
+
+/// Format a timestamp as an HTTP date for `Upload-Expires` and `Last-Modified`.
+pub(super) fn http_date(t: DateTime<Utc>) -> String {
+    t.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn expires_from_now() -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::from_std(UPLOAD_EXPIRY).expect("expiry fits")
+}
+
+/// Parse `Upload-Metadata`: comma-separated `key base64(value)` pairs.
+fn parse_metadata(value: &str) -> Option<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+    for item in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (key, encoded) = item.split_once(' ').unwrap_or((item, ""));
+        let decoded = base64::decode(encoded.trim()).ok()?;
+        pairs.push((key.to_string(), String::from_utf8(decoded).ok()?));
+    }
+    Some(pairs)
+}
+
+/// Look up an unexpired upload belonging to the caller.
+async fn find_upload(state: &AppState, id: Uuid, user_id: Uuid) -> Result<Upload, AppError> {
+    upload_repo::get_upload(&state.db, id)
+        .await?
+        .filter(|u| u.user_id == user_id)
+        .ok_or_else(|| AppError::NotFound("upload not found".to_string()))
+}
+
+/// Start an upload. Requires `Upload-Length`; `Upload-Metadata` may carry
+/// `name` (the encrypted file name), `filetype` and `channel_id`.
+async fn create_upload(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    if let Some(response) = check_version(&headers) {
+        return Ok(response);
+    }
+    let Some(length) = header_i64(&headers, "upload-length") else {
+        return Err(AppError::Validation(
+            "Upload-Length header is required".to_string(),
+        ));
+    };
+    let max_size = state.config.max_file_size_mb * 1024 * 1024;
+    if length as u64 > max_size {
+        return Ok(tus_error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "validation_error",
+            &format!("file too large (max {} MB)", state.config.max_file_size_mb),
+        ));
+    }
+    if length == 0 {
+        return Err(AppError::Validation(
+            "file type not allowed: empty file".to_string(),
+        ));
+    }
+
+    let mut meta = UploadMeta {
+        encrypted_name: String::from("unnamed"),
+        content_type: None,
+        channel_id: None,
+    };
+    if let Some(value) = headers.get("upload-metadata") {
+        let pairs = value
+            .to_str()
+            .ok()
+            .and_then(parse_metadata)
+            .ok_or_else(|| AppError::Validation("invalid Upload-Metadata".to_string()))?;
+        for (key, value) in pairs {
+            match key.as_str() {
+                "name" => meta.encrypted_name = value,
+                "filetype" => meta.content_type = Some(value),
+                "channel_id" => {
+                    meta.channel_id = Some(
+                        Uuid::parse_str(&value)
+                            .map_err(|_| AppError::Validation("invalid channel_id".to_string()))?,
+                    )
+                }
+                _ => {}
+            }
+        }
+    }
+    if meta.encrypted_name.len() > 512 {
+        return Err(AppError::Validation(
+            "file name too long (max 512 bytes)".to_string(),
+        ));
+    }
+    if meta.content_type.as_ref().is_some_and(|ct| ct.len() > 255) {
+        return Err(AppError::Validation("filetype too long".to_string()));
+    }
+
+    // Rate limiting: max 10 uploads per minute per user
+    upload_service::check_rate_limit(claims.sub)?;
+
+    // Verify channel membership if a channel_id was provided
+    if let Some(cid) = meta.channel_id
+        && !channel_repo::is_member(&state.db, cid, claims.sub).await?
+    {
+        return Err(AppError::Forbidden);
+    }
+
+    let (pending, _) = upload_repo::get_pending_for_user(&state.db, claims.sub).await?;
+    if pending >= MAX_PENDING_UPLOADS {
+        return Err(AppError::Validation(format!(
+            "too many unfinished uploads (max {MAX_PENDING_UPLOADS})"
+        )));
+    }
+    upload_service::check_quota(&state, claims.sub, meta.channel_id, length, length).await?;
+
+    let id = Uuid::now_v7();
+    let partial = upload_service::partial_path(&state.config.file_storage_path, id);
+    if let Some(dir) = partial.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| AppError::Internal(format!("create dir: {e}")))?;
+    }
+    tokio::fs::File::create(&partial)
+        .await
+        .map_err(|e| AppError::Internal(format!("create file: {e}")))?;
+
+    let upload = match upload_repo::create_upload(
+        &state.db,
+        id,
+        claims.sub,
+        length,
+        &meta.encrypted_name,
+        meta.content_type.as_deref(),
+        meta.channel_id,
+        expires_from_now(),
+    )
+    .await
+    {
+        Ok(upload) => upload,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e.into());
+        }
+    };
+
+    Ok((
+        StatusCode::CREATED,
+        [
+            (header::LOCATION, format!("/api/uploads/{id}")),
+            (
+                header::HeaderName::from_static("upload-expires"),
+                http_date(upload.expires_at),
+            ),
+        ],
+    )
+        .into_response())
+}
+
+/// Report how many bytes have been received.
+async fn get_offset(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(upload_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    if let Some(response) = check_version(&headers) {
+        return Ok(response);
+    }
+    let upload = find_upload(&state, upload_id, claims.sub).await?;
+    Ok((
+        StatusCode::OK,
+        [
+            ("upload-offset", upload.upload_offset.to_string()),
+            ("upload-length", upload.upload_length.to_string()),
+            ("upload-expires", http_date(upload.expires_at)),
+            ("cache-control", "no-store".to_string()),
+        ],
+    )
+        .into_response())
+}
+
+/// Removes an upload from [`ACTIVE`] when the request finishes or is dropped.
+struct ActiveGuard(Uuid);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        ACTIVE.remove(&self.0);
+    }
+}
+
+/// Append a chunk at `Upload-Offset`. Bytes that arrive before the client
+/// disconnects are kept, so it can resume from the offset `HEAD` reports.
+async fn append_chunk(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(upload_id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, AppError> {
+    if let Some(response) = check_version(&headers) {
+        return Ok(response);
+    }
+    if headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        != Some(OFFSET_CONTENT_TYPE)
+    {
+        return Ok(tus_error(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "validation_error",
+            "Content-Type must be application/offset+octet-stream",
+        ));
+    }
+    let Some(offset) = header_i64(&headers, "upload-offset") else {
+        return Err(AppError::Validation(
+            "Upload-Offset header is required".to_string(),
+        ));
+    };
+
+    let upload = find_upload(&state, upload_id, claims.sub).await?;
+    if offset != upload.upload_offset {
+        return Err(AppError::Conflict(format!(
+            "Upload-Offset {offset} does not match the current offset {}",
+            upload.upload_offset
+        )));
+    }
+    if !ACTIVE.insert(upload_id) {
+        return Err(AppError::Conflict(
+            "upload is already receiving a chunk".to_string(),
+        ));
+    }
+    let _guard = ActiveGuard(upload_id);
+
+    let partial = upload_service::partial_path(&state.config.file_storage_path, upload_id);
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(&partial)
+        .await
+        .map_err(|e| match e.kind() {
+            // Partials live on the node that created the upload
+            std::io::ErrorKind::NotFound => {
+                AppError::NotFound("upload data not found on this server".to_string())
+            }
+            _ => AppError::Internal(format!("open upload: {e}")),
+        })?;
+    // Drop anything past the recorded offset left by an interrupted write.
+    file.set_len(offset as u64)
+        .await
+        .map_err(|e| AppError::Internal(format!("truncate upload: {e}")))?;
+    file.seek(std::io::SeekFrom::Start(offset as u64))
+        .await
+        .map_err(|e| AppError::Internal(format!("seek upload: {e}")))?;
+
+    let remaining = upload.upload_length - offset;
+    let mut received = 0i64;
+    let mut interrupted = false;
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        let Ok(chunk) = chunk else {
+            interrupted = true;
+            break;
+        };
+        received += chunk.len() as i64;
+        if received > remaining {
+            let _ = file.set_len(offset as u64).await;
+            return Ok(tus_error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "validation_error",
+                "chunk extends past Upload-Length",
+            ));
+        }
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| AppError::Internal(format!("write upload: {e}")))?;
+    }
+    file.flush()
+        .await
+        .map_err(|e| AppError::Internal(format!("flush upload: {e}")))?;
+    // The offset is only recorded once the bytes are durable.
+    file.sync_data()
+        .await
+        .map_err(|e| AppError::Internal(format!("sync upload: {e}")))?;
+    drop(file);
+
+    let new_offset = offset + received;
+    let expires_at = expires_from_now();
+    if !upload_repo::advance_offset(&state.db, upload_id, offset, new_offset, expires_at).await? {
+        return Err(AppError::Conflict(
+            "upload offset changed while receiving".to_string(),
+        ));
+    }
+    if interrupted {
+        return Err(AppError::Validation(format!(
+            "connection interrupted; resume from offset {new_offset}"
+        )));
+    }
+
+    if new_offset == upload.upload_length {
+        // Claim the upload so a retried final PATCH cannot store it twice.
+        if !upload_repo::delete_upload(&state.db, upload_id).await? {
+            return Err(AppError::NotFound("upload not found".to_string()));
+        }
+        let meta = UploadMeta {
+            encrypted_name: upload.encrypted_name,
+            content_type: upload.content_type,
+            channel_id: upload.channel_id,
+        };
+        upload_service::finish_upload(&state, claims.sub, upload_id, &partial, meta).await?;
+    }
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [
+            ("upload-offset", new_offset.to_string()),
+            ("upload-expires", http_date(expires_at)),
+        ],
+    )
+        .into_response())
+}
+
+/// Abandon an upload and delete the bytes received so far.
+async fn cancel_upload(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(upload_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    if let Some(response) = check_version(&headers) {
+        return Ok(response);
+    }
+    find_upload(&state, upload_id, claims.sub).await?;
+    if ACTIVE.contains(&upload_id) {
+        return Err(AppError::Conflict(
+            "upload is receiving a chunk".to_string(),
+        ));
+    }
+    upload_repo::delete_upload(&state.db, upload_id).await?;
+    let _ = tokio::fs::remove_file(upload_service::partial_path(
+        &state.config.file_storage_path,
+        upload_id,
+    ))
+    .await;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_pairs_are_base64_decoded() {
+        let pairs =
+            parse_metadata("name bXkuZmlsZQ==, channel_id,filetype dGV4dC9wbGFpbg==").unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                ("name".to_string(), "my.file".to_string()),
+                ("channel_id".to_string(), String::new()),
+                ("filetype".to_string(), "text/plain".to_string()),
+            ]
+        );
+        assert!(parse_metadata("name not*base64").is_none());
+    }
+}
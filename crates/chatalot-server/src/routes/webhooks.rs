@@ -14,6 +14,7 @@ use chatalot_db::repos::{channel_repo, message_repo, webhook_repo};
 
 use crate::app_state::AppState;
 use crate::error::AppError;
+use crate::metrics::METRICS;
 use crate::middleware::auth::AccessClaims;
 use crate::permissions;
 use crate::routes::account::validate_avatar_url;
@@ -232,6 +233,7 @@ async fn execute_webhook(
     let stored =
         message_repo::create_webhook_message(&state.db, message_id, webhook.channel_id, &plaintext)
             .await?;
+    METRICS.message_sent();
 
     // Broadcast to channel
     state.connections.broadcast_to_channel(
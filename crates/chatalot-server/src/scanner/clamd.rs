@@ -0,0 +1,265 @@
+use std::io;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use futures_util::future::BoxFuture;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpStream, UnixStream};
+
+use super::{Scanner, Verdict};
+use crate::config::{ClamdAddress, ScanConfig};
+use crate::storage::ByteStream;
+
+/// Largest chunk sent in one INSTREAM frame.
+const CHUNK: usize = 64 * 1024;
+/// Replies are a single line; anything longer is not clamd.
+const MAX_REPLY: u64 = 4096;
+
+/// ClamAV's `clamd`, over its Unix socket or TCP. Files are sent with
+/// `INSTREAM`, so clamd needs no access to the storage.
+pub struct ClamdScanner {
+    address: ClamdAddress,
+    timeout: Duration,
+}
+
+impl ClamdScanner {
+    pub fn new(config: &ScanConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            address: config.clamd_address.clone(),
+            timeout: Duration::from_secs(config.timeout_secs),
+        })
+    }
+
+    /// Send one command (and for INSTREAM, the file) and read the reply.
+    async fn call(&self, command: &str, body: Option<ByteStream>) -> io::Result<String> {
+        let exchange = async {
+            match &self.address {
+                ClamdAddress::Unix(path) => {
+                    exchange(UnixStream::connect(path).await?, command, body).await
+                }
+                ClamdAddress::Tcp(addr) => {
+                    exchange(TcpStream::connect(addr).await?, command, body).await
+                }
+            }
+        };
+        tokio::time::timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "clamd did not answer in time"))?
+    }
+
+    async fn scan_stream(&self, body: ByteStream) -> io::Result<Verdict> {
+        parse_scan_reply(&self.call("INSTREAM", Some(body)).await?)
+    }
+
+    async fn version(&self) -> io::Result<String> {
+        let reply = self.call("VERSION", None).await?;
+        // "ClamAV 1.0.7/27434/Tue Oct 14 08:35:01 2026": engine/signatures/date
+        Ok(reply.split('/').take(2).collect::<Vec<_>>().join("/"))
+    }
+}
+
+impl Scanner for ClamdScanner {
+    fn kind(&self) -> &'static str {
+        "clamd"
+    }
+
+    fn scan<'a>(&'a self, body: ByteStream) -> BoxFuture<'a, io::Result<Verdict>> {
+        Box::pin(self.scan_stream(body))
+    }
+
+    fn signature_version<'a>(&'a self) -> BoxFuture<'a, io::Result<String>> {
+        Box::pin(self.version())
+    }
+}
+
+/// Speak clamd's `z`-prefixed (NUL-terminated) protocol on one connection.
+async fn exchange<S>(mut conn: S, command: &str, body: Option<ByteStream>) -> io::Result<String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // clamd answers and hangs up as soon as a stream exceeds its
+    // StreamMaxLength, so a failed write may still have a reply to read.
+    let sent = match send(&mut conn, command, body).await {
+        Err(Sent::Body(e)) => return Err(e),
+        Err(Sent::Conn(e)) => Err(e),
+        Ok(()) => Ok(()),
+    };
+
+    let mut reply = Vec::new();
+    let read = (&mut conn).take(MAX_REPLY).read_to_end(&mut reply).await;
+    if reply.is_empty() {
+        sent?;
+        read?;
+    }
+    let reply = String::from_utf8_lossy(&reply);
+    Ok(reply.trim_end_matches(['\0', '\n']).to_string())
+}
+
+/// Why sending a request failed: reading the file, or writing to clamd.
+enum Sent {
+    Body(io::Error),
+    Conn(io::Error),
+}
+
+async fn send<S>(conn: &mut S, command: &str, body: Option<ByteStream>) -> Result<(), Sent>
+where
+    S: AsyncWrite + Unpin,
+{
+    conn.write_all(format!("z{command}\0").as_bytes())
+        .await
+        .map_err(Sent::Conn)?;
+    if let Some(mut body) = body {
+        while let Some(chunk) = body.next().await {
+            for part in chunk.map_err(Sent::Body)?.chunks(CHUNK) {
+                conn.write_all(&(part.len() as u32).to_be_bytes())
+                    .await
+                    .map_err(Sent::Conn)?;
+                conn.write_all(part).await.map_err(Sent::Conn)?;
+            }
+        }
+        conn.write_all(&0u32.to_be_bytes())
+            .await
+            .map_err(Sent::Conn)?;
+    }
+    conn.flush().await.map_err(Sent::Conn)
+}
+
+/// `stream: OK`, `stream: <signature> FOUND`, `INSTREAM size limit
+/// exceeded. ERROR`, or another error.
+fn parse_scan_reply(reply: &str) -> io::Result<Verdict> {
+    let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+    if result == "OK" {
+        return Ok(Verdict::Clean);
+    }
+    if let Some(signature) = result.strip_suffix(" FOUND") {
+        return Ok(Verdict::Infected(signature.to_string()));
+    }
+    if result.starts_with("INSTREAM size limit exceeded") {
+        return Ok(Verdict::TooLarge);
+    }
+    Err(io::Error::other(format!("clamd: {reply}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use tokio::net::UnixListener;
+
+    use super::*;
+
+    const EICAR: &[u8] = br"X5O!P%@AP[4\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*";
+
+    /// A clamd stand-in that flags the EICAR test file. Like clamd, it
+    /// answers and hangs up once a stream exceeds `max_len` bytes.
+    async fn fake_clamd(listener: UnixListener, max_len: usize) {
+        while let Ok((mut conn, _)) = listener.accept().await {
+            let mut command = Vec::new();
+            loop {
+                let b = conn.read_u8().await.unwrap();
+                if b == 0 {
+                    break;
+                }
+                command.push(b);
+            }
+            let reply = match &command[..] {
+                b"zVERSION" => "ClamAV 1.0.7/27434/Tue Oct 14 08:35:01 2026".to_string(),
+                b"zINSTREAM" => {
+                    let mut data = Vec::new();
+                    loop {
+                        let len = conn.read_u32().await.unwrap() as usize;
+                        if len == 0 {
+                            break;
+                        }
+                        let mut chunk = vec![0; len];
+                        conn.read_exact(&mut chunk).await.unwrap();
+                        data.extend(chunk);
+                        if data.len() > max_len {
+                            break;
+                        }
+                    }
+                    if data.len() > max_len {
+                        "INSTREAM size limit exceeded. ERROR".to_string()
+                    } else if data.windows(EICAR.len()).any(|w| w == EICAR) {
+                        "stream: Win.Test.EICAR_HDB-1 FOUND".to_string()
+                    } else {
+                        "stream: OK".to_string()
+                    }
+                }
+                _ => "UNKNOWN COMMAND".to_string(),
+            };
+            conn.write_all(format!("{reply}\0").as_bytes())
+                .await
+                .unwrap();
+        }
+    }
+
+    fn body(parts: &[&'static [u8]]) -> ByteStream {
+        let parts: Vec<io::Result<Bytes>> =
+            parts.iter().map(|p| Ok(Bytes::from_static(p))).collect();
+        futures_util::stream::iter(parts).boxed()
+    }
+
+    fn scanner(path: &std::path::Path) -> ClamdScanner {
+        ClamdScanner::new(&ScanConfig {
+            clamd_address: ClamdAddress::Unix(path.to_path_buf()),
+            timeout_secs: 5,
+            fail_closed: false,
+            rescan_days: 7,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn scans_streams_over_the_clamd_socket() {
+        let path = std::env::temp_dir().join(format!("clamd-{}.sock", uuid::Uuid::new_v4()));
+        tokio::spawn(fake_clamd(UnixListener::bind(&path).unwrap(), usize::MAX));
+        let scanner = scanner(&path);
+
+        assert_eq!(
+            scanner.signature_version().await.unwrap(),
+            "ClamAV 1.0.7/27434"
+        );
+        assert_eq!(
+            scanner.scan(body(&[b"hello"])).await.unwrap(),
+            Verdict::Clean
+        );
+        // Split across chunks, inside a larger file
+        let (head, tail) = EICAR.split_at(20);
+        assert_eq!(
+            scanner.scan(body(&[b"zip", head, tail])).await.unwrap(),
+            Verdict::Infected("Win.Test.EICAR_HDB-1".to_string())
+        );
+
+        let _ = std::fs::remove_file(&path);
+        assert!(scanner.scan(body(&[b"x"])).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn streams_over_the_size_limit_are_reported_as_too_large() {
+        let path = std::env::temp_dir().join(format!("clamd-{}.sock", uuid::Uuid::new_v4()));
+        tokio::spawn(fake_clamd(UnixListener::bind(&path).unwrap(), 1024));
+        let scanner = scanner(&path);
+
+        static ZEROS: [u8; 1024 * 1024] = [0; 1024 * 1024];
+        assert_eq!(
+            scanner.scan(body(&[&ZEROS[..1000]])).await.unwrap(),
+            Verdict::Clean
+        );
+        // clamd hangs up long before the whole file is sent
+        assert_eq!(
+            scanner.scan(body(&[&ZEROS[..]; 16])).await.unwrap(),
+            Verdict::TooLarge
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn parses_scan_replies() {
+        assert_eq!(parse_scan_reply("stream: OK").unwrap(), Verdict::Clean);
+        assert_eq!(
+            parse_scan_reply("INSTREAM size limit exceeded. ERROR").unwrap(),
+            Verdict::TooLarge
+        );
+        assert!(parse_scan_reply("stream: Can't allocate memory ERROR").is_err());
+    }
+}
@@ -0,0 +1,47 @@
+//! Malware scanning of uploads.
+//!
+//! When a scanner is configured, every upload is scanned before it is stored
+//! and quarantined if the scanner reports a detection. The `malware_rescan`
+//! job scans recent files again whenever the scanner's signatures change.
+
+mod clamd;
+
+use std::io;
+use std::sync::Arc;
+
+use futures_util::future::BoxFuture;
+
+use crate::config::Config;
+use crate::storage::ByteStream;
+
+pub use clamd::ClamdScanner;
+
+/// What a scanner made of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Clean,
+    /// Name of the signature that matched.
+    Infected(String),
+    /// The scanner refused the file as too large (clamd's `StreamMaxLength`).
+    TooLarge,
+}
+
+/// Something that can tell whether a file is malware.
+pub trait Scanner: Send + Sync {
+    /// Scanner name for logs and audit entries (`clamd`).
+    fn kind(&self) -> &'static str;
+
+    /// Scan the bytes read from `body`.
+    fn scan<'a>(&'a self, body: ByteStream) -> BoxFuture<'a, io::Result<Verdict>>;
+
+    /// Identifies the signatures in use; files are rescanned when it changes.
+    fn signature_version<'a>(&'a self) -> BoxFuture<'a, io::Result<String>>;
+}
+
+/// The scanner configured with `CLAMD_ADDRESS`, if any.
+pub fn from_config(config: &Config) -> anyhow::Result<Option<Arc<dyn Scanner>>> {
+    let Some(ref scan) = config.malware_scan else {
+        return Ok(None);
+    };
+    Ok(Some(Arc::new(ClamdScanner::new(scan)?)))
+}
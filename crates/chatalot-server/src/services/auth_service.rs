@@ -319,7 +319,7 @@ pub async fn register(
     ip_address: Option<&str>,
 ) -> Result<AuthResponse, AppError> {
     // Check registration mode (but don't consume invite yet)
-    let invite_code = match state.config.registration_mode.as_str() {
+    let invite_code = match state.live().registration_mode.as_str() {
         "closed" => {
             return Err(AppError::Validation(
                 "registration is currently disabled".to_string(),
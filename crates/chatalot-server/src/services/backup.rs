@@ -0,0 +1,1401 @@
+//! Single-file instance backups: a database snapshot plus everything under the
+//! file storage root (uploads, thumbnails, avatars, community/group assets and
+//! custom emojis), with a manifest of SHA-256 checksums.
+//!
+//! An archive is a gzipped tar with this layout:
+//!
+//! ```text
+//! db/<table>.copy     one per table, PostgreSQL COPY text format
+//! db/chatalot.sqlite  instead of the above, a snapshot of a SQLite database
+//! files/<path>        storage root contents, relative to the root
+//! manifest.json       written last
+//! ```
+//!
+//! An archive restores only into the backend it was taken from.
+//!
+//! With a passphrase, the gzipped tar is wrapped in XChaCha20-Poly1305 using
+//! a key derived with Argon2id (see [`EncryptWriter`]).
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, bail};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chatalot_db::db::{self, Backend, Db, Tx};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{Connection, PgPool, SqlitePool};
+use tokio::io::AsyncWriteExt;
+
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+/// Name of the SQLite snapshot under `db/`.
+const SQLITE_SNAPSHOT: &str = "chatalot.sqlite";
+
+/// Columns holding absolute paths under the storage root, with the
+/// migration that added them. Restore rewrites them when the archive was
+/// taken with a different `FILE_STORAGE_PATH`.
+const PATH_COLUMNS: &[(&str, &str, i64)] = &[
+    ("files", "storage_path", 8),
+    ("files", "thumbnail_path", 50),
+    ("custom_emojis", "file_path", 29),
+    ("file_blobs", "storage_path", 60),
+    ("file_blobs", "thumbnail_path", 60),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub server_version: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// `postgres` or `sqlite`; archives from before SQLite support omit it.
+    #[serde(default = "default_backend")]
+    pub backend: String,
+    /// Latest migration applied to the database when it was dumped.
+    pub schema_version: i64,
+    /// `FILE_STORAGE_PATH` of the source instance.
+    pub storage_root: String,
+    pub tables: Vec<TableDump>,
+    /// Every other archive member, keyed by path.
+    pub entries: BTreeMap<String, EntrySum>,
+}
+
+fn default_backend() -> String {
+    Backend::Postgres.name().to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableDump {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub rows: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntrySum {
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl Manifest {
+    pub fn file_count(&self) -> usize {
+        self.entries
+            .keys()
+            .filter(|k| k.starts_with("files/"))
+            .count()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.entries.values().map(|e| e.size).sum()
+    }
+}
+
+/// Write a backup of `db` and `storage_root` to `output`.
+///
+/// The database part is a consistent snapshot: Postgres tables are dumped
+/// inside one repeatable-read transaction, SQLite is copied with `VACUUM INTO`.
+/// Files are copied afterwards. The archive is written to a temporary name
+/// and renamed into place on success.
+pub async fn create(
+    db: &Db,
+    storage_root: &str,
+    output: &Path,
+    passphrase: Option<&str>,
+) -> anyhow::Result<Manifest> {
+    let staging = sibling(output, "staging");
+    tokio::fs::create_dir_all(&staging)
+        .await
+        .with_context(|| format!("failed to create {}", staging.display()))?;
+
+    let result = async {
+        let (schema_version, tables) = match db {
+            Db::Postgres(pool) => dump_postgres(pool, &staging).await?,
+            Db::Sqlite(pool) => dump_sqlite(pool, &staging).await?,
+        };
+        let manifest = Manifest {
+            format: FORMAT_VERSION,
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: chrono::Utc::now(),
+            backend: db.backend().name().to_string(),
+            schema_version,
+            storage_root: storage_root.to_string(),
+            tables,
+            entries: BTreeMap::new(),
+        };
+
+        let partial = sibling(output, "partial");
+        let (staging, root, passphrase) = (
+            staging.clone(),
+            PathBuf::from(storage_root),
+            passphrase.map(str::to_string),
+        );
+        let partial_clone = partial.clone();
+        let manifest = tokio::task::spawn_blocking(move || {
+            write_archive(
+                &partial_clone,
+                manifest,
+                &staging,
+                &root,
+                passphrase.as_deref(),
+            )
+        })
+        .await??;
+        tokio::fs::rename(&partial, output)
+            .await
+            .with_context(|| format!("failed to move archive to {}", output.display()))?;
+        Ok(manifest)
+    }
+    .await;
+
+    let _ = tokio::fs::remove_dir_all(&staging).await;
+    let _ = tokio::fs::remove_file(sibling(output, "partial")).await;
+    result
+}
+
+/// Read an archive end to end and check every member against the manifest.
+pub async fn verify(archive: &Path, passphrase: Option<&str>) -> anyhow::Result<Manifest> {
+    let (archive, passphrase) = (archive.to_path_buf(), passphrase.map(str::to_string));
+    tokio::task::spawn_blocking(move || unpack(&archive, passphrase.as_deref(), None)).await?
+}
+
+pub struct RestoreSummary {
+    pub manifest: Manifest,
+    pub rows: u64,
+    pub files: usize,
+    /// `(old, new)` if stored paths were rewritten to a new storage root.
+    pub rewritten_root: Option<(String, String)>,
+}
+
+/// Restore an archive into `db` and `storage_root`.
+///
+/// The archive is unpacked into a staging directory inside the storage root
+/// and fully validated before anything else is touched. The database must be
+/// empty unless `force` is set, in which case its tables are dropped.
+/// Tables are loaded in one transaction at the archive's schema version, and
+/// any newer migrations are applied afterwards.
+pub async fn restore(
+    db: &Db,
+    storage_root: &str,
+    archive: &Path,
+    passphrase: Option<&str>,
+    force: bool,
+) -> anyhow::Result<RestoreSummary> {
+    let root = PathBuf::from(storage_root);
+    tokio::fs::create_dir_all(&root)
+        .await
+        .with_context(|| format!("failed to create {storage_root}"))?;
+    if !force && has_visible_entries(&root)? {
+        bail!("{storage_root} is not empty; pass --force to restore over it");
+    }
+
+    let staging = root.join(format!(".restore-{}", uuid::Uuid::now_v7()));
+    let result = async {
+        let manifest = {
+            let (archive, passphrase, staging) = (
+                archive.to_path_buf(),
+                passphrase.map(str::to_string),
+                staging.clone(),
+            );
+            tokio::task::spawn_blocking(move || {
+                unpack(&archive, passphrase.as_deref(), Some(&staging))
+            })
+            .await??
+        };
+        if manifest.backend != db.backend().name() {
+            bail!(
+                "the archive was taken from a {} database but DATABASE_URL points to {}",
+                manifest.backend,
+                db.backend().name()
+            );
+        }
+        let latest = chatalot_db::pool::latest_migration(db.backend());
+        if manifest.schema_version > latest {
+            bail!(
+                "archive schema version {} is newer than this build ({latest}); upgrade first",
+                manifest.schema_version
+            );
+        }
+
+        let (rows, rewritten_root) =
+            load_database(db, &manifest, &staging.join("db"), storage_root, force).await?;
+        chatalot_db::pool::run_migrations(db)
+            .await
+            .context("failed to apply migrations after restore")?;
+
+        let files_dir = staging.join("files");
+        if tokio::fs::try_exists(&files_dir).await? {
+            let (files_dir, root) = (files_dir, root.clone());
+            tokio::task::spawn_blocking(move || move_tree(&files_dir, &root)).await??;
+        }
+        Ok(RestoreSummary {
+            files: manifest.file_count(),
+            manifest,
+            rows,
+            rewritten_root,
+        })
+    }
+    .await;
+
+    let _ = tokio::fs::remove_dir_all(&staging).await;
+    result
+}
+
+// ── Database ──
+
+async fn dump_postgres(db: &PgPool, staging: &Path) -> anyhow::Result<(i64, Vec<TableDump>)> {
+    let mut tx = db.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let schema_version: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success = true",
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .context("failed to read schema version")?;
+
+    let mut tables = Vec::new();
+    for name in list_tables(&mut tx).await? {
+        let columns: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT column_name::text FROM information_schema.columns
+            WHERE table_schema = 'public' AND table_name = $1
+            ORDER BY ordinal_position
+            "#,
+        )
+        .bind(&name)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let path = staging.join(format!("{name}.copy"));
+        let mut out = tokio::io::BufWriter::new(tokio::fs::File::create(&path).await?);
+        let statement = format!(
+            "COPY (SELECT {} FROM {}) TO STDOUT",
+            column_list(&columns),
+            quote_ident(&name)
+        );
+        let mut rows = 0u64;
+        let mut stream = tx.copy_out_raw(&statement).await?;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.with_context(|| format!("failed to dump table {name}"))?;
+            rows += chunk.iter().filter(|b| **b == b'\n').count() as u64;
+            out.write_all(&chunk).await?;
+        }
+        drop(stream);
+        out.flush().await?;
+        tables.push(TableDump {
+            name,
+            columns,
+            rows,
+        });
+    }
+    tx.commit().await?;
+    Ok((schema_version, tables))
+}
+
+/// User tables in the `public` schema, excluding partitions (their rows are
+/// dumped through the parent) and sqlx's own bookkeeping.
+async fn list_tables(conn: &mut sqlx::PgConnection) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT c.relname::text FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = 'public' AND c.relkind IN ('r', 'p') AND NOT c.relispartition
+          AND c.relname <> '_sqlx_migrations'
+        ORDER BY c.relname
+        "#,
+    )
+    .fetch_all(conn)
+    .await
+}
+
+/// Tables of a SQLite database, excluding sqlx's own bookkeeping.
+async fn list_sqlite_tables(db: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT name FROM sqlite_master WHERE type = 'table' \
+         AND name NOT LIKE 'sqlite_%' AND name <> '_sqlx_migrations' ORDER BY name",
+    )
+    .fetch_all(db)
+    .await
+}
+
+async fn dump_sqlite(db: &SqlitePool, staging: &Path) -> anyhow::Result<(i64, Vec<TableDump>)> {
+    let path = staging.join(SQLITE_SNAPSHOT);
+    sqlx::query("VACUUM INTO ?1")
+        .bind(path.to_string_lossy().into_owned())
+        .execute(db)
+        .await
+        .context("failed to snapshot the database")?;
+
+    // Describe the snapshot rather than the live database, which may have
+    // moved on already.
+    let snapshot = open_snapshot(&path).await?;
+    let schema_version: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success = true",
+    )
+    .fetch_one(&snapshot)
+    .await
+    .context("failed to read schema version")?;
+    let mut tables = Vec::new();
+    for name in list_sqlite_tables(&snapshot).await? {
+        let columns: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM pragma_table_info(?1) ORDER BY cid")
+                .bind(&name)
+                .fetch_all(&snapshot)
+                .await?;
+        let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", quote_ident(&name)))
+            .fetch_one(&snapshot)
+            .await?;
+        tables.push(TableDump {
+            name,
+            columns,
+            rows: rows as u64,
+        });
+    }
+    snapshot.close().await;
+    Ok((schema_version, tables))
+}
+
+/// Open a SQLite snapshot on a single connection in rollback-journal mode,
+/// so it stays one self-contained file once closed.
+async fn open_snapshot(path: &Path) -> Result<SqlitePool, sqlx::Error> {
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .journal_mode(SqliteJournalMode::Delete);
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+}
+
+async fn load_database(
+    db: &Db,
+    manifest: &Manifest,
+    dumps: &Path,
+    storage_root: &str,
+    force: bool,
+) -> anyhow::Result<(u64, Option<(String, String)>)> {
+    let tables: i64 = match db.backend() {
+        Backend::Postgres => {
+            db::query_scalar(
+                "SELECT COUNT(*) FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 WHERE n.nspname = 'public' AND c.relkind IN ('r', 'p')",
+            )
+            .fetch_one(db)
+            .await?
+        }
+        Backend::Sqlite => db::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        )
+        .fetch_one(db)
+        .await?,
+    };
+    if tables > 0 {
+        // A server that started against an empty database has created the
+        // schema but holds no accounts yet; that counts as empty too.
+        let users: Option<i64> = db::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(db)
+            .await
+            .ok();
+        if users != Some(0) && !force {
+            bail!("the database is not empty; pass --force to replace it");
+        }
+        drop_schema(db)
+            .await
+            .context("failed to drop the existing schema")?;
+    }
+    chatalot_db::pool::run_migrations_to(db, manifest.schema_version)
+        .await
+        .context("failed to create the schema")?;
+
+    match db {
+        Db::Postgres(_) => load_postgres(db, manifest, dumps, storage_root).await,
+        Db::Sqlite(pool) => load_sqlite(pool, manifest, dumps, storage_root).await,
+    }
+}
+
+async fn drop_schema(db: &Db) -> Result<(), sqlx::Error> {
+    match db {
+        Db::Postgres(pool) => {
+            sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")
+                .execute(pool)
+                .await?;
+        }
+        Db::Sqlite(pool) => {
+            let mut conn = pool.acquire().await?;
+            let tables: Vec<String> = sqlx::query_scalar(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            )
+            .fetch_all(&mut *conn)
+            .await?;
+            sqlx::query("PRAGMA foreign_keys = OFF")
+                .execute(&mut *conn)
+                .await?;
+            let mut result = Ok(());
+            for table in tables {
+                result = sqlx::query(&format!("DROP TABLE {}", quote_ident(&table)))
+                    .execute(&mut *conn)
+                    .await
+                    .map(|_| ());
+                if result.is_err() {
+                    break;
+                }
+            }
+            sqlx::query("PRAGMA foreign_keys = ON")
+                .execute(&mut *conn)
+                .await?;
+            result?;
+        }
+    }
+    Ok(())
+}
+
+async fn load_postgres(
+    db: &Db,
+    manifest: &Manifest,
+    dumps: &Path,
+    storage_root: &str,
+) -> anyhow::Result<(u64, Option<(String, String)>)> {
+    let mut tx = db.begin().await?;
+    let Tx::Postgres(pg) = &mut tx else {
+        unreachable!("postgres pool");
+    };
+    let order = load_order(pg, manifest).await?;
+    // Drop rows seeded by migrations (e.g. default instance settings).
+    let names: Vec<String> = order.iter().map(|t| quote_ident(&t.name)).collect();
+    if !names.is_empty() {
+        sqlx::query(&format!("TRUNCATE {}", names.join(", ")))
+            .execute(&mut **pg)
+            .await?;
+    }
+    // The archive was consistent when it was taken, and may legitimately hold
+    // replies to deleted messages, which the reference checks only allow on
+    // rows that already exist. Skip them while loading.
+    let checks = message_reference_checks(pg).await?;
+    set_triggers(pg, &checks, false).await?;
+    // Messages older than the existing monthly partitions would otherwise
+    // all land in the default partition, where they can never be archived.
+    // Stage them first so the months they cover can be created.
+    let partitioned: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM pg_class WHERE oid = to_regclass('messages') AND relkind = 'p')",
+    )
+    .fetch_one(&mut **pg)
+    .await?;
+    let mut rows = 0u64;
+    for table in order {
+        let staged = partitioned && table.name == "messages";
+        if staged {
+            sqlx::query("CREATE TEMP TABLE restore_messages (LIKE messages) ON COMMIT DROP")
+                .execute(&mut **pg)
+                .await?;
+        }
+        let statement = format!(
+            "COPY {} ({}) FROM STDIN",
+            if staged {
+                "restore_messages".to_string()
+            } else {
+                quote_ident(&table.name)
+            },
+            column_list(&table.columns)
+        );
+        let mut copy = pg.copy_in_raw(&statement).await?;
+        let mut file = tokio::fs::File::open(dumps.join(format!("{}.copy", table.name))).await?;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = tokio::io::AsyncReadExt::read(&mut file, &mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            copy.send(&buf[..n]).await?;
+        }
+        rows += copy
+            .finish()
+            .await
+            .with_context(|| format!("failed to load table {}", table.name))?;
+        if staged {
+            sqlx::query(
+                "SELECT create_message_partitions((MIN(created_at) AT TIME ZONE 'UTC')::date, \
+                 (MAX(created_at) AT TIME ZONE 'UTC')::date) FROM restore_messages",
+            )
+            .execute(&mut **pg)
+            .await?;
+            sqlx::query("INSERT INTO messages SELECT * FROM restore_messages")
+                .execute(&mut **pg)
+                .await?;
+        }
+    }
+    set_triggers(pg, &checks, true).await?;
+    let rewritten_root = rewrite_paths(&mut tx, manifest, storage_root).await?;
+    tx.commit().await?;
+    Ok((rows, rewritten_root))
+}
+
+/// The triggers standing in for foreign keys onto messages (migration 067),
+/// as (table, trigger). Partitions inherit their parent's.
+async fn message_reference_checks(
+    conn: &mut sqlx::PgConnection,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        SELECT c.relname::text, t.tgname::text FROM pg_trigger t
+        JOIN pg_proc p ON p.oid = t.tgfoid
+        JOIN pg_class c ON c.oid = t.tgrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE p.proname = 'check_message_reference' AND n.nspname = 'public'
+          AND NOT c.relispartition
+        "#,
+    )
+    .fetch_all(conn)
+    .await
+}
+
+async fn set_triggers(
+    conn: &mut sqlx::PgConnection,
+    triggers: &[(String, String)],
+    enabled: bool,
+) -> Result<(), sqlx::Error> {
+    for (table, trigger) in triggers {
+        sqlx::query(&format!(
+            "ALTER TABLE {} {} TRIGGER {}",
+            quote_ident(table),
+            if enabled { "ENABLE" } else { "DISABLE" },
+            quote_ident(trigger)
+        ))
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Copy every archived table out of the snapshot, attached to one connection,
+/// in a single transaction. Foreign keys are checked at commit, so the order
+/// rows arrive in does not matter.
+async fn load_sqlite(
+    pool: &SqlitePool,
+    manifest: &Manifest,
+    dumps: &Path,
+    storage_root: &str,
+) -> anyhow::Result<(u64, Option<(String, String)>)> {
+    let snapshot_path = dumps.join(SQLITE_SNAPSHOT);
+    // Rewrite paths in the staged copy first; it is thrown away afterwards.
+    let snapshot = Db::Sqlite(open_snapshot(&snapshot_path).await?);
+    let mut snapshot_tx = snapshot.begin().await?;
+    let rewritten_root = rewrite_paths(&mut snapshot_tx, manifest, storage_root).await?;
+    snapshot_tx.commit().await?;
+    snapshot.close().await;
+
+    let present = list_sqlite_tables(pool).await?;
+    for table in &manifest.tables {
+        if !present.contains(&table.name) {
+            bail!("archive table {} does not exist in the schema", table.name);
+        }
+    }
+
+    let mut conn = pool.acquire().await?;
+    sqlx::query("ATTACH DATABASE ?1 AS archive")
+        .bind(snapshot_path.to_string_lossy().into_owned())
+        .execute(&mut *conn)
+        .await
+        .context("failed to open the archived database")?;
+    let result = async {
+        let mut tx = conn.begin_with("BEGIN IMMEDIATE").await?;
+        sqlx::query("PRAGMA defer_foreign_keys = ON")
+            .execute(&mut *tx)
+            .await?;
+        // Drop rows seeded by migrations (e.g. default instance settings).
+        for table in &manifest.tables {
+            sqlx::query(&format!("DELETE FROM main.{}", quote_ident(&table.name)))
+                .execute(&mut *tx)
+                .await?;
+        }
+        let mut rows = 0u64;
+        for table in &manifest.tables {
+            let columns = column_list(&table.columns);
+            rows += sqlx::query(&format!(
+                "INSERT INTO main.{t} ({columns}) SELECT {columns} FROM archive.{t}",
+                t = quote_ident(&table.name)
+            ))
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("failed to load table {}", table.name))?
+            .rows_affected();
+        }
+        tx.commit().await.context("failed to commit the restore")?;
+        anyhow::Ok(rows)
+    }
+    .await;
+    let detached = sqlx::query("DETACH DATABASE archive")
+        .execute(&mut *conn)
+        .await;
+    let rows = result?;
+    detached?;
+    Ok((rows, rewritten_root))
+}
+
+/// Order the archived tables so that every table comes after the tables its
+/// foreign keys reference, and tables guarded by the message reference
+/// triggers come after `messages`.
+async fn load_order<'m>(
+    conn: &mut sqlx::PgConnection,
+    manifest: &'m Manifest,
+) -> anyhow::Result<Vec<&'m TableDump>> {
+    let present = list_tables(conn).await?;
+    for table in &manifest.tables {
+        if !present.contains(&table.name) {
+            bail!("archive table {} does not exist in the schema", table.name);
+        }
+    }
+    let edges: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT c.conrelid::regclass::text, c.confrelid::regclass::text
+        FROM pg_constraint c JOIN pg_namespace n ON n.oid = c.connamespace
+        WHERE c.contype = 'f' AND n.nspname = 'public' AND c.conrelid <> c.confrelid
+        UNION
+        SELECT c.relname::text, 'messages' FROM pg_trigger t
+        JOIN pg_proc p ON p.oid = t.tgfoid
+        JOIN pg_class c ON c.oid = t.tgrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE p.proname = 'check_message_reference' AND n.nspname = 'public'
+          AND NOT c.relispartition AND c.relname <> 'messages'
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let names: Vec<&str> = manifest.tables.iter().map(|t| t.name.as_str()).collect();
+    let sorted = topo_sort(&names, &edges)?;
+    Ok(sorted
+        .into_iter()
+        .filter_map(|name| manifest.tables.iter().find(|t| t.name == name))
+        .collect())
+}
+
+fn topo_sort<'a>(names: &[&'a str], edges: &[(String, String)]) -> anyhow::Result<Vec<&'a str>> {
+    let mut deps: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (from, to) in edges {
+        deps.entry(from.as_str()).or_default().push(to.as_str());
+    }
+    let mut done: Vec<&'a str> = Vec::with_capacity(names.len());
+    let mut remaining: Vec<&'a str> = names.to_vec();
+    while !remaining.is_empty() {
+        let before = remaining.len();
+        remaining.retain(|name| {
+            let ready = deps.get(name).is_none_or(|d| {
+                d.iter()
+                    .all(|dep| done.contains(dep) || !names.contains(dep))
+            });
+            if ready {
+                done.push(name);
+            }
+            !ready
+        });
+        if remaining.len() == before {
+            bail!(
+                "foreign keys form a cycle between: {}",
+                remaining.join(", ")
+            );
+        }
+    }
+    Ok(done)
+}
+
+/// Rewrite stored paths from the archive's storage root to `new_root`.
+async fn rewrite_paths(
+    tx: &mut Tx,
+    manifest: &Manifest,
+    new_root: &str,
+) -> anyhow::Result<Option<(String, String)>> {
+    let (old, new) = (
+        manifest.storage_root.trim_end_matches('/'),
+        new_root.trim_end_matches('/'),
+    );
+    if old == new {
+        return Ok(None);
+    }
+    let (old_prefix, new_prefix) = (format!("{old}/"), format!("{new}/"));
+    // Tables newer than the archive are filled in by later migrations
+    for (table, column, _) in PATH_COLUMNS
+        .iter()
+        .filter(|(_, _, since)| *since <= manifest.schema_version)
+    {
+        let statement = format!(
+            "UPDATE {t} SET {c} = $2 || substr({c}, length($1) + 1) WHERE substr({c}, 1, length($1)) = $1",
+            t = quote_ident(table),
+            c = quote_ident(column)
+        );
+        db::query(statement)
+            .bind(&old_prefix)
+            .bind(&new_prefix)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("failed to rewrite {table}.{column}"))?;
+    }
+    Ok(Some((old.to_string(), new.to_string())))
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn column_list(columns: &[String]) -> String {
+    columns
+        .iter()
+        .map(|c| quote_ident(c))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// ── Archive ──
+
+fn write_archive(
+    path: &Path,
+    mut manifest: Manifest,
+    dumps: &Path,
+    storage_root: &Path,
+    passphrase: Option<&str>,
+) -> anyhow::Result<Manifest> {
+    let file = BufWriter::new(
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?,
+    );
+    let sink = match passphrase {
+        Some(p) => Sink::Encrypted(EncryptWriter::new(file, p)?),
+        None => Sink::Plain(file),
+    };
+    let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+        sink,
+        flate2::Compression::default(),
+    ));
+
+    let mut dump_files: Vec<_> = std::fs::read_dir(dumps)?
+        .map(|e| e.map(|e| e.file_name()))
+        .collect::<Result<_, _>>()?;
+    dump_files.sort();
+    for file_name in dump_files {
+        let name = format!("db/{}", file_name.to_string_lossy());
+        let sum = append_file(&mut tar, &name, &dumps.join(&file_name))?;
+        manifest.entries.insert(name, sum);
+    }
+
+    if storage_root.exists() {
+        let mut stack = vec![storage_root.to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                // Skip dotfiles such as restore staging directories and the
+                // readiness probe.
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let kind = entry.file_type()?;
+                if kind.is_dir() {
+                    stack.push(path);
+                } else if kind.is_file() {
+                    let rel = path.strip_prefix(storage_root)?;
+                    let name = format!("files/{}", rel.to_string_lossy());
+                    match append_file(&mut tar, &name, &path) {
+                        Ok(sum) => {
+                            manifest.entries.insert(name, sum);
+                        }
+                        // Deleted between listing and reading.
+                        Err(e) if is_not_found(&e) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+        }
+    }
+
+    let json = serde_json::to_vec_pretty(&manifest)?;
+    let mut header = tar_header(json.len() as u64);
+    tar.append_data(&mut header, MANIFEST, json.as_slice())?;
+    tar.into_inner()?.finish()?.finish()?;
+    Ok(manifest)
+}
+
+fn append_file<W: Write>(
+    tar: &mut tar::Builder<W>,
+    name: &str,
+    path: &Path,
+) -> anyhow::Result<EntrySum> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut reader = HashingReader::new(file.take(size));
+    let mut header = tar_header(size);
+    tar.append_data(&mut header, name, &mut reader)
+        .with_context(|| format!("failed to archive {}", path.display()))?;
+    Ok(reader.finish())
+}
+
+fn tar_header(size: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o600);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    header.set_entry_type(tar::EntryType::Regular);
+    header
+}
+
+fn is_not_found(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::NotFound)
+}
+
+/// Read every member, hashing it and (with `dest`) extracting it under
+/// `dest`, then check the result against the manifest.
+fn unpack(
+    archive: &Path,
+    passphrase: Option<&str>,
+    dest: Option<&Path>,
+) -> anyhow::Result<Manifest> {
+    let mut file = BufReader::new(
+        File::open(archive).with_context(|| format!("failed to open {}", archive.display()))?,
+    );
+    let source: Box<dyn Read> = if is_encrypted(&mut file)? {
+        let Some(passphrase) = passphrase else {
+            bail!("the archive is encrypted; a passphrase is required");
+        };
+        Box::new(DecryptReader::new(file, passphrase)?)
+    } else {
+        Box::new(file)
+    };
+
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(source));
+    let mut sums: BTreeMap<String, EntrySum> = BTreeMap::new();
+    let mut manifest: Option<Manifest> = None;
+    for entry in tar.entries().context("failed to read the archive")? {
+        let mut entry = entry.context("failed to read the archive")?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        if manifest.is_some() {
+            bail!("unexpected member {name} after the manifest");
+        }
+        if name == MANIFEST {
+            let mut json = Vec::new();
+            entry.read_to_end(&mut json)?;
+            manifest = Some(serde_json::from_slice(&json).context("invalid manifest")?);
+            continue;
+        }
+        let rel = safe_member_path(&name)?;
+        let mut reader = HashingReader::new(&mut entry);
+        match dest {
+            Some(dest) => {
+                let target = dest.join(rel);
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut out = BufWriter::new(File::create(&target)?);
+                io::copy(&mut reader, &mut out)?;
+                out.flush()?;
+            }
+            None => {
+                io::copy(&mut reader, &mut io::sink())?;
+            }
+        }
+        sums.insert(name, reader.finish());
+    }
+
+    let Some(manifest) = manifest else {
+        bail!("the archive has no manifest (truncated?)");
+    };
+    if manifest.format != FORMAT_VERSION {
+        bail!("unsupported archive format {}", manifest.format);
+    }
+    for (name, expected) in &manifest.entries {
+        match sums.remove(name) {
+            Some(actual) if actual == *expected => {}
+            Some(_) => bail!("checksum mismatch for {name}"),
+            None => bail!("{name} is listed in the manifest but missing"),
+        }
+    }
+    if let Some(extra) = sums.keys().next() {
+        bail!("{extra} is not listed in the manifest");
+    }
+    if manifest.backend == Backend::Sqlite.name() {
+        if !manifest
+            .entries
+            .contains_key(&format!("db/{SQLITE_SNAPSHOT}"))
+        {
+            bail!("the archive has no database snapshot");
+        }
+    } else {
+        for table in &manifest.tables {
+            if !manifest
+                .entries
+                .contains_key(&format!("db/{}.copy", table.name))
+            {
+                bail!("table {} has no dump in the archive", table.name);
+            }
+        }
+    }
+    Ok(manifest)
+}
+
+/// Validate a member name, returning it relative to the extraction root.
+fn safe_member_path(name: &str) -> anyhow::Result<PathBuf> {
+    let path = Path::new(name);
+    let safe = path.components().all(|c| matches!(c, Component::Normal(_)));
+    let known = name.starts_with("files/")
+        || (name.starts_with("db/")
+            && (name.ends_with(".copy") || name == format!("db/{SQLITE_SNAPSHOT}"))
+            && path.components().count() == 2);
+    if !safe || !known {
+        bail!("unexpected archive member {name}");
+    }
+    Ok(path.to_path_buf())
+}
+
+/// Move everything under `from` into `to`, merging directories.
+fn move_tree(from: &Path, to: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            move_tree(&entry.path(), &target)?;
+        } else {
+            std::fs::rename(entry.path(), &target)
+                .with_context(|| format!("failed to move {}", target.display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn has_visible_entries(dir: &Path) -> io::Result<bool> {
+    for entry in std::fs::read_dir(dir)? {
+        if !entry?.file_name().to_string_lossy().starts_with('.') {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{suffix}"));
+    path.with_file_name(name)
+}
+
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    fn finish(self) -> EntrySum {
+        EntrySum {
+            size: self.size,
+            sha256: hex::encode(self.hasher.finalize()),
+        }
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+}
+
+// ── Encryption ──
+//
+// Encrypted archives start with MAGIC, a format byte, a 16-byte Argon2id salt
+// and a 19-byte nonce prefix. The payload follows as chunks of up to
+// CHUNK_SIZE plaintext bytes, each stored as a big-endian u32 ciphertext
+// length and the ciphertext. A chunk's nonce is the prefix, its index as a
+// big-endian u32, and a byte that is 1 only for the final chunk, so
+// reordered, dropped or truncated chunks fail to decrypt.
+
+const MAGIC: &[u8; 16] = b"chatalot-backup\n";
+const CIPHER_VERSION: u8 = 1;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+
+fn is_encrypted(reader: &mut BufReader<File>) -> io::Result<bool> {
+    use io::BufRead;
+    Ok(reader.fill_buf()?.starts_with(MAGIC))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> anyhow::Result<XChaCha20Poly1305> {
+    let mut key = zeroize::Zeroizing::new([0u8; 32]);
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| anyhow::anyhow!("key derivation failed: {e}"))?;
+    Ok(XChaCha20Poly1305::new(key.as_ref().into()))
+}
+
+fn chunk_nonce(prefix: &[u8; 19], index: u32, last: bool) -> XNonce {
+    let mut nonce = [0u8; 24];
+    nonce[..19].copy_from_slice(prefix);
+    nonce[19..23].copy_from_slice(&index.to_be_bytes());
+    nonce[23] = last as u8;
+    nonce.into()
+}
+
+pub struct EncryptWriter<W: Write> {
+    inner: W,
+    cipher: XChaCha20Poly1305,
+    prefix: [u8; 19],
+    index: u32,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    pub fn new(mut inner: W, passphrase: &str) -> anyhow::Result<Self> {
+        use rand::RngCore;
+        let mut salt = [0u8; 16];
+        let mut prefix = [0u8; 19];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut prefix);
+        inner.write_all(MAGIC)?;
+        inner.write_all(&[CIPHER_VERSION])?;
+        inner.write_all(&salt)?;
+        inner.write_all(&prefix)?;
+        Ok(Self {
+            inner,
+            cipher: derive_key(passphrase, &salt)?,
+            prefix,
+            index: 0,
+            buf: Vec::with_capacity(CHUNK_SIZE),
+        })
+    }
+
+    fn seal(&mut self, last: bool) -> io::Result<()> {
+        let nonce = chunk_nonce(&self.prefix, self.index, last);
+        let sealed = self
+            .cipher
+            .encrypt(&nonce, self.buf.as_slice())
+            .map_err(|_| io::Error::other("encryption failed"))?;
+        self.inner.write_all(&(sealed.len() as u32).to_be_bytes())?;
+        self.inner.write_all(&sealed)?;
+        self.buf.clear();
+        self.index = self
+            .index
+            .checked_add(1)
+            .ok_or_else(|| io::Error::other("archive too large"))?;
+        Ok(())
+    }
+
+    /// Seal the final chunk and flush, returning the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.seal(true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        // Seal only once more data arrives, so the final chunk is never empty
+        // unless the whole payload is.
+        if self.buf.len() == CHUNK_SIZE {
+            self.seal(false)?;
+        }
+        let n = data.len().min(CHUNK_SIZE - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct DecryptReader<R: Read> {
+    inner: R,
+    cipher: XChaCha20Poly1305,
+    prefix: [u8; 19],
+    index: u32,
+    plain: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: Read> DecryptReader<R> {
+    pub fn new(mut inner: R, passphrase: &str) -> anyhow::Result<Self> {
+        let mut header = [0u8; 16 + 1 + 16 + 19];
+        inner
+            .read_exact(&mut header)
+            .context("truncated archive header")?;
+        if &header[..16] != MAGIC {
+            bail!("not an encrypted backup archive");
+        }
+        if header[16] != CIPHER_VERSION {
+            bail!("unsupported archive encryption version {}", header[16]);
+        }
+        let mut prefix = [0u8; 19];
+        prefix.copy_from_slice(&header[33..]);
+        Ok(Self {
+            inner,
+            cipher: derive_key(passphrase, &header[17..33])?,
+            prefix,
+            index: 0,
+            plain: Vec::new(),
+            pos: 0,
+            done: false,
+        })
+    }
+
+    fn next_chunk(&mut self) -> io::Result<()> {
+        let mut len = [0u8; 4];
+        self.inner
+            .read_exact(&mut len)
+            .map_err(|_| io::Error::new(io::ErrorKind::UnexpectedEof, "archive is truncated"))?;
+        let len = u32::from_be_bytes(len) as usize;
+        if !(TAG_SIZE..=CHUNK_SIZE + TAG_SIZE).contains(&len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "corrupt archive chunk",
+            ));
+        }
+        let mut sealed = vec![0u8; len];
+        self.inner
+            .read_exact(&mut sealed)
+            .map_err(|_| io::Error::new(io::ErrorKind::UnexpectedEof, "archive is truncated"))?;
+
+        // The final-chunk flag is part of the nonce, so try "not last" first
+        // and fall back to "last".
+        let mut last = false;
+        let plain = match self.cipher.decrypt(
+            &chunk_nonce(&self.prefix, self.index, false),
+            sealed.as_slice(),
+        ) {
+            Ok(p) => p,
+            Err(_) => {
+                last = true;
+                self.cipher
+                    .decrypt(
+                        &chunk_nonce(&self.prefix, self.index, true),
+                        sealed.as_slice(),
+                    )
+                    .map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "decryption failed (wrong passphrase or corrupt archive)",
+                        )
+                    })?
+            }
+        };
+        self.plain = plain;
+        self.pos = 0;
+        self.index = self.index.wrapping_add(1);
+        self.done = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plain.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.next_chunk()?;
+        }
+        let n = buf.len().min(self.plain.len() - self.pos);
+        buf[..n].copy_from_slice(&self.plain[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+enum Sink {
+    Plain(BufWriter<File>),
+    Encrypted(EncryptWriter<BufWriter<File>>),
+}
+
+impl Sink {
+    fn finish(self) -> io::Result<()> {
+        let mut file = match self {
+            Sink::Plain(file) => file,
+            Sink::Encrypted(enc) => enc.finish()?,
+        };
+        file.flush()?;
+        file.get_ref().sync_all()
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Plain(w) => w.write(data),
+            Sink::Encrypted(w) => w.write(data),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Plain(w) => w.flush(),
+            Sink::Encrypted(w) => w.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chatalot_db::models::channel::ChannelType;
+    use chatalot_db::repos::{bookmark_repo, channel_repo, message_repo, user_repo};
+    use uuid::Uuid;
+
+    #[test]
+    fn encryption_round_trips_and_detects_tampering() {
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 100).map(|i| i as u8).collect();
+        let mut enc = EncryptWriter::new(Vec::new(), "correct horse").unwrap();
+        enc.write_all(&data).unwrap();
+        let sealed = enc.finish().unwrap();
+
+        let mut out = Vec::new();
+        DecryptReader::new(sealed.as_slice(), "correct horse")
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, data);
+
+        let mut wrong = DecryptReader::new(sealed.as_slice(), "battery staple").unwrap();
+        assert!(wrong.read_to_end(&mut Vec::new()).is_err());
+
+        // Dropping the final chunk must not read as a clean end of stream.
+        let first_chunk = 52 + 4 + CHUNK_SIZE + TAG_SIZE;
+        let mut truncated = DecryptReader::new(&sealed[..first_chunk], "correct horse").unwrap();
+        assert!(truncated.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn member_paths_are_confined() {
+        assert!(safe_member_path("files/ab/abcdef").is_ok());
+        assert!(safe_member_path("db/users.copy").is_ok());
+        assert!(safe_member_path("files/../../etc/passwd").is_err());
+        assert!(safe_member_path("/files/x").is_err());
+        assert!(safe_member_path("db/nested/users.copy").is_err());
+        assert!(safe_member_path("other/x").is_err());
+    }
+
+    #[test]
+    fn tables_load_after_their_references() {
+        let edges = vec![
+            ("messages".to_string(), "channels".to_string()),
+            ("messages".to_string(), "users".to_string()),
+            ("channels".to_string(), "users".to_string()),
+        ];
+        let order = topo_sort(&["messages", "channels", "users"], &edges).unwrap();
+        assert_eq!(order, vec!["users", "channels", "messages"]);
+
+        let cycle = vec![
+            ("a".to_string(), "b".to_string()),
+            ("b".to_string(), "a".to_string()),
+        ];
+        assert!(topo_sort(&["a", "b"], &cycle).is_err());
+    }
+
+    async fn post(
+        db: &Db,
+        channel: Uuid,
+        sender: Uuid,
+        reply_to: Option<Uuid>,
+        thread: Option<Uuid>,
+    ) -> Uuid {
+        let id = Uuid::now_v7();
+        message_repo::create_message(
+            db, id, channel, sender, b"hi", &[0u8; 12], "text", None, reply_to, None, None, thread,
+        )
+        .await
+        .unwrap();
+        id
+    }
+
+    /// Runs only when `TEST_RESTORE_DATABASE_URL` points to a scratch
+    /// PostgreSQL database; its schema is dropped.
+    #[tokio::test]
+    async fn postgres_restore_loads_message_references() {
+        let Ok(url) = std::env::var("TEST_RESTORE_DATABASE_URL") else {
+            return;
+        };
+        let db = chatalot_db::pool::create_pool(&url).await.unwrap();
+        drop_schema(&db).await.unwrap();
+        chatalot_db::pool::run_migrations(&db).await.unwrap();
+
+        let user = Uuid::now_v7();
+        user_repo::create_user(
+            &db,
+            user,
+            "restorer",
+            "Restorer",
+            "r@example.com",
+            "hash",
+            &[7u8; 32],
+            "fp",
+        )
+        .await
+        .unwrap();
+        let channel = Uuid::now_v7();
+        channel_repo::create_channel(
+            &db,
+            channel,
+            "general",
+            ChannelType::Text,
+            None,
+            user,
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+        let parent = post(&db, channel, user, None, None).await;
+        let reply = post(&db, channel, user, Some(parent), None).await;
+        let in_thread = post(&db, channel, user, None, Some(parent)).await;
+        // Replies outlive the message they answer
+        let gone = post(&db, channel, user, None, None).await;
+        let orphan = post(&db, channel, user, Some(gone), None).await;
+        assert!(message_repo::hard_delete_message(&db, gone).await.unwrap());
+        bookmark_repo::create(&db, Uuid::now_v7(), user, reply, None)
+            .await
+            .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("chatalot-restore-{}", Uuid::now_v7()));
+        let root = dir.join("files");
+        std::fs::create_dir_all(&root).unwrap();
+        let root = root.to_str().unwrap();
+        let archive = dir.join("backup.tar.gz");
+        create(&db, root, &archive, None).await.unwrap();
+        let restored = restore(&db, root, &archive, None, true).await;
+        let _ = std::fs::remove_dir_all(&dir);
+        restored.unwrap();
+
+        for id in [parent, reply, in_thread] {
+            assert!(
+                message_repo::get_message_by_id(&db, id)
+                    .await
+                    .unwrap()
+                    .is_some()
+            );
+        }
+        let orphan = message_repo::get_message_by_id(&db, orphan)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(orphan.reply_to_id, Some(gone));
+        assert!(bookmark_repo::exists(&db, user, reply).await.unwrap());
+        // The reference checks are back on
+        assert!(
+            bookmark_repo::create(&db, Uuid::now_v7(), user, gone, None)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! The hash blocklist: exact SHA-256 checksums of files, and perceptual
+//! hashes (dHash) of images matched within a Hamming distance, so an image
+//! that was re-encoded or slightly edited is still caught.
+//!
+//! Blocklists are shared between instances as JSON in the
+//! [`BlocklistFile`] format: a `format` marker, a `version`, and entries of
+//! `kind`, `hash`, optional `max_distance` and optional `reason`.
+
+use chatalot_common::api_types::{BlocklistEntry, BlocklistFile};
+use chatalot_db::db::Db;
+use chatalot_db::models::blocked_hash::BlockedHash;
+use chatalot_db::models::file::FileRecord;
+use chatalot_db::repos::blocked_hash_repo::{self, DHASH, NewBlockedHash, SHA256};
+use uuid::Uuid;
+
+use crate::services::thumbnail_service;
+
+/// Bits a dHash entry may differ by when the admin gives no distance.
+pub const DEFAULT_MAX_DISTANCE: i32 = 8;
+/// Beyond this, unrelated images start to match.
+pub const MAX_DISTANCE_LIMIT: i32 = 16;
+
+/// `format` marker of blocklist files.
+pub const FORMAT: &str = "chatalot-blocklist";
+pub const VERSION: u32 = 1;
+
+const MAX_REASON_LEN: usize = 500;
+
+/// A checked blocklist entry, ready to store.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub kind: &'static str,
+    /// Lower-case hex.
+    pub hash: String,
+    pub max_distance: Option<i32>,
+    pub reason: Option<String>,
+}
+
+/// Check an entry given by an admin or read from a blocklist file. The kind
+/// defaults to `sha256`; dHash entries get [`DEFAULT_MAX_DISTANCE`] if they
+/// have no distance, and SHA-256 entries never have one.
+pub fn validate(
+    kind: Option<&str>,
+    hash: &str,
+    max_distance: Option<i32>,
+    reason: Option<&str>,
+) -> Result<Entry, String> {
+    let hash = hash.trim().to_ascii_lowercase();
+    let (kind, max_distance) = match kind.unwrap_or(SHA256) {
+        SHA256 => {
+            if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err("sha256 hash must be 64 hex characters".to_string());
+            }
+            (SHA256, None)
+        }
+        DHASH => {
+            if thumbnail_service::parse_phash(&hash).is_none() {
+                return Err("dhash must be 16 hex characters".to_string());
+            }
+            let distance = max_distance.unwrap_or(DEFAULT_MAX_DISTANCE);
+            if !(0..=MAX_DISTANCE_LIMIT).contains(&distance) {
+                return Err(format!(
+                    "max_distance must be between 0 and {MAX_DISTANCE_LIMIT}"
+                ));
+            }
+            (DHASH, Some(distance))
+        }
+        other => {
+            return Err(format!(
+                "unknown hash kind '{other}' (expected sha256 or dhash)"
+            ));
+        }
+    };
+    if reason.is_some_and(|r| r.chars().count() > MAX_REASON_LEN) {
+        return Err(format!(
+            "reason must be at most {MAX_REASON_LEN} characters"
+        ));
+    }
+    Ok(Entry {
+        kind,
+        hash,
+        max_distance,
+        reason: reason.map(str::to_string),
+    })
+}
+
+/// Add a checked entry to the blocklist.
+pub async fn add(
+    db: &Db,
+    entry: &Entry,
+    blocked_by: Option<Uuid>,
+) -> Result<BlockedHash, sqlx::Error> {
+    blocked_hash_repo::add_blocked_hash(
+        db,
+        Uuid::now_v7(),
+        entry.kind,
+        &entry.hash,
+        entry.max_distance,
+        entry.reason.as_deref(),
+        blocked_by,
+    )
+    .await
+}
+
+/// The blocked image an image with perceptual hash `phash` is a near-copy
+/// of, if any.
+pub async fn blocked_image(db: &Db, phash: &str) -> Result<Option<BlockedHash>, sqlx::Error> {
+    let blocked = blocked_hash_repo::list_perceptual_hashes(db).await?;
+    Ok(blocked.into_iter().find(|entry| {
+        thumbnail_service::phash_distance(phash, &entry.hash)
+            .is_some_and(|d| d as i32 <= entry.max_distance.unwrap_or(0))
+    }))
+}
+
+/// Block the checksums of `files`, and the perceptual hashes of those that
+/// are images. Returns how many entries were added or updated.
+pub async fn block_files(
+    db: &Db,
+    files: &[FileRecord],
+    reason: &str,
+    blocked_by: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let mut checksums: Vec<String> = files.iter().map(|f| f.checksum.clone()).collect();
+    let mut phashes: Vec<String> = files.iter().filter_map(|f| f.phash.clone()).collect();
+    checksums.sort();
+    checksums.dedup();
+    phashes.sort();
+    phashes.dedup();
+    let mut blocked = blocked_hash_repo::add_blocked_hashes(
+        db,
+        SHA256,
+        &checksums,
+        None,
+        Some(reason),
+        blocked_by,
+    )
+    .await?;
+    blocked += blocked_hash_repo::add_blocked_hashes(
+        db,
+        DHASH,
+        &phashes,
+        Some(DEFAULT_MAX_DISTANCE),
+        Some(reason),
+        blocked_by,
+    )
+    .await?;
+    Ok(blocked)
+}
+
+/// The whole blocklist as a blocklist file.
+pub async fn export(db: &Db) -> Result<BlocklistFile, sqlx::Error> {
+    let entries = blocked_hash_repo::list_all_blocked_hashes(db)
+        .await?
+        .into_iter()
+        .map(|h| BlocklistEntry {
+            kind: h.kind,
+            hash: h.hash,
+            max_distance: h.max_distance,
+            reason: h.reason,
+        })
+        .collect();
+    Ok(BlocklistFile {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        entries,
+    })
+}
+
+/// Check every entry of a blocklist file. Errors name the first bad entry,
+/// so nothing is imported from a file that is partly wrong.
+pub fn parse(file: &BlocklistFile) -> Result<Vec<Entry>, String> {
+    if file.format != FORMAT {
+        return Err(format!("not a blocklist file (format must be '{FORMAT}')"));
+    }
+    if file.version != VERSION {
+        return Err(format!("unsupported blocklist version {}", file.version));
+    }
+    file.entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            validate(Some(&e.kind), &e.hash, e.max_distance, e.reason.as_deref())
+                .map_err(|err| format!("entry {}: {err}", i + 1))
+        })
+        .collect()
+}
+
+/// Add parsed entries to the blocklist in one transaction. Hashes already
+/// blocked take the imported reason and distance. Returns how many entries
+/// were stored.
+pub async fn import(
+    db: &Db,
+    entries: &[Entry],
+    blocked_by: Option<Uuid>,
+) -> Result<u64, sqlx::Error> {
+    let entries: Vec<NewBlockedHash<'_>> = entries
+        .iter()
+        .map(|e| NewBlockedHash {
+            kind: e.kind,
+            hash: &e.hash,
+            max_distance: e.max_distance,
+            reason: e.reason.as_deref(),
+        })
+        .collect();
+    blocked_hash_repo::import_blocked_hashes(db, &entries, blocked_by).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_checked_by_kind() {
+        let sha = "AB".repeat(32);
+        let entry = validate(None, &sha, Some(3), None).unwrap();
+        assert_eq!(entry.kind, SHA256);
+        assert_eq!(entry.hash, "ab".repeat(32));
+        assert_eq!(entry.max_distance, None);
+
+        let dhash = validate(Some("dhash"), "00ff00ff00ff00ff", None, Some("spam")).unwrap();
+        assert_eq!(dhash.max_distance, Some(DEFAULT_MAX_DISTANCE));
+        assert!(validate(Some("dhash"), &sha, None, None).is_err());
+        assert!(validate(Some("dhash"), "00ff00ff00ff00ff", Some(40), None).is_err());
+        assert!(validate(Some("md5"), &sha, None, None).is_err());
+        assert!(validate(None, &sha, None, Some(&"x".repeat(501))).is_err());
+        // Counted in characters, not bytes
+        assert!(validate(None, &sha, None, Some(&"é".repeat(500))).is_ok());
+    }
+
+    #[test]
+    fn blocklist_files_are_all_or_nothing() {
+        let json = r#"{
+            "format": "chatalot-blocklist",
+            "version": 1,
+            "entries": [
+                {"kind": "dhash", "hash": "00ff00ff00ff00ff", "max_distance": 4},
+                {"kind": "sha256", "hash": "nope"}
+            ]
+        }"#;
+        let mut file: BlocklistFile = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            parse(&file).unwrap_err(),
+            "entry 2: sha256 hash must be 64 hex characters"
+        );
+
+        file.entries.pop();
+        let entries = parse(&file).unwrap();
+        assert_eq!(entries[0].max_distance, Some(4));
+
+        file.version = 2;
+        assert!(parse(&file).is_err());
+    }
+}
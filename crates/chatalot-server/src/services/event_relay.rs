@@ -0,0 +1,141 @@
+//! Cross-node delivery for realtime events raised by cluster jobs.
+//!
+//! WebSocket and SSE sessions live in the memory of the node they connected
+//! to, but a cluster job runs on whichever node holds its lease. Such jobs
+//! [`publish`] their events to the `ws_events` table instead of the local
+//! connection manager; the node-scoped `event_relay` job on every node then
+//! delivers each event to its own connections via [`relay`]. `chatalot-admin`
+//! publishes suspensions the same way, so running nodes apply them.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use chatalot_common::ws_messages::ServerMessage;
+use chatalot_db::db::Db;
+use chatalot_db::repos::ws_event_repo;
+
+use crate::app_state::AppState;
+
+/// How far back each relay pass looks. Covers rows that commit a little after
+/// their `created_at`; already-delivered ids are skipped.
+const WINDOW_SECS: i64 = 60;
+/// Rows older than this are deleted.
+const RETAIN_SECS: i64 = 10 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RelayedEvent {
+    /// Broadcast to everyone subscribed to the channel.
+    Channel {
+        channel_id: Uuid,
+        message: ServerMessage,
+    },
+    /// Send to each listed user's sessions.
+    Users {
+        user_ids: Vec<Uuid>,
+        message: ServerMessage,
+    },
+    /// A presence change, announced to community mates by the node(s) the
+    /// user is connected to, as if the user had changed it themselves.
+    Presence { user_id: Uuid, status: String },
+    /// A user was suspended or unsuspended. Every node updates its suspended
+    /// set; on suspension it also drops the user's sessions.
+    Suspension { user_id: Uuid, suspended: bool },
+}
+
+/// Per-node relay bookkeeping.
+pub struct EventRelay {
+    /// Events created before this node started are not replayed.
+    started_at: DateTime<Utc>,
+    /// Events already delivered on this node, with their creation time.
+    seen: DashMap<Uuid, DateTime<Utc>>,
+}
+
+impl Default for EventRelay {
+    fn default() -> Self {
+        Self {
+            started_at: Utc::now(),
+            seen: DashMap::new(),
+        }
+    }
+}
+
+/// Record an event for every node to deliver.
+pub async fn publish(db: &Db, event: &RelayedEvent) -> Result<(), sqlx::Error> {
+    let json = serde_json::to_string(event).expect("relayed events serialize");
+    ws_event_repo::insert(db, Uuid::now_v7(), &json).await
+}
+
+/// Deliver events this node hasn't delivered yet, and prune old ones.
+pub async fn relay(state: &Arc<AppState>) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    let since = (now - Duration::seconds(WINDOW_SECS)).max(state.event_relay.started_at);
+    let events = ws_event_repo::list_since(&state.db, since).await?;
+
+    for row in events {
+        if state
+            .event_relay
+            .seen
+            .insert(row.id, row.created_at)
+            .is_some()
+        {
+            continue;
+        }
+        match serde_json::from_str::<RelayedEvent>(&row.event) {
+            Ok(event) => deliver(state, event).await,
+            Err(e) => tracing::warn!(id = %row.id, "Skipping malformed relayed event: {e}"),
+        }
+    }
+
+    let cutoff = now - Duration::seconds(2 * WINDOW_SECS);
+    state
+        .event_relay
+        .seen
+        .retain(|_, created| *created > cutoff);
+    ws_event_repo::delete_older_than(&state.db, RETAIN_SECS).await?;
+    Ok(())
+}
+
+async fn deliver(state: &AppState, event: RelayedEvent) {
+    match event {
+        RelayedEvent::Channel {
+            channel_id,
+            message,
+        } => state.connections.broadcast_to_channel(channel_id, message),
+        RelayedEvent::Users { user_ids, message } => {
+            for user_id in &user_ids {
+                state.connections.send_to_user(user_id, &message);
+            }
+        }
+        RelayedEvent::Presence { user_id, status } => {
+            if state.connections.is_online(&user_id) {
+                crate::ws::handler::broadcast_presence(
+                    &state.db,
+                    &state.connections,
+                    user_id,
+                    &status,
+                )
+                .await;
+            }
+        }
+        RelayedEvent::Suspension { user_id, suspended } => {
+            if suspended {
+                state.suspended_users.insert(user_id);
+                state.connections.send_to_user(
+                    &user_id,
+                    &ServerMessage::Error {
+                        code: "account_suspended".to_string(),
+                        message: "account is suspended".to_string(),
+                    },
+                );
+                state.connections.disconnect_user(&user_id);
+            } else {
+                state.suspended_users.remove(&user_id);
+            }
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! File retention: channels and communities can limit how old their files
+//! may get and how much space they may take, and the `file_retention` job
+//! deletes what falls outside. Files shared in a channel with a message TTL
+//! are deleted once they outlive it, like the messages that shared them.
+//!
+//! Deleting a file removes its row, releases its blob (with thumbnail and
+//! variants) and gives the uploader their quota back, as a manual delete does.
+
+use std::collections::BTreeMap;
+
+use chatalot_common::api_types::FileRetentionPolicy;
+use chatalot_db::db::Db;
+use chatalot_db::models::file::FileRecord;
+use chatalot_db::models::file_retention_policy::FileRetentionPolicy as Policy;
+use chatalot_db::repos::{file_retention_repo, user_repo};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::services::upload_service;
+use crate::storage::Storage;
+
+/// Files deleted per policy and run; the rest wait for the next run.
+const BATCH: i64 = 500;
+
+const MB: i64 = 1024 * 1024;
+const MAX_AGE_DAYS: i32 = 3650;
+const MAX_TOTAL_MB: i64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Default)]
+pub struct RetentionReport {
+    pub deleted: u64,
+    pub bytes: u64,
+    pub failed: u64,
+}
+
+/// The policy of a scope as the API shows it.
+pub fn to_response(policy: Option<&Policy>) -> FileRetentionPolicy {
+    policy.map_or_else(Default::default, |p| FileRetentionPolicy {
+        max_age_days: p.max_age_days,
+        max_total_mb: p.max_total_bytes.map(|b| b / MB),
+    })
+}
+
+/// Set or, with no limits, remove the policy of a channel or community.
+pub async fn set_policy(
+    db: &Db,
+    scope_type: &str,
+    scope_id: Uuid,
+    req: &FileRetentionPolicy,
+    user_id: Uuid,
+) -> Result<FileRetentionPolicy, AppError> {
+    if let Some(days) = req.max_age_days
+        && !(1..=MAX_AGE_DAYS).contains(&days)
+    {
+        return Err(AppError::Validation(format!(
+            "max_age_days must be between 1 and {MAX_AGE_DAYS}"
+        )));
+    }
+    if let Some(mb) = req.max_total_mb
+        && !(1..=MAX_TOTAL_MB).contains(&mb)
+    {
+        return Err(AppError::Validation(format!(
+            "max_total_mb must be between 1 and {MAX_TOTAL_MB}"
+        )));
+    }
+
+    let policy = if req.max_age_days.is_none() && req.max_total_mb.is_none() {
+        file_retention_repo::delete_policy(db, scope_type, scope_id).await?;
+        None
+    } else {
+        let max_total_bytes = req.max_total_mb.map(|mb| mb * MB);
+        let policy = file_retention_repo::upsert_policy(
+            db,
+            scope_type,
+            scope_id,
+            req.max_age_days,
+            max_total_bytes,
+            user_id,
+        )
+        .await?;
+        Some(policy)
+    };
+
+    user_repo::insert_audit_log(
+        db,
+        Uuid::now_v7(),
+        Some(user_id),
+        "set_file_retention",
+        None,
+        None,
+        Some(serde_json::json!({
+            "scope_type": scope_type,
+            "scope_id": scope_id,
+            "max_age_days": req.max_age_days,
+            "max_total_mb": req.max_total_mb,
+        })),
+    )
+    .await?;
+
+    Ok(to_response(policy.as_ref()))
+}
+
+/// Delete every file outside its channel's or community's policy, or past
+/// its channel's message TTL. Each scope's deletions are audited.
+pub async fn enforce(db: &Db, storage: &dyn Storage) -> Result<RetentionReport, sqlx::Error> {
+    let mut report = RetentionReport::default();
+    for policy in file_retention_repo::list_policies(db).await? {
+        let (scope_type, scope_id) = (policy.scope_type.as_str(), policy.scope_id);
+        if let Some(days) = policy.max_age_days {
+            let files =
+                file_retention_repo::list_files_older_than(db, scope_type, scope_id, days, BATCH)
+                    .await?;
+            delete(
+                db,
+                storage,
+                &files,
+                scope_type,
+                scope_id,
+                "max_age",
+                &mut report,
+            )
+            .await?;
+        }
+        if let Some(max) = policy.max_total_bytes {
+            let files =
+                file_retention_repo::list_files_over_size(db, scope_type, scope_id, max, BATCH)
+                    .await?;
+            delete(
+                db,
+                storage,
+                &files,
+                scope_type,
+                scope_id,
+                "max_total_size",
+                &mut report,
+            )
+            .await?;
+        }
+    }
+
+    let files = file_retention_repo::list_files_past_message_ttl(db, BATCH).await?;
+    let mut by_channel: BTreeMap<Uuid, Vec<FileRecord>> = BTreeMap::new();
+    for file in files {
+        if let Some(channel_id) = file.channel_id {
+            by_channel.entry(channel_id).or_default().push(file);
+        }
+    }
+    for (channel_id, files) in by_channel {
+        delete(
+            db,
+            storage,
+            &files,
+            "channel",
+            channel_id,
+            "message_ttl",
+            &mut report,
+        )
+        .await?;
+    }
+    Ok(report)
+}
+
+async fn delete(
+    db: &Db,
+    storage: &dyn Storage,
+    files: &[FileRecord],
+    scope_type: &str,
+    scope_id: Uuid,
+    rule: &str,
+    report: &mut RetentionReport,
+) -> Result<(), sqlx::Error> {
+    let (mut deleted, mut bytes) = (0u64, 0u64);
+    for file in files {
+        match upload_service::delete_file(db, storage, file).await {
+            Ok(true) => {
+                deleted += 1;
+                bytes += file.size_bytes as u64;
+            }
+            Ok(false) => {} // deleted since it was listed
+            Err(e) => {
+                report.failed += 1;
+                tracing::warn!(file_id = %file.id, "File retention: failed to delete file: {e}");
+            }
+        }
+    }
+    if deleted == 0 {
+        return Ok(());
+    }
+    report.deleted += deleted;
+    report.bytes += bytes;
+    user_repo::insert_audit_log(
+        db,
+        Uuid::now_v7(),
+        None,
+        "file_retention_delete",
+        None,
+        None,
+        Some(serde_json::json!({
+            "scope_type": scope_type,
+            "scope_id": scope_id,
+            "rule": rule,
+            "files_deleted": deleted,
+            "bytes_deleted": bytes,
+        })),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,181 @@
+//! Scanning uploads with the configured [`Scanner`] and quarantining what it
+//! finds.
+//!
+//! New uploads are scanned before they are stored; a detection is stored
+//! anyway and quarantined at once, so moderators can review it. The
+//! `malware_rescan` job scans recent files again after a signature update.
+
+use std::path::Path;
+
+use anyhow::Context;
+use chatalot_db::db::Db;
+use chatalot_db::models::file::FileRecord;
+use chatalot_db::repos::{file_repo, settings_repo, user_repo};
+use futures_util::StreamExt;
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::scanner::{Scanner, Verdict};
+
+/// Instance setting holding the signature version of the last full rescan.
+const SIGNATURES_SETTING: &str = "malware_scan_signatures";
+
+/// Scan the upload at `path`. Returns the matched signature, if any. A
+/// scanner failure, or a file larger than the scanner accepts, lets the
+/// upload through unscanned, unless `MALWARE_SCAN_FAIL_CLOSED` is set.
+pub async fn scan_upload(state: &AppState, path: &Path) -> Result<Option<String>, AppError> {
+    let (Some(scanner), Some(config)) = (&state.scanner, &state.config.malware_scan) else {
+        return Ok(None);
+    };
+    let scanned = match tokio::fs::File::open(path).await {
+        Ok(file) => {
+            scanner
+                .scan(tokio_util::io::ReaderStream::new(file).boxed())
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match scanned {
+        Ok(Verdict::Clean) => Ok(None),
+        Ok(Verdict::Infected(signature)) => Ok(Some(signature)),
+        Ok(Verdict::TooLarge) if config.fail_closed => Err(AppError::Validation(format!(
+            "file is larger than the {} malware scanner accepts",
+            scanner.kind()
+        ))),
+        Ok(Verdict::TooLarge) => {
+            tracing::warn!(
+                "Upload exceeds the {} stream size limit, storing it unscanned",
+                scanner.kind()
+            );
+            Ok(None)
+        }
+        Err(e) if config.fail_closed => Err(AppError::Internal(format!(
+            "{} scan failed, rejecting upload: {e}",
+            scanner.kind()
+        ))),
+        Err(e) => {
+            tracing::warn!(
+                "{} scan failed, storing upload unscanned: {e}",
+                scanner.kind()
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Quarantine a new upload the scanner flagged.
+pub async fn quarantine_upload(
+    state: &AppState,
+    file: &FileRecord,
+    signature: &str,
+) -> Result<(), sqlx::Error> {
+    let Some(scanner) = &state.scanner else {
+        return Ok(());
+    };
+    if file_repo::quarantine_file(&state.db, file.id, None).await? {
+        record_detection(&state.db, file, scanner.as_ref(), signature).await?;
+    }
+    Ok(())
+}
+
+/// Audit log entry for a file the scanner quarantined. It has no acting
+/// user; the uploader is in the metadata.
+async fn record_detection(
+    db: &Db,
+    file: &FileRecord,
+    scanner: &dyn Scanner,
+    signature: &str,
+) -> Result<(), sqlx::Error> {
+    tracing::warn!(
+        "{} found {signature} in file {} uploaded by {}; quarantined",
+        scanner.kind(),
+        file.id,
+        file.uploader_id
+    );
+    user_repo::insert_audit_log(
+        db,
+        Uuid::now_v7(),
+        None,
+        "malware_quarantine_file",
+        None,
+        None,
+        Some(serde_json::json!({
+            "file_id": file.id,
+            "uploader_id": file.uploader_id,
+            "scanner": scanner.kind(),
+            "signature": signature,
+        })),
+    )
+    .await
+}
+
+#[derive(Debug, Default)]
+pub struct RescanReport {
+    pub signatures: String,
+    pub scanned: u64,
+    /// Files quarantined, counting every file that shares a flagged blob.
+    pub quarantined: u64,
+    /// Blobs larger than the scanner accepts. They are not retried.
+    pub too_large: u64,
+    pub failed: u64,
+}
+
+/// Scan the blobs of files uploaded in the last `MALWARE_RESCAN_DAYS` again
+/// if the scanner's signatures changed since the last rescan. Returns `None`
+/// if there is no scanner or nothing changed. The new signature version is
+/// only recorded once every blob was scanned, so failures are retried.
+pub async fn rescan(state: &AppState) -> anyhow::Result<Option<RescanReport>> {
+    let (Some(scanner), Some(config)) = (&state.scanner, &state.config.malware_scan) else {
+        return Ok(None);
+    };
+    let signatures = scanner
+        .signature_version()
+        .await
+        .with_context(|| format!("failed to ask {} for its signature version", scanner.kind()))?;
+    let last = settings_repo::get(&state.db, SIGNATURES_SETTING).await?;
+    if last.as_deref() == Some(signatures.as_str()) {
+        return Ok(None);
+    }
+
+    let since = chrono::Utc::now() - chrono::Duration::days(config.rescan_days.into());
+    let mut report = RescanReport {
+        signatures,
+        ..Default::default()
+    };
+    for path in file_repo::list_recent_blob_paths(&state.db, since).await? {
+        let scanned = match state.storage.get(&path).await {
+            Ok(body) => scanner.scan(body).await,
+            // Deleted since it was listed
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => Err(e),
+        };
+        match scanned {
+            Ok(Verdict::Clean) => {}
+            Ok(Verdict::Infected(signature)) => {
+                for file in file_repo::quarantine_blob_files(&state.db, &path).await? {
+                    record_detection(&state.db, &file, scanner.as_ref(), &signature).await?;
+                    report.quarantined += 1;
+                }
+            }
+            Ok(Verdict::TooLarge) => {
+                tracing::warn!(
+                    "Malware rescan: {path} exceeds the {} stream size limit, skipped",
+                    scanner.kind()
+                );
+                report.too_large += 1;
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("Malware rescan: {} failed on {path}: {e}", scanner.kind());
+                report.failed += 1;
+                continue;
+            }
+        }
+        report.scanned += 1;
+    }
+    if report.failed == 0 {
+        settings_repo::set(&state.db, SIGNATURES_SETTING, &report.signatures).await?;
+    }
+    Ok(Some(report))
+}
@@ -1,5 +1,14 @@
 pub mod auth_service;
+pub mod backup;
+pub mod blocklist;
 pub mod css_sanitizer;
+pub mod event_relay;
+pub mod file_retention;
 pub mod file_security;
+pub mod malware_scan;
+pub mod orphan_files;
 pub mod push_service;
+pub mod quiet_hours;
+pub mod storage_quota;
 pub mod thumbnail_service;
+pub mod upload_service;
@@ -0,0 +1,151 @@
+//! Reconciles blob storage with the `files` and `file_blobs` tables.
+//!
+//! Uploads live under two-character shard prefixes. Avatars, community/group
+//! assets and custom emojis share the same storage but are not tracked in
+//! `files`, so their prefixes are never scanned.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use chatalot_db::db::Db;
+use chatalot_db::models::file::FileBlob;
+use uuid::Uuid;
+
+use chatalot_db::repos::file_repo;
+
+use crate::services::upload_service;
+use crate::storage::{self, Storage};
+
+/// Top-level prefixes that are not managed by `files`.
+/// `uploads` holds partial uploads, which the upload expiry job cleans up.
+const SKIP_DIRS: &[&str] = &[
+    storage::AVATARS,
+    storage::COMMUNITY_ASSETS,
+    storage::GROUP_ASSETS,
+    storage::EMOJIS,
+    "uploads",
+];
+
+/// Blobs younger than this are left alone: an upload stores its blob before
+/// inserting the row, so a fresh blob without a row may still be in flight.
+const MIN_AGE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Default)]
+pub struct OrphanReport {
+    /// Blob keys in storage that no `files` row refers to.
+    pub storage_only: Vec<String>,
+    /// `files` rows whose blob is missing from storage, as (id, storage_path).
+    pub missing_blobs: Vec<(Uuid, String)>,
+    /// Blob records that no file refers to any more, whatever their count
+    /// says (e.g. a delete that failed halfway).
+    pub unreferenced: Vec<FileBlob>,
+}
+
+/// Compare storage with the database without changing either.
+/// `storage_root` maps rows that still hold absolute local paths to keys.
+pub async fn scan(
+    db: &Db,
+    storage: &dyn Storage,
+    storage_root: &str,
+) -> anyhow::Result<OrphanReport> {
+    let db_files = file_repo::list_all_file_paths(db)
+        .await
+        .context("failed to list DB files")?;
+    let blobs = file_repo::list_blobs(db)
+        .await
+        .context("failed to list DB blobs")?;
+    let objects = storage
+        .list("")
+        .await
+        .with_context(|| format!("failed to list {} storage", storage.kind()))?;
+    let stored: HashSet<&str> = objects.iter().map(|o| o.key.as_str()).collect();
+
+    let root = Path::new(storage_root);
+    let mut known: HashSet<String> = HashSet::with_capacity(db_files.len() * 2);
+    let mut report = OrphanReport::default();
+    for (id, path, thumb) in db_files {
+        let key = storage::object_key(root, &path).into_owned();
+        if !stored.contains(key.as_str()) {
+            report.missing_blobs.push((id, path));
+        }
+        known.insert(key);
+        if let Some(thumb) = thumb {
+            known.insert(storage::object_key(root, &thumb).into_owned());
+        }
+    }
+    // Unreferenced blobs are reported with their records, not as bare keys
+    for blob in &blobs {
+        for key in upload_service::stored_keys(blob) {
+            known.insert(storage::object_key(root, &key).into_owned());
+        }
+    }
+
+    let cutoff = SystemTime::now() - MIN_AGE;
+    report.unreferenced = file_repo::list_unreferenced_blobs(db, cutoff.into())
+        .await
+        .context("failed to list unreferenced blobs")?;
+    for object in objects {
+        // Only blobs inside a shard directory are uploads; probes and other
+        // top-level files are not.
+        let Some((dir, _)) = object.key.split_once('/') else {
+            continue;
+        };
+        if SKIP_DIRS.contains(&dir) || known.contains(&object.key) {
+            continue;
+        }
+        if object.modified.is_some_and(|t| t < cutoff) {
+            report.storage_only.push(object.key);
+        }
+    }
+
+    report.storage_only.sort();
+    Ok(report)
+}
+
+/// Delete what `scan` found unreferenced: bare blobs in storage and blob
+/// records no file uses, with their bytes. Returns how many blobs were removed.
+pub async fn remove(db: &Db, storage: &dyn Storage, report: &OrphanReport) -> u64 {
+    let mut removed = 0u64;
+    for key in &report.storage_only {
+        match storage.delete(key).await {
+            Ok(()) => removed += 1,
+            Err(e) => tracing::warn!("Orphan cleanup: failed to remove {key}: {e}"),
+        }
+    }
+    for blob in &report.unreferenced {
+        // A file or an upload may have taken the blob since the scan. The
+        // tombstone keeps uploads from storing the key again until the bytes
+        // are gone.
+        match file_repo::tombstone_unreferenced_blob(db, &blob.storage_path, blob.ref_count).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                tracing::warn!(
+                    "Orphan cleanup: failed to drop blob record {}: {e}",
+                    blob.storage_path
+                );
+                continue;
+            }
+        }
+        let mut deleted = true;
+        for key in upload_service::stored_keys(blob) {
+            if let Err(e) = storage.delete(&key).await {
+                tracing::warn!("Orphan cleanup: failed to remove {key}: {e}");
+                deleted = false;
+            }
+        }
+        if !deleted {
+            continue;
+        }
+        if let Err(e) = file_repo::delete_blob_record(db, &blob.storage_path).await {
+            tracing::warn!(
+                "Orphan cleanup: failed to drop blob record {}: {e}",
+                blob.storage_path
+            );
+        }
+        removed += 1;
+    }
+    removed
+}
@@ -1,11 +1,16 @@
-use sqlx::PgPool;
+use chatalot_db::db::Db;
+use futures_util::future::BoxFuture;
 use uuid::Uuid;
 use web_push::{
     ContentEncoding, IsahcWebPushClient, PartialVapidSignatureBuilder, SubscriptionInfo,
     VapidSignatureBuilder, WebPushClient, WebPushMessageBuilder,
 };
 
-use chatalot_db::repos::push_subscription_repo;
+use chatalot_db::models::notification_setting::EffectiveNotificationSetting;
+use chatalot_db::models::push_subscription::PushSubscription;
+use chatalot_db::repos::{notification_repo, push_subscription_repo};
+
+use crate::metrics::METRICS;
 
 /// Notification payload sent via Web Push (metadata only — never message content).
 #[derive(serde::Serialize)]
@@ -16,7 +21,30 @@ pub struct PushPayload {
     pub channel_name: String,
 }
 
-pub struct PushService {
+/// Why a push delivery failed.
+#[derive(Debug)]
+pub enum PushError {
+    /// The endpoint no longer exists; the subscription should be removed.
+    Gone,
+    /// Any other failure; counted towards `cleanup_failed`.
+    Failed(String),
+}
+
+/// A way of delivering a notification to one subscription.
+/// Each transport handles the subscriptions whose `kind` matches.
+pub trait PushTransport: Send + Sync {
+    /// Subscription kind this transport delivers ('webpush', 'unifiedpush').
+    fn kind(&self) -> &'static str;
+
+    fn send<'a>(
+        &'a self,
+        sub: &'a PushSubscription,
+        payload: &'a [u8],
+    ) -> BoxFuture<'a, Result<(), PushError>>;
+}
+
+/// Browser Web Push: payload encrypted to the subscription keys and VAPID-signed.
+pub struct WebPushTransport {
     client: IsahcWebPushClient,
     /// Pre-parsed VAPID signing key (reused across all sends).
     vapid_builder: PartialVapidSignatureBuilder,
@@ -24,8 +52,8 @@ pub struct PushService {
     contact: String,
 }
 
-impl PushService {
-    /// Create a new PushService. Returns Err if the VAPID key is invalid.
+impl WebPushTransport {
+    /// Returns Err if the VAPID key is invalid.
     pub fn new(vapid_private_key: &str, public_url: Option<&str>) -> Result<Self, String> {
         let vapid_builder = VapidSignatureBuilder::from_base64_no_sub(
             vapid_private_key,
@@ -47,9 +75,229 @@ impl PushService {
         })
     }
 
+    async fn deliver(&self, sub: &PushSubscription, payload: &[u8]) -> Result<(), PushError> {
+        let (Some(p256dh), Some(auth)) = (&sub.p256dh_key, &sub.auth_key) else {
+            return Err(PushError::Failed("missing encryption keys".into()));
+        };
+        let sub_info = SubscriptionInfo::new(&sub.endpoint, p256dh, auth);
+
+        let mut builder = self.vapid_builder.clone().add_sub_info(&sub_info);
+        builder.add_claim("sub", serde_json::Value::String(self.contact.clone()));
+        let sig = builder
+            .build()
+            .map_err(|e| PushError::Failed(format!("failed to build VAPID sig: {e}")))?;
+
+        let mut msg_builder = WebPushMessageBuilder::new(&sub_info);
+        msg_builder.set_payload(ContentEncoding::Aes128Gcm, payload);
+        msg_builder.set_vapid_signature(sig);
+        let message = msg_builder
+            .build()
+            .map_err(|e| PushError::Failed(format!("failed to build push message: {e}")))?;
+
+        self.client.send(message).await.map_err(|e| {
+            let err_str = format!("{e}");
+            // EndpointNotValid / EndpointNotFound → subscription is dead
+            if err_str.contains("410")
+                || err_str.contains("404")
+                || err_str.contains("NotFound")
+                || err_str.contains("NotValid")
+            {
+                PushError::Gone
+            } else {
+                PushError::Failed(err_str)
+            }
+        })
+    }
+}
+
+impl PushTransport for WebPushTransport {
+    fn kind(&self) -> &'static str {
+        "webpush"
+    }
+
+    fn send<'a>(
+        &'a self,
+        sub: &'a PushSubscription,
+        payload: &'a [u8],
+    ) -> BoxFuture<'a, Result<(), PushError>> {
+        Box::pin(self.deliver(sub, payload))
+    }
+}
+
+/// UnifiedPush / ntfy: the payload is POSTed to the distributor endpoint,
+/// which forwards it to the app on the device. Subscriptions with keys get
+/// the payload encrypted as for Web Push (RFC 8291), so the distributor only
+/// sees ciphertext; subscriptions without keys get an empty wake-up ping.
+pub struct UnifiedPushTransport {
+    client: reqwest::Client,
+    allowed_hosts: Vec<String>,
+}
+
+impl UnifiedPushTransport {
+    pub fn new(allowed_hosts: &[String]) -> Result<Self, String> {
+        let resolver = PublicResolver {
+            trusted: allowed_hosts
+                .iter()
+                .map(|h| host_without_port(h).to_string())
+                .collect(),
+        };
+        // No redirects or proxies: every connection goes through the resolver
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .dns_resolver(std::sync::Arc::new(resolver))
+            .build()
+            .map_err(|e| format!("failed to create UnifiedPush client: {e}"))?;
+        Ok(Self {
+            client,
+            allowed_hosts: allowed_hosts.to_vec(),
+        })
+    }
+
+    async fn deliver(&self, sub: &PushSubscription, payload: &[u8]) -> Result<(), PushError> {
+        // Checked again at send time: the allowlist may have changed, and IP
+        // literals never reach the resolver
+        validate_unifiedpush_endpoint(&sub.endpoint, &self.allowed_hosts)
+            .map_err(PushError::Failed)?;
+
+        // RFC 8030 TTL, honored by UnifiedPush distributors
+        let request = self.client.post(&sub.endpoint).header("TTL", "86400");
+        let request = match (&sub.p256dh_key, &sub.auth_key) {
+            (Some(p256dh), Some(auth)) => {
+                let sub_info = SubscriptionInfo::new(&sub.endpoint, p256dh, auth);
+                let mut builder = WebPushMessageBuilder::new(&sub_info);
+                builder.set_payload(ContentEncoding::Aes128Gcm, payload);
+                let body = builder
+                    .build()
+                    .ok()
+                    .and_then(|m| m.payload)
+                    .ok_or_else(|| PushError::Failed("failed to encrypt payload".into()))?;
+                request
+                    .header(reqwest::header::CONTENT_ENCODING, "aes128gcm")
+                    .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                    .body(body.content)
+            }
+            _ => request,
+        };
+        let response = request
+            .send()
+            .await
+            .map_err(|e| PushError::Failed(e.to_string()))?;
+
+        match response.status() {
+            s if s.is_success() => Ok(()),
+            reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE => Err(PushError::Gone),
+            s => Err(PushError::Failed(format!("distributor returned {s}"))),
+        }
+    }
+}
+
+/// Resolves distributor hosts and drops private addresses, so a public name
+/// that points (or rebinds) to an internal address is never connected to.
+/// Allowlisted hosts resolve unfiltered.
+struct PublicResolver {
+    trusted: Vec<String>,
+}
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_ascii_lowercase();
+        let trusted = self.trusted.contains(&host);
+        Box::pin(async move {
+            let addrs: Vec<std::net::SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|a| trusted || !crate::routes::link_preview::is_private_ip(a.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{host} has no public address").into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// `host` of an allowlist entry that may be `host:port`.
+fn host_without_port(entry: &str) -> &str {
+    entry
+        .rsplit_once(':')
+        .filter(|(_, port)| port.parse::<u16>().is_ok())
+        .map_or(entry, |(host, _)| host)
+}
+
+impl PushTransport for UnifiedPushTransport {
+    fn kind(&self) -> &'static str {
+        "unifiedpush"
+    }
+
+    fn send<'a>(
+        &'a self,
+        sub: &'a PushSubscription,
+        payload: &'a [u8],
+    ) -> BoxFuture<'a, Result<(), PushError>> {
+        Box::pin(self.deliver(sub, payload))
+    }
+}
+
+/// Check a UnifiedPush endpoint before storing it. Endpoints must use HTTPS
+/// and a public host, unless the host is on the configured allowlist.
+/// A non-empty allowlist also rejects every host not on it.
+pub fn validate_unifiedpush_endpoint(
+    endpoint: &str,
+    allowed_hosts: &[String],
+) -> Result<(), String> {
+    let url = url::Url::parse(endpoint).map_err(|_| "Invalid endpoint URL".to_string())?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| "Endpoint must have a host".to_string())?
+        .to_ascii_lowercase();
+    let host_port = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.clone(),
+    };
+
+    if !allowed_hosts.is_empty() {
+        if !allowed_hosts.iter().any(|h| *h == host || *h == host_port) {
+            return Err("Endpoint host is not an allowed UnifiedPush distributor".into());
+        }
+        return match url.scheme() {
+            "https" | "http" => Ok(()),
+            _ => Err("Endpoint must use HTTP(S)".into()),
+        };
+    }
+
+    if url.scheme() != "https" {
+        return Err("Endpoint must use HTTPS".into());
+    }
+    if crate::routes::link_preview::is_private_host(&host) {
+        return Err("Endpoint must be a public host".into());
+    }
+    Ok(())
+}
+
+/// Delivers notifications to every subscription of a user, dispatching each
+/// one to the transport for its kind.
+pub struct PushService {
+    transports: Vec<Box<dyn PushTransport>>,
+}
+
+impl PushService {
+    pub fn new(transports: Vec<Box<dyn PushTransport>>) -> Self {
+        Self { transports }
+    }
+
+    /// Kinds of subscription this server can deliver to.
+    pub fn kinds(&self) -> Vec<&'static str> {
+        self.transports.iter().map(|t| t.kind()).collect()
+    }
+
+    pub fn supports(&self, kind: &str) -> bool {
+        self.transports.iter().any(|t| t.kind() == kind)
+    }
+
     /// Send a push notification to all subscriptions for a user.
     /// Handles failure tracking and dead subscription cleanup.
-    pub async fn send_to_user(&self, pool: &PgPool, user_id: Uuid, payload: &PushPayload) {
+    pub async fn send_to_user(&self, pool: &Db, user_id: Uuid, payload: &PushPayload) {
         let subscriptions =
             match push_subscription_repo::get_subscriptions_for_user(pool, user_id).await {
                 Ok(subs) => subs,
@@ -72,59 +320,176 @@ impl PushService {
         };
 
         for sub in subscriptions {
-            let sub_info = SubscriptionInfo::new(&sub.endpoint, &sub.p256dh_key, &sub.auth_key);
-
-            let mut builder = self.vapid_builder.clone().add_sub_info(&sub_info);
-            builder.add_claim("sub", serde_json::Value::String(self.contact.clone()));
-
-            let sig = match builder.build() {
-                Ok(sig) => sig,
-                Err(e) => {
-                    tracing::warn!("Failed to build VAPID sig for {}: {e}", sub.id);
-                    let _ = push_subscription_repo::increment_failure_count(pool, sub.id).await;
-                    continue;
-                }
-            };
-
-            let mut msg_builder = WebPushMessageBuilder::new(&sub_info);
-            msg_builder.set_payload(ContentEncoding::Aes128Gcm, payload_json.as_bytes());
-            msg_builder.set_vapid_signature(sig);
-
-            let message = match msg_builder.build() {
-                Ok(msg) => msg,
-                Err(e) => {
-                    tracing::warn!("Failed to build push message for {}: {e}", sub.id);
-                    let _ = push_subscription_repo::increment_failure_count(pool, sub.id).await;
-                    continue;
-                }
+            // Transport disabled since the subscription was made: leave it alone
+            let Some(transport) = self.transports.iter().find(|t| t.kind() == sub.kind) else {
+                continue;
             };
 
-            match self.client.send(message).await {
+            match transport.send(&sub, payload_json.as_bytes()).await {
                 Ok(()) => {
                     let _ = push_subscription_repo::mark_used(pool, sub.id).await;
                 }
-                Err(e) => {
-                    let err_str = format!("{e}");
-                    // EndpointNotValid / EndpointNotFound → subscription is dead
-                    if err_str.contains("410")
-                        || err_str.contains("404")
-                        || err_str.contains("NotFound")
-                        || err_str.contains("NotValid")
-                    {
-                        tracing::info!("Removing expired push subscription {}", sub.id);
-                        let _ = push_subscription_repo::delete_by_endpoint(
-                            pool,
-                            sub.user_id,
-                            &sub.endpoint,
-                        )
-                        .await;
-                    } else {
-                        tracing::warn!("Push delivery failed for {}: {e}", sub.id);
-                        let _ =
-                            push_subscription_repo::increment_failure_count(pool, sub.id).await;
-                    }
+                Err(PushError::Gone) => {
+                    METRICS.push_failed(&sub.kind, "gone");
+                    tracing::info!("Removing expired {} subscription {}", sub.kind, sub.id);
+                    let _ = push_subscription_repo::delete_by_endpoint(
+                        pool,
+                        sub.user_id,
+                        &sub.endpoint,
+                    )
+                    .await;
+                }
+                Err(PushError::Failed(e)) => {
+                    METRICS.push_failed(&sub.kind, "failed");
+                    tracing::warn!("Push delivery failed for {} ({}): {e}", sub.id, sub.kind);
+                    let _ = push_subscription_repo::increment_failure_count(pool, sub.id).await;
                 }
             }
         }
     }
 }
+
+/// Whether a message should produce a push for a user with the given effective setting.
+/// Without a setting, DMs notify on every message and other channels only on mentions.
+fn allows_push(
+    setting: Option<&EffectiveNotificationSetting>,
+    is_dm: bool,
+    mentioned: bool,
+    now: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    let level = match setting {
+        Some(s) if s.is_muted(now) => return false,
+        Some(s) => s.level.as_str(),
+        None if is_dm => "all",
+        None => "mentions",
+    };
+    match level {
+        "all" => true,
+        "mentions" => mentioned,
+        _ => false,
+    }
+}
+
+/// Filter push candidates for a message down to the users whose per-channel,
+/// group, community or DM notification settings allow it.
+/// Fails closed: if settings can't be loaded, nobody is notified.
+pub async fn filter_recipients(
+    pool: &Db,
+    channel_id: Uuid,
+    is_dm: bool,
+    candidates: &[Uuid],
+    mentioned: &[Uuid],
+) -> Vec<Uuid> {
+    let settings =
+        match notification_repo::get_effective_settings(pool, channel_id, candidates).await {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!(%channel_id, "Failed to load notification settings: {e}");
+                return Vec::new();
+            }
+        };
+    let now = chrono::Utc::now();
+    candidates
+        .iter()
+        .copied()
+        .filter(|uid| {
+            let setting = settings.iter().find(|s| s.user_id == *uid);
+            allows_push(setting, is_dm, mentioned.contains(uid), now)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setting(level: &str, muted_for_secs: Option<i64>) -> EffectiveNotificationSetting {
+        EffectiveNotificationSetting {
+            user_id: Uuid::nil(),
+            level: level.to_string(),
+            muted_until: muted_for_secs.map(|s| chrono::Utc::now() + chrono::Duration::seconds(s)),
+        }
+    }
+
+    #[test]
+    fn defaults_depend_on_channel_kind() {
+        let now = chrono::Utc::now();
+        assert!(allows_push(None, true, false, now));
+        assert!(!allows_push(None, false, false, now));
+        assert!(allows_push(None, false, true, now));
+    }
+
+    #[test]
+    fn levels_are_enforced() {
+        let now = chrono::Utc::now();
+        assert!(allows_push(Some(&setting("all", None)), false, false, now));
+        assert!(!allows_push(
+            Some(&setting("mentions", None)),
+            true,
+            false,
+            now
+        ));
+        assert!(allows_push(
+            Some(&setting("mentions", None)),
+            true,
+            true,
+            now
+        ));
+        assert!(!allows_push(
+            Some(&setting("nothing", None)),
+            true,
+            true,
+            now
+        ));
+    }
+
+    #[test]
+    fn unifiedpush_endpoints_must_be_public_https() {
+        assert!(validate_unifiedpush_endpoint("https://ntfy.sh/upAbc123", &[]).is_ok());
+        assert!(validate_unifiedpush_endpoint("http://ntfy.sh/upAbc123", &[]).is_err());
+        assert!(validate_unifiedpush_endpoint("https://localhost/up", &[]).is_err());
+        assert!(validate_unifiedpush_endpoint("https://10.0.0.5/up", &[]).is_err());
+    }
+
+    #[test]
+    fn unifiedpush_allowlist_restricts_and_permits_local() {
+        let allowed = vec!["localhost:8090".to_string(), "push.example.org".to_string()];
+        assert!(validate_unifiedpush_endpoint("http://localhost:8090/upX", &allowed).is_ok());
+        assert!(validate_unifiedpush_endpoint("https://push.example.org/upX", &allowed).is_ok());
+        assert!(validate_unifiedpush_endpoint("https://ntfy.sh/upX", &allowed).is_err());
+        assert!(validate_unifiedpush_endpoint("http://localhost:9000/upX", &allowed).is_err());
+        assert_eq!(host_without_port("localhost:8090"), "localhost");
+        assert_eq!(host_without_port("push.example.org"), "push.example.org");
+    }
+
+    #[tokio::test]
+    async fn resolver_drops_private_addresses_unless_allowlisted() {
+        use reqwest::dns::Resolve;
+        let resolver = PublicResolver { trusted: vec![] };
+        let name: reqwest::dns::Name = "localhost".parse().unwrap();
+        assert!(resolver.resolve(name).await.is_err());
+
+        let resolver = PublicResolver {
+            trusted: vec!["localhost".to_string()],
+        };
+        let name: reqwest::dns::Name = "localhost".parse().unwrap();
+        assert!(resolver.resolve(name).await.unwrap().next().is_some());
+    }
+
+    #[test]
+    fn mute_until_silences_only_while_active() {
+        let now = chrono::Utc::now();
+        assert!(!allows_push(
+            Some(&setting("all", Some(3600))),
+            false,
+            true,
+            now
+        ));
+        assert!(allows_push(
+            Some(&setting("all", Some(-3600))),
+            false,
+            false,
+            now
+        ));
+    }
+}
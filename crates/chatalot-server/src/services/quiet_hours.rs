@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use chatalot_db::db::Db;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use chrono_tz::Tz;
+use uuid::Uuid;
+
+use chatalot_db::models::quiet_hours::{QuietHoursSchedule, QuietHoursSettings};
+use chatalot_db::repos::{quiet_hours_repo, user_repo};
+
+use crate::app_state::AppState;
+use crate::services::event_relay::{self, RelayedEvent};
+use crate::services::push_service::{PushPayload, PushService};
+
+/// Whether any schedule covers `now` in the given time zone.
+/// A window whose end is not after its start wraps past local midnight,
+/// so it belongs to the weekday it starts on.
+pub fn is_quiet_at(schedules: &[QuietHoursSchedule], tz: Tz, now: DateTime<Utc>) -> bool {
+    let local = now.with_timezone(&tz);
+    let minute = (local.hour() * 60 + local.minute()) as i16;
+    let today = 1i16 << local.weekday().num_days_from_monday();
+    let yesterday = 1i16 << local.weekday().pred().num_days_from_monday();
+
+    schedules.iter().any(|s| {
+        if s.start_minute < s.end_minute {
+            s.days & today != 0 && (s.start_minute..s.end_minute).contains(&minute)
+        } else {
+            (s.days & today != 0 && minute >= s.start_minute)
+                || (s.days & yesterday != 0 && minute < s.end_minute)
+        }
+    })
+}
+
+/// Split push recipients into those to notify now and those whose
+/// notification should be held until quiet hours end. Recipients in quiet
+/// hours or DND with `suppress` delivery are dropped; DMs from a user on the
+/// recipient's breakthrough list are always delivered.
+/// Fails closed: if quiet hours can't be loaded, nobody is notified.
+pub async fn partition_recipients(
+    pool: &Db,
+    sender_id: Uuid,
+    is_dm: bool,
+    recipients: Vec<Uuid>,
+) -> (Vec<Uuid>, Vec<Uuid>) {
+    let quiet = match quiet_hours_repo::get_quiet_recipients(pool, &recipients, sender_id).await {
+        Ok(q) => q,
+        Err(e) => {
+            tracing::warn!("Failed to load quiet hours for push recipients: {e}");
+            return (Vec::new(), Vec::new());
+        }
+    };
+
+    let mut now = Vec::new();
+    let mut deferred = Vec::new();
+    for uid in recipients {
+        match quiet.iter().find(|q| q.user_id == uid) {
+            None => now.push(uid),
+            Some(q) if is_dm && q.breakthrough => now.push(uid),
+            Some(q) if q.delivery == "batch" => deferred.push(uid),
+            Some(_) => {}
+        }
+    }
+    (now, deferred)
+}
+
+/// Send whatever was deferred for a user during quiet hours: the original
+/// notification if there was only one, otherwise a single summary.
+pub async fn flush_deferred(pool: &Db, push: &PushService, user_id: Uuid) {
+    let mut deferred = match quiet_hours_repo::take_deferred(pool, user_id).await {
+        Ok(d) => d,
+        Err(e) => {
+            tracing::warn!(%user_id, "Failed to load deferred notifications: {e}");
+            return;
+        }
+    };
+
+    let total: i64 = deferred.iter().map(|d| i64::from(d.count)).sum();
+    let payload = match total {
+        0 => return,
+        1 => {
+            let d = deferred.remove(0);
+            PushPayload {
+                notification_type: d.notification_type,
+                sender_name: d.sender_name,
+                channel_id: d.channel_id.to_string(),
+                channel_name: d.channel_name,
+            }
+        }
+        n => PushPayload {
+            notification_type: "digest".to_string(),
+            sender_name: "Chatalot".to_string(),
+            channel_id: deferred[deferred.len() - 1].channel_id.to_string(),
+            channel_name: format!("{n} notifications during quiet hours"),
+        },
+    };
+    push.send_to_user(pool, user_id, &payload).await;
+}
+
+/// Bring a user's `quiet_active` flag in line with their schedules, switching
+/// presence and flushing deferred notifications when it changes.
+pub async fn apply_schedule(
+    state: &Arc<AppState>,
+    settings: &QuietHoursSettings,
+    schedules: &[QuietHoursSchedule],
+    now: DateTime<Utc>,
+) {
+    let user_id = settings.user_id;
+    let quiet = settings.enabled
+        && match settings.timezone.parse::<Tz>() {
+            Ok(tz) => is_quiet_at(schedules, tz, now),
+            Err(_) => {
+                tracing::warn!(%user_id, timezone = %settings.timezone, "Invalid quiet hours time zone");
+                false
+            }
+        };
+    if quiet == settings.quiet_active {
+        return;
+    }
+
+    if let Err(e) = quiet_hours_repo::set_quiet_active(&state.db, user_id, quiet).await {
+        tracing::warn!(%user_id, "Failed to update quiet hours state: {e}");
+        return;
+    }
+
+    // A manually chosen DND or invisible status always wins over the schedule
+    let manual_status = match user_repo::find_by_id(&state.db, user_id).await {
+        Ok(Some(u)) => u.status,
+        _ => return,
+    };
+    let manual_override = matches!(manual_status.as_str(), "dnd" | "invisible");
+
+    // The user may be connected to any node, so the change goes through the relay
+    if settings.auto_dnd && !manual_override {
+        let event = RelayedEvent::Presence {
+            user_id,
+            status: if quiet { "dnd" } else { "online" }.to_string(),
+        };
+        if let Err(e) = event_relay::publish(&state.db, &event).await {
+            tracing::warn!(%user_id, "Failed to publish quiet hours presence: {e}");
+        }
+    }
+
+    if !quiet
+        && manual_status != "dnd"
+        && let Some(ref push) = state.push_service
+    {
+        flush_deferred(&state.db, push, user_id).await;
+    }
+}
+
+/// Re-evaluate one user's quiet hours right away (e.g. after editing them).
+pub async fn refresh_user(state: &Arc<AppState>, user_id: Uuid) {
+    let settings = match quiet_hours_repo::get_settings(&state.db, user_id).await {
+        Ok(Some(s)) => s,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!(%user_id, "Failed to load quiet hours: {e}");
+            return;
+        }
+    };
+    match quiet_hours_repo::list_schedules(&state.db, user_id).await {
+        Ok(schedules) => apply_schedule(state, &settings, &schedules, Utc::now()).await,
+        Err(e) => tracing::warn!(%user_id, "Failed to load quiet hours schedules: {e}"),
+    }
+}
+
+/// Scheduler tick: re-evaluate every user with quiet hours configured.
+pub async fn run_scheduler(state: &Arc<AppState>) -> Result<(), sqlx::Error> {
+    let (settings, schedules) = quiet_hours_repo::list_enabled(&state.db).await?;
+    let now = Utc::now();
+    for s in &settings {
+        let own: Vec<_> = schedules
+            .iter()
+            .filter(|sch| sch.user_id == s.user_id)
+            .cloned()
+            .collect();
+        apply_schedule(state, s, &own, now).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const WEEKDAYS: i16 = 0b001_1111;
+
+    fn schedule(days: i16, start: i16, end: i16) -> QuietHoursSchedule {
+        QuietHoursSchedule {
+            id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            days,
+            start_minute: start,
+            end_minute: end,
+        }
+    }
+
+    #[test]
+    fn same_day_window() {
+        // 2026-03-04 is a Wednesday
+        let s = [schedule(WEEKDAYS, 9 * 60, 17 * 60)];
+        let at = |h| Utc.with_ymd_and_hms(2026, 3, 4, h, 0, 0).unwrap();
+        assert!(is_quiet_at(&s, Tz::UTC, at(9)));
+        assert!(is_quiet_at(&s, Tz::UTC, at(16)));
+        assert!(!is_quiet_at(&s, Tz::UTC, at(17)));
+        assert!(!is_quiet_at(&s, Tz::UTC, at(8)));
+    }
+
+    #[test]
+    fn window_wraps_past_midnight_into_next_day() {
+        // Fridays 22:00 - 07:00 covers early Saturday but not early Friday
+        let s = [schedule(1 << 4, 22 * 60, 7 * 60)];
+        let fri = |h| Utc.with_ymd_and_hms(2026, 3, 6, h, 0, 0).unwrap();
+        let sat = |h| Utc.with_ymd_and_hms(2026, 3, 7, h, 0, 0).unwrap();
+        assert!(is_quiet_at(&s, Tz::UTC, fri(23)));
+        assert!(is_quiet_at(&s, Tz::UTC, sat(6)));
+        assert!(!is_quiet_at(&s, Tz::UTC, sat(7)));
+        assert!(!is_quiet_at(&s, Tz::UTC, fri(3)));
+    }
+
+    #[test]
+    fn evaluated_in_user_time_zone() {
+        let s = [schedule(0b111_1111, 22 * 60, 7 * 60)];
+        let tz: Tz = "America/New_York".parse().unwrap();
+        // 03:00 UTC is 22:00 or 23:00 in New York, 12:00 UTC is morning there
+        assert!(is_quiet_at(
+            &s,
+            tz,
+            Utc.with_ymd_and_hms(2026, 3, 4, 3, 30, 0).unwrap()
+        ));
+        assert!(!is_quiet_at(
+            &s,
+            tz,
+            Utc.with_ymd_and_hms(2026, 3, 4, 15, 0, 0).unwrap()
+        ));
+        assert!(!is_quiet_at(
+            &s,
+            Tz::UTC,
+            Utc.with_ymd_and_hms(2026, 3, 4, 15, 0, 0).unwrap()
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+//! Storage quotas of communities and groups, and per-role overrides of the
+//! per-user upload quota. [`upload_service::check_quota`] enforces them;
+//! this module manages them.
+//!
+//! [`upload_service::check_quota`]: crate::services::upload_service::check_quota
+
+use chatalot_common::api_types::StorageQuotaResponse;
+use chatalot_db::db::Db;
+use chatalot_db::models::storage_quota::StorageQuota;
+use chatalot_db::repos::storage_quota_repo::{self, GROUP, ROLE};
+use chatalot_db::repos::{community_repo, group_repo, user_repo};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+const MB: i64 = 1024 * 1024;
+
+/// Largest community or group quota.
+const MAX_SCOPE_MB: i64 = 10 * 1024 * 1024;
+/// Largest role override, the same as `UPLOAD_QUOTA_MB` allows.
+const MAX_ROLE_MB: i64 = 100_000;
+
+/// Community roles a quota can override the per-user quota for.
+pub const ROLES: &[&str] = &["owner", "admin", "moderator", "member"];
+
+/// Check a quota of `max_mb` for a scope. Role overrides may be 0 for
+/// unlimited; community and group quotas need at least 1 MB.
+pub fn validate(scope_type: &str, role: &str, max_mb: i64) -> Result<(), String> {
+    let min = if scope_type == ROLE {
+        if !ROLES.contains(&role) {
+            return Err(format!("role must be one of: {}", ROLES.join(", ")));
+        }
+        0
+    } else {
+        1
+    };
+    let max = if scope_type == ROLE {
+        MAX_ROLE_MB
+    } else {
+        MAX_SCOPE_MB
+    };
+    if !(min..=max).contains(&max_mb) {
+        return Err(format!("max_mb must be between {min} and {max}"));
+    }
+    Ok(())
+}
+
+pub fn to_response(quota: StorageQuota) -> StorageQuotaResponse {
+    StorageQuotaResponse {
+        role: (quota.scope_type == ROLE).then_some(quota.role),
+        scope_type: quota.scope_type,
+        scope_id: quota.scope_id,
+        max_mb: quota.max_bytes / MB,
+        updated_by: quota.updated_by,
+        updated_at: quota.updated_at.to_rfc3339(),
+    }
+}
+
+/// Set the quota of a community or group, or a role override in a community
+/// (`role` is empty for the first two).
+pub async fn set_quota(
+    db: &Db,
+    scope_type: &'static str,
+    scope_id: Uuid,
+    role: &str,
+    max_mb: i64,
+    user_id: Uuid,
+) -> Result<StorageQuotaResponse, AppError> {
+    validate(scope_type, role, max_mb).map_err(AppError::Validation)?;
+    let exists = if scope_type == GROUP {
+        group_repo::get_group(db, scope_id).await?.is_some()
+    } else {
+        community_repo::get_community(db, scope_id).await?.is_some()
+    };
+    if !exists {
+        return Err(AppError::NotFound(format!("{scope_type} not found")));
+    }
+
+    let quota =
+        storage_quota_repo::upsert_quota(db, scope_type, scope_id, role, max_mb * MB, user_id)
+            .await?;
+    audit(
+        db,
+        "admin_set_storage_quota",
+        scope_type,
+        scope_id,
+        role,
+        Some(max_mb),
+        user_id,
+    )
+    .await?;
+    Ok(to_response(quota))
+}
+
+/// Remove a quota; uploads into the scope are limited by the others again.
+pub async fn remove_quota(
+    db: &Db,
+    scope_type: &'static str,
+    scope_id: Uuid,
+    role: &str,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    if !storage_quota_repo::delete_quota(db, scope_type, scope_id, role).await? {
+        return Err(AppError::NotFound("storage quota not found".to_string()));
+    }
+    audit(
+        db,
+        "admin_remove_storage_quota",
+        scope_type,
+        scope_id,
+        role,
+        None,
+        user_id,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn audit(
+    db: &Db,
+    action: &str,
+    scope_type: &str,
+    scope_id: Uuid,
+    role: &str,
+    max_mb: Option<i64>,
+    user_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let mut metadata = serde_json::json!({
+        "scope_type": scope_type,
+        "scope_id": scope_id,
+    });
+    if scope_type == ROLE {
+        metadata["role"] = role.into();
+    }
+    if let Some(max_mb) = max_mb {
+        metadata["max_mb"] = max_mb.into();
+    }
+    user_repo::insert_audit_log(
+        db,
+        Uuid::now_v7(),
+        Some(user_id),
+        action,
+        None,
+        None,
+        Some(metadata),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chatalot_db::repos::storage_quota_repo::COMMUNITY;
+
+    #[test]
+    fn role_overrides_may_be_unlimited() {
+        assert!(validate(ROLE, "moderator", 0).is_ok());
+        assert!(validate(ROLE, "moderator", MAX_ROLE_MB + 1).is_err());
+        assert!(validate(ROLE, "janitor", 100).is_err());
+        assert!(validate(COMMUNITY, "", 0).is_err());
+        assert!(validate(GROUP, "", MAX_SCOPE_MB).is_ok());
+    }
+}
@@ -1,57 +1,210 @@
+//! Thumbnails and previews for uploaded files.
+//!
+//! Raster images are thumbnailed and perceptually hashed in-process. Video, audio and PDF previews
+//! come from external tools (ffmpeg, ffprobe, pdftoppm), which run with an
+//! empty environment, resource limits and a timeout, since they parse
+//! untrusted files.
+
 use std::io::Cursor;
 use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use image::imageops::FilterType;
 use image::ImageFormat;
-use tokio::io::AsyncWriteExt;
+
+use crate::config::PreviewConfig;
 
 const THUMB_MAX_DIM: u32 = 300;
 const THUMB_JPEG_QUALITY: u8 = 80;
 
-/// Generate a JPEG thumbnail for a raster image.
-/// Returns `Ok(true)` if a thumbnail was generated, `Ok(false)` if the content
-/// type is not a supported image format, or an error on I/O failure.
-pub async fn generate_thumbnail(
+/// Longer side of the WebP variants stored for images, smallest first.
+pub const VARIANT_SIZES: &[u32] = &[300, 800, 1600];
+const VARIANT_WEBP_QUALITY: f32 = 80.0;
+/// The BlurHash is computed from a copy scaled down to this size.
+const BLURHASH_SAMPLE_DIM: u32 = 32;
+/// The dHash compares neighbouring pixels of a grayscale copy this size:
+/// one bit per pair, 8 pairs per row, 8 rows.
+const DHASH_WIDTH: u32 = 9;
+const DHASH_HEIGHT: u32 = 8;
+
+/// Address space a preview tool may use.
+const TOOL_MEMORY_LIMIT: u64 = 2 << 30;
+/// Largest file a preview tool may write.
+const TOOL_FILE_SIZE_LIMIT: u64 = 32 << 20;
+
+/// Everything generated from a raster image at upload time.
+#[derive(Debug)]
+pub struct ImagePreview {
+    /// JPEG thumbnail, at most [`THUMB_MAX_DIM`] on a side.
+    pub thumbnail: Vec<u8>,
+    /// WebP variants as (size, bytes), smallest first.
+    pub variants: Vec<(u32, Vec<u8>)>,
+    pub width: u32,
+    pub height: u32,
+    pub blurhash: Option<String>,
+    /// Perceptual hash, see [`dhash`].
+    pub dhash: u64,
+}
+
+/// Generate a JPEG thumbnail, WebP size variants, dimensions, a BlurHash and
+/// a perceptual hash for a raster image.
+/// Returns `Ok(None)` if the content type is not a supported image format,
+/// or an error if decoding fails.
+pub async fn generate_image_preview(
     data: &[u8],
     content_type: &str,
-    thumb_path: &Path,
-) -> Result<bool, String> {
+) -> Result<Option<ImagePreview>, String> {
     let format = match content_type {
         "image/png" => ImageFormat::Png,
         "image/jpeg" => ImageFormat::Jpeg,
         "image/gif" => ImageFormat::Gif,
         "image/webp" => ImageFormat::WebP,
         "image/bmp" => ImageFormat::Bmp,
-        _ => return Ok(false),
+        _ => return Ok(None),
     };
 
     // Decode the image (blocking — run in spawn_blocking to avoid blocking the runtime)
     let data = data.to_vec();
-    let thumb_bytes = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, String> {
+    let preview = tokio::task::spawn_blocking(move || -> Result<ImagePreview, String> {
         let img = image::load_from_memory_with_format(&data, format)
             .map_err(|e| format!("decode image: {e}"))?;
 
         let thumb = img.resize(THUMB_MAX_DIM, THUMB_MAX_DIM, FilterType::Lanczos3);
 
         let mut buf = Cursor::new(Vec::new());
-        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, THUMB_JPEG_QUALITY);
+        let encoder =
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, THUMB_JPEG_QUALITY);
         thumb
             .write_with_encoder(encoder)
             .map_err(|e| format!("encode thumbnail: {e}"))?;
 
-        Ok(buf.into_inner())
+        let variants = variant_sizes_for(img.width(), img.height())
+            .into_iter()
+            .map(|size| {
+                Ok((
+                    size,
+                    encode_webp(&img.resize(size, size, FilterType::Lanczos3))?,
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(ImagePreview {
+            thumbnail: buf.into_inner(),
+            variants,
+            width: img.width(),
+            height: img.height(),
+            blurhash: blurhash(&img),
+            dhash: dhash(&img),
+        })
     })
     .await
     .map_err(|e| format!("spawn_blocking: {e}"))??;
 
-    let mut f = tokio::fs::File::create(thumb_path)
-        .await
-        .map_err(|e| format!("create thumbnail file: {e}"))?;
-    f.write_all(&thumb_bytes)
-        .await
-        .map_err(|e| format!("write thumbnail: {e}"))?;
+    Ok(Some(preview))
+}
+
+/// Sizes of the variants to store for an image: every [`VARIANT_SIZES`] entry
+/// smaller than its longer side, and always the smallest one. Images are
+/// never scaled up, so a small image's only variant has its own size.
+fn variant_sizes_for(width: u32, height: u32) -> Vec<u32> {
+    let longest = width.max(height);
+    VARIANT_SIZES
+        .iter()
+        .enumerate()
+        .filter(|&(i, &size)| i == 0 || size < longest)
+        .map(|(_, &size)| size.min(longest))
+        .collect()
+}
+
+/// Parse the comma-separated variant sizes stored with a file.
+pub fn parse_variants(variants: Option<&str>) -> Vec<u32> {
+    variants
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|s| s.trim().parse().ok())
+        .collect()
+}
+
+/// Format variant sizes for storing with a file.
+pub fn format_variants(sizes: &[u32]) -> String {
+    sizes
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The stored variant to serve for a requested size: the smallest one at
+/// least that large, or else the largest.
+pub fn pick_variant(available: &[u32], requested: u32) -> Option<u32> {
+    available
+        .iter()
+        .copied()
+        .filter(|&size| size >= requested)
+        .min()
+        .or_else(|| available.iter().copied().max())
+}
+
+fn encode_webp(img: &image::DynamicImage) -> Result<Vec<u8>, String> {
+    // libwebp takes 8-bit RGB(A) only
+    let img = if img.color().has_alpha() {
+        image::DynamicImage::ImageRgba8(img.to_rgba8())
+    } else {
+        image::DynamicImage::ImageRgb8(img.to_rgb8())
+    };
+    let encoder = webp::Encoder::from_image(&img).map_err(|e| format!("encode variant: {e}"))?;
+    Ok(encoder.encode(VARIANT_WEBP_QUALITY).to_vec())
+}
 
-    Ok(true)
+/// BlurHash of the image, with more components along its longer side.
+fn blurhash(img: &image::DynamicImage) -> Option<String> {
+    let small = img
+        .thumbnail(BLURHASH_SAMPLE_DIM, BLURHASH_SAMPLE_DIM)
+        .to_rgba8();
+    let (x, y) = if small.width() >= small.height() {
+        (4, 3)
+    } else {
+        (3, 4)
+    };
+    blurhash::encode(x, y, small.width(), small.height(), small.as_raw()).ok()
+}
+
+/// Difference hash of the image: whether each pixel of a tiny grayscale copy
+/// is brighter than its right-hand neighbour. Re-encoding, resizing or small
+/// edits flip few bits, so near-copies are close in Hamming distance.
+fn dhash(img: &image::DynamicImage) -> u64 {
+    let small = img
+        .resize_exact(DHASH_WIDTH, DHASH_HEIGHT, FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..DHASH_HEIGHT {
+        for x in 0..DHASH_WIDTH - 1 {
+            let brighter = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(brighter);
+        }
+    }
+    hash
+}
+
+/// Format a perceptual hash for storing with a file: 16 hex digits.
+pub fn format_phash(hash: u64) -> String {
+    format!("{hash:016x}")
+}
+
+/// Parse a stored perceptual hash.
+pub fn parse_phash(hash: &str) -> Option<u64> {
+    if hash.len() != 16 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u64::from_str_radix(hash, 16).ok()
+}
+
+/// Number of bits two stored perceptual hashes differ in, or `None` if
+/// either is malformed.
+pub fn phash_distance(a: &str, b: &str) -> Option<u32> {
+    Some((parse_phash(a)? ^ parse_phash(b)?).count_ones())
 }
 
 /// Strip EXIF/metadata from an image by decoding and re-encoding.
@@ -91,3 +244,384 @@ pub async fn strip_exif(data: &[u8], content_type: &str) -> Option<Vec<u8>> {
     .ok()
     .flatten()
 }
+
+/// What the external tools extracted from a video, audio or PDF file.
+#[derive(Debug, Default, PartialEq)]
+pub struct MediaPreview {
+    /// JPEG poster frame, waveform or first page.
+    pub thumbnail: Option<Vec<u8>>,
+    /// Playing time of video and audio.
+    pub duration_ms: Option<i64>,
+}
+
+/// Whether `content_type` is previewed by [`generate_media_preview`].
+pub fn is_media(content_type: &str) -> bool {
+    content_type.starts_with("video/")
+        || content_type.starts_with("audio/")
+        || content_type == "application/pdf"
+}
+
+/// Preview the local file at `path`: a poster frame and duration for video,
+/// a waveform and duration for audio, the first page for PDFs. Returns
+/// `Ok(None)` for other types, when previews are disabled, or when the tool
+/// is not installed. Temporary output goes next to `path`.
+pub async fn generate_media_preview(
+    config: &PreviewConfig,
+    path: &Path,
+    content_type: &str,
+) -> Result<Option<MediaPreview>, String> {
+    if !config.enabled || !is_media(content_type) {
+        return Ok(None);
+    }
+    let timeout = Duration::from_secs(config.timeout_secs);
+    // The tools run in the file's directory, so a relative path (like the
+    // default `./data/files`) would no longer point at it
+    let path = &tokio::fs::canonicalize(path)
+        .await
+        .map_err(|e| format!("resolve {}: {e}", path.display()))?;
+    let input = path.to_string_lossy();
+
+    if content_type == "application/pdf" {
+        let out_root = format!("{input}.preview");
+        let scale = THUMB_MAX_DIM.to_string();
+        let args = [
+            "-jpeg",
+            "-jpegopt",
+            "quality=80",
+            "-f",
+            "1",
+            "-l",
+            "1",
+            "-singlefile",
+            "-scale-to",
+            &scale,
+            &input,
+            &out_root,
+        ];
+        let rendered = run_tool(&config.pdftoppm_path, &args, path, timeout).await;
+        let out_path = format!("{out_root}.jpg");
+        let page = match rendered {
+            Ok(Some(_)) => tokio::fs::read(&out_path)
+                .await
+                .map_err(|e| format!("read page: {e}")),
+            Ok(None) => return Ok(None),
+            Err(e) => Err(e),
+        };
+        let _ = tokio::fs::remove_file(&out_path).await;
+        return Ok(Some(MediaPreview {
+            thumbnail: Some(page?),
+            duration_ms: None,
+        }));
+    }
+
+    let probe = [
+        "-v",
+        "error",
+        "-protocol_whitelist",
+        "file",
+        "-show_entries",
+        "format=duration",
+        "-of",
+        "default=noprint_wrappers=1:nokey=1",
+        &input,
+    ];
+    let duration_ms = match run_tool(&config.ffprobe_path, &probe, path, timeout).await? {
+        Some(out) => parse_duration_ms(&out),
+        None => return Ok(None),
+    };
+
+    // Skip the first second of longer videos, which is often a black frame
+    let seek = if duration_ms.is_some_and(|d| d > 2000) {
+        "1"
+    } else {
+        "0"
+    };
+    let waveform =
+        format!("aformat=channel_layouts=mono,showwavespic=s={THUMB_MAX_DIM}x64:colors=0x8b9cf7");
+    let scale =
+        format!("scale=w={THUMB_MAX_DIM}:h={THUMB_MAX_DIM}:force_original_aspect_ratio=decrease");
+    let mut args = vec![
+        "-nostdin",
+        "-hide_banner",
+        "-loglevel",
+        "error",
+        "-protocol_whitelist",
+        "file",
+    ];
+    if content_type.starts_with("video/") {
+        args.extend(["-ss", seek, "-i", &input, "-frames:v", "1", "-vf", &scale]);
+    } else {
+        args.extend(["-i", &input, "-filter_complex", &waveform, "-frames:v", "1"]);
+    }
+    args.extend(["-f", "image2", "-c:v", "mjpeg", "-q:v", "4", "pipe:1"]);
+    let thumbnail = match run_tool(&config.ffmpeg_path, &args, path, timeout).await {
+        Ok(out) => out.filter(|jpeg| !jpeg.is_empty()),
+        // The duration is still worth keeping
+        Err(e) if duration_ms.is_some() => {
+            tracing::warn!("preview of {}: {e}", path.display());
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(Some(MediaPreview {
+        thumbnail,
+        duration_ms,
+    }))
+}
+
+/// ffprobe prints the duration in seconds, or `N/A`.
+fn parse_duration_ms(out: &[u8]) -> Option<i64> {
+    let secs: f64 = std::str::from_utf8(out).ok()?.trim().parse().ok()?;
+    (secs.is_finite() && secs >= 0.0).then(|| (secs * 1000.0).round() as i64)
+}
+
+/// Run a preview tool on a file in `path`'s directory and return its
+/// stdout. `Ok(None)` means the tool is not installed, which is only logged
+/// once. The tool gets no environment besides `PATH`, no stdin, and is
+/// killed when `timeout` elapses. On Unix it also runs under CPU, memory,
+/// file size and core dump limits.
+async fn run_tool(
+    program: &str,
+    args: &[&str],
+    path: &Path,
+    timeout: Duration,
+) -> Result<Option<Vec<u8>>, String> {
+    static WARNED_MISSING: AtomicBool = AtomicBool::new(false);
+
+    let mut cmd = tokio::process::Command::new(program);
+    cmd.args(args)
+        .env_clear()
+        .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = path.parent() {
+        cmd.current_dir(dir);
+    }
+    // SAFETY: only async-signal-safe calls (setrlimit) run in the child
+    // between fork and exec.
+    #[cfg(unix)]
+    unsafe {
+        let cpu_secs = timeout.as_secs() + 1;
+        cmd.pre_exec(move || {
+            limit(libc::RLIMIT_CPU, cpu_secs)?;
+            limit(libc::RLIMIT_AS, TOOL_MEMORY_LIMIT)?;
+            limit(libc::RLIMIT_FSIZE, TOOL_FILE_SIZE_LIMIT)?;
+            limit(libc::RLIMIT_CORE, 0)
+        });
+    }
+
+    let child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if !WARNED_MISSING.swap(true, Ordering::Relaxed) {
+                tracing::warn!(
+                    "{program} not found; video, audio and PDF previews are unavailable"
+                );
+            }
+            return Ok(None);
+        }
+        Err(e) => return Err(format!("{program}: {e}")),
+    };
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| format!("{program} timed out after {}s", timeout.as_secs()))?
+        .map_err(|e| format!("{program}: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{program} failed ({}): {}",
+            output.status,
+            stderr.trim().lines().last().unwrap_or_default()
+        ));
+    }
+    Ok(Some(output.stdout))
+}
+
+/// The type of `setrlimit`'s resource argument: glibc uses its own enum,
+/// other libcs a plain int.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type Resource = libc::c_int;
+
+#[cfg(unix)]
+fn limit(resource: Resource, value: u64) -> std::io::Result<()> {
+    let rlim = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    if unsafe { libc::setrlimit(resource, &rlim) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_are_never_scaled_up() {
+        assert_eq!(variant_sizes_for(4000, 3000), [300, 800, 1600]);
+        assert_eq!(variant_sizes_for(600, 1000), [300, 800]);
+        assert_eq!(variant_sizes_for(800, 800), [300]);
+        assert_eq!(variant_sizes_for(120, 80), [120]);
+
+        let stored = parse_variants(Some(&format_variants(&[300, 800])));
+        assert_eq!(stored, [300, 800]);
+        assert_eq!(pick_variant(&stored, 200), Some(300));
+        assert_eq!(pick_variant(&stored, 301), Some(800));
+        assert_eq!(pick_variant(&stored, 1600), Some(800));
+        assert_eq!(pick_variant(&parse_variants(None), 300), None);
+    }
+
+    #[tokio::test]
+    async fn images_get_variants_dimensions_and_a_blurhash() {
+        let img = image::RgbImage::from_fn(1000, 500, |x, y| {
+            image::Rgb([(x % 256) as u8, (y % 256) as u8, 90])
+        });
+        let mut png = Cursor::new(Vec::new());
+        img.write_to(&mut png, ImageFormat::Png).unwrap();
+
+        let preview = generate_image_preview(png.get_ref(), "image/png")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((preview.width, preview.height), (1000, 500));
+        let sizes: Vec<u32> = preview.variants.iter().map(|(s, _)| *s).collect();
+        assert_eq!(sizes, [300, 800]);
+        let large =
+            image::load_from_memory_with_format(&preview.variants[1].1, ImageFormat::WebP).unwrap();
+        assert_eq!((large.width(), large.height()), (800, 400));
+        assert_eq!(preview.blurhash.map(|h| h.len()), Some(28));
+
+        assert!(
+            generate_image_preview(b"%PDF", "application/pdf")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn perceptual_hashes_survive_small_edits() {
+        fn png(img: &image::RgbImage) -> Vec<u8> {
+            let mut buf = Cursor::new(Vec::new());
+            img.write_to(&mut buf, ImageFormat::Png).unwrap();
+            buf.into_inner()
+        }
+        async fn phash(data: &[u8], content_type: &str) -> String {
+            let preview = generate_image_preview(data, content_type).await.unwrap();
+            format_phash(preview.unwrap().dhash)
+        }
+
+        let mut img = image::RgbImage::from_fn(640, 480, |x, y| {
+            let v = ((x * 3 + y * 2) % 256) as u8;
+            image::Rgb([v, 255 - v, (x / 5 % 256) as u8])
+        });
+        let original = phash(&png(&img), "image/png").await;
+
+        // One changed pixel, and a lossy re-encode at a different size
+        img.put_pixel(10, 10, image::Rgb([255, 0, 0]));
+        let edited = phash(&png(&img), "image/png").await;
+        let mut jpeg = Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(img.clone())
+            .resize(320, 240, FilterType::Lanczos3)
+            .write_to(&mut jpeg, ImageFormat::Jpeg)
+            .unwrap();
+        let resized = phash(jpeg.get_ref(), "image/jpeg").await;
+        assert!(phash_distance(&original, &edited).unwrap() <= 2);
+        assert!(phash_distance(&original, &resized).unwrap() <= 8);
+
+        let other = image::RgbImage::from_fn(640, 480, |x, y| {
+            image::Rgb([(y % 256) as u8, (x % 256) as u8, ((x ^ y) % 256) as u8])
+        });
+        let other = phash(&png(&other), "image/png").await;
+        assert!(phash_distance(&original, &other).unwrap() > 8);
+
+        assert_eq!(phash_distance(&original, "not-a-hash"), None);
+        assert_eq!(parse_phash(&format_phash(u64::MAX)), Some(u64::MAX));
+    }
+
+    #[test]
+    fn parses_probe_durations() {
+        assert_eq!(parse_duration_ms(b"12.345600\n"), Some(12346));
+        assert_eq!(parse_duration_ms(b"0.000000"), Some(0));
+        assert_eq!(parse_duration_ms(b"N/A\n"), None);
+        assert_eq!(parse_duration_ms(b""), None);
+    }
+
+    #[tokio::test]
+    async fn tools_are_killed_on_timeout_and_missing_tools_are_skipped() {
+        let path = std::env::temp_dir().join("chatalot-preview-test");
+        let slow = run_tool("sh", &["-c", "sleep 5"], &path, Duration::from_millis(200)).await;
+        assert!(slow.unwrap_err().contains("timed out"));
+
+        let env = run_tool(
+            "sh",
+            &["-c", "echo ${TOTP_ENCRYPTION_KEY:-clean}"],
+            &path,
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+        assert_eq!(env.as_deref(), Some(&b"clean\n"[..]));
+
+        let missing = run_tool("chatalot-no-such-tool", &[], &path, Duration::from_secs(1)).await;
+        assert_eq!(missing, Ok(None));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn relative_file_paths_reach_the_tool() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A scratch directory under the temp dir, named relative to the
+        // working directory
+        let base = std::env::temp_dir().join(format!("preview-test-{}", std::process::id()));
+        tokio::fs::create_dir_all(&base).await.unwrap();
+        let base = tokio::fs::canonicalize(&base).await.unwrap();
+        let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let dir: std::path::PathBuf = cwd
+            .components()
+            .skip(1)
+            .map(|_| std::path::Component::ParentDir)
+            .chain(base.components().skip(1))
+            .collect();
+        assert!(dir.is_relative());
+
+        // Stands in for pdftoppm: writes `<out_root>.jpg` if the input exists
+        let tool = dir.join("fake-pdftoppm");
+        tokio::fs::write(
+            &tool,
+            "#!/bin/sh\neval in=\\${$(($# - 1))}; eval out=\\${$#}\ntest -f \"$in\" && printf page > \"$out.jpg\"\n",
+        )
+        .await
+        .unwrap();
+        tokio::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755))
+            .await
+            .unwrap();
+        let pdf = dir.join("doc.pdf");
+        tokio::fs::write(&pdf, b"%PDF-1.4").await.unwrap();
+
+        let config = PreviewConfig {
+            enabled: true,
+            ffmpeg_path: "ffmpeg".into(),
+            ffprobe_path: "ffprobe".into(),
+            pdftoppm_path: tokio::fs::canonicalize(&tool)
+                .await
+                .unwrap()
+                .to_string_lossy()
+                .into_owned(),
+            timeout_secs: 5,
+        };
+        let preview = generate_media_preview(&config, &pdf, "application/pdf").await;
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+        assert_eq!(
+            preview.unwrap().unwrap().thumbnail.as_deref(),
+            Some(&b"page"[..])
+        );
+    }
+}
@@ -0,0 +1,634 @@
+//! Turns received upload bytes into a stored `files` row.
+//!
+//! Both the single-request multipart upload and the resumable (tus) upload
+//! write the raw bytes to `FILE_STORAGE_PATH/uploads/<id>` as they arrive;
+//! [`finish_upload`] then validates the complete file and moves it into blob
+//! storage. Only images that need re-encoding or a thumbnail are read into
+//! memory; everything else is hashed and moved without buffering.
+//!
+//! Blobs are content-addressed: uploads with the same SHA-256 share one
+//! blob, counted in `file_blobs`, and [`delete_file`] removes the blob with
+//! its last file.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use bytes::Bytes;
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+use uuid::Uuid;
+
+use chatalot_db::db::Db;
+use chatalot_db::models::file::{FileBlob, FileRecord};
+use chatalot_db::repos::file_repo::BlobPreview;
+use chatalot_db::repos::storage_quota_repo::{COMMUNITY, GROUP, ROLE};
+use chatalot_db::repos::{
+    blocked_hash_repo, file_repo, storage_quota_repo, upload_repo, user_repo,
+};
+
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::metrics::METRICS;
+use crate::services::thumbnail_service::{ImagePreview, MediaPreview};
+use crate::services::{blocklist, file_security, malware_scan, thumbnail_service};
+use crate::storage::{self, Storage};
+
+/// Directory under the storage root holding partially received uploads.
+pub const PARTIAL_DIR: &str = "uploads";
+
+/// Unfinished resumable uploads expire this long after their last chunk.
+pub const UPLOAD_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Per-user upload rate limiter: max 10 uploads per 60 seconds.
+static UPLOAD_RATE: std::sync::LazyLock<DashMap<Uuid, (Instant, u32)>> =
+    std::sync::LazyLock::new(DashMap::new);
+const UPLOAD_RATE_WINDOW: u64 = 60;
+const UPLOAD_RATE_MAX: u32 = 10;
+
+/// How long an upload waits for an identical upload to finish storing its
+/// blob, or for a deleted blob's bytes to be removed, before giving up.
+const CLAIM_WAIT: Duration = Duration::from_secs(30);
+const CLAIM_POLL: Duration = Duration::from_millis(200);
+
+/// How much of the file magic-byte detection looks at.
+const SNIFF_LEN: usize = 8192;
+
+const MB: i64 = 1024 * 1024;
+
+/// Image types whose EXIF metadata is stripped before storing.
+const EXIF_TYPES: &[&str] = &["image/jpeg", "image/png", "image/webp"];
+
+/// Client-supplied details stored alongside the file.
+pub struct UploadMeta {
+    pub encrypted_name: String,
+    pub content_type: Option<String>,
+    pub channel_id: Option<Uuid>,
+}
+
+/// Where the bytes of upload `id` are written while it is in progress.
+pub fn partial_path(storage_root: &str, id: Uuid) -> PathBuf {
+    Path::new(storage_root)
+        .join(PARTIAL_DIR)
+        .join(id.to_string())
+}
+
+/// Count one upload against the user's rate limit.
+pub fn check_rate_limit(user_id: Uuid) -> Result<(), AppError> {
+    let mut entry = UPLOAD_RATE.entry(user_id).or_insert((Instant::now(), 0));
+    let (window_start, count) = entry.value_mut();
+    if window_start.elapsed().as_secs() >= UPLOAD_RATE_WINDOW {
+        *window_start = Instant::now();
+        *count = 1;
+    } else if *count >= UPLOAD_RATE_MAX {
+        return Err(AppError::Validation(
+            "upload rate limit exceeded (max 10 per minute)".to_string(),
+        ));
+    } else {
+        *count += 1;
+    }
+    Ok(())
+}
+
+/// Check that an upload of `size_bytes` into `channel_id` fits in every
+/// quota it falls under. The uploader's quota is charged `charge` of it, as
+/// content they already uploaded costs nothing, and is replaced by the
+/// override for their role in the channel's community if there is one. The
+/// channel's group and community quotas count the whole file. Unfinished
+/// resumable uploads count as used. Members of a community with quotas must
+/// name the channel, or they could upload around them.
+pub async fn check_quota(
+    state: &AppState,
+    user_id: Uuid,
+    channel_id: Option<Uuid>,
+    size_bytes: i64,
+    charge: i64,
+) -> Result<(), AppError> {
+    let mut quotas = match channel_id {
+        Some(channel_id) => {
+            storage_quota_repo::quotas_for_upload(&state.db, user_id, channel_id).await?
+        }
+        None if storage_quota_repo::has_quotas_for_member(&state.db, user_id).await? => {
+            return Err(AppError::Validation(
+                "channel_id is required: your communities have storage quotas".to_string(),
+            ));
+        }
+        None => Vec::new(),
+    };
+
+    if charge > 0 {
+        let (scope, limit, what) = match quotas.iter().find(|q| q.scope_type == ROLE) {
+            Some(q) => (
+                ROLE,
+                q.max_bytes,
+                format!("upload quota for community {}s", q.role),
+            ),
+            None => (
+                "user",
+                state.live().upload_quota_mb as i64 * MB,
+                "upload quota".to_string(),
+            ),
+        };
+        if limit > 0 {
+            let used = user_repo::get_upload_bytes_used(&state.db, user_id).await?;
+            let (_, pending) = upload_repo::get_pending_for_user(&state.db, user_id).await?;
+            let used = used + pending;
+            if used + charge > limit {
+                return Err(quota_exceeded(scope, &what, limit, used));
+            }
+        }
+    }
+
+    // The narrower group quota is reported first
+    quotas.retain(|q| q.scope_type != ROLE);
+    quotas.sort_by_key(|q| q.scope_type != GROUP);
+    for quota in quotas {
+        let used = file_repo::scope_usage(&state.db, &quota.scope_type, quota.scope_id).await?;
+        if used + size_bytes > quota.max_bytes {
+            let scope = if quota.scope_type == GROUP {
+                GROUP
+            } else {
+                COMMUNITY
+            };
+            let what = format!("{scope} storage quota");
+            return Err(quota_exceeded(scope, &what, quota.max_bytes, used));
+        }
+    }
+    Ok(())
+}
+
+fn quota_exceeded(scope: &'static str, what: &str, limit: i64, used: i64) -> AppError {
+    let remaining_mb = ((limit - used) as f64 / MB as f64).max(0.0);
+    AppError::QuotaExceeded {
+        scope,
+        message: format!(
+            "{what} exceeded ({} MB limit, {remaining_mb:.1} MB remaining)",
+            limit / MB
+        ),
+    }
+}
+
+/// Validate the complete file at `partial` and store it as file `file_id`.
+/// The partial file is consumed either way: moved into storage on success,
+/// deleted on failure.
+pub async fn finish_upload(
+    state: &AppState,
+    user_id: Uuid,
+    file_id: Uuid,
+    partial: &Path,
+    meta: UploadMeta,
+) -> Result<FileRecord, AppError> {
+    let result = store(state, user_id, file_id, partial, meta).await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(partial).await;
+    }
+    result
+}
+
+async fn store(
+    state: &AppState,
+    user_id: Uuid,
+    file_id: Uuid,
+    partial: &Path,
+    meta: UploadMeta,
+) -> Result<FileRecord, AppError> {
+    let size_bytes = tokio::fs::metadata(partial)
+        .await
+        .map_err(|e| AppError::Internal(format!("stat upload: {e}")))?
+        .len() as i64;
+
+    let (checksum, head) = hash_file(partial)
+        .await
+        .map_err(|e| AppError::Internal(format!("read upload: {e}")))?;
+
+    // Security: check if this file hash is blocked
+    if blocked_hash_repo::is_hash_blocked(&state.db, &checksum).await? {
+        return Err(AppError::Validation(
+            "this file has been blocked by an administrator".to_string(),
+        ));
+    }
+
+    // Security: validate file type via magic bytes and use detected type
+    let detected_type = match file_security::validate_file_type(&head) {
+        Ok(detected) => Some(detected.to_string()),
+        Err(reason) => {
+            return Err(AppError::Validation(format!(
+                "file type not allowed: {reason}"
+            )));
+        }
+    };
+
+    // Security: scan for malware. Detections are stored, then quarantined.
+    let detection = malware_scan::scan_upload(state, partial).await?;
+
+    // Prefer server-detected content type over client-claimed MIME
+    let content_type = detected_type.or(meta.content_type);
+    let ct = content_type.as_deref().unwrap_or_default();
+
+    // Identical content is stored once: uploading it again only takes
+    // another reference to the existing blob. New content is claimed before
+    // it is stored, and a key that is still being stored or deleted is
+    // waited for rather than overwritten.
+    let key = storage::blob_key(&checksum);
+    let waiting_since = Instant::now();
+    let blob = loop {
+        if let Some(blob) = file_repo::acquire_blob(&state.db, &checksum).await? {
+            let _ = tokio::fs::remove_file(partial).await;
+            break blob;
+        }
+        // Fail before storing anything new
+        check_quota(state, user_id, meta.channel_id, size_bytes, size_bytes).await?;
+        match file_repo::claim_blob(&state.db, &key, &checksum, size_bytes).await? {
+            Some(blob) if blob.stored => {
+                let _ = tokio::fs::remove_file(partial).await;
+                break blob;
+            }
+            Some(_) => {
+                let stored = store_blob(state, partial, &key, ct, size_bytes).await;
+                if stored.is_err() {
+                    let _ = release_blob(&state.db, state.storage.as_ref(), &key).await;
+                }
+                break stored?;
+            }
+            None if waiting_since.elapsed() < CLAIM_WAIT => tokio::time::sleep(CLAIM_POLL).await,
+            None => {
+                return Err(AppError::Conflict(
+                    "an identical upload is still being processed — please try again".to_string(),
+                ));
+            }
+        }
+    };
+
+    let record = async {
+        check_blocked_image(state, &blob).await?;
+        record_file(
+            state,
+            user_id,
+            file_id,
+            &meta.encrypted_name,
+            content_type.as_deref(),
+            meta.channel_id,
+            &blob,
+        )
+        .await
+    }
+    .await;
+    if record.is_err() {
+        let _ = release_blob(&state.db, state.storage.as_ref(), &blob.storage_path).await;
+    }
+    let record = record?;
+    if let Some(signature) = detection {
+        malware_scan::quarantine_upload(state, &record, &signature).await?;
+    }
+    Ok(record)
+}
+
+/// Security: reject near-copies of blocked images, which the checksum check
+/// misses once an image was re-encoded or edited.
+async fn check_blocked_image(state: &AppState, blob: &FileBlob) -> Result<(), AppError> {
+    let Some(phash) = blob.phash.as_deref() else {
+        return Ok(());
+    };
+    if let Some(entry) = blocklist::blocked_image(&state.db, phash).await? {
+        tracing::info!("Rejected upload matching blocked image hash {}", entry.id);
+        return Err(AppError::Validation(
+            "this file has been blocked by an administrator".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Store new content under the blob key claimed for it and mark the blob
+/// stored. Images are re-encoded (EXIF stripping, SVG sanitizing) and
+/// thumbnailed, which needs the whole file; anything else is moved as-is.
+/// Video, audio and PDFs are previewed from the local file before the move.
+async fn store_blob(
+    state: &AppState,
+    partial: &Path,
+    key: &str,
+    ct: &str,
+    size_bytes: i64,
+) -> Result<FileBlob, AppError> {
+    let strip = EXIF_TYPES.contains(&ct);
+    let svg = ct == "image/svg+xml";
+    let thumbnail = strip || matches!(ct, "image/gif" | "image/bmp");
+
+    // Non-fatal — the upload still succeeds without a preview
+    let media = match thumbnail_service::generate_media_preview(&state.config.previews, partial, ct)
+        .await
+    {
+        Ok(preview) => preview.unwrap_or_default(),
+        Err(e) => {
+            tracing::warn!("preview generation failed for {key}: {e}");
+            MediaPreview::default()
+        }
+    };
+
+    let mut stored_size = size_bytes;
+    let mut data = None;
+    if strip || svg || thumbnail {
+        let mut bytes = tokio::fs::read(partial)
+            .await
+            .map_err(|e| AppError::Internal(format!("read upload: {e}")))?;
+
+        // Security: strip EXIF metadata and sanitize SVGs before storing
+        if strip {
+            // Strip EXIF from images (removes GPS coordinates, camera info, etc.)
+            match thumbnail_service::strip_exif(&bytes, ct).await {
+                Some(clean) => bytes = clean,
+                None => {
+                    tracing::warn!("EXIF stripping failed for upload, rejecting");
+                    return Err(AppError::Validation(
+                        "failed to process image metadata — please try a different file"
+                            .to_string(),
+                    ));
+                }
+            }
+        }
+        // Sanitize SVG files (strip scripts, event handlers, etc.)
+        if svg {
+            match file_security::sanitize_svg(&bytes) {
+                Ok(clean) => bytes = clean,
+                Err(reason) => {
+                    return Err(AppError::Validation(format!("invalid SVG: {reason}")));
+                }
+            }
+        }
+
+        // Update size after potential re-encoding
+        stored_size = bytes.len() as i64;
+        let bytes = Bytes::from(bytes);
+        state
+            .storage
+            .put(key, bytes.clone())
+            .await
+            .map_err(|e| AppError::Internal(format!("store file: {e}")))?;
+        let _ = tokio::fs::remove_file(partial).await;
+        data = Some(bytes);
+    } else {
+        state
+            .storage
+            .put_file(key, partial)
+            .await
+            .map_err(|e| AppError::Internal(format!("store file: {e}")))?;
+    }
+
+    // Generate thumbnail, variants and placeholder for image files
+    let mut preview = store_media_preview(state.storage.as_ref(), key, media).await;
+    if let Some(data) = data.as_deref()
+        && thumbnail
+    {
+        match thumbnail_service::generate_image_preview(data, ct).await {
+            Ok(Some(image)) => {
+                preview = store_image_preview(state.storage.as_ref(), key, image).await;
+            }
+            Ok(None) => {} // not an image type
+            Err(e) => {
+                tracing::warn!("thumbnail generation failed for {key}: {e}");
+                // Non-fatal — upload still succeeds without thumbnail
+            }
+        }
+    }
+
+    let preview = preview.as_blob_preview();
+    Ok(file_repo::finish_blob(&state.db, key, stored_size, &preview).await?)
+}
+
+/// Previews stored next to a blob, to be recorded with it.
+#[derive(Debug, Default)]
+pub struct StoredPreview {
+    pub thumbnail_path: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub blurhash: Option<String>,
+    pub variants: Option<String>,
+    pub phash: Option<String>,
+}
+
+impl StoredPreview {
+    pub fn as_blob_preview(&self) -> BlobPreview<'_> {
+        BlobPreview {
+            thumbnail_path: self.thumbnail_path.as_deref(),
+            duration_ms: self.duration_ms,
+            width: self.width,
+            height: self.height,
+            blurhash: self.blurhash.as_deref(),
+            variants: self.variants.as_deref(),
+            phash: self.phash.as_deref(),
+        }
+    }
+}
+
+/// Store an image's thumbnail and WebP variants next to the blob at `key`.
+/// A part that fails to store is logged and left out.
+pub async fn store_image_preview(
+    storage: &dyn Storage,
+    key: &str,
+    image: ImagePreview,
+) -> StoredPreview {
+    let mut stored = StoredPreview {
+        width: i32::try_from(image.width).ok(),
+        height: i32::try_from(image.height).ok(),
+        blurhash: image.blurhash,
+        phash: Some(thumbnail_service::format_phash(image.dhash)),
+        ..Default::default()
+    };
+    let thumb_key = storage::thumbnail_key(key);
+    match storage.put(&thumb_key, image.thumbnail.into()).await {
+        Ok(()) => stored.thumbnail_path = Some(thumb_key),
+        Err(e) => tracing::warn!("storing thumbnail for {key} failed: {e}"),
+    }
+    let mut sizes = Vec::with_capacity(image.variants.len());
+    for (size, webp) in image.variants {
+        match storage
+            .put(&storage::variant_key(key, size), webp.into())
+            .await
+        {
+            Ok(()) => sizes.push(size),
+            Err(e) => tracing::warn!("storing {size}px variant of {key} failed: {e}"),
+        }
+    }
+    if !sizes.is_empty() {
+        stored.variants = Some(thumbnail_service::format_variants(&sizes));
+    }
+    stored
+}
+
+/// Store the poster frame, waveform or page of a video, audio or PDF file
+/// next to the blob at `key`.
+pub async fn store_media_preview(
+    storage: &dyn Storage,
+    key: &str,
+    media: MediaPreview,
+) -> StoredPreview {
+    let mut stored = StoredPreview {
+        duration_ms: media.duration_ms,
+        ..Default::default()
+    };
+    if let Some(thumb) = media.thumbnail {
+        let thumb_key = storage::thumbnail_key(key);
+        match storage.put(&thumb_key, thumb.into()).await {
+            Ok(()) => stored.thumbnail_path = Some(thumb_key),
+            Err(e) => tracing::warn!("storing preview for {key} failed: {e}"),
+        }
+    }
+    stored
+}
+
+/// Every storage key of a blob: its bytes, thumbnail and image variants.
+pub fn stored_keys(blob: &FileBlob) -> Vec<String> {
+    let variants = thumbnail_service::parse_variants(blob.variants.as_deref());
+    std::iter::once(blob.storage_path.clone())
+        .chain(blob.thumbnail_path.clone())
+        .chain(
+            variants
+                .into_iter()
+                .map(|size| storage::variant_key(&blob.storage_path, size)),
+        )
+        .collect()
+}
+
+/// Insert the `files` row for an upload stored in `blob` and charge the
+/// uploader's quota. Quota is charged once per user and blob, so a user
+/// posting the same file again pays nothing.
+async fn record_file(
+    state: &AppState,
+    user_id: Uuid,
+    file_id: Uuid,
+    encrypted_name: &str,
+    content_type: Option<&str>,
+    channel_id: Option<Uuid>,
+    blob: &FileBlob,
+) -> Result<FileRecord, AppError> {
+    let charge =
+        if file_repo::user_has_blob(&state.db, user_id, &blob.storage_path, file_id).await? {
+            0
+        } else {
+            blob.size_bytes
+        };
+    check_quota(state, user_id, channel_id, blob.size_bytes, charge).await?;
+
+    // Images of these types are always stripped (or rejected) before storing
+    let exif_stripped = content_type.is_some_and(|ct| EXIF_TYPES.contains(&ct));
+    let record = file_repo::create_file(
+        &state.db,
+        file_id,
+        user_id,
+        encrypted_name,
+        blob.size_bytes,
+        content_type,
+        &blob.storage_path,
+        &blob.checksum,
+        channel_id,
+        &BlobPreview::of(blob),
+        exif_stripped,
+    )
+    .await?;
+
+    user_repo::increment_upload_bytes(&state.db, user_id, charge).await?;
+    METRICS.uploaded(blob.size_bytes as u64);
+
+    Ok(record)
+}
+
+/// Delete a file: remove its row, return its quota to the uploader unless
+/// they have another file with the same content, and delete the blob once
+/// no file refers to it. Returns `false` if the file was already gone.
+pub async fn delete_file(
+    db: &Db,
+    storage: &dyn Storage,
+    file: &FileRecord,
+) -> Result<bool, sqlx::Error> {
+    if !file_repo::delete_file(db, file.id).await? {
+        return Ok(false);
+    }
+    if !file_repo::user_has_blob(db, file.uploader_id, &file.storage_path, file.id).await? {
+        user_repo::decrement_upload_bytes(db, file.uploader_id, file.size_bytes).await?;
+    }
+    release_blob(db, storage, &file.storage_path).await?;
+    Ok(true)
+}
+
+/// Drop one reference to the blob at `key`, deleting the stored bytes and
+/// thumbnail if it was the last. The record is deleted only after the bytes
+/// are; if that fails the tombstone is left for the orphan cleanup job.
+/// Returns whether the blob was deleted.
+async fn release_blob(db: &Db, storage: &dyn Storage, key: &str) -> Result<bool, sqlx::Error> {
+    let Some(blob) = file_repo::release_blob(db, key).await? else {
+        return Ok(false);
+    };
+    let mut deleted = true;
+    for path in stored_keys(&blob) {
+        if let Err(e) = storage.delete(&path).await {
+            tracing::warn!("Failed to delete blob {path} from storage: {e}");
+            deleted = false;
+        }
+    }
+    if deleted {
+        file_repo::delete_blob_record(db, key).await?;
+    }
+    Ok(deleted)
+}
+
+/// SHA-256 of the file, plus its first [`SNIFF_LEN`] bytes for type detection.
+async fn hash_file(path: &Path) -> std::io::Result<(String, Vec<u8>)> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut head = Vec::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        if head.len() < SNIFF_LEN {
+            let take = n.min(SNIFF_LEN - head.len());
+            head.extend_from_slice(&buf[..take]);
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok((hex::encode(hasher.finalize()), head))
+}
+
+/// Delete resumable uploads that expired. Their partial files are on the
+/// disk of the node that received them; [`sweep_partials`] removes them there.
+/// Returns the number of uploads deleted.
+pub async fn expire_uploads(state: &AppState) -> anyhow::Result<usize> {
+    Ok(upload_repo::delete_expired(&state.db).await?.len())
+}
+
+/// Delete partial files on this node that no upload row tracks any more
+/// (expired, or their user was deleted). Returns the number removed.
+pub async fn sweep_partials(state: &AppState) -> anyhow::Result<u64> {
+    let storage_root = &state.config.file_storage_path;
+    let mut removed = 0u64;
+    let mut entries = match tokio::fs::read_dir(Path::new(storage_root).join(PARTIAL_DIR)).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(removed),
+        Err(e) => return Err(e.into()),
+    };
+    let known: std::collections::HashSet<Uuid> = upload_repo::list_upload_ids(&state.db)
+        .await?
+        .into_iter()
+        .collect();
+    // Multipart uploads also pass through here without a row; leave anything
+    // recent alone so in-flight requests are not cut off.
+    let cutoff = SystemTime::now() - UPLOAD_EXPIRY;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let tracked = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| Uuid::parse_str(name).ok())
+            .is_some_and(|id| known.contains(&id));
+        let stale = entry
+            .metadata()
+            .await
+            .and_then(|m| m.modified())
+            .is_ok_and(|t| t < cutoff);
+        if !tracked && stale && tokio::fs::remove_file(entry.path()).await.is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
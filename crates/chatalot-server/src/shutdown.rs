@@ -0,0 +1,57 @@
+//! Graceful shutdown helpers. `AppState::shutdown` flips to true on SIGTERM or
+//! Ctrl+C; background jobs and realtime sessions watch it to wind down.
+
+use rand::Rng;
+use tokio::sync::watch;
+
+/// Clients are told to wait somewhere in this window before reconnecting,
+/// so a restart doesn't bring every client back at the same instant.
+const RECONNECT_BASE_MS: u64 = 1_000;
+const RECONNECT_JITTER_MS: u64 = 15_000;
+
+/// Resolves when the process receives Ctrl+C or SIGTERM.
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutdown signal received");
+}
+
+/// Sleep unless shutdown begins first. Returns false on shutdown.
+pub async fn sleep(duration: std::time::Duration, shutdown: &mut watch::Receiver<bool>) -> bool {
+    tokio::select! {
+        biased;
+        _ = wait(shutdown) => false,
+        _ = tokio::time::sleep(duration) => true,
+    }
+}
+
+/// Resolves once shutdown has begun.
+pub async fn wait(shutdown: &mut watch::Receiver<bool>) {
+    // An error means the sender is gone, which only happens at exit anyway
+    let _ = shutdown.wait_for(|s| *s).await;
+}
+
+/// Randomized reconnect delay for `ServerShuttingDown`.
+pub fn reconnect_after_ms() -> u64 {
+    RECONNECT_BASE_MS + rand::thread_rng().gen_range(0..=RECONNECT_JITTER_MS)
+}
@@ -0,0 +1,241 @@
+use std::io;
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+use bytes::Bytes;
+use futures_util::StreamExt;
+use futures_util::future::BoxFuture;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::{ByteStream, ObjectInfo, Storage, object_key};
+
+/// Blobs as files under `FILE_STORAGE_PATH`, one directory level per `/` in the key.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Where `key` lives on disk. Absolute paths outside the root (rows
+    /// restored from an older layout) are used as they are.
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        let key = object_key(&self.root, key);
+        let rel = Path::new(key.as_ref());
+        if rel.is_absolute() {
+            return Ok(rel.to_path_buf());
+        }
+        if rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid storage key '{key}'"),
+            ));
+        }
+        Ok(self.root.join(rel))
+    }
+
+    /// Path for a new blob, with its parent directory created.
+    async fn create_path(&self, key: &str) -> io::Result<PathBuf> {
+        let path = self.path(key)?;
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        Ok(path)
+    }
+
+    async fn write_stream(&self, key: &str, mut body: ByteStream) -> io::Result<()> {
+        let path = self.create_path(key).await?;
+        let mut f = tokio::fs::File::create(&path).await?;
+        while let Some(chunk) = body.next().await {
+            f.write_all(&chunk?).await?;
+        }
+        f.flush().await
+    }
+
+    async fn move_file(&self, key: &str, src: &Path) -> io::Result<()> {
+        let path = self.create_path(key).await?;
+        match tokio::fs::rename(src, &path).await {
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                tokio::fs::copy(src, &path).await?;
+                tokio::fs::remove_file(src).await
+            }
+            other => other,
+        }
+    }
+
+    async fn open(&self, key: &str) -> io::Result<ByteStream> {
+        let file = tokio::fs::File::open(self.path(key)?).await?;
+        Ok(tokio_util::io::ReaderStream::new(file).boxed())
+    }
+
+    async fn open_range(&self, key: &str, range: Range<u64>) -> io::Result<ByteStream> {
+        let mut file = tokio::fs::File::open(self.path(key)?).await?;
+        file.seek(SeekFrom::Start(range.start)).await?;
+        let part = file.take(range.end - range.start);
+        Ok(tokio_util::io::ReaderStream::new(part).boxed())
+    }
+
+    async fn stat(&self, key: &str) -> io::Result<Option<u64>> {
+        match tokio::fs::metadata(self.path(key)?).await {
+            Ok(m) => Ok(Some(m.len())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn remove(&self, key: &str) -> io::Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    async fn walk(&self, prefix: &str) -> io::Result<Vec<ObjectInfo>> {
+        // Start from the deepest directory the prefix names in full.
+        let start = match prefix.rfind('/') {
+            Some(i) => self.path(&prefix[..i])?,
+            None => self.root.clone(),
+        };
+        let mut objects = Vec::new();
+        let mut stack = vec![start];
+        while let Some(dir) = stack.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let meta = entry.metadata().await?;
+                if meta.is_dir() {
+                    stack.push(entry.path());
+                    continue;
+                }
+                let Ok(rel) = entry.path().strip_prefix(&self.root).map(Path::to_path_buf) else {
+                    continue;
+                };
+                let key = rel
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                if key.starts_with(prefix) {
+                    objects.push(ObjectInfo {
+                        key,
+                        size: meta.len(),
+                        modified: meta.modified().ok(),
+                    });
+                }
+            }
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+}
+
+impl Storage for LocalStorage {
+    fn kind(&self) -> &'static str {
+        "local"
+    }
+
+    fn put<'a>(&'a self, key: &'a str, data: Bytes) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(self.write_stream(key, super::once(data)))
+    }
+
+    fn put_stream<'a>(
+        &'a self,
+        key: &'a str,
+        body: ByteStream,
+        _len: u64,
+    ) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(self.write_stream(key, body))
+    }
+
+    fn put_file<'a>(&'a self, key: &'a str, path: &'a Path) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(self.move_file(key, path))
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<ByteStream>> {
+        Box::pin(self.open(key))
+    }
+
+    fn get_range<'a>(
+        &'a self,
+        key: &'a str,
+        range: Range<u64>,
+    ) -> BoxFuture<'a, io::Result<ByteStream>> {
+        Box::pin(self.open_range(key, range))
+    }
+
+    fn size<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<Option<u64>>> {
+        Box::pin(self.stat(key))
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(self.remove(key))
+    }
+
+    fn list<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, io::Result<Vec<ObjectInfo>>> {
+        Box::pin(self.walk(prefix))
+    }
+
+    fn read<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<Vec<u8>>> {
+        Box::pin(async move { tokio::fs::read(self.path(key)?).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn keys_map_to_paths_under_the_root() {
+        let root = std::env::temp_dir().join(format!("chatalot-storage-{}", uuid::Uuid::new_v4()));
+        let storage = LocalStorage::new(&root);
+
+        storage
+            .put("ab/abc", Bytes::from_static(b"blob"))
+            .await
+            .unwrap();
+        storage
+            .put("emojis/x.png", Bytes::from_static(b"png"))
+            .await
+            .unwrap();
+        assert_eq!(tokio::fs::read(root.join("ab/abc")).await.unwrap(), b"blob");
+
+        // Legacy absolute paths resolve to the same blob
+        let legacy = root.join("ab/abc").to_string_lossy().into_owned();
+        assert_eq!(storage.read(&legacy).await.unwrap(), b"blob");
+
+        let part = storage.get_range("ab/abc", 1..3).await.unwrap();
+        let part: Vec<Bytes> = part.try_collect().await.unwrap();
+        assert_eq!(part.concat(), b"lo");
+
+        let keys: Vec<_> = storage
+            .list("")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|o| o.key)
+            .collect();
+        assert_eq!(keys, ["ab/abc", "emojis/x.png"]);
+        let emojis = storage.list("emojis/").await.unwrap();
+        assert_eq!(emojis.len(), 1);
+        assert_eq!(emojis[0].size, 3);
+
+        assert!(storage.put("../escape", Bytes::new()).await.is_err());
+        storage.delete("ab/abc").await.unwrap();
+        storage.delete("ab/abc").await.unwrap();
+        assert_eq!(storage.size("ab/abc").await.unwrap(), None);
+        assert_eq!(
+            storage.get("ab/abc").await.err().map(|e| e.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+
+        let _ = tokio::fs::remove_dir_all(&root).await;
+    }
+}
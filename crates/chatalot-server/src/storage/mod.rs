@@ -0,0 +1,182 @@
+//! Blob storage for uploads, thumbnails, avatars, community/group assets and
+//! custom emojis.
+//!
+//! Blobs are addressed by keys relative to the storage root, e.g.
+//! `ab/ab12…` for a file and `avatars/<user>.png` for an avatar. Rows written
+//! before keys were introduced hold absolute paths under `FILE_STORAGE_PATH`;
+//! every backend maps those to the same key (see [`object_key`]), and
+//! `chatalot-admin storage migrate` rewrites them.
+//!
+//! Partially received uploads always stay on local disk under
+//! `FILE_STORAGE_PATH/uploads` and are moved into a backend once complete.
+
+mod local;
+mod s3;
+
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::bail;
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, TryStreamExt};
+
+use crate::config::Config;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+/// Key prefixes of blobs that are not tracked in `files`.
+pub const AVATARS: &str = "avatars";
+pub const COMMUNITY_ASSETS: &str = "community_assets";
+pub const GROUP_ASSETS: &str = "group_assets";
+pub const EMOJIS: &str = "emojis";
+
+/// The contents of a blob, read as it is needed.
+pub type ByteStream = BoxStream<'static, io::Result<Bytes>>;
+
+/// One blob returned by [`Storage::list`].
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Response headers a presigned download should carry.
+pub struct Presign<'a> {
+    pub content_type: &'a str,
+    pub content_disposition: &'a str,
+}
+
+/// A place to keep blobs. Missing blobs are reported as
+/// [`io::ErrorKind::NotFound`]; every other failure is an I/O error too.
+pub trait Storage: Send + Sync {
+    /// Backend name for logs and the admin CLI (`local`, `s3`).
+    fn kind(&self) -> &'static str;
+
+    fn put<'a>(&'a self, key: &'a str, data: Bytes) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Store `len` bytes read from `body`.
+    fn put_stream<'a>(
+        &'a self,
+        key: &'a str,
+        body: ByteStream,
+        len: u64,
+    ) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Move a local file into storage. The file is gone afterwards.
+    fn put_file<'a>(&'a self, key: &'a str, path: &'a Path) -> BoxFuture<'a, io::Result<()>>;
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<ByteStream>>;
+
+    /// The bytes of the blob in `range`, which must lie within the blob.
+    fn get_range<'a>(
+        &'a self,
+        key: &'a str,
+        range: Range<u64>,
+    ) -> BoxFuture<'a, io::Result<ByteStream>>;
+
+    /// Size of the blob, or `None` if it does not exist.
+    fn size<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<Option<u64>>>;
+
+    /// Delete a blob. Deleting a missing blob is not an error.
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Every blob whose key starts with `prefix`.
+    fn list<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, io::Result<Vec<ObjectInfo>>>;
+
+    /// A URL the client can download the blob from directly, if the backend
+    /// supports it and presigned downloads are enabled.
+    fn presigned_url(&self, _key: &str, _presign: &Presign<'_>) -> Option<String> {
+        None
+    }
+
+    /// Read a whole blob into memory.
+    fn read<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<Vec<u8>>> {
+        Box::pin(async move {
+            self.get(key)
+                .await?
+                .try_fold(Vec::new(), |mut buf, chunk| async move {
+                    buf.extend_from_slice(&chunk);
+                    Ok(buf)
+                })
+                .await
+        })
+    }
+}
+
+/// The backend selected by `STORAGE_BACKEND`.
+pub fn from_config(config: &Config) -> anyhow::Result<Arc<dyn Storage>> {
+    open(&config.storage_backend, config)
+}
+
+/// Open the named backend (`local` or `s3`) with the settings in `config`.
+pub fn open(backend: &str, config: &Config) -> anyhow::Result<Arc<dyn Storage>> {
+    match backend {
+        "local" => Ok(Arc::new(LocalStorage::new(&config.file_storage_path))),
+        "s3" => {
+            let Some(ref s3) = config.s3 else {
+                bail!("S3 storage needs S3_BUCKET, S3_ACCESS_KEY_ID and S3_SECRET_ACCESS_KEY");
+            };
+            Ok(Arc::new(S3Storage::new(s3, &config.file_storage_path)?))
+        }
+        other => bail!("unknown storage backend '{other}' (expected local or s3)"),
+    }
+}
+
+/// The key for a stored path: absolute paths under the local storage root
+/// lose the root, anything else is already a key.
+pub fn object_key<'a>(storage_root: &Path, stored: &'a str) -> std::borrow::Cow<'a, str> {
+    match Path::new(stored).strip_prefix(storage_root) {
+        Ok(rel) => rel.to_string_lossy().into_owned().into(),
+        Err(_) => stored.into(),
+    }
+}
+
+/// Key of the blob holding uploads with this SHA-256 checksum: sharded by
+/// its first two hex digits. Files uploaded before deduplication are keyed
+/// by their ID the same way.
+pub fn blob_key(checksum: &str) -> String {
+    format!("{}/{checksum}", &checksum[..2])
+}
+
+/// Key of the thumbnail stored next to the blob at `key`.
+pub fn thumbnail_key(key: &str) -> String {
+    format!("{key}_thumb")
+}
+
+/// Key of the WebP variant of the image at `key` that is `size` pixels on
+/// its longer side.
+pub fn variant_key(key: &str, size: u32) -> String {
+    format!("{key}_w{size}")
+}
+
+/// A single-chunk stream, for handing in-memory data to [`Storage::put_stream`].
+pub fn once(data: Bytes) -> ByteStream {
+    futures_util::stream::once(async move { Ok(data) }).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_paths_map_to_keys() {
+        let root = Path::new("/var/lib/chatalot/files");
+        assert_eq!(object_key(root, "/var/lib/chatalot/files/ab/abc"), "ab/abc");
+        assert_eq!(object_key(root, "ab/abc"), "ab/abc");
+        assert_eq!(object_key(root, "/elsewhere/ab/abc"), "/elsewhere/ab/abc");
+
+        let relative = Path::new("./data/files");
+        assert_eq!(
+            object_key(relative, "./data/files/emojis/x.png"),
+            "emojis/x.png"
+        );
+        assert_eq!(object_key(relative, "emojis/x.png"), "emojis/x.png");
+    }
+}
@@ -1,5 +1,6 @@
 use dashmap::DashMap;
 use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use chatalot_common::ws_messages::ServerMessage;
@@ -10,6 +11,8 @@ pub struct SessionHandle {
     pub session_id: Uuid,
     pub user_id: Uuid,
     pub tx: mpsc::UnboundedSender<ServerMessage>,
+    /// Cancelled to make the transport close the session.
+    pub kicked: CancellationToken,
 }
 
 /// Manages all active WebSocket connections and channel subscriptions.
@@ -29,6 +32,12 @@ pub struct ConnectionManager {
 /// Maximum concurrent WebSocket sessions per user (multi-device support).
 const MAX_SESSIONS_PER_USER: usize = 8;
 
+impl Default for ConnectionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ConnectionManager {
     pub fn new() -> Self {
         Self {
@@ -93,6 +102,26 @@ impl ConnectionManager {
         self.connections.contains_key(user_id)
     }
 
+    /// Number of users with at least one open session.
+    pub fn user_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Number of open sessions across all users.
+    pub fn session_count(&self) -> usize {
+        self.connections.iter().map(|e| e.value().len()).sum()
+    }
+
+    /// Close every session of a user (e.g. after a suspension). Messages
+    /// already queued, such as a notice telling them why, are still sent.
+    pub fn disconnect_user(&self, user_id: &Uuid) {
+        if let Some(sessions) = self.connections.get(user_id) {
+            for session in sessions.iter() {
+                session.kicked.cancel();
+            }
+        }
+    }
+
     /// Send a message directly to all sessions of a specific user.
     pub fn send_to_user(&self, user_id: &Uuid, message: &ServerMessage) {
         if let Some(sessions) = self.connections.get(user_id) {
@@ -1,257 +1,366 @@
 use std::sync::Arc;
 
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{CloseFrame, Message, WebSocket, close_code};
 use futures_util::{SinkExt, StreamExt};
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use chatalot_common::ws_messages::{ClientMessage, MessageType, ServerMessage};
 use chatalot_db::models::channel::ChannelType;
 use chatalot_db::repos::{
-    block_repo, channel_repo, community_repo, message_repo, reaction_repo, timeout_repo,
-    unread_repo, user_repo, voice_repo,
+    block_repo, channel_repo, community_repo, delivery_repo, mention_repo, message_repo,
+    notification_repo, quiet_hours_repo, reaction_repo, timeout_repo, unread_repo, user_repo,
+    voice_repo,
 };
 
+use crate::metrics::METRICS;
 use crate::permissions;
+use crate::services::{push_service, quiet_hours};
 
 use crate::app_state::AppState;
 use crate::ws::connection_manager::SessionHandle;
 
-/// Handle an authenticated WebSocket connection.
-pub async fn handle_socket(
-    socket: WebSocket,
-    user_id: Uuid,
+/// Maximum incoming client message size (1 MB)
+const MAX_CLIENT_MESSAGE_SIZE: usize = 1_048_576;
+
+// Per-session message rate limiter (token bucket).
+// Burst of 30 accommodates WebRTC signaling (offer + ~15 ICE candidates per peer).
+const RATE_LIMIT_BURST: f64 = 30.0;
+const RATE_LIMIT_REFILL: f64 = 10.0;
+
+/// Transport-independent state of one realtime session. The WebSocket and the
+/// SSE + HTTP POST fallback both feed client messages through this and receive
+/// server messages from its `tx` channel.
+pub struct ClientSession {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
     is_instance_owner: bool,
     is_instance_admin: bool,
-    state: Arc<AppState>,
-) {
-    let conn_mgr = &state.connections;
-    let session_id = Uuid::new_v4();
-    let (mut ws_sink, mut ws_stream) = socket.split();
+    tx: mpsc::UnboundedSender<ServerMessage>,
+    kicked: CancellationToken,
+    heartbeat_task: tokio::task::JoinHandle<()>,
+    // Track spawned subscription tasks per channel so we can abort them on disconnect.
+    // Using a HashMap prevents duplicate subscriptions to the same channel.
+    subscription_tasks: std::collections::HashMap<Uuid, tokio::task::JoinHandle<()>>,
+    tokens: f64,
+    last_refill: tokio::time::Instant,
+}
 
-    // Channel for sending messages to this client
-    let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
+impl ClientSession {
+    /// Register a session for an authenticated user and announce their presence.
+    /// Returns None (after telling the client why) if the user has too many sessions.
+    pub async fn open(
+        state: &Arc<AppState>,
+        user_id: Uuid,
+        is_instance_owner: bool,
+        is_instance_admin: bool,
+        tx: mpsc::UnboundedSender<ServerMessage>,
+    ) -> Option<Self> {
+        let conn_mgr = &state.connections;
+        let session_id = Uuid::new_v4();
+        let kicked = CancellationToken::new();
+
+        // Register the session (enforces per-user connection limit)
+        let handle = SessionHandle {
+            session_id,
+            user_id,
+            tx: tx.clone(),
+            kicked: kicked.clone(),
+        };
+        if !conn_mgr.add_session(handle) {
+            tracing::warn!(%user_id, "Realtime session rejected: too many concurrent sessions");
+            let _ = tx.send(ServerMessage::Error {
+                code: "too_many_sessions".to_string(),
+                message: "too many concurrent connections, close another device first".to_string(),
+            });
+            return None;
+        }
 
-    // Register the session (enforces per-user connection limit)
-    let handle = SessionHandle {
-        session_id,
-        user_id,
-        tx: tx.clone(),
-    };
-    if !conn_mgr.add_session(handle) {
-        tracing::warn!(%user_id, "WebSocket rejected: too many concurrent sessions");
-        let _ = tx.send(ServerMessage::Error {
-            code: "too_many_sessions".to_string(),
-            message: "too many concurrent connections, close another device first".to_string(),
+        // Broadcast presence: this user is online (or DND, if chosen or scheduled)
+        let initial_status = match quiet_hours_repo::is_dnd(&state.db, user_id).await {
+            Ok(true) => "dnd",
+            _ => "online",
+        };
+        broadcast_presence(&state.db, conn_mgr, user_id, initial_status).await;
+
+        // Send initial presence state: which community mates are currently online
+        if let Ok(mates) = community_repo::get_community_mates(&state.db, user_id).await {
+            let statuses: Vec<_> = mates
+                .into_iter()
+                .filter(|uid| conn_mgr.is_online(uid))
+                .map(|uid| (uid, chatalot_common::ws_messages::PresenceStatus::Online))
+                .collect();
+            if !statuses.is_empty() {
+                let _ = tx.send(ServerMessage::PresenceBulk { statuses });
+            }
+        }
+
+        // Heartbeat: server sends ping every 15 seconds (keeps proxies/tunnels alive)
+        let heartbeat_tx = tx.clone();
+        let heartbeat_task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(15));
+            loop {
+                interval.tick().await;
+                let timestamp = chrono::Utc::now().timestamp();
+                if heartbeat_tx
+                    .send(ServerMessage::Pong { timestamp })
+                    .is_err()
+                {
+                    break;
+                }
+            }
         });
-        return;
+
+        Some(Self {
+            session_id,
+            user_id,
+            is_instance_owner,
+            is_instance_admin,
+            tx,
+            kicked,
+            heartbeat_task,
+            subscription_tasks: std::collections::HashMap::new(),
+            tokens: RATE_LIMIT_BURST,
+            last_refill: tokio::time::Instant::now(),
+        })
     }
 
-    // Broadcast presence: this user is online
-    broadcast_presence(&state.db, conn_mgr, user_id, "online").await;
+    /// Validate, rate-limit, parse and dispatch one raw client message.
+    pub async fn handle_text(&mut self, state: &AppState, text: &str) {
+        let user_id = self.user_id;
+
+        // Reject oversized messages
+        if text.len() > MAX_CLIENT_MESSAGE_SIZE {
+            let _ = self.tx.send(ServerMessage::Error {
+                code: "validation_error".to_string(),
+                message: "message too large".to_string(),
+            });
+            return;
+        }
 
-    // Send initial presence state: which community mates are currently online
-    if let Ok(mates) = community_repo::get_community_mates(&state.db, user_id).await {
-        let statuses: Vec<_> = mates
-            .into_iter()
-            .filter(|uid| conn_mgr.is_online(uid))
-            .map(|uid| {
-                (
-                    uid,
-                    chatalot_common::ws_messages::PresenceStatus::Online,
+        // Refill tokens
+        let now = tokio::time::Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * RATE_LIMIT_REFILL).min(RATE_LIMIT_BURST);
+        self.last_refill = now;
+
+        if self.tokens < 1.0 {
+            METRICS.rate_limited("ws_message");
+            let _ = self.tx.send(ServerMessage::Error {
+                code: "rate_limited".to_string(),
+                message: "too many messages, slow down".to_string(),
+            });
+            return;
+        }
+        self.tokens -= 1.0;
+
+        match serde_json::from_str::<ClientMessage>(text) {
+            Ok(client_msg) => {
+                handle_client_message(
+                    client_msg,
+                    user_id,
+                    self.is_instance_owner,
+                    self.is_instance_admin,
+                    state,
+                    &self.tx,
+                    &mut self.subscription_tasks,
                 )
-            })
-            .collect();
-        if !statuses.is_empty() {
-            let _ = tx.send(ServerMessage::PresenceBulk { statuses });
+                .await;
+            }
+            Err(e) => {
+                tracing::warn!(%user_id, error = %e, "malformed client message");
+                let _ = self.tx.send(ServerMessage::Error {
+                    code: "invalid_message".to_string(),
+                    message: "malformed message".to_string(),
+                });
+            }
         }
     }
 
-    tracing::info!(%user_id, %session_id, "WebSocket connected");
+    /// Cancelled when the server drops the session (e.g. the user was
+    /// suspended); the transport should then close it.
+    pub fn kicked(&self) -> CancellationToken {
+        self.kicked.clone()
+    }
 
-    // Writer task: forwards messages from the mpsc channel to the WebSocket
-    let write_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            match serde_json::to_string(&msg) {
-                Ok(text) => {
-                    if ws_sink.send(Message::Text(text.into())).await.is_err() {
-                        break;
-                    }
-                }
-                Err(e) => {
-                    tracing::error!(%user_id, "failed to serialize outgoing WS message: {e}");
-                }
-            }
+    /// Tell the client the server is going away and when to come back.
+    pub fn notify_shutdown(&self) {
+        let _ = self.tx.send(ServerMessage::ServerShuttingDown {
+            reconnect_after_ms: crate::shutdown::reconnect_after_ms(),
+        });
+    }
+
+    /// Unregister the session and clean up typing, voice and presence state.
+    pub async fn close(self, state: &Arc<AppState>) {
+        let conn_mgr = &state.connections;
+        let user_id = self.user_id;
+
+        conn_mgr.remove_session(user_id, self.session_id);
+        self.heartbeat_task.abort();
+
+        // Abort all channel subscription tasks
+        for task in self.subscription_tasks.values() {
+            task.abort();
         }
-    });
 
-    // Heartbeat: server sends ping every 15 seconds (keeps proxies/tunnels alive)
-    let heartbeat_tx = tx.clone();
-    let heartbeat_task = tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(15));
-        loop {
-            interval.tick().await;
-            let timestamp = chrono::Utc::now().timestamp();
-            if heartbeat_tx
-                .send(ServerMessage::Pong { timestamp })
-                .is_err()
-            {
-                break;
-            }
+        // Clean up typing state — broadcast stop-typing for all channels this user was typing in
+        let typing_channels = conn_mgr.clear_all_typing_for_user(user_id);
+        for channel_id in typing_channels {
+            conn_mgr.broadcast_to_channel(
+                channel_id,
+                ServerMessage::UserStoppedTyping {
+                    channel_id,
+                    user_id,
+                },
+            );
         }
-    });
 
-    // Track spawned subscription tasks per channel so we can abort them on disconnect.
-    // Using a HashMap prevents duplicate subscriptions to the same channel.
-    let mut subscription_tasks: std::collections::HashMap<uuid::Uuid, tokio::task::JoinHandle<()>> =
-        std::collections::HashMap::new();
-
-    // Maximum incoming WebSocket message size (1 MB)
-    const MAX_WS_MESSAGE_SIZE: usize = 1_048_576;
-
-    // Per-connection message rate limiter (token bucket).
-    // Burst of 30 accommodates WebRTC signaling (offer + ~15 ICE candidates per peer).
-    const RATE_LIMIT_BURST: f64 = 30.0;
-    const RATE_LIMIT_REFILL: f64 = 10.0;
-    let mut tokens: f64 = RATE_LIMIT_BURST;
-    let mut last_refill = tokio::time::Instant::now();
-
-    // Reader task: processes incoming WebSocket messages
-    while let Some(Ok(msg)) = ws_stream.next().await {
-        match msg {
-            Message::Text(text) => {
-                // Reject oversized messages
-                if text.len() > MAX_WS_MESSAGE_SIZE {
-                    let _ = tx.send(ServerMessage::Error {
-                        code: "validation_error".to_string(),
-                        message: "message too large".to_string(),
-                    });
-                    continue;
+        // Clean up voice sessions — delay cleanup to allow quick reconnects (e.g. tunnel flaps).
+        // Record the disconnect time so we only clean up sessions that were active BEFORE this
+        // disconnect. If the user reconnects and rejoins voice (getting a new joined_at), their
+        // new session is preserved even if this timer fires.
+        {
+            let state = Arc::clone(state);
+            let disconnect_time = chrono::Utc::now();
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(15)).await;
+
+                // If user reconnected in the meantime, skip voice cleanup entirely
+                if state.connections.is_online(&user_id) {
+                    tracing::debug!(%user_id, "Skipping voice cleanup — user reconnected");
+                    return;
                 }
 
-                // Refill tokens
-                let now = tokio::time::Instant::now();
-                let elapsed = now.duration_since(last_refill).as_secs_f64();
-                tokens = (tokens + elapsed * RATE_LIMIT_REFILL).min(RATE_LIMIT_BURST);
-                last_refill = now;
-
-                if tokens < 1.0 {
-                    let _ = tx.send(ServerMessage::Error {
-                        code: "rate_limited".to_string(),
-                        message: "too many messages, slow down".to_string(),
-                    });
-                    continue;
-                }
-                tokens -= 1.0;
+                // Only leave sessions the user joined before the disconnect.
+                // If they rejoined during the grace period (new joined_at > disconnect_time),
+                // those sessions are preserved.
+                match voice_repo::leave_sessions_joined_before(&state.db, user_id, disconnect_time)
+                    .await
+                {
+                    Ok(left_sessions) => {
+                        for (voice_session_id, channel_id) in &left_sessions {
+                            state.connections.broadcast_to_channel(
+                                *channel_id,
+                                ServerMessage::UserLeftVoice {
+                                    channel_id: *channel_id,
+                                    user_id,
+                                },
+                            );
 
-                match serde_json::from_str::<ClientMessage>(&text) {
-                    Ok(client_msg) => {
-                        handle_client_message(
-                            client_msg,
-                            user_id,
-                            is_instance_owner,
-                            is_instance_admin,
-                            &state,
-                            &tx,
-                            &mut subscription_tasks,
-                        )
-                        .await;
+                            if let Ok(participants) =
+                                voice_repo::get_participants(&state.db, *voice_session_id).await
+                            {
+                                state.connections.broadcast_to_channel(
+                                    *channel_id,
+                                    ServerMessage::VoiceStateUpdate {
+                                        channel_id: *channel_id,
+                                        participants: participants.clone(),
+                                    },
+                                );
+
+                                if participants.is_empty() {
+                                    let _ =
+                                        voice_repo::end_session(&state.db, *voice_session_id).await;
+                                }
+                            }
+                        }
                     }
                     Err(e) => {
-                        tracing::warn!(%user_id, error = %e, "malformed WebSocket message");
-                        let _ = tx.send(ServerMessage::Error {
-                            code: "invalid_message".to_string(),
-                            message: "malformed message".to_string(),
-                        });
+                        tracing::warn!(%user_id, "Failed to clean up voice sessions: {e}");
                     }
                 }
-            }
-            Message::Close(_) => break,
-            _ => {}
+            });
         }
-    }
 
-    // Cleanup
-    conn_mgr.remove_session(user_id, session_id);
-    write_task.abort();
-    heartbeat_task.abort();
-
-    // Abort all channel subscription tasks
-    for task in subscription_tasks.values() {
-        task.abort();
+        // If no more sessions for this user, broadcast offline (checked after grace period
+        // by the voice cleanup task above — also check immediately for non-voice users)
+        if !conn_mgr.is_online(&user_id) {
+            broadcast_presence(&state.db, conn_mgr, user_id, "offline").await;
+        }
     }
+}
 
-    // Clean up typing state — broadcast stop-typing for all channels this user was typing in
-    let typing_channels = conn_mgr.clear_all_typing_for_user(user_id);
-    for channel_id in typing_channels {
-        conn_mgr.broadcast_to_channel(
-            channel_id,
-            ServerMessage::UserStoppedTyping {
-                channel_id,
-                user_id,
-            },
-        );
-    }
+/// Handle an authenticated WebSocket connection.
+pub async fn handle_socket(
+    socket: WebSocket,
+    user_id: Uuid,
+    is_instance_owner: bool,
+    is_instance_admin: bool,
+    state: Arc<AppState>,
+) {
+    let (mut ws_sink, mut ws_stream) = socket.split();
 
-    // Clean up voice sessions — delay cleanup to allow quick reconnects (e.g. tunnel flaps).
-    // Record the disconnect time so we only clean up sessions that were active BEFORE this
-    // disconnect. If the user reconnects and rejoins voice (getting a new joined_at), their
-    // new session is preserved even if this timer fires.
-    {
-        let state = Arc::clone(&state);
-        let disconnect_time = chrono::Utc::now();
-        tokio::spawn(async move {
-            tokio::time::sleep(std::time::Duration::from_secs(15)).await;
-
-            // If user reconnected in the meantime, skip voice cleanup entirely
-            if state.connections.is_online(&user_id) {
-                tracing::debug!(%user_id, "Skipping voice cleanup — user reconnected");
-                return;
-            }
+    // Channel for sending messages to this client
+    let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
 
-            // Only leave sessions the user joined before the disconnect.
-            // If they rejoined during the grace period (new joined_at > disconnect_time),
-            // those sessions are preserved.
-            match voice_repo::leave_sessions_joined_before(&state.db, user_id, disconnect_time).await {
-                Ok(left_sessions) => {
-                    for (voice_session_id, channel_id) in &left_sessions {
-                        state.connections.broadcast_to_channel(
-                            *channel_id,
-                            ServerMessage::UserLeftVoice {
-                                channel_id: *channel_id,
-                                user_id,
-                            },
-                        );
+    let Some(mut session) =
+        ClientSession::open(&state, user_id, is_instance_owner, is_instance_admin, tx).await
+    else {
+        return;
+    };
+    let session_id = session.session_id;
 
-                        if let Ok(participants) =
-                            voice_repo::get_participants(&state.db, *voice_session_id).await
-                        {
-                            state.connections.broadcast_to_channel(
-                                *channel_id,
-                                ServerMessage::VoiceStateUpdate {
-                                    channel_id: *channel_id,
-                                    participants: participants.clone(),
-                                },
-                            );
+    tracing::info!(%user_id, %session_id, "WebSocket connected");
 
-                            if participants.is_empty() {
-                                let _ =
-                                    voice_repo::end_session(&state.db, *voice_session_id).await;
-                            }
-                        }
+    // Writer task: forwards messages from the mpsc channel to the WebSocket.
+    // Once every sender is gone (session closed), it hands the sink back so
+    // the socket can be closed with the right reason.
+    let mut write_task = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            match serde_json::to_string(&msg) {
+                Ok(text) => {
+                    if ws_sink.send(Message::Text(text.into())).await.is_err() {
+                        return None;
                     }
                 }
                 Err(e) => {
-                    tracing::warn!(%user_id, "Failed to clean up voice sessions: {e}");
+                    tracing::error!(%user_id, "failed to serialize outgoing WS message: {e}");
                 }
             }
-        });
-    }
+        }
+        Some(ws_sink)
+    });
+
+    // Reader loop: processes incoming WebSocket messages until the client
+    // leaves, the server drops the session, or the server starts shutting down
+    let mut shutdown = state.shutdown.subscribe();
+    let kicked = session.kicked();
+    let close_frame = loop {
+        tokio::select! {
+            msg = ws_stream.next() => match msg {
+                Some(Ok(Message::Text(text))) => session.handle_text(&state, &text).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break None,
+                Some(Ok(_)) => {}
+            },
+            _ = kicked.cancelled() => break Some(CloseFrame {
+                code: close_code::POLICY,
+                reason: "session ended by the server".into(),
+            }),
+            _ = crate::shutdown::wait(&mut shutdown) => {
+                session.notify_shutdown();
+                break Some(CloseFrame {
+                    code: close_code::RESTART,
+                    reason: "server shutting down".into(),
+                });
+            }
+        }
+    };
 
-    // If no more sessions for this user, broadcast offline (checked after grace period
-    // by the voice cleanup task above — also check immediately for non-voice users)
-    if !conn_mgr.is_online(&user_id) {
-        broadcast_presence(&state.db, conn_mgr, user_id, "offline").await;
+    // Cleanup
+    if let Some(frame) = close_frame {
+        session.close(&state).await;
+        // Let the writer flush pending notices, then send the close frame
+        match tokio::time::timeout(std::time::Duration::from_secs(5), &mut write_task).await {
+            Ok(Ok(Some(mut ws_sink))) => {
+                let _ = ws_sink.send(Message::Close(Some(frame))).await;
+            }
+            Ok(_) => {}
+            Err(_) => write_task.abort(),
+        }
+    } else {
+        write_task.abort();
+        session.close(&state).await;
     }
 
     tracing::info!(%user_id, %session_id, "WebSocket disconnected");
@@ -280,6 +389,7 @@ async fn handle_client_message(
             reply_to,
             sender_key_id,
             thread_id,
+            mentions,
         } => {
             // Reject empty or oversized ciphertext (64 KiB limit)
             const MAX_CIPHERTEXT_SIZE: usize = 65_536;
@@ -304,6 +414,30 @@ async fn handle_client_message(
                 return;
             }
 
+            // Validate declared mentions (membership is resolved when they are stored)
+            const MAX_MENTIONED_USERS: usize = 100;
+            const MENTIONABLE_ROLES: &[&str] = &["owner", "admin", "moderator", "member"];
+            let mentions = mentions.unwrap_or_default();
+            if mentions.user_ids.len() > MAX_MENTIONED_USERS {
+                let _ = tx.send(ServerMessage::Error {
+                    code: "validation_error".to_string(),
+                    message: format!("cannot mention more than {MAX_MENTIONED_USERS} users"),
+                });
+                return;
+            }
+            if mentions.roles.len() > MENTIONABLE_ROLES.len()
+                || mentions
+                    .roles
+                    .iter()
+                    .any(|r| !MENTIONABLE_ROLES.contains(&r.as_str()))
+            {
+                let _ = tx.send(ServerMessage::Error {
+                    code: "validation_error".to_string(),
+                    message: "invalid mentioned role".to_string(),
+                });
+                return;
+            }
+
             // Verify membership
             match channel_repo::is_member(&state.db, channel_id, user_id).await {
                 Ok(true) => {}
@@ -333,7 +467,7 @@ async fn handle_client_message(
             };
 
             // Check role for non-DM channels (admins/owners/instance roles exempt from slow mode, read-only)
-            let is_privileged = if channel.channel_type != ChannelType::Dm {
+            let (is_privileged, can_mass_mention) = if channel.channel_type != ChannelType::Dm {
                 let channel_role = match channel_repo::get_member_role(&state.db, channel_id, user_id).await {
                     Ok(r) => r,
                     Err(e) => {
@@ -342,11 +476,23 @@ async fn handle_client_message(
                     }
                 };
                 let effective = permissions::effective_role(channel_role.as_deref(), is_instance_owner, is_instance_admin);
-                permissions::can_delete_others_messages(&effective)
+                (
+                    permissions::can_delete_others_messages(&effective),
+                    permissions::can_mass_mention(&effective),
+                )
             } else {
-                is_instance_owner || is_instance_admin
+                // Role and @everyone mentions are meaningless in DMs
+                (is_instance_owner || is_instance_admin, false)
             };
 
+            if (mentions.everyone || !mentions.roles.is_empty()) && !can_mass_mention {
+                let _ = tx.send(ServerMessage::Error {
+                    code: "forbidden".to_string(),
+                    message: "you don't have permission to mention everyone or roles".to_string(),
+                });
+                return;
+            }
+
             if channel.channel_type != ChannelType::Dm {
                 if channel.archived {
                     let _ = tx.send(ServerMessage::Error {
@@ -575,6 +721,8 @@ async fn handle_client_message(
             .await
             {
                 Ok(stored) => {
+                    METRICS.message_sent();
+
                     // Clear typing indicator now that the message is sent
                     conn_mgr.clear_typing(channel_id, user_id);
                     conn_mgr.broadcast_to_channel(
@@ -606,6 +754,28 @@ async fn handle_client_message(
                         thread_id: resolved_thread_id,
                     };
 
+                    // Store declared mentions (resolved against channel membership)
+                    let mentioned = match mention_repo::record_mentions(
+                        &state.db,
+                        message_id,
+                        channel_id,
+                        user_id,
+                        &mentions.user_ids,
+                        &mentions.roles,
+                        mentions.everyone,
+                    )
+                    .await
+                    {
+                        Ok(m) => m,
+                        Err(e) => {
+                            tracing::warn!(%message_id, "Failed to record mentions: {e}");
+                            Vec::new()
+                        }
+                    };
+
+                    // Offline users who may get a push (filtered by their settings later)
+                    let mut push_candidates: Vec<Uuid> = Vec::new();
+
                     // For DM channels, deliver directly to the other member
                     // to avoid race conditions with subscription timing.
                     // For group channels, use broadcast as normal.
@@ -646,33 +816,101 @@ async fn handle_client_message(
                                     }
                                     conn_mgr.send_to_user(&member.user_id, &new_msg);
 
-                                    // Push notification for offline DM recipients
-                                    if !conn_mgr.is_online(&member.user_id)
-                                        && let Some(ref push_svc) = state.push_service
-                                    {
-                                        let push_svc = push_svc.clone();
-                                        let pool = state.db.clone();
-                                        let recipient_id = member.user_id;
-                                        let ch_id = channel_id.to_string();
-                                        tokio::spawn(async move {
-                                            let sender_name = match user_repo::find_by_id(&pool, user_id).await {
-                                                Ok(Some(u)) => u.display_name,
-                                                _ => "Someone".to_string(),
-                                            };
-                                            let payload = crate::services::push_service::PushPayload {
-                                                notification_type: "dm".to_string(),
-                                                sender_name,
-                                                channel_id: ch_id,
-                                                channel_name: "Direct Message".to_string(),
-                                            };
-                                            push_svc.send_to_user(&pool, recipient_id, &payload).await;
-                                        });
+                                    if !conn_mgr.is_online(&member.user_id) {
+                                        push_candidates.push(member.user_id);
                                     }
                                 }
                             }
                         }
                     } else {
                         conn_mgr.broadcast_to_channel(channel_id, new_msg);
+
+                        // Mentioned users, plus members who asked for every message
+                        if state.push_service.is_some() {
+                            push_candidates.extend(mentioned.iter().copied());
+                            match notification_repo::list_notify_all_members(
+                                &state.db, channel_id, user_id,
+                            )
+                            .await
+                            {
+                                Ok(ids) => push_candidates.extend(ids),
+                                Err(e) => {
+                                    tracing::warn!(%channel_id, "Failed to list notify-all members: {e}");
+                                }
+                            }
+                            push_candidates.sort_unstable();
+                            push_candidates.dedup();
+                            push_candidates.retain(|uid| !conn_mgr.is_online(uid));
+                        }
+                    }
+
+                    // Push notifications for offline recipients, honoring notification settings
+                    if !push_candidates.is_empty()
+                        && let Some(ref push_svc) = state.push_service
+                    {
+                        let push_svc = push_svc.clone();
+                        let pool = state.db.clone();
+                        let channel_name = if is_dm {
+                            "Direct Message".to_string()
+                        } else {
+                            channel
+                                .name
+                                .clone()
+                                .unwrap_or_else(|| "a channel".to_string())
+                        };
+                        tokio::spawn(async move {
+                            let recipients = push_service::filter_recipients(
+                                &pool,
+                                channel_id,
+                                is_dm,
+                                &push_candidates,
+                                &mentioned,
+                            )
+                            .await;
+                            let (recipients, deferred) = quiet_hours::partition_recipients(
+                                &pool, user_id, is_dm, recipients,
+                            )
+                            .await;
+                            if recipients.is_empty() && deferred.is_empty() {
+                                return;
+                            }
+                            let sender_name = match user_repo::find_by_id(&pool, user_id).await {
+                                Ok(Some(u)) => u.display_name,
+                                _ => "Someone".to_string(),
+                            };
+                            let notification_type = |recipient_id: &Uuid| {
+                                if is_dm {
+                                    "dm"
+                                } else if mentioned.contains(recipient_id) {
+                                    "mention"
+                                } else {
+                                    "message"
+                                }
+                            };
+                            for recipient_id in &deferred {
+                                if let Err(e) = quiet_hours_repo::defer_push(
+                                    &pool,
+                                    *recipient_id,
+                                    notification_type(recipient_id),
+                                    &sender_name,
+                                    channel_id,
+                                    &channel_name,
+                                )
+                                .await
+                                {
+                                    tracing::warn!(%recipient_id, "Failed to defer push notification: {e}");
+                                }
+                            }
+                            for recipient_id in recipients {
+                                let payload = push_service::PushPayload {
+                                    notification_type: notification_type(&recipient_id).to_string(),
+                                    sender_name: sender_name.clone(),
+                                    channel_id: channel_id.to_string(),
+                                    channel_name: channel_name.clone(),
+                                };
+                                push_svc.send_to_user(&pool, recipient_id, &payload).await;
+                            }
+                        });
                     }
 
                     // Update slow mode tracker after successful send (skip for exempt users)
@@ -792,6 +1030,25 @@ async fn handle_client_message(
 
         ClientMessage::UpdatePresence { status } => {
             let status_str = format!("{:?}", status).to_lowercase();
+            if status_str != "offline" {
+                if let Err(e) =
+                    user_repo::set_presence_override(&state.db, user_id, &status_str).await
+                {
+                    tracing::warn!(%user_id, "Failed to record DND/invisible presence: {e}");
+                }
+                // Leaving DND outside quiet hours releases anything held back
+                if status_str != "dnd"
+                    && let Some(ref push_svc) = state.push_service
+                    && let Ok(settings) = quiet_hours_repo::get_settings(&state.db, user_id).await
+                    && !settings.is_some_and(|s| s.enabled && s.quiet_active)
+                {
+                    let push_svc = push_svc.clone();
+                    let pool = state.db.clone();
+                    tokio::spawn(async move {
+                        quiet_hours::flush_deferred(&pool, &push_svc, user_id).await;
+                    });
+                }
+            }
             broadcast_presence(&state.db, conn_mgr, user_id, &status_str).await;
         }
 
@@ -863,6 +1120,7 @@ async fn handle_client_message(
                             }
                             Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
                                 tracing::warn!(%uid, %channel_id, skipped = n, "broadcast subscriber lagged");
+                                METRICS.broadcast_lagged(n);
                                 let _ = tx.send(ServerMessage::Error {
                                     code: "out_of_sync".to_string(),
                                     message: "connection lagged, please refresh".to_string(),
@@ -981,6 +1239,7 @@ async fn handle_client_message(
         ClientMessage::JoinVoice { channel_id } => {
             // Rate-limit voice join/leave (2s cooldown)
             if !conn_mgr.check_voice_cooldown(user_id) {
+                METRICS.rate_limited("voice");
                 let _ = tx.send(ServerMessage::Error {
                     code: "rate_limited".to_string(),
                     message: "please wait before joining/leaving voice again".to_string(),
@@ -1088,6 +1347,7 @@ async fn handle_client_message(
         ClientMessage::LeaveVoice { channel_id } => {
             // Rate-limit voice join/leave (shared 2s cooldown with JoinVoice)
             if !conn_mgr.check_voice_cooldown(user_id) {
+                METRICS.rate_limited("voice");
                 let _ = tx.send(ServerMessage::Error {
                     code: "rate_limited".to_string(),
                     message: "please wait before joining/leaving voice again".to_string(),
@@ -1329,6 +1589,7 @@ async fn handle_client_message(
         ClientMessage::AddReaction { message_id, emoji } => {
             // Per-user reaction cooldown (200ms between reactions, across all connections)
             if !conn_mgr.check_reaction_cooldown(user_id) {
+                METRICS.rate_limited("reaction");
                 let _ = tx.send(ServerMessage::Error {
                     code: "rate_limited".to_string(),
                     message: "adding reactions too quickly".to_string(),
@@ -1373,6 +1634,18 @@ async fn handle_client_message(
                 return;
             }
 
+            // Archived messages are read-only; their reactions are kept as they were
+            if message_repo::is_archived(&state.db, message_id)
+                .await
+                .unwrap_or(false)
+            {
+                let _ = tx.send(ServerMessage::Error {
+                    code: "message_archived".to_string(),
+                    message: "reactions on archived messages can't be changed".to_string(),
+                });
+                return;
+            }
+
             // Limit unique reactions per message (max 20)
             const MAX_UNIQUE_REACTIONS: i64 = 20;
             match reaction_repo::count_unique_reactions(&state.db, message_id).await {
@@ -1447,6 +1720,18 @@ async fn handle_client_message(
                 return;
             }
 
+            // Archived messages are read-only; their reactions are kept as they were
+            if message_repo::is_archived(&state.db, message_id)
+                .await
+                .unwrap_or(false)
+            {
+                let _ = tx.send(ServerMessage::Error {
+                    code: "message_archived".to_string(),
+                    message: "reactions on archived messages can't be changed".to_string(),
+                });
+                return;
+            }
+
             match reaction_repo::remove_reaction(&state.db, message_id, user_id, &emoji).await {
                 Ok(true) => {
                     conn_mgr.broadcast_to_channel(
@@ -1506,11 +1791,106 @@ async fn handle_client_message(
                 tracing::warn!(user_id = %user_id, error = %e, "Failed to mark all read");
             }
         }
+
+        ClientMessage::MarkDelivered {
+            channel_id,
+            message_ids,
+        } => {
+            // Delivery receipts are aggregated per message, so only track them where
+            // the recipient list stays small enough to be meaningful.
+            const MAX_DELIVERY_RECEIPT_MEMBERS: i64 = 50;
+            const MAX_MESSAGES_PER_MARK: usize = 100;
+
+            if message_ids.is_empty() || message_ids.len() > MAX_MESSAGES_PER_MARK {
+                let _ = tx.send(ServerMessage::Error {
+                    code: "validation_error".to_string(),
+                    message: format!("must mark between 1 and {MAX_MESSAGES_PER_MARK} messages"),
+                });
+                return;
+            }
+
+            if !channel_repo::is_member(&state.db, channel_id, user_id)
+                .await
+                .unwrap_or(false)
+            {
+                return;
+            }
+
+            // Same privacy opt-out as read receipts.
+            // Fail-secure: if the DB check errors, assume receipts are disabled (privacy).
+            if unread_repo::is_read_receipts_disabled(&state.db, user_id)
+                .await
+                .unwrap_or(true)
+            {
+                return;
+            }
+
+            let channel = match channel_repo::get_channel(&state.db, channel_id).await {
+                Ok(Some(ch)) => ch,
+                Ok(None) => return,
+                Err(e) => {
+                    tracing::warn!(%channel_id, "Failed to fetch channel for delivery receipt: {e}");
+                    return;
+                }
+            };
+            if channel.channel_type != ChannelType::Dm {
+                match channel_repo::count_members(&state.db, channel_id).await {
+                    Ok(n) if n <= MAX_DELIVERY_RECEIPT_MEMBERS => {}
+                    Ok(_) => return,
+                    Err(e) => {
+                        tracing::warn!(%channel_id, "Failed to count channel members: {e}");
+                        return;
+                    }
+                }
+            }
+
+            let delivered = match delivery_repo::mark_delivered(
+                &state.db,
+                user_id,
+                channel_id,
+                &message_ids,
+            )
+            .await
+            {
+                Ok(d) => d,
+                Err(e) => {
+                    tracing::warn!(%user_id, %channel_id, error = %e, "Failed to mark delivered");
+                    return;
+                }
+            };
+
+            let timestamp = chrono::Utc::now().to_rfc3339();
+            for d in delivered {
+                let Some(sender_id) = d.sender_id else {
+                    continue;
+                };
+                if !conn_mgr.is_online(&sender_id) {
+                    continue;
+                }
+                match delivery_repo::get_delivered_user_ids(&state.db, d.message_id).await {
+                    Ok(delivered_to) => {
+                        conn_mgr.send_to_user(
+                            &sender_id,
+                            &ServerMessage::DeliveryReceipt {
+                                channel_id,
+                                message_id: d.message_id,
+                                user_id,
+                                delivered_to,
+                                timestamp: timestamp.clone(),
+                            },
+                        );
+                    }
+                    Err(e) => {
+                        tracing::warn!(message_id = %d.message_id, "Failed to load deliveries: {e}");
+                    }
+                }
+            }
+        }
     }
 }
 
-async fn broadcast_presence(
-    db: &sqlx::PgPool,
+pub(crate) async fn broadcast_presence(
+    db: &chatalot_db::db::Db,
     conn_mgr: &crate::ws::connection_manager::ConnectionManager,
     user_id: Uuid,
     status: &str,
@@ -1,3 +1,4 @@
 pub mod connection_manager;
 pub mod handler;
 pub mod session;
+pub mod sse;
@@ -2,7 +2,8 @@ use std::sync::Arc;
 
 use axum::extract::State;
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
-use axum::response::Response;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use futures_util::StreamExt;
 
 use crate::app_state::AppState;
@@ -14,6 +15,10 @@ use crate::ws::handler;
 /// Authentication is done via the first message (ClientMessage::Authenticate)
 /// rather than via headers, since WebSocket headers are unreliable across browsers.
 pub async fn ws_upgrade(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
+    // Draining for shutdown: refuse new sockets so clients go elsewhere or retry later
+    if state.is_shutting_down() {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
     ws.on_upgrade(move |socket| handle_ws_auth(socket, state))
 }
 
@@ -0,0 +1,438 @@
+//! Server-Sent Events + HTTP POST fallback for clients whose network blocks
+//! WebSocket upgrades. Events carry the same `ServerMessage` JSON as the socket
+//! and POSTed `ClientMessage`s go through the same `ClientSession` dispatch.
+//!
+//! Every event has an increasing id and is kept in a per-session buffer, so a
+//! client that loses the stream can reconnect with `session_id` and
+//! `Last-Event-ID` and receive what it missed. Sessions without an attached
+//! stream are closed after `RESUME_WINDOW`.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Router};
+use dashmap::DashMap;
+use futures_util::stream::{self, StreamExt};
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc};
+use uuid::Uuid;
+
+use chatalot_common::ws_messages::ServerMessage;
+
+use crate::app_state::AppState;
+use crate::error::AppError;
+use crate::middleware::auth::AccessClaims;
+use crate::ws::handler::ClientSession;
+
+/// Events kept per session for resumption.
+const EVENT_BUFFER_SIZE: usize = 1000;
+/// How long a session survives without an attached stream.
+const RESUME_WINDOW: std::time::Duration = std::time::Duration::from_secs(60);
+/// Maximum client messages accepted in one POST.
+const MAX_POST_BATCH: usize = 50;
+/// Maximum POST body size (matches the WebSocket per-message limit).
+const MAX_POST_BODY: usize = 1_048_576;
+
+#[derive(Clone)]
+struct BufferedEvent {
+    id: u64,
+    data: Arc<str>,
+}
+
+struct EventBuffer {
+    events: VecDeque<BufferedEvent>,
+    next_id: u64,
+}
+
+impl EventBuffer {
+    /// Events after `last_id`, or None if some of them were already dropped
+    /// (or `last_id` was never sent) and the client has to start over.
+    fn since(&self, last_id: u64) -> Option<VecDeque<BufferedEvent>> {
+        if last_id >= self.next_id {
+            return None;
+        }
+        let oldest = self.events.front().map_or(self.next_id, |e| e.id);
+        if oldest > last_id + 1 {
+            return None;
+        }
+        Some(
+            self.events
+                .iter()
+                .filter(|e| e.id > last_id)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+struct SseSession {
+    user_id: Uuid,
+    /// None once the session has been closed.
+    client: tokio::sync::Mutex<Option<ClientSession>>,
+    buffer: std::sync::Mutex<EventBuffer>,
+    /// Fan-out to attached streams; dropped when the session ends so they finish.
+    live: std::sync::Mutex<Option<broadcast::Sender<BufferedEvent>>>,
+    attached: AtomicUsize,
+    detached_since: std::sync::Mutex<Option<tokio::time::Instant>>,
+}
+
+/// Open SSE sessions, keyed by session id.
+#[derive(Default)]
+pub struct SseSessions {
+    sessions: DashMap<Uuid, Arc<SseSession>>,
+}
+
+impl SseSessions {
+    /// Number of SSE sessions, including detached ones inside the resume window.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+}
+
+/// Marks a stream as attached to its session for as long as it lives.
+struct AttachGuard(Arc<SseSession>);
+
+impl SseSession {
+    fn subscribe(&self) -> Option<broadcast::Receiver<BufferedEvent>> {
+        self.live.lock().ok()?.as_ref().map(|l| l.subscribe())
+    }
+}
+
+impl AttachGuard {
+    fn new(session: Arc<SseSession>) -> Self {
+        session.attached.fetch_add(1, Ordering::SeqCst);
+        Self(session)
+    }
+}
+
+impl Drop for AttachGuard {
+    fn drop(&mut self) {
+        if self.0.attached.fetch_sub(1, Ordering::SeqCst) == 1
+            && let Ok(mut since) = self.0.detached_since.lock()
+        {
+            *since = Some(tokio::time::Instant::now());
+        }
+    }
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/events", axum::routing::get(event_stream))
+        .route("/events/{session_id}", axum::routing::post(post_messages))
+}
+
+#[derive(Debug, Deserialize)]
+struct EventStreamQuery {
+    session_id: Option<Uuid>,
+    /// Alternative to the `Last-Event-ID` header for clients that can't set it.
+    last_event_id: Option<u64>,
+}
+
+/// Open (or resume) an event stream. The first event is always `session`,
+/// carrying the session id for POSTs and whether the stream was resumed; a
+/// client that gets `resumed: false` must resync as after a fresh connect.
+async fn event_stream(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Query(query): Query<EventStreamQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    // Draining for shutdown: no new or resumed streams
+    if state.is_shutting_down() {
+        return Ok(StatusCode::SERVICE_UNAVAILABLE.into_response());
+    }
+
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .or(query.last_event_id)
+        .unwrap_or(0);
+
+    // Resume if the session is still alive and has everything the client missed
+    let resumed = query
+        .session_id
+        .and_then(|id| {
+            state
+                .sse_sessions
+                .sessions
+                .get(&id)
+                .map(|s| (id, s.clone()))
+        })
+        .filter(|(_, s)| s.user_id == claims.sub)
+        .and_then(|(id, session)| {
+            // Subscribe before snapshotting so no event falls between the two
+            let live = session.subscribe()?;
+            let replay = session.buffer.lock().ok()?.since(last_event_id)?;
+            Some((id, session, live, replay))
+        });
+
+    let (session_id, session, live, replay, was_resumed) = match resumed {
+        Some((id, session, live, replay)) => (id, session, live, replay, true),
+        None => {
+            let (id, session) = create_session(&state, &claims).await?;
+            let live = session
+                .subscribe()
+                .ok_or_else(|| AppError::Internal("SSE session closed on creation".into()))?;
+            let replay = session
+                .buffer
+                .lock()
+                .map(|b| b.events.clone())
+                .unwrap_or_default();
+            (id, session, live, replay, false)
+        }
+    };
+
+    let hello = Event::default()
+        .event("session")
+        .data(serde_json::json!({ "session_id": session_id, "resumed": was_resumed }).to_string());
+
+    let guard = AttachGuard::new(session);
+    let last_sent = if was_resumed { last_event_id } else { 0 };
+    let events = stream::unfold(
+        (replay, live, last_sent, guard),
+        |(mut replay, mut live, mut last_sent, guard)| async move {
+            if let Some(ev) = replay.pop_front() {
+                last_sent = ev.id;
+                return Some((to_event(&ev), (replay, live, last_sent, guard)));
+            }
+            loop {
+                match live.recv().await {
+                    Ok(ev) if ev.id <= last_sent => continue,
+                    Ok(ev) => {
+                        last_sent = ev.id;
+                        return Some((to_event(&ev), (replay, live, last_sent, guard)));
+                    }
+                    // Lagging streams end; the client resumes from the buffer
+                    Err(_) => return None,
+                }
+            }
+        },
+    );
+
+    let stream = stream::once(async move { hello })
+        .chain(events)
+        .map(Ok::<_, Infallible>);
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+/// Accept one `ClientMessage` or a JSON array of them for a session.
+/// Replies and errors arrive on the event stream, exactly as over the socket.
+async fn post_messages(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<AccessClaims>,
+    Path(session_id): Path<Uuid>,
+    body: Bytes,
+) -> Result<StatusCode, AppError> {
+    let session = state
+        .sse_sessions
+        .sessions
+        .get(&session_id)
+        .map(|s| s.clone())
+        .filter(|s| s.user_id == claims.sub)
+        .ok_or_else(|| AppError::NotFound("session not found".into()))?;
+
+    if body.len() > MAX_POST_BODY {
+        return Err(AppError::Validation("message too large".into()));
+    }
+    let messages = match serde_json::from_slice::<serde_json::Value>(&body) {
+        Ok(serde_json::Value::Array(items)) => items,
+        Ok(item) => vec![item],
+        Err(_) => return Err(AppError::Validation("malformed message".into())),
+    };
+    if messages.len() > MAX_POST_BATCH {
+        return Err(AppError::Validation(format!(
+            "at most {MAX_POST_BATCH} messages per request"
+        )));
+    }
+
+    let mut client = session.client.lock().await;
+    let client = client
+        .as_mut()
+        .ok_or_else(|| AppError::NotFound("session not found".into()))?;
+    for message in messages {
+        client.handle_text(&state, &message.to_string()).await;
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+fn to_event(ev: &BufferedEvent) -> Event {
+    Event::default().id(ev.id.to_string()).data(&*ev.data)
+}
+
+/// Open a new realtime session whose output is buffered for SSE streams.
+async fn create_session(
+    state: &Arc<AppState>,
+    claims: &AccessClaims,
+) -> Result<(Uuid, Arc<SseSession>), AppError> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
+    let _ = tx.send(ServerMessage::Authenticated {
+        user_id: claims.sub,
+        server_version: state.client_version.clone(),
+    });
+
+    let client = ClientSession::open(state, claims.sub, claims.is_owner, claims.is_admin, tx)
+        .await
+        .ok_or_else(|| {
+            AppError::Conflict("too many concurrent connections, close another device first".into())
+        })?;
+    let session_id = client.session_id;
+    let kicked = client.kicked();
+
+    let (live, _) = broadcast::channel(256);
+    let session = Arc::new(SseSession {
+        user_id: claims.sub,
+        client: tokio::sync::Mutex::new(Some(client)),
+        buffer: std::sync::Mutex::new(EventBuffer {
+            events: VecDeque::new(),
+            next_id: 1,
+        }),
+        live: std::sync::Mutex::new(Some(live)),
+        attached: AtomicUsize::new(0),
+        detached_since: std::sync::Mutex::new(Some(tokio::time::Instant::now())),
+    });
+    state
+        .sse_sessions
+        .sessions
+        .insert(session_id, session.clone());
+
+    // Pump: number, buffer and fan out everything sent to the session.
+    // Ends once the session is closed and its senders are dropped.
+    {
+        let session = session.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                let data: Arc<str> = match serde_json::to_string(&msg) {
+                    Ok(text) => text.into(),
+                    Err(e) => {
+                        tracing::error!("failed to serialize outgoing SSE message: {e}");
+                        continue;
+                    }
+                };
+                let Ok(mut buffer) = session.buffer.lock() else {
+                    break;
+                };
+                let event = BufferedEvent {
+                    id: buffer.next_id,
+                    data,
+                };
+                buffer.next_id += 1;
+                buffer.events.push_back(event.clone());
+                if buffer.events.len() > EVENT_BUFFER_SIZE {
+                    buffer.events.pop_front();
+                }
+                drop(buffer);
+                if let Ok(live) = session.live.lock()
+                    && let Some(live) = live.as_ref()
+                {
+                    let _ = live.send(event);
+                }
+            }
+            // Session closed: end attached streams once they've drained
+            if let Ok(mut live) = session.live.lock() {
+                live.take();
+            }
+        });
+    }
+
+    // Reaper: close the session once no stream has been attached for a while,
+    // right away when the server drops it, or right away (with a reconnect
+    // hint) when the server shuts down
+    {
+        let state = state.clone();
+        let session = session.clone();
+        let mut shutdown = state.shutdown.subscribe();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(10));
+            loop {
+                let shutting_down = tokio::select! {
+                    _ = interval.tick() => false,
+                    _ = kicked.cancelled() => {
+                        state.sse_sessions.sessions.remove(&session_id);
+                        if let Some(client) = session.client.lock().await.take() {
+                            client.close(&state).await;
+                        }
+                        break;
+                    }
+                    _ = crate::shutdown::wait(&mut shutdown) => true,
+                };
+                if shutting_down {
+                    state.sse_sessions.sessions.remove(&session_id);
+                    if let Some(client) = session.client.lock().await.take() {
+                        client.notify_shutdown();
+                        client.close(&state).await;
+                    }
+                    break;
+                }
+                let expired = session.attached.load(Ordering::SeqCst) == 0
+                    && session
+                        .detached_since
+                        .lock()
+                        .ok()
+                        .and_then(|s| *s)
+                        .is_some_and(|t| t.elapsed() > RESUME_WINDOW);
+                if !expired {
+                    continue;
+                }
+                state.sse_sessions.sessions.remove(&session_id);
+                if let Some(client) = session.client.lock().await.take() {
+                    let user_id = client.user_id;
+                    client.close(&state).await;
+                    tracing::info!(%user_id, %session_id, "SSE session closed");
+                }
+                break;
+            }
+        });
+    }
+
+    tracing::info!(user_id = %claims.sub, %session_id, "SSE session opened");
+    Ok((session_id, session))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(ids: std::ops::Range<u64>, next_id: u64) -> EventBuffer {
+        EventBuffer {
+            events: ids
+                .map(|id| BufferedEvent {
+                    id,
+                    data: Arc::from("{}"),
+                })
+                .collect(),
+            next_id,
+        }
+    }
+
+    #[test]
+    fn resume_replays_missed_events() {
+        let b = buffer(1..6, 6);
+        let ids: Vec<_> = b.since(3).unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![4, 5]);
+        assert!(b.since(5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn resume_refused_when_events_were_dropped_or_unknown() {
+        let b = buffer(10..20, 20);
+        assert!(b.since(8).is_none());
+        assert!(b.since(9).is_some());
+        assert!(b.since(20).is_none());
+        assert!(buffer(0..0, 1).since(0).unwrap().is_empty());
+    }
+}
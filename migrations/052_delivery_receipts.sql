@@ -0,0 +1,11 @@
+-- Per-recipient delivery receipts: the message reached one of the recipient's
+-- devices (distinct from read_cursors, which track what was actually read).
+-- Only recorded for DMs and small channels.
+CREATE TABLE IF NOT EXISTS message_deliveries (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    delivered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_deliveries_user ON message_deliveries(user_id);
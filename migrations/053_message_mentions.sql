@@ -0,0 +1,15 @@
+-- Client-declared mentions. Message content is encrypted, so the sender's client
+-- tells the server who was mentioned. Role and @everyone mentions are expanded to
+-- one row per channel member at send time so counts and the inbox stay cheap.
+CREATE TABLE IF NOT EXISTS message_mentions (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- 'user', 'role' or 'everyone'
+    kind VARCHAR(16) NOT NULL DEFAULT 'user',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (message_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_mentions_user ON message_mentions(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_message_mentions_channel_user ON message_mentions(channel_id, user_id);
@@ -0,0 +1,21 @@
+-- Server-side notification settings, enforced by the push pipeline.
+-- The most specific scope wins: channel/dm > group > community.
+CREATE TABLE IF NOT EXISTS notification_settings (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- 'channel', 'dm', 'group' or 'community'
+    scope_type VARCHAR(16) NOT NULL,
+    scope_id UUID NOT NULL,
+    -- 'all', 'mentions' or 'nothing'
+    level VARCHAR(16) NOT NULL DEFAULT 'all',
+    -- While in the future, the scope is silenced regardless of level
+    muted_until TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, scope_type, scope_id),
+    CONSTRAINT notification_settings_scope_check
+        CHECK (scope_type IN ('channel', 'dm', 'group', 'community')),
+    CONSTRAINT notification_settings_level_check
+        CHECK (level IN ('all', 'mentions', 'nothing'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_notification_settings_scope
+    ON notification_settings(scope_type, scope_id);
@@ -0,0 +1,55 @@
+-- Quiet hours: recurring per-user windows during which push notifications are
+-- suppressed or batched, and presence optionally switches to Do Not Disturb.
+CREATE TABLE IF NOT EXISTS quiet_hours_settings (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    -- IANA time zone name the schedules are expressed in
+    timezone VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    -- 'suppress' drops notifications, 'batch' sends one summary afterwards
+    delivery VARCHAR(16) NOT NULL DEFAULT 'suppress',
+    -- Switch presence between online and dnd on schedule
+    auto_dnd BOOLEAN NOT NULL DEFAULT true,
+    -- Maintained by the scheduler; true while a window is in effect
+    quiet_active BOOLEAN NOT NULL DEFAULT false,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT quiet_hours_delivery_check CHECK (delivery IN ('suppress', 'batch'))
+);
+
+CREATE TABLE IF NOT EXISTS quiet_hours_schedules (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Bitmask of local weekdays the window starts on (bit 0 = Monday)
+    days SMALLINT NOT NULL,
+    -- Minutes after local midnight; end < start wraps past midnight
+    start_minute SMALLINT NOT NULL,
+    end_minute SMALLINT NOT NULL,
+    CONSTRAINT quiet_hours_days_check CHECK (days BETWEEN 1 AND 127),
+    CONSTRAINT quiet_hours_start_check CHECK (start_minute BETWEEN 0 AND 1439),
+    CONSTRAINT quiet_hours_end_check CHECK (end_minute BETWEEN 0 AND 1439),
+    CONSTRAINT quiet_hours_window_check CHECK (start_minute <> end_minute)
+);
+
+CREATE INDEX IF NOT EXISTS idx_quiet_hours_schedules_user ON quiet_hours_schedules(user_id);
+
+-- DMs from these users are delivered even during quiet hours or DND
+CREATE TABLE IF NOT EXISTS quiet_hours_breakthrough (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    allowed_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, allowed_user_id)
+);
+
+-- Notifications held back in 'batch' mode, flushed as a summary when quiet
+-- ends. One row per user and channel: later notifications only raise the
+-- count and replace the sender. Names are copied as they were when the
+-- message arrived, so they are not limited more than the source.
+CREATE TABLE IF NOT EXISTS deferred_push_notifications (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    notification_type VARCHAR(16) NOT NULL,
+    sender_name TEXT NOT NULL,
+    channel_id UUID NOT NULL,
+    channel_name TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT deferred_push_user_channel_key UNIQUE (user_id, channel_id)
+);
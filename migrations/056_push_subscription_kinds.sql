@@ -0,0 +1,13 @@
+-- Push subscriptions can target different transports: browser Web Push
+-- (encrypted, VAPID-signed) or UnifiedPush/ntfy distributors (plain HTTP POST).
+ALTER TABLE push_subscriptions
+    ADD COLUMN IF NOT EXISTS kind VARCHAR(16) NOT NULL DEFAULT 'webpush';
+
+ALTER TABLE push_subscriptions ALTER COLUMN p256dh_key DROP NOT NULL;
+ALTER TABLE push_subscriptions ALTER COLUMN auth_key DROP NOT NULL;
+
+ALTER TABLE push_subscriptions
+    ADD CONSTRAINT push_subscriptions_kind_check
+        CHECK (kind IN ('webpush', 'unifiedpush')),
+    ADD CONSTRAINT push_subscriptions_webpush_keys_check
+        CHECK (kind <> 'webpush' OR (p256dh_key IS NOT NULL AND auth_key IS NOT NULL));
@@ -0,0 +1,16 @@
+-- Cluster-wide background jobs. Each row is leased by one node while it runs,
+-- so a job never runs concurrently on two replicas.
+CREATE TABLE IF NOT EXISTS jobs (
+    name VARCHAR(64) PRIMARY KEY,
+    next_run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Node currently running the job, and when its lease lapses
+    lease_owner UUID,
+    lease_expires_at TIMESTAMPTZ,
+    last_started_at TIMESTAMPTZ,
+    last_finished_at TIMESTAMPTZ,
+    last_success_at TIMESTAMPTZ,
+    last_duration_ms BIGINT,
+    last_error TEXT,
+    consecutive_failures INT NOT NULL DEFAULT 0,
+    run_count BIGINT NOT NULL DEFAULT 0
+);
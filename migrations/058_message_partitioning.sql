@@ -0,0 +1,227 @@
+-- Range-partition messages, reactions and message_edits by month, and add
+-- an archive tier for cold months.
+--
+-- messages is partitioned on created_at; reactions and message_edits carry
+-- their message's created_at so each month's rows live in matching
+-- partitions and can be archived together. The primary key of a partitioned
+-- table must include the partition key, so messages(id) is no longer unique
+-- on its own and other tables cannot reference it: their foreign keys are
+-- replaced by a trigger that performs the same cascades.
+
+-- ── Partition management ──
+
+-- Create the monthly partitions of all three tables for every month from
+-- first_month to last_month (inclusive). Returns the number created.
+CREATE FUNCTION create_message_partitions(first_month DATE, last_month DATE)
+RETURNS INTEGER LANGUAGE plpgsql AS $$
+DECLARE
+    month DATE := date_trunc('month', first_month);
+    created INTEGER := 0;
+    parent TEXT;
+    part TEXT;
+BEGIN
+    WHILE month <= last_month LOOP
+        FOREACH parent IN ARRAY ARRAY['messages', 'reactions', 'message_edits'] LOOP
+            part := parent || to_char(month, '"_y"YYYY"m"MM');
+            IF to_regclass(part) IS NULL THEN
+                EXECUTE format(
+                    'CREATE TABLE %I PARTITION OF %I FOR VALUES FROM (%L) TO (%L)',
+                    part, parent,
+                    month::timestamp AT TIME ZONE 'UTC',
+                    (month + INTERVAL '1 month')::timestamp AT TIME ZONE 'UTC'
+                );
+                created := created + 1;
+            END IF;
+        END LOOP;
+        month := month + INTERVAL '1 month';
+    END LOOP;
+    RETURN created;
+END;
+$$;
+
+-- ── New tables ──
+
+CREATE TABLE messages_partitioned (
+    id              UUID NOT NULL,
+    channel_id      UUID NOT NULL,
+    sender_id       UUID,
+    ciphertext      BYTEA NOT NULL,
+    nonce           BYTEA NOT NULL,
+    message_type    VARCHAR(16) NOT NULL DEFAULT 'text',
+    sender_key_id   UUID,
+    reply_to_id     UUID,
+    edited_at       TIMESTAMPTZ,
+    deleted_at      TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    quarantined_at  TIMESTAMPTZ,
+    quarantined_by  UUID,
+    plaintext       TEXT,
+    expires_at      TIMESTAMPTZ,
+    thread_id       UUID
+) PARTITION BY RANGE (created_at);
+
+CREATE TABLE reactions_partitioned (
+    id                  UUID NOT NULL DEFAULT gen_random_uuid(),
+    message_id          UUID NOT NULL,
+    user_id             UUID NOT NULL,
+    emoji               VARCHAR(32) NOT NULL,
+    created_at          TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    message_created_at  TIMESTAMPTZ NOT NULL
+) PARTITION BY RANGE (message_created_at);
+
+CREATE TABLE message_edits_partitioned (
+    id                  UUID NOT NULL DEFAULT gen_random_uuid(),
+    message_id          UUID NOT NULL,
+    old_ciphertext      BYTEA NOT NULL,
+    old_nonce           BYTEA NOT NULL,
+    edited_at           TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    message_created_at  TIMESTAMPTZ NOT NULL
+) PARTITION BY RANGE (message_created_at);
+
+-- Rows that fall outside every monthly partition land here rather than
+-- failing; the partition job keeps upcoming months created ahead of time.
+CREATE TABLE messages_default PARTITION OF messages_partitioned DEFAULT;
+CREATE TABLE reactions_default PARTITION OF reactions_partitioned DEFAULT;
+CREATE TABLE message_edits_default PARTITION OF message_edits_partitioned DEFAULT;
+
+-- ── Copy existing rows ──
+
+ALTER TABLE messages_partitioned RENAME TO messages_new;
+ALTER TABLE reactions_partitioned RENAME TO reactions_new;
+ALTER TABLE message_edits_partitioned RENAME TO message_edits_new;
+
+DO $$
+DECLARE
+    oldest DATE;
+BEGIN
+    SELECT date_trunc('month', MIN(created_at) AT TIME ZONE 'UTC') INTO oldest FROM messages;
+    -- create_message_partitions() names the parents literally, so point
+    -- those names at the new tables while creating partitions.
+    ALTER TABLE messages RENAME TO messages_old;
+    ALTER TABLE reactions RENAME TO reactions_old;
+    ALTER TABLE message_edits RENAME TO message_edits_old;
+    ALTER TABLE messages_new RENAME TO messages;
+    ALTER TABLE reactions_new RENAME TO reactions;
+    ALTER TABLE message_edits_new RENAME TO message_edits;
+    PERFORM create_message_partitions(
+        COALESCE(oldest, date_trunc('month', NOW() AT TIME ZONE 'UTC')::date),
+        (date_trunc('month', NOW() AT TIME ZONE 'UTC') + INTERVAL '2 months')::date
+    );
+END;
+$$;
+
+INSERT INTO messages (id, channel_id, sender_id, ciphertext, nonce, message_type, sender_key_id,
+    reply_to_id, edited_at, deleted_at, created_at, quarantined_at, quarantined_by, plaintext,
+    expires_at, thread_id)
+SELECT id, channel_id, sender_id, ciphertext, nonce, message_type, sender_key_id,
+    reply_to_id, edited_at, deleted_at, created_at, quarantined_at, quarantined_by, plaintext,
+    expires_at, thread_id
+FROM messages_old;
+
+INSERT INTO reactions (id, message_id, user_id, emoji, created_at, message_created_at)
+SELECT r.id, r.message_id, r.user_id, r.emoji, r.created_at, m.created_at
+FROM reactions_old r JOIN messages_old m ON m.id = r.message_id;
+
+INSERT INTO message_edits (id, message_id, old_ciphertext, old_nonce, edited_at, message_created_at)
+SELECT e.id, e.message_id, e.old_ciphertext, e.old_nonce, e.edited_at, m.created_at
+FROM message_edits_old e JOIN messages_old m ON m.id = e.message_id;
+
+-- Also drops the foreign keys other tables held on messages(id).
+DROP TABLE message_edits_old, reactions_old, messages_old CASCADE;
+
+-- ── Keys and indexes ──
+
+ALTER TABLE messages
+    ADD CONSTRAINT messages_pkey PRIMARY KEY (id, created_at),
+    ADD CONSTRAINT messages_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    ADD CONSTRAINT messages_sender_id_fkey FOREIGN KEY (sender_id) REFERENCES users(id) ON DELETE SET NULL,
+    ADD CONSTRAINT messages_quarantined_by_fkey FOREIGN KEY (quarantined_by) REFERENCES users(id) ON DELETE SET NULL;
+CREATE INDEX idx_messages_channel ON messages(channel_id, created_at DESC);
+CREATE INDEX idx_messages_sender ON messages(sender_id, created_at DESC);
+CREATE INDEX idx_messages_channel_active ON messages(channel_id, created_at DESC) WHERE deleted_at IS NULL;
+CREATE INDEX idx_messages_quarantined ON messages(quarantined_at) WHERE quarantined_at IS NOT NULL;
+CREATE INDEX idx_messages_deleted ON messages(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_messages_expires ON messages(expires_at) WHERE expires_at IS NOT NULL;
+CREATE INDEX idx_messages_thread ON messages(thread_id, created_at ASC) WHERE thread_id IS NOT NULL;
+
+ALTER TABLE reactions
+    ADD CONSTRAINT reactions_pkey PRIMARY KEY (id, message_created_at),
+    ADD CONSTRAINT reactions_message_id_user_id_emoji_key UNIQUE (message_id, user_id, emoji, message_created_at),
+    ADD CONSTRAINT reactions_message_id_fkey FOREIGN KEY (message_id, message_created_at)
+        REFERENCES messages(id, created_at) ON DELETE CASCADE,
+    ADD CONSTRAINT reactions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+CREATE INDEX idx_reactions_message ON reactions(message_id);
+
+ALTER TABLE message_edits
+    ADD CONSTRAINT message_edits_pkey PRIMARY KEY (id, message_created_at),
+    ADD CONSTRAINT message_edits_message_id_fkey FOREIGN KEY (message_id, message_created_at)
+        REFERENCES messages(id, created_at) ON DELETE CASCADE;
+CREATE INDEX idx_message_edits_message_id ON message_edits(message_id);
+
+-- ── Archive tier ──
+
+-- Cold months are moved here by the message_archival job. The tables are
+-- unpartitioned, carry only the indexes history paging needs, and toast
+-- (compress) any row over 128 bytes instead of the default ~2 kB.
+CREATE TABLE archived_messages (
+    id              UUID PRIMARY KEY,
+    channel_id      UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    sender_id       UUID REFERENCES users(id) ON DELETE SET NULL,
+    ciphertext      BYTEA NOT NULL,
+    nonce           BYTEA NOT NULL,
+    message_type    VARCHAR(16) NOT NULL DEFAULT 'text',
+    sender_key_id   UUID,
+    reply_to_id     UUID,
+    edited_at       TIMESTAMPTZ,
+    deleted_at      TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL,
+    quarantined_at  TIMESTAMPTZ,
+    quarantined_by  UUID REFERENCES users(id) ON DELETE SET NULL,
+    plaintext       TEXT,
+    expires_at      TIMESTAMPTZ,
+    thread_id       UUID
+) WITH (toast_tuple_target = 128);
+CREATE INDEX idx_archived_messages_channel ON archived_messages(channel_id, created_at DESC);
+CREATE INDEX idx_archived_messages_sender ON archived_messages(sender_id);
+CREATE INDEX idx_archived_messages_expires ON archived_messages(expires_at) WHERE expires_at IS NOT NULL;
+
+CREATE TABLE archived_reactions (
+    id                  UUID PRIMARY KEY,
+    message_id          UUID NOT NULL REFERENCES archived_messages(id) ON DELETE CASCADE,
+    user_id             UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    emoji               VARCHAR(32) NOT NULL,
+    created_at          TIMESTAMPTZ NOT NULL,
+    message_created_at  TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_archived_reactions_message ON archived_reactions(message_id);
+
+CREATE TABLE archived_message_edits (
+    id                  UUID PRIMARY KEY,
+    message_id          UUID NOT NULL REFERENCES archived_messages(id) ON DELETE CASCADE,
+    old_ciphertext      BYTEA NOT NULL,
+    old_nonce           BYTEA NOT NULL,
+    edited_at           TIMESTAMPTZ NOT NULL,
+    message_created_at  TIMESTAMPTZ NOT NULL
+) WITH (toast_tuple_target = 128);
+CREATE INDEX idx_archived_message_edits_message ON archived_message_edits(message_id);
+
+-- ── Cascades formerly done by foreign keys on messages(id) ──
+
+CREATE FUNCTION delete_message_dependents() RETURNS trigger LANGUAGE plpgsql AS $$
+BEGIN
+    DELETE FROM pinned_messages WHERE message_id IN (SELECT id FROM deleted_messages);
+    DELETE FROM bookmarks WHERE message_id IN (SELECT id FROM deleted_messages);
+    DELETE FROM message_deliveries WHERE message_id IN (SELECT id FROM deleted_messages);
+    DELETE FROM message_mentions WHERE message_id IN (SELECT id FROM deleted_messages);
+    UPDATE read_cursors SET last_read_message_id = NULL
+        WHERE last_read_message_id IN (SELECT id FROM deleted_messages);
+    RETURN NULL;
+END;
+$$;
+
+CREATE TRIGGER messages_delete_dependents
+    AFTER DELETE ON messages REFERENCING OLD TABLE AS deleted_messages
+    FOR EACH STATEMENT EXECUTE FUNCTION delete_message_dependents();
+CREATE TRIGGER archived_messages_delete_dependents
+    AFTER DELETE ON archived_messages REFERENCING OLD TABLE AS deleted_messages
+    FOR EACH STATEMENT EXECUTE FUNCTION delete_message_dependents();
@@ -0,0 +1,16 @@
+-- In-progress resumable (tus) uploads. The bytes received so far live in
+-- FILE_STORAGE_PATH/uploads/<id>; the row becomes a `files` row once the
+-- last chunk arrives, or is dropped when it expires.
+CREATE TABLE uploads (
+    id              UUID PRIMARY KEY,
+    user_id         UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    upload_length   BIGINT NOT NULL,
+    upload_offset   BIGINT NOT NULL DEFAULT 0,
+    encrypted_name  VARCHAR(512) NOT NULL,
+    content_type    VARCHAR(255),
+    channel_id      UUID REFERENCES channels(id) ON DELETE CASCADE,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at      TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_uploads_user ON uploads(user_id);
+CREATE INDEX idx_uploads_expires ON uploads(expires_at);
@@ -0,0 +1,24 @@
+-- Uploaded bytes are stored once per distinct content, keyed by the SHA-256
+-- of the upload. Every `files` row points at a blob through storage_path;
+-- ref_count is the number of rows that do. At ref_count 0 the record is a
+-- tombstone until the stored bytes are deleted, so an identical upload
+-- cannot store the same key in the meantime. An upload claims the record
+-- before storing its bytes (stored = false) and marks it stored afterwards;
+-- only stored blobs are shared.
+CREATE TABLE file_blobs (
+    storage_path    TEXT PRIMARY KEY,
+    checksum        VARCHAR(128) NOT NULL,
+    size_bytes      BIGINT NOT NULL,
+    thumbnail_path  TEXT,
+    ref_count       BIGINT NOT NULL DEFAULT 1,
+    stored          BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_file_blobs_checksum ON file_blobs(checksum);
+CREATE INDEX idx_files_storage_path ON files(storage_path);
+
+-- Files uploaded before deduplication each own their blob
+INSERT INTO file_blobs (storage_path, checksum, size_bytes, thumbnail_path, ref_count, created_at)
+SELECT storage_path, MIN(checksum), MAX(size_bytes), MAX(thumbnail_path), COUNT(*), MIN(created_at)
+FROM files
+GROUP BY storage_path;
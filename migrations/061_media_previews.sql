@@ -0,0 +1,4 @@
+-- Playing time of video and audio uploads, read by ffprobe when the blob is
+-- stored. Poster frames, waveforms and PDF pages reuse thumbnail_path.
+ALTER TABLE files ADD COLUMN duration_ms BIGINT;
+ALTER TABLE file_blobs ADD COLUMN duration_ms BIGINT;
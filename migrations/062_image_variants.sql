@@ -0,0 +1,10 @@
+-- Image dimensions, a BlurHash placeholder, and the widths of the WebP
+-- variants stored next to the blob (comma-separated, e.g. '300,800,1600').
+ALTER TABLE files ADD COLUMN width INTEGER;
+ALTER TABLE files ADD COLUMN height INTEGER;
+ALTER TABLE files ADD COLUMN blurhash TEXT;
+ALTER TABLE files ADD COLUMN variants TEXT;
+ALTER TABLE file_blobs ADD COLUMN width INTEGER;
+ALTER TABLE file_blobs ADD COLUMN height INTEGER;
+ALTER TABLE file_blobs ADD COLUMN blurhash TEXT;
+ALTER TABLE file_blobs ADD COLUMN variants TEXT;
@@ -0,0 +1,7 @@
+-- Perceptual hashes (64-bit dHash, 16 hex digits) of images, so near-copies
+-- of a blocked image can be matched by Hamming distance. Blocklist entries
+-- are either exact SHA-256 checksums or dHashes with a maximum distance.
+ALTER TABLE files ADD COLUMN phash VARCHAR(16);
+ALTER TABLE file_blobs ADD COLUMN phash VARCHAR(16);
+ALTER TABLE blocked_hashes ADD COLUMN kind VARCHAR(16) NOT NULL DEFAULT 'sha256';
+ALTER TABLE blocked_hashes ADD COLUMN max_distance INTEGER;
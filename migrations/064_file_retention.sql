@@ -0,0 +1,19 @@
+-- File retention policies of channels and communities, enforced by the
+-- file_retention job. When a channel and its community both have a policy,
+-- each applies.
+CREATE TABLE IF NOT EXISTS file_retention_policies (
+    -- 'channel' or 'community'
+    scope_type VARCHAR(16) NOT NULL,
+    scope_id UUID NOT NULL,
+    -- Files older than this are deleted
+    max_age_days INTEGER,
+    -- Beyond this total size, the oldest files are deleted
+    max_total_bytes BIGINT,
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (scope_type, scope_id),
+    CONSTRAINT file_retention_policies_scope_check
+        CHECK (scope_type IN ('channel', 'community'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_files_channel_created ON files(channel_id, created_at);
@@ -0,0 +1,18 @@
+-- Storage quotas beyond the per-user UPLOAD_QUOTA_MB: the total size of the
+-- files posted in a community or group, and per-role overrides of the
+-- per-user quota for uploads into a community.
+CREATE TABLE IF NOT EXISTS storage_quotas (
+    -- 'community', 'group' or 'role'
+    scope_type VARCHAR(16) NOT NULL,
+    -- Community ID ('community' and 'role') or group ID ('group')
+    scope_id UUID NOT NULL,
+    -- Community role of a 'role' quota, empty otherwise
+    role VARCHAR(16) NOT NULL DEFAULT '',
+    -- 0 = unlimited ('role' only)
+    max_bytes BIGINT NOT NULL,
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (scope_type, scope_id, role),
+    CONSTRAINT storage_quotas_scope_check
+        CHECK (scope_type IN ('community', 'group', 'role'))
+);
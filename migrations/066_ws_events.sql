@@ -0,0 +1,11 @@
+-- Realtime events raised by cluster jobs. A cluster job runs on one node but
+-- its recipients may be connected to any node, so the job records the event
+-- here and every node's event_relay job delivers it to its own connections.
+-- Rows are only needed for a few minutes and are pruned by the relay.
+CREATE TABLE ws_events (
+    id UUID PRIMARY KEY,
+    event TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_ws_events_created_at ON ws_events (created_at);
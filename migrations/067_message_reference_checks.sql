@@ -0,0 +1,60 @@
+-- 058 had to drop the foreign keys onto messages(id): a partitioned table's
+-- primary key includes the partition key, and a message may also live in
+-- archived_messages. delete_message_dependents() took over the delete side
+-- (cascades and SET NULL); this restores the insert side.
+--
+-- A row that names a message is only accepted if that message exists in
+-- messages or archived_messages. The referenced row is locked FOR KEY SHARE,
+-- as a foreign key check would, so it cannot be deleted before the insert
+-- commits. What is still given up compared to real foreign keys:
+--   * messages.id is only unique per partition, and not across the live and
+--     archive tables. Ids are server-generated UUIDv7s, so this is not
+--     enforced.
+--   * messages.reply_to_id and messages.thread_id used to block deleting a
+--     message that had replies. They are now left pointing at the deleted
+--     message, which readers already treat as "original message deleted".
+--   * Archiving moves a message between tables. References follow it by id,
+--     but the check only runs on insert and on updates of the column, not
+--     when the referenced message moves.
+
+CREATE FUNCTION check_message_reference() RETURNS trigger LANGUAGE plpgsql AS $$
+DECLARE
+    ref UUID := (to_jsonb(NEW) ->> TG_ARGV[0])::uuid;
+BEGIN
+    IF ref IS NULL THEN
+        RETURN NEW;
+    END IF;
+    PERFORM 1 FROM messages WHERE id = ref FOR KEY SHARE;
+    IF FOUND THEN
+        RETURN NEW;
+    END IF;
+    PERFORM 1 FROM archived_messages WHERE id = ref FOR KEY SHARE;
+    IF FOUND THEN
+        RETURN NEW;
+    END IF;
+    RAISE foreign_key_violation
+        USING MESSAGE = format('%I.%I references missing message %s', TG_TABLE_NAME, TG_ARGV[0], ref);
+END;
+$$;
+
+CREATE TRIGGER pinned_messages_check_message
+    BEFORE INSERT OR UPDATE OF message_id ON pinned_messages
+    FOR EACH ROW EXECUTE FUNCTION check_message_reference('message_id');
+CREATE TRIGGER bookmarks_check_message
+    BEFORE INSERT OR UPDATE OF message_id ON bookmarks
+    FOR EACH ROW EXECUTE FUNCTION check_message_reference('message_id');
+CREATE TRIGGER message_deliveries_check_message
+    BEFORE INSERT OR UPDATE OF message_id ON message_deliveries
+    FOR EACH ROW EXECUTE FUNCTION check_message_reference('message_id');
+CREATE TRIGGER message_mentions_check_message
+    BEFORE INSERT OR UPDATE OF message_id ON message_mentions
+    FOR EACH ROW EXECUTE FUNCTION check_message_reference('message_id');
+CREATE TRIGGER read_cursors_check_message
+    BEFORE INSERT OR UPDATE OF last_read_message_id ON read_cursors
+    FOR EACH ROW EXECUTE FUNCTION check_message_reference('last_read_message_id');
+CREATE TRIGGER messages_check_reply_to
+    BEFORE INSERT OR UPDATE OF reply_to_id ON messages
+    FOR EACH ROW EXECUTE FUNCTION check_message_reference('reply_to_id');
+CREATE TRIGGER messages_check_thread
+    BEFORE INSERT OR UPDATE OF thread_id ON messages
+    FOR EACH ROW EXECUTE FUNCTION check_message_reference('thread_id');
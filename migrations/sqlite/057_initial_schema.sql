@@ -0,0 +1,760 @@
+-- SQLite schema, equivalent to the Postgres migrations 001-057.
+--
+-- SQLite installs start from this snapshot; later migrations are numbered to
+-- match their Postgres counterparts so schema versions mean the same thing on
+-- both backends.
+--
+-- Type mapping: UUID -> BLOB (16 bytes), TIMESTAMPTZ -> TEXT in
+-- '%Y-%m-%dT%H:%M:%fZ' (UTC, milliseconds), JSONB and TEXT[] -> TEXT holding
+-- JSON, the channel_type enum -> TEXT with a CHECK.
+
+CREATE TABLE announcement_dismissals (
+    user_id BLOB NOT NULL,
+    announcement_id BLOB NOT NULL,
+    dismissed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT announcement_dismissals_pkey PRIMARY KEY (user_id, announcement_id),
+    CONSTRAINT announcement_dismissals_announcement_id_fkey FOREIGN KEY (announcement_id) REFERENCES announcements(id) ON DELETE CASCADE,
+    CONSTRAINT announcement_dismissals_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE announcements (
+    id BLOB NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_by BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT announcements_pkey PRIMARY KEY (id),
+    CONSTRAINT announcements_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE audit_log (
+    id BLOB NOT NULL,
+    user_id BLOB,
+    action TEXT NOT NULL,
+    ip_address TEXT,
+    user_agent TEXT,
+    metadata TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT audit_log_pkey PRIMARY KEY (id),
+    CONSTRAINT audit_log_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE blocked_hashes (
+    id BLOB NOT NULL,
+    hash TEXT NOT NULL,
+    reason TEXT,
+    blocked_by BLOB,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT blocked_hashes_pkey PRIMARY KEY (id),
+    CONSTRAINT blocked_hashes_hash_key UNIQUE (hash),
+    CONSTRAINT blocked_hashes_blocked_by_fkey FOREIGN KEY (blocked_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE bookmarks (
+    id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    message_id BLOB NOT NULL,
+    note TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT bookmarks_pkey PRIMARY KEY (id),
+    CONSTRAINT bookmarks_user_id_message_id_key UNIQUE (user_id, message_id),
+    CONSTRAINT bookmarks_message_id_fkey FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE,
+    CONSTRAINT bookmarks_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE channel_bans (
+    channel_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    banned_by BLOB,
+    reason TEXT,
+    banned_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT channel_bans_pkey PRIMARY KEY (channel_id, user_id),
+    CONSTRAINT channel_bans_banned_by_fkey FOREIGN KEY (banned_by) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT channel_bans_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT channel_bans_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE channel_members (
+    channel_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    role TEXT NOT NULL DEFAULT 'member',
+    joined_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT channel_members_pkey PRIMARY KEY (channel_id, user_id),
+    CONSTRAINT channel_members_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT channel_members_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE channel_slowmode_tracker (
+    channel_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    last_sent TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT channel_slowmode_tracker_pkey PRIMARY KEY (channel_id, user_id),
+    CONSTRAINT channel_slowmode_tracker_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT channel_slowmode_tracker_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE channels (
+    id BLOB NOT NULL,
+    name TEXT,
+    channel_type TEXT NOT NULL CHECK (channel_type IN ('text', 'voice', 'dm', 'gallery')),
+    topic TEXT,
+    created_by BLOB,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    group_id BLOB,
+    read_only BOOLEAN NOT NULL DEFAULT false,
+    slow_mode_seconds INTEGER NOT NULL DEFAULT 0,
+    message_ttl_seconds INTEGER,
+    discoverable BOOLEAN NOT NULL DEFAULT true,
+    archived BOOLEAN NOT NULL DEFAULT false,
+    voice_background TEXT,
+    CONSTRAINT channels_pkey PRIMARY KEY (id),
+    CONSTRAINT channels_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT channels_group_id_fkey FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE
+);
+
+CREATE TABLE communities (
+    id BLOB NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    icon_url TEXT,
+    owner_id BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    who_can_create_groups TEXT NOT NULL DEFAULT 'admin',
+    who_can_create_invites TEXT NOT NULL DEFAULT 'admin',
+    warn_escalation TEXT,
+    discoverable BOOLEAN NOT NULL DEFAULT true,
+    community_theme TEXT,
+    banner_url TEXT,
+    welcome_message TEXT,
+    CONSTRAINT communities_pkey PRIMARY KEY (id),
+    CONSTRAINT communities_owner_id_fkey FOREIGN KEY (owner_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE community_bans (
+    community_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    banned_by BLOB,
+    reason TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT community_bans_pkey PRIMARY KEY (community_id, user_id),
+    CONSTRAINT community_bans_banned_by_fkey FOREIGN KEY (banned_by) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT community_bans_community_id_fkey FOREIGN KEY (community_id) REFERENCES communities(id) ON DELETE CASCADE,
+    CONSTRAINT community_bans_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE community_invites (
+    id BLOB NOT NULL,
+    community_id BLOB NOT NULL,
+    code TEXT NOT NULL,
+    created_by BLOB,
+    max_uses INTEGER,
+    used_count INTEGER NOT NULL DEFAULT 0,
+    expires_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT community_invites_pkey PRIMARY KEY (id),
+    CONSTRAINT community_invites_code_key UNIQUE (code),
+    CONSTRAINT community_invites_community_id_fkey FOREIGN KEY (community_id) REFERENCES communities(id) ON DELETE CASCADE,
+    CONSTRAINT community_invites_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE community_members (
+    community_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    role TEXT NOT NULL DEFAULT 'member',
+    nickname TEXT,
+    joined_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT community_members_pkey PRIMARY KEY (community_id, user_id),
+    CONSTRAINT community_members_community_id_fkey FOREIGN KEY (community_id) REFERENCES communities(id) ON DELETE CASCADE,
+    CONSTRAINT community_members_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE custom_emojis (
+    id BLOB NOT NULL,
+    community_id BLOB NOT NULL,
+    shortcode TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    uploaded_by BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT custom_emojis_pkey PRIMARY KEY (id),
+    CONSTRAINT custom_emojis_community_id_shortcode_key UNIQUE (community_id, shortcode),
+    CONSTRAINT custom_emojis_community_id_fkey FOREIGN KEY (community_id) REFERENCES communities(id) ON DELETE CASCADE,
+    CONSTRAINT custom_emojis_uploaded_by_fkey FOREIGN KEY (uploaded_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE deferred_push_notifications (
+    id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    notification_type TEXT NOT NULL,
+    sender_name TEXT NOT NULL,
+    channel_id BLOB NOT NULL,
+    channel_name TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT deferred_push_notifications_pkey PRIMARY KEY (id),
+    CONSTRAINT deferred_push_user_channel_key UNIQUE (user_id, channel_id),
+    CONSTRAINT deferred_push_notifications_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE dm_pairs (
+    user_a BLOB NOT NULL,
+    user_b BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT dm_pairs_pkey PRIMARY KEY (user_a, user_b),
+    CONSTRAINT dm_pairs_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT dm_pairs_user_a_fkey FOREIGN KEY (user_a) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT dm_pairs_user_b_fkey FOREIGN KEY (user_b) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT dm_pairs_ordered CHECK ((user_a < user_b))
+);
+
+CREATE TABLE files (
+    id BLOB NOT NULL,
+    uploader_id BLOB,
+    encrypted_name TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    content_type TEXT,
+    storage_path TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    channel_id BLOB,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    quarantined_at TEXT,
+    quarantined_by BLOB,
+    thumbnail_path TEXT,
+    exif_stripped BOOLEAN NOT NULL DEFAULT false,
+    CONSTRAINT files_pkey PRIMARY KEY (id),
+    CONSTRAINT files_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE SET NULL,
+    CONSTRAINT files_quarantined_by_fkey FOREIGN KEY (quarantined_by) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT files_uploader_id_fkey FOREIGN KEY (uploader_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE group_invites (
+    id BLOB NOT NULL,
+    group_id BLOB NOT NULL,
+    created_by BLOB,
+    code TEXT NOT NULL,
+    max_uses INTEGER,
+    used_count INTEGER NOT NULL DEFAULT 0,
+    expires_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT group_invites_pkey PRIMARY KEY (id),
+    CONSTRAINT group_invites_code_key UNIQUE (code),
+    CONSTRAINT group_invites_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT group_invites_group_id_fkey FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE
+);
+
+CREATE TABLE group_members (
+    group_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    role TEXT NOT NULL DEFAULT 'member',
+    joined_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT group_members_pkey PRIMARY KEY (group_id, user_id),
+    CONSTRAINT group_members_group_id_fkey FOREIGN KEY (group_id) REFERENCES groups(id) ON DELETE CASCADE,
+    CONSTRAINT group_members_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE groups (
+    id BLOB NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    owner_id BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    community_id BLOB NOT NULL,
+    visibility TEXT NOT NULL DEFAULT 'public',
+    discoverable BOOLEAN NOT NULL DEFAULT true,
+    assigned_member_id BLOB,
+    allow_invites BOOLEAN NOT NULL DEFAULT false,
+    icon_url TEXT,
+    banner_url TEXT,
+    accent_color TEXT,
+    CONSTRAINT groups_pkey PRIMARY KEY (id),
+    CONSTRAINT groups_assigned_member_id_fkey FOREIGN KEY (assigned_member_id) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT groups_community_id_fkey FOREIGN KEY (community_id) REFERENCES communities(id) ON DELETE CASCADE,
+    CONSTRAINT groups_owner_id_fkey FOREIGN KEY (owner_id) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE identity_keys (
+    user_id BLOB NOT NULL,
+    identity_key BLOB NOT NULL,
+    fingerprint TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    rotated_at TEXT,
+    CONSTRAINT identity_keys_pkey PRIMARY KEY (user_id),
+    CONSTRAINT identity_keys_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE instance_settings (
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT instance_settings_pkey PRIMARY KEY (key)
+);
+
+CREATE TABLE jobs (
+    name TEXT NOT NULL,
+    next_run_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    lease_owner BLOB,
+    lease_expires_at TEXT,
+    last_started_at TEXT,
+    last_finished_at TEXT,
+    last_success_at TEXT,
+    last_duration_ms INTEGER,
+    last_error TEXT,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    run_count INTEGER NOT NULL DEFAULT 0,
+    CONSTRAINT jobs_pkey PRIMARY KEY (name)
+);
+
+CREATE TABLE message_deliveries (
+    message_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    delivered_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT message_deliveries_pkey PRIMARY KEY (message_id, user_id),
+    CONSTRAINT message_deliveries_message_id_fkey FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE,
+    CONSTRAINT message_deliveries_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE message_edits (
+    id BLOB NOT NULL DEFAULT (randomblob(16)),
+    message_id BLOB NOT NULL,
+    old_ciphertext BLOB NOT NULL,
+    old_nonce BLOB NOT NULL,
+    edited_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT message_edits_pkey PRIMARY KEY (id),
+    CONSTRAINT message_edits_message_id_fkey FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE
+);
+
+CREATE TABLE message_mentions (
+    message_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'user',
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT message_mentions_pkey PRIMARY KEY (message_id, user_id),
+    CONSTRAINT message_mentions_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT message_mentions_message_id_fkey FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE,
+    CONSTRAINT message_mentions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE messages (
+    id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    sender_id BLOB,
+    ciphertext BLOB NOT NULL,
+    nonce BLOB NOT NULL,
+    message_type TEXT NOT NULL DEFAULT 'text',
+    sender_key_id BLOB,
+    reply_to_id BLOB,
+    edited_at TEXT,
+    deleted_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    quarantined_at TEXT,
+    quarantined_by BLOB,
+    plaintext TEXT,
+    expires_at TEXT,
+    thread_id BLOB,
+    CONSTRAINT messages_pkey PRIMARY KEY (id),
+    CONSTRAINT messages_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT messages_quarantined_by_fkey FOREIGN KEY (quarantined_by) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT messages_reply_to_id_fkey FOREIGN KEY (reply_to_id) REFERENCES messages(id),
+    CONSTRAINT messages_sender_id_fkey FOREIGN KEY (sender_id) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT messages_thread_id_fkey FOREIGN KEY (thread_id) REFERENCES messages(id)
+);
+
+CREATE TABLE notification_settings (
+    user_id BLOB NOT NULL,
+    scope_type TEXT NOT NULL,
+    scope_id BLOB NOT NULL,
+    level TEXT NOT NULL DEFAULT 'all',
+    muted_until TEXT,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT notification_settings_pkey PRIMARY KEY (user_id, scope_type, scope_id),
+    CONSTRAINT notification_settings_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT notification_settings_level_check CHECK (level IN ('all', 'mentions', 'nothing')),
+    CONSTRAINT notification_settings_scope_check CHECK (scope_type IN ('channel', 'dm', 'group', 'community'))
+);
+
+CREATE TABLE one_time_prekeys (
+    id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    key_id INTEGER NOT NULL,
+    public_key BLOB NOT NULL,
+    used BOOLEAN NOT NULL DEFAULT false,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT one_time_prekeys_pkey PRIMARY KEY (id),
+    CONSTRAINT one_time_prekeys_user_id_key_id_key UNIQUE (user_id, key_id),
+    CONSTRAINT one_time_prekeys_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE pinned_messages (
+    message_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    pinned_by BLOB NOT NULL,
+    pinned_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT pinned_messages_pkey PRIMARY KEY (message_id),
+    CONSTRAINT pinned_messages_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT pinned_messages_message_id_fkey FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE,
+    CONSTRAINT pinned_messages_pinned_by_fkey FOREIGN KEY (pinned_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE poll_votes (
+    id BLOB NOT NULL,
+    poll_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    option_index INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT poll_votes_pkey PRIMARY KEY (id),
+    CONSTRAINT poll_votes_poll_id_user_id_option_index_key UNIQUE (poll_id, user_id, option_index),
+    CONSTRAINT poll_votes_poll_id_fkey FOREIGN KEY (poll_id) REFERENCES polls(id) ON DELETE CASCADE,
+    CONSTRAINT poll_votes_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE polls (
+    id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    created_by BLOB NOT NULL,
+    question TEXT NOT NULL,
+    options TEXT NOT NULL,
+    multi_select BOOLEAN NOT NULL DEFAULT false,
+    anonymous BOOLEAN NOT NULL DEFAULT false,
+    closed BOOLEAN NOT NULL DEFAULT false,
+    expires_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT polls_pkey PRIMARY KEY (id),
+    CONSTRAINT polls_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT polls_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE push_subscriptions (
+    id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    endpoint TEXT NOT NULL,
+    p256dh_key TEXT,
+    auth_key TEXT,
+    user_agent TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    last_used TEXT,
+    failure_count INTEGER NOT NULL DEFAULT 0,
+    kind TEXT NOT NULL DEFAULT 'webpush',
+    CONSTRAINT push_subscriptions_pkey PRIMARY KEY (id),
+    CONSTRAINT push_subscriptions_user_id_endpoint_key UNIQUE (user_id, endpoint),
+    CONSTRAINT push_subscriptions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT push_subscriptions_kind_check CHECK (kind IN ('webpush', 'unifiedpush')),
+    CONSTRAINT push_subscriptions_webpush_keys_check CHECK (((kind <> 'webpush') OR ((p256dh_key IS NOT NULL) AND (auth_key IS NOT NULL))))
+);
+
+CREATE TABLE quiet_hours_breakthrough (
+    user_id BLOB NOT NULL,
+    allowed_user_id BLOB NOT NULL,
+    CONSTRAINT quiet_hours_breakthrough_pkey PRIMARY KEY (user_id, allowed_user_id),
+    CONSTRAINT quiet_hours_breakthrough_allowed_user_id_fkey FOREIGN KEY (allowed_user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT quiet_hours_breakthrough_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE quiet_hours_schedules (
+    id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    days INTEGER NOT NULL,
+    start_minute INTEGER NOT NULL,
+    end_minute INTEGER NOT NULL,
+    CONSTRAINT quiet_hours_schedules_pkey PRIMARY KEY (id),
+    CONSTRAINT quiet_hours_schedules_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT quiet_hours_days_check CHECK (((days >= 1) AND (days <= 127))),
+    CONSTRAINT quiet_hours_end_check CHECK (((end_minute >= 0) AND (end_minute <= 1439))),
+    CONSTRAINT quiet_hours_start_check CHECK (((start_minute >= 0) AND (start_minute <= 1439))),
+    CONSTRAINT quiet_hours_window_check CHECK ((start_minute <> end_minute))
+);
+
+CREATE TABLE quiet_hours_settings (
+    user_id BLOB NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    timezone TEXT NOT NULL DEFAULT 'UTC',
+    delivery TEXT NOT NULL DEFAULT 'suppress',
+    auto_dnd BOOLEAN NOT NULL DEFAULT true,
+    quiet_active BOOLEAN NOT NULL DEFAULT false,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT quiet_hours_settings_pkey PRIMARY KEY (user_id),
+    CONSTRAINT quiet_hours_settings_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT quiet_hours_delivery_check CHECK (delivery IN ('suppress', 'batch'))
+);
+
+CREATE TABLE reactions (
+    id BLOB NOT NULL DEFAULT (randomblob(16)),
+    message_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    emoji TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT reactions_pkey PRIMARY KEY (id),
+    CONSTRAINT reactions_message_id_user_id_emoji_key UNIQUE (message_id, user_id, emoji),
+    CONSTRAINT reactions_message_id_fkey FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE,
+    CONSTRAINT reactions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE read_cursors (
+    user_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    last_read_message_id BLOB,
+    last_read_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT read_cursors_pkey PRIMARY KEY (user_id, channel_id),
+    CONSTRAINT read_cursors_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT read_cursors_last_read_message_id_fkey FOREIGN KEY (last_read_message_id) REFERENCES messages(id) ON DELETE SET NULL,
+    CONSTRAINT read_cursors_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE refresh_tokens (
+    id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    token_hash BLOB NOT NULL,
+    device_name TEXT,
+    ip_address TEXT,
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    revoked_at TEXT,
+    CONSTRAINT refresh_tokens_pkey PRIMARY KEY (id),
+    CONSTRAINT refresh_tokens_token_hash_key UNIQUE (token_hash),
+    CONSTRAINT refresh_tokens_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE registration_invites (
+    id BLOB NOT NULL,
+    code TEXT NOT NULL,
+    created_by BLOB,
+    max_uses INTEGER,
+    used_count INTEGER NOT NULL DEFAULT 0,
+    expires_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT registration_invites_pkey PRIMARY KEY (id),
+    CONSTRAINT registration_invites_code_key UNIQUE (code),
+    CONSTRAINT registration_invites_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE reports (
+    id BLOB NOT NULL,
+    reporter_id BLOB NOT NULL,
+    report_type TEXT NOT NULL,
+    target_id BLOB NOT NULL,
+    reason TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    reviewed_by BLOB,
+    reviewed_at TEXT,
+    admin_notes TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT reports_pkey PRIMARY KEY (id),
+    CONSTRAINT reports_reporter_id_fkey FOREIGN KEY (reporter_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT reports_reviewed_by_fkey FOREIGN KEY (reviewed_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE scheduled_messages (
+    id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    ciphertext TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    scheduled_for TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    content_preview TEXT,
+    CONSTRAINT scheduled_messages_pkey PRIMARY KEY (id),
+    CONSTRAINT scheduled_messages_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT scheduled_messages_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE sender_key_distributions (
+    id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    chain_id INTEGER NOT NULL,
+    distribution TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT sender_key_distributions_pkey PRIMARY KEY (id),
+    CONSTRAINT sender_key_distributions_channel_id_user_id_key UNIQUE (channel_id, user_id),
+    CONSTRAINT sender_key_distributions_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT sender_key_distributions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE signed_prekeys (
+    id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    key_id INTEGER NOT NULL,
+    public_key BLOB NOT NULL,
+    signature BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT signed_prekeys_pkey PRIMARY KEY (id),
+    CONSTRAINT signed_prekeys_user_id_key_id_key UNIQUE (user_id, key_id),
+    CONSTRAINT signed_prekeys_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE user_blocks (
+    blocker_id BLOB NOT NULL,
+    blocked_id BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT user_blocks_pkey PRIMARY KEY (blocker_id, blocked_id),
+    CONSTRAINT user_blocks_blocked_id_fkey FOREIGN KEY (blocked_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT user_blocks_blocker_id_fkey FOREIGN KEY (blocker_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT user_blocks_check CHECK ((blocker_id <> blocked_id))
+);
+
+CREATE TABLE user_preferences (
+    user_id BLOB NOT NULL,
+    preferences TEXT NOT NULL DEFAULT '{}',
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT user_preferences_pkey PRIMARY KEY (user_id),
+    CONSTRAINT user_preferences_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE user_timeouts (
+    id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    issued_by BLOB NOT NULL,
+    reason TEXT,
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT user_timeouts_pkey PRIMARY KEY (id),
+    CONSTRAINT user_timeouts_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT user_timeouts_issued_by_fkey FOREIGN KEY (issued_by) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT user_timeouts_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE users (
+    id BLOB NOT NULL,
+    username TEXT NOT NULL,
+    display_name TEXT NOT NULL,
+    email TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    avatar_url TEXT,
+    status TEXT NOT NULL DEFAULT 'offline',
+    custom_status TEXT,
+    totp_secret BLOB,
+    totp_enabled BOOLEAN NOT NULL DEFAULT false,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    is_admin BOOLEAN NOT NULL DEFAULT false,
+    suspended_at TEXT,
+    suspended_reason TEXT,
+    is_owner BOOLEAN NOT NULL DEFAULT false,
+    upload_bytes_used INTEGER NOT NULL DEFAULT 0,
+    bio TEXT,
+    pronouns TEXT,
+    banner_url TEXT,
+    recovery_code_hash TEXT,
+    totp_backup_codes TEXT,
+    voice_background_url TEXT,
+    CONSTRAINT users_pkey PRIMARY KEY (id),
+    CONSTRAINT users_email_key UNIQUE (email),
+    CONSTRAINT users_username_key UNIQUE (username)
+);
+
+CREATE TABLE voice_session_participants (
+    session_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    joined_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    left_at TEXT,
+    CONSTRAINT voice_session_participants_pkey PRIMARY KEY (session_id, user_id),
+    CONSTRAINT voice_session_participants_session_id_fkey FOREIGN KEY (session_id) REFERENCES voice_sessions(id) ON DELETE CASCADE,
+    CONSTRAINT voice_session_participants_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE voice_sessions (
+    id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    started_by BLOB,
+    started_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    ended_at TEXT,
+    CONSTRAINT voice_sessions_pkey PRIMARY KEY (id),
+    CONSTRAINT voice_sessions_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT voice_sessions_started_by_fkey FOREIGN KEY (started_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE warnings (
+    id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    issued_by BLOB NOT NULL,
+    reason TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT warnings_pkey PRIMARY KEY (id),
+    CONSTRAINT warnings_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT warnings_issued_by_fkey FOREIGN KEY (issued_by) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT warnings_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE TABLE webhooks (
+    id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    name TEXT NOT NULL,
+    token TEXT NOT NULL,
+    created_by BLOB NOT NULL,
+    avatar_url TEXT,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT webhooks_pkey PRIMARY KEY (id),
+    CONSTRAINT webhooks_token_key UNIQUE (token),
+    CONSTRAINT webhooks_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT webhooks_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_audit_action ON audit_log (action, created_at DESC);
+CREATE INDEX idx_audit_user ON audit_log (user_id, created_at DESC);
+CREATE INDEX idx_blocked_hashes_hash ON blocked_hashes (hash);
+CREATE INDEX idx_bookmarks_user ON bookmarks (user_id);
+CREATE INDEX idx_channel_members_user ON channel_members (user_id);
+CREATE INDEX idx_channels_group ON channels (group_id) WHERE (group_id IS NOT NULL);
+CREATE INDEX idx_community_invites_code ON community_invites (code);
+CREATE INDEX idx_community_members_community ON community_members (community_id);
+CREATE INDEX idx_community_members_user ON community_members (user_id);
+CREATE UNIQUE INDEX idx_dm_pairs_channel ON dm_pairs (channel_id);
+CREATE INDEX idx_dm_pairs_user_b ON dm_pairs (user_b);
+CREATE INDEX idx_emojis_community ON custom_emojis (community_id);
+CREATE INDEX idx_files_channel ON files (channel_id);
+CREATE INDEX idx_files_created ON files (created_at DESC);
+CREATE INDEX idx_files_quarantined ON files (quarantined_at) WHERE (quarantined_at IS NOT NULL);
+CREATE INDEX idx_files_size ON files (size_bytes DESC);
+CREATE INDEX idx_files_uploader ON files (uploader_id);
+CREATE INDEX idx_group_invites_code ON group_invites (code);
+CREATE INDEX idx_group_invites_group ON group_invites (group_id);
+CREATE INDEX idx_group_members_user ON group_members (user_id);
+CREATE INDEX idx_message_deliveries_user ON message_deliveries (user_id);
+CREATE INDEX idx_message_edits_message_id ON message_edits (message_id);
+CREATE INDEX idx_message_mentions_channel_user ON message_mentions (channel_id, user_id);
+CREATE INDEX idx_message_mentions_user ON message_mentions (user_id, created_at DESC);
+CREATE INDEX idx_messages_channel ON messages (channel_id, created_at DESC);
+CREATE INDEX idx_messages_channel_active ON messages (channel_id, created_at DESC) WHERE (deleted_at IS NULL);
+CREATE INDEX idx_messages_deleted ON messages (deleted_at) WHERE (deleted_at IS NOT NULL);
+CREATE INDEX idx_messages_expires ON messages (expires_at) WHERE (expires_at IS NOT NULL);
+CREATE INDEX idx_messages_quarantined ON messages (quarantined_at) WHERE (quarantined_at IS NOT NULL);
+CREATE INDEX idx_messages_sender ON messages (sender_id, created_at DESC);
+CREATE INDEX idx_messages_thread ON messages (thread_id, created_at) WHERE (thread_id IS NOT NULL);
+CREATE INDEX idx_notification_settings_scope ON notification_settings (scope_type, scope_id);
+CREATE INDEX idx_otp_available ON one_time_prekeys (user_id, used) WHERE (NOT used);
+CREATE INDEX idx_pinned_messages_channel ON pinned_messages (channel_id, pinned_at DESC);
+CREATE INDEX idx_poll_votes_poll ON poll_votes (poll_id);
+CREATE INDEX idx_push_subs_user ON push_subscriptions (user_id);
+CREATE INDEX idx_quiet_hours_schedules_user ON quiet_hours_schedules (user_id);
+CREATE INDEX idx_reactions_message ON reactions (message_id);
+CREATE INDEX idx_read_cursors_channel ON read_cursors (channel_id);
+CREATE INDEX idx_read_cursors_user_channel ON read_cursors (user_id, channel_id);
+CREATE INDEX idx_refresh_active ON refresh_tokens (user_id) WHERE (revoked_at IS NULL);
+CREATE INDEX idx_registration_invites_code ON registration_invites (code);
+CREATE INDEX idx_reports_reporter ON reports (reporter_id);
+CREATE INDEX idx_reports_status ON reports (status) WHERE (status = 'pending');
+CREATE INDEX idx_reports_target ON reports (target_id);
+CREATE INDEX idx_scheduled_due ON scheduled_messages (scheduled_for);
+CREATE INDEX idx_scheduled_messages_user ON scheduled_messages (user_id);
+CREATE INDEX idx_sender_key_dist_channel ON sender_key_distributions (channel_id);
+CREATE INDEX idx_signed_prekeys_user_latest ON signed_prekeys (user_id, created_at DESC);
+CREATE INDEX idx_timeouts_expires ON user_timeouts (expires_at);
+CREATE INDEX idx_timeouts_user_channel ON user_timeouts (user_id, channel_id);
+CREATE INDEX idx_user_blocks_blocked ON user_blocks (blocked_id);
+CREATE INDEX idx_user_blocks_blocker ON user_blocks (blocker_id);
+CREATE INDEX idx_users_email ON users (email);
+CREATE INDEX idx_users_upload_bytes ON users (upload_bytes_used DESC);
+CREATE INDEX idx_users_username ON users (username);
+CREATE INDEX idx_voice_participants_user ON voice_session_participants (user_id) WHERE (left_at IS NULL);
+CREATE INDEX idx_voice_sessions_channel ON voice_sessions (channel_id) WHERE (ended_at IS NULL);
+CREATE INDEX idx_warnings_channel ON warnings (user_id, channel_id);
+CREATE INDEX idx_warnings_user ON warnings (user_id);
+CREATE INDEX idx_webhooks_channel ON webhooks (channel_id);
+CREATE INDEX idx_webhooks_token ON webhooks (token);
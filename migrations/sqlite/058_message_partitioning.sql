@@ -0,0 +1,62 @@
+-- SQLite counterpart of 058: SQLite has no table partitioning and the
+-- archival job only runs on Postgres, so this adds just the columns and
+-- tables the shared queries rely on. The archive tables stay empty.
+
+ALTER TABLE reactions ADD COLUMN message_created_at TEXT NOT NULL DEFAULT '';
+UPDATE reactions SET message_created_at = (SELECT m.created_at FROM messages m WHERE m.id = reactions.message_id);
+CREATE UNIQUE INDEX reactions_message_id_user_id_emoji_created_key
+    ON reactions(message_id, user_id, emoji, message_created_at);
+
+ALTER TABLE message_edits ADD COLUMN message_created_at TEXT NOT NULL DEFAULT '';
+UPDATE message_edits SET message_created_at = (SELECT m.created_at FROM messages m WHERE m.id = message_edits.message_id);
+
+CREATE TABLE archived_messages (
+    id BLOB NOT NULL,
+    channel_id BLOB NOT NULL,
+    sender_id BLOB,
+    ciphertext BLOB NOT NULL,
+    nonce BLOB NOT NULL,
+    message_type TEXT NOT NULL DEFAULT 'text',
+    sender_key_id BLOB,
+    reply_to_id BLOB,
+    edited_at TEXT,
+    deleted_at TEXT,
+    created_at TEXT NOT NULL,
+    quarantined_at TEXT,
+    quarantined_by BLOB,
+    plaintext TEXT,
+    expires_at TEXT,
+    thread_id BLOB,
+    CONSTRAINT archived_messages_pkey PRIMARY KEY (id),
+    CONSTRAINT archived_messages_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE,
+    CONSTRAINT archived_messages_sender_id_fkey FOREIGN KEY (sender_id) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT archived_messages_quarantined_by_fkey FOREIGN KEY (quarantined_by) REFERENCES users(id) ON DELETE SET NULL
+);
+CREATE INDEX idx_archived_messages_channel ON archived_messages(channel_id, created_at DESC);
+CREATE INDEX idx_archived_messages_sender ON archived_messages(sender_id);
+CREATE INDEX idx_archived_messages_expires ON archived_messages(expires_at) WHERE expires_at IS NOT NULL;
+
+CREATE TABLE archived_reactions (
+    id BLOB NOT NULL,
+    message_id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    emoji TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    message_created_at TEXT NOT NULL,
+    CONSTRAINT archived_reactions_pkey PRIMARY KEY (id),
+    CONSTRAINT archived_reactions_message_id_fkey FOREIGN KEY (message_id) REFERENCES archived_messages(id) ON DELETE CASCADE,
+    CONSTRAINT archived_reactions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_archived_reactions_message ON archived_reactions(message_id);
+
+CREATE TABLE archived_message_edits (
+    id BLOB NOT NULL,
+    message_id BLOB NOT NULL,
+    old_ciphertext BLOB NOT NULL,
+    old_nonce BLOB NOT NULL,
+    edited_at TEXT NOT NULL,
+    message_created_at TEXT NOT NULL,
+    CONSTRAINT archived_message_edits_pkey PRIMARY KEY (id),
+    CONSTRAINT archived_message_edits_message_id_fkey FOREIGN KEY (message_id) REFERENCES archived_messages(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_archived_message_edits_message ON archived_message_edits(message_id);
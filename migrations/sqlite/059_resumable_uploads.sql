@@ -0,0 +1,16 @@
+CREATE TABLE uploads (
+    id BLOB NOT NULL,
+    user_id BLOB NOT NULL,
+    upload_length INTEGER NOT NULL,
+    upload_offset INTEGER NOT NULL DEFAULT 0,
+    encrypted_name TEXT NOT NULL,
+    content_type TEXT,
+    channel_id BLOB,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    expires_at TEXT NOT NULL,
+    CONSTRAINT uploads_pkey PRIMARY KEY (id),
+    CONSTRAINT uploads_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT uploads_channel_id_fkey FOREIGN KEY (channel_id) REFERENCES channels(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_uploads_user ON uploads(user_id);
+CREATE INDEX idx_uploads_expires ON uploads(expires_at);
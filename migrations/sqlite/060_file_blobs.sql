@@ -0,0 +1,17 @@
+CREATE TABLE file_blobs (
+    storage_path TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    thumbnail_path TEXT,
+    ref_count INTEGER NOT NULL DEFAULT 1,
+    stored BOOLEAN NOT NULL DEFAULT true,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT file_blobs_pkey PRIMARY KEY (storage_path)
+);
+CREATE INDEX idx_file_blobs_checksum ON file_blobs(checksum);
+CREATE INDEX idx_files_storage_path ON files(storage_path);
+
+INSERT INTO file_blobs (storage_path, checksum, size_bytes, thumbnail_path, ref_count, created_at)
+SELECT storage_path, MIN(checksum), MAX(size_bytes), MAX(thumbnail_path), COUNT(*), MIN(created_at)
+FROM files
+GROUP BY storage_path;
@@ -0,0 +1,2 @@
+ALTER TABLE files ADD COLUMN duration_ms INTEGER;
+ALTER TABLE file_blobs ADD COLUMN duration_ms INTEGER;
@@ -0,0 +1,8 @@
+ALTER TABLE files ADD COLUMN width INTEGER;
+ALTER TABLE files ADD COLUMN height INTEGER;
+ALTER TABLE files ADD COLUMN blurhash TEXT;
+ALTER TABLE files ADD COLUMN variants TEXT;
+ALTER TABLE file_blobs ADD COLUMN width INTEGER;
+ALTER TABLE file_blobs ADD COLUMN height INTEGER;
+ALTER TABLE file_blobs ADD COLUMN blurhash TEXT;
+ALTER TABLE file_blobs ADD COLUMN variants TEXT;
@@ -0,0 +1,4 @@
+ALTER TABLE files ADD COLUMN phash TEXT;
+ALTER TABLE file_blobs ADD COLUMN phash TEXT;
+ALTER TABLE blocked_hashes ADD COLUMN kind TEXT NOT NULL DEFAULT 'sha256';
+ALTER TABLE blocked_hashes ADD COLUMN max_distance INTEGER;
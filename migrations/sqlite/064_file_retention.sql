@@ -0,0 +1,12 @@
+CREATE TABLE file_retention_policies (
+    scope_type TEXT NOT NULL,
+    scope_id BLOB NOT NULL,
+    max_age_days INTEGER,
+    max_total_bytes INTEGER,
+    updated_by BLOB,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT file_retention_policies_pkey PRIMARY KEY (scope_type, scope_id),
+    CONSTRAINT file_retention_policies_updated_by_fkey FOREIGN KEY (updated_by) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT file_retention_policies_scope_check CHECK (scope_type IN ('channel', 'community'))
+);
+CREATE INDEX idx_files_channel_created ON files(channel_id, created_at);
@@ -0,0 +1,11 @@
+CREATE TABLE storage_quotas (
+    scope_type TEXT NOT NULL,
+    scope_id BLOB NOT NULL,
+    role TEXT NOT NULL DEFAULT '',
+    max_bytes INTEGER NOT NULL,
+    updated_by BLOB,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    CONSTRAINT storage_quotas_pkey PRIMARY KEY (scope_type, scope_id, role),
+    CONSTRAINT storage_quotas_updated_by_fkey FOREIGN KEY (updated_by) REFERENCES users(id) ON DELETE SET NULL,
+    CONSTRAINT storage_quotas_scope_check CHECK (scope_type IN ('community', 'group', 'role'))
+);
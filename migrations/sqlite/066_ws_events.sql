@@ -0,0 +1,7 @@
+CREATE TABLE ws_events (
+    id BLOB PRIMARY KEY,
+    event TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX idx_ws_events_created_at ON ws_events (created_at);
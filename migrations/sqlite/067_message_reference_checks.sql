@@ -0,0 +1,4 @@
+-- SQLite keeps the original foreign keys onto messages(id) (see 058), so
+-- there is nothing to check here. Kept so the numbering matches the
+-- Postgres migrations.
+SELECT 1;